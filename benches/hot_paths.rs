@@ -0,0 +1,236 @@
+//! Benchmarks of the paths every index or query goes through: extracting
+//! text from rustdoc HTML, chunking it, reading a cached crate index and
+//! scoring a question against 10k chunk embeddings.
+//!
+//! Run with `cargo bench --features bench`. The corpora are synthetic, so
+//! results compare changes to this code, not crates.
+
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use rayon::prelude::*;
+use rustdocs_mcp_server::{
+    DocsIndex,
+    config::Config,
+    document_chunker::DocumentChunker,
+    embeddings::{CachedDocumentEmbedding, Embedding, EmbeddingProvider},
+    global_cache::{CacheKey, CachedCrateIndex, GlobalCache},
+    mock::MOCK_PROVIDER,
+    page_text,
+    vector_store::VectorStore,
+};
+use scraper::{Html, Selector};
+use tempfile::tempdir;
+
+/// Chunks in the synthetic indexes
+const CHUNKS: usize = 10_000;
+
+/// Dimensions of `text-embedding-3-small`, the default model
+const DIMENSIONS: usize = 1536;
+
+/// A rustdoc-like page for a struct with `methods` documented methods
+fn rustdoc_page(methods: usize) -> String {
+    let mut html = String::from(
+        "<html><body><section id=\"main-content\" class=\"content\">\
+         <div class=\"main-heading\"><h1>Struct <a class=\"struct\" href=\"#\">Widget</a>\
+         <button id=\"copy-path\">Copy item path</button></h1>\
+         <span class=\"out-of-band\"><span class=\"since\">1.0.0</span> · \
+         <a class=\"src\" href=\"#\">Source</a></span></div>\
+         <details class=\"toggle top-doc\" open><summary class=\"hideme\"><span>Expand description</span></summary>\
+         <div class=\"docblock\"><p>A widget drawn on a canvas.</p></div></details>",
+    );
+    for i in 0..methods {
+        html.push_str(&format!(
+            "<details class=\"toggle method-toggle\" open><summary><section id=\"method.resize_{i}\" class=\"method\">\
+             <a class=\"src rightside\" href=\"#\">Source</a><a href=\"#\" class=\"anchor\">§</a>\
+             <h4 class=\"code-header\">pub fn <a class=\"fn\">resize_{i}</a>(&amp;mut self, factor: <a class=\"primitive\">u32</a>)</h4>\
+             </section></summary><div class=\"docblock\"><p>Resizes the widget by <code>factor</code>, \
+             keeping its aspect ratio. Returns early if the factor is zero.</p>\
+             <div class=\"example-wrap\"><pre class=\"rust rust-example-rendered\"><code>\
+             <span class=\"kw\">let</span> <span class=\"kw\">mut</span> widget = Widget::new();\n\
+             widget.resize_{i}(<span class=\"number\">2</span>);</code></pre>\
+             <a class=\"test-arrow\" href=\"#\">Run</a></div></div></details>"
+        ));
+    }
+    html.push_str("</section></body></html>");
+    html
+}
+
+/// Prose of about `bytes` bytes, varied enough for content-defined chunking
+fn document(bytes: usize) -> String {
+    let mut text = String::with_capacity(bytes + 200);
+    let mut i = 0;
+    while text.len() < bytes {
+        text.push_str(&format!(
+            "Paragraph {i} explains how `Widget::resize_{i}` scales a widget by a factor of {} \
+             and when it returns early.\n\n",
+            i * 7 % 13
+        ));
+        i += 1;
+    }
+    text
+}
+
+/// A deterministic pseudo-random unit vector
+fn vector(seed: usize) -> Vec<f32> {
+    let mut state = seed as u64 ^ 0x9e37_79b9_7f4a_7c15;
+    let values: Vec<f32> = (0..DIMENSIONS)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 2000) as f32 / 1000.0 - 1.0
+        })
+        .collect();
+    Embedding::new(values, EmbeddingProvider::OpenAI, String::new())
+        .normalized()
+        .values
+}
+
+fn html_extraction(c: &mut Criterion) {
+    let html = rustdoc_page(200);
+    let selector = Selector::parse("section#main-content.content").unwrap();
+    let mut group = c.benchmark_group("html_extraction");
+    group.throughput(Throughput::Bytes(html.len() as u64));
+    group.bench_function("page_with_200_methods", |b| {
+        b.iter(|| {
+            let page = Html::parse_document(black_box(&html));
+            let main = page.select(&selector).next().unwrap();
+            page_text::extract(main)
+        })
+    });
+    group.finish();
+}
+
+fn chunking(c: &mut Criterion) {
+    let chunker = DocumentChunker::new();
+    let mut group = c.benchmark_group("chunking");
+    for bytes in [64 * 1024, 1024 * 1024] {
+        let text = document(bytes);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(format!("{}_kib", bytes / 1024), |b| {
+            b.iter(|| chunker.chunk_document(black_box(&text)))
+        });
+    }
+    group.finish();
+}
+
+fn cache_read(c: &mut Criterion) {
+    let root = tempdir().unwrap();
+    let cache = GlobalCache::with_root(root.path().to_path_buf()).unwrap();
+    let key = CacheKey::local("bench");
+    let index = CachedCrateIndex {
+        crate_name: "bench".to_string(),
+        version: "0.1.0".to_string(),
+        features: Vec::new(),
+        model: "text-embedding-3-small".to_string(),
+        documents: (0..CHUNKS)
+            .map(|i| CachedDocumentEmbedding {
+                path: format!("struct.Widget{i}.html"),
+                content: document(600),
+                vector: vector(i),
+            })
+            .collect(),
+    };
+    cache.store(&key, &index).unwrap();
+
+    let mut group = c.benchmark_group("cache_read");
+    group.sample_size(10);
+    group.bench_function("index_with_10k_chunks", |b| {
+        b.iter(|| cache.load(black_box(&key)).unwrap().unwrap())
+    });
+    group.finish();
+}
+
+fn similarity_search(c: &mut Criterion) {
+    let embeddings: Vec<Embedding> = (0..CHUNKS)
+        .map(|i| Embedding::new(vector(i), EmbeddingProvider::OpenAI, String::new()))
+        .collect();
+    let question = Embedding::new(vector(CHUNKS), EmbeddingProvider::OpenAI, String::new());
+
+    let mut group = c.benchmark_group("similarity_search");
+    group.throughput(Throughput::Elements(CHUNKS as u64));
+    group.bench_function("scan_10k_chunks", |b| {
+        b.iter(|| {
+            let mut scores: Vec<(usize, f32)> = embeddings
+                .par_iter()
+                .enumerate()
+                .map(|(i, embedding)| (i, question.similarity(embedding)))
+                .collect();
+            scores.par_sort_by(|a, b| b.1.total_cmp(&a.1));
+            scores.truncate(5);
+            scores
+        })
+    });
+
+    // The same scan over vectors mapped from disk
+    let root = tempdir().unwrap();
+    let mapped = VectorStore::new(
+        embeddings
+            .iter()
+            .enumerate()
+            .map(|(i, embedding)| (format!("struct.Widget{i}.html"), embedding.clone()))
+            .collect(),
+        Some(0),
+        root.path(),
+    )
+    .unwrap();
+    group.bench_function("scan_10k_mapped_chunks", |b| {
+        b.iter(|| {
+            let mut scores = mapped.scores(black_box(&question), f32::MIN);
+            scores.par_sort_by(|a, b| b.1.total_cmp(&a.1));
+            scores.truncate(5);
+            scores
+        })
+    });
+
+    // The whole search path of an indexed crate, with mock embeddings
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let index = runtime.block_on(search_corpus(root.path()));
+    group.bench_function("docs_index_10k_documents", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(index.search(black_box("How do I resize a widget?"), 5))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+/// Indexes a crate of 10k pages with the mock providers
+async fn search_corpus(root: &Path) -> DocsIndex {
+    let doc_dir = root.join("doc");
+    let crate_dir = doc_dir.join("bench");
+    fs::create_dir_all(&crate_dir).unwrap();
+    for i in 0..CHUNKS {
+        fs::write(
+            crate_dir.join(format!("struct.Widget{i}.html")),
+            format!(
+                "<html><body><section id=\"main-content\" class=\"content\"><p>Widget{i} is resized \
+                 with Widget{i}::resize and drawn with Widget{i}::draw_{}.</p></section></body></html>",
+                i % 97
+            ),
+        )
+        .unwrap();
+    }
+
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = doc_dir;
+    config.docs.project_dir = root.to_path_buf();
+    config.cache.dir = Some(root.join("chunks"));
+    config.cache.global_dir = Some(root.join("global"));
+    DocsIndex::open_with_config("bench", config).await.unwrap()
+}
+
+criterion_group!(
+    benches,
+    html_extraction,
+    chunking,
+    cache_read,
+    similarity_search
+);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+//! Chunks arbitrary text with chunker parameters taken from the input's first
+//! bytes, checking that chunking never panics, loses or adds text, or makes
+//! chunks longer than the maximum size.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustdocs_mcp_server::document_chunker::DocumentChunker;
+
+fuzz_target!(|data: &[u8]| {
+    let [min, target, max, text @ ..] = data else {
+        return;
+    };
+    let Ok(text) = std::str::from_utf8(text) else {
+        return;
+    };
+    // Keep `4 <= min <= target <= max`, so any character fits in a chunk
+    let min = usize::from(*min).max(4);
+    let target = min + usize::from(*target);
+    let max = target + usize::from(*max);
+    let chunker = DocumentChunker::with_params(min, target, max);
+
+    let chunks = chunker.chunk_document(text);
+    let joined: String = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+    assert_eq!(joined, text);
+    for chunk in &chunks {
+        assert!(chunk.content.len() <= max);
+        assert_eq!(chunk.id, chunker.generate_chunk_id(&chunk.content));
+    }
+    chunker.embeddable_chunks(text);
+});
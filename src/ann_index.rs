@@ -0,0 +1,287 @@
+//! Approximate nearest neighbor search for crates too large to scan.
+//!
+//! Scoring a question against every vector is fast for most crates but takes
+//! seconds once a crate has hundreds of thousands of them. For those, an HNSW
+//! graph built with usearch is stored next to the crate's cached embeddings.
+//! Queries memory-map it the first time they search the crate and visit only a
+//! small part of it; the most recently searched graphs stay open.
+
+use bincode::{Decode, Encode};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+use usearch::{Index, IndexOptions, MetricKind, ScalarKind};
+
+use crate::embeddings::Embedding;
+use crate::error::{Result, ServerError};
+use crate::utils::with_context;
+
+/// Identifies an ANN header file, followed by its format version
+const MAGIC: &[u8; 8] = b"RDMCPANN";
+const VERSION: u32 = 1;
+
+/// What a graph was built from, stored in a header file next to it
+#[derive(Debug, Encode, Decode)]
+struct AnnHeader {
+    /// Hash of the documentation the vectors were embedded from, as in the
+    /// crate's [`IndexManifest`](crate::index_manifest::IndexManifest)
+    content_hash: String,
+    model: String,
+    dimensions: u32,
+    /// Document path of every vector the graph was built from, by key.
+    /// Vectors of other dimensions are left out of the graph.
+    paths: Vec<String>,
+}
+
+/// A crate's HNSW graph, mapped from disk
+pub struct AnnIndex {
+    index: Index,
+    header: AnnHeader,
+}
+
+impl AnnIndex {
+    /// Builds the graph of `embeddings`, embedded with `model` from docs with
+    /// `content_hash`, and writes it to `path`. Each vector's key is its
+    /// position in `embeddings`.
+    pub fn build(
+        embeddings: &[(String, Embedding)],
+        content_hash: &str,
+        model: &str,
+        path: &Path,
+    ) -> Result<()> {
+        // Vectors of another length than most can't be in the same graph
+        let mut lengths: HashMap<usize, usize> = HashMap::new();
+        for (_, embedding) in embeddings {
+            *lengths.entry(embedding.dimensions).or_default() += 1;
+        }
+        let Some((dimensions, count)) = lengths.into_iter().max_by_key(|(_, count)| *count) else {
+            return Ok(());
+        };
+
+        let index = Index::new(&options(dimensions)).map_err(ann_error)?;
+        index.reserve(count).map_err(ann_error)?;
+        for (key, (_, embedding)) in embeddings.iter().enumerate() {
+            if embedding.dimensions == dimensions {
+                index
+                    .add(key as u64, &embedding.values)
+                    .map_err(ann_error)?;
+            }
+        }
+        let header = AnnHeader {
+            content_hash: content_hash.to_string(),
+            model: model.to_string(),
+            dimensions: dimensions as u32,
+            paths: embeddings.iter().map(|(path, _)| path.clone()).collect(),
+        };
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend(bincode::encode_to_vec(
+            &header,
+            bincode::config::standard(),
+        )?);
+
+        // Both files are written under temporary names and renamed, so other
+        // servers that have the previous graph mapped keep reading it intact
+        let context = || format!("Failed to write ANN index {}", path.display());
+        let graph = path.with_extension("tmp");
+        index.save(utf8(&graph)?).map_err(ann_error)?;
+        with_context(fs::rename(&graph, path), context)?;
+        let header_file = header_path(path).with_extension("header.tmp");
+        with_context(fs::write(&header_file, bytes), context)?;
+        with_context(fs::rename(&header_file, header_path(path)), context)?;
+        debug!(
+            "Wrote ANN index of {} vectors to {}",
+            index.size(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// Maps the graph at `path`, returning `None` if there is none
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        let Some(header) = read_header(path)? else {
+            return Ok(None);
+        };
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let index = Index::new(&options(header.dimensions as usize)).map_err(ann_error)?;
+        index.view(utf8(path)?).map_err(ann_error)?;
+        if index.size() > header.paths.len() {
+            return Err(invalid_data(path));
+        }
+        Ok(Some(Self { index, header }))
+    }
+
+    /// Deletes the graph at `path` and its header
+    pub fn remove(path: &Path) -> Result<()> {
+        for file in [path.to_path_buf(), header_path(path)] {
+            if file.is_file() {
+                with_context(fs::remove_file(&file), || {
+                    format!("Failed to remove ANN index {}", file.display())
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the graph at `path` was built from `vectors` vectors of
+    /// docs with `content_hash`, embedded with `model`
+    pub fn is_current(path: &Path, content_hash: &str, model: &str, vectors: usize) -> bool {
+        matches!(
+            read_header(path),
+            Ok(Some(header)) if header.content_hash == content_hash
+                && header.model == model
+                && header.paths.len() == vectors
+        ) && path.is_file()
+    }
+
+    /// Returns true if the graph was built from `vectors` vectors of docs with
+    /// `content_hash`, embedded with `model`
+    pub fn matches(&self, content_hash: &str, model: &str, vectors: usize) -> bool {
+        self.header.content_hash == content_hash
+            && self.header.model == model
+            && self.header.paths.len() == vectors
+    }
+
+    /// Path of the document whose vector has `key`
+    pub fn path(&self, key: usize) -> Option<&str> {
+        self.header.paths.get(key).map(String::as_str)
+    }
+
+    /// Keys and cosine similarities of about the `count` vectors most similar
+    /// to `question`, best first. Empty if it has other dimensions.
+    pub fn search(&self, question: &Embedding, count: usize) -> Result<Vec<(usize, f32)>> {
+        if question.dimensions != self.header.dimensions as usize {
+            return Ok(Vec::new());
+        }
+        let matches = self
+            .index
+            .search(&question.values, count)
+            .map_err(ann_error)?;
+        Ok(matches
+            .keys
+            .iter()
+            .zip(&matches.distances)
+            .map(|(key, distance)| (*key as usize, 1.0 - distance))
+            .collect())
+    }
+}
+
+/// The graphs queries opened, closing the least recently searched one when
+/// more than a set number are open
+pub struct AnnIndexes {
+    capacity: usize,
+    open: Mutex<VecDeque<(PathBuf, Arc<AnnIndex>)>>,
+}
+
+impl AnnIndexes {
+    /// Keeps at most `capacity` graphs open
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            open: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// The graph at `path`, opening it unless it is open already. `None` if
+    /// there is none or it can't be read.
+    pub fn get(&self, path: &Path) -> Option<Arc<AnnIndex>> {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(position) = open.iter().position(|(open_path, _)| open_path == path) {
+            let entry = open.remove(position)?;
+            let index = Arc::clone(&entry.1);
+            open.push_front(entry);
+            return Some(index);
+        }
+        let index = match AnnIndex::open(path) {
+            Ok(index) => Arc::new(index?),
+            Err(e) => {
+                warn!("Ignoring unreadable ANN index {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        open.push_front((path.to_path_buf(), Arc::clone(&index)));
+        open.truncate(self.capacity);
+        Some(index)
+    }
+
+    /// Closes the graph at `path`, e.g. because it is being rebuilt
+    pub fn close(&self, path: &Path) {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        open.retain(|(open_path, _)| open_path != path);
+    }
+
+    /// Number of open graphs
+    pub fn len(&self) -> usize {
+        self.open.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns true if no graph is open
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Cosine similarity over `f32` vectors of `dimensions`, with usearch's
+/// default graph connectivity and expansion
+fn options(dimensions: usize) -> IndexOptions {
+    IndexOptions {
+        dimensions,
+        metric: MetricKind::Cos,
+        quantization: ScalarKind::F32,
+        ..Default::default()
+    }
+}
+
+/// Path of the header stored next to the graph at `path`
+fn header_path(path: &Path) -> PathBuf {
+    path.with_extension("header")
+}
+
+/// Reads the header of the graph at `path`, or `None` if there is none
+fn read_header(path: &Path) -> Result<Option<AnnHeader>> {
+    let header_file = header_path(path);
+    if !header_file.is_file() {
+        return Ok(None);
+    }
+    let context = || format!("Failed to read ANN index header {}", header_file.display());
+    let bytes = with_context(fs::read(&header_file), context)?;
+    let Some(rest) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        return Err(invalid_data(&header_file));
+    };
+    match rest.split_first_chunk::<4>() {
+        Some((version, rest)) if u32::from_le_bytes(*version) == VERSION => {
+            let (header, _) = with_context(
+                bincode::decode_from_slice(rest, bincode::config::standard()),
+                context,
+            )?;
+            Ok(Some(header))
+        }
+        // Graphs of other format versions are rebuilt
+        _ => Ok(None),
+    }
+}
+
+fn utf8(path: &Path) -> Result<&str> {
+    path.to_str().ok_or_else(|| {
+        ServerError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("ANN index path {} is not UTF-8", path.display()),
+        ))
+    })
+}
+
+fn invalid_data(path: &Path) -> ServerError {
+    ServerError::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Malformed ANN index {}", path.display()),
+    ))
+}
+
+fn ann_error(e: impl std::fmt::Display) -> ServerError {
+    ServerError::Io(io::Error::other(format!("ANN index error: {}", e)))
+}
@@ -0,0 +1,217 @@
+//! Renders an answer as the content the query tools return.
+//!
+//! Answers are assembled from parts — the generated text, links to the
+//! items it mentions, the confidence and any warnings — and a formatter
+//! picked per request renders them: prose (the default), Markdown for
+//! clients that display it, JSON for agents that parse the answer, or only
+//! the answer's code for clients that want something to paste.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::confidence::Confidence;
+use crate::crate_features;
+use crate::docs_links::{self, ItemLink};
+
+/// Shape of the content a query returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerFormat {
+    /// Prose headed by the crate searched, followed by the confidence
+    #[default]
+    Text,
+    /// Markdown with headings, linked items and fenced code
+    Markdown,
+    /// A JSON object with the answer, links, confidence and warnings
+    Json,
+    /// Only the fenced code blocks of the answer
+    Code,
+}
+
+impl AnswerFormat {
+    /// The formatter rendering answers in this format
+    pub fn formatter(self) -> &'static dyn AnswerFormatter {
+        match self {
+            Self::Text => &TextFormatter,
+            Self::Markdown => &MarkdownFormatter,
+            Self::Json => &JsonFormatter,
+            Self::Code => &CodeFormatter,
+        }
+    }
+}
+
+/// The parts of an answer, before it is formatted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    /// The crates searched, e.g. `tokio` or `tokio (with tokio_util)`
+    pub searched: String,
+    /// The generated answer, or the matched documentation if none was generated
+    pub body: String,
+    /// docs.rs pages of items the answer mentions
+    pub links: Vec<ItemLink>,
+    pub confidence: Confidence,
+    /// Cargo features the docs were generated with, if known, shown after
+    /// the confidence; empty for the default features
+    pub features: Option<Vec<String>>,
+    /// Warning that the docs may be stale, shown before the answer
+    pub stale_warning: Option<String>,
+    /// Warnings about the matched items and the search, shown after the answer
+    pub warnings: Vec<String>,
+    /// The latency budget ran out, so the answer is the best result
+    /// available in time, e.g. documentation that wasn't summarized
+    pub partial: bool,
+}
+
+/// Renders answers as tool content
+pub trait AnswerFormatter: Send + Sync {
+    fn format(&self, answer: &Answer) -> String;
+}
+
+/// `From tokio docs: ...` followed by the confidence and warnings
+pub struct TextFormatter;
+
+impl AnswerFormatter for TextFormatter {
+    fn format(&self, answer: &Answer) -> String {
+        let mut text = format!(
+            "From {} docs: {}{}\n\nConfidence: {}",
+            answer.searched,
+            answer.body,
+            docs_links::format_links(&answer.links),
+            answer.confidence
+        );
+        if let Some(features) = &answer.features {
+            text.push_str(&format!(
+                "\nDocs generated with {}",
+                crate_features::describe_enabled(features)
+            ));
+        }
+        for warning in &answer.warnings {
+            text.push_str(&format!("\n\nWarning: {}", warning));
+        }
+        match &answer.stale_warning {
+            Some(warning) => format!("Warning: {}\n\n{}", warning, text),
+            None => text,
+        }
+    }
+}
+
+/// A Markdown document headed by the crate searched, with warnings as quotes
+pub struct MarkdownFormatter;
+
+impl AnswerFormatter for MarkdownFormatter {
+    fn format(&self, answer: &Answer) -> String {
+        let mut text = String::new();
+        if let Some(warning) = &answer.stale_warning {
+            text.push_str(&format!("> **Warning:** {}\n\n", warning));
+        }
+        text.push_str(&format!(
+            "## From {} docs\n\n{}",
+            answer.searched,
+            answer.body.trim_end()
+        ));
+        if !answer.links.is_empty() {
+            text.push_str("\n\n### Docs\n");
+            for link in &answer.links {
+                text.push_str(&format!("\n- [`{}`]({})", link.name, link.url));
+            }
+        }
+        text.push_str(&format!("\n\n**Confidence:** {}", answer.confidence));
+        if let Some(features) = &answer.features {
+            text.push_str(&format!(
+                "\n\n**Docs generated with:** {}",
+                crate_features::describe_enabled(features)
+            ));
+        }
+        for warning in &answer.warnings {
+            text.push_str(&format!("\n\n> **Warning:** {}", warning));
+        }
+        text
+    }
+}
+
+/// `{"crate", "answer", "links", "confidence", "features", "warnings",
+/// "partial"}`, the stale docs warning first among the warnings. `features`
+/// is null when the features the docs were generated with aren't known.
+pub struct JsonFormatter;
+
+impl AnswerFormatter for JsonFormatter {
+    fn format(&self, answer: &Answer) -> String {
+        let links: Vec<_> = answer
+            .links
+            .iter()
+            .map(|link| json!({ "name": link.name, "url": link.url }))
+            .collect();
+        let warnings: Vec<&str> = answer
+            .stale_warning
+            .iter()
+            .chain(&answer.warnings)
+            .map(String::as_str)
+            .collect();
+        let value = json!({
+            "crate": answer.searched,
+            "answer": answer.body,
+            "links": links,
+            "confidence": {
+                "level": answer.confidence.level.to_string(),
+                // Rounded as in the text, rather than printing f32 noise
+                "value": (answer.confidence.value as f64 * 100.0).round() / 100.0,
+            },
+            "features": answer.features,
+            "warnings": warnings,
+            "partial": answer.partial,
+        });
+        serde_json::to_string_pretty(&value).unwrap_or_else(|_| value.to_string())
+    }
+}
+
+/// The fenced code blocks of the answer, fences included, or a note that
+/// it has none
+pub struct CodeFormatter;
+
+impl AnswerFormatter for CodeFormatter {
+    fn format(&self, answer: &Answer) -> String {
+        let blocks = code_blocks(&answer.body);
+        if blocks.is_empty() {
+            return format!("No code in the answer from {} docs.", answer.searched);
+        }
+        blocks.join("\n\n")
+    }
+}
+
+/// Fenced code blocks of Markdown text, from opening to closing fence. A
+/// block left open runs to the end of the text.
+pub fn code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut block: Option<(&str, Vec<&str>)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match &mut block {
+            None => {
+                if let Some(fence) = fence_of(trimmed) {
+                    block = Some((fence, vec![line]));
+                }
+            }
+            Some((fence, lines)) => {
+                lines.push(line);
+                if trimmed.trim_end() == *fence {
+                    blocks.push(lines.join("\n"));
+                    block = None;
+                }
+            }
+        }
+    }
+    if let Some((fence, mut lines)) = block {
+        lines.push(fence);
+        blocks.push(lines.join("\n"));
+    }
+    blocks
+}
+
+/// The fence opening a code block on `line`, e.g. "```" for "```rust"
+fn fence_of(line: &str) -> Option<&str> {
+    ["```", "~~~"].into_iter().find_map(|marker| {
+        let length = line.len() - line.trim_start_matches(&marker[..1]).len();
+        (length >= 3 && line.starts_with(marker)).then(|| &line[..length])
+    })
+}
@@ -0,0 +1,102 @@
+//! Reads the OpenAI API key from a file, the OS keychain or `OPENAI_API_KEY`.
+//!
+//! Keys kept in a file or the keychain can be rotated while the server runs:
+//! on `SIGHUP` the key is read again and used for all further requests.
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use crate::config::{KeychainEntry, OpenAiSettings};
+use crate::error::{Result, ServerError};
+
+/// Reads the key from the configured source, or `OPENAI_API_KEY` if none is
+/// configured. Returns `None` if the variable isn't set.
+pub fn load_api_key(settings: &OpenAiSettings) -> Result<Option<String>> {
+    let key = if let Some(path) = &settings.api_key_file {
+        fs::read_to_string(path).map_err(|e| {
+            ServerError::Config(format!(
+                "Failed to read the API key from {}: {}",
+                path.display(),
+                e
+            ))
+        })?
+    } else if let Some(entry) = &settings.api_key_keychain {
+        read_keychain(entry)?
+    } else {
+        return Ok(env::var("OPENAI_API_KEY").ok());
+    };
+
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(ServerError::Config(format!(
+            "The API key from {} is empty",
+            describe_source(settings)
+        )));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// Names where the key is read from, for logs and diagnostics
+pub fn describe_source(settings: &OpenAiSettings) -> String {
+    if let Some(path) = &settings.api_key_file {
+        format!("file {}", path.display())
+    } else if let Some(entry) = &settings.api_key_keychain {
+        format!(
+            "keychain entry {} (account {})",
+            entry.service, entry.account
+        )
+    } else {
+        "OPENAI_API_KEY".to_string()
+    }
+}
+
+/// Reads a generic password with the platform's keychain CLI: `security` on
+/// macOS, `secret-tool` (libsecret) elsewhere
+fn read_keychain(entry: &KeychainEntry) -> Result<String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args([
+            "find-generic-password",
+            "-s",
+            &entry.service,
+            "-a",
+            &entry.account,
+            "-w",
+        ]);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("secret-tool");
+        command.args([
+            "lookup",
+            "service",
+            &entry.service,
+            "account",
+            &entry.account,
+        ]);
+        command
+    } else {
+        return Err(ServerError::Config(
+            "openai.api_key_keychain is only supported on macOS and Linux; use api_key_file"
+                .to_string(),
+        ));
+    };
+
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().map_err(|e| {
+        ServerError::Config(format!(
+            "Failed to run {} to read the API key: {}",
+            program, e
+        ))
+    })?;
+    if !output.status.success() {
+        return Err(ServerError::Config(format!(
+            "No API key found in the keychain for service '{}' and account '{}': {}",
+            entry.service,
+            entry.account,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| ServerError::Config("The keychain returned a non-UTF-8 API key".to_string()))
+}
@@ -0,0 +1,158 @@
+//! Opt-in audit log of what agents ask the server, kept on this machine.
+//!
+//! With `audit.enabled`, every tool invocation appends a line of JSON to a
+//! local file: the tool, its arguments, how long it took and what it returned
+//! or why it failed. Every answered question additionally appends the
+//! documents retrieval matched with their scores, the models involved, token
+//! counts and the answer, so questions that retrieval fails on can be found
+//! and replayed later, e.g. with `jq`. Long texts are cut down to
+//! `audit.max_answer_chars`.
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::platform_paths::{self, ProjectDirs};
+use crate::utils::{ensure_dir_exists, with_context};
+
+/// File name of the log in the XDG data directory
+const AUDIT_FILE_NAME: &str = "audit.jsonl";
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Any tool invocation
+    ToolCall(ToolCallEvent),
+    /// A question answered from a crate's docs, by `query_rust_docs` or
+    /// `query_rust_docs_batch`
+    Query(QueryEvent),
+}
+
+/// An invoked tool and its outcome
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolCallEvent {
+    pub tool: String,
+    /// Arguments as the client sent them
+    pub arguments: Value,
+    pub latency_ms: f64,
+    /// Text the tool returned, if it succeeded
+    pub result: Option<String>,
+    /// Why the tool failed, if it did
+    pub error: Option<String>,
+}
+
+/// A question and what retrieval found for it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryEvent {
+    pub crate_name: String,
+    pub question: String,
+    /// `semantic`, or `keywords` when the latency budget ran out before the
+    /// question was embedded
+    pub retrieval: String,
+    /// Documents the answer is based on, best first
+    pub matches: Vec<AuditMatch>,
+    pub embedding_model: String,
+    /// Chat model that wrote the answer, if one did
+    pub llm_model: Option<String>,
+    /// Tokens of the documents sent as context
+    pub context_tokens: usize,
+    pub answer_tokens: usize,
+    pub latency_ms: f64,
+    /// Whether the latency budget cut the answer short
+    pub partial: bool,
+    pub answer: String,
+}
+
+/// A matched document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditMatch {
+    pub crate_name: String,
+    pub path: String,
+    /// Cosine similarity, or BM25 score for keyword retrieval
+    pub score: f32,
+}
+
+/// Line written to the log: the event with the time it was recorded
+#[derive(Serialize)]
+struct AuditLine<'a> {
+    recorded_at: String,
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+}
+
+/// Local audit log file
+#[derive(Debug)]
+pub struct AuditLog {
+    file: Mutex<File>,
+    max_answer_chars: usize,
+}
+
+impl AuditLog {
+    /// Opens the log at `path` for appending, creating it if needed. Answers
+    /// and tool results longer than `max_answer_chars` are cut down; 0 keeps
+    /// them whole.
+    pub fn open(path: &Path, max_answer_chars: usize) -> Result<Self> {
+        let path = platform_paths::long_path(path);
+        if let Some(parent) = path.parent() {
+            ensure_dir_exists(parent)?;
+        }
+        let file = with_context(
+            OpenOptions::new().create(true).append(true).open(&path),
+            || format!("Failed to open audit log {}", path.display()),
+        )?;
+        Ok(Self {
+            file: Mutex::new(file),
+            max_answer_chars,
+        })
+    }
+
+    /// Appends `event` as one line
+    pub fn record(&self, mut event: AuditEvent) -> Result<()> {
+        match &mut event {
+            AuditEvent::ToolCall(call) => {
+                if let Some(result) = &mut call.result {
+                    truncate(result, self.max_answer_chars);
+                }
+            }
+            AuditEvent::Query(query) => truncate(&mut query.answer, self.max_answer_chars),
+        }
+        let line = AuditLine {
+            recorded_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            event: &event,
+        };
+        let mut line = serde_json::to_string(&line)?;
+        line.push('\n');
+        // One write per line, so lines of concurrent servers don't interleave
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Resolves the platform default location of the log
+pub fn default_path() -> Result<PathBuf> {
+    Ok(ProjectDirs::new()?.data_dir().join(AUDIT_FILE_NAME))
+}
+
+/// `duration` in fractional milliseconds, as the log records latencies
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Cuts `text` down to `max_chars` characters, marking the cut
+fn truncate(text: &mut String, max_chars: usize) {
+    if max_chars == 0 {
+        return;
+    }
+    if let Some((end, _)) = text.char_indices().nth(max_chars) {
+        text.truncate(end);
+        text.push('…');
+    }
+}
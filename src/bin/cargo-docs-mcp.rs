@@ -0,0 +1,171 @@
+//! `cargo docs-mcp`: runs the documentation server for the Cargo project it is
+//! invoked in, with the project root and target directory taken from cargo.
+
+use clap::{Args, Parser, Subcommand};
+use rustdocs_mcp_server::{
+    cargo_project::CargoProject,
+    config::{CONFIG_FILE_NAME, Config},
+    crate_discovery,
+    crate_spec::CrateSpec,
+    error::ServerError,
+    logging,
+    server::{self, RustDocsServer},
+};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum CargoCli {
+    /// MCP server for the documentation of the current Cargo project
+    #[command(name = "docs-mcp", version, author)]
+    DocsMcp(DocsMcpArgs),
+}
+
+#[derive(Args, Debug)]
+struct DocsMcpArgs {
+    #[command(subcommand)]
+    command: DocsMcpCommand,
+
+    /// Forbid network calls; answer from cached embeddings with raw documentation
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Configuration file to use instead of the project's rustdocs-mcp.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum DocsMcpCommand {
+    /// Start the MCP server over stdio for this project
+    Serve {
+        /// Serve only this crate (`name` or `name@version_req`) and pre-load it at startup
+        #[arg(long = "crate", value_name = "SPEC")]
+        crate_spec: Option<String>,
+        /// Features the crate's documentation was generated with (requires --crate)
+        #[arg(short = 'F', long, value_delimiter = ',', requires = "crate_spec")]
+        features: Vec<String>,
+        /// Watch the doc directory and re-index crates when their docs change
+        #[arg(long)]
+        watch: bool,
+        /// Refuse to start when a startup check fails instead of running degraded
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Embed the documentation of crates ahead of time so the first query is fast
+    Index {
+        /// Crates to index, as named in the doc directory
+        #[arg(required_unless_present = "all")]
+        crates: Vec<String>,
+        /// Index every crate with generated documentation
+        #[arg(long, conflicts_with = "crates")]
+        all: bool,
+    },
+    /// Write a rustdocs-mcp.toml for this project
+    GenConfig {
+        /// Print the configuration instead of writing the file
+        #[arg(long)]
+        stdout: bool,
+        /// Overwrite an existing configuration file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ServerError> {
+    let CargoCli::DocsMcp(args) = CargoCli::parse();
+
+    // Relative paths in the project's configuration are relative to its root
+    let cwd = env::current_dir()?;
+    let project = CargoProject::detect(&cwd)?;
+    let config_path = args.config.map(|path| cwd.join(path));
+    env::set_current_dir(&project.root)?;
+    dotenvy::dotenv().ok();
+
+    // Generating a configuration must work even if the existing one is invalid
+    let load_config = || -> Result<Config, ServerError> {
+        let mut config = Config::load(config_path.as_deref())?;
+        if args.offline {
+            config.offline = true;
+        }
+        project.apply_to(&mut config);
+        logging::init(&config.logging.level, config.logging.file.as_deref())?;
+        Ok(config)
+    };
+
+    match args.command {
+        DocsMcpCommand::Serve {
+            crate_spec,
+            features,
+            watch,
+            strict,
+        } => {
+            let crate_scope = match crate_spec {
+                Some(spec) => Some(CrateSpec::parse(&spec, features)?),
+                None => None,
+            };
+            let mut config = load_config()?;
+            config.strict |= strict;
+            server::serve_stdio(config, None, crate_scope, watch).await
+        }
+        DocsMcpCommand::Index { crates, all } => run_index(load_config()?, crates, all).await,
+        DocsMcpCommand::GenConfig { stdout, force } => run_gen_config(&project, stdout, force),
+    }
+}
+
+/// Indexes the named crates, or every documented crate with `all`
+async fn run_index(config: Config, crates: Vec<String>, all: bool) -> Result<(), ServerError> {
+    let crates = if all {
+        crate_discovery::discover_in_roots(&config.docs.doc_roots())
+            .into_iter()
+            .map(|discovered| discovered.name)
+            .collect()
+    } else {
+        crates
+    };
+    if crates.is_empty() {
+        return Err(ServerError::Config(format!(
+            "No documented crates found in {}; run `cargo doc` first",
+            config.docs.dir.display()
+        )));
+    }
+
+    let server = RustDocsServer::with_config(String::new(), config)?;
+    for name in &crates {
+        let index = server.index_crate(name).await?;
+        print!(
+            "Indexed {} documents of '{}'",
+            index.documents.len(),
+            index.crate_name
+        );
+        if index.skipped.is_empty() {
+            println!();
+        } else {
+            println!(" ({} could not be embedded)", index.skipped.len());
+        }
+    }
+    Ok(())
+}
+
+/// Writes the project's configuration file, or prints it
+fn run_gen_config(project: &CargoProject, stdout: bool, force: bool) -> Result<(), ServerError> {
+    let toml = project.generated_config().to_toml()?;
+    if stdout {
+        print!("{}", toml);
+        return Ok(());
+    }
+
+    let path = project.root.join(CONFIG_FILE_NAME);
+    if path.exists() && !force {
+        return Err(ServerError::Config(format!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        )));
+    }
+    fs::write(&path, toml)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
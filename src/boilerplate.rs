@@ -0,0 +1,111 @@
+//! Recognizes chunks of rustdoc text that aren't worth embedding.
+//!
+//! Every type page ends with lists rustdoc generates on its own: the trait
+//! implementations (mostly derives), the auto traits (`Send`, `Sync`, ...)
+//! and the blanket implementations every type gets (`From`, `Into`, `Any`,
+//! ...). For derive-heavy crates these lists are a large share of the text,
+//! match almost any question about conversions or traits, and say nothing
+//! specific about the crate. Chunks made up of them, or only of navigation
+//! leftovers, are skipped when documents are embedded.
+
+/// A chunk listing at least this many impls can be boilerplate
+const MIN_IMPLS: usize = 3;
+
+/// Largest share of a chunk's bytes that may be prose for it to still count
+/// as boilerplate
+const MAX_PROSE_SHARE: f32 = 0.2;
+
+/// Fewest words in a line of prose
+const MIN_PROSE_WORDS: usize = 5;
+
+/// Text of rustdoc's page chrome that ends up in the extracted content
+const NAVIGATION: &[&str] = &[
+    "§",
+    "Source",
+    "Read more",
+    "Expand description",
+    "Copy item path",
+    "Summary",
+    "Sections",
+    "Settings",
+    "Help",
+    "ⓘ",
+    "−",
+    "[−]",
+    "[src]",
+];
+
+/// Headings of the generated implementation lists
+const HEADINGS: &[&str] = &[
+    "Trait Implementations",
+    "Auto Trait Implementations",
+    "Blanket Implementations",
+];
+
+/// Documentation of the blanket implementations, repeated on every type page
+const BLANKET_SENTENCES: &[&str] = &[
+    "Gets the `TypeId` of `self`.",
+    "Gets the TypeId of self.",
+    "Immutably borrows from an owned value.",
+    "Mutably borrows from an owned value.",
+    "Returns the argument unchanged.",
+    "Calls `U::from(self)`.",
+    "Calls U::from(self).",
+    "That is, this conversion is whatever the implementation of",
+    "The type returned in the event of a conversion error.",
+    "Performs the conversion.",
+    "The resulting type after obtaining ownership.",
+    "Creates owned data from borrowed data, usually by cloning.",
+    "Uses borrowed data to replace owned data, usually by cloning.",
+    "Converts the given value to a `String`.",
+    "Converts the given value to a String.",
+    "This is a nightly-only experimental API.",
+];
+
+/// Returns true if `text` is generated boilerplate: mostly lists of trait,
+/// auto trait and blanket implementations with hardly any prose, or nothing
+/// but navigation text
+pub fn is_boilerplate(text: &str) -> bool {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return false;
+    }
+    if lines.iter().all(|line| NAVIGATION.contains(line)) {
+        return true;
+    }
+
+    let impls = lines
+        .iter()
+        .filter(|line| **line == "impl" || line.starts_with("impl<") || line.starts_with("impl "))
+        .count();
+    let headings = lines.iter().filter(|line| HEADINGS.contains(line)).count();
+    if impls < MIN_IMPLS && headings == 0 {
+        return false;
+    }
+
+    let prose: usize = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| {
+            // A summary followed by "Read more" is copied from the trait's docs
+            let copied = lines.get(i + 1) == Some(&"Read more");
+            !copied && is_prose(line)
+        })
+        .map(|(_, line)| line.len())
+        .sum();
+    (prose as f32) < MAX_PROSE_SHARE * text.len() as f32
+}
+
+/// Returns true if `line` reads like a sentence written for this crate
+/// rather than a method signature or blanket impl documentation
+fn is_prose(line: &str) -> bool {
+    !line.starts_with("fn ")
+        && line.split_whitespace().count() >= MIN_PROSE_WORDS
+        && !BLANKET_SENTENCES
+            .iter()
+            .any(|sentence| line.contains(sentence))
+}
@@ -0,0 +1,169 @@
+//! Process-wide limit on provider usage, so an agent stuck in a loop can't
+//! spend an API budget overnight.
+//!
+//! Embedding and chat requests are charged by their tokens, and by an
+//! estimated price from the configured per-token prices. Once the limit of
+//! the current period is reached, new requests are refused: searches still
+//! use cached embeddings, and answers are the raw matched documentation.
+
+use chrono::{NaiveDate, Utc};
+use std::sync::{Mutex, MutexGuard};
+use tracing::warn;
+
+use crate::config::{BudgetPeriod, BudgetSettings};
+use crate::error::{Result, ServerError};
+
+/// A provider request to charge, priced by what it was for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spend {
+    /// Tokens sent to the embedding model
+    Embedding { tokens: u64 },
+    /// Tokens sent to and generated by the chat model
+    Chat {
+        input_tokens: u64,
+        output_tokens: u64,
+    },
+}
+
+impl Spend {
+    fn tokens(self) -> u64 {
+        match self {
+            Self::Embedding { tokens } => tokens,
+            Self::Chat {
+                input_tokens,
+                output_tokens,
+            } => input_tokens + output_tokens,
+        }
+    }
+
+    /// Estimated price in US dollars
+    fn usd(self, settings: &BudgetSettings) -> f64 {
+        let per_million = |tokens: u64, price: f64| tokens as f64 * price / 1_000_000.0;
+        match self {
+            Self::Embedding { tokens } => per_million(tokens, settings.embedding_usd_per_million),
+            Self::Chat {
+                input_tokens,
+                output_tokens,
+            } => {
+                per_million(input_tokens, settings.chat_input_usd_per_million)
+                    + per_million(output_tokens, settings.chat_output_usd_per_million)
+            }
+        }
+    }
+}
+
+/// Usage in the current period
+#[derive(Debug, Default)]
+struct Usage {
+    // Day the usage was counted on; always None for session budgets
+    day: Option<NaiveDate>,
+    tokens: u64,
+    usd: f64,
+    // Set once running out was logged, so it is logged once per period
+    reported: bool,
+}
+
+/// Tokens and dollars the server may spend on providers per period, shared
+/// by the embedding service and the chat client
+#[derive(Debug)]
+pub struct UsageBudget {
+    settings: BudgetSettings,
+    usage: Mutex<Usage>,
+}
+
+impl UsageBudget {
+    pub fn new(settings: &BudgetSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            usage: Mutex::new(Usage::default()),
+        }
+    }
+
+    /// A budget that never runs out, only counting usage
+    pub fn unlimited() -> Self {
+        Self::new(&BudgetSettings::default())
+    }
+
+    /// Fails if the budget of the current period is spent
+    pub fn check(&self) -> Result<()> {
+        let usage = self.current();
+        match self.exceeded(&usage) {
+            Some(spent) => Err(ServerError::BudgetExceeded(format!(
+                "{}; no new embeddings or chat completions until {} (raise budget.max_tokens or budget.max_usd to continue)",
+                spent,
+                self.resets()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns true if the budget of the current period is spent
+    pub fn is_exhausted(&self) -> bool {
+        self.check().is_err()
+    }
+
+    /// Charges a completed request, warning (and so telling MCP clients) the
+    /// first time the budget of the period runs out
+    pub fn charge(&self, spend: Spend) {
+        let mut usage = self.current();
+        usage.tokens += spend.tokens();
+        usage.usd += spend.usd(&self.settings);
+        if !usage.reported
+            && let Some(spent) = self.exceeded(&usage)
+        {
+            usage.reported = true;
+            warn!(
+                "Provider budget spent ({}): only cached embeddings and raw documentation are served until {}",
+                spent,
+                self.resets()
+            );
+        }
+    }
+
+    /// Tokens and estimated US dollars spent in the current period
+    pub fn spent(&self) -> (u64, f64) {
+        let usage = self.current();
+        (usage.tokens, usage.usd)
+    }
+
+    /// Locks the usage, starting over if a new day began
+    fn current(&self) -> MutexGuard<'_, Usage> {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if self.settings.period == BudgetPeriod::Daily {
+            let today = Utc::now().date_naive();
+            if usage.day != Some(today) {
+                *usage = Usage {
+                    day: Some(today),
+                    ..Usage::default()
+                };
+            }
+        }
+        usage
+    }
+
+    /// Describes the spent limit, if one is
+    fn exceeded(&self, usage: &Usage) -> Option<String> {
+        let settings = &self.settings;
+        if settings.max_tokens > 0 && usage.tokens >= settings.max_tokens {
+            Some(format!(
+                "{} of {} tokens used",
+                usage.tokens, settings.max_tokens
+            ))
+        } else if settings.max_usd > 0.0 && usage.usd >= settings.max_usd {
+            Some(format!(
+                "${:.2} of ${:.2} spent",
+                usage.usd, settings.max_usd
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// When the budget starts over
+    fn resets(&self) -> &'static str {
+        match self.settings.period {
+            BudgetPeriod::Daily => "midnight UTC",
+            BudgetPeriod::Session => "the server restarts",
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::document_chunker::{self, DocumentChunker};
+use crate::embedding_cache_service::CachedEmbedding;
+use crate::embeddings::{CachedDocumentEmbedding, EmbeddingProvider, l2_norm};
+use crate::error::{Result, ServerError};
+use crate::global_cache::{CacheKey, CachedCrateIndex, GlobalCache};
+use crate::utils::ensure_dir_exists;
+
+/// Version of the bundle layout written by [`export_crate`]
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// One line of a JSONL bundle. A bundle starts with a header, followed by
+/// the crate's documents and the chunk embeddings they were built from.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BundleRecord {
+    Header {
+        format_version: u32,
+        crate_name: String,
+        version: String,
+        features: Vec<String>,
+        model: String,
+    },
+    Document {
+        path: String,
+        content: String,
+        vector: Vec<f32>,
+    },
+    Chunk {
+        id: String,
+        document_path: String,
+        content: String,
+        vector: Vec<f32>,
+        model: String,
+        provider: EmbeddingProvider,
+    },
+}
+
+/// Result of exporting a crate
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    pub documents: usize,
+    pub chunks: usize,
+    /// Chunks of the crate's documents that had no entry in the chunk cache
+    pub missing_chunks: usize,
+}
+
+/// Result of importing a bundle
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub crate_name: String,
+    pub version: String,
+    pub documents: usize,
+    pub chunks: usize,
+    /// Chunk records whose id didn't match their content and were skipped
+    pub rejected_chunks: usize,
+}
+
+/// Writes the cached index of a crate and its chunk embeddings as JSONL
+pub fn export_crate(
+    global: &GlobalCache,
+    chunk_dir: &Path,
+    chunker: &DocumentChunker,
+    key: &CacheKey,
+    mut writer: impl Write,
+) -> Result<ExportReport> {
+    let index = load_cached_index(global, key)?;
+
+    write_record(
+        &mut writer,
+        &BundleRecord::Header {
+            format_version: BUNDLE_FORMAT_VERSION,
+            crate_name: index.crate_name.clone(),
+            version: index.version.clone(),
+            features: index.features.clone(),
+            model: index.model.clone(),
+        },
+    )?;
+
+    let mut report = ExportReport::default();
+    for doc in &index.documents {
+        write_record(
+            &mut writer,
+            &BundleRecord::Document {
+                path: doc.path.clone(),
+                content: doc.content.clone(),
+                vector: doc.vector.clone(),
+            },
+        )?;
+        report.documents += 1;
+    }
+
+    for doc in &index.documents {
+        for chunk in chunker.embeddable_chunks(&doc.content) {
+            let Some(cached) = read_cached_chunk(chunk_dir, &chunk.id) else {
+                report.missing_chunks += 1;
+                continue;
+            };
+            write_record(
+                &mut writer,
+                &BundleRecord::Chunk {
+                    id: chunk.id,
+                    document_path: doc.path.clone(),
+                    content: cached.document,
+                    vector: cached.vector,
+                    model: cached.model,
+                    provider: cached.provider,
+                },
+            )?;
+            report.chunks += 1;
+        }
+    }
+
+    writer.flush()?;
+    Ok(report)
+}
+
+/// Reads a JSONL bundle and stores its index and chunk embeddings in the local caches
+pub fn import_bundle(
+    reader: impl BufRead,
+    global: &GlobalCache,
+    chunk_dir: &Path,
+    chunker: &DocumentChunker,
+) -> Result<ImportReport> {
+    ensure_dir_exists(chunk_dir)?;
+
+    let mut index: Option<CachedCrateIndex> = None;
+    let mut report = ImportReport::default();
+
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: BundleRecord = serde_json::from_str(&line).map_err(|e| {
+            ServerError::Config(format!("Invalid bundle line {}: {}", line_number + 1, e))
+        })?;
+
+        match (record, index.as_mut()) {
+            (
+                BundleRecord::Header {
+                    format_version,
+                    crate_name,
+                    version,
+                    features,
+                    model,
+                },
+                None,
+            ) => {
+                if format_version > BUNDLE_FORMAT_VERSION {
+                    return Err(ServerError::Config(format!(
+                        "Bundle format version {} is newer than the supported version {}",
+                        format_version, BUNDLE_FORMAT_VERSION
+                    )));
+                }
+                index = Some(CachedCrateIndex {
+                    crate_name,
+                    version,
+                    features,
+                    model,
+                    documents: Vec::new(),
+                });
+            }
+            (BundleRecord::Header { .. }, Some(_)) => {
+                return Err(ServerError::Config(format!(
+                    "Unexpected second header on bundle line {}",
+                    line_number + 1
+                )));
+            }
+            (_, None) => {
+                return Err(ServerError::Config(
+                    "Bundle must start with a header record".to_string(),
+                ));
+            }
+            (
+                BundleRecord::Document {
+                    path,
+                    content,
+                    vector,
+                },
+                Some(index),
+            ) => {
+                index.documents.push(CachedDocumentEmbedding {
+                    path,
+                    content,
+                    vector,
+                });
+                report.documents += 1;
+            }
+            (
+                BundleRecord::Chunk {
+                    id,
+                    content,
+                    vector,
+                    model,
+                    provider,
+                    ..
+                },
+                Some(_),
+            ) => {
+                // The id must hash the content; bundles exported under an
+                // older chunk id scheme are stored under the current one
+                let current_id = chunker.generate_chunk_id(&content);
+                if document_chunker::chunk_id_hash(&current_id)
+                    != document_chunker::chunk_id_hash(&id)
+                    || vector.is_empty()
+                {
+                    report.rejected_chunks += 1;
+                    continue;
+                }
+                let cached = CachedEmbedding {
+                    norm: Some(l2_norm(&vector)),
+                    vector,
+                    document: content,
+                    model,
+                    provider,
+                    input_type: None,
+                };
+                fs::write(chunk_dir.join(&current_id), serde_json::to_string(&cached)?)?;
+                report.chunks += 1;
+            }
+        }
+    }
+
+    let index = index.ok_or_else(|| ServerError::Config("Bundle is empty".to_string()))?;
+    let key = CacheKey {
+        crate_name: index.crate_name.clone(),
+        version: index.version.clone(),
+        features: index.features.clone(),
+    };
+    global.store(&key, &index)?;
+
+    report.crate_name = index.crate_name;
+    report.version = index.version;
+    Ok(report)
+}
+
+/// Loads the cached index of a crate, failing if it was never built
+pub(crate) fn load_cached_index(global: &GlobalCache, key: &CacheKey) -> Result<CachedCrateIndex> {
+    global.load(key)?.ok_or_else(|| {
+        ServerError::Config(format!(
+            "No cached index for crate '{}' (version '{}'); query it once to build one",
+            key.crate_name, key.version
+        ))
+    })
+}
+
+/// Reads a chunk's embedding from the chunk cache, if present and readable
+pub(crate) fn read_cached_chunk(chunk_dir: &Path, chunk_id: &str) -> Option<CachedEmbedding> {
+    fs::read_to_string(chunk_dir.join(chunk_id))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+}
+
+fn write_record(writer: &mut impl Write, record: &BundleRecord) -> Result<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+impl fmt::Display for ExportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Exported {} documents and {} chunk embeddings",
+            self.documents, self.chunks
+        )?;
+        if self.missing_chunks > 0 {
+            write!(
+                f,
+                " ({} chunks were not in the chunk cache)",
+                self.missing_chunks
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} documents and {} chunk embeddings for {}@{}",
+            self.documents, self.chunks, self.crate_name, self.version
+        )?;
+        if self.rejected_chunks > 0 {
+            write!(
+                f,
+                " ({} chunks rejected: id does not match content)",
+                self.rejected_chunks
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,709 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::ann_index::AnnIndex;
+use crate::document_chunker::{self, DocumentChunker};
+use crate::embedding_cache_service::CachedEmbedding;
+use crate::embeddings::{Embedding, EmbeddingProvider};
+use crate::error::Result;
+use crate::global_cache::{self, CachedCrateIndex, GlobalCache};
+use crate::index_manifest::IndexManifest;
+use crate::telemetry::Telemetry;
+
+/// Entry count and on-disk size for a group of cache entries
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EntryStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl EntryStats {
+    fn add(&mut self, bytes: u64) {
+        self.entries += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Summary of one crate index stored in the global cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateStats {
+    pub model: String,
+    pub documents: usize,
+    pub bytes: u64,
+}
+
+/// Size and entry counts for both caches
+#[derive(Debug)]
+pub struct CacheStats {
+    pub chunk_dir: PathBuf,
+    pub chunk_total: EntryStats,
+    pub chunks_by_model: BTreeMap<String, EntryStats>,
+    pub global_dir: PathBuf,
+    pub global_total: EntryStats,
+    /// Crate indexes keyed by `crate@version`
+    pub crates: BTreeMap<String, CrateStats>,
+    /// Chunk embeddings referenced by more than one crate index, embedded
+    /// once and shared
+    pub shared_chunks: usize,
+}
+
+/// Result of clearing the caches
+#[derive(Debug, Default)]
+pub struct ClearReport {
+    pub removed: EntryStats,
+}
+
+/// Result of pruning orphaned chunk embeddings
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    /// Number of chunk ids referenced by at least one cached crate index
+    pub referenced: usize,
+    pub removed: EntryStats,
+    /// Set when pruning was skipped because no crate index exists
+    pub skipped: bool,
+}
+
+/// Result of moving chunk embeddings cached under an older chunk id scheme
+/// to the current one
+#[derive(Debug, Default)]
+pub struct MigrateReport {
+    /// Entries renamed to their current chunk id
+    pub migrated: EntryStats,
+    /// Entries removed because their name matched no scheme, or an entry
+    /// with the current id already existed
+    pub removed: EntryStats,
+}
+
+/// Result of verifying cache integrity
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    /// Entries that failed verification along with the reason
+    pub problems: Vec<(PathBuf, String)>,
+    /// Number of broken entries removed (only when fixing)
+    pub removed: usize,
+}
+
+/// Result of compacting the caches
+#[derive(Debug, Default)]
+pub struct CompactReport {
+    /// Size of both caches and the telemetry database before compacting
+    pub size_before: u64,
+    pub size_after: u64,
+    /// Files of cache entries with neither a crate index nor generated docs,
+    /// e.g. the graphs of crates no longer indexed, and of interrupted writes
+    pub stale: EntryStats,
+    /// Crate indexes rewritten because their files held unused bytes
+    pub rewritten: usize,
+    /// HNSW graphs rebuilt from their crate's embeddings
+    pub graphs_rebuilt: usize,
+    /// HNSW graphs removed because their crate's manifest is gone
+    pub graphs_removed: usize,
+    pub pruned: PruneReport,
+    /// Whether the telemetry database was vacuumed
+    pub vacuumed: bool,
+}
+
+impl CompactReport {
+    /// Bytes the caches shrank by
+    pub fn reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
+/// Lists the chunk embedding files in the chunk cache directory
+fn chunk_files(chunk_dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(chunk_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+            .map(|entry| entry.path())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Total size of the files under `dir`
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| file_size(entry.path()))
+        .sum()
+}
+
+/// Removes `dir` and everything in it, counting the files removed
+fn remove_counted(dir: &Path, removed: &mut EntryStats) -> Result<()> {
+    for file in walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+    {
+        removed.add(file_size(file.path()));
+    }
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+/// Removes a cache entry directory along with the version and crate
+/// directories it leaves empty, counting the files removed
+fn remove_entry_dir(entry_dir: &Path, removed: &mut EntryStats) -> Result<()> {
+    remove_counted(entry_dir, removed)?;
+    for parent in entry_dir.ancestors().skip(1).take(2) {
+        if fs::remove_dir(parent).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_chunk(path: &Path) -> std::result::Result<CachedEmbedding, String> {
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&data).map_err(|e| e.to_string())
+}
+
+/// Collects size and entry statistics for both caches, re-chunking indexes
+/// that don't list their chunks with `chunker`
+pub fn stats(chunk_dir: &Path, global: &GlobalCache, chunker: &DocumentChunker) -> CacheStats {
+    let mut chunk_total = EntryStats::default();
+    let mut chunks_by_model: BTreeMap<String, EntryStats> = BTreeMap::new();
+
+    for path in chunk_files(chunk_dir) {
+        let size = file_size(&path);
+        chunk_total.add(size);
+        let model = read_chunk(&path)
+            .map(|cached| cached.model)
+            .unwrap_or_else(|_| "<unreadable>".to_string());
+        chunks_by_model.entry(model).or_default().add(size);
+    }
+
+    let mut global_total = EntryStats::default();
+    let mut crates = BTreeMap::new();
+    for path in global.index_files() {
+        let size = file_size(&path);
+        global_total.add(size);
+        if let Ok(index) = global_cache::read_index(&path) {
+            crates.insert(
+                format!("{}@{}", index.crate_name, index.version),
+                CrateStats {
+                    model: index.model,
+                    documents: index.documents.len(),
+                    bytes: size,
+                },
+            );
+        }
+    }
+
+    let shared_chunks = reference_counts(global, chunker)
+        .map(|counts| counts.values().filter(|&&count| count > 1).count())
+        .unwrap_or(0);
+
+    CacheStats {
+        chunk_dir: chunk_dir.to_path_buf(),
+        chunk_total,
+        chunks_by_model,
+        global_dir: global.root().to_path_buf(),
+        global_total,
+        crates,
+        shared_chunks,
+    }
+}
+
+/// Removes every entry from both caches. The rest of the global cache root,
+/// such as telemetry, the audit log, ingested pages, doc workspaces and the
+/// index snapshot, is left in place.
+pub fn clear(chunk_dir: &Path, global: &GlobalCache) -> Result<ClearReport> {
+    let mut report = ClearReport::default();
+
+    for path in chunk_files(chunk_dir) {
+        let size = file_size(&path);
+        fs::remove_file(&path)?;
+        report.removed.add(size);
+    }
+
+    for entry_dir in global.entry_dirs() {
+        remove_entry_dir(&entry_dir, &mut report.removed)?;
+    }
+    // Index files outside the `<crate>/<version>/<features hash>` layout
+    for path in global.index_files() {
+        let size = file_size(&path);
+        fs::remove_file(&path)?;
+        report.removed.add(size);
+    }
+
+    Ok(report)
+}
+
+/// Counts the cached crate indexes referencing each chunk cache entry. Indexes
+/// written before their chunks were recorded are re-chunked with `chunker`,
+/// which should have the configured chunker settings.
+pub fn reference_counts(
+    global: &GlobalCache,
+    chunker: &DocumentChunker,
+) -> Result<HashMap<String, usize>> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for path in global.index_files() {
+        let mut chunk_keys = match global_cache::read_chunk_refs(&path)? {
+            Some(chunk_keys) => chunk_keys,
+            None => {
+                // An unreadable index can't vouch for its chunks; leave everything in place
+                let index = global_cache::read_index(&path)?;
+                index
+                    .documents
+                    .iter()
+                    .flat_map(|doc| chunker.chunk_document(&doc.content))
+                    .map(|chunk| chunk.id)
+                    .collect()
+            }
+        };
+        chunk_keys.sort_unstable();
+        chunk_keys.dedup();
+        for chunk_key in chunk_keys {
+            *counts.entry(chunk_key).or_default() += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Removes chunk embeddings that aren't referenced by any cached crate index,
+/// re-chunking indexes that don't list their chunks with `chunker`
+pub fn prune(
+    chunk_dir: &Path,
+    global: &GlobalCache,
+    chunker: &DocumentChunker,
+) -> Result<PruneReport> {
+    if global.index_files().is_empty() {
+        return Ok(PruneReport {
+            skipped: true,
+            ..Default::default()
+        });
+    }
+
+    let referenced = reference_counts(global, chunker)?;
+    // Indexes written before a chunk id scheme change still name their
+    // chunks by the old ids, so chunks are matched by content hash alone
+    let referenced_hashes: HashSet<&str> = referenced
+        .keys()
+        .map(|id| document_chunker::chunk_id_hash(id))
+        .collect();
+
+    let mut report = PruneReport {
+        referenced: referenced.len(),
+        ..Default::default()
+    };
+    for path in chunk_files(chunk_dir) {
+        // Entries embedded with an input type are named after the type too,
+        // so only their content tells which chunk they belong to
+        let is_referenced = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| referenced_hashes.contains(document_chunker::chunk_id_hash(name)))
+            || read_chunk(&path).is_ok_and(|cached| {
+                cached.input_type.is_some()
+                    && referenced_hashes.contains(
+                        document_chunker::chunk_id_with_prefix("", &cached.document).as_str(),
+                    )
+            });
+        if !is_referenced {
+            let size = file_size(&path);
+            fs::remove_file(&path)?;
+            report.removed.add(size);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Reclaims the space the caches no longer need: removes cache entries
+/// whose crate index and generated docs are gone and files of interrupted
+/// writes, rewrites crate indexes holding unused bytes, rebuilds HNSW graphs
+/// from scratch, prunes chunk embeddings no index references, as [`prune`]
+/// does with `chunker`, and vacuums the telemetry database at `telemetry`,
+/// if any
+pub fn compact(
+    chunk_dir: &Path,
+    global: &GlobalCache,
+    chunker: &DocumentChunker,
+    telemetry: Option<&Path>,
+) -> Result<CompactReport> {
+    // A telemetry database in the global cache root, as by default, is
+    // already counted with it
+    let telemetry_outside = telemetry.filter(|path| !path.starts_with(global.root()));
+    let size =
+        || dir_size(chunk_dir) + dir_size(global.root()) + telemetry_outside.map_or(0, file_size);
+    let mut report = CompactReport {
+        size_before: size(),
+        ..Default::default()
+    };
+
+    for entry_dir in global.entry_dirs() {
+        let index_path = entry_dir.join(global_cache::INDEX_FILE_NAME);
+        if !index_path.is_file() {
+            // Generated docs are kept, so the crate is indexed again without
+            // running `cargo doc`
+            if !entry_dir.join(global_cache::DOCS_DIR_NAME).is_dir() {
+                remove_entry_dir(&entry_dir, &mut report.stale)?;
+            }
+            continue;
+        }
+
+        for leftover in chunk_files(&entry_dir)
+            .into_iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+        {
+            let size = file_size(&leftover);
+            fs::remove_file(&leftover)?;
+            report.stale.add(size);
+        }
+
+        // Unreadable indexes are left for `cache verify` to report
+        let Ok(index) = global_cache::read_index(&index_path) else {
+            continue;
+        };
+        if rewrite_index(&index_path, &index)? {
+            report.rewritten += 1;
+        }
+
+        let ann_path = entry_dir.join(global_cache::ANN_INDEX_FILE_NAME);
+        if !ann_path.is_file() {
+            continue;
+        }
+        let manifest_path = entry_dir.join(global_cache::MANIFEST_FILE_NAME);
+        match IndexManifest::load(&manifest_path).ok().flatten() {
+            Some(manifest) => {
+                // A graph built in one go has none of the slack of one grown
+                // vector by vector
+                let embeddings: Vec<(String, Embedding)> = index
+                    .documents
+                    .into_iter()
+                    .map(|doc| {
+                        let embedding = Embedding::new(
+                            doc.vector,
+                            EmbeddingProvider::OpenAI,
+                            index.model.clone(),
+                        );
+                        (doc.path, embedding)
+                    })
+                    .collect();
+                AnnIndex::build(
+                    &embeddings,
+                    &manifest.content_hash,
+                    &manifest.embedding_model,
+                    &ann_path,
+                )?;
+                report.graphs_rebuilt += 1;
+            }
+            None => {
+                AnnIndex::remove(&ann_path)?;
+                report.graphs_removed += 1;
+            }
+        }
+    }
+
+    report.pruned = prune(chunk_dir, global, chunker)?;
+
+    if let Some(path) = telemetry.filter(|path| path.is_file()) {
+        Telemetry::open(path)?.vacuum()?;
+        report.vacuumed = true;
+    }
+
+    report.size_after = size();
+    Ok(report)
+}
+
+/// Writes `index` back to `path` if its encoding is shorter than the file,
+/// e.g. because the file has bytes trailing the index. Returns true if it
+/// was rewritten.
+fn rewrite_index(path: &Path, index: &CachedCrateIndex) -> Result<bool> {
+    let bytes = bincode::encode_to_vec(index, bincode::config::standard())?;
+    if bytes.len() as u64 >= file_size(path) {
+        return Ok(false);
+    }
+    // Written under a temporary name so readers never see half an index
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, bytes)?;
+    fs::rename(&temp, path)?;
+    Ok(true)
+}
+
+/// Evicts the least recently modified chunk embeddings until the chunk cache
+/// fits in `max_bytes`
+pub fn enforce_size_limit(chunk_dir: &Path, max_bytes: u64) -> Result<EntryStats> {
+    let mut files: Vec<(PathBuf, u64, SystemTime)> = chunk_files(chunk_dir)
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = fs::metadata(&path).ok()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((path, metadata.len(), modified))
+        })
+        .collect();
+    files.sort_by_key(|(_, _, modified)| *modified);
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    let mut evicted = EntryStats::default();
+    for (path, size, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        fs::remove_file(&path)?;
+        total -= size;
+        evicted.add(size);
+    }
+
+    Ok(evicted)
+}
+
+/// Renames chunk embeddings cached under an older chunk id scheme, such as
+/// the bare SHA-256 ids of earlier versions, to the ids `chunker` gives them,
+/// so they are found again instead of lingering unused. Embeddings only
+/// depend on the chunk text, so no entry needs to be embedded again. Entries
+/// named by no known scheme are removed.
+pub fn migrate(chunk_dir: &Path, chunker: &DocumentChunker) -> Result<MigrateReport> {
+    let mut report = MigrateReport::default();
+    for path in chunk_files(chunk_dir) {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if document_chunker::chunk_id_prefix(name).is_some() {
+            continue;
+        }
+        let size = file_size(&path);
+        // Before ids were versioned, they were the hashes without a prefix
+        let key = read_chunk(&path)
+            .ok()
+            .filter(|cached| cached.key_with_prefix("") == name)
+            .map(|cached| cached.key(chunker));
+        match key {
+            Some(key) if !chunk_dir.join(&key).exists() => {
+                fs::rename(&path, chunk_dir.join(key))?;
+                report.migrated.add(size);
+            }
+            _ => {
+                fs::remove_file(&path)?;
+                report.removed.add(size);
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn verify_chunk(path: &Path) -> std::result::Result<(), String> {
+    let cached = read_chunk(path)?;
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    // Entries are checked against the scheme and chunker settings they were
+    // named by; bare hashes are those of entries not migrated yet
+    let prefix = document_chunker::chunk_id_prefix(file_name).unwrap_or_default();
+    if cached.key_with_prefix(prefix) != file_name {
+        return Err("content hash does not match file name".to_string());
+    }
+    if cached.vector.is_empty() {
+        return Err("empty embedding vector".to_string());
+    }
+    if cached.vector.iter().any(|v| !v.is_finite()) {
+        return Err("embedding vector contains non-finite values".to_string());
+    }
+    Ok(())
+}
+
+fn verify_index(path: &Path) -> std::result::Result<(), String> {
+    let index = global_cache::read_index(path).map_err(|e| e.to_string())?;
+    let dimensions = index.documents.first().map(|d| d.vector.len());
+    for doc in &index.documents {
+        if Some(doc.vector.len()) != dimensions || doc.vector.is_empty() {
+            return Err(format!(
+                "inconsistent embedding dimensions for {}",
+                doc.path
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Reads back every entry in both caches and reports corrupt ones.
+/// When `fix` is set, broken entries are deleted so they get regenerated.
+pub fn verify(chunk_dir: &Path, global: &GlobalCache, fix: bool) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+
+    let chunk_results = chunk_files(chunk_dir)
+        .into_iter()
+        .map(|path| (verify_chunk(&path), path));
+    let index_results = global
+        .index_files()
+        .into_iter()
+        .map(|path| (verify_index(&path), path));
+
+    for (result, path) in chunk_results.chain(index_results) {
+        report.checked += 1;
+        if let Err(reason) = result {
+            if fix {
+                fs::remove_file(&path)?;
+                report.removed += 1;
+            }
+            report.problems.push((path, reason));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Formats a byte count using binary units
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Chunk embedding cache: {}", self.chunk_dir.display())?;
+        writeln!(
+            f,
+            "  {} entries, {}",
+            self.chunk_total.entries,
+            format_bytes(self.chunk_total.bytes)
+        )?;
+        for (model, stats) in &self.chunks_by_model {
+            writeln!(
+                f,
+                "  {}: {} entries, {}",
+                model,
+                stats.entries,
+                format_bytes(stats.bytes)
+            )?;
+        }
+        writeln!(f, "Global crate cache: {}", self.global_dir.display())?;
+        writeln!(
+            f,
+            "  {} crate indexes, {}",
+            self.global_total.entries,
+            format_bytes(self.global_total.bytes)
+        )?;
+        for (name, stats) in &self.crates {
+            writeln!(
+                f,
+                "  {} ({}): {} documents, {}",
+                name,
+                stats.model,
+                stats.documents,
+                format_bytes(stats.bytes)
+            )?;
+        }
+        if self.shared_chunks > 0 {
+            writeln!(
+                f,
+                "  {} chunk embeddings shared between crates",
+                self.shared_chunks
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ClearReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Removed {} cache files ({})",
+            self.removed.entries,
+            format_bytes(self.removed.bytes)
+        )
+    }
+}
+
+impl fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.skipped {
+            return write!(
+                f,
+                "No crate indexes found in the global cache; nothing can be considered orphaned"
+            );
+        }
+        write!(
+            f,
+            "Removed {} orphaned chunk embeddings ({}); {} referenced chunks kept",
+            self.removed.entries,
+            format_bytes(self.removed.bytes),
+            self.referenced
+        )
+    }
+}
+
+impl fmt::Display for MigrateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Migrated {} chunk embeddings ({}) to chunk id scheme v{}; removed {} unusable entries ({})",
+            self.migrated.entries,
+            format_bytes(self.migrated.bytes),
+            document_chunker::CHUNK_ID_VERSION,
+            self.removed.entries,
+            format_bytes(self.removed.bytes)
+        )
+    }
+}
+
+impl fmt::Display for CompactReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Removed {} files of stale cache entries ({})",
+            self.stale.entries,
+            format_bytes(self.stale.bytes)
+        )?;
+        writeln!(f, "Rewrote {} crate indexes", self.rewritten)?;
+        writeln!(
+            f,
+            "Rebuilt {} ANN indexes; removed {} without a manifest",
+            self.graphs_rebuilt, self.graphs_removed
+        )?;
+        writeln!(f, "{}", self.pruned)?;
+        if self.vacuumed {
+            writeln!(f, "Vacuumed the telemetry database")?;
+        }
+        writeln!(
+            f,
+            "Reclaimed {} ({} -> {})",
+            format_bytes(self.reclaimed()),
+            format_bytes(self.size_before),
+            format_bytes(self.size_after)
+        )
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Checked {} cache entries, {} problems found",
+            self.checked,
+            self.problems.len()
+        )?;
+        for (path, reason) in &self.problems {
+            writeln!(f, "  {}: {}", path.display(), reason)?;
+        }
+        if self.removed > 0 {
+            writeln!(f, "Removed {} broken entries", self.removed)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//! Locates the Cargo project whose docs and dependencies are served: the one
+//! `cargo docs-mcp` is run in, or one of the workspace roots an MCP client
+//! reports, so no configuration is needed.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{Config, DocsSettings};
+use crate::error::{Result, ServerError};
+
+/// Environment variables cargo reads the target directory from, in priority order
+const TARGET_DIR_VARS: &[&str] = &["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"];
+
+/// A Cargo workspace and where it builds to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoProject {
+    /// Directory of the workspace's root `Cargo.toml`
+    pub root: PathBuf,
+    /// Build output directory, `<root>/target` unless overridden
+    pub target_dir: PathBuf,
+}
+
+impl CargoProject {
+    /// Finds the workspace containing `start`. Cargo passes its own path to
+    /// subcommands in `CARGO`, which is asked for the workspace root; without
+    /// it, the nearest `Cargo.toml` above `start` is used. The target directory
+    /// follows `CARGO_TARGET_DIR`/`CARGO_BUILD_TARGET_DIR` like cargo does.
+    pub fn detect(start: &Path) -> Result<Self> {
+        let root = locate_workspace_root(start)
+            .or_else(|| find_manifest_dir(start))
+            .ok_or_else(|| {
+                ServerError::Config(format!(
+                    "No Cargo.toml found in {} or any parent directory",
+                    start.display()
+                ))
+            })?;
+        let target_dir_override = TARGET_DIR_VARS
+            .iter()
+            .find_map(env::var_os)
+            .filter(|value| !value.is_empty())
+            .map(|value| start.join(value));
+        Ok(Self::new(root, target_dir_override))
+    }
+
+    /// Describes the workspace at `root`, building to `target_dir` if given
+    pub fn new(root: PathBuf, target_dir: Option<PathBuf>) -> Self {
+        let target_dir = target_dir.unwrap_or_else(|| root.join("target"));
+        Self { root, target_dir }
+    }
+
+    /// Directory `cargo doc` writes to
+    pub fn doc_dir(&self) -> PathBuf {
+        self.target_dir.join("doc")
+    }
+
+    /// Points the doc directory and project at this workspace, unless the
+    /// configuration already chose them
+    pub fn apply_to(&self, config: &mut Config) {
+        let defaults = DocsSettings::default();
+        if config.docs.dir == defaults.dir {
+            config.docs.dir = self.doc_dir();
+        }
+        if config.docs.project_dir == defaults.project_dir {
+            config.docs.project_dir = self.root.clone();
+        }
+    }
+
+    /// Returns a configuration for this workspace, with paths inside it
+    /// written relative to the root so the file can be committed
+    pub fn generated_config(&self) -> Config {
+        let mut config = Config::default();
+        config.docs.dir = self.relative(&self.doc_dir());
+        config.docs.project_dir = PathBuf::from(".");
+        config
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.root) {
+            Ok(relative) => Path::new(".").join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+/// Asks cargo for the workspace root, if cargo is available and `start` is in a project
+fn locate_workspace_root(start: &Path) -> Option<PathBuf> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .current_dir(start)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let manifest = PathBuf::from(String::from_utf8(output.stdout).ok()?.trim());
+    manifest.parent().map(Path::to_path_buf)
+}
+
+/// Returns the nearest directory at or above `start` containing a `Cargo.toml`
+pub fn find_manifest_dir(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Picks the project among the workspace roots a client reported (as
+/// `file://` URIs): the first root inside a Cargo project, otherwise the first
+/// root that is an existing directory
+pub fn project_from_roots<'a>(uris: impl IntoIterator<Item = &'a str>) -> Option<CargoProject> {
+    let dirs: Vec<PathBuf> = uris
+        .into_iter()
+        .filter_map(path_from_file_uri)
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.iter()
+        .find_map(|dir| CargoProject::detect(dir).ok())
+        .or_else(|| dirs.first().map(|dir| CargoProject::new(dir.clone(), None)))
+}
+
+/// Converts a `file://` URI to a local path, decoding percent escapes.
+/// Other schemes and remote hosts yield `None`.
+pub fn path_from_file_uri(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        return None;
+    }
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+
+    // file:///C:/dir names a Windows drive path
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &path[1..],
+        _ => path.as_str(),
+    };
+    Some(PathBuf::from(path))
+}
@@ -0,0 +1,203 @@
+//! Records the HTTP traffic of provider requests to a cassette file and
+//! replays it, so tests exercise real request and response shapes without an
+//! API key or network access.
+//!
+//! With `http.cassette` set, the [`OpenAiClient`](crate::openai_client::OpenAiClient)
+//! sends requests to the provider and appends every exchange to the file in
+//! `record` mode, or answers them from the file in `replay` mode. Replayed
+//! requests must match a recorded one by method, path under the API base and
+//! JSON body; recorded exchanges are used in order, so a rate-limited attempt
+//! and its retry replay as they happened. The API key and headers are never
+//! recorded.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::error::{ProviderErrorKind, Result, ServerError};
+use crate::utils::with_context;
+
+/// Whether a cassette is written or read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMode {
+    /// Answer requests from the cassette, failing on ones it doesn't hold
+    #[default]
+    Replay,
+    /// Send requests to the provider and record them, replacing the cassette
+    Record,
+}
+
+/// One request and the provider's response to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Interaction {
+    /// `GET` or `POST`
+    pub method: String,
+    /// Path under the API base, e.g. `embeddings`
+    pub path: String,
+    /// JSON body of the request, if it had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<Value>,
+    /// HTTP status of the response
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+    /// Body of the response: its JSON, or a string if it wasn't JSON
+    pub response: Value,
+}
+
+impl Interaction {
+    /// Body of the response as the provider sent it
+    pub fn body(&self) -> String {
+        match &self.response {
+            Value::String(text) => text.clone(),
+            json => json.to_string(),
+        }
+    }
+
+    /// Delay the provider asked for before retrying
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after_secs.map(Duration::from_secs)
+    }
+
+    fn matches(&self, method: &str, path: &str, request: Option<&Value>) -> bool {
+        self.method == method && self.path == path && self.request.as_ref() == request
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    interactions: Vec<Interaction>,
+    // Whether each interaction was replayed already
+    used: Vec<bool>,
+}
+
+/// Recorded provider traffic, backed by a JSON file
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<State>,
+}
+
+impl Cassette {
+    /// Opens the cassette at `path`: reads it for replaying, or starts it
+    /// over for recording
+    pub fn open(path: &Path, mode: CassetteMode) -> Result<Self> {
+        let interactions = match mode {
+            CassetteMode::Replay => {
+                let context = || format!("Failed to read cassette {}", path.display());
+                let text = with_context(fs::read_to_string(path), context)?;
+                let file: CassetteFile = with_context(serde_json::from_str(&text), context)?;
+                file.interactions
+            }
+            CassetteMode::Record => Vec::new(),
+        };
+        let cassette = Self {
+            path: path.to_path_buf(),
+            mode,
+            state: Mutex::new(State {
+                used: vec![false; interactions.len()],
+                interactions,
+            }),
+        };
+        if mode == CassetteMode::Record {
+            cassette.save(&[])?;
+        }
+        Ok(cassette)
+    }
+
+    /// The cassette at `path`, shared by every client of the process using
+    /// it, so the embedding and chat clients record to the same file
+    pub fn shared(path: &Path, mode: CassetteMode) -> Result<Arc<Self>> {
+        type OpenCassettes = HashMap<(PathBuf, CassetteMode), Arc<Cassette>>;
+        static OPEN: OnceLock<Mutex<OpenCassettes>> = OnceLock::new();
+        let mut open = OPEN
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let key = (path.to_path_buf(), mode);
+        if let Some(cassette) = open.get(&key) {
+            return Ok(Arc::clone(cassette));
+        }
+        let cassette = Arc::new(Self::open(path, mode)?);
+        open.insert(key, Arc::clone(&cassette));
+        Ok(cassette)
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// The recorded response to a request: the first matching interaction not
+    /// replayed yet, else the last matching one
+    pub fn replay(&self, method: &str, path: &str, request: Option<&Value>) -> Result<Interaction> {
+        let mut state = self.lock();
+        let matching: Vec<usize> = (0..state.interactions.len())
+            .filter(|&i| state.interactions[i].matches(method, path, request))
+            .collect();
+        let found = matching
+            .iter()
+            .find(|&&i| !state.used[i])
+            .or(matching.last())
+            .copied();
+        match found {
+            Some(i) => {
+                state.used[i] = true;
+                Ok(state.interactions[i].clone())
+            }
+            None => Err(ServerError::Provider {
+                kind: ProviderErrorKind::Other,
+                status: None,
+                message: format!(
+                    "No recorded response to {} {} in cassette {}; record it again with http.cassette_mode = \"record\"",
+                    method,
+                    path,
+                    self.path.display()
+                ),
+                retry_after: None,
+            }),
+        }
+    }
+
+    /// Appends an exchange and writes the cassette
+    pub fn record(&self, interaction: Interaction) -> Result<()> {
+        let mut state = self.lock();
+        state.interactions.push(interaction);
+        state.used.push(true);
+        self.save(&state.interactions)
+    }
+
+    /// The interactions recorded or loaded so far
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.lock().interactions.clone()
+    }
+
+    fn save(&self, interactions: &[Interaction]) -> Result<()> {
+        let file = CassetteFile {
+            interactions: interactions.to_vec(),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        with_context(
+            fs::write(&self.path, serde_json::to_string_pretty(&file)?),
+            || format!("Failed to write cassette {}", self.path.display()),
+        )
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
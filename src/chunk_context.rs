@@ -0,0 +1,91 @@
+//! Cuts long matched documents down to the part that answers the question.
+//!
+//! A page like a big trait's or a crate root can be far longer than the few
+//! paragraphs a question is about. Such documents are sent to the LLM as
+//! their best matching chunk plus as many of the chunks around it as fit the
+//! token budget, so a definition split across a chunk boundary (signature in
+//! one chunk, example in the next) still arrives whole.
+
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use tiktoken_rs::CoreBPE;
+
+use crate::document_chunker::Chunk;
+
+/// Marks text left out before or after the sent chunks
+pub const OMITTED: &str = "[...]";
+
+/// Number of tokens `text` takes up in the LLM's context. The cl100k_base
+/// tokenizer is loaded once and shared by every caller. Falls back to an
+/// estimate of four bytes per token if it can't be loaded.
+pub fn count_tokens(text: &str) -> usize {
+    static TOKENIZER: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    match TOKENIZER.get_or_init(|| tiktoken_rs::cl100k_base().ok()) {
+        Some(tokenizer) => tokenizer.encode_ordinary(text).len(),
+        None => text.len().div_ceil(4),
+    }
+}
+
+/// Range of chunks sent for a document whose chunk `best` matches best,
+/// given each chunk's token count. Neighbors are added alternately after and
+/// before the range, the following one first since examples follow the
+/// signatures they show, for as long as they fit within `budget`. The best
+/// chunk is always included.
+pub fn expand(chunk_tokens: &[usize], best: usize, budget: usize) -> Range<usize> {
+    let mut range = best..best + 1;
+    let mut used = chunk_tokens[best];
+    let (mut forward, mut backward) = (true, true);
+    while forward || backward {
+        if forward {
+            match chunk_tokens.get(range.end) {
+                Some(&tokens) if used + tokens <= budget => {
+                    used += tokens;
+                    range.end += 1;
+                }
+                _ => forward = false,
+            }
+        }
+        if backward {
+            match range.start.checked_sub(1).map(|i| chunk_tokens[i]) {
+                Some(tokens) if used + tokens <= budget => {
+                    used += tokens;
+                    range.start -= 1;
+                }
+                _ => backward = false,
+            }
+        }
+    }
+    range
+}
+
+/// Number of tokens of the text [`assemble`] makes of the chunks in `range`,
+/// estimated from the chunks' own token counts without tokenizing it again.
+/// Tokens may merge across chunk boundaries, so it can be off by a few.
+pub fn assembled_tokens(chunks: &[Chunk], range: Range<usize>) -> usize {
+    let markers = usize::from(range.start > 0) + usize::from(range.end < chunks.len());
+    let chunk_tokens: usize = chunks[range].iter().map(|chunk| chunk.tokens).sum();
+    // Each marker also takes up a line break
+    chunk_tokens + markers * (count_tokens(OMITTED) + 1)
+}
+
+/// Joins the chunks in `range`, marking where text before or after them was
+/// left out
+pub fn assemble(chunks: &[Chunk], range: Range<usize>) -> String {
+    let mut text = String::new();
+    if range.start > 0 {
+        text.push_str(OMITTED);
+        text.push('\n');
+    }
+    for chunk in &chunks[range.clone()] {
+        text.push_str(&chunk.content);
+    }
+    if range.end < chunks.len() {
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(OMITTED);
+        text.push('\n');
+    }
+    text
+}
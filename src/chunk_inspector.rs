@@ -0,0 +1,241 @@
+//! Reports how the chunker splits a crate's documentation: the boundaries,
+//! sizes, token counts and ids of every page's chunks, and how many chunks keep their id
+//! when a page is edited. Used by `inspect chunks` to tune the `[chunker]`
+//! settings for a corpus.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::doc_loader::Document;
+use crate::document_chunker::{self, DocumentChunker};
+
+/// Number of characters of a chunk id's content hash shown in reports
+const SHORT_ID_LEN: usize = 16;
+
+/// The start of the content hash of a chunk id, without the scheme prefix
+fn short_id(id: &str) -> &str {
+    let hash = document_chunker::chunk_id_hash(id);
+    &hash[..SHORT_ID_LEN.min(hash.len())]
+}
+
+/// A chunk's position in its page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSpan {
+    pub id: String,
+    /// Byte offsets of the chunk in the page's extracted text
+    pub start: usize,
+    pub end: usize,
+    /// LLM tokens the chunk takes up
+    pub tokens: usize,
+}
+
+impl ChunkSpan {
+    /// Size of the chunk in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if the chunk holds no text
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// The chunks of one page
+#[derive(Debug, Clone)]
+pub struct PageChunks {
+    pub path: String,
+    pub bytes: usize,
+    pub chunks: Vec<ChunkSpan>,
+}
+
+/// How a crate's documentation is chunked
+#[derive(Debug, Clone)]
+pub struct ChunkReport {
+    pub crate_name: String,
+    /// Minimum, target and maximum chunk size of the chunker, in bytes
+    pub sizes: (usize, usize, usize),
+    pub pages: Vec<PageChunks>,
+    /// Chunks of the edited pages that kept their id after a one-character
+    /// edit in the middle of the page
+    pub stable_chunks: usize,
+    /// Chunks of the pages the edit was tried on
+    pub edited_chunks: usize,
+}
+
+/// Chunks every document and measures how stable the chunk ids are
+pub fn inspect_chunks(
+    crate_name: &str,
+    chunker: &DocumentChunker,
+    documents: &[Document],
+) -> ChunkReport {
+    let mut report = ChunkReport {
+        crate_name: crate_name.to_string(),
+        sizes: (
+            chunker.min_chunk_size(),
+            chunker.target_chunk_size(),
+            chunker.max_chunk_size(),
+        ),
+        pages: Vec::with_capacity(documents.len()),
+        stable_chunks: 0,
+        edited_chunks: 0,
+    };
+
+    for doc in documents {
+        let mut start = 0;
+        let chunks: Vec<ChunkSpan> = chunker
+            .chunk_document(&doc.content)
+            .into_iter()
+            .map(|chunk| {
+                let span = ChunkSpan {
+                    id: chunk.id,
+                    start,
+                    end: start + chunk.content.len(),
+                    tokens: chunk.tokens,
+                };
+                start = span.end;
+                span
+            })
+            .collect();
+
+        if !doc.content.is_empty() {
+            let edited: HashSet<String> = chunker
+                .chunk_document(&edit_middle(&doc.content))
+                .into_iter()
+                .map(|chunk| chunk.id)
+                .collect();
+            report.edited_chunks += chunks.len();
+            report.stable_chunks += chunks
+                .iter()
+                .filter(|chunk| edited.contains(&chunk.id))
+                .count();
+        }
+
+        report.pages.push(PageChunks {
+            path: doc.path.clone(),
+            bytes: doc.content.len(),
+            chunks,
+        });
+    }
+    report
+}
+
+/// Inserts a character in the middle of `text`
+fn edit_middle(text: &str) -> String {
+    let mut middle = text.len() / 2;
+    while !text.is_char_boundary(middle) {
+        middle -= 1;
+    }
+    format!("{}x{}", &text[..middle], &text[middle..])
+}
+
+impl ChunkReport {
+    fn chunks(&self) -> impl Iterator<Item = &ChunkSpan> {
+        self.pages.iter().flat_map(|page| &page.chunks)
+    }
+
+    /// Total number of chunks
+    pub fn chunk_count(&self) -> usize {
+        self.chunks().count()
+    }
+
+    /// Number of distinct chunk ids; lower than the chunk count when pages
+    /// share identical chunks, which are embedded once
+    pub fn unique_ids(&self) -> usize {
+        self.chunks()
+            .map(|chunk| chunk.id.as_str())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Renders every page's chunk boundaries, sizes and ids
+    pub fn format_pages(&self) -> String {
+        let mut text = String::new();
+        for page in &self.pages {
+            text.push_str(&format!(
+                "{} ({} bytes, {} chunks)\n",
+                page.path,
+                page.bytes,
+                page.chunks.len()
+            ));
+            for chunk in &page.chunks {
+                text.push_str(&format!(
+                    "  {:>7}..{:<7} {:>6} B {:>5} tok  {}\n",
+                    chunk.start,
+                    chunk.end,
+                    chunk.len(),
+                    chunk.tokens,
+                    short_id(&chunk.id)
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// Summary statistics
+impl fmt::Display for ChunkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (min, target, max) = self.sizes;
+        writeln!(
+            f,
+            "Chunks of '{}' (min {}, target {}, max {} bytes)",
+            self.crate_name, min, target, max
+        )?;
+        let split = self
+            .pages
+            .iter()
+            .filter(|page| page.chunks.len() > 1)
+            .count();
+        writeln!(
+            f,
+            "Pages:  {} ({} split into several chunks)",
+            self.pages.len(),
+            split
+        )?;
+        writeln!(
+            f,
+            "Chunks: {} ({} unique ids)",
+            self.chunk_count(),
+            self.unique_ids()
+        )?;
+
+        let mut sizes: Vec<usize> = self.chunks().map(ChunkSpan::len).collect();
+        if sizes.is_empty() {
+            return Ok(());
+        }
+        sizes.sort_unstable();
+        let at = |fraction: usize| sizes[(sizes.len() - 1) * fraction / 4];
+        writeln!(
+            f,
+            "Size:   min {}, 25% {}, median {}, 75% {}, max {}, mean {} bytes",
+            at(0),
+            at(1),
+            at(2),
+            at(3),
+            at(4),
+            sizes.iter().sum::<usize>() / sizes.len()
+        )?;
+        let tokens: usize = self.chunks().map(|chunk| chunk.tokens).sum();
+        writeln!(
+            f,
+            "Tokens: {} total, mean {} per chunk",
+            tokens,
+            tokens / sizes.len()
+        )?;
+        // Chunks cut by size rather than at a content boundary or page end
+        let at_target = sizes.iter().filter(|&&size| size == target).count();
+        let at_max = sizes.iter().filter(|&&size| size >= max).count();
+        writeln!(f, "Cut by size: {} at target, {} at max", at_target, at_max)?;
+        if self.edited_chunks > 0 {
+            writeln!(
+                f,
+                "Stability: {}/{} chunks ({:.1}%) keep their id after a one-character edit in the middle of their page",
+                self.stable_chunks,
+                self.edited_chunks,
+                100.0 * self.stable_chunks as f64 / self.edited_chunks as f64
+            )?;
+        }
+        Ok(())
+    }
+}
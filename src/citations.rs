@@ -0,0 +1,139 @@
+//! Labels the documentation sent to the LLM with where it comes from: each
+//! page starts with its crate and item path, and each of its headings is
+//! replaced by the trail of headings leading to it, so answers can cite
+//! e.g. "the `tokio::select!` docs, Fairness section".
+//!
+//! Extracted documents are flat text, so headings are found again in the
+//! page's HTML and matched to the lines of the text they were extracted to.
+
+use scraper::{ElementRef, Html, Selector};
+use std::path::Path;
+
+use crate::doc_loader::{self, Document};
+use crate::item_status::ItemStatus;
+use crate::page_text;
+
+/// Trail entries longer than this, e.g. long method signatures, are shortened
+const MAX_HEADING_CHARS: usize = 80;
+
+/// A heading of a page, with the lines its text nodes were extracted to
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Heading {
+    level: usize,
+    title: String,
+    lines: Vec<String>,
+}
+
+/// Renders a document for the LLM context, labelled with its crate and item
+/// path and, if the page's `html` is given, with the version the item is
+/// stable since, its deprecation and availability banners and heading trails
+pub fn cite_document(crate_name: &str, doc: &Document, html: Option<&str>) -> String {
+    let page = html.map(Html::parse_document);
+    let mut label = source_label(crate_name, &doc.path);
+    if let Some(since) = page.as_ref().and_then(page_text::page_since) {
+        label.push_str(&format!(", stable since Rust {}", since));
+    }
+    let mut text = format!("[Source: {}]\n", label);
+    let status = page.as_ref().map(ItemStatus::from_page).unwrap_or_default();
+    for note in status.notes() {
+        text.push_str(&format!("[Status: {}]\n", note));
+    }
+    let headings = page.as_ref().map(page_headings).unwrap_or_default();
+
+    // Find each heading's lines in order, skipping any that weren't extracted
+    let lines: Vec<&str> = doc.content.lines().collect();
+    let mut found = Vec::new();
+    let mut cursor = 0;
+    for heading in headings {
+        let offset = lines[cursor..]
+            .windows(heading.lines.len())
+            .position(|window| window.iter().eq(heading.lines.iter()));
+        if let Some(offset) = offset {
+            cursor += offset + heading.lines.len();
+            found.push((cursor - heading.lines.len(), heading));
+        }
+    }
+
+    let mut trail: Vec<(usize, String)> = Vec::new();
+    let mut found = found.into_iter().peekable();
+    let mut i = 0;
+    while i < lines.len() {
+        match found.next_if(|(at, _)| *at == i) {
+            Some((_, heading)) => {
+                trail.retain(|(level, _)| *level < heading.level);
+                trail.push((heading.level, heading.title));
+                let titles: Vec<&str> = trail.iter().map(|(_, title)| title.as_str()).collect();
+                text.push_str(&format!("[Section: {}]\n", titles.join(" > ")));
+                i += heading.lines.len();
+            }
+            None => {
+                text.push_str(lines[i]);
+                text.push('\n');
+                i += 1;
+            }
+        }
+    }
+    text
+}
+
+/// Names the crate, the item documented by the page at `path` (relative to
+/// the crate's doc directory) and the page itself
+fn source_label(crate_name: &str, path: &str) -> String {
+    let item = doc_loader::item_path(crate_name, Path::new(path)).map(|item| {
+        let is_macro = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("macro."));
+        if is_macro { format!("{}!", item) } else { item }
+    });
+    match item {
+        Some(item) => format!("crate `{}`, item `{}` ({})", crate_name, item, path),
+        None => format!("crate `{}` ({})", crate_name, path),
+    }
+}
+
+/// Headings of the page's main content below its title, in document order
+fn page_headings(page: &Html) -> Vec<Heading> {
+    let (Ok(selector), Ok(anchor)) = (
+        Selector::parse(
+            "section#main-content h2, section#main-content h3, section#main-content h4, \
+             section#main-content h5, section#main-content h6",
+        ),
+        Selector::parse("a.anchor, a.doc-anchor"),
+    ) else {
+        return Vec::new();
+    };
+    page.select(&selector)
+        .filter_map(|element| {
+            let level = element.value().name()[1..].parse().ok()?;
+            // Extracted text holds the anchors, but titles shouldn't
+            let anchors: Vec<ElementRef> = element.select(&anchor).collect();
+            let title: String = element
+                .descendants()
+                .filter(|node| {
+                    !anchors
+                        .iter()
+                        .any(|anchor| node.ancestors().any(|a| a.id() == anchor.id()))
+                })
+                .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+                .collect();
+            let title = shorten(&title.split_whitespace().collect::<Vec<_>>().join(" "));
+            // Split the same way as the extracted text, e.g. at where clauses
+            let extracted = page_text::extract(element);
+            let lines: Vec<String> = extracted.lines().map(str::to_string).collect();
+            (!title.is_empty() && !lines.is_empty()).then_some(Heading {
+                level,
+                title,
+                lines,
+            })
+        })
+        .collect()
+}
+
+fn shorten(title: &str) -> String {
+    if title.chars().count() <= MAX_HEADING_CHARS {
+        return title.to_string();
+    }
+    let short: String = title.chars().take(MAX_HEADING_CHARS - 1).collect();
+    format!("{}…", short.trim_end())
+}
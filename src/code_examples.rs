@@ -0,0 +1,66 @@
+//! Finds the code examples of a documentation page in its extracted text,
+//! so queries that don't want them can leave them out of the LLM context.
+//!
+//! As with headings in [`citations`](crate::citations), extracted documents
+//! are flat text, so the examples are read from the page's HTML and matched
+//! to the lines of the text they were extracted to.
+
+use scraper::{Html, Selector};
+
+use crate::page_text;
+
+/// Replaces each left out example
+pub const OMITTED_EXAMPLE: &str = "[Example omitted]";
+
+/// Returns `content`, extracted from the page `html`, without the code
+/// examples of its doc comments. Each example is replaced by a line
+/// [`OMITTED_EXAMPLE`]; item declarations and signatures are kept.
+pub fn strip_examples(content: &str, html: &str) -> String {
+    let examples = page_examples(html);
+    let lines: Vec<&str> = content.lines().collect();
+
+    // Find each example's lines in order, skipping any that weren't extracted
+    let mut found = Vec::new();
+    let mut cursor = 0;
+    for example in examples {
+        let offset = lines[cursor..]
+            .windows(example.len())
+            .position(|window| window.iter().eq(example.iter()));
+        if let Some(offset) = offset {
+            found.push(cursor + offset..cursor + offset + example.len());
+            cursor += offset + example.len();
+        }
+    }
+    if found.is_empty() {
+        return content.to_string();
+    }
+
+    let mut kept = Vec::with_capacity(lines.len());
+    let mut next = 0;
+    for range in found {
+        kept.extend_from_slice(&lines[next..range.start]);
+        kept.push(OMITTED_EXAMPLE);
+        next = range.end;
+    }
+    kept.extend_from_slice(&lines[next..]);
+    kept.join("\n")
+}
+
+/// Extracted lines of each code block in the page's doc comments, in
+/// document order
+fn page_examples(html: &str) -> Vec<Vec<String>> {
+    let Ok(selector) = Selector::parse("section#main-content .docblock pre") else {
+        return Vec::new();
+    };
+    let page = Html::parse_document(html);
+    page.select(&selector)
+        .map(|element| {
+            // Split the same way as the extracted text, at every text node
+            page_text::extract(element)
+                .lines()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter(|lines| !lines.is_empty())
+        .collect()
+}
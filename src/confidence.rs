@@ -0,0 +1,122 @@
+//! Estimates how far an answer can be trusted from the similarity scores of
+//! the retrieved documents, optionally combined with the chat model's own
+//! assessment, so agents can fall back to reading the raw docs.
+
+use std::fmt;
+
+/// Number of best-scoring documents whose scores are looked at, even when
+/// fewer are passed to the LLM
+pub const SCORE_SAMPLE: usize = 5;
+
+/// Best similarity at or below which retrieval is considered irrelevant
+const FLOOR_SCORE: f32 = 0.2;
+/// Best similarity at or above which retrieval is considered fully relevant
+const CEILING_SCORE: f32 = 0.6;
+/// Lead of the best score over the runners-up that counts as a clear winner
+const CLEAR_LEAD: f32 = 0.15;
+
+/// Coarse confidence bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfidenceLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl ConfidenceLevel {
+    fn from_value(value: f32) -> Self {
+        if value >= 0.7 {
+            Self::High
+        } else if value >= 0.4 {
+            Self::Medium
+        } else {
+            Self::Low
+        }
+    }
+
+    /// Parses `high`, `medium` or `low`, ignoring case
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "high" => Some(Self::High),
+            "medium" => Some(Self::Medium),
+            "low" => Some(Self::Low),
+            _ => None,
+        }
+    }
+
+    fn value(self) -> f32 {
+        match self {
+            Self::High => 0.9,
+            Self::Medium => 0.6,
+            Self::Low => 0.2,
+        }
+    }
+}
+
+impl fmt::Display for ConfidenceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        })
+    }
+}
+
+/// Confidence in an answer, between 0 and 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Confidence {
+    pub value: f32,
+    pub level: ConfidenceLevel,
+}
+
+impl Confidence {
+    /// Rates retrieval from the similarity scores of the best documents, best
+    /// first: mostly how similar the best one is, and partly how clearly it
+    /// beats the others. No documents means no confidence.
+    pub fn from_scores(scores: &[f32]) -> Self {
+        let Some((&best, rest)) = scores.split_first() else {
+            return Self::new(0.0);
+        };
+        let relevance = (best - FLOOR_SCORE) / (CEILING_SCORE - FLOOR_SCORE);
+        // A lone candidate says nothing about how it compares
+        let separation = if rest.is_empty() {
+            0.5
+        } else {
+            let runners_up = rest.iter().sum::<f32>() / rest.len() as f32;
+            (best - runners_up) / CLEAR_LEAD
+        };
+        Self::new(0.7 * relevance.clamp(0.0, 1.0) + 0.3 * separation.clamp(0.0, 1.0))
+    }
+
+    /// Averages in the level the chat model gave its own answer
+    pub fn with_assessment(self, assessed: ConfidenceLevel) -> Self {
+        Self::new((self.value + assessed.value()) / 2.0)
+    }
+
+    fn new(value: f32) -> Self {
+        Self {
+            value,
+            level: ConfidenceLevel::from_value(value),
+        }
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:.2})", self.level, self.value)
+    }
+}
+
+/// Splits a trailing `Confidence: <level>` line off an LLM answer, returning
+/// the rest of the answer and the level
+pub fn split_assessment(answer: &str) -> Option<(&str, ConfidenceLevel)> {
+    let trimmed = answer.trim_end();
+    let (body, last_line) = trimmed.rsplit_once('\n').unwrap_or(("", trimmed));
+    let level = last_line
+        .trim()
+        .trim_matches('*')
+        .strip_prefix("Confidence:")
+        .and_then(|level| ConfidenceLevel::parse(level.trim_matches('*')))?;
+    Some((body.trim_end(), level))
+}
@@ -0,0 +1,1108 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audit_log;
+use crate::cassette::CassetteMode;
+use crate::crate_discovery::{DocRoot, PROJECT_ROOT_NAME};
+use crate::crate_spec::normalize_crate_name;
+use crate::doc_generator::RustdocOptions;
+use crate::document_chunker::DocumentChunker;
+use crate::embedding_cache_service;
+use crate::embeddings::EmbeddingInputType;
+use crate::error::{Result, ServerError};
+use crate::global_cache::GlobalCache;
+use crate::mock::MOCK_PROVIDER;
+use crate::openai_client;
+use crate::platform_paths::ProjectDirs;
+use crate::redaction::Redactor;
+use crate::telemetry;
+
+/// File name looked up in the project directory and the user config directory
+pub const CONFIG_FILE_NAME: &str = "rustdocs-mcp.toml";
+
+/// Default embedding model
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Default chat model used to summarize answers
+pub const DEFAULT_LLM_MODEL: &str = "gpt-4o-mini-2024-07-18";
+
+/// OpenAI API base used when no `api_base` is configured
+pub const DEFAULT_API_BASE: &str = "https://api.openai.com/v1";
+
+/// Effective server configuration.
+///
+/// Built from defaults, then `~/.config/rustdocs-mcp/rustdocs-mcp.toml`, then
+/// `./rustdocs-mcp.toml`, then environment variables, then CLI flags; later
+/// sources override earlier ones key by key.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Forbid network calls: embeddings must already be cached and answers
+    /// are the raw matched documentation instead of an LLM summary
+    pub offline: bool,
+    /// Refuse to start when a startup check fails, instead of running
+    /// degraded (e.g. retrieval only when the provider can't be reached)
+    pub strict: bool,
+    pub embedding: EmbeddingSettings,
+    pub llm: LlmSettings,
+    pub http: HttpSettings,
+    pub openai: OpenAiSettings,
+    pub budget: BudgetSettings,
+    pub chunker: ChunkerSettings,
+    pub cache: CacheSettings,
+    pub retrieval: RetrievalSettings,
+    pub docs: DocsSettings,
+    pub memory: MemorySettings,
+    pub logging: LoggingSettings,
+    pub redaction: RedactionSettings,
+    pub tools: ToolsSettings,
+    pub telemetry: TelemetrySettings,
+    pub audit: AuditSettings,
+    /// Further projects served alongside the default one, selected with the
+    /// `project` argument of `query_rust_docs`
+    pub projects: Vec<ProjectSettings>,
+    /// Query settings for individual crates (`[crate.<name>]`), keyed by
+    /// crate name
+    #[serde(rename = "crate")]
+    pub crates: BTreeMap<String, CrateQuerySettings>,
+}
+
+/// Embedding provider settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmbeddingSettings {
+    /// Embedding provider: `openai` (and compatible APIs), or `mock` for
+    /// deterministic vectors computed locally
+    pub provider: String,
+    pub model: String,
+    /// Base URL of an OpenAI-compatible API (env: `OPENAI_API_BASE`)
+    pub api_base: Option<String>,
+    /// Extra HTTP headers sent with every embedding request, e.g. a proxy's
+    /// routing or authentication headers
+    pub headers: BTreeMap<String, String>,
+    /// Maximum number of embedding requests in flight at once
+    pub concurrency: usize,
+    /// Number of recent question embeddings kept in memory; 0 disables
+    pub question_cache_size: usize,
+    /// Marks chunks and questions for providers that embed documents and
+    /// queries differently; unset sends no input type
+    pub input_types: Option<EmbeddingInputTypes>,
+}
+
+/// The request field naming what a text is embedded for, and its values
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmbeddingInputTypes {
+    /// Request field carrying the input type, e.g. `input_type` for Cohere
+    /// and Voyage
+    pub field: String,
+    /// Sent with documentation chunks, e.g. `search_document` for Cohere
+    pub document: String,
+    /// Sent with questions, e.g. `search_query` for Cohere
+    pub query: String,
+}
+
+impl EmbeddingInputTypes {
+    /// Returns the value sent for texts embedded as `input_type`
+    pub fn value(&self, input_type: EmbeddingInputType) -> &str {
+        match input_type {
+            EmbeddingInputType::Document => &self.document,
+            EmbeddingInputType::Query => &self.query,
+        }
+    }
+}
+
+impl Default for EmbeddingSettings {
+    fn default() -> Self {
+        Self {
+            provider: "openai".to_string(),
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            api_base: None,
+            headers: BTreeMap::new(),
+            concurrency: embedding_cache_service::DEFAULT_CONCURRENCY,
+            question_cache_size: embedding_cache_service::DEFAULT_QUESTION_CACHE_SIZE,
+            input_types: None,
+        }
+    }
+}
+
+/// Chat model settings used for answer generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmSettings {
+    /// Chat provider: `openai` (and compatible APIs), or `mock` to echo the
+    /// question and matched documents
+    pub provider: String,
+    pub model: String,
+    /// Base URL of an OpenAI-compatible API (env: `OPENAI_API_BASE`)
+    pub api_base: Option<String>,
+    /// Extra HTTP headers sent with every chat request
+    pub headers: BTreeMap<String, String>,
+    /// Natural language answers are written in, e.g. `Japanese`, unless a
+    /// query asks for another; code and identifiers are never translated
+    pub answer_language: Option<String>,
+    /// Ask the model to rate its own answer and fold that into the reported
+    /// confidence
+    pub self_assess: bool,
+    /// List docs.rs links to the crate's items an answer mentions
+    pub docs_links: bool,
+    /// Answer by quoting the documentation's best matching sentences instead
+    /// of asking the chat model, unless a query asks otherwise
+    pub extractive: bool,
+}
+
+impl Default for LlmSettings {
+    fn default() -> Self {
+        Self {
+            provider: "openai".to_string(),
+            model: DEFAULT_LLM_MODEL.to_string(),
+            api_base: None,
+            headers: BTreeMap::new(),
+            answer_language: None,
+            self_assess: false,
+            docs_links: true,
+            extractive: false,
+        }
+    }
+}
+
+/// Settings applied whenever one crate is queried (`[crate.<name>]`), e.g. a
+/// larger model for crates whose docs are hard to answer from. How its docs
+/// are generated is set in [`CrateDocSettings`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrateQuerySettings {
+    /// Overrides `retrieval.top_k`
+    pub top_k: Option<usize>,
+    /// Overrides `llm.model`
+    pub llm_model: Option<String>,
+    /// Replaces the opening instructions of the system prompt; `{crate}`
+    /// stands for the crate's name. Instructions for versions, features,
+    /// the answer language and examples are still added.
+    pub prompt_template: Option<String>,
+}
+
+/// Content-defined chunking parameters, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChunkerSettings {
+    pub min_chunk_size: usize,
+    pub target_chunk_size: usize,
+    pub max_chunk_size: usize,
+    /// Leave generated boilerplate (trait implementation lists, blanket
+    /// impls, navigation text) out of embeddings
+    pub skip_boilerplate: bool,
+}
+
+impl Default for ChunkerSettings {
+    fn default() -> Self {
+        Self {
+            min_chunk_size: 1000,
+            target_chunk_size: 4000,
+            max_chunk_size: 8000,
+            skip_boilerplate: true,
+        }
+    }
+}
+
+/// Cache locations and limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheSettings {
+    /// Chunk embedding cache directory (env: `EMBEDDING_CACHE_DIR`; default:
+    /// `rustdocs-mcp-server/embeddings` under the XDG cache directory)
+    pub dir: Option<PathBuf>,
+    /// Global crate cache directory (default: XDG data dir)
+    pub global_dir: Option<PathBuf>,
+    /// Size limit for the chunk cache enforced by `cache prune`
+    pub max_size_mb: Option<u64>,
+    /// Save the indexed crates to the global cache when the server stops and
+    /// restore them when it starts, instead of re-indexing them on first use
+    pub snapshot: bool,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            global_dir: None,
+            max_size_mb: None,
+            snapshot: true,
+        }
+    }
+}
+
+/// Limits on document text and vectors kept in memory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemorySettings {
+    /// Crates whose document text exceeds this size keep it in a temporary
+    /// file and read back only the documents retrieval selects
+    pub max_document_mb: Option<u64>,
+    /// Crates whose embedding vectors exceed this size keep them in a
+    /// memory-mapped file, scored without loading them onto the heap
+    pub max_vector_mb: Option<u64>,
+    /// Directory for those files (default: the system temp directory)
+    pub spill_dir: Option<PathBuf>,
+}
+
+impl MemorySettings {
+    /// The document text budget in bytes, if one is set
+    pub fn max_document_bytes(&self) -> Option<u64> {
+        self.max_document_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// The vector budget in bytes, if one is set
+    pub fn max_vector_bytes(&self) -> Option<u64> {
+        self.max_vector_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// Directory spilled document text and mapped vectors are written to
+    pub fn spill_dir(&self) -> PathBuf {
+        self.spill_dir.clone().unwrap_or_else(env::temp_dir)
+    }
+}
+
+/// Retrieval tuning
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetrievalSettings {
+    /// Number of best-matching documents passed to the LLM
+    pub top_k: usize,
+    /// Documents scoring below this cosine similarity are ignored
+    pub min_score: f32,
+    /// Longest question accepted by `query_rust_docs`, in characters
+    pub max_question_chars: usize,
+    /// Added to the score of documents mentioning all of a question's Rust
+    /// identifiers (`CamelCase`, `snake_case`, `a::path`), less for common
+    /// ones or partial matches; 0 disables the boost
+    pub identifier_boost: f32,
+    /// Longest a matched document sent to the LLM may be, in tokens; longer
+    /// ones are cut down to their best matching chunk and the chunks around
+    /// it. 0 sends whole documents.
+    pub max_document_tokens: usize,
+    /// Most documents a query may ask for with its `top_k` argument
+    pub max_top_k: usize,
+    /// Most tokens of documentation sent to the LLM for one question, also
+    /// capping a query's `max_context_tokens` argument; 0 sets no limit
+    pub max_context_tokens: usize,
+    /// Crates with at least this many indexed documents are searched through
+    /// an HNSW graph stored in the global cache instead of scoring every
+    /// vector; 0 disables approximate search
+    pub ann_min_documents: usize,
+    /// Most HNSW graphs kept open, the least recently searched closed first
+    pub max_open_ann_indexes: usize,
+    /// Answer questions that only name an item from the item index, and
+    /// questions about the crate's version from Cargo's metadata, without
+    /// embedding them or calling the LLM
+    pub route_questions: bool,
+    /// Milliseconds a question may take before the best result available so
+    /// far is returned, flagged as partial: the matched documentation if
+    /// the LLM is still answering, keyword matches if the question isn't
+    /// embedded yet. 0 waits as long as it takes.
+    pub latency_budget_ms: u64,
+}
+
+impl Default for RetrievalSettings {
+    fn default() -> Self {
+        Self {
+            top_k: 1,
+            min_score: 0.0,
+            max_question_chars: 4000,
+            identifier_boost: 0.1,
+            max_document_tokens: 3000,
+            max_top_k: 10,
+            max_context_tokens: 16000,
+            ann_min_documents: 50_000,
+            max_open_ann_indexes: 4,
+            route_questions: false,
+            latency_budget_ms: 0,
+        }
+    }
+}
+
+/// Where generated documentation is read from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocsSettings {
+    /// Directory containing `cargo doc` output
+    pub dir: PathBuf,
+    /// Run `cargo doc` for crates whose documentation is missing
+    pub auto_generate: bool,
+    /// Project whose dependencies are documented when auto-generating
+    pub project_dir: PathBuf,
+    /// Seconds a single `cargo doc` run may take before it is killed
+    pub generate_timeout_secs: u64,
+    /// Include private items when generating docs (`--document-private-items`)
+    pub document_private_items: bool,
+    /// Extra rustdoc flags for generated docs, e.g. `["--cfg", "docsrs"]`
+    pub rustdoc_flags: Vec<String>,
+    /// Generation settings for individual crates, keyed by crate name
+    pub crates: BTreeMap<String, CrateDocSettings>,
+    /// Further doc directories searched, in order, for crates not in `dir`
+    pub roots: Vec<DocRootSettings>,
+    /// Seconds between checks of the doc roots for added, removed or
+    /// regenerated crates; 0 disables resource list change notifications
+    pub poll_interval_secs: u64,
+    /// Crates indexed in the background at startup; `"*"` means every
+    /// documented crate
+    pub preload: Vec<String>,
+    /// Pages larger than this that aren't embedded yet are embedded in a
+    /// background task after the rest of the crate is searchable, so a huge
+    /// page doesn't hold up the query; 0 embeds every page up front
+    pub large_document_kb: u64,
+    /// Seconds between checks of an indexed crate's pages for edits; edited
+    /// pages are indexed again on the next query. 0 checks on every query.
+    pub freshness_interval_secs: u64,
+    /// Index the doc comments of a crate's sources in cargo's registry when
+    /// its HTML docs can't be generated or downloaded
+    pub registry_sources: bool,
+}
+
+/// An additional documentation directory (`[[docs.roots]]`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DocRootSettings {
+    /// Short name; also namespaces the root's cached embeddings
+    pub name: String,
+    /// Directory containing rustdoc output
+    pub dir: PathBuf,
+}
+
+/// A project served by name (`[[projects]]`). Its relative doc paths resolve
+/// against `dir`, and its crates are indexed separately from other projects.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSettings {
+    /// Name passed as the `project` tool argument
+    pub name: String,
+    /// Project root, e.g. a repository checkout or a monorepo member
+    pub dir: PathBuf,
+}
+
+/// Doc generation settings for one crate (`[docs.crates.<name>]`). How it is
+/// queried is set in [`CrateQuerySettings`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrateDocSettings {
+    /// Features enabled when documenting the crate
+    pub features: Vec<String>,
+    /// Overrides `docs.document_private_items` for this crate
+    pub document_private_items: Option<bool>,
+    /// Rustdoc flags added after `docs.rustdoc_flags`
+    pub rustdoc_flags: Vec<String>,
+    /// Crates searched along with this one, e.g. `tokio-util` and
+    /// `tokio-stream` for `tokio`, whose items facade crates re-export or
+    /// split-crate ecosystems spread across companion crates
+    pub companions: Vec<String>,
+    /// Where the crate's documents come from: rustdoc's HTML pages or its
+    /// Rust sources
+    pub backend: DocBackend,
+    /// Sources read with the `source` backend, the directory holding the
+    /// crate's `Cargo.toml`; found with `cargo metadata` or in cargo's
+    /// registry if unset
+    pub source_dir: Option<PathBuf>,
+}
+
+/// How a crate's documents are produced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocBackend {
+    /// Pages of rustdoc output, generated or downloaded if missing
+    #[default]
+    Html,
+    /// One document per item parsed out of the crate's sources, with its
+    /// exact signature; needs no build. See `source_loader`.
+    Source,
+}
+
+impl Default for DocsSettings {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("./target/doc"),
+            auto_generate: false,
+            project_dir: PathBuf::from("."),
+            generate_timeout_secs: 900,
+            document_private_items: false,
+            rustdoc_flags: Vec::new(),
+            crates: BTreeMap::new(),
+            roots: Vec::new(),
+            poll_interval_secs: 5,
+            preload: Vec::new(),
+            large_document_kb: 1024,
+            freshness_interval_secs: 2,
+            registry_sources: true,
+        }
+    }
+}
+
+impl DocsSettings {
+    /// Size above which pages are embedded in the background, if any
+    pub fn large_document_bytes(&self) -> Option<usize> {
+        (self.large_document_kb > 0).then(|| self.large_document_kb as usize * 1024)
+    }
+
+    /// All doc roots in priority order, starting with `dir`
+    pub fn doc_roots(&self) -> Vec<DocRoot> {
+        std::iter::once(DocRoot::new(PROJECT_ROOT_NAME, self.dir.clone()))
+            .chain(
+                self.roots
+                    .iter()
+                    .map(|root| DocRoot::new(&root.name, root.dir.clone())),
+            )
+            .collect()
+    }
+
+    /// Returns a copy with the relative doc and project directories resolved
+    /// against `base` instead of the current directory
+    pub fn rebased(&self, base: &Path) -> Self {
+        let mut docs = self.clone();
+        docs.dir = rebase(base, &self.dir);
+        docs.project_dir = rebase(base, &self.project_dir);
+        for root in &mut docs.roots {
+            root.dir = rebase(base, &root.dir);
+        }
+        for settings in docs.crates.values_mut() {
+            if let Some(dir) = &settings.source_dir {
+                settings.source_dir = Some(rebase(base, dir));
+            }
+        }
+        docs
+    }
+
+    /// Returns the settings for `crate_name`, ignoring case and `-`/`_`
+    /// differences. Query settings are in [`Config::crate_settings`].
+    pub fn for_crate(&self, crate_name: &str) -> Option<&CrateDocSettings> {
+        settings_for_crate(&self.crates, crate_name)
+    }
+
+    /// Features to enable when documenting `crate_name`
+    pub fn crate_features(&self, crate_name: &str) -> Vec<String> {
+        self.for_crate(crate_name)
+            .map(|settings| settings.features.clone())
+            .unwrap_or_default()
+    }
+
+    /// How the documents of `crate_name` are produced
+    pub fn backend(&self, crate_name: &str) -> DocBackend {
+        self.for_crate(crate_name)
+            .map(|settings| settings.backend)
+            .unwrap_or_default()
+    }
+
+    /// Rustdoc options for `crate_name`, with per-crate settings applied
+    pub fn rustdoc_options(&self, crate_name: &str) -> RustdocOptions {
+        let settings = self.for_crate(crate_name);
+        let mut flags = self.rustdoc_flags.clone();
+        if let Some(settings) = settings {
+            flags.extend(settings.rustdoc_flags.iter().cloned());
+        }
+        RustdocOptions {
+            document_private_items: settings
+                .and_then(|settings| settings.document_private_items)
+                .unwrap_or(self.document_private_items),
+            flags,
+        }
+    }
+}
+
+/// Log verbosity and output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingSettings {
+    /// A level (`debug`) or `RUST_LOG`-style directives (env: `RUST_LOG`)
+    pub level: String,
+    /// Also append log output to this file
+    pub file: Option<PathBuf>,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            file: None,
+        }
+    }
+}
+
+/// HTTP settings of embedding and chat requests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpSettings {
+    /// Seconds a request may take in total before it fails
+    pub timeout_secs: u64,
+    /// Seconds to wait for the connection to the API (or proxy)
+    pub connect_timeout_secs: u64,
+    /// Seconds to wait for the response once connected, and then for its body
+    pub read_timeout_secs: u64,
+    /// Times a rate-limited or failed request is retried, with backoff
+    pub max_retries: u32,
+    /// Proxy for all provider requests, e.g. `http://proxy:3128`; without it
+    /// the `HTTPS_PROXY` environment variable is honored
+    pub proxy: Option<String>,
+    /// File provider requests are recorded to or replayed from, e.g. for
+    /// tests that run without an API key
+    pub cassette: Option<PathBuf>,
+    /// Whether the cassette is recorded or replayed
+    pub cassette_mode: CassetteMode,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 60,
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            max_retries: 3,
+            proxy: None,
+            cassette: None,
+            cassette_mode: CassetteMode::Replay,
+        }
+    }
+}
+
+/// OpenAI account settings: where the API key comes from and which
+/// organization and project requests are billed to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenAiSettings {
+    /// Sent as `OpenAI-Organization` (env: `OPENAI_ORG_ID`)
+    pub organization: Option<String>,
+    /// Sent as `OpenAI-Project` (env: `OPENAI_PROJECT_ID`)
+    pub project: Option<String>,
+    /// File holding the API key, read instead of `OPENAI_API_KEY`
+    pub api_key_file: Option<PathBuf>,
+    /// OS keychain entry holding the API key, read instead of `OPENAI_API_KEY`
+    pub api_key_keychain: Option<KeychainEntry>,
+}
+
+/// A generic password in the macOS keychain or the Secret Service (Linux)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeychainEntry {
+    pub service: String,
+    pub account: String,
+}
+
+impl OpenAiSettings {
+    /// Headers attributing requests to the organization and project, if set
+    pub fn headers(&self) -> BTreeMap<String, String> {
+        let mut headers = BTreeMap::new();
+        if let Some(organization) = &self.organization {
+            headers.insert("OpenAI-Organization".to_string(), organization.clone());
+        }
+        if let Some(project) = &self.project {
+            headers.insert("OpenAI-Project".to_string(), project.clone());
+        }
+        headers
+    }
+}
+
+/// Limit on provider usage per period. Once either limit is reached, new
+/// embeddings and chat completions are refused until the period ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetSettings {
+    /// Tokens sent to or generated by the providers per period; 0 sets no limit
+    pub max_tokens: u64,
+    /// Estimated spend in US dollars per period; 0 sets no limit
+    pub max_usd: f64,
+    /// Whether the budget starts over every day or lasts until the server stops
+    pub period: BudgetPeriod,
+    /// Prices used to estimate the spend, in US dollars per million tokens
+    pub embedding_usd_per_million: f64,
+    pub chat_input_usd_per_million: f64,
+    pub chat_output_usd_per_million: f64,
+}
+
+impl Default for BudgetSettings {
+    fn default() -> Self {
+        Self {
+            max_tokens: 0,
+            max_usd: 0.0,
+            period: BudgetPeriod::Daily,
+            embedding_usd_per_million: 0.02,
+            chat_input_usd_per_million: 0.15,
+            chat_output_usd_per_million: 0.60,
+        }
+    }
+}
+
+/// How long a provider budget lasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    /// Starts over at midnight UTC
+    Daily,
+    /// Lasts until the server restarts
+    Session,
+}
+
+/// Optional MCP tools, offered only when enabled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolsSettings {
+    /// `get_source`: source code of documented items, read from rustdoc's
+    /// `src/` pages
+    pub get_source: bool,
+    /// `debug_retrieval`: the ranked documents, scores and prompt behind an
+    /// answer, for finding out why it was bad
+    pub debug_retrieval: bool,
+}
+
+/// Opt-in statistics about queries and indexing, kept in a local SQLite
+/// database and summarized by `stats report`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetrySettings {
+    /// Record query latencies, cache hit rates and retrieval scores
+    pub enabled: bool,
+    /// Database file (default: `telemetry.sqlite3` in the XDG data directory)
+    pub path: Option<PathBuf>,
+}
+
+/// Opt-in JSONL log of every tool invocation and answered question, for
+/// analyzing what agents ask and where retrieval fails
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditSettings {
+    /// Append a line per tool call and answered question
+    pub enabled: bool,
+    /// Log file (default: `audit.jsonl` in the XDG data directory)
+    pub path: Option<PathBuf>,
+    /// Cut logged answers and tool results down to this many characters; 0
+    /// keeps them whole
+    pub max_answer_chars: usize,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            max_answer_chars: 2000,
+        }
+    }
+}
+
+/// Rules scrubbing document chunks and questions before they are sent to
+/// remote embedding and chat providers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionSettings {
+    /// Redact common API keys, tokens and private keys
+    pub secrets: bool,
+    /// Further patterns to redact, applied after the built-in ones
+    pub rules: Vec<RedactionRule>,
+}
+
+/// A regular expression whose matches are replaced before text leaves the machine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedactionRule {
+    pub pattern: String,
+    /// Replacement text, `[REDACTED]` by default; may refer to capture groups as `$1`
+    pub replacement: Option<String>,
+}
+
+impl Config {
+    /// Loads the configuration files and applies environment overrides.
+    /// An explicit `path` replaces the project-level `./rustdocs-mcp.toml`.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut merged = toml::Table::new();
+
+        let mut files: Vec<PathBuf> = user_config_path().into_iter().collect();
+        match path {
+            Some(path) => {
+                if !path.is_file() {
+                    return Err(ServerError::Config(format!(
+                        "Configuration file not found: {}",
+                        path.display()
+                    )));
+                }
+                files.push(path.to_path_buf());
+            }
+            None => files.push(PathBuf::from(CONFIG_FILE_NAME)),
+        }
+
+        for file in files.iter().filter(|file| file.is_file()) {
+            merge_tables(&mut merged, read_table(file)?);
+        }
+
+        let mut config: Config = merged.try_into().map_err(|e: toml::de::Error| {
+            ServerError::Config(format!("Invalid configuration: {}", e))
+        })?;
+        config.apply_env_overrides();
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Returns the project named `name`
+    pub fn project(&self, name: &str) -> Option<&ProjectSettings> {
+        self.projects.iter().find(|project| project.name == name)
+    }
+
+    /// Returns the `[crate.<name>]` settings for `crate_name`, ignoring case
+    /// and `-`/`_` differences. Doc generation settings are in
+    /// [`DocsSettings::for_crate`].
+    pub fn crate_settings(&self, crate_name: &str) -> Option<&CrateQuerySettings> {
+        settings_for_crate(&self.crates, crate_name)
+    }
+
+    /// Number of documents answers about `crate_name` are drawn from, unless
+    /// a query asks for another
+    pub fn top_k(&self, crate_name: &str) -> usize {
+        self.crate_settings(crate_name)
+            .and_then(|settings| settings.top_k)
+            .unwrap_or(self.retrieval.top_k)
+    }
+
+    /// Chat model answering questions about `crate_name`
+    pub fn llm_model(&self, crate_name: &str) -> &str {
+        self.crate_settings(crate_name)
+            .and_then(|settings| settings.llm_model.as_deref())
+            .unwrap_or(&self.llm.model)
+    }
+
+    /// Returns true if an OpenAI API key is needed: some provider isn't mocked
+    /// and the server isn't running offline
+    pub fn requires_api_key(&self) -> bool {
+        !self.offline
+            && (self.embedding.provider != MOCK_PROVIDER || self.llm.provider != MOCK_PROVIDER)
+    }
+
+    /// Returns the default configuration with environment overrides applied
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_env_overrides();
+        config
+    }
+
+    /// Applies `EMBEDDING_PROVIDER`, `EMBEDDING_MODEL`, `EMBEDDING_CACHE_DIR`,
+    /// `LLM_PROVIDER`, `LLM_MODEL`, `OPENAI_API_BASE`, `OPENAI_ORG_ID`,
+    /// `OPENAI_PROJECT_ID` and `RUST_LOG` if set
+    pub fn apply_env_overrides(&mut self) {
+        if let Ok(provider) = env::var("EMBEDDING_PROVIDER") {
+            self.embedding.provider = provider;
+        }
+        if let Ok(provider) = env::var("LLM_PROVIDER") {
+            self.llm.provider = provider;
+        }
+        if let Ok(model) = env::var("EMBEDDING_MODEL") {
+            self.embedding.model = model;
+        }
+        if let Ok(dir) = env::var("EMBEDDING_CACHE_DIR") {
+            self.cache.dir = Some(PathBuf::from(dir));
+        }
+        if let Ok(model) = env::var("LLM_MODEL") {
+            self.llm.model = model;
+        }
+        if let Ok(api_base) = env::var("OPENAI_API_BASE") {
+            self.embedding.api_base = Some(api_base.clone());
+            self.llm.api_base = Some(api_base);
+        }
+        if let Ok(organization) = env::var("OPENAI_ORG_ID") {
+            self.openai.organization = Some(organization);
+        }
+        if let Ok(project) = env::var("OPENAI_PROJECT_ID") {
+            self.openai.project = Some(project);
+        }
+        if let Ok(level) = env::var("RUST_LOG") {
+            self.logging.level = level;
+        }
+    }
+
+    /// Checks that the values are usable
+    pub fn validate(&self) -> Result<()> {
+        for (section, provider) in [
+            ("embedding", &self.embedding.provider),
+            ("llm", &self.llm.provider),
+        ] {
+            if provider != "openai" && provider != MOCK_PROVIDER {
+                return Err(ServerError::Config(format!(
+                    "Unsupported {} provider '{}'; use 'openai' or 'mock'",
+                    section, provider
+                )));
+            }
+        }
+
+        let chunker = &self.chunker;
+        if chunker.min_chunk_size == 0
+            || chunker.min_chunk_size > chunker.target_chunk_size
+            || chunker.target_chunk_size > chunker.max_chunk_size
+        {
+            return Err(ServerError::Config(format!(
+                "Chunk sizes must satisfy 0 < min ({}) <= target ({}) <= max ({})",
+                chunker.min_chunk_size, chunker.target_chunk_size, chunker.max_chunk_size
+            )));
+        }
+
+        openai_client::header_map(&self.embedding.headers)?;
+        openai_client::header_map(&self.llm.headers)?;
+        for (name, secs) in [
+            ("timeout_secs", self.http.timeout_secs),
+            ("connect_timeout_secs", self.http.connect_timeout_secs),
+            ("read_timeout_secs", self.http.read_timeout_secs),
+        ] {
+            if secs == 0 {
+                return Err(ServerError::Config(format!(
+                    "http.{} must be at least 1",
+                    name
+                )));
+            }
+        }
+        if let Some(proxy) = &self.http.proxy {
+            openai_client::parse_proxy(proxy)?;
+        }
+        if let Some(cassette) = &self.http.cassette
+            && self.http.cassette_mode == CassetteMode::Replay
+            && !cassette.is_file()
+        {
+            return Err(ServerError::Config(format!(
+                "http.cassette {} does not exist; record it with http.cassette_mode = \"record\"",
+                cassette.display()
+            )));
+        }
+        openai_client::header_map(&self.openai.headers())?;
+        if self.openai.api_key_file.is_some() && self.openai.api_key_keychain.is_some() {
+            return Err(ServerError::Config(
+                "Set only one of openai.api_key_file and openai.api_key_keychain".to_string(),
+            ));
+        }
+        for (name, value) in [
+            ("max_usd", self.budget.max_usd),
+            (
+                "embedding_usd_per_million",
+                self.budget.embedding_usd_per_million,
+            ),
+            (
+                "chat_input_usd_per_million",
+                self.budget.chat_input_usd_per_million,
+            ),
+            (
+                "chat_output_usd_per_million",
+                self.budget.chat_output_usd_per_million,
+            ),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(ServerError::Config(format!(
+                    "budget.{} must be a non-negative number, got {}",
+                    name, value
+                )));
+            }
+        }
+        if self.embedding.concurrency == 0 {
+            return Err(ServerError::Config(
+                "embedding.concurrency must be at least 1".to_string(),
+            ));
+        }
+        if let Some(input_types) = &self.embedding.input_types {
+            let field = input_types.field.as_str();
+            if field.is_empty() || field == "input" || field == "model" {
+                return Err(ServerError::Config(format!(
+                    "embedding.input_types.field must name a request field other than 'input' and 'model', got '{}'",
+                    field
+                )));
+            }
+        }
+        if self.retrieval.top_k == 0 {
+            return Err(ServerError::Config(
+                "retrieval.top_k must be at least 1".to_string(),
+            ));
+        }
+        if self.retrieval.max_top_k < self.retrieval.top_k {
+            return Err(ServerError::Config(format!(
+                "retrieval.max_top_k ({}) must be at least retrieval.top_k ({})",
+                self.retrieval.max_top_k, self.retrieval.top_k
+            )));
+        }
+        if self.retrieval.max_question_chars == 0 {
+            return Err(ServerError::Config(
+                "retrieval.max_question_chars must be at least 1".to_string(),
+            ));
+        }
+        if !(-1.0..=1.0).contains(&self.retrieval.min_score) {
+            return Err(ServerError::Config(format!(
+                "retrieval.min_score must be between -1 and 1, got {}",
+                self.retrieval.min_score
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.retrieval.identifier_boost) {
+            return Err(ServerError::Config(format!(
+                "retrieval.identifier_boost must be between 0 and 1, got {}",
+                self.retrieval.identifier_boost
+            )));
+        }
+        for (i, root) in self.docs.roots.iter().enumerate() {
+            if root.name.is_empty()
+                || root.name == PROJECT_ROOT_NAME
+                || self.docs.roots[..i].iter().any(|r| r.name == root.name)
+            {
+                return Err(ServerError::Config(format!(
+                    "docs.roots names must be unique, non-empty and not '{}', got '{}'",
+                    PROJECT_ROOT_NAME, root.name
+                )));
+            }
+        }
+        for (name, settings) in &self.docs.crates {
+            if let Some(companion) = settings.companions.iter().find(|companion| {
+                companion.is_empty()
+                    || normalize_crate_name(companion) == normalize_crate_name(name)
+            }) {
+                return Err(ServerError::Config(format!(
+                    "docs.crates.{}.companions must name other crates, got '{}'",
+                    name, companion
+                )));
+            }
+        }
+        for (name, settings) in &self.crates {
+            if let Some(top_k) = settings.top_k
+                && (top_k == 0 || top_k > self.retrieval.max_top_k)
+            {
+                return Err(ServerError::Config(format!(
+                    "crate.{}.top_k must be between 1 and retrieval.max_top_k ({}), got {}",
+                    name, self.retrieval.max_top_k, top_k
+                )));
+            }
+            if settings
+                .llm_model
+                .as_ref()
+                .is_some_and(|model| model.trim().is_empty())
+            {
+                return Err(ServerError::Config(format!(
+                    "crate.{}.llm_model must not be empty",
+                    name
+                )));
+            }
+            if settings
+                .prompt_template
+                .as_ref()
+                .is_some_and(|template| template.trim().is_empty())
+            {
+                return Err(ServerError::Config(format!(
+                    "crate.{}.prompt_template must not be empty",
+                    name
+                )));
+            }
+        }
+        for (i, project) in self.projects.iter().enumerate() {
+            if project.name.is_empty() || self.projects[..i].iter().any(|p| p.name == project.name)
+            {
+                return Err(ServerError::Config(format!(
+                    "projects names must be unique and non-empty, got '{}'",
+                    project.name
+                )));
+            }
+        }
+        Redactor::new(&self.redaction)?;
+        if self.docs.generate_timeout_secs == 0 {
+            return Err(ServerError::Config(
+                "docs.generate_timeout_secs must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the configured chunk cache directory or the default one
+    pub fn chunk_cache_dir(&self) -> Result<PathBuf> {
+        match &self.cache.dir {
+            Some(dir) => Ok(dir.clone()),
+            None => embedding_cache_service::default_cache_dir(),
+        }
+    }
+
+    /// Returns the configured telemetry database or the default one
+    pub fn telemetry_path(&self) -> Result<PathBuf> {
+        match &self.telemetry.path {
+            Some(path) => Ok(path.clone()),
+            None => telemetry::default_path(),
+        }
+    }
+
+    /// Returns the configured audit log or the default one
+    pub fn audit_path(&self) -> Result<PathBuf> {
+        match &self.audit.path {
+            Some(path) => Ok(path.clone()),
+            None => audit_log::default_path(),
+        }
+    }
+
+    /// Opens the global crate cache at the configured or default location
+    pub fn global_cache(&self) -> Result<GlobalCache> {
+        match &self.cache.global_dir {
+            Some(dir) => GlobalCache::with_root(dir.clone()),
+            None => GlobalCache::new(),
+        }
+    }
+
+    /// Creates a chunker with the configured chunk sizes and filtering
+    pub fn chunker(&self) -> DocumentChunker {
+        DocumentChunker::with_params(
+            self.chunker.min_chunk_size,
+            self.chunker.target_chunk_size,
+            self.chunker.max_chunk_size,
+        )
+        .skipping_boilerplate(self.chunker.skip_boilerplate)
+    }
+
+    /// Renders the configuration as TOML
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self)
+            .map_err(|e| ServerError::Config(format!("Failed to render configuration: {}", e)))
+    }
+}
+
+/// Location of the per-user configuration file
+pub fn user_config_path() -> Option<PathBuf> {
+    ProjectDirs::new()
+        .ok()
+        .map(|dirs| dirs.config_dir().join(CONFIG_FILE_NAME))
+}
+
+/// Joins a relative `path` onto `base`, dropping a leading `./`
+fn rebase(base: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let relative = path.strip_prefix(".").unwrap_or(path);
+    if relative.as_os_str().is_empty() {
+        base.to_path_buf()
+    } else {
+        base.join(relative)
+    }
+}
+
+/// Looks up `crate_name` in per-crate settings keyed by crate name, ignoring
+/// case and `-`/`_` differences
+fn settings_for_crate<'a, T>(settings: &'a BTreeMap<String, T>, crate_name: &str) -> Option<&'a T> {
+    let normalized = normalize_crate_name(crate_name);
+    settings
+        .iter()
+        .find(|(name, _)| normalize_crate_name(name) == normalized)
+        .map(|(_, settings)| settings)
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let content = fs::read_to_string(path)?;
+    content
+        .parse::<toml::Table>()
+        .map_err(|e| ServerError::Config(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+/// Recursively merges `overlay` into `base`, with `overlay` winning on conflicts
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::crate_spec::{canonical_crate_name, normalize_crate_name};
+use crate::doc_loader::DocLoaderError;
+use crate::global_cache::{CacheKey, LOCAL_VERSION};
+
+/// Name of the doc root configured with `docs.dir`
+pub const PROJECT_ROOT_NAME: &str = "project";
+
+/// A directory of rustdoc output, e.g. a workspace's `target/doc`, a shared
+/// team directory or the standard library docs installed by rustup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocRoot {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl DocRoot {
+    /// Creates a root named `name` for the doc directory `dir`
+    pub fn new(name: &str, dir: PathBuf) -> Self {
+        Self {
+            name: name.to_string(),
+            dir,
+        }
+    }
+
+    /// Key under which embeddings of a crate documented in this root are
+    /// cached. Each root gets its own namespace so the same crate name in two
+    /// roots never shares an index.
+    pub fn cache_key(&self, crate_name: &str) -> CacheKey {
+        let mut key = CacheKey::local(crate_name);
+        if self.name != PROJECT_ROOT_NAME {
+            key.version = format!("{}-{}", LOCAL_VERSION, self.name);
+        }
+        key
+    }
+}
+
+/// A crate whose generated documentation was found in a doc directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredCrate {
+    /// Name of the crate's directory in the doc output (dashes become underscores)
+    pub name: String,
+    /// Doc directory the crate was found in
+    pub doc_dir: PathBuf,
+    /// Version of the documented crate, if known
+    pub version: Option<String>,
+    /// When the crate's documentation was last generated
+    pub generated_at: Option<SystemTime>,
+}
+
+impl DiscoveredCrate {
+    /// Returns true if the docs were generated before `changed_at`, e.g. the
+    /// last modification of the project's `Cargo.lock`
+    pub fn is_stale(&self, changed_at: Option<SystemTime>) -> bool {
+        match (self.generated_at, changed_at) {
+            (Some(generated_at), Some(changed_at)) => generated_at < changed_at,
+            _ => false,
+        }
+    }
+}
+
+/// Lists the crates documented in `doc_dir`: subdirectories containing an `index.html`.
+/// A missing or unreadable directory yields an empty list.
+pub fn discover_available_crates(doc_dir: &Path) -> Vec<DiscoveredCrate> {
+    let Ok(entries) = fs::read_dir(doc_dir) else {
+        return Vec::new();
+    };
+
+    let mut crates: Vec<DiscoveredCrate> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+        .filter_map(|entry| {
+            let index = entry.path().join("index.html");
+            let metadata = fs::metadata(&index).ok()?;
+            Some(DiscoveredCrate {
+                name: entry.file_name().to_str()?.to_string(),
+                doc_dir: doc_dir.to_path_buf(),
+                version: None,
+                generated_at: metadata.modified().ok(),
+            })
+        })
+        .collect();
+    crates.sort_by(|a, b| a.name.cmp(&b.name));
+    crates
+}
+
+/// Lists the crates documented in `roots`, searched in priority order: a crate
+/// found in several roots is reported from the first one only
+pub fn discover_in_roots(roots: &[DocRoot]) -> Vec<DiscoveredCrate> {
+    let mut crates: Vec<DiscoveredCrate> = Vec::new();
+    for root in roots {
+        for discovered in discover_available_crates(&root.dir) {
+            if !crates.iter().any(|c| c.name == discovered.name) {
+                crates.push(discovered);
+            }
+        }
+    }
+    crates
+}
+
+/// Fills in each crate's version from `versions` (as returned by [`package_versions`])
+pub fn apply_versions(crates: &mut [DiscoveredCrate], versions: &HashMap<String, String>) {
+    for discovered in crates.iter_mut().filter(|c| c.version.is_none()) {
+        discovered.version = versions.get(&discovered.name).cloned();
+    }
+}
+
+/// Last modification time of the project's `Cargo.lock`, if it has one
+pub fn lockfile_modified(project_dir: &Path) -> Option<SystemTime> {
+    fs::metadata(project_dir.join("Cargo.lock"))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// Versions of `crate_name` that the project's `Cargo.lock` resolves, empty
+/// if it has no lockfile or doesn't depend on the crate
+pub fn locked_versions(project_dir: &Path, crate_name: &str) -> Vec<String> {
+    #[derive(Deserialize)]
+    struct Lockfile {
+        #[serde(default)]
+        package: Vec<LockedPackage>,
+    }
+
+    #[derive(Deserialize)]
+    struct LockedPackage {
+        name: String,
+        version: String,
+    }
+
+    let Ok(text) = fs::read_to_string(project_dir.join("Cargo.lock")) else {
+        return Vec::new();
+    };
+    let Ok(lockfile) = toml::from_str::<Lockfile>(&text) else {
+        return Vec::new();
+    };
+    let wanted = normalize_crate_name(crate_name);
+    let mut versions: Vec<String> = lockfile
+        .package
+        .into_iter()
+        .filter(|package| normalize_crate_name(&package.name) == wanted)
+        .map(|package| package.version)
+        .collect();
+    versions.sort_by(|a, b| compare_versions(a, b));
+    versions
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<MetadataPackage>,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    name: String,
+    version: String,
+    manifest_path: PathBuf,
+}
+
+/// Runs `cargo metadata` for the project and maps every package in its
+/// dependency graph (keyed by doc directory name) to its version. Packages
+/// resolved at several versions map to all of them, comma-separated.
+pub async fn package_versions(
+    project_dir: &Path,
+) -> Result<HashMap<String, String>, DocLoaderError> {
+    Ok(versions_by_doc_name(
+        cargo_metadata(project_dir).await?.packages,
+    ))
+}
+
+/// Returns the `Cargo.toml` of the package `crate_name` in the project's
+/// dependency graph: its registry source for dependencies. If several
+/// versions are resolved, the newest one's is returned.
+pub async fn package_manifest(
+    project_dir: &Path,
+    crate_name: &str,
+) -> Result<Option<PathBuf>, DocLoaderError> {
+    let wanted = normalize_crate_name(crate_name);
+    let newest = cargo_metadata(project_dir)
+        .await?
+        .packages
+        .into_iter()
+        .filter(|package| normalize_crate_name(&package.name) == wanted)
+        .max_by(|a, b| compare_versions(&a.version, &b.version));
+    Ok(newest.map(|package| package.manifest_path))
+}
+
+async fn cargo_metadata(project_dir: &Path) -> Result<Metadata, DocLoaderError> {
+    // Never touch the network just to describe packages
+    let output = Command::new("cargo")
+        .arg("metadata")
+        .arg("--format-version")
+        .arg("1")
+        .arg("--offline")
+        .arg("--manifest-path")
+        .arg(project_dir.join("Cargo.toml"))
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(DocLoaderError::CargoDoc(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| DocLoaderError::CargoDoc(format!("Invalid cargo metadata output: {}", e)))
+}
+
+/// Orders versions by their numeric release components, e.g. `1.10.0` after `1.9.2`
+pub(crate) fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parts(a).cmp(&parts(b))
+}
+
+fn versions_by_doc_name(packages: Vec<MetadataPackage>) -> HashMap<String, String> {
+    let mut grouped: HashMap<String, Vec<String>> = HashMap::new();
+    for package in packages {
+        grouped
+            .entry(canonical_crate_name(&package.name))
+            .or_default()
+            .push(package.version);
+    }
+    grouped
+        .into_iter()
+        .map(|(name, mut versions)| {
+            versions.sort();
+            versions.dedup();
+            (name, versions.join(", "))
+        })
+        .collect()
+}
+
+/// Number of single-character edits needed to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Available crate names sorted by similarity to `query`, closest first
+pub fn closest_crate_names(query: &str, available: &[String], limit: usize) -> Vec<String> {
+    let query = normalize_crate_name(query);
+    let mut scored: Vec<(usize, &String)> = available
+        .iter()
+        .map(|name| (edit_distance(&query, &normalize_crate_name(name)), name))
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, name)| name.clone())
+        .collect()
+}
+
+/// Available crate names that `query` plausibly refers to, closest first.
+///
+/// Names equal up to case and `-`/`_` come first; otherwise names within a
+/// small edit distance (about one typo per four characters) match.
+pub fn find_matching_crate_names(query: &str, available: &[String]) -> Vec<String> {
+    let normalized = normalize_crate_name(query);
+    let max_distance = (normalized.chars().count() / 4).max(1);
+    let mut scored: Vec<(usize, &String)> = available
+        .iter()
+        .map(|name| {
+            (
+                edit_distance(&normalized, &normalize_crate_name(name)),
+                name,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    scored.sort();
+
+    // An exact match makes looser ones irrelevant
+    if scored.first().is_some_and(|(distance, _)| *distance == 0) {
+        scored.retain(|(distance, _)| *distance == 0);
+    }
+    scored.into_iter().map(|(_, name)| name.clone()).collect()
+}
@@ -0,0 +1,241 @@
+//! Lists a crate's Cargo features with their documentation and the items
+//! each one gates, so "which feature do I need for X?" has an exact answer.
+//!
+//! Features and their doc comments come from the crate's `Cargo.toml`
+//! (comments directly above a feature, including `document-features` style
+//! `##` lines). Gated items come from the "Available on crate feature ...
+//! only" notes rustdoc renders on item pages.
+
+use scraper::{Html, Selector};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::doc_loader::{self, DocLoaderError};
+
+/// A feature declared in `[features]`, or implied by an optional dependency
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrateFeature {
+    pub name: String,
+    /// Features and dependencies it enables
+    pub enables: Vec<String>,
+    /// Comments directly above it in `Cargo.toml`
+    pub doc: Option<String>,
+    /// Paths of the items only available with it
+    pub gated_items: Vec<String>,
+}
+
+/// Reads the features of the manifest at `path`. Registry sources are read
+/// from `Cargo.toml.orig`, since publishing strips the comments documenting
+/// features from `Cargo.toml`.
+pub fn read_manifest_features(path: &Path) -> Result<Vec<CrateFeature>, DocLoaderError> {
+    let original = path.with_extension("toml.orig");
+    let path = if original.is_file() { &original } else { path };
+    parse_manifest_features(&fs::read_to_string(path)?)
+        .map_err(|e| DocLoaderError::Manifest(format!("{}: {}", path.display(), e)))
+}
+
+/// Parses the features of a `Cargo.toml`, in declaration order. Optional
+/// dependencies not referred to as `dep:name` are implicit features.
+pub fn parse_manifest_features(manifest: &str) -> Result<Vec<CrateFeature>, toml::de::Error> {
+    let table: toml::Table = manifest.parse()?;
+    let declared = table.get("features").and_then(toml::Value::as_table);
+
+    let docs = feature_comments(manifest);
+    let mut features: Vec<CrateFeature> = docs
+        .iter()
+        .filter_map(|(name, doc)| {
+            let enables = declared?.get(name)?.as_array()?;
+            Some(CrateFeature {
+                name: name.clone(),
+                enables: enables
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect(),
+                doc: doc.clone(),
+                gated_items: Vec::new(),
+            })
+        })
+        .collect();
+    // Keys the line scan missed, e.g. from inline tables
+    for (name, enables) in declared.into_iter().flatten() {
+        if !features.iter().any(|feature| &feature.name == name) {
+            features.push(CrateFeature {
+                name: name.clone(),
+                enables: enables
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect(),
+                ..Default::default()
+            });
+        }
+    }
+
+    let explicit_deps: Vec<&str> = features
+        .iter()
+        .flat_map(|feature| &feature.enables)
+        .filter_map(|enabled| enabled.strip_prefix("dep:"))
+        .collect();
+    let mut implicit = Vec::new();
+    for section in ["dependencies", "build-dependencies"] {
+        let Some(deps) = table.get(section).and_then(toml::Value::as_table) else {
+            continue;
+        };
+        for (name, dep) in deps {
+            let optional = dep
+                .get("optional")
+                .and_then(toml::Value::as_bool)
+                .unwrap_or(false);
+            if optional
+                && !explicit_deps.contains(&name.as_str())
+                && !features.iter().any(|feature| &feature.name == name)
+            {
+                implicit.push(CrateFeature {
+                    name: name.clone(),
+                    enables: vec![format!("dep:{}", name)],
+                    doc: Some(format!("Optional dependency `{}`", name)),
+                    gated_items: Vec::new(),
+                });
+            }
+        }
+    }
+    features.extend(implicit);
+    Ok(features)
+}
+
+/// Feature names in `[features]` in order, each with the comment lines right above it
+fn feature_comments(manifest: &str) -> Vec<(String, Option<String>)> {
+    let mut in_features = false;
+    let mut comment: Vec<&str> = Vec::new();
+    let mut features = Vec::new();
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_features = line.trim_end_matches(|c: char| c != ']') == "[features]";
+            comment.clear();
+        } else if !in_features {
+            continue;
+        } else if let Some(text) = line.strip_prefix('#') {
+            // `#!` lines are document-features section headings, not feature docs
+            if !text.starts_with('!') {
+                comment.push(text.trim_start_matches('#').trim());
+            }
+        } else if line.is_empty() {
+            comment.clear();
+        } else if let Some((key, _)) = line.split_once('=') {
+            let name = key.trim().trim_matches('"').to_string();
+            let doc = comment.join("\n").trim().to_string();
+            features.push((name, (!doc.is_empty()).then_some(doc)));
+            comment.clear();
+        } else {
+            // Continuation of a multi-line array
+            comment.clear();
+        }
+    }
+    features
+}
+
+/// Maps each feature to the items documented in `crate_doc_dir` (the
+/// crate's directory of rustdoc output) that require it, sorted by path
+pub fn gated_items(
+    crate_doc_dir: &Path,
+    crate_name: &str,
+) -> Result<BTreeMap<String, Vec<String>>, DocLoaderError> {
+    // Only the note of the page's own item, not of its methods and impls
+    let note_selector = Selector::parse("#main-content > .item-info .stab.portability")
+        .map_err(|e| DocLoaderError::Selector(e.to_string()))?;
+    let code_selector =
+        Selector::parse("code").map_err(|e| DocLoaderError::Selector(e.to_string()))?;
+
+    let mut gated: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in WalkDir::new(crate_doc_dir) {
+        let entry = entry?;
+        let relative = entry
+            .path()
+            .strip_prefix(crate_doc_dir)
+            .unwrap_or(entry.path());
+        let Some(item) = doc_loader::item_path(crate_name, relative) else {
+            continue;
+        };
+        let html = fs::read_to_string(entry.path())?;
+        // Cheap check before parsing: most pages aren't gated
+        if !html.contains("crate feature") {
+            continue;
+        }
+        let page = Html::parse_document(&html);
+        let Some(note) = page.select(&note_selector).next() else {
+            continue;
+        };
+        for code in note.select(&code_selector) {
+            let feature: String = code.text().collect();
+            let items = gated.entry(feature).or_default();
+            if !items.contains(&item) {
+                items.push(item.clone());
+            }
+        }
+    }
+    for items in gated.values_mut() {
+        items.sort();
+    }
+    Ok(gated)
+}
+
+/// Adds gated items to their features; features only known from the docs,
+/// e.g. because the manifest couldn't be read, are appended
+pub fn attach_gated_items(features: &mut Vec<CrateFeature>, gated: BTreeMap<String, Vec<String>>) {
+    for (name, items) in gated {
+        match features.iter_mut().find(|feature| feature.name == name) {
+            Some(feature) => feature.gated_items = items,
+            None => features.push(CrateFeature {
+                name,
+                gated_items: items,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// Renders the features as Markdown, listing at most `max_items` gated
+/// items per feature
+pub fn format_features(crate_name: &str, features: &[CrateFeature], max_items: usize) -> String {
+    if features.is_empty() {
+        return format!("Crate '{}' has no Cargo features.", crate_name);
+    }
+    let mut text = format!("Features of crate '{}':\n", crate_name);
+    for feature in features {
+        text.push_str(&format!("\n## `{}`\n", feature.name));
+        if let Some(doc) = &feature.doc {
+            text.push_str(&format!("\n{}\n", doc));
+        }
+        if !feature.enables.is_empty() {
+            text.push_str(&format!("\nEnables: {}\n", feature.enables.join(", ")));
+        }
+        if !feature.gated_items.is_empty() {
+            text.push_str("\nRequired by:\n");
+            for item in feature.gated_items.iter().take(max_items) {
+                text.push_str(&format!("- `{}`\n", item));
+            }
+            let more = feature.gated_items.len().saturating_sub(max_items);
+            if more > 0 {
+                text.push_str(&format!("- and {} more\n", more));
+            }
+        }
+    }
+    text
+}
+
+/// Describes the features docs were generated with, as recorded by
+/// [`recorded_features`](crate::doc_generator::recorded_features), e.g.
+/// "the default features plus `rt`, `macros`"
+pub fn describe_enabled(features: &[String]) -> String {
+    if features.is_empty() {
+        return "the default features".to_string();
+    }
+    let features: Vec<String> = features
+        .iter()
+        .map(|feature| format!("`{}`", feature))
+        .collect();
+    format!("the default features plus {}", features.join(", "))
+}
@@ -0,0 +1,258 @@
+//! Reads a crate's documentation straight from its published sources.
+//!
+//! Cargo unpacks the `.crate` archive of every dependency it builds into
+//! `~/.cargo/registry/src/<index>/<name>-<version>/`. When no HTML docs can
+//! be generated or downloaded, the doc comments of the `.rs` files there are
+//! parsed out with `syn` and indexed instead, one document per source file.
+//! That needs no rustdoc run, no build of the crate and no network.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use syn::{Attribute, Expr, ExprLit, ImplItem, Item, Lit, Meta, TraitItem, Visibility};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::crate_discovery;
+use crate::crate_spec::{canonical_crate_name, normalize_crate_name};
+use crate::doc_loader::{self, DocLoaderError, Document};
+use crate::platform_paths;
+
+/// Cargo's home directory: `CARGO_HOME`, else `~/.cargo`
+pub fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+}
+
+/// Finds the unpacked sources of the newest release of `crate_name`
+/// matching `version` (e.g. `1.0` or `=1.0.200`, any release if `None`) in
+/// the registry source directories under `cargo_home`
+pub fn find_registry_sources(
+    cargo_home: &Path,
+    crate_name: &str,
+    version: Option<&str>,
+) -> Option<PathBuf> {
+    let crate_name = normalize_crate_name(crate_name);
+    // `<name>-<version>`, where names may contain dashes and digits too
+    let dir_name = Regex::new(r"^(.+?)-(\d+\.\d+\.\d+\S*)$").expect("valid regex");
+    let indexes = fs::read_dir(cargo_home.join("registry").join("src")).ok()?;
+    indexes
+        .filter_map(|index| index.ok())
+        .filter_map(|index| fs::read_dir(index.path()).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let captures = dir_name.captures(&name)?;
+            let release = captures[2].to_string();
+            let matches = normalize_crate_name(&captures[1]) == crate_name
+                && version.is_none_or(|version| doc_loader::version_matches(&release, version));
+            (matches && entry.path().is_dir()).then(|| (release, entry.path()))
+        })
+        .max_by(|(a, _), (b, _)| crate_discovery::compare_versions(a, b))
+        .map(|(_, dir)| dir)
+}
+
+/// Reads the doc comments of the sources under `crate_dir/src`. Each source
+/// file with any becomes a document, its path relative to `crate_dir`, of
+/// the module's own docs followed by those of its public items. Files that
+/// don't parse are skipped.
+pub fn load_documents_from_sources(
+    crate_dir: &Path,
+    crate_name: &str,
+) -> Result<Vec<Document>, DocLoaderError> {
+    let src_dir = crate_dir.join("src");
+    if !src_dir.is_dir() {
+        return Err(DocLoaderError::DocNotFound(format!(
+            "No sources of {} in {}",
+            crate_name,
+            crate_dir.display()
+        )));
+    }
+
+    let mut documents = Vec::new();
+    for entry in WalkDir::new(&src_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let relative = path.strip_prefix(&src_dir).unwrap_or(path);
+        let Some(module) = module_path(crate_name, relative) else {
+            continue;
+        };
+        let file = match syn::parse_file(&fs::read_to_string(path)?) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Skipping {}, it doesn't parse: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let mut sections = Vec::new();
+        push_section(&mut sections, "Module", &module, &file.attrs);
+        item_sections(&file.items, &module, &mut sections);
+        if sections.is_empty() {
+            continue;
+        }
+        documents.push(Document {
+            path: platform_paths::portable_path(path.strip_prefix(crate_dir).unwrap_or(path)),
+            content: sections.join("\n\n"),
+        });
+    }
+
+    if documents.is_empty() {
+        return Err(DocLoaderError::DocNotFound(format!(
+            "No doc comments in the sources of {} in {}",
+            crate_name,
+            crate_dir.display()
+        )));
+    }
+    Ok(documents)
+}
+
+/// Module path of a source file given its path relative to `src/`, e.g.
+/// `my_crate::de` for `de.rs` or `de/mod.rs`; `None` for binaries
+pub(crate) fn module_path(crate_name: &str, relative: &Path) -> Option<String> {
+    let mut segments = vec![canonical_crate_name(crate_name)];
+    let components: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if components
+        .first()
+        .is_some_and(|first| first == "bin" || first == "main")
+    {
+        return None;
+    }
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        if last && (component == "lib" || component == "mod") {
+            break;
+        }
+        segments.push(component.clone());
+    }
+    Some(segments.join("::"))
+}
+
+/// Joins the `#[doc = "..."]` attributes `///` and `//!` comments turn into,
+/// dropping the space after the comment marker
+pub(crate) fn doc_text(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(doc) => match &doc.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(text),
+                    ..
+                }) => Some(text.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|text| {
+            text.lines()
+                .map(|line| line.strip_prefix(' ').unwrap_or(line).to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Adds a section for each documented public item of `items` in `module`,
+/// including the members of traits and impls and of inline modules
+fn item_sections(items: &[Item], module: &str, sections: &mut Vec<String>) {
+    for item in items {
+        let mut add = |kind: &str, name: &dyn std::fmt::Display, attrs: &[Attribute]| {
+            push_section(sections, kind, &format!("{}::{}", module, name), attrs)
+        };
+        match item {
+            Item::Fn(item) if is_public(&item.vis) => add("Function", &item.sig.ident, &item.attrs),
+            Item::Struct(item) if is_public(&item.vis) => add("Struct", &item.ident, &item.attrs),
+            Item::Enum(item) if is_public(&item.vis) => add("Enum", &item.ident, &item.attrs),
+            Item::Union(item) if is_public(&item.vis) => add("Union", &item.ident, &item.attrs),
+            Item::Type(item) if is_public(&item.vis) => add("Type", &item.ident, &item.attrs),
+            Item::Const(item) if is_public(&item.vis) => add("Constant", &item.ident, &item.attrs),
+            Item::Static(item) if is_public(&item.vis) => add("Static", &item.ident, &item.attrs),
+            Item::Macro(item)
+                if item.mac.path.is_ident("macro_rules")
+                    && item
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path().is_ident("macro_export")) =>
+            {
+                // Exported macros live at the crate root
+                if let Some(ident) = &item.ident {
+                    let root = module.split("::").next().unwrap_or(module);
+                    push_section(
+                        sections,
+                        "Macro",
+                        &format!("{}::{}", root, ident),
+                        &item.attrs,
+                    );
+                }
+            }
+            Item::Trait(item) if is_public(&item.vis) => {
+                add("Trait", &item.ident, &item.attrs);
+                for member in &item.items {
+                    let (name, attrs) = match member {
+                        TraitItem::Fn(member) => (&member.sig.ident, &member.attrs),
+                        TraitItem::Type(member) => (&member.ident, &member.attrs),
+                        TraitItem::Const(member) => (&member.ident, &member.attrs),
+                        _ => continue,
+                    };
+                    add("Trait item", &format!("{}::{}", item.ident, name), attrs);
+                }
+            }
+            Item::Impl(item) => {
+                let syn::Type::Path(self_ty) = item.self_ty.as_ref() else {
+                    continue;
+                };
+                let Some(type_name) = self_ty.path.segments.last().map(|s| &s.ident) else {
+                    continue;
+                };
+                // Members of trait impls are public wherever the trait is
+                let in_trait = item.trait_.is_some();
+                for member in &item.items {
+                    let (kind, name, attrs) = match member {
+                        ImplItem::Fn(member) if in_trait || is_public(&member.vis) => {
+                            ("Method", &member.sig.ident, &member.attrs)
+                        }
+                        ImplItem::Const(member) if in_trait || is_public(&member.vis) => {
+                            ("Associated constant", &member.ident, &member.attrs)
+                        }
+                        ImplItem::Type(member) if in_trait || is_public(&member.vis) => {
+                            ("Associated type", &member.ident, &member.attrs)
+                        }
+                        _ => continue,
+                    };
+                    add(kind, &format!("{}::{}", type_name, name), attrs);
+                }
+            }
+            Item::Mod(item) if is_public(&item.vis) => {
+                add("Module", &item.ident, &item.attrs);
+                if let Some((_, items)) = &item.content {
+                    item_sections(items, &format!("{}::{}", module, item.ident), sections);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Adds a section titled by the item's kind and path if it has docs
+fn push_section(sections: &mut Vec<String>, kind: &str, path: &str, attrs: &[Attribute]) {
+    let docs = doc_text(attrs);
+    if !docs.is_empty() {
+        sections.push(format!("{} `{}`\n\n{}", kind, path, docs));
+    }
+}
+
+fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
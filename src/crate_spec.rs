@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::error::{Result, ServerError};
+use crate::global_cache::CacheKey;
+
+/// Longest crate name crates.io accepts
+pub const MAX_CRATE_NAME_LEN: usize = 64;
+
+/// The canonical form of a crate name, with dashes as underscores: how
+/// rustdoc names the crate's doc directory and how its indexes, caches and
+/// resources are keyed, whichever form a request uses
+pub fn canonical_crate_name(name: &str) -> String {
+    name.trim().replace('-', "_")
+}
+
+/// A crate name for comparisons: canonical and lowercase, as crates.io
+/// allows no two names differing only in case or `-`/`_`
+pub fn normalize_crate_name(name: &str) -> String {
+    canonical_crate_name(name).to_lowercase()
+}
+
+/// A crate given on the command line as `name` or `name@version_req`,
+/// together with the features requested with `-F`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateSpec {
+    pub name: String,
+    pub version_req: Option<String>,
+    pub features: Vec<String>,
+}
+
+impl CrateSpec {
+    /// Parses a `name[@version_req]` specification
+    pub fn parse(spec: &str, features: Vec<String>) -> Result<Self> {
+        let (name, version_req) = match spec.split_once('@') {
+            Some((name, version)) => (name.trim(), Some(version.trim())),
+            None => (spec.trim(), None),
+        };
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(ServerError::Config(format!(
+                "Invalid crate name in '{}': use letters, digits, '-' or '_'",
+                spec
+            )));
+        }
+        if name.len() > MAX_CRATE_NAME_LEN {
+            return Err(ServerError::Config(format!(
+                "Crate name in '{}' is longer than {} characters",
+                spec, MAX_CRATE_NAME_LEN
+            )));
+        }
+        if version_req.is_some_and(str::is_empty) {
+            return Err(ServerError::Config(format!(
+                "Missing version requirement after '@' in '{}'",
+                spec
+            )));
+        }
+        // Version requirements end up in cache and workspace paths, so only
+        // allow what semver requirements are made of
+        if let Some(version_req) = version_req
+            && !version_req
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || " .*^~=<>,-+".contains(c))
+        {
+            return Err(ServerError::Config(format!(
+                "Invalid version requirement in '{}'",
+                spec
+            )));
+        }
+
+        let mut features: Vec<String> = features
+            .into_iter()
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty())
+            .collect();
+        features.sort();
+        features.dedup();
+
+        Ok(Self {
+            name: name.to_string(),
+            version_req: version_req.map(str::to_string),
+            features,
+        })
+    }
+
+    /// Name of the crate's directory in `cargo doc` output (dashes become underscores)
+    pub fn doc_name(&self) -> String {
+        canonical_crate_name(&self.name)
+    }
+
+    /// Key under which documentation and embeddings generated for this exact
+    /// release and feature set are cached
+    pub fn cache_key(&self) -> CacheKey {
+        CacheKey {
+            crate_name: self.doc_name(),
+            version: self.version_req.clone().unwrap_or_else(|| "*".to_string()),
+            features: self.features.clone(),
+        }
+    }
+
+    /// Returns true if `crate_name` refers to this crate, ignoring case and
+    /// `-`/`_` differences
+    pub fn matches(&self, crate_name: &str) -> bool {
+        normalize_crate_name(crate_name) == normalize_crate_name(&self.name)
+    }
+}
+
+impl fmt::Display for CrateSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version_req) = &self.version_req {
+            write!(f, "@{}", version_req)?;
+        }
+        if !self.features.is_empty() {
+            write!(f, " (features: {})", self.features.join(","))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Notices edited rustdoc pages so indexes aren't served stale.
+//!
+//! A [`DocFingerprint`] records the size and modification time of every page
+//! in a crate's doc directory when it is indexed. Comparing it with a fresh
+//! scan only reads file metadata, so it is cheap enough to do on queries, and
+//! tells which pages changed: re-indexing reads just those, and the chunk
+//! cache supplies the embeddings of any text that didn't change.
+
+use bincode::{Decode, Encode};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use crate::doc_loader::Document;
+use crate::platform_paths;
+
+/// Size and modification time of each page in a crate's doc directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct DocFingerprint {
+    /// `(bytes, nanoseconds since the epoch)` of every HTML file, keyed by
+    /// its path relative to the crate's doc directory
+    files: BTreeMap<String, (u64, u64)>,
+}
+
+impl DocFingerprint {
+    /// Reads the metadata of every HTML file under `crate_dir`; a missing
+    /// directory has no pages
+    pub fn scan(crate_dir: &Path) -> Self {
+        let files = WalkDir::new(crate_dir)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry.file_type().is_file()
+                    && entry.path().extension().is_some_and(|ext| ext == "html")
+            })
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |since| since.as_nanos() as u64);
+                let relative = entry.path().strip_prefix(crate_dir).ok()?;
+                Some((
+                    platform_paths::portable_path(relative),
+                    (metadata.len(), modified),
+                ))
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Number of pages
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if the directory had no pages
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// The text of an earlier index's pages whose files haven't changed since,
+/// so loading the docs again only parses the pages that did
+#[derive(Debug, Default)]
+pub struct UnchangedPages {
+    crate_dir: PathBuf,
+    /// The scan the unchanged pages were found with
+    fingerprint: DocFingerprint,
+    unchanged: HashSet<String>,
+    /// Text of the unchanged pages; pages without text are left out
+    documents: HashMap<String, Document>,
+}
+
+impl UnchangedPages {
+    /// Compares `crate_dir` with the `fingerprint` taken when `documents`
+    /// were loaded from it
+    pub fn new(crate_dir: &Path, fingerprint: &DocFingerprint, documents: Vec<Document>) -> Self {
+        let current = DocFingerprint::scan(crate_dir);
+        let unchanged: HashSet<String> = current
+            .files
+            .iter()
+            .filter(|(path, stamp)| fingerprint.files.get(*path) == Some(stamp))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let documents = documents
+            .into_iter()
+            .filter(|doc| unchanged.contains(&doc.path))
+            .map(|doc| (doc.path.clone(), doc))
+            .collect();
+        Self {
+            crate_dir: crate_dir.to_path_buf(),
+            fingerprint: current,
+            unchanged,
+            documents,
+        }
+    }
+
+    /// Reads the pages at `paths` again even if unchanged, e.g. ones the
+    /// earlier index left out, whose text it doesn't have
+    pub fn excluding<'a>(mut self, paths: impl IntoIterator<Item = &'a str>) -> Self {
+        for path in paths {
+            self.unchanged.remove(path);
+            self.documents.remove(path);
+        }
+        self
+    }
+
+    /// Number of pages that don't need to be read again
+    pub fn len(&self) -> usize {
+        self.unchanged.len()
+    }
+
+    /// Returns true if every page must be read
+    pub fn is_empty(&self) -> bool {
+        self.unchanged.is_empty()
+    }
+
+    /// The earlier text of the page at `file` if it is unchanged: `Some(None)`
+    /// if it had none. `None` means the page must be read.
+    pub fn get(&self, file: &Path) -> Option<Option<Document>> {
+        let relative = file.strip_prefix(&self.crate_dir).ok()?;
+        let path = platform_paths::portable_path(relative);
+        self.unchanged
+            .contains(&path)
+            .then(|| self.documents.get(&path).cloned())
+    }
+
+    /// The fingerprint of `crate_dir` as the pages were read: the scan these
+    /// pages were compared with if it is their directory, else a new one
+    pub fn fingerprint(self, crate_dir: &Path) -> DocFingerprint {
+        if self.crate_dir == crate_dir {
+            self.fingerprint
+        } else {
+            DocFingerprint::scan(crate_dir)
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, instrument, warn};
+use walkdir::WalkDir;
+
+use crate::crate_spec::{CrateSpec, canonical_crate_name};
+use crate::doc_loader::DocLoaderError;
+use crate::doc_workspace::DocWorkspace;
+
+/// Package name of the temporary project used to document arbitrary crates
+const SHIM_PACKAGE_NAME: &str = "rustdocs-mcp-shim";
+
+/// File next to a crate's `index.html` listing the Cargo features its docs
+/// were generated with, one per line
+pub const FEATURES_FILE_NAME: &str = ".rustdocs-mcp-features";
+
+/// How long a single `cargo doc` run may take unless configured otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(900);
+
+/// Receives `cargo doc` output line by line while it runs
+pub type ProgressFn = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Extra rustdoc settings applied to a `cargo doc` run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RustdocOptions {
+    /// Pass `--document-private-items`, e.g. for internal workspace crates
+    pub document_private_items: bool,
+    /// Flags passed to rustdoc, e.g. `["--cfg", "docsrs"]`
+    pub flags: Vec<String>,
+}
+
+/// How documentation for a crate was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationMethod {
+    /// The crate is part of the project's dependency graph
+    Project,
+    /// The crate was added to a temporary shim project depending on it
+    Shim,
+}
+
+/// Runs `cargo doc` so that documentation for a crate appears in the doc directory
+#[derive(Clone)]
+pub struct DocGenerator {
+    project_dir: PathBuf,
+    target_dir: PathBuf,
+    workspace: DocWorkspace,
+    offline: bool,
+    timeout: Duration,
+    cancel: CancellationToken,
+    progress: Option<ProgressFn>,
+    rustdoc: RustdocOptions,
+}
+
+impl DocGenerator {
+    /// Creates a generator for the project in `project_dir` writing docs into
+    /// `doc_dir` (which must be a `<target-dir>/doc` directory). Shim projects
+    /// are kept in `workspace` and reused by later generations.
+    pub fn new(project_dir: &Path, doc_dir: &Path, workspace: DocWorkspace, offline: bool) -> Self {
+        let target_dir = doc_dir
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("target"));
+        Self {
+            project_dir: project_dir.to_path_buf(),
+            target_dir,
+            workspace,
+            offline,
+            timeout: DEFAULT_TIMEOUT,
+            cancel: CancellationToken::new(),
+            progress: None,
+            rustdoc: RustdocOptions::default(),
+        }
+    }
+
+    /// Sets how long a single `cargo doc` run may take before it is killed
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Kills any running `cargo doc` once `cancel` is cancelled
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Applies extra rustdoc settings to every run
+    pub fn with_rustdoc_options(mut self, rustdoc: RustdocOptions) -> Self {
+        self.rustdoc = rustdoc;
+        self
+    }
+
+    /// Reports each line `cargo doc` writes to stderr (compile progress, warnings)
+    pub fn with_progress(mut self, progress: ProgressFn) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Documents `crate_name`, first from the project and otherwise from a
+    /// shim project that depends on the latest release of the crate
+    #[instrument(name = "generate_docs", skip(self))]
+    pub async fn generate(
+        &self,
+        crate_name: &str,
+        features: &[String],
+    ) -> Result<GenerationMethod, DocLoaderError> {
+        let project_manifest = self.project_dir.join("Cargo.toml");
+        if project_manifest.is_file() {
+            match self
+                .run_cargo_doc(&project_manifest, &self.target_dir, crate_name, features)
+                .await
+            {
+                Ok(()) => {
+                    record_features(&self.target_dir.join("doc"), crate_name, features);
+                    return Ok(GenerationMethod::Project);
+                }
+                Err(e @ (DocLoaderError::Cancelled | DocLoaderError::Timeout(_))) => {
+                    return Err(e);
+                }
+                Err(e) => warn!(
+                    "Crate '{}' could not be documented from the project, trying a shim project: {}",
+                    crate_name, e
+                ),
+            }
+        }
+
+        let shim = self.workspace.acquire(&format!("shim-{}", crate_name))?;
+        let shim_manifest = write_shim_project(shim.path(), crate_name, None, features)?;
+
+        // The shim's manifest already enables the features on the dependency
+        self.run_cargo_doc(&shim_manifest, &self.target_dir, crate_name, &[])
+            .await?;
+        record_features(&self.target_dir.join("doc"), crate_name, features);
+        Ok(GenerationMethod::Shim)
+    }
+
+    /// Documents the crates.io release matching `spec` (e.g. `serde@1.0.200`)
+    /// in a shim project and copies the resulting doc tree to `doc_dir`,
+    /// replacing whatever was there. The build itself stays in the workspace,
+    /// so regenerating the same release and features is incremental.
+    #[instrument(name = "generate_versioned_docs", skip(self, doc_dir), fields(spec = %spec))]
+    pub async fn generate_versioned(
+        &self,
+        spec: &CrateSpec,
+        doc_dir: &Path,
+    ) -> Result<(), DocLoaderError> {
+        let key = spec.cache_key();
+        let shim = self.workspace.acquire(&format!(
+            "shim-{}-{}-{}",
+            spec.name,
+            key.sanitized_version(),
+            key.features_hash()
+        ))?;
+        let shim_manifest = write_shim_project(
+            shim.path(),
+            &spec.name,
+            spec.version_req.as_deref(),
+            &spec.features,
+        )?;
+        let target_dir = shim.path().join("target");
+        self.run_cargo_doc(&shim_manifest, &target_dir, &spec.name, &[])
+            .await?;
+
+        if doc_dir.exists() {
+            fs::remove_dir_all(doc_dir)?;
+        }
+        copy_dir_all(&target_dir.join("doc"), doc_dir)?;
+        record_features(doc_dir, &spec.name, &spec.features);
+        Ok(())
+    }
+
+    /// Runs `cargo doc`, streaming its stderr to the progress callback. The
+    /// process is killed if it outlives the timeout or the generation is cancelled.
+    async fn run_cargo_doc(
+        &self,
+        manifest: &Path,
+        target_dir: &Path,
+        crate_name: &str,
+        features: &[String],
+    ) -> Result<(), DocLoaderError> {
+        let mut command = Command::new("cargo");
+        command
+            .arg("doc")
+            .arg("--no-deps")
+            .arg("--package")
+            .arg(crate_name)
+            .arg("--manifest-path")
+            .arg(manifest)
+            .arg("--target-dir")
+            .arg(target_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if !features.is_empty() {
+            command.arg("--features").arg(features.join(","));
+        }
+        if self.offline {
+            command.arg("--offline");
+        }
+        if self.rustdoc.document_private_items {
+            command.arg("--document-private-items");
+        }
+        if !self.rustdoc.flags.is_empty() {
+            // The encoded form keeps flags containing spaces intact and takes
+            // precedence over RUSTDOCFLAGS, so carry over whatever is already set
+            let mut flags: Vec<String> = match std::env::var("CARGO_ENCODED_RUSTDOCFLAGS") {
+                Ok(encoded) => encoded
+                    .split('\x1f')
+                    .filter(|flag| !flag.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                Err(_) => std::env::var("RUSTDOCFLAGS")
+                    .map(|flags| flags.split_whitespace().map(str::to_string).collect())
+                    .unwrap_or_default(),
+            };
+            flags.extend(self.rustdoc.flags.iter().cloned());
+            command.env("CARGO_ENCODED_RUSTDOCFLAGS", flags.join("\x1f"));
+        }
+
+        info!("Running cargo doc for crate '{}'", crate_name);
+        let mut child = command.spawn()?;
+        let mut lines = BufReader::new(child.stderr.take().expect("stderr is piped")).lines();
+
+        // Collect stderr while it streams so a failure can be summarized
+        let run = async {
+            let mut stderr = Vec::new();
+            while let Some(line) = lines.next_line().await? {
+                debug!(target: "cargo_doc", "{}", line);
+                if let Some(progress) = &self.progress {
+                    progress(&line);
+                }
+                stderr.push(line);
+            }
+            let status = child.wait().await?;
+            Ok::<_, std::io::Error>((status, stderr))
+        };
+
+        // Returning early drops the child, and `kill_on_drop` terminates it
+        let (status, stderr) = tokio::select! {
+            result = tokio::time::timeout(self.timeout, run) => match result {
+                Ok(result) => result?,
+                Err(_) => return Err(DocLoaderError::Timeout(self.timeout)),
+            },
+            _ = self.cancel.cancelled() => return Err(DocLoaderError::Cancelled),
+        };
+        if status.success() {
+            return Ok(());
+        }
+
+        let summary = stderr
+            .iter()
+            .filter(|line| line.trim_start().starts_with("error"))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(DocLoaderError::CargoDoc(if summary.is_empty() {
+            stderr.join("\n").trim().to_string()
+        } else {
+            summary
+        }))
+    }
+}
+
+impl fmt::Debug for DocGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DocGenerator")
+            .field("project_dir", &self.project_dir)
+            .field("target_dir", &self.target_dir)
+            .field("workspace", &self.workspace)
+            .field("offline", &self.offline)
+            .field("timeout", &self.timeout)
+            .field("rustdoc", &self.rustdoc)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Lists `features` next to the `index.html` of `crate_name` in `doc_dir`.
+/// Failing to is only logged: the docs are usable without the record.
+fn record_features(doc_dir: &Path, crate_name: &str, features: &[String]) {
+    let mut features = features.to_vec();
+    features.sort();
+    features.dedup();
+    let path = doc_dir
+        .join(canonical_crate_name(crate_name))
+        .join(FEATURES_FILE_NAME);
+    let contents: String = features
+        .iter()
+        .map(|feature| format!("{}\n", feature))
+        .collect();
+    if let Err(e) = fs::write(&path, contents) {
+        warn!("Failed to record the features of {}: {}", path.display(), e);
+    }
+}
+
+/// Reads the Cargo features recorded next to a crate's `index.html` when
+/// this server generated its docs; empty for the default features. `None`
+/// if the docs were generated some other way, or rebuilt since.
+pub fn recorded_features(index_file: &Path) -> Option<Vec<String>> {
+    let path = index_file.with_file_name(FEATURES_FILE_NAME);
+    let recorded = fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    // A later `cargo doc` run rewrites index.html, maybe with other features
+    let generated = fs::metadata(index_file)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    if recorded < generated {
+        return None;
+    }
+    let contents = fs::read_to_string(&path).ok()?;
+    Some(
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|feature| !feature.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Creates a library project in `dir` that depends on the crate, returning its manifest path.
+/// Files are only rewritten when their contents change so cargo can reuse earlier builds.
+fn write_shim_project(
+    dir: &Path,
+    crate_name: &str,
+    version_req: Option<&str>,
+    features: &[String],
+) -> Result<PathBuf, DocLoaderError> {
+    fs::create_dir_all(dir.join("src"))?;
+    write_if_changed(&dir.join("src").join("lib.rs"), "")?;
+    let manifest = dir.join("Cargo.toml");
+    write_if_changed(
+        &manifest,
+        &shim_manifest_contents(crate_name, version_req, features),
+    )?;
+    Ok(manifest)
+}
+
+fn write_if_changed(path: &Path, contents: &str) -> Result<(), DocLoaderError> {
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Recursively copies a directory tree
+fn copy_dir_all(from: &Path, to: &Path) -> Result<(), DocLoaderError> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let relative = entry
+            .path()
+            .strip_prefix(from)
+            .expect("walkdir yields paths below its root");
+        let destination = to.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)?;
+        } else {
+            fs::copy(entry.path(), &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders the manifest of a shim project that depends on `crate_name`,
+/// at `version_req` if given and the latest release otherwise
+pub fn shim_manifest_contents(
+    crate_name: &str,
+    version_req: Option<&str>,
+    features: &[String],
+) -> String {
+    let features = features
+        .iter()
+        .map(|f| format!("\"{}\"", f))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "[package]\n\
+         name = \"{}\"\n\
+         version = \"0.0.0\"\n\
+         edition = \"2021\"\n\
+         publish = false\n\
+         \n\
+         [dependencies]\n\
+         {} = {{ version = \"{}\", features = [{}] }}\n\
+         \n\
+         # Keep the shim out of any enclosing workspace\n\
+         [workspace]\n",
+        SHIM_PACKAGE_NAME,
+        crate_name,
+        version_req.unwrap_or("*"),
+        features
+    )
+}
@@ -2,13 +2,32 @@ use scraper::{Html, Selector};
 use std::{
     collections::HashMap,
     fs,
+    io::Cursor,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::Error as AnyhowError;
 use thiserror::Error;
+use tracing::{debug, info, instrument, warn};
 use walkdir::WalkDir;
 
+use crate::config::{Config, DocBackend};
+use crate::crate_discovery::DocRoot;
+use crate::crate_sources;
+use crate::crate_spec::{CrateSpec, canonical_crate_name};
+use crate::doc_freshness::UnchangedPages;
+use crate::doc_generator::{self, DocGenerator};
+use crate::doc_workspace::DocWorkspace;
+use crate::error::{self, ServerError};
+use crate::openai_client;
+use crate::page_text;
+use crate::platform_paths;
+use crate::source_loader;
+
+/// Site serving the rustdoc output of every crate published to crates.io
+const DOCS_RS_URL: &str = "https://docs.rs";
+
 #[derive(Debug, Error)]
 pub enum DocLoaderError {
     #[error("IO Error: {0}")]
@@ -21,6 +40,16 @@ pub enum DocLoaderError {
     CargoLib(#[from] AnyhowError),
     #[error("Documentation not found: {0}")]
     DocNotFound(String),
+    #[error("cargo doc failed: {0}")]
+    CargoDoc(String),
+    #[error("cargo doc timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("cargo doc was cancelled")]
+    Cancelled,
+    #[error("Invalid Cargo.toml: {0}")]
+    Manifest(String),
+    #[error("docs.rs download failed: {0}")]
+    Download(String),
 }
 
 // Simple struct to hold document content, maybe add path later if needed
@@ -30,11 +59,259 @@ pub struct Document {
     pub content: String,
 }
 
+/// Loads the documentation of `crate_name`, using the configuration from the
+/// environment. See [`load_documents_with_config`].
+pub async fn load_documents(
+    crate_name: &str,
+    version: Option<&str>,
+    features: Option<&[String]>,
+) -> error::Result<Vec<Document>> {
+    load_documents_with_config(crate_name, version, features, &Config::from_env()).await
+}
+
+/// Loads the documentation of `crate_name`, optionally of the release
+/// matching `version` (e.g. `1.0` or `1.0.200`) built with `features`,
+/// from wherever it can be found first:
+///
+/// 1. the configured doc roots, e.g. the project's `target/doc`, if their
+///    docs are of a matching version and were built with the features;
+/// 2. docs generated before into the global cache, or generated now with
+///    `cargo doc` in a temporary project depending on the crate;
+/// 3. the docs.rs build of the release, downloaded into the global cache if
+///    `cargo doc` fails. docs.rs builds with the features the crate's
+///    metadata lists, not `features`. Skipped with `offline`;
+/// 4. the doc comments of the release's sources in cargo's registry, if
+///    `docs.registry_sources` is on. See [`crate_sources`].
+///
+/// Crates configured with the `source` backend are read from their sources
+/// by [`source_loader`] instead.
+pub async fn load_documents_with_config(
+    crate_name: &str,
+    version: Option<&str>,
+    features: Option<&[String]>,
+    config: &Config,
+) -> error::Result<Vec<Document>> {
+    let spec = match version {
+        Some(version) => format!("{}@{}", crate_name, version),
+        None => crate_name.to_string(),
+    };
+    let spec = CrateSpec::parse(&spec, features.unwrap_or_default().to_vec())?;
+
+    if config.docs.backend(&spec.name) == DocBackend::Source {
+        let (crate_dir, documents) = source_loader::load_crate_documents(
+            &spec.name,
+            spec.version_req.as_deref(),
+            &config.docs,
+        )
+        .await?;
+        debug!(
+            "Loaded {} from its sources in {}",
+            spec,
+            crate_dir.display()
+        );
+        return Ok(documents);
+    }
+
+    for root in config.docs.doc_roots() {
+        let index_file = root.dir.join(spec.doc_name()).join("index.html");
+        if !index_file.is_file() {
+            continue;
+        }
+        let version_matches = spec.version_req.as_deref().is_none_or(|requested| {
+            documented_version(&index_file)
+                .is_some_and(|documented| version_matches(&documented, requested))
+        });
+        let features_match = spec.features.is_empty()
+            || doc_generator::recorded_features(&index_file).is_some_and(|recorded| {
+                spec.features
+                    .iter()
+                    .all(|feature| recorded.contains(feature))
+            });
+        if version_matches && features_match {
+            debug!("Loading docs of {} from the {} doc root", spec, root.name);
+            return Ok(load_documents_from_doc_dir(&root.dir, &spec.name)?);
+        }
+    }
+
+    let global_cache = config.global_cache()?;
+    let doc_dir = global_cache.docs_dir(&spec.cache_key());
+    if !doc_dir.join(spec.doc_name()).join("index.html").is_file() {
+        let workspace = DocWorkspace::in_cache_root(global_cache.root())?;
+        let generated = DocGenerator::new(
+            &config.docs.project_dir,
+            &config.docs.dir,
+            workspace,
+            config.offline,
+        )
+        .with_timeout(Duration::from_secs(config.docs.generate_timeout_secs))
+        .with_rustdoc_options(config.docs.rustdoc_options(&spec.name))
+        .generate_versioned(&spec, &doc_dir)
+        .await;
+        let error = match generated {
+            Ok(()) => None,
+            Err(e) if config.offline => Some(e),
+            Err(e) => {
+                warn!(
+                    "Could not generate the docs of {}, downloading them from docs.rs: {}",
+                    spec, e
+                );
+                download_docs_rs(&spec, &doc_dir, config)
+                    .await
+                    .err()
+                    .map(|download| {
+                        DocLoaderError::DocNotFound(format!(
+                            "Documentation for {} could not be generated ({}) or downloaded ({})",
+                            spec, e, download
+                        ))
+                    })
+            }
+        };
+        if let Some(error) = error {
+            return load_registry_sources(&spec, config).ok_or_else(|| error.into());
+        }
+    }
+    Ok(load_documents_from_doc_dir(&doc_dir, &spec.name)?)
+}
+
+/// Reads the doc comments of the sources of the release matching `spec` in
+/// cargo's registry, if enabled and they are there
+fn load_registry_sources(spec: &CrateSpec, config: &Config) -> Option<Vec<Document>> {
+    if !config.docs.registry_sources {
+        return None;
+    }
+    let sources = crate_sources::find_registry_sources(
+        &crate_sources::cargo_home()?,
+        &spec.name,
+        spec.version_req.as_deref(),
+    )?;
+    match crate_sources::load_documents_from_sources(&sources, &spec.name) {
+        Ok(documents) => {
+            info!(
+                "Indexing the doc comments of the sources of {} in {}",
+                spec,
+                sources.display()
+            );
+            Some(documents)
+        }
+        Err(e) => {
+            debug!("No usable sources of {}: {}", spec, e);
+            None
+        }
+    }
+}
+
+/// Returns true if docs of the `documented` version serve a request for
+/// `requested`: the same version, or a release of a partial one like `1.0`
+pub(crate) fn version_matches(documented: &str, requested: &str) -> bool {
+    let requested = requested.trim().trim_start_matches(['=', '^']);
+    requested == "*"
+        || documented == requested
+        || documented.starts_with(&format!("{}.", requested))
+}
+
+/// Downloads the docs.rs build of the release matching `spec` and unpacks
+/// it to `doc_dir`, replacing whatever was there
+async fn download_docs_rs(spec: &CrateSpec, doc_dir: &Path, config: &Config) -> error::Result<()> {
+    if !spec.features.is_empty() {
+        warn!(
+            "docs.rs builds {} with the features its metadata lists, not the requested ones",
+            spec.name
+        );
+    }
+    let mut url = reqwest::Url::parse(DOCS_RS_URL).expect("DOCS_RS_URL is a valid URL");
+    url.path_segments_mut()
+        .expect("DOCS_RS_URL can be a base")
+        .extend([
+            "crate",
+            spec.name.as_str(),
+            spec.version_req.as_deref().unwrap_or("latest"),
+            "download",
+        ]);
+    let mut client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.http.timeout_secs))
+        .user_agent(concat!("rustdocs_mcp_server/", env!("CARGO_PKG_VERSION")));
+    if let Some(proxy) = &config.http.proxy {
+        client = client.proxy(openai_client::parse_proxy(proxy)?);
+    }
+    info!("Downloading the docs of {} from {}", spec, url);
+    let archive = client
+        .build()?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(archive))
+        .map_err(|e| DocLoaderError::Download(e.to_string()))?;
+    if doc_dir.exists() {
+        fs::remove_dir_all(doc_dir)?;
+    }
+    // Entry names are checked to stay inside `doc_dir`
+    archive
+        .extract(doc_dir)
+        .map_err(|e| DocLoaderError::Download(e.to_string()))?;
+    if !doc_dir.join(spec.doc_name()).join("index.html").is_file() {
+        fs::remove_dir_all(doc_dir)?;
+        return Err(ServerError::DocLoader(DocLoaderError::Download(format!(
+            "the archive has no {}/index.html",
+            spec.doc_name()
+        ))));
+    }
+    Ok(())
+}
+
+/// Loads documentation for a crate from the first of `roots` (in priority
+/// order) that contains it. Returns the root together with the documents.
+pub fn load_documents_from_roots<'a>(
+    roots: &'a [DocRoot],
+    crate_name: &str,
+) -> Result<(&'a DocRoot, Vec<Document>), DocLoaderError> {
+    load_changed_documents_from_roots(roots, crate_name, &UnchangedPages::default())
+}
+
+/// Like [`load_documents_from_roots`], but takes the text of `unchanged`
+/// pages from an earlier index instead of parsing them again
+pub fn load_changed_documents_from_roots<'a>(
+    roots: &'a [DocRoot],
+    crate_name: &str,
+    unchanged: &UnchangedPages,
+) -> Result<(&'a DocRoot, Vec<Document>), DocLoaderError> {
+    let doc_name = canonical_crate_name(crate_name);
+    let root = roots
+        .iter()
+        .find(|root| root.dir.join(&doc_name).join("index.html").is_file())
+        .ok_or_else(|| {
+            let searched = roots
+                .iter()
+                .map(|root| root.dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            DocLoaderError::DocNotFound(format!(
+                "Documentation for crate '{}' not found in {}. Make sure the crate name is correct and run `cargo doc --package {}`.",
+                crate_name, searched, crate_name
+            ))
+        })?;
+    let documents = load_changed_documents_from_doc_dir(&root.dir, crate_name, unchanged)?;
+    Ok((root, documents))
+}
+
 /// Processes HTML documents from a directory, extracting content from the main content area.
 /// Used by both load_documents and load_documents_from_cargo_doc to avoid duplication.
 pub fn process_html_documents(
     docs_path: &Path,
     crate_name: &str,
+) -> Result<Vec<Document>, DocLoaderError> {
+    process_changed_html_documents(docs_path, crate_name, &UnchangedPages::default())
+}
+
+/// Like [`process_html_documents`], but takes the text of `unchanged` pages
+/// from an earlier index instead of parsing them again
+pub fn process_changed_html_documents(
+    docs_path: &Path,
+    crate_name: &str,
+    unchanged: &UnchangedPages,
 ) -> Result<Vec<Document>, DocLoaderError> {
     let mut documents = Vec::new();
 
@@ -52,8 +329,8 @@ pub fn process_html_documents(
         .map(|e| e.into_path()) // Get the PathBuf
         .collect();
 
-    eprintln!(
-        "[DEBUG] Found {} total HTML files for crate {}.",
+    debug!(
+        "Found {} total HTML files for crate {}.",
         all_html_paths.len(),
         crate_name
     );
@@ -68,13 +345,10 @@ pub fn process_html_documents(
                     .or_default()
                     .push(path);
             } else {
-                eprintln!(
-                    "[WARN] Skipping file with non-UTF8 name: {}",
-                    path.display()
-                );
+                warn!("Skipping file with non-UTF8 name: {}", path.display());
             }
         } else {
-            eprintln!("[WARN] Skipping file with no name: {}", path.display());
+            warn!("Skipping file with no name: {}", path.display());
         }
     }
 
@@ -129,14 +403,14 @@ pub fn process_html_documents(
                     paths_to_process.push(p);
                 }
                 Ok(None) => {
-                    eprintln!(
-                        "[WARN] No files found for basename '{}' during size comparison.",
+                    warn!(
+                        "No files found for basename '{}' during size comparison.",
                         basename
                     );
                 }
                 Err(e) => {
-                    eprintln!(
-                        "[WARN] Error getting metadata for basename '{}', skipping: {}",
+                    warn!(
+                        "Error getting metadata for basename '{}', skipping: {}",
                         basename, e
                     );
                 }
@@ -144,20 +418,24 @@ pub fn process_html_documents(
         }
     }
 
-    eprintln!(
-        "[DEBUG] Filtered down to {} files to process for crate {}.",
+    debug!(
+        "Filtered down to {} files to process for crate {}.",
         paths_to_process.len(),
         crate_name
     );
 
     // Process the filtered list of files
     for path in paths_to_process {
+        if let Some(document) = unchanged.get(&path) {
+            documents.extend(document);
+            continue;
+        }
         // Calculate path relative to the docs_path
         let relative_path = match path.strip_prefix(docs_path) {
             Ok(p) => p.to_path_buf(),
             Err(e) => {
-                eprintln!(
-                    "[WARN] Failed to strip prefix {} from {}: {}",
+                warn!(
+                    "Failed to strip prefix {} from {}: {}",
                     docs_path.display(),
                     path.display(),
                     e
@@ -165,12 +443,12 @@ pub fn process_html_documents(
                 continue;
             }
         };
-        let path_str = relative_path.to_string_lossy().to_string();
+        let path_str = platform_paths::portable_path(&relative_path);
 
-        let html_content = match fs::read_to_string(&path) {
+        let html_content = match fs::read_to_string(platform_paths::long_path(&path)) {
             Ok(content) => content,
             Err(e) => {
-                eprintln!("[WARN] Failed to read file {}: {}", path.display(), e);
+                warn!("Failed to read file {}: {}", path.display(), e);
                 continue;
             }
         };
@@ -178,12 +456,7 @@ pub fn process_html_documents(
         let document = Html::parse_document(&html_content);
 
         if let Some(main_content_element) = document.select(&content_selector).next() {
-            let text_content: String = main_content_element
-                .text()
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty())
-                .collect::<Vec<&str>>()
-                .join("\n");
+            let text_content = page_text::extract(main_content_element);
 
             if !text_content.is_empty() {
                 documents.push(Document {
@@ -208,8 +481,29 @@ pub fn process_html_documents(
 pub fn load_documents_from_cargo_doc(crate_name: &str) -> Result<Vec<Document>, DocLoaderError> {
     // Find the target directory in the current project
     // The standard location is `./target/doc/`
-    let target_doc_path = Path::new("./target/doc");
+    load_documents_from_doc_dir(Path::new("./target/doc"), crate_name)
+}
 
+/// Loads documentation for a crate from a `cargo doc` output directory at any location.
+///
+/// # Arguments
+/// * `target_doc_path` - The directory containing `cargo doc` output (e.g. `./target/doc`)
+/// * `crate_name` - The name of the crate to load documentation for
+pub fn load_documents_from_doc_dir(
+    target_doc_path: &Path,
+    crate_name: &str,
+) -> Result<Vec<Document>, DocLoaderError> {
+    load_changed_documents_from_doc_dir(target_doc_path, crate_name, &UnchangedPages::default())
+}
+
+/// Like [`load_documents_from_doc_dir`], but takes the text of `unchanged`
+/// pages from an earlier index instead of parsing them again
+#[instrument(name = "load_docs", skip(target_doc_path, unchanged), fields(doc_dir = %target_doc_path.display(), unchanged = unchanged.len()))]
+pub fn load_changed_documents_from_doc_dir(
+    target_doc_path: &Path,
+    crate_name: &str,
+    unchanged: &UnchangedPages,
+) -> Result<Vec<Document>, DocLoaderError> {
     if !target_doc_path.exists() {
         return Err(DocLoaderError::DocNotFound(format!(
             "Documentation directory not found at {}. Run `cargo doc` first.",
@@ -218,7 +512,7 @@ pub fn load_documents_from_cargo_doc(crate_name: &str) -> Result<Vec<Document>,
     }
 
     // Check if the crate documentation exists
-    let crate_doc_path = target_doc_path.join(crate_name.replace('-', "_"));
+    let crate_doc_path = target_doc_path.join(canonical_crate_name(crate_name));
 
     if !crate_doc_path.exists() || !crate_doc_path.is_dir() {
         return Err(DocLoaderError::DocNotFound(format!(
@@ -230,9 +524,9 @@ pub fn load_documents_from_cargo_doc(crate_name: &str) -> Result<Vec<Document>,
     }
 
     // Process the documents using the shared helper function
-    let documents = process_html_documents(&crate_doc_path, crate_name)?;
+    let documents = process_changed_html_documents(&crate_doc_path, crate_name, unchanged)?;
 
-    eprintln!(
+    info!(
         "Finished loading documents from local cargo doc. Found {} final documents for crate {}.",
         documents.len(),
         crate_name
@@ -240,3 +534,35 @@ pub fn load_documents_from_cargo_doc(crate_name: &str) -> Result<Vec<Document>,
 
     Ok(documents)
 }
+
+/// Reads the crate version rustdoc shows in the sidebar of a crate's
+/// `index.html` (cargo passes it as `--crate-version`)
+pub fn documented_version(index_file: &Path) -> Option<String> {
+    let html = fs::read_to_string(index_file).ok()?;
+    let selector = Selector::parse(".version").ok()?;
+    let page = Html::parse_document(&html);
+    let text: String = page.select(&selector).next()?.text().collect();
+    // Older rustdoc releases render "Version 1.2.3"
+    let version = text.trim().trim_start_matches("Version").trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Item path for a rustdoc page path relative to the crate's doc directory,
+/// e.g. `de/struct.Error.html` becomes `serde_json::de::Error` and
+/// `de/index.html` the module `serde_json::de`. Pages that aren't items
+/// (source views, indexes of all items) yield `None`.
+pub fn item_path(crate_name: &str, relative: &Path) -> Option<String> {
+    let mut segments: Vec<&str> = relative
+        .iter()
+        .map(|segment| segment.to_str())
+        .collect::<Option<_>>()?;
+    let page = segments.pop()?.strip_suffix(".html")?;
+    let mut path = vec![canonical_crate_name(crate_name)];
+    path.extend(segments.iter().map(|segment| segment.to_string()));
+    match page.split_once('.') {
+        None if page == "index" => {}
+        None => return None,
+        Some((_kind, name)) => path.push(name.to_string()),
+    }
+    Some(path.join("::"))
+}
@@ -0,0 +1,99 @@
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tracing::warn;
+
+use crate::error::Result;
+use crate::utils::ensure_dir_exists;
+
+/// Directories rustdoc writes at the top of `target/doc` that don't belong to a crate
+const SHARED_DOC_DIRS: &[&str] = &[
+    "src",
+    "static.files",
+    "implementors",
+    "trait.impl",
+    "type.impl",
+];
+
+/// Watches a documentation directory and reports which crates' docs changed.
+/// Events are debounced so a whole `cargo doc` run is reported as one batch.
+pub struct DocWatcher {
+    // Kept alive for as long as events should be delivered
+    _watcher: RecommendedWatcher,
+    receiver: UnboundedReceiver<PathBuf>,
+    doc_dir: PathBuf,
+    debounce: Duration,
+}
+
+impl DocWatcher {
+    /// Starts watching `doc_dir` recursively, creating it if it doesn't exist yet
+    pub fn new(doc_dir: &Path, debounce: Duration) -> Result<Self> {
+        ensure_dir_exists(doc_dir)?;
+        let doc_dir = doc_dir.canonicalize()?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            match event {
+                Ok(event) => {
+                    for path in event.paths {
+                        // The receiver only goes away when the watcher is dropped
+                        let _ = sender.send(path);
+                    }
+                }
+                Err(e) => warn!("Documentation watcher error: {}", e),
+            }
+        })?;
+        watcher.watch(&doc_dir, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+            doc_dir,
+            debounce,
+        })
+    }
+
+    /// Returns the (canonicalized) directory being watched
+    pub fn doc_dir(&self) -> &Path {
+        &self.doc_dir
+    }
+
+    /// Waits for changes and returns the names of the crates whose docs changed.
+    /// Collects events until none arrive for the debounce interval.
+    /// Returns `None` once the watcher has shut down.
+    pub async fn next_batch(&mut self) -> Option<BTreeSet<String>> {
+        let mut crates = BTreeSet::new();
+        loop {
+            let path = if crates.is_empty() {
+                self.receiver.recv().await?
+            } else {
+                match tokio::time::timeout(self.debounce, self.receiver.recv()).await {
+                    Ok(Some(path)) => path,
+                    Ok(None) | Err(_) => return Some(crates),
+                }
+            };
+
+            if let Some(crate_name) = crate_for_path(&self.doc_dir, &path) {
+                crates.insert(crate_name);
+            }
+        }
+    }
+}
+
+/// Maps a changed path inside the doc directory to the crate it documents.
+/// Returns `None` for shared rustdoc assets and files at the top level.
+pub fn crate_for_path(doc_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(doc_dir).ok()?;
+    let first = match relative.components().next()? {
+        Component::Normal(name) => name.to_str()?,
+        _ => return None,
+    };
+
+    // Crate directories never contain a dot, unlike top-level files (search-index.js, ...)
+    if first.contains('.') || SHARED_DOC_DIRS.contains(&first) {
+        return None;
+    }
+    Some(first.to_string())
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tracing::{debug, warn};
+
+use crate::platform_paths;
+use crate::utils::ensure_dir_exists;
+
+/// Name of the workspace directory inside the global cache root. Crate names
+/// can't start with a dot, so it never collides with a cached crate.
+pub const WORKSPACES_DIR_NAME: &str = ".workspaces";
+
+/// File touched whenever a workspace is acquired or released
+const LAST_USED_MARKER: &str = ".last-used";
+
+/// Workspaces unused for this long are removed by [`DocWorkspace::cleanup_stale`]
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Persistent directory holding the shim projects used to generate documentation.
+///
+/// Each generation gets a named subdirectory that is reused across runs, so
+/// `Cargo.lock` and build artifacts survive and regenerating docs is incremental.
+#[derive(Debug, Clone)]
+pub struct DocWorkspace {
+    root: PathBuf,
+}
+
+/// A workspace subdirectory in use by a generation. Dropping the handle marks
+/// the workspace as recently used; the directory itself is kept for reuse.
+#[derive(Debug)]
+pub struct WorkspaceHandle {
+    dir: PathBuf,
+}
+
+impl DocWorkspace {
+    /// Opens (and creates) the workspace directory
+    pub fn new(root: PathBuf) -> io::Result<Self> {
+        ensure_dir_exists(&root)?;
+        Ok(Self { root })
+    }
+
+    /// Opens the workspace directory inside a global cache root
+    pub fn in_cache_root(cache_root: &Path) -> io::Result<Self> {
+        Self::new(cache_root.join(WORKSPACES_DIR_NAME))
+    }
+
+    /// Returns the root directory of the workspace
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the subdirectory for `name`, creating it if needed
+    pub fn acquire(&self, name: &str) -> io::Result<WorkspaceHandle> {
+        let dir = self.root.join(platform_paths::safe_file_name(name));
+        ensure_dir_exists(&dir)?;
+        touch(&dir);
+        debug!("Acquired doc workspace {}", dir.display());
+        Ok(WorkspaceHandle { dir })
+    }
+
+    /// Removes workspaces that haven't been used for `max_age`.
+    /// Returns the number of workspaces removed.
+    pub fn cleanup_stale(&self, max_age: Duration) -> io::Result<usize> {
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.root)? {
+            let dir = entry?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            let last_used = fs::metadata(dir.join(LAST_USED_MARKER))
+                .or_else(|_| fs::metadata(&dir))
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let age = now.duration_since(last_used).unwrap_or_default();
+            if age > max_age {
+                match fs::remove_dir_all(&dir) {
+                    Ok(()) => removed += 1,
+                    Err(e) => warn!("Failed to remove stale workspace {}: {}", dir.display(), e),
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+impl WorkspaceHandle {
+    /// Returns the workspace directory
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for WorkspaceHandle {
+    fn drop(&mut self) {
+        touch(&self.dir);
+    }
+}
+
+/// Records that a workspace was just used
+fn touch(dir: &Path) {
+    // Rewriting the marker updates its modification time
+    if let Err(e) = fs::write(dir.join(LAST_USED_MARKER), b"") {
+        warn!("Failed to mark workspace {} as used: {}", dir.display(), e);
+    }
+}
@@ -0,0 +1,135 @@
+//! High-level API for embedding documentation search in other tools.
+//!
+//! [`DocsIndex`] runs the same load, embed and search pipeline as the MCP
+//! server behind three calls:
+//!
+//! ```no_run
+//! # async fn demo() -> rustdocs_mcp_server::Result<()> {
+//! use rustdocs_mcp_server::DocsIndex;
+//!
+//! let index = DocsIndex::open("serde").await?;
+//! for hit in index.search("derive Serialize for an enum", 3).await? {
+//!     println!("{} ({:.2})", hit.path, hit.score);
+//! }
+//! println!("{}", index.answer("How do I rename a field?").await?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Providers are chosen by the configuration just as for the server:
+//! `embedding.provider` and `llm.provider` select OpenAI (or any compatible
+//! endpoint via `api_base`) or the offline `mock` backends.
+//!
+//! Documentation that isn't rustdoc output can be fed in with
+//! [`DocsIndex::ingest`], which stores it as a pseudo-crate the server's tools
+//! can query as well.
+
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::doc_loader::Document;
+use crate::error::{Result, ServerError};
+use crate::ingest;
+use crate::server::RustDocsServer;
+use crate::state::{CrateIndex, SkippedDocument};
+
+/// A document matching a search, with its similarity to the query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    /// Page path relative to the crate's doc directory
+    pub path: String,
+    /// Cosine similarity between the query and the document
+    pub score: f32,
+    /// Extracted text of the page
+    pub content: String,
+}
+
+/// The documentation of one crate, loaded and embedded, ready to search
+pub struct DocsIndex {
+    server: RustDocsServer,
+    index: Arc<CrateIndex>,
+}
+
+impl DocsIndex {
+    /// Opens the docs of `crate_name` using the configuration from the environment
+    pub async fn open(crate_name: &str) -> Result<Self> {
+        Self::open_with_config(crate_name, Config::from_env()).await
+    }
+
+    /// Opens the docs of `crate_name` using an explicitly loaded configuration
+    pub async fn open_with_config(crate_name: &str, config: Config) -> Result<Self> {
+        let server = RustDocsServer::with_config(String::new(), config)?;
+        let index = server.index_crate(crate_name).await?;
+        Ok(Self { server, index })
+    }
+
+    /// Stores `documents` (Markdown or plain text, keyed by path) as the
+    /// pseudo-crate `name`, replacing what was ingested under that name
+    /// before, and indexes it using the configuration from the environment
+    pub async fn ingest(name: &str, documents: &[Document]) -> Result<Self> {
+        Self::ingest_with_config(name, documents, Config::from_env()).await
+    }
+
+    /// Like [`DocsIndex::ingest`], using an explicitly loaded configuration.
+    /// The pages are written to the global cache, where servers sharing it
+    /// find them on their next query.
+    pub async fn ingest_with_config(
+        name: &str,
+        documents: &[Document],
+        config: Config,
+    ) -> Result<Self> {
+        let doc_dir = config.global_cache()?.ingested_dir();
+        let report = ingest::ingest_documents(&doc_dir, name, documents)?;
+        Self::open_with_config(&report.crate_name, config).await
+    }
+
+    /// Name of the indexed crate's doc directory, e.g. `serde_json`
+    pub fn crate_name(&self) -> &str {
+        &self.index.crate_name
+    }
+
+    /// Number of documents in the index
+    pub fn len(&self) -> usize {
+        self.index.documents.len()
+    }
+
+    /// Returns true if the crate has no indexed documents
+    pub fn is_empty(&self) -> bool {
+        self.index.documents.is_empty()
+    }
+
+    /// Documents left out of the index because they couldn't be embedded
+    pub fn skipped(&self) -> &[SkippedDocument] {
+        &self.index.skipped
+    }
+
+    /// Paths of large documents still being embedded in the background,
+    /// which searches don't cover yet
+    pub fn pending(&self) -> &[String] {
+        &self.index.pending
+    }
+
+    /// Returns up to `k` documents most relevant to `query`, best first
+    pub async fn search(&self, query: &str, k: usize) -> Result<Vec<SearchHit>> {
+        let hits = self.server.search_index(&self.index, query, k).await?;
+        Ok(hits
+            .into_iter()
+            .map(|(doc, score)| SearchHit {
+                path: doc.path,
+                score,
+                content: doc.content,
+            })
+            .collect())
+    }
+
+    /// Answers `question` from the most relevant documents using the
+    /// configured chat provider, exactly as the `query_rust_docs` tool does
+    pub async fn answer(&self, question: &str) -> Result<String> {
+        self.server
+            .answer_query(self.crate_name(), question, None, &CancellationToken::new())
+            .await
+            .map_err(|e| ServerError::McpRuntime(e.message.to_string()))
+    }
+}
@@ -0,0 +1,106 @@
+//! Links the items an answer mentions to their pages on docs.rs.
+//!
+//! Answers name items the way the documentation does: `Client`,
+//! `de::from_str`, `serde_json::Value`, or `Client::send` for a method. Each
+//! name that is the item path of one of the crate's pages, or ends one
+//! unambiguously, is linked to that page on docs.rs for the documented
+//! version. Links are listed after the answer, leaving its text as the model
+//! wrote it.
+
+use std::path::Path;
+
+use crate::crate_spec::canonical_crate_name;
+use crate::doc_loader;
+use crate::identifier_boost;
+
+/// Where the links point
+const DOCS_RS: &str = "https://docs.rs";
+
+/// Page kinds that document methods, linked to with a `#method.` anchor
+const TYPE_KINDS: &[&str] = &["struct", "enum", "union", "trait"];
+
+/// An item of the answer and its page on docs.rs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemLink {
+    /// The item as the answer names it
+    pub name: String,
+    pub url: String,
+}
+
+/// URL of the page at `page`, relative to the crate's doc directory, on
+/// docs.rs; the latest release if `version` is unknown
+pub fn docs_rs_url(crate_name: &str, version: Option<&str>, page: &str) -> String {
+    format!(
+        "{}/{}/{}/{}/{}",
+        DOCS_RS,
+        crate_name,
+        version.unwrap_or("latest"),
+        canonical_crate_name(crate_name),
+        page
+    )
+}
+
+/// Links the items of `crate_name` that `answer` mentions, in the order they
+/// are first mentioned. `pages` are the crate's documented pages, relative
+/// to its doc directory.
+pub fn item_links(
+    answer: &str,
+    crate_name: &str,
+    version: Option<&str>,
+    pages: &[String],
+) -> Vec<ItemLink> {
+    let items: Vec<(String, &str)> = pages
+        .iter()
+        .filter_map(|page| {
+            doc_loader::item_path(crate_name, Path::new(page)).map(|item| (item, page.as_str()))
+        })
+        .collect();
+
+    identifier_boost::identifiers(answer)
+        .into_iter()
+        .filter_map(|name| {
+            let target = resolve(&items, &name)?;
+            Some(ItemLink {
+                url: docs_rs_url(crate_name, version, &target),
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Renders links as a list to append to an answer, or nothing if there are none
+pub fn format_links(links: &[ItemLink]) -> String {
+    if links.is_empty() {
+        return String::new();
+    }
+    let mut text = "\n\nDocs:".to_string();
+    for link in links {
+        text.push_str(&format!("\n- `{}`: {}", link.name, link.url));
+    }
+    text
+}
+
+/// Finds the page documenting `name`: an item whose path is `name` or ends
+/// with it, or a method of such a type. Names matching several items link
+/// nowhere.
+fn resolve(items: &[(String, &str)], name: &str) -> Option<String> {
+    if let Some(page) = unique_page(items, name) {
+        return Some(page.to_string());
+    }
+    let (owner, method) = name.rsplit_once("::")?;
+    let page = unique_page(items, owner)?;
+    let kind = Path::new(page).file_name()?.to_str()?.split_once('.')?.0;
+    TYPE_KINDS
+        .contains(&kind)
+        .then(|| format!("{}#method.{}", page, method))
+}
+
+fn unique_page<'a>(items: &[(String, &'a str)], name: &str) -> Option<&'a str> {
+    if let Some((_, page)) = items.iter().find(|(item, _)| item == name) {
+        return Some(page);
+    }
+    let suffix = format!("::{}", name);
+    let mut matches = items.iter().filter(|(item, _)| item.ends_with(&suffix));
+    let (_, page) = matches.next()?;
+    matches.next().is_none().then_some(*page)
+}
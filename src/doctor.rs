@@ -0,0 +1,351 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::api_key;
+use crate::config::Config;
+use crate::embedding_cache_service;
+use crate::error::{ProviderErrorKind, ServerError};
+use crate::global_cache;
+use crate::openai_client::OpenAiClient;
+
+/// Oldest rustdoc release whose HTML layout (`section#main-content`) the loader understands
+const MIN_RUSTDOC_MINOR: u32 = 54;
+
+/// Names of the checks that decide whether the provider can be used
+const PROVIDER_CHECKS: [&str; 2] = ["API key", "API connectivity"];
+
+/// Outcome of a single diagnostic check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// A diagnostic check result with an optional suggested fix
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.status {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "FAIL",
+        };
+        write!(f, "[{:>4}] {}: {}", label, self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the documentation directory exists and contains crate docs
+pub fn check_doc_dir(doc_dir: &Path) -> Check {
+    const NAME: &str = "cargo doc output";
+    if !doc_dir.is_dir() {
+        return Check::fail(
+            NAME,
+            format!("{} not found", doc_dir.display()),
+            "run `cargo doc` in your project before starting the server",
+        );
+    }
+
+    let crate_count = fs::read_dir(doc_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join("index.html").is_file())
+                .count()
+        })
+        .unwrap_or(0);
+
+    if crate_count == 0 {
+        Check::warn(
+            NAME,
+            format!("{} contains no crate documentation", doc_dir.display()),
+            "run `cargo doc --package <crate_name>` for the crates you want to query",
+        )
+    } else {
+        Check::ok(
+            NAME,
+            format!("{} crates documented in {}", crate_count, doc_dir.display()),
+        )
+    }
+}
+
+/// Checks that a cache directory exists (or can be created) and is writable
+pub fn check_cache_writable(name: &'static str, dir: &Path) -> Check {
+    let probe = dir.join(".doctor-write-probe");
+    let result = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b"probe"))
+        .and_then(|_| fs::remove_file(&probe));
+
+    match result {
+        Ok(()) => Check::ok(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::fail(
+            name,
+            format!("{} is not writable: {}", dir.display(), e),
+            "fix the directory permissions or free up disk space",
+        ),
+    }
+}
+
+/// Parses the minor version out of `rustdoc --version` output (e.g. `rustdoc 1.86.0 (...)`)
+pub fn parse_rustdoc_minor(version_output: &str) -> Option<u32> {
+    let version = version_output.split_whitespace().nth(1)?;
+    let mut parts = version.split('.');
+    if parts.next()? != "1" {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
+
+/// Checks that the installed rustdoc produces HTML the loader understands
+pub fn check_rustdoc() -> Check {
+    const NAME: &str = "rustdoc";
+    let output = match Command::new("rustdoc").arg("--version").output() {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            return Check::fail(
+                NAME,
+                "rustdoc not found on PATH",
+                "install a Rust toolchain with `rustup` so `cargo doc` works",
+            );
+        }
+    };
+
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match parse_rustdoc_minor(&version) {
+        Some(minor) if minor >= MIN_RUSTDOC_MINOR => Check::ok(NAME, version),
+        Some(_) => Check::warn(
+            NAME,
+            format!("{} predates the supported HTML layout", version),
+            "run `rustup update` and regenerate docs with `cargo doc`",
+        ),
+        None => Check::warn(
+            NAME,
+            format!("unrecognized version string: {}", version),
+            "make sure `rustdoc --version` reports a stable 1.x release",
+        ),
+    }
+}
+
+/// Checks the API key and that the configured chat endpoint answers a tiny
+/// authenticated request, sent the way answers are requested
+pub async fn check_openai(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let source = api_key::describe_source(&config.openai);
+    let api_key = match api_key::load_api_key(&config.openai) {
+        Ok(Some(key)) if !key.trim().is_empty() => {
+            checks.push(Check::ok("API key", format!("read from {}", source)));
+            key
+        }
+        Ok(_) => {
+            checks.push(Check::fail(
+                "API key",
+                "OPENAI_API_KEY not set",
+                "export OPENAI_API_KEY=sk-..., add it to a .env file or set openai.api_key_file",
+            ));
+            return checks;
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "API key",
+                e.to_string(),
+                "fix openai.api_key_file or openai.api_key_keychain",
+            ));
+            return checks;
+        }
+    };
+
+    let client = match OpenAiClient::new(
+        api_key,
+        config.llm.api_base.as_deref(),
+        &config.llm.headers,
+        &config.http,
+    )
+    .and_then(|client| client.with_account(&config.openai))
+    {
+        Ok(client) => client,
+        Err(e) => {
+            checks.push(Check::fail(
+                "API connectivity",
+                e.to_string(),
+                "fix the [llm], [http] and [openai] settings",
+            ));
+            return checks;
+        }
+    };
+    let api_base = client.api_base();
+
+    checks.push(match client.check_access().await {
+        Ok(()) => Check::ok("API connectivity", format!("{} reachable", api_base)),
+        Err(ServerError::Provider {
+            kind: ProviderErrorKind::Auth,
+            ..
+        }) => Check::fail(
+            "API connectivity",
+            format!("{} rejected the API key", api_base),
+            "check that OPENAI_API_KEY is valid and not revoked",
+        ),
+        Err(ServerError::Provider {
+            kind: ProviderErrorKind::Network,
+            message,
+            ..
+        }) => Check::fail(
+            "API connectivity",
+            format!("could not reach {}: {}", api_base, message),
+            "check network access, proxy settings and OPENAI_API_BASE",
+        ),
+        Err(e) => Check::warn(
+            "API connectivity",
+            format!("{} answered with an error: {}", api_base, e),
+            "verify OPENAI_API_BASE points at an OpenAI-compatible /v1 endpoint",
+        ),
+    });
+
+    checks
+}
+
+/// Runs every diagnostic check against the effective configuration
+pub async fn run_diagnostics(config: &Config) -> Vec<Check> {
+    let mut checks = vec![check_doc_dir(&config.docs.dir), check_rustdoc()];
+    checks.extend(check_caches(config));
+    checks.extend(check_provider(config).await);
+    checks
+}
+
+/// Checks run before the server starts: the doc directory it serves, the
+/// caches and the provider. Unlike `doctor`, rustdoc isn't required to serve
+/// documentation that was already generated.
+pub async fn startup_checks(config: &Config, doc_dir: &Path) -> Vec<Check> {
+    let mut checks = vec![check_doc_dir(doc_dir)];
+    checks.extend(check_caches(config));
+    checks.extend(check_provider(config).await);
+    checks
+}
+
+/// How the server runs after its startup checks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartupMode {
+    /// Everything the server needs is available, or offline mode was asked for
+    Full,
+    /// The provider can't be used, so the server runs offline: questions are
+    /// answered with raw documentation retrieved with cached embeddings
+    RetrievalOnly { reason: String },
+}
+
+/// Decides how to start from the startup checks. In strict mode any failed
+/// check is an error naming every failure and its fix; otherwise a provider
+/// failure degrades the server to retrieval only, and other failures are
+/// left to surface when they matter.
+pub fn startup_mode(checks: &[Check], strict: bool) -> Result<StartupMode, ServerError> {
+    let failures: Vec<&Check> = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .collect();
+    if strict && !failures.is_empty() {
+        let report = failures
+            .iter()
+            .map(|check| check.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(ServerError::Config(format!(
+            "Startup checks failed in strict mode:\n{}",
+            report
+        )));
+    }
+
+    Ok(failures
+        .iter()
+        .find(|check| PROVIDER_CHECKS.contains(&check.name))
+        .map_or(StartupMode::Full, |check| StartupMode::RetrievalOnly {
+            reason: check.detail.clone(),
+        }))
+}
+
+/// Checks that the chunk and global caches can be written
+fn check_caches(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let chunk_dir = match &config.cache.dir {
+        Some(dir) => Ok(dir.clone()),
+        None => embedding_cache_service::default_cache_dir(),
+    };
+    match chunk_dir {
+        Ok(dir) => checks.push(check_cache_writable("chunk cache", &dir)),
+        Err(e) => checks.push(Check::fail(
+            "chunk cache",
+            e.to_string(),
+            "set HOME so the cache directory can be located",
+        )),
+    }
+    let global_dir = match &config.cache.global_dir {
+        Some(dir) => Ok(dir.clone()),
+        None => global_cache::default_root(),
+    };
+    match global_dir {
+        Ok(dir) => checks.push(check_cache_writable("global cache", &dir)),
+        Err(e) => checks.push(Check::fail(
+            "global cache",
+            e.to_string(),
+            "set HOME or XDG_DATA_HOME so the cache directory can be located",
+        )),
+    }
+    checks
+}
+
+/// Checks the provider, unless the configuration never calls it
+async fn check_provider(config: &Config) -> Vec<Check> {
+    if config.offline {
+        vec![Check::ok(
+            "API connectivity",
+            "skipped in offline mode; only cached embeddings will be used",
+        )]
+    } else if !config.requires_api_key() {
+        vec![Check::ok(
+            "API connectivity",
+            "skipped; embedding and chat providers are mocked",
+        )]
+    } else {
+        check_openai(config).await
+    }
+}
@@ -2,6 +2,9 @@ use fnv::FnvHasher;
 use sha2::{Digest, Sha256};
 use std::hash::{Hash, Hasher};
 
+use crate::boilerplate;
+use crate::chunk_context;
+
 /// Default values for the chunker
 const DEFAULT_MIN_CHUNK_SIZE: usize = 1000; // ~1KB minimum
 const DEFAULT_TARGET_CHUNK_SIZE: usize = 4000; // ~4KB target
@@ -13,6 +16,14 @@ const POLYNOMIAL: u32 = 69997;
 /// Bit mask for determining chunk boundaries (2^13-1)
 const CHUNK_MASK: u32 = 0x1FFF;
 
+/// Version of the chunk id scheme, the `v1` of `v1-3f2a9c1b-<sha256>`. Bump it
+/// when the chunking algorithm or the text it hashes changes, so entries cached
+/// under the old scheme are migrated instead of silently going unused.
+pub const CHUNK_ID_VERSION: u32 = 1;
+
+/// Hex digits of the chunking parameters' hash in chunk ids
+const PARAMS_HASH_LEN: usize = 8;
+
 /// Implements Content-Defined Chunking (CDC) for documents.
 /// Uses a rolling hash function to find natural chunk boundaries based on content.
 #[derive(Debug, Clone)]
@@ -20,25 +31,34 @@ pub struct DocumentChunker {
     min_chunk_size: usize,
     target_chunk_size: usize,
     max_chunk_size: usize,
+    /// Whether [`embeddable_chunks`](Self::embeddable_chunks) drops generated
+    /// boilerplate
+    skip_boilerplate: bool,
+    /// Start of every chunk id, naming the scheme and the parameters
+    id_prefix: String,
 }
 
 /// Represents a single chunk from a document
 #[derive(Debug, Clone)]
 pub struct Chunk {
-    /// Unique identifier for the chunk based on its content
+    /// Unique identifier for the chunk based on its content, prefixed with
+    /// the id scheme and chunking parameters
     pub id: String,
     /// The content of the chunk
     pub content: String,
+    /// Number of LLM tokens the content takes up, counted once when the
+    /// chunk is made and reused for every budget decision about it
+    pub tokens: usize,
 }
 
 impl DocumentChunker {
     /// Creates a new DocumentChunker with default parameters
     pub fn new() -> Self {
-        Self {
-            min_chunk_size: DEFAULT_MIN_CHUNK_SIZE,
-            target_chunk_size: DEFAULT_TARGET_CHUNK_SIZE,
-            max_chunk_size: DEFAULT_MAX_CHUNK_SIZE,
-        }
+        Self::with_params(
+            DEFAULT_MIN_CHUNK_SIZE,
+            DEFAULT_TARGET_CHUNK_SIZE,
+            DEFAULT_MAX_CHUNK_SIZE,
+        )
     }
 
     /// Returns the minimum chunk size
@@ -61,27 +81,51 @@ impl DocumentChunker {
     /// Creates a new DocumentChunker with custom parameters
     #[allow(dead_code)]
     pub fn with_params(min_size: usize, target_size: usize, max_size: usize) -> Self {
+        let params = format!(
+            "sha256;cdc;{};{};{};{};{}",
+            min_size, target_size, max_size, POLYNOMIAL, CHUNK_MASK
+        );
+        let params_hash = format!("{:x}", Sha256::digest(params.as_bytes()));
         Self {
             min_chunk_size: min_size,
             target_chunk_size: target_size,
             max_chunk_size: max_size,
+            skip_boilerplate: true,
+            id_prefix: format!("v{}-{}-", CHUNK_ID_VERSION, &params_hash[..PARAMS_HASH_LEN]),
         }
     }
 
+    /// Returns the start of the ids of chunks made by this chunker, e.g.
+    /// `v1-3f2a9c1b-`
+    pub fn id_prefix(&self) -> &str {
+        &self.id_prefix
+    }
+
+    /// Sets whether boilerplate chunks are left out of embeddings
+    pub fn skipping_boilerplate(mut self, skip: bool) -> Self {
+        self.skip_boilerplate = skip;
+        self
+    }
+
+    /// Returns true if boilerplate chunks are left out of embeddings
+    pub fn skips_boilerplate(&self) -> bool {
+        self.skip_boilerplate
+    }
+
     /// Creates a new chunk with content and ID
     fn create_chunk(&self, content: &str) -> Chunk {
         Chunk {
             id: self.generate_chunk_id(content),
             content: content.to_string(),
+            tokens: chunk_context::count_tokens(content),
         }
     }
 
-    /// Process document for chunk boundaries using rolling hash
-    fn find_chunk_boundaries(&self, document: &str) -> Vec<usize> {
+    /// Returns the end of the chunk of `document` starting at `start`, found
+    /// with a rolling hash
+    fn next_boundary(&self, document: &str, start: usize) -> usize {
         let bytes = document.as_bytes();
-        let mut boundaries = Vec::new();
-        let mut start_idx = 0;
-        let mut i = 0;
+        let mut i = start;
         let mut rolling_hash: u32 = 0;
 
         while i < bytes.len() {
@@ -89,71 +133,74 @@ impl DocumentChunker {
             rolling_hash = ((rolling_hash << 1) | (bytes[i] as u32)) % POLYNOMIAL;
             i += 1;
 
-            // Only consider boundaries after minimum chunk size
-            if i - start_idx < self.min_chunk_size {
+            // Chunks only end between characters
+            if !document.is_char_boundary(i) {
                 continue;
             }
 
-            // Forced break at maximum chunk size
-            if i - start_idx >= self.max_chunk_size {
-                boundaries.push(i);
-                start_idx = i;
-                rolling_hash = 0;
+            // Forced break at maximum chunk size, before a character that
+            // would no longer fit
+            let next_char = document[i..].chars().next().map_or(0, char::len_utf8);
+            if i - start + next_char > self.max_chunk_size {
+                return i;
+            }
+
+            // Only consider boundaries after minimum chunk size
+            if i - start < self.min_chunk_size {
                 continue;
             }
 
             // Check if rolling hash matches chunk boundary pattern
             // We use a bit mask to create breakpoints with a certain probability
-            if (rolling_hash & CHUNK_MASK) == 0 || (i - start_idx >= self.target_chunk_size) {
-                boundaries.push(i);
-                start_idx = i;
-                rolling_hash = 0;
+            if (rolling_hash & CHUNK_MASK) == 0 || (i - start >= self.target_chunk_size) {
+                return i;
             }
         }
 
-        // Add the end of document if not already included
-        if !boundaries.is_empty() && boundaries[boundaries.len() - 1] != bytes.len() {
-            boundaries.push(bytes.len());
-        }
-
-        boundaries
+        bytes.len()
     }
 
     /// Splits a document into content-defined chunks
     pub fn chunk_document(&self, document: &str) -> Vec<Chunk> {
-        // Handle small documents that don't need chunking
-        if document.len() <= self.min_chunk_size {
-            return vec![self.create_chunk(document)];
-        }
-
-        // Find all chunk boundaries
-        let boundaries = self.find_chunk_boundaries(document);
+        self.chunks(document).collect()
+    }
 
-        // No boundaries found, just return the whole document
-        if boundaries.is_empty() {
-            return vec![self.create_chunk(document)];
+    /// Splits a document into content-defined chunks as they are consumed,
+    /// so a very large document is never held as chunks all at once. Yields
+    /// the same chunks as [`chunk_document`](Self::chunk_document).
+    pub fn chunks<'a>(&'a self, document: &'a str) -> Chunks<'a> {
+        Chunks {
+            chunker: self,
+            document,
+            start: 0,
+            done: false,
         }
+    }
 
-        // Create chunks from the boundaries
-        let mut chunks = Vec::with_capacity(boundaries.len());
-        let mut start_idx = 0;
-
-        for boundary in boundaries {
-            let chunk_content = &document[start_idx..boundary];
-            chunks.push(self.create_chunk(chunk_content));
-            start_idx = boundary;
+    /// Splits a document into the chunks worth embedding: all of them, minus
+    /// generated boilerplate such as trait implementation lists unless that
+    /// filter is off. A document made only of boilerplate keeps all its
+    /// chunks, so it still gets an embedding.
+    pub fn embeddable_chunks(&self, document: &str) -> Vec<Chunk> {
+        let chunks = if document.len() < self.min_chunk_size {
+            vec![self.create_chunk(document)]
+        } else {
+            self.chunk_document(document)
+        };
+        if !self.skip_boilerplate {
+            return chunks;
         }
-
-        chunks
+        let kept: Vec<Chunk> = chunks
+            .iter()
+            .filter(|chunk| !boilerplate::is_boilerplate(&chunk.content))
+            .cloned()
+            .collect();
+        if kept.is_empty() { chunks } else { kept }
     }
 
     /// Generates a stable unique identifier for a chunk based on its content
     pub fn generate_chunk_id(&self, content: &str) -> String {
-        // Use SHA-256 for content-based ID
-        let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
-        let result = hasher.finalize();
-        format!("{:x}", result)
+        chunk_id_with_prefix(&self.id_prefix, content)
     }
 
     /// Alternative method using FNV hasher (faster but less collision-resistant)
@@ -164,6 +211,60 @@ impl DocumentChunker {
     }
 }
 
+/// Chunks of a document, found as they are consumed; see
+/// [`DocumentChunker::chunks`]
+#[derive(Debug, Clone)]
+pub struct Chunks<'a> {
+    chunker: &'a DocumentChunker,
+    document: &'a str,
+    start: usize,
+    done: bool,
+}
+
+impl Iterator for Chunks<'_> {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Chunk> {
+        if self.done {
+            return None;
+        }
+        // Small documents, including empty ones, are a single chunk
+        let end = if self.document.len() <= self.chunker.min_chunk_size {
+            self.document.len()
+        } else {
+            self.chunker.next_boundary(self.document, self.start)
+        };
+        let chunk = self.chunker.create_chunk(&self.document[self.start..end]);
+        self.start = end;
+        self.done = end == self.document.len();
+        Some(chunk)
+    }
+}
+
+/// The id of a chunk of `content` made by a chunker whose ids start with
+/// `prefix`: the prefix followed by the SHA-256 of the content
+pub fn chunk_id_with_prefix(prefix: &str, content: &str) -> String {
+    format!("{}{:x}", prefix, Sha256::digest(content.as_bytes()))
+}
+
+/// Returns the scheme and parameters prefix of `id` if it is a chunk id of
+/// the current [`CHUNK_ID_VERSION`], or `None` for ids of older schemes,
+/// including the bare SHA-256 ids used before ids were versioned
+pub fn chunk_id_prefix(id: &str) -> Option<&str> {
+    let rest = id.strip_prefix(&format!("v{}-", CHUNK_ID_VERSION))?;
+    let (params_hash, content_hash) = rest.split_once('-')?;
+    let is_hex =
+        |text: &str, len: usize| text.len() == len && text.bytes().all(|b| b.is_ascii_hexdigit());
+    (is_hex(params_hash, PARAMS_HASH_LEN) && is_hex(content_hash, 64))
+        .then(|| &id[..id.len() - content_hash.len()])
+}
+
+/// Returns the content hash part of a chunk id, the whole id for bare
+/// SHA-256 ids
+pub fn chunk_id_hash(id: &str) -> &str {
+    id.rsplit('-').next().unwrap_or(id)
+}
+
 impl Default for DocumentChunker {
     fn default() -> Self {
         Self::new()
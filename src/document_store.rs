@@ -0,0 +1,148 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use tempfile::NamedTempFile;
+use tracing::debug;
+
+use crate::doc_loader::Document;
+
+/// Where the text of a loaded crate's documents lives.
+///
+/// Retrieval only needs embeddings and paths; the text of the few matching
+/// documents is read back afterwards. Crates whose text exceeds the memory
+/// budget are therefore spilled to a temporary file and re-read on demand.
+#[derive(Debug, Clone)]
+pub enum DocumentStore {
+    /// All document text is held in memory
+    InMemory(Vec<Document>),
+    /// Document text is kept in a temporary file, removed when the last
+    /// clone of the store is dropped
+    Spilled(Arc<SpillFile>),
+}
+
+/// Document text written to disk, addressed by byte range
+#[derive(Debug)]
+pub struct SpillFile {
+    file: NamedTempFile,
+    entries: Vec<SpilledDocument>,
+}
+
+#[derive(Debug)]
+struct SpilledDocument {
+    path: String,
+    offset: u64,
+    len: u64,
+}
+
+impl DocumentStore {
+    /// Keeps `documents` in memory unless their total text exceeds
+    /// `max_bytes`, in which case the text is spilled to a file in `spill_dir`
+    pub fn new(
+        documents: Vec<Document>,
+        max_bytes: Option<u64>,
+        spill_dir: &Path,
+    ) -> io::Result<Self> {
+        let total: u64 = documents.iter().map(|doc| doc.content.len() as u64).sum();
+        match max_bytes {
+            Some(max_bytes) if total > max_bytes => {
+                debug!(
+                    "Spilling {} documents ({} bytes) to disk to stay under {} bytes",
+                    documents.len(),
+                    total,
+                    max_bytes
+                );
+                Self::spill(documents, spill_dir)
+            }
+            _ => Ok(Self::InMemory(documents)),
+        }
+    }
+
+    fn spill(documents: Vec<Document>, spill_dir: &Path) -> io::Result<Self> {
+        let mut file = tempfile::Builder::new()
+            .prefix("rustdocs-mcp-spill-")
+            .tempfile_in(spill_dir)?;
+        let mut entries = Vec::with_capacity(documents.len());
+        let mut offset = 0;
+        for doc in documents {
+            file.write_all(doc.content.as_bytes())?;
+            let len = doc.content.len() as u64;
+            entries.push(SpilledDocument {
+                path: doc.path,
+                offset,
+                len,
+            });
+            offset += len;
+        }
+        file.flush()?;
+        Ok(Self::Spilled(Arc::new(SpillFile { file, entries })))
+    }
+
+    /// Number of documents in the store
+    pub fn len(&self) -> usize {
+        match self {
+            Self::InMemory(documents) => documents.len(),
+            Self::Spilled(spill) => spill.entries.len(),
+        }
+    }
+
+    /// Returns true if the store holds no documents
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if document text lives on disk
+    pub fn is_spilled(&self) -> bool {
+        matches!(self, Self::Spilled(_))
+    }
+
+    /// Bytes of document text held in memory
+    pub fn memory_bytes(&self) -> usize {
+        match self {
+            Self::InMemory(documents) => documents.iter().map(|doc| doc.content.len()).sum(),
+            Self::Spilled(_) => 0,
+        }
+    }
+
+    /// Returns every document in the store, reading spilled text back from disk
+    pub fn documents(&self) -> io::Result<Vec<Document>> {
+        match self {
+            Self::InMemory(documents) => Ok(documents.clone()),
+            Self::Spilled(spill) => {
+                let mut file = File::open(spill.file.path())?;
+                let mut documents = Vec::with_capacity(spill.entries.len());
+                for entry in &spill.entries {
+                    file.seek(SeekFrom::Start(entry.offset))?;
+                    let mut content = String::with_capacity(entry.len as usize);
+                    (&mut file).take(entry.len).read_to_string(&mut content)?;
+                    documents.push(Document {
+                        path: entry.path.clone(),
+                        content,
+                    });
+                }
+                Ok(documents)
+            }
+        }
+    }
+
+    /// Returns the document at `path`, reading its text back from disk if spilled
+    pub fn get(&self, path: &str) -> io::Result<Option<Document>> {
+        match self {
+            Self::InMemory(documents) => Ok(documents.iter().find(|doc| doc.path == path).cloned()),
+            Self::Spilled(spill) => {
+                let Some(entry) = spill.entries.iter().find(|entry| entry.path == path) else {
+                    return Ok(None);
+                };
+                let mut file = File::open(spill.file.path())?;
+                file.seek(SeekFrom::Start(entry.offset))?;
+                let mut content = String::with_capacity(entry.len as usize);
+                file.take(entry.len).read_to_string(&mut content)?;
+                Ok(Some(Document {
+                    path: entry.path.clone(),
+                    content,
+                }))
+            }
+        }
+    }
+}
@@ -1,29 +1,199 @@
-use reqwest::Client;
+use futures::{StreamExt, TryStreamExt, stream};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::fs;
 use std::io::{Error, ErrorKind};
 use std::path::{Path, PathBuf};
-
-use crate::document_chunker::DocumentChunker;
-use crate::embeddings::{Embedding, EmbeddingProvider};
-use crate::error::Result;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Semaphore;
+use tracing::{debug, info, instrument, warn};
+
+use crate::boilerplate;
+use crate::budget::{Spend, UsageBudget};
+use crate::cache_manager;
+use crate::chunk_context;
+use crate::config::{Config, EmbeddingInputTypes};
+use crate::document_chunker::{DocumentChunker, chunk_id_with_prefix};
+use crate::embeddings::{Embedding, EmbeddingInputType, EmbeddingProvider, l2_norm};
+use crate::error::{Result, ServerError};
+use crate::mock::{MOCK_MODEL, MOCK_PROVIDER, MockEmbeddingBackend};
+use crate::openai_client::OpenAiClient;
+use crate::platform_paths::{self, ProjectDirs};
+use crate::redaction::Redactor;
+use crate::utils::with_context;
 
 #[derive(Debug)]
 pub struct EmbeddingCacheService {
-    cache_dir: PathBuf,
-    client: Client,
-    openai_api_key: String,
+    // None if no cache directory could be created; embeddings then only live
+    // in `memory_cache` for the lifetime of the process
+    cache_dir: Option<PathBuf>,
+    memory_cache: Mutex<HashMap<String, CachedEmbedding>>,
+    client: OpenAiClient,
     chunker: DocumentChunker,
+    model: String,
+    offline: bool,
+    concurrency: usize,
+    // Bounds embedding API calls across all documents and chunks
+    api_permits: Semaphore,
+    // Replaces the API when the embedding provider is `mock`
+    mock: Option<MockEmbeddingBackend>,
+    // Scrubs text sent to the provider; cached documents keep the original
+    redactor: Redactor,
+    // Recent question embeddings, so agents repeating a question in a loop
+    // don't pay for a cache lookup or API call each time
+    questions: Mutex<QuestionCache>,
+    // Sent with each request for providers that tell documents and queries apart
+    input_types: Option<EmbeddingInputTypes>,
+    // Limits the tokens embedded; shared with the chat client
+    budget: UsageBudget,
+    // Chunk embeddings read from the cache and generated, for telemetry
+    chunk_hits: AtomicU64,
+    chunk_misses: AtomicU64,
 }
 
-#[derive(Serialize, Deserialize)]
-struct CachedEmbedding {
-    vector: Vec<f32>, // This remains 'vector' for serialization
-    document: String,
-    model: String,
-    provider: EmbeddingProvider,
+/// Default maximum number of embedding requests in flight at once
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Default number of question embeddings kept in memory
+pub const DEFAULT_QUESTION_CACHE_SIZE: usize = 256;
+
+/// Directory inside the chunk cache that unusable entries are moved to
+pub const QUARANTINE_DIR_NAME: &str = "quarantine";
+
+/// Most chunks of one document embedded at once; a document's chunks are
+/// found a batch at a time, so huge pages don't hold all theirs in memory
+const CHUNKS_PER_BATCH: usize = 64;
+
+/// Running mean of a document's chunk embeddings
+#[derive(Debug, Default)]
+struct ChunkAverage {
+    first: Option<Embedding>,
+    sum: Vec<f32>,
+    count: usize,
+}
+
+impl ChunkAverage {
+    fn add(&mut self, embedding: Embedding) -> Result<()> {
+        if self.count == 0 {
+            self.sum = vec![0.0; embedding.dimensions];
+        } else if embedding.dimensions != self.sum.len() {
+            return Err(ServerError::EmbeddingDimensionMismatch {
+                expected: self.sum.len(),
+                actual: embedding.dimensions,
+            });
+        }
+        for (total, value) in self.sum.iter_mut().zip(&embedding.values) {
+            *total += value;
+        }
+        self.count += 1;
+        if self.count == 1 {
+            self.first = Some(embedding);
+        }
+        Ok(())
+    }
+
+    /// The first embedding if there was only one, else the normalized mean
+    fn finish(self) -> Option<Embedding> {
+        let first = self.first?;
+        if self.count == 1 {
+            return Some(first);
+        }
+        let count = self.count as f32;
+        let mean = self.sum.into_iter().map(|total| total / count).collect();
+        Some(Embedding::new(mean, EmbeddingProvider::OpenAI, first.model).normalized())
+    }
+}
+
+/// Least-recently-used question embeddings, keyed by normalized question hash
+#[derive(Debug)]
+struct QuestionCache {
+    capacity: usize,
+    entries: HashMap<String, Embedding>,
+    // Keys from least to most recently used
+    order: VecDeque<String>,
+}
+
+impl QuestionCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Embedding> {
+        let embedding = self.entries.get(key)?.clone();
+        self.touch(key);
+        Some(embedding)
+    }
+
+    fn insert(&mut self, key: String, embedding: Embedding) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key.clone(), embedding).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(position);
+            self.order.extend(key);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedEmbedding {
+    pub(crate) vector: Vec<f32>, // This remains 'vector' for serialization
+    pub(crate) document: String,
+    pub(crate) model: String,
+    pub(crate) provider: EmbeddingProvider,
+    /// L2 norm of `vector`; missing from entries written by older versions,
+    /// which get it added the first time they are read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) norm: Option<f32>,
+    /// Input type sent with the text, if the provider was told one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) input_type: Option<String>,
+}
+
+impl CachedEmbedding {
+    /// Returns the name the entry is cached under
+    pub(crate) fn key(&self, chunker: &DocumentChunker) -> String {
+        self.key_with_prefix(chunker.id_prefix())
+    }
+
+    /// Returns the name the entry is cached under by a chunker whose chunk
+    /// ids start with `prefix`
+    pub(crate) fn key_with_prefix(&self, prefix: &str) -> String {
+        typed_chunk_id(
+            prefix,
+            &chunk_id_with_prefix(prefix, &self.document),
+            self.input_type.as_deref(),
+        )
+    }
+}
+
+/// Returns the key a chunk's embedding is cached under by a chunker whose
+/// chunk ids start with `prefix`. The input type is part of the key, as the
+/// same text embeds differently as a document and as a query.
+fn typed_chunk_id(prefix: &str, chunk_id: &str, input_type: Option<&str>) -> String {
+    match input_type {
+        Some(input_type) => chunk_id_with_prefix(prefix, &format!("{}\0{}", input_type, chunk_id)),
+        None => chunk_id.to_string(),
+    }
 }
 
 /// Safely creates a directory and all parent directories if they don't exist
@@ -42,193 +212,627 @@ fn ensure_dir_exists(path: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Returns the default location of the chunk embedding cache, first moving
+/// entries over from where earlier versions kept them
+pub fn default_cache_dir() -> Result<PathBuf> {
+    let dirs = ProjectDirs::new()?;
+    let dir = dirs.embedding_cache_dir().to_path_buf();
+    if let Some(legacy) = dirs
+        .legacy_embedding_cache_dir()
+        .filter(|legacy| legacy.is_dir())
+    {
+        match migrate_cache_dir(legacy, &dir) {
+            Ok(moved) => info!(
+                "Moved {} cached chunk embeddings from {} to {}",
+                moved,
+                legacy.display(),
+                dir.display()
+            ),
+            Err(e) => warn!(
+                "Failed to move the chunk embedding cache from {} to {}: {}",
+                legacy.display(),
+                dir.display(),
+                e
+            ),
+        }
+    }
+    Ok(dir)
+}
+
+/// Moves every entry of the cache directory `from` into `to` and removes
+/// `from`, returning the number of entries moved. Entries already in `to`
+/// are kept, as chunk entries are named after their content.
+pub fn migrate_cache_dir(from: &Path, to: &Path) -> std::io::Result<usize> {
+    if !from.is_dir() {
+        return Ok(0);
+    }
+    let files: Vec<PathBuf> = walkdir::WalkDir::new(from)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect();
+
+    // Within one file system the whole directory can simply be renamed
+    if !to.exists() {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::rename(from, to).is_ok() {
+            return Ok(files.len());
+        }
+    }
+
+    let mut moved = 0;
+    for file in files {
+        let Ok(relative) = file.strip_prefix(from) else {
+            continue;
+        };
+        let target = to.join(relative);
+        if target.exists() {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::rename(&file, &target).is_err() {
+            fs::copy(&file, &target)?;
+        }
+        moved += 1;
+    }
+    fs::remove_dir_all(from)?;
+    Ok(moved)
+}
+
+/// Picks a usable chunk cache directory: `configured` (or the default under
+/// the XDG cache directory), then one under the system temp directory. Returns
+/// `None` if neither can be created, so the server can still start in
+/// containers without a writable home.
+fn open_cache_dir(configured: Option<PathBuf>) -> Option<PathBuf> {
+    let preferred = match configured {
+        Some(dir) => Ok(dir),
+        None => default_cache_dir(),
+    };
+    match preferred.and_then(|dir| ensure_dir_exists(&dir).map(|_| dir).map_err(Into::into)) {
+        Ok(dir) => return Some(dir),
+        Err(e) => warn!(
+            "Embedding cache directory unavailable ({}); falling back to a temporary directory",
+            e
+        ),
+    }
+
+    let fallback = env::temp_dir().join("rust-doc-embedding-cache");
+    match ensure_dir_exists(&fallback) {
+        Ok(()) => Some(fallback),
+        Err(e) => {
+            warn!(
+                "Temporary embedding cache {} unavailable ({}); caching embeddings in memory only",
+                fallback.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Opens the chunk cache directory like [`open_cache_dir`] and moves entries
+/// cached under an older chunk id scheme to the ids `chunker` gives them, see
+/// [`cache_manager::migrate`]
+fn open_chunk_cache(configured: Option<PathBuf>, chunker: &DocumentChunker) -> Option<PathBuf> {
+    let dir = open_cache_dir(configured)?;
+    match cache_manager::migrate(&dir, chunker) {
+        Ok(report) if report.migrated.entries + report.removed.entries > 0 => info!("{}", report),
+        Ok(_) => {}
+        Err(e) => warn!(
+            "Failed to migrate chunk embeddings in {}: {}",
+            dir.display(),
+            e
+        ),
+    }
+    Some(dir)
+}
+
 impl EmbeddingCacheService {
     pub fn new(openai_api_key: String) -> Result<Self> {
-        let home_dir = dirs::home_dir().ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Could not find home directory",
-            )
-        })?;
-
-        let cache_dir = home_dir.join(".rust-doc-embedding-cache");
-        ensure_dir_exists(&cache_dir)?;
+        Self::with_config(openai_api_key, &Config::from_env())
+    }
 
-        Ok(Self {
-            cache_dir,
-            client: Client::new(),
-            openai_api_key,
-            chunker: DocumentChunker::new(),
-        })
+    /// Creates a new service using the cache location, chunker parameters and
+    /// embedding model from the configuration
+    pub fn with_config(openai_api_key: String, config: &Config) -> Result<Self> {
+        Self::from_parts(openai_api_key, config, config.chunker())
     }
 
     /// Creates a new service with custom chunker parameters
-    #[allow(dead_code)]
     pub fn with_chunker_params(
         openai_api_key: String,
         min_size: usize,
         target_size: usize,
         max_size: usize,
     ) -> Result<Self> {
-        let home_dir = dirs::home_dir().ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Could not find home directory",
-            )
-        })?;
-
-        let cache_dir = home_dir.join(".rust-doc-embedding-cache");
-        ensure_dir_exists(&cache_dir)?;
+        let chunker = DocumentChunker::with_params(min_size, target_size, max_size);
+        Self::from_parts(openai_api_key, &Config::from_env(), chunker)
+    }
 
+    /// Creates a service from the configuration that splits documents with
+    /// `chunker`
+    fn from_parts(
+        openai_api_key: String,
+        config: &Config,
+        chunker: DocumentChunker,
+    ) -> Result<Self> {
+        // Mock embeddings are cheap to recompute and must never end up in the
+        // on-disk cache next to real ones
+        let mock = (config.embedding.provider == MOCK_PROVIDER).then(MockEmbeddingBackend::default);
+        let (cache_dir, model) = match mock {
+            Some(_) => (None, MOCK_MODEL.to_string()),
+            None => (
+                open_chunk_cache(config.cache.dir.clone(), &chunker),
+                config.embedding.model.clone(),
+            ),
+        };
         Ok(Self {
             cache_dir,
-            client: Client::new(),
-            openai_api_key,
-            chunker: DocumentChunker::with_params(min_size, target_size, max_size),
+            memory_cache: Mutex::new(HashMap::new()),
+            client: OpenAiClient::new(
+                openai_api_key,
+                config.embedding.api_base.as_deref(),
+                &config.embedding.headers,
+                &config.http,
+            )?
+            .with_account(&config.openai)?,
+            chunker,
+            model,
+            offline: config.offline,
+            concurrency: config.embedding.concurrency,
+            api_permits: Semaphore::new(config.embedding.concurrency),
+            mock,
+            redactor: Redactor::new(&config.redaction)?,
+            questions: Mutex::new(QuestionCache::new(config.embedding.question_cache_size)),
+            input_types: config.embedding.input_types.clone(),
+            budget: UsageBudget::new(&config.budget),
+            chunk_hits: AtomicU64::new(0),
+            chunk_misses: AtomicU64::new(0),
         })
     }
 
-    /// Compute the cache path for a chunk based on its ID
-    fn cache_path(&self, chunk_id: &str) -> PathBuf {
-        self.cache_dir.join(chunk_id)
+    /// Sends `api_key` with all further embedding requests
+    pub fn set_api_key(&self, api_key: String) {
+        self.client.set_api_key(api_key);
     }
 
-    /// Get embedding for a document by chunking it first
-    pub async fn get_embedding(&self, document: &str) -> Result<Embedding> {
-        // For small documents, don't bother chunking
-        if document.len() < self.chunker.min_chunk_size() {
-            return self.get_embedding_for_chunk(document).await;
-        }
+    /// Returns the directory chunk embeddings are cached in, or `None` if
+    /// they are only cached in memory
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
 
-        // Use chunking for larger documents
-        let chunks = self.chunker.chunk_document(document);
+    /// Returns the embedding model requested from the provider
+    pub fn model(&self) -> &str {
+        &self.model
+    }
 
-        // If there's only one chunk, process it directly
-        if chunks.len() == 1 {
-            return self.get_embedding_for_chunk(&chunks[0].content).await;
-        }
+    /// Returns the rules applied to text before it is sent to a provider
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
 
-        // Process all chunks and combine their embeddings
-        let mut chunk_embeddings = HashMap::new();
-        for chunk in chunks {
-            let cache_path = self.cache_path(&chunk.id);
+    /// Returns the budget provider requests are charged to, shared with chat
+    pub fn budget(&self) -> &UsageBudget {
+        &self.budget
+    }
 
-            let embedding = if cache_path.exists() {
-                self.read_cached_embedding(&cache_path, &chunk.content)?
-            } else {
-                self.generate_and_cache_embedding(&chunk.content, &cache_path)
-                    .await?
-            };
+    /// Returns true if cache misses fail instead of calling the embedding API
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Returns the chunker used to split documents before embedding
+    pub fn chunker(&self) -> &DocumentChunker {
+        &self.chunker
+    }
 
-            chunk_embeddings.insert(chunk.id, embedding);
+    /// Compute the cache path for a chunk based on its ID (just the ID when
+    /// caching in memory)
+    fn cache_path(&self, chunk_id: &str) -> PathBuf {
+        match &self.cache_dir {
+            Some(dir) => platform_paths::long_path(&dir.join(chunk_id)),
+            None => PathBuf::from(chunk_id),
         }
+    }
 
-        // Return the combined embedding (average all chunk embeddings)
-        self.combine_chunk_embeddings(chunk_embeddings)
+    /// Returns the value sent as the input type of texts embedded as
+    /// `input_type`, if input types are configured
+    fn input_type_value(&self, input_type: EmbeddingInputType) -> Option<&str> {
+        self.input_types
+            .as_ref()
+            .map(|input_types| input_types.value(input_type))
     }
 
-    /// Get embedding for a single chunk of content
-    pub async fn get_embedding_for_chunk(&self, chunk_content: &str) -> Result<Embedding> {
-        // Generate chunk ID
-        let chunk_id = self.chunker.generate_chunk_id(chunk_content);
-        let cache_path = self.cache_path(&chunk_id);
+    /// Returns the key a chunk's embedding is cached under
+    fn cache_key(&self, chunk_id: &str, input_type: EmbeddingInputType) -> String {
+        typed_chunk_id(
+            self.chunker.id_prefix(),
+            chunk_id,
+            self.input_type_value(input_type),
+        )
+    }
+
+    /// Returns the keys of the chunk cache entries `document` is embedded
+    /// from, the same way [`get_embedding`](Self::get_embedding) looks them up
+    pub fn chunk_keys(&self, document: &str) -> Vec<String> {
+        self.chunker
+            .embeddable_chunks(document)
+            .iter()
+            .map(|chunk| self.cache_key(&chunk.id, EmbeddingInputType::Document))
+            .collect()
+    }
 
-        if cache_path.exists() {
-            return self.read_cached_embedding(&cache_path, chunk_content);
+    /// Compares the chunks a document's `old` and `new` text are embedded
+    /// from, as `(unchanged, added, removed)` chunk counts. Only added chunks
+    /// need new embeddings.
+    pub fn chunk_changes(&self, old: &str, new: &str) -> (usize, usize, usize) {
+        let old: HashSet<String> = self.chunk_keys(old).into_iter().collect();
+        let new: HashSet<String> = self.chunk_keys(new).into_iter().collect();
+        let unchanged = old.intersection(&new).count();
+        (unchanged, new.len() - unchanged, old.len() - unchanged)
+    }
+
+    /// Removes the chunk cache entries with the given keys, as listed by
+    /// [`chunk_keys`](Self::chunk_keys). Returns the number removed.
+    pub fn remove_chunks(&self, keys: &[String]) -> usize {
+        if self.cache_dir.is_none() {
+            let mut memory_cache = self.memory_cache.lock().unwrap_or_else(|e| e.into_inner());
+            return keys
+                .iter()
+                .filter(|key| memory_cache.remove(*key).is_some())
+                .count();
+        }
+        let mut removed = 0;
+        for key in keys {
+            let path = self.cache_path(key);
+            match fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to remove {}: {}", path.display(), e),
+            }
         }
+        removed
+    }
 
-        let embedding = self
-            .generate_and_cache_embedding(chunk_content, &cache_path)
+    /// Get embedding for a document by chunking it first
+    #[instrument(name = "embed", level = "debug", skip_all, fields(bytes = document.len()))]
+    pub async fn get_embedding(&self, document: &str) -> Result<Embedding> {
+        self.embed_as(document, EmbeddingInputType::Document).await
+    }
+
+    /// Embeds `text` as `input_type`, chunking it if it is large
+    async fn embed_as(&self, text: &str, input_type: EmbeddingInputType) -> Result<Embedding> {
+        // For small texts, don't bother chunking
+        if text.len() < self.chunker.min_chunk_size() {
+            let chunk_id = self.chunker.generate_chunk_id(text);
+            return self.cached_or_generate(&chunk_id, text, input_type).await;
+        }
+
+        // Documents leave out boilerplate, unless that is all they are made of
+        let skip_boilerplate =
+            input_type == EmbeddingInputType::Document && self.chunker.skips_boilerplate();
+        let mut embedding = self
+            .embed_chunks(text, input_type, skip_boilerplate)
             .await?;
-        Ok(embedding)
+        if embedding.is_none() && skip_boilerplate {
+            embedding = self.embed_chunks(text, input_type, false).await?;
+        }
+        embedding.ok_or_else(|| ServerError::EmbeddingCache {
+            path: self.cache_path(""),
+            reason: "no chunk embeddings to combine".to_string(),
+        })
     }
 
-    /// Combine multiple chunk embeddings into a single document embedding
-    fn combine_chunk_embeddings(
+    /// Embeds the chunks of `text` a batch at a time as the chunker finds
+    /// them, so a very large document is never held as chunks all at once,
+    /// and averages their embeddings. A single chunk's embedding is returned
+    /// as is; `None` means every chunk was left out as boilerplate.
+    async fn embed_chunks(
         &self,
-        chunk_embeddings: HashMap<String, Embedding>,
-    ) -> Result<Embedding> {
-        if chunk_embeddings.is_empty() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "No chunk embeddings to combine",
-            )
-            .into());
+        text: &str,
+        input_type: EmbeddingInputType,
+        skip_boilerplate: bool,
+    ) -> Result<Option<Embedding>> {
+        let mut chunks = self
+            .chunker
+            .chunks(text)
+            .filter(|chunk| !skip_boilerplate || !boilerplate::is_boilerplate(&chunk.content));
+        // Repeated chunks count once
+        let mut seen = HashSet::new();
+        let mut average = ChunkAverage::default();
+        loop {
+            // The futures are built up front so the stream doesn't borrow a
+            // closure argument, which would make the future non-`Send`
+            let pending: Vec<_> = chunks
+                .by_ref()
+                .filter(|chunk| seen.insert(chunk.id.clone()))
+                .take(CHUNKS_PER_BATCH.max(self.concurrency))
+                .map(|chunk| self.get_chunk_embedding(chunk.id, chunk.content, input_type))
+                .collect();
+            if pending.is_empty() {
+                return Ok(average.finish());
+            }
+            let mut batch = stream::iter(pending).buffer_unordered(self.concurrency);
+            while let Some((_, embedding)) = batch.try_next().await? {
+                average.add(embedding)?;
+            }
         }
+    }
 
-        // Ensure all embeddings have the same dimensionality
-        let first_embedding = chunk_embeddings.values().next().unwrap();
-        let dim = first_embedding.dimensions;
-        let model = first_embedding.model.clone();
+    /// Gets the embedding of a search question. Questions differing only in
+    /// whitespace share an embedding, and recent ones are answered from memory.
+    pub async fn get_question_embedding(&self, question: &str) -> Result<Embedding> {
+        let (normalized, key) = self.question_key(question);
+        if let Some(embedding) = self.questions.lock().ok().and_then(|mut q| q.get(&key)) {
+            debug!("Question embedding served from memory");
+            return Ok(embedding);
+        }
 
-        // Initialize sum vector with zeros
-        let mut sum_vector = vec![0.0; dim];
+        let embedding = self
+            .embed_as(&normalized, EmbeddingInputType::Query)
+            .await?;
+        if let Ok(mut questions) = self.questions.lock() {
+            questions.insert(key, embedding.clone());
+        }
+        Ok(embedding)
+    }
 
-        // Sum all vectors
-        for embedding in chunk_embeddings.values() {
-            if embedding.dimensions != dim {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Cannot combine embeddings with different dimensions",
-                )
-                .into());
+    /// Moves the chunk cache entries `document` is embedded from whose vectors
+    /// don't have `dimensions` values, or can't be read, into the cache's
+    /// `quarantine` directory, so they are regenerated when next needed.
+    /// Returns where the entries were; entries cached in memory are dropped.
+    pub fn quarantine_chunks(&self, document: &str, dimensions: usize) -> Vec<PathBuf> {
+        let mut quarantined = Vec::new();
+        for key in self.chunk_keys(document) {
+            let path = self.cache_path(&key);
+            let Some(dir) = &self.cache_dir else {
+                let mut memory_cache = self.memory_cache.lock().unwrap_or_else(|e| e.into_inner());
+                if memory_cache
+                    .get(&key)
+                    .is_some_and(|cached| cached.vector.len() != dimensions)
+                {
+                    memory_cache.remove(&key);
+                    quarantined.push(path);
+                }
+                continue;
+            };
+            // Unreadable entries are quarantined too, missing ones skipped
+            if !path.exists() {
+                continue;
             }
-
-            for (i, val) in embedding.values.iter().enumerate() {
-                sum_vector[i] += val;
+            let matches = fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<CachedEmbedding>(&json).ok())
+                .is_some_and(|cached| cached.vector.len() == dimensions);
+            if matches {
+                continue;
+            }
+            let target = dir.join(QUARANTINE_DIR_NAME).join(&key);
+            let moved = ensure_dir_exists(&dir.join(QUARANTINE_DIR_NAME))
+                .and_then(|_| fs::rename(&path, platform_paths::long_path(&target)));
+            match moved {
+                Ok(()) => quarantined.push(path),
+                Err(e) => warn!("Failed to quarantine {}: {}", path.display(), e),
             }
         }
+        quarantined
+    }
 
-        // Normalize the resulting vector
-        let count = chunk_embeddings.len() as f32;
-        for val in &mut sum_vector {
-            *val /= count;
-        }
+    /// Returns true if [`get_question_embedding`](Self::get_question_embedding)
+    /// would answer `question` from memory
+    pub fn is_question_cached(&self, question: &str) -> bool {
+        let (_, key) = self.question_key(question);
+        self.questions
+            .lock()
+            .is_ok_and(|questions| questions.entries.contains_key(&key))
+    }
+
+    /// Returns a question with its whitespace normalized, and its key in the
+    /// question cache
+    fn question_key(&self, question: &str) -> (String, String) {
+        let normalized = question.split_whitespace().collect::<Vec<_>>().join(" ");
+        let key = self.chunker.generate_chunk_id(&normalized);
+        (normalized, key)
+    }
+
+    /// Numbers of chunk embeddings read from the cache and generated since
+    /// the service was created, as `(hits, misses)`
+    pub fn chunk_cache_counts(&self) -> (u64, u64) {
+        (
+            self.chunk_hits.load(Ordering::Relaxed),
+            self.chunk_misses.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Gets the embeddings of several questions, embedding up to the
+    /// configured concurrency at once. Results are in input order.
+    pub async fn get_question_embeddings(&self, questions: &[&str]) -> Vec<Result<Embedding>> {
+        let pending: Vec<_> = questions
+            .iter()
+            .map(|question| self.get_question_embedding(question))
+            .collect();
+        stream::iter(pending)
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Returns the number of question embeddings held in memory
+    pub fn cached_question_count(&self) -> usize {
+        self.questions.lock().map(|q| q.entries.len()).unwrap_or(0)
+    }
+
+    /// Gets embeddings for several documents, embedding up to the configured
+    /// number of uncached chunks at once. Results are in input order.
+    pub async fn get_embeddings(&self, documents: &[&str]) -> Result<Vec<Embedding>> {
+        self.try_get_embeddings(documents)
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Like [`get_embeddings`](Self::get_embeddings), but returns each
+    /// document's result separately so one failure doesn't discard the rest
+    pub async fn try_get_embeddings(&self, documents: &[&str]) -> Vec<Result<Embedding>> {
+        let pending: Vec<_> = documents
+            .iter()
+            .map(|document| self.get_embedding(document))
+            .collect();
+        stream::iter(pending)
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Reads a chunk's embedding from the cache or generates it
+    async fn get_chunk_embedding(
+        &self,
+        chunk_id: String,
+        content: String,
+        input_type: EmbeddingInputType,
+    ) -> Result<(String, Embedding)> {
+        let embedding = self
+            .cached_or_generate(&chunk_id, &content, input_type)
+            .await?;
+        Ok((chunk_id, embedding))
+    }
 
-        // Normalize to unit length
-        let magnitude: f32 = sum_vector.iter().map(|v| v * v).sum::<f32>().sqrt();
-        if magnitude > 0.0 {
-            for val in &mut sum_vector {
-                *val /= magnitude;
+    /// Get embedding for a single chunk of content
+    pub async fn get_embedding_for_chunk(&self, chunk_content: &str) -> Result<Embedding> {
+        // Generate chunk ID
+        let chunk_id = self.chunker.generate_chunk_id(chunk_content);
+        self.cached_or_generate(&chunk_id, chunk_content, EmbeddingInputType::Document)
+            .await
+    }
+
+    /// Reads a chunk's embedding from the cache, regenerating it if the entry
+    /// is missing, unreadable or belongs to different content
+    async fn cached_or_generate(
+        &self,
+        chunk_id: &str,
+        content: &str,
+        input_type: EmbeddingInputType,
+    ) -> Result<Embedding> {
+        let key = self.cache_key(chunk_id, input_type);
+        match self.read_cached_embedding(&key, content) {
+            Ok(Some(embedding)) => {
+                self.chunk_hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(embedding);
+            }
+            Ok(None) => {}
+            Err(ServerError::EmbeddingCache { path, reason }) => {
+                debug!(
+                    "Regenerating cached embedding {}: {}",
+                    path.display(),
+                    reason
+                )
             }
+            Err(e) => return Err(e),
         }
-
-        Ok(Embedding::new(sum_vector, EmbeddingProvider::OpenAI, model))
+        self.chunk_misses.fetch_add(1, Ordering::Relaxed);
+        with_context(
+            self.generate_and_cache_embedding(&key, content, input_type)
+                .await,
+            || format!("Failed to embed chunk {}", key),
+        )
     }
 
-    fn read_cached_embedding(&self, path: &Path, original_document: &str) -> Result<Embedding> {
-        let cached_data = fs::read_to_string(path)?;
-        let cached: CachedEmbedding = serde_json::from_str(&cached_data)?;
+    /// Returns the cached embedding of a chunk, or `None` on a cache miss
+    fn read_cached_embedding(
+        &self,
+        chunk_id: &str,
+        original_document: &str,
+    ) -> Result<Option<Embedding>> {
+        let path = self.cache_path(chunk_id);
+        let cached: CachedEmbedding = if self.cache_dir.is_some() {
+            if !path.exists() {
+                return Ok(None);
+            }
+            let cached_data = with_context(fs::read_to_string(&path), || {
+                format!("Failed to read cached embedding {}", path.display())
+            })?;
+            serde_json::from_str(&cached_data).map_err(|e| ServerError::EmbeddingCache {
+                path: path.clone(),
+                reason: format!("unreadable cache entry: {}", e),
+            })?
+        } else {
+            let memory_cache = self.memory_cache.lock().unwrap_or_else(|e| e.into_inner());
+            match memory_cache.get(chunk_id) {
+                Some(cached) => cached.clone(),
+                None => return Ok(None),
+            }
+        };
 
         // Verify document matches to prevent hash collisions
         if cached.document != original_document {
-            // Document changed, need to regenerate
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Cached document doesn't match input",
-            )
-            .into());
+            return Err(ServerError::EmbeddingCache {
+                path,
+                reason: "cached document doesn't match input".to_string(),
+            });
         }
 
-        // Clone the vector to avoid moving it
-        let vector_clone = cached.vector.clone();
-        let dimensions = cached.vector.len();
+        let norm = match cached.norm {
+            Some(norm) => norm,
+            None => {
+                let norm = l2_norm(&cached.vector);
+                if self.cache_dir.is_some() {
+                    let migrated = CachedEmbedding {
+                        norm: Some(norm),
+                        ..cached.clone()
+                    };
+                    if let Err(e) = serde_json::to_string(&migrated)
+                        .map_err(ServerError::from)
+                        .and_then(|json| fs::write(&path, json).map_err(ServerError::from))
+                    {
+                        debug!("Failed to add the norm to {}: {}", path.display(), e);
+                    }
+                }
+                norm
+            }
+        };
 
-        Ok(Embedding {
-            values: vector_clone,
-            provider: cached.provider,
-            model: cached.model,
-            dimensions,
-        })
+        Ok(Some(Embedding::with_norm(
+            cached.vector,
+            norm,
+            cached.provider,
+            cached.model,
+        )))
     }
 
     async fn generate_and_cache_embedding(
         &self,
+        chunk_id: &str,
         document: &str,
-        cache_path: &Path,
+        input_type: EmbeddingInputType,
     ) -> Result<Embedding> {
+        if let Some(mock) = &self.mock {
+            return Ok(mock.embed(document));
+        }
+        if self.offline {
+            return Err(ServerError::Offline(format!(
+                "no cached embedding for chunk {}; run once without --offline to populate the cache",
+                self.cache_path(chunk_id).display()
+            )));
+        }
+
         // OpenAI API call
-        let embedding = self.generate_openai_embedding(document).await?;
+        let embedding = {
+            let _permit = self
+                .api_permits
+                .acquire()
+                .await
+                .map_err(|e| ServerError::Config(format!("Embedding limiter closed: {}", e)))?;
+            self.budget.check()?;
+            self.generate_openai_embedding(document, input_type)
+                .await?
+                .normalized()
+        };
 
         // Cache the result
         let cached = CachedEmbedding {
@@ -236,71 +840,43 @@ impl EmbeddingCacheService {
             document: document.to_string(),
             model: embedding.model.clone(),
             provider: embedding.provider,
+            norm: Some(embedding.norm),
+            input_type: self.input_type_value(input_type).map(str::to_string),
         };
 
-        let json = serde_json::to_string(&cached)?;
-        fs::write(cache_path, json)?;
+        match &self.cache_dir {
+            Some(_) => {
+                let json = serde_json::to_string(&cached)?;
+                let path = self.cache_path(chunk_id);
+                with_context(fs::write(&path, json), || {
+                    format!("Failed to cache embedding {}", path.display())
+                })?;
+            }
+            None => {
+                let mut memory_cache = self.memory_cache.lock().unwrap_or_else(|e| e.into_inner());
+                memory_cache.insert(chunk_id.to_string(), cached);
+            }
+        }
 
         Ok(embedding)
     }
 
-    async fn generate_openai_embedding(&self, document: &str) -> Result<Embedding> {
-        #[derive(Serialize)]
-        struct EmbeddingRequest {
-            input: String,
-            model: String,
-        }
-
-        #[derive(Deserialize)]
-        struct EmbeddingData {
-            embedding: Vec<f32>,
-        }
-
-        #[derive(Deserialize)]
-        struct EmbeddingResponse {
-            data: Vec<EmbeddingData>,
-            model: String,
-        }
-
-        // Get the embedding model from environment or use default
-        let model =
-            env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string());
-
-        let request = EmbeddingRequest {
-            input: document.to_string(),
-            model: model.clone(),
-        };
-
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/embeddings")
-            .header("Authorization", format!("Bearer {}", self.openai_api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(
-                std::io::Error::other(format!("OpenAI API error: {}", response.status())).into(),
-            );
-        }
-
-        let embedding_response: EmbeddingResponse = response.json().await?;
-
-        // Extract the embedding values from the response
-        if let Some(data) = embedding_response.data.first() {
-            Ok(Embedding::new(
-                data.embedding.clone(),
-                EmbeddingProvider::OpenAI,
-                embedding_response.model,
-            ))
-        } else {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "No embedding data received from OpenAI",
-            )
-            .into())
-        }
+    #[instrument(name = "embedding_request", skip_all, fields(model = %self.model, bytes = document.len()))]
+    async fn generate_openai_embedding(
+        &self,
+        document: &str,
+        input_type: EmbeddingInputType,
+    ) -> Result<Embedding> {
+        debug!("Requesting embedding from the provider");
+        let input = self.redactor.redact(document);
+        let input_type = self
+            .input_types
+            .as_ref()
+            .map(|types| (types.field.as_str(), types.value(input_type)));
+        let (vector, model) = self.client.embed(&self.model, &input, input_type).await?;
+        self.budget.charge(Spend::Embedding {
+            tokens: chunk_context::count_tokens(&input) as u64,
+        });
+        Ok(Embedding::new(vector, EmbeddingProvider::OpenAI, model))
     }
 }
@@ -1,10 +1,5 @@
 use crate::error::ServerError;
-use async_openai::{Client as OpenAIClient, config::OpenAIConfig};
 use ndarray::{Array1, ArrayView1};
-use std::sync::OnceLock;
-
-// Static OnceLock for the OpenAI client
-pub static OPENAI_CLIENT: OnceLock<OpenAIClient<OpenAIConfig>> = OnceLock::new();
 
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
@@ -15,6 +10,7 @@ use std::fmt;
 pub enum EmbeddingProvider {
     OpenAI,
     Onnx,
+    Mock,
     // Can be extended with other providers
 }
 
@@ -23,10 +19,21 @@ impl fmt::Display for EmbeddingProvider {
         match self {
             EmbeddingProvider::OpenAI => write!(f, "OpenAI"),
             EmbeddingProvider::Onnx => write!(f, "ONNX"),
+            EmbeddingProvider::Mock => write!(f, "Mock"),
         }
     }
 }
 
+/// What a text is embedded for. Some providers embed documents and search
+/// queries differently, which improves retrieval when each side is marked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmbeddingInputType {
+    /// A documentation chunk stored in the index
+    Document,
+    /// A question searched for in the index
+    Query,
+}
+
 /// Represents an embedding vector with metadata
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct Embedding {
@@ -38,18 +45,57 @@ pub struct Embedding {
     pub model: String,
     /// Dimension of the embedding vector
     pub dimensions: usize,
+    /// L2 norm of `values`, computed once so scoring only needs a dot product
+    pub norm: f32,
 }
 
 impl Embedding {
     /// Creates a new Embedding instance
     pub fn new(vector: Vec<f32>, provider: EmbeddingProvider, model: String) -> Self {
+        let norm = l2_norm(&vector);
+        Self::with_norm(vector, norm, provider, model)
+    }
+
+    /// Creates an Embedding whose norm is already known, e.g. from the cache
+    pub fn with_norm(
+        vector: Vec<f32>,
+        norm: f32,
+        provider: EmbeddingProvider,
+        model: String,
+    ) -> Self {
         let dimensions = vector.len();
         Self {
             values: vector,
             provider,
             model,
             dimensions,
+            norm,
+        }
+    }
+
+    /// Scales the vector to unit length; zero vectors are left as they are
+    pub fn normalized(mut self) -> Self {
+        if self.norm > 0.0 && self.norm != 1.0 {
+            for value in &mut self.values {
+                *value /= self.norm;
+            }
+            self.norm = 1.0;
         }
+        self
+    }
+
+    /// Cosine similarity with another embedding, using the precomputed norms
+    pub fn similarity(&self, other: &Embedding) -> f32 {
+        if self.norm == 0.0 || other.norm == 0.0 {
+            return 0.0;
+        }
+        let dot_product: f32 = self
+            .values
+            .iter()
+            .zip(&other.values)
+            .map(|(a, b)| a * b)
+            .sum();
+        dot_product / (self.norm * other.norm)
     }
 
     /// Converts the embedding to an ndarray::Array1 for numerical operations
@@ -69,6 +115,11 @@ pub struct CachedDocumentEmbedding {
 /// Result type specific to embedding operations
 pub type EmbeddingResult<T> = std::result::Result<T, crate::error::ServerError>;
 
+/// Calculates the L2 norm of a vector
+pub fn l2_norm(values: &[f32]) -> f32 {
+    values.iter().map(|v| v * v).sum::<f32>().sqrt()
+}
+
 /// Calculates the cosine similarity between two vectors.
 pub fn cosine_similarity(v1: ArrayView1<f32>, v2: ArrayView1<f32>) -> f32 {
     let dot_product = v1.dot(&v2);
@@ -93,9 +144,5 @@ pub fn embedding_similarity(e1: &Embedding, e2: &Embedding) -> EmbeddingResult<f
         });
     }
 
-    let v1 = e1.to_array();
-    let v2 = e2.to_array();
-
-    Ok(cosine_similarity(v1.view(), v2.view()))
+    Ok(e1.similarity(e2))
 }
-
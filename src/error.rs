@@ -1,7 +1,106 @@
 use crate::doc_loader::DocLoaderError;
-use rmcp::ServiceError; // Assuming ServiceError is the correct top-level error
+use rmcp::model::ErrorCode;
+use rmcp::{Error as McpError, ServiceError}; // Assuming ServiceError is the correct top-level error
+use serde_json::json;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error; // Need to import DocLoaderError from the sibling module
 
+// JSON-RPC error codes for OpenAI failures, taken from the range reserved for
+// implementation-defined errors (-32002 is MCP's "resource not found")
+/// Provider failure that doesn't fit a more specific code
+pub const PROVIDER_ERROR: ErrorCode = ErrorCode(-32000);
+/// The API key was missing, invalid or lacks access to the model
+pub const PROVIDER_AUTH_ERROR: ErrorCode = ErrorCode(-32001);
+/// The account has no remaining quota; retrying won't help
+pub const PROVIDER_QUOTA_EXCEEDED: ErrorCode = ErrorCode(-32003);
+/// Too many requests; retry after the hinted delay
+pub const PROVIDER_RATE_LIMITED: ErrorCode = ErrorCode(-32004);
+/// The provider couldn't be reached or failed on its side
+pub const PROVIDER_UNAVAILABLE: ErrorCode = ErrorCode(-32005);
+
+/// Kind of failure reported by the OpenAI API or the connection to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderErrorKind {
+    Auth,
+    QuotaExceeded,
+    RateLimited,
+    ContextLength,
+    Network,
+    Unavailable,
+    Other,
+}
+
+impl ProviderErrorKind {
+    /// Classifies a failure from its HTTP status and the `code`/`type` of the
+    /// error object in the response body, either of which may be missing
+    pub fn classify(status: Option<u16>, code: Option<&str>) -> Self {
+        match code {
+            Some("insufficient_quota") => return Self::QuotaExceeded,
+            Some("context_length_exceeded" | "string_above_max_length") => {
+                return Self::ContextLength;
+            }
+            Some("invalid_api_key" | "invalid_authentication" | "authentication_error") => {
+                return Self::Auth;
+            }
+            Some("rate_limit_exceeded" | "requests" | "tokens") => return Self::RateLimited,
+            _ => {}
+        }
+        match status {
+            Some(401 | 403) => Self::Auth,
+            Some(429) => Self::RateLimited,
+            Some(408 | 500..=599) => Self::Unavailable,
+            _ => Self::Other,
+        }
+    }
+
+    /// Returns true if the same request may succeed when retried later
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::RateLimited | Self::Network | Self::Unavailable)
+    }
+
+    /// Name used in MCP error data
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::QuotaExceeded => "quota_exceeded",
+            Self::RateLimited => "rate_limited",
+            Self::ContextLength => "context_length",
+            Self::Network => "network",
+            Self::Unavailable => "unavailable",
+            Self::Other => "other",
+        }
+    }
+
+    fn error_code(self) -> ErrorCode {
+        match self {
+            Self::Auth => PROVIDER_AUTH_ERROR,
+            Self::QuotaExceeded => PROVIDER_QUOTA_EXCEEDED,
+            Self::RateLimited => PROVIDER_RATE_LIMITED,
+            Self::ContextLength => ErrorCode::INVALID_PARAMS,
+            Self::Network | Self::Unavailable => PROVIDER_UNAVAILABLE,
+            Self::Other => PROVIDER_ERROR,
+        }
+    }
+
+    /// What the user can do about the failure, if anything specific
+    fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::Auth => Some("check that OPENAI_API_KEY is set and valid"),
+            Self::QuotaExceeded => Some("the OpenAI account has no remaining quota"),
+            Self::ContextLength => Some("the input is too long for the model"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProviderErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 // Define a Result type alias for convenience
 pub type Result<T> = std::result::Result<T, ServerError>;
 
@@ -16,8 +115,6 @@ pub enum ServerError {
     Io(#[from] std::io::Error),
     #[error("Document Loading Error: {0}")]
     DocLoader(#[from] DocLoaderError),
-    #[error("OpenAI Error: {0}")]
-    OpenAI(#[from] async_openai::error::OpenAIError),
     #[error("JSON Error: {0}")]
     Json(#[from] serde_json::Error),
     #[error("MCP Runtime Error: {0}")]
@@ -31,10 +128,27 @@ pub enum ServerError {
     // Embedding related errors
     #[error("Embedding Dimension Mismatch: expected {expected}, got {actual}")]
     EmbeddingDimensionMismatch { expected: usize, actual: usize },
+    #[error("Embedding Cache Error: {reason} ({})", path.display())]
+    EmbeddingCache { path: PathBuf, reason: String },
+    #[error("Provider Error ({kind}): {message}")]
+    Provider {
+        kind: ProviderErrorKind,
+        /// HTTP status returned by the provider, if it answered at all
+        status: Option<u16>,
+        message: String,
+        /// Delay the provider asked for before retrying
+        retry_after: Option<Duration>,
+    },
     #[error("Bincode Error: {0}")]
     Bincode(#[from] bincode::error::EncodeError),
     #[error("Bincode Decode Error: {0}")]
     BincodeDecode(#[from] bincode::error::DecodeError),
+    #[error("Arrow Error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+    #[error("Parquet Error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error("SQLite Error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
 
     // HTTP client errors
     #[error("HTTP Request Error: {0}")]
@@ -42,4 +156,111 @@ pub enum ServerError {
     #[error("HTTP Transport Error: {0}")]
     #[allow(dead_code)]
     HttpTransport(String),
+
+    // Raised instead of making a network call when running with --offline
+    #[error("Offline: {0}")]
+    Offline(String),
+
+    // Raised instead of calling a provider once `[budget]` is spent
+    #[error("Budget exhausted: {0}")]
+    BudgetExceeded(String),
+
+    // Documentation watcher errors
+    #[error("File Watch Error: {0}")]
+    Watch(#[from] notify::Error),
+}
+
+impl ServerError {
+    /// Classifies an unsuccessful response from the OpenAI API from its status
+    /// and the error object in its body
+    pub fn from_api_response(status: u16, retry_after: Option<Duration>, body: &str) -> Self {
+        let value: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+        let error = &value["error"];
+        let code = error["code"].as_str().or(error["type"].as_str());
+        let detail = error["message"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| body.trim().to_string());
+        Self::Provider {
+            kind: ProviderErrorKind::classify(Some(status), code),
+            status: Some(status),
+            message: format!("OpenAI API returned {}: {}", status, detail),
+            retry_after,
+        }
+    }
+
+    /// Classifies a failed HTTP request to the provider
+    pub fn from_reqwest(error: reqwest::Error) -> Self {
+        let status = error.status().map(|s| s.as_u16());
+        let kind = if error.is_connect() || error.is_timeout() {
+            ProviderErrorKind::Network
+        } else {
+            ProviderErrorKind::classify(status, None)
+        };
+        Self::Provider {
+            kind,
+            status,
+            message: error.to_string(),
+            retry_after: None,
+        }
+    }
+
+    /// Returns true for provider failures that may succeed when retried later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Provider { kind, .. } => kind.is_retryable(),
+            Self::Reqwest(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// Converts the error into an MCP error whose code reflects the kind of
+    /// failure, prefixing the message with `context`
+    pub fn into_mcp_error(self, context: &str) -> McpError {
+        let this = match self {
+            Self::Reqwest(e) => Self::from_reqwest(e),
+            other => other,
+        };
+        let mut message = format!("{}: {}", context, this);
+        match this {
+            Self::Provider {
+                kind,
+                status,
+                retry_after,
+                ..
+            } => {
+                if let Some(hint) = kind.hint() {
+                    message = format!("{} ({})", message, hint);
+                }
+                McpError::new(
+                    kind.error_code(),
+                    message,
+                    Some(json!({
+                        "kind": kind.as_str(),
+                        "status": status,
+                        "retryable": kind.is_retryable(),
+                        "retry_after_secs": retry_after.map(|d| d.as_secs()),
+                    })),
+                )
+            }
+            Self::Offline(_) => McpError::new(
+                ErrorCode::INVALID_REQUEST,
+                message,
+                Some(json!({ "offline": true })),
+            ),
+            Self::BudgetExceeded(_) => McpError::new(
+                ErrorCode::INVALID_REQUEST,
+                message,
+                Some(json!({ "budget_exceeded": true })),
+            ),
+            Self::EmbeddingCache { path, .. } => {
+                McpError::internal_error(message, Some(json!({ "cache_path": path })))
+            }
+            Self::EmbeddingDimensionMismatch { expected, actual } => McpError::internal_error(
+                message,
+                Some(json!({ "expected": expected, "actual": actual })),
+            ),
+            _ => McpError::internal_error(message, None),
+        }
+    }
 }
@@ -0,0 +1,100 @@
+//! Machine-readable context in the `data` of the MCP errors tools return, so
+//! agents can decide to retry, regenerate docs or ask the user without
+//! parsing messages.
+//!
+//! Every tool error carries `retryable` and, when there is something to do
+//! about it, a `suggestion`. Errors about a crate or document name it in
+//! `crate_name` and `doc_path`, and provider failures name the `provider`.
+//! Fields set closer to the failure win over ones added on the way out.
+
+use rmcp::Error as McpError;
+use rmcp::model::ErrorCode;
+use serde_json::{Map, Value};
+
+/// What a client can do about a failed tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suggestion {
+    /// Call again, after `retry_after_secs` if given
+    Retry,
+    /// Change the arguments, e.g. pick another crate or shorten the question
+    FixArguments,
+    /// Generate the crate's documentation with `cargo doc`, then call again
+    GenerateDocs,
+    /// Ask the user, e.g. to fix the API key, add quota or raise the budget
+    AskUser,
+    /// Enable the tool or setting in the server's configuration
+    ChangeConfig,
+}
+
+impl Suggestion {
+    /// Name used in MCP error data
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Retry => "retry",
+            Self::FixArguments => "fix_arguments",
+            Self::GenerateDocs => "generate_docs",
+            Self::AskUser => "ask_user",
+            Self::ChangeConfig => "change_config",
+        }
+    }
+}
+
+/// Sets `key` in the error's data unless it is already set. Data that isn't
+/// an object is kept under `detail`.
+pub fn with_field(mut error: McpError, key: &str, value: impl Into<Value>) -> McpError {
+    let mut data = match error.data.take() {
+        Some(Value::Object(data)) => data,
+        Some(Value::Null) | None => Map::new(),
+        Some(other) => Map::from_iter([("detail".to_string(), other)]),
+    };
+    data.entry(key).or_insert_with(|| value.into());
+    error.data = Some(Value::Object(data));
+    error
+}
+
+/// Names the crate the error is about
+pub fn with_crate(error: McpError, crate_name: &str) -> McpError {
+    with_field(error, "crate_name", crate_name)
+}
+
+/// Names the document the error is about, relative to the crate's doc directory
+pub fn with_doc_path(error: McpError, path: &str) -> McpError {
+    with_field(error, "doc_path", path)
+}
+
+/// Names the embedding or chat provider that failed
+pub fn with_provider(error: McpError, provider: &str) -> McpError {
+    with_field(error, "provider", provider)
+}
+
+/// Says what the client can do about the error
+pub fn with_suggestion(error: McpError, suggestion: Suggestion) -> McpError {
+    with_field(error, "suggestion", suggestion.as_str())
+}
+
+/// Completes the data of an error a tool returns: `retryable` defaults to
+/// false, and a suggestion is derived from the error if none was given
+pub fn finish(error: McpError) -> McpError {
+    let error = with_field(error, "retryable", false);
+    let data = error.data.as_ref().unwrap_or(&Value::Null);
+    let suggestion = if data["retryable"] == true {
+        Some(Suggestion::Retry)
+    } else if data["offline"] == true || data["budget_exceeded"] == true {
+        Some(Suggestion::AskUser)
+    } else {
+        match data["kind"].as_str() {
+            Some("auth" | "quota_exceeded") => Some(Suggestion::AskUser),
+            Some("context_length") => Some(Suggestion::FixArguments),
+            _ if error.code == ErrorCode::INVALID_PARAMS
+                || error.code == ErrorCode::RESOURCE_NOT_FOUND =>
+            {
+                Some(Suggestion::FixArguments)
+            }
+            _ => None,
+        }
+    };
+    match suggestion {
+        Some(suggestion) => with_suggestion(error, suggestion),
+        None => error,
+    }
+}
@@ -0,0 +1,200 @@
+//! Answers questions by quoting the documentation instead of summarizing it.
+//!
+//! The sentences of the matched documents are ranked by their TF-IDF cosine
+//! similarity to the question, and the best ones are returned verbatim, with
+//! the words they share with the question in bold. Nothing is generated, so
+//! such answers cost no provider requests and can't state anything the docs
+//! don't. Embedding each sentence would rank paraphrases better, but cost a
+//! request per sentence.
+
+use std::collections::{HashMap, HashSet};
+
+/// Most sentences an answer quotes
+pub const DEFAULT_EXCERPTS: usize = 6;
+
+/// Sentences longer than this are usually code or lists run together
+const MAX_SENTENCE_CHARS: usize = 600;
+
+/// Sentences with fewer words say too little to quote
+const MIN_SENTENCE_WORDS: usize = 3;
+
+/// Words too common to count as matching the question
+const STOP_WORDS: &[&str] = &[
+    "about", "and", "are", "can", "does", "for", "from", "has", "have", "how", "into", "its",
+    "not", "that", "the", "this", "use", "using", "was", "what", "when", "where", "which", "who",
+    "why", "will", "with", "you", "your",
+];
+
+/// A quoted sentence of a matched document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Excerpt {
+    /// Index of the document among those passed to [`excerpts`]
+    pub document: usize,
+    /// The sentence, with the words it shares with the question in `**bold**`
+    pub text: String,
+    /// Similarity to the question, between 0 and 1
+    pub score: f32,
+}
+
+/// The at most `limit` sentences of `documents` most similar to `question`,
+/// in the order of the documents and of the sentences within them. Sentences
+/// sharing no word with the question are never picked.
+pub fn excerpts(question: &str, documents: &[&str], limit: usize) -> Vec<Excerpt> {
+    let asked: HashSet<String> = words(question).map(|(_, stem)| stem).collect();
+    if asked.is_empty() || limit == 0 {
+        return Vec::new();
+    }
+
+    // (document, position, sentence, stem counts)
+    let candidates: Vec<(usize, usize, String, HashMap<String, usize>)> = documents
+        .iter()
+        .enumerate()
+        .flat_map(|(document, text)| {
+            sentences(text)
+                .into_iter()
+                .enumerate()
+                .map(move |(position, sentence)| (document, position, sentence))
+        })
+        .filter_map(|(document, position, sentence)| {
+            let mut counts = HashMap::new();
+            for (_, stem) in words(&sentence) {
+                *counts.entry(stem).or_insert(0) += 1;
+            }
+            (counts.values().sum::<usize>() >= MIN_SENTENCE_WORDS)
+                .then_some((document, position, sentence, counts))
+        })
+        .collect();
+
+    // Words in many of the candidate sentences tell little about any one
+    let total = candidates.len() as f32;
+    let mut frequency: HashMap<&str, usize> = HashMap::new();
+    for (_, _, _, counts) in &candidates {
+        for stem in counts.keys() {
+            *frequency.entry(stem.as_str()).or_insert(0) += 1;
+        }
+    }
+    let idf = |stem: &str| {
+        let frequency = frequency.get(stem).copied().unwrap_or(0) as f32;
+        ((total + 1.0) / (frequency + 1.0)).ln() + 1.0
+    };
+
+    let question_norm = asked
+        .iter()
+        .map(|stem| idf(stem).powi(2))
+        .sum::<f32>()
+        .sqrt();
+    let mut scored: Vec<(usize, usize, &str, f32)> = candidates
+        .iter()
+        .filter_map(|(document, position, sentence, counts)| {
+            let mut dot = 0.0;
+            let mut norm = 0.0;
+            for (stem, &count) in counts {
+                let weight = count as f32 * idf(stem);
+                norm += weight * weight;
+                if asked.contains(stem) {
+                    dot += weight * idf(stem);
+                }
+            }
+            (dot > 0.0).then(|| {
+                let score = dot / (norm.sqrt() * question_norm);
+                (*document, *position, sentence.as_str(), score)
+            })
+        })
+        .collect();
+    scored.sort_by(|a, b| b.3.total_cmp(&a.3));
+    scored.truncate(limit);
+    scored.sort_by_key(|(document, position, _, _)| (*document, *position));
+
+    scored
+        .into_iter()
+        .map(|(document, _, sentence, score)| Excerpt {
+            document,
+            text: highlight(sentence, &asked),
+            score,
+        })
+        .collect()
+}
+
+/// Splits text into sentences on one line each, at `.`, `!` or `?` followed
+/// by whitespace. Extracted documentation has a line per text node, so lines
+/// are joined first.
+pub fn sentences(text: &str) -> Vec<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at_end = matches!(chars.peek(), Some((_, ' ')) | None);
+        if matches!(c, '.' | '!' | '?') && at_end {
+            sentences.push(text[start..=i].trim());
+            start = i + 1;
+        }
+    }
+    sentences.push(text[start..].trim());
+    sentences
+        .into_iter()
+        .filter(|sentence| !sentence.is_empty() && sentence.len() <= MAX_SENTENCE_CHARS)
+        .map(str::to_string)
+        .collect()
+}
+
+/// `sentence` with the words whose stem is in `stems` in `**bold**`
+fn highlight(sentence: &str, stems: &HashSet<String>) -> String {
+    let mut text = String::with_capacity(sentence.len());
+    let mut copied = 0;
+    for (range, stem) in words(sentence) {
+        if stems.contains(&stem) {
+            text.push_str(&sentence[copied..range.start]);
+            text.push_str("**");
+            text.push_str(&sentence[range.clone()]);
+            text.push_str("**");
+            copied = range.end;
+        }
+    }
+    text.push_str(&sentence[copied..]);
+    text
+}
+
+/// Byte ranges and stems of the words of `text` that aren't stop words.
+/// Identifiers and paths like `Widget::resize` are one word.
+fn words(text: &str) -> impl Iterator<Item = (std::ops::Range<usize>, String)> + '_ {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        let in_word = c.is_alphanumeric()
+            || c == '_'
+            || (c == ':' && start.is_some() && text[i..].starts_with("::"))
+            || (c == ':' && start.is_some() && text[..i].ends_with(':'));
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(begin)) => {
+                let word = text[begin..i].trim_end_matches(':');
+                words.push(begin..begin + word.len());
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words.into_iter().filter_map(move |range| {
+        let word = text[range.clone()].to_lowercase();
+        (word.chars().count() >= 3 && !STOP_WORDS.contains(&word.as_str()))
+            .then(|| (range, stem(&word)))
+    })
+}
+
+/// Strips common English suffixes, so `resizes`, `resized` and `resizing`
+/// match `resize`
+fn stem(word: &str) -> String {
+    for suffix in ["ing", "ed", "es", "s", "e"] {
+        if let Some(stem) = word.strip_suffix(suffix)
+            && stem.chars().count() >= 3
+            && !stem.ends_with(':')
+        {
+            return stem.to_string();
+        }
+    }
+    word.to_string()
+}
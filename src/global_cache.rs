@@ -0,0 +1,285 @@
+use bincode::{Decode, Encode};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::crate_spec::canonical_crate_name;
+use crate::embeddings::CachedDocumentEmbedding;
+use crate::error::Result;
+use crate::platform_paths::{self, ProjectDirs};
+use crate::utils::{ensure_dir_exists, with_context};
+
+/// File name of a serialized crate index inside its cache directory
+pub(crate) const INDEX_FILE_NAME: &str = "embeddings.bin";
+
+/// File name of the manifest of the inputs a cached index was built from
+pub(crate) const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// File name of the keyword index stored next to a crate's embeddings
+const KEYWORD_INDEX_FILE_NAME: &str = "keywords.bin";
+
+/// File name of the HNSW graph of a large crate's embeddings
+pub(crate) const ANN_INDEX_FILE_NAME: &str = "ann.usearch";
+
+/// File name of the list of chunk cache entries a crate's index references
+const CHUNK_REFS_FILE_NAME: &str = "chunks.json";
+
+/// File name of the snapshot of the server's indexed crates, in the cache root
+const SNAPSHOT_FILE_NAME: &str = "snapshot.bin";
+
+/// Directory holding generated rustdoc output inside a crate's cache directory
+pub(crate) const DOCS_DIR_NAME: &str = "doc";
+
+/// Directory in the cache root holding the pages of ingested documents,
+/// laid out like a `target/doc` directory
+const INGESTED_DIR_NAME: &str = ".ingested";
+
+/// Version key used for crates documented from the local `target/doc` output
+pub const LOCAL_VERSION: &str = "local";
+
+/// Identifies a cached crate index by crate name, version and feature set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub crate_name: String,
+    pub version: String,
+    pub features: Vec<String>,
+}
+
+impl CacheKey {
+    /// Creates a key for a crate documented from the local `target/doc` directory
+    pub fn local(crate_name: &str) -> Self {
+        Self {
+            crate_name: crate_name.to_string(),
+            version: LOCAL_VERSION.to_string(),
+            features: Vec::new(),
+        }
+    }
+
+    /// Creates a key for a crate whose documents were read from its sources
+    /// with the `source` backend, apart from any of its rustdoc output
+    pub fn sources(crate_name: &str) -> Self {
+        Self {
+            version: format!("{}-sources", LOCAL_VERSION),
+            ..Self::local(crate_name)
+        }
+    }
+
+    /// Returns the version requirement with path-hostile characters replaced
+    pub fn sanitized_version(&self) -> String {
+        platform_paths::safe_file_name(&self.version)
+    }
+
+    /// Returns a short, order-independent hash of the requested features
+    pub fn features_hash(&self) -> String {
+        let mut features = self.features.clone();
+        features.sort();
+        features.dedup();
+
+        let mut hasher = Sha256::new();
+        hasher.update(features.join(",").as_bytes());
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+}
+
+/// Documents and their embeddings for one crate, as stored in the global cache
+#[derive(Debug, Encode, Decode)]
+pub struct CachedCrateIndex {
+    pub crate_name: String,
+    pub version: String,
+    pub features: Vec<String>,
+    /// The embedding model the vectors were generated with
+    pub model: String,
+    pub documents: Vec<CachedDocumentEmbedding>,
+}
+
+/// Per-crate cache of extracted documents and their embeddings stored in the
+/// XDG data directory (`~/.local/share/rustdocs-mcp-server/` or similar).
+#[derive(Debug, Clone)]
+pub struct GlobalCache {
+    root: PathBuf,
+}
+
+impl GlobalCache {
+    /// Opens the global cache at the platform default location
+    pub fn new() -> Result<Self> {
+        Self::with_root(default_root()?)
+    }
+
+    /// Opens a global cache rooted at a custom directory
+    pub fn with_root(root: PathBuf) -> Result<Self> {
+        ensure_dir_exists(&root)?;
+        Ok(Self { root })
+    }
+
+    /// Returns the root directory of the cache
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the snapshot the server restores its indexed crates from
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.root.join(SNAPSHOT_FILE_NAME)
+    }
+
+    /// Doc directory the documents fed in with
+    /// [`ingest_documents`](crate::ingest::ingest_documents) are written to
+    pub fn ingested_dir(&self) -> PathBuf {
+        self.root.join(INGESTED_DIR_NAME)
+    }
+
+    /// Computes the cache directory for a key. Spellings of a crate name
+    /// that differ only in dashes and underscores share a directory.
+    fn entry_dir(&self, key: &CacheKey) -> PathBuf {
+        self.crate_dir(&canonical_crate_name(&key.crate_name))
+            .join(key.sanitized_version())
+            .join(key.features_hash())
+    }
+
+    fn crate_dir(&self, crate_name: &str) -> PathBuf {
+        self.root.join(platform_paths::safe_file_name(crate_name))
+    }
+
+    /// Moves the entries of a crate cached under its dashed name, as they
+    /// were before names were canonicalized, to the canonical directory
+    fn migrate_dashed_entries(&self, crate_name: &str) {
+        let canonical = canonical_crate_name(crate_name);
+        let dashed = canonical.replace('_', "-");
+        if dashed == canonical {
+            return;
+        }
+        let (from, to) = (self.crate_dir(&dashed), self.crate_dir(&canonical));
+        if from.is_dir()
+            && !to.exists()
+            && let Err(e) = fs::rename(&from, &to)
+        {
+            warn!(
+                "Failed to move {} to {}: {}",
+                from.display(),
+                to.display(),
+                e
+            );
+        }
+    }
+
+    /// Computes the index file path for a cache key
+    pub fn index_path(&self, key: &CacheKey) -> PathBuf {
+        self.entry_dir(key).join(INDEX_FILE_NAME)
+    }
+
+    /// Computes the path of the manifest stored next to a key's index file
+    pub fn manifest_path(&self, key: &CacheKey) -> PathBuf {
+        self.entry_dir(key).join(MANIFEST_FILE_NAME)
+    }
+
+    /// Computes the path of the keyword index stored next to a key's index file
+    pub fn keyword_index_path(&self, key: &CacheKey) -> PathBuf {
+        self.entry_dir(key).join(KEYWORD_INDEX_FILE_NAME)
+    }
+
+    /// Computes the path of the HNSW graph stored next to a key's index file
+    pub fn ann_index_path(&self, key: &CacheKey) -> PathBuf {
+        self.entry_dir(key).join(ANN_INDEX_FILE_NAME)
+    }
+
+    /// Records the chunk cache entries the index of `key` was embedded from.
+    /// Chunks are shared by every crate containing the same text, so an entry
+    /// is only orphaned once no crate lists it.
+    pub fn store_chunk_refs(&self, key: &CacheKey, chunk_keys: &[String]) -> Result<()> {
+        let path = platform_paths::long_path(&self.entry_dir(key).join(CHUNK_REFS_FILE_NAME));
+        if let Some(parent) = path.parent() {
+            ensure_dir_exists(parent)?;
+        }
+        with_context(fs::write(&path, serde_json::to_string(chunk_keys)?), || {
+            format!("Failed to write chunk references {}", path.display())
+        })
+    }
+
+    /// Computes the directory holding generated documentation for a cache key,
+    /// laid out like a `target/doc` directory
+    pub fn docs_dir(&self, key: &CacheKey) -> PathBuf {
+        self.entry_dir(key).join(DOCS_DIR_NAME)
+    }
+
+    /// Loads the cached index for a key, returning `None` if nothing is cached
+    pub fn load(&self, key: &CacheKey) -> Result<Option<CachedCrateIndex>> {
+        self.migrate_dashed_entries(&key.crate_name);
+        let path = platform_paths::long_path(&self.index_path(key));
+        if !path.is_file() {
+            return Ok(None);
+        }
+        read_index(&path).map(Some)
+    }
+
+    /// Stores an index, replacing any previous entry for the same key
+    pub fn store(&self, key: &CacheKey, index: &CachedCrateIndex) -> Result<()> {
+        let path = platform_paths::long_path(&self.index_path(key));
+        if let Some(parent) = path.parent() {
+            ensure_dir_exists(parent)?;
+        }
+        let bytes = bincode::encode_to_vec(index, bincode::config::standard())?;
+        with_context(fs::write(&path, bytes), || {
+            format!("Failed to write crate index {}", path.display())
+        })
+    }
+
+    /// Lists the directory of every cache entry,
+    /// `<crate>/<version>/<features hash>`, whether or not it holds an index
+    pub fn entry_dirs(&self) -> Vec<PathBuf> {
+        let Ok(crate_dirs) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        crate_dirs
+            .filter_map(|entry| entry.ok())
+            // Ingested pages and doc workspaces live in hidden directories
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .flat_map(|entry| {
+                walkdir::WalkDir::new(entry.path())
+                    .min_depth(2)
+                    .max_depth(2)
+                    .into_iter()
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().is_dir())
+                    .map(|entry| entry.into_path())
+            })
+            .collect()
+    }
+
+    /// Lists the paths of every index file in the cache
+    pub fn index_files(&self) -> Vec<PathBuf> {
+        walkdir::WalkDir::new(&self.root)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file() && entry.file_name() == INDEX_FILE_NAME)
+            .map(|entry| entry.into_path())
+            .collect()
+    }
+}
+
+/// Decodes a crate index from an index file
+pub fn read_index(path: &Path) -> Result<CachedCrateIndex> {
+    let context = || format!("Failed to read crate index {}", path.display());
+    let bytes = with_context(fs::read(platform_paths::long_path(path)), context)?;
+    let (index, _) = with_context(
+        bincode::decode_from_slice(&bytes, bincode::config::standard()),
+        context,
+    )?;
+    Ok(index)
+}
+
+/// Reads the chunk cache entries recorded next to an index file by
+/// [`GlobalCache::store_chunk_refs`], or `None` if the index predates them
+pub fn read_chunk_refs(index_path: &Path) -> Result<Option<Vec<String>>> {
+    let path = platform_paths::long_path(&index_path.with_file_name(CHUNK_REFS_FILE_NAME));
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let context = || format!("Failed to read chunk references {}", path.display());
+    let json = with_context(fs::read_to_string(&path), context)?;
+    with_context(serde_json::from_str(&json), context).map(Some)
+}
+
+/// Resolves the platform default root directory of the global cache
+pub fn default_root() -> Result<PathBuf> {
+    Ok(ProjectDirs::new()?.data_dir().to_path_buf())
+}
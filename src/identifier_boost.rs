@@ -0,0 +1,162 @@
+//! Boosts documents that mention the Rust identifiers of a question.
+//!
+//! Names like `HashMap`, `buffer_unordered` or `tokio::select` are strong
+//! signals of what a question is about, but embeddings tend to underweight
+//! them. Documents mentioning them exactly get a bonus on top of their
+//! similarity, weighted by how rare (IDF) each identifier is in the crate, so
+//! a name appearing on every page barely counts.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::keyword_index::KeywordIndex;
+
+/// Most identifiers taken from a question
+const MAX_IDENTIFIERS: usize = 8;
+
+/// How many documents of a crate contain each word, for IDF weights
+#[derive(Debug, Clone, Default)]
+pub struct IdentifierIndex {
+    documents: usize,
+    frequencies: HashMap<String, usize>,
+}
+
+impl IdentifierIndex {
+    /// Counts the documents each word of `texts` appears in
+    pub fn new<'a>(texts: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut index = Self::default();
+        for text in texts {
+            index.documents += 1;
+            let words: HashSet<&str> = words(text).collect();
+            for word in words {
+                *index.frequencies.entry(word.to_string()).or_default() += 1;
+            }
+        }
+        index
+    }
+
+    /// Rarity of `identifier` between 0 (in every document) and 1 (in none);
+    /// paths are as rare as their last segment
+    pub fn idf(&self, identifier: &str) -> f32 {
+        if self.documents == 0 {
+            return 0.0;
+        }
+        let name = identifier.rsplit("::").next().unwrap_or(identifier);
+        let frequency = self.frequencies.get(name).copied().unwrap_or(0);
+        let documents = self.documents as f32;
+        ((documents + 1.0) / (frequency as f32 + 1.0)).ln() / (documents + 1.0).ln()
+    }
+
+    /// Share of the question's identifiers a document mentions, each weighted
+    /// by its rarity; between 0 and 1. A document mentions an identifier if
+    /// its text contains it as a whole word or its item path ends with it.
+    pub fn score(&self, identifiers: &[String], text: &str, item_path: Option<&str>) -> f32 {
+        if identifiers.is_empty() {
+            return 0.0;
+        }
+        let matched: f32 = identifiers
+            .iter()
+            .filter(|identifier| {
+                item_path.is_some_and(|path| path_ends_with(path, identifier))
+                    || contains_word(text, identifier)
+            })
+            .map(|identifier| self.idf(identifier))
+            .sum();
+        matched / identifiers.len() as f32
+    }
+}
+
+/// Like [`IdentifierIndex::score`], but looks the identifiers up in the
+/// crate's [`KeywordIndex`] instead of scanning the text of the document at
+/// `path`
+pub fn indexed_score(
+    index: &KeywordIndex,
+    identifiers: &[String],
+    path: &str,
+    item_path: Option<&str>,
+) -> f32 {
+    if identifiers.is_empty() {
+        return 0.0;
+    }
+    let matched: f32 = identifiers
+        .iter()
+        .filter(|identifier| {
+            item_path.is_some_and(|item| path_ends_with(item, identifier))
+                || index.contains(path, identifier)
+        })
+        .map(|identifier| index.idf(identifier))
+        .sum();
+    matched / identifiers.len() as f32
+}
+
+/// Finds the Rust identifiers in a question: `CamelCase` and `snake_case`
+/// names, `::` paths and anything quoted in backticks, without call
+/// parentheses or a macro's `!`
+pub fn question_identifiers(question: &str) -> Vec<String> {
+    let mut identifiers = identifiers(question);
+    identifiers.truncate(MAX_IDENTIFIERS);
+    identifiers
+}
+
+/// Like [`question_identifiers`], but finds every identifier of `text`
+pub fn identifiers(text: &str) -> Vec<String> {
+    let mut identifiers: Vec<String> = Vec::new();
+    for (i, span) in text.split('`').enumerate() {
+        // Odd spans are inside backticks
+        let quoted = i % 2 == 1;
+        for token in span.split(|c: char| !(is_word_char(c) || c == ':')) {
+            let token = token.trim_matches(':');
+            let is_path = token.contains("::") && token.split("::").all(is_identifier);
+            let is_name = is_identifier(token)
+                && token.len() > 1
+                && (quoted || is_snake_case(token) || is_camel_case(token));
+            if (is_path || is_name) && !identifiers.iter().any(|known| known == token) {
+                identifiers.push(token.to_string());
+            }
+        }
+    }
+    identifiers
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_identifier(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && token.chars().all(is_word_char)
+}
+
+fn is_snake_case(token: &str) -> bool {
+    token.trim_matches('_').contains('_')
+}
+
+/// `HashMap` or `StreamExt`, but not a capitalized word like `How`
+fn is_camel_case(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_uppercase())
+        && token.chars().any(|c| c.is_ascii_lowercase())
+        && token.chars().skip(1).any(|c| c.is_ascii_uppercase())
+}
+
+/// Words of a text, as counted by [`IdentifierIndex`]
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !is_word_char(c))
+        .filter(|word| is_identifier(word))
+}
+
+/// Returns true if `text` contains `word` not directly preceded or followed
+/// by another identifier character
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + word.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+/// Returns true if the last segments of `path` are `suffix`
+fn path_ends_with(path: &str, suffix: &str) -> bool {
+    path == suffix || path.ends_with(&format!("::{}", suffix))
+}
@@ -0,0 +1,141 @@
+//! Records what a crate's index was built from: the documentation text, the
+//! chunker settings, the embedding provider and model, and the server version.
+//! An index is only reused while all of them are unchanged, so answers can be
+//! reproduced and stale indexes are noticed.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::config::ChunkerSettings;
+use crate::doc_loader::Document;
+use crate::document_chunker::DocumentChunker;
+use crate::error::Result;
+use crate::utils::with_context;
+
+/// The inputs a crate's index was built from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexManifest {
+    pub crate_name: String,
+    /// SHA-256 of the path and text of every document, in order
+    pub content_hash: String,
+    pub documents: usize,
+    pub chunker: ChunkerSettings,
+    pub embedding_provider: String,
+    pub embedding_model: String,
+    /// Version of the server that built the index
+    pub server_version: String,
+}
+
+impl IndexManifest {
+    /// Describes an index of `documents` built with the given chunker and
+    /// embedding model by this version of the server
+    pub fn new(
+        crate_name: &str,
+        documents: &[Document],
+        chunker: &DocumentChunker,
+        embedding_provider: &str,
+        embedding_model: &str,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        for doc in documents {
+            hasher.update(doc.path.as_bytes());
+            hasher.update([0]);
+            hasher.update(doc.content.as_bytes());
+            hasher.update([0]);
+        }
+        Self {
+            crate_name: crate_name.to_string(),
+            content_hash: format!("{:x}", hasher.finalize()),
+            documents: documents.len(),
+            chunker: ChunkerSettings {
+                min_chunk_size: chunker.min_chunk_size(),
+                target_chunk_size: chunker.target_chunk_size(),
+                max_chunk_size: chunker.max_chunk_size(),
+                skip_boilerplate: chunker.skips_boilerplate(),
+            },
+            embedding_provider: embedding_provider.to_string(),
+            embedding_model: embedding_model.to_string(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Names the inputs that differ from `previous`, empty if the index built
+    /// from them would be the same
+    pub fn changes_since(&self, previous: &IndexManifest) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.content_hash != previous.content_hash {
+            changes.push("documentation");
+        }
+        if self.chunker != previous.chunker {
+            changes.push("chunker settings");
+        }
+        if self.embedding_provider != previous.embedding_provider {
+            changes.push("embedding provider");
+        }
+        if self.embedding_model != previous.embedding_model {
+            changes.push("embedding model");
+        }
+        if self.server_version != previous.server_version {
+            changes.push("server version");
+        }
+        changes
+    }
+
+    /// Returns true if embeddings made for `previous` can be reused: only the
+    /// documentation changed, and unchanged documents keep their embeddings
+    pub fn embeddings_compatible(&self, previous: &IndexManifest) -> bool {
+        self.changes_since(previous)
+            .iter()
+            .all(|change| *change == "documentation")
+    }
+
+    /// Reads a manifest written by [`IndexManifest::save`]
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let context = || format!("Failed to read index manifest {}", path.display());
+        let json = with_context(fs::read_to_string(path), context)?;
+        with_context(serde_json::from_str(&json), context).map(Some)
+    }
+
+    /// Writes the manifest as JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        with_context(fs::write(path, serde_json::to_string_pretty(self)?), || {
+            format!("Failed to write index manifest {}", path.display())
+        })
+    }
+}
+
+impl fmt::Display for IndexManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chunker = &self.chunker;
+        writeln!(f, "Crate: {}", self.crate_name)?;
+        writeln!(
+            f,
+            "Documents: {} (content sha256 {})",
+            self.documents, self.content_hash
+        )?;
+        writeln!(
+            f,
+            "Chunker: min {}, target {}, max {} bytes{}",
+            chunker.min_chunk_size,
+            chunker.target_chunk_size,
+            chunker.max_chunk_size,
+            if chunker.skip_boilerplate {
+                ", boilerplate skipped"
+            } else {
+                ""
+            }
+        )?;
+        writeln!(
+            f,
+            "Embeddings: {} ({})",
+            self.embedding_model, self.embedding_provider
+        )?;
+        write!(f, "Server version: {}", self.server_version)
+    }
+}
@@ -0,0 +1,228 @@
+//! Feeds documents that aren't rustdoc output, such as design docs, ADRs or
+//! wiki exports, into the server as a pseudo-crate.
+//!
+//! Each document becomes a page laid out like rustdoc's, in a doc directory
+//! inside the global cache that the server searches like one more
+//! `[[docs.roots]]` entry. The pseudo-crate is then discovered, indexed,
+//! cached and queried through the same tools as real crates. Pages whose text
+//! didn't change are left untouched, so ingesting a corpus again only
+//! re-embeds the documents that changed.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::crate_spec::canonical_crate_name;
+use crate::doc_loader::Document;
+use crate::error::{Result, ServerError};
+use crate::platform_paths;
+use crate::utils::with_context;
+
+/// Name of the doc root ingested documents are served from
+pub const INGESTED_ROOT_NAME: &str = "ingested";
+
+/// Extensions of the files [`read_documents`] picks up
+const EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// What [`ingest_documents`] changed in a pseudo-crate's doc directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestReport {
+    /// Name the documents are queried under
+    pub crate_name: String,
+    /// Directory holding the pseudo-crate's pages
+    pub dir: PathBuf,
+    /// Documents written because they are new or their text changed
+    pub written: usize,
+    /// Documents whose page was already up to date
+    pub unchanged: usize,
+    /// Pages removed because their document is no longer in the corpus
+    pub removed: usize,
+}
+
+impl fmt::Display for IngestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Ingested {} documents as '{}' ({} new or changed, {} removed) into {}",
+            self.written + self.unchanged,
+            self.crate_name,
+            self.written,
+            self.removed,
+            self.dir.display()
+        )
+    }
+}
+
+/// Reads the Markdown and text files at `paths`, descending into directories.
+/// Each document's path is relative to the directory it was found in, or the
+/// file name for files given directly.
+pub fn read_documents(paths: &[PathBuf]) -> Result<Vec<Document>> {
+    let mut documents = Vec::new();
+    for path in paths {
+        if path.is_file() {
+            let name = path.file_name().map(Path::new).unwrap_or(path);
+            documents.push(read_document(path, name)?);
+            continue;
+        }
+        if !path.is_dir() {
+            return Err(ServerError::Config(format!(
+                "No such file or directory: {}",
+                path.display()
+            )));
+        }
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry.map_err(|e| ServerError::Io(e.into()))?;
+            let is_document = entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+            if is_document {
+                let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+                documents.push(read_document(entry.path(), relative)?);
+            }
+        }
+    }
+    Ok(documents)
+}
+
+fn read_document(path: &Path, relative: &Path) -> Result<Document> {
+    let content = with_context(fs::read_to_string(path), || {
+        format!("Failed to read document {}", path.display())
+    })?;
+    Ok(Document {
+        path: platform_paths::portable_path(relative),
+        content,
+    })
+}
+
+/// Writes `documents` as the pages of the pseudo-crate `crate_name` in
+/// `doc_dir`, replacing the documents ingested under that name before.
+/// `crate_name` takes the characters of a crate name; dashes become
+/// underscores as in rustdoc output.
+pub fn ingest_documents(
+    doc_dir: &Path,
+    crate_name: &str,
+    documents: &[Document],
+) -> Result<IngestReport> {
+    if crate_name.is_empty()
+        || !crate_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(ServerError::Config(format!(
+            "Ingested documents need a name made of letters, digits, '-' and '_', got '{}'",
+            crate_name
+        )));
+    }
+    let crate_name = canonical_crate_name(crate_name);
+    let dir = doc_dir.join(&crate_name);
+    let context = || format!("Failed to write ingested documents to {}", dir.display());
+    with_context(fs::create_dir_all(&dir), context)?;
+
+    let mut report = IngestReport {
+        crate_name: crate_name.clone(),
+        dir: dir.clone(),
+        written: 0,
+        unchanged: 0,
+        removed: 0,
+    };
+    let mut pages: HashSet<String> = HashSet::new();
+    let mut listing = Vec::new();
+    for document in documents {
+        let page = unique_page_name(&document.path, &pages);
+        let html = render_page(&document.path, &document.content);
+        if with_context(write_if_changed(&dir.join(&page), &html), context)? {
+            report.written += 1;
+        } else {
+            report.unchanged += 1;
+        }
+        listing.push(document.path.as_str());
+        pages.insert(page);
+    }
+
+    for entry in with_context(fs::read_dir(&dir), context)? {
+        let path = with_context(entry, context)?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name != "index.html" && name.ends_with(".html") && !pages.contains(name) {
+            with_context(fs::remove_file(&path), context)?;
+            report.removed += 1;
+        }
+    }
+
+    // The server notices changed docs by the modification time of the
+    // crate's `index.html`, so it is written whenever any page changed
+    let index_file = dir.join("index.html");
+    let index = render_page(
+        &crate_name,
+        &format!(
+            "Documents ingested as {}:\n{}",
+            crate_name,
+            listing.join("\n")
+        ),
+    );
+    if report.written > 0 || report.removed > 0 {
+        with_context(fs::write(&index_file, index), context)?;
+    } else {
+        with_context(write_if_changed(&index_file, &index), context)?;
+    }
+    debug!("{}", report);
+    Ok(report)
+}
+
+/// File name of the page of the document at `path`. Pages sit in one
+/// directory with no `.` before `.html`, so they're never mistaken for item
+/// pages; documents whose names flatten alike get a numbered suffix.
+fn unique_page_name(path: &str, taken: &HashSet<String>) -> String {
+    let stem = platform_paths::safe_file_name(&path.replace(['/', '\\', '.'], "-"));
+    let mut page = format!("{}.html", stem);
+    let mut n = 2;
+    while taken.contains(&page) || page == "index.html" {
+        page = format!("{}-{}.html", stem, n);
+        n += 1;
+    }
+    page
+}
+
+/// A page with the document's text as the main content rustdoc pages have,
+/// headed by its path
+fn render_page(title: &str, text: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+         <body><section id=\"main-content\" class=\"content\">\
+         <h1>{title}</h1>\n<div class=\"docblock\">{text}</div>\
+         </section></body></html>\n",
+        title = escape_html(title),
+        text = escape_html(text)
+    )
+}
+
+/// Writes `contents` to `path` unless it already holds them, keeping the
+/// modification time of unchanged pages. Returns true if it wrote.
+fn write_if_changed(path: &Path, contents: &str) -> std::io::Result<bool> {
+    if fs::read_to_string(path).is_ok_and(|current| current == contents) {
+        return Ok(false);
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,184 @@
+//! Finds which documented crates provide an item, e.g. where
+//! `StreamExt::buffer_unordered` comes from, by matching item paths derived
+//! from the rustdoc page layout instead of embedding anything.
+
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::crate_spec::canonical_crate_name;
+use crate::doc_loader::{self, DocLoaderError};
+use crate::item_source::ASSOCIATED_KINDS;
+
+/// Kinds of pages that can document associated items
+const CONTAINER_KINDS: &[&str] = &["struct", "enum", "union", "trait", "primitive"];
+
+/// An item matching a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundItem {
+    /// Name of the crate's directory in the doc output
+    pub crate_name: String,
+    /// Full path of the item, e.g. `futures_util::stream::StreamExt::buffer_unordered`
+    pub path: String,
+    /// Rustdoc item kind, e.g. `struct`, `fn` or `method`
+    pub kind: String,
+}
+
+/// Searches the documented crates, given as `(crate name, doc directory)`
+/// pairs, for items whose path ends with `query` (`Name`, `module::Name` or
+/// `Type::member`). Exact matches come before ones differing in case, and
+/// shorter paths before longer ones. At most `limit` items are returned.
+pub fn find_items(
+    crates: &[(String, &Path)],
+    query: &str,
+    limit: usize,
+) -> Result<Vec<FoundItem>, DocLoaderError> {
+    let requested: Vec<&str> = query
+        .trim()
+        .split("::")
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if requested.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pages = Vec::new();
+    for (crate_name, doc_dir) in crates {
+        let crate_name = canonical_crate_name(crate_name);
+        let crate_dir = doc_dir.join(&crate_name);
+        for entry in WalkDir::new(&crate_dir) {
+            let entry = entry?;
+            let relative = entry
+                .path()
+                .strip_prefix(&crate_dir)
+                .unwrap_or(entry.path());
+            if let Some(path) = doc_loader::item_path(&crate_name, relative) {
+                let kind = page_kind(relative);
+                pages.push((crate_name.clone(), path, kind, entry.into_path()));
+            }
+        }
+    }
+
+    let mut found: Vec<(bool, FoundItem)> = Vec::new();
+    for (crate_name, path, kind, file) in &pages {
+        if let Some(exact) = path_matches(path, &requested) {
+            // Items re-exported from private modules leave redirects behind
+            if is_redirect(file)? {
+                continue;
+            }
+            found.push((
+                exact,
+                FoundItem {
+                    crate_name: crate_name.clone(),
+                    path: path.clone(),
+                    kind: kind.clone(),
+                },
+            ));
+        }
+    }
+
+    // Associated items have no page of their own; look for them on the
+    // pages of their types and traits
+    if found.is_empty()
+        && let Some((member, parent)) = requested.split_last()
+    {
+        for (crate_name, path, kind, file) in &pages {
+            if !CONTAINER_KINDS.contains(&kind.as_str()) {
+                continue;
+            }
+            let exact = match parent {
+                [] => true,
+                parent => match path_matches(path, parent) {
+                    Some(exact) => exact,
+                    None => continue,
+                },
+            };
+            let html = fs::read_to_string(file)?;
+            if let Some(member_kind) = ASSOCIATED_KINDS
+                .iter()
+                .find(|kind| html.contains(&format!("id=\"{}.{}\"", kind, member)))
+            {
+                found.push((
+                    exact,
+                    FoundItem {
+                        crate_name: crate_name.clone(),
+                        path: format!("{}::{}", path, member),
+                        kind: member_kind.to_string(),
+                    },
+                ));
+            }
+        }
+    }
+
+    found.sort_by(|(a_exact, a), (b_exact, b)| {
+        let depth = |item: &FoundItem| item.path.matches("::").count();
+        b_exact
+            .cmp(a_exact)
+            .then_with(|| depth(a).cmp(&depth(b)))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    found.dedup_by(|(_, a), (_, b)| a.path == b.path);
+    Ok(found
+        .into_iter()
+        .take(limit)
+        .map(|(_, item)| item)
+        .collect())
+}
+
+/// Returns true for the stub pages rustdoc writes at an item's original
+/// path when it is documented under another one
+fn is_redirect(file: &Path) -> Result<bool, DocLoaderError> {
+    Ok(fs::read_to_string(file)?.contains("http-equiv=\"refresh\""))
+}
+
+/// Rustdoc item kind of a page, e.g. `struct` for `struct.Client.html`
+fn page_kind(relative: &Path) -> String {
+    let file = relative
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match file.split_once('.') {
+        Some(("index", _)) if relative.parent().is_none_or(|p| p.as_os_str().is_empty()) => {
+            "crate".to_string()
+        }
+        Some(("index", _)) => "mod".to_string(),
+        Some((kind, _)) => kind.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Returns whether the path ends with the requested segments and whether they
+/// match exactly, or `None` if they don't match even ignoring case
+fn path_matches(path: &str, requested: &[&str]) -> Option<bool> {
+    let segments: Vec<&str> = path.split("::").collect();
+    let tail = segments.get(segments.len().checked_sub(requested.len())?..)?;
+    if tail == requested {
+        Some(true)
+    } else if tail
+        .iter()
+        .zip(requested)
+        .all(|(segment, requested)| segment.eq_ignore_ascii_case(requested))
+    {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Renders the found items as Markdown
+pub fn format_found_items(query: &str, items: &[FoundItem]) -> String {
+    let mut crates: Vec<&str> = items.iter().map(|item| item.crate_name.as_str()).collect();
+    crates.sort_unstable();
+    crates.dedup();
+    let mut text = format!(
+        "`{}` matches {} item(s) in crate(s) {}:\n",
+        query.trim(),
+        items.len(),
+        crates.join(", ")
+    );
+    for item in items {
+        text.push_str(&format!("\n- `{}` ({})", item.path, item.kind));
+    }
+    text
+}
@@ -0,0 +1,254 @@
+//! Looks up the source code of a documented item through the "Source" links
+//! rustdoc puts on item pages, and reads it back from the pretty-printed
+//! `src/` pages, so it always matches the documented version of the crate.
+
+use scraper::{ElementRef, Html, Node, Selector};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::crate_spec::canonical_crate_name;
+use crate::doc_loader::{self, DocLoaderError};
+use crate::platform_paths;
+
+/// Kinds of associated items whose source can be looked up on their parent's page
+pub(crate) const ASSOCIATED_KINDS: &[&str] =
+    &["method", "tymethod", "associatedconstant", "associatedtype"];
+
+/// Source code of an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemSource {
+    /// Full path of the item, e.g. `serde_json::de::from_str`
+    pub item: String,
+    /// Source file, relative to the directory of the crate root (usually `src/`)
+    pub file: String,
+    /// First and last line of the item in `file`
+    pub lines: (usize, usize),
+    pub code: String,
+    /// True if the item is longer than the lines returned
+    pub truncated: bool,
+}
+
+/// Finds the source of `item` (`Type`, `module::function`, `Type::method`,
+/// optionally prefixed by the crate name) in the crate's docs under
+/// `doc_dir`. Returns at most `max_lines` lines, or `None` if no documented
+/// item matches or its docs link no source.
+pub fn find_item_source(
+    doc_dir: &Path,
+    crate_name: &str,
+    item: &str,
+    max_lines: usize,
+) -> Result<Option<ItemSource>, DocLoaderError> {
+    let crate_name = canonical_crate_name(crate_name);
+    let requested: Vec<&str> = item
+        .trim()
+        .split("::")
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let requested = match requested.split_first() {
+        Some((&first, rest)) if first == crate_name => rest.to_vec(),
+        _ => requested,
+    };
+    let crate_dir = doc_dir.join(&crate_name);
+    let pages = item_pages(&crate_dir, &crate_name)?;
+
+    // Look for the item's own page, then for a member on its parent's page
+    let mut lookups = vec![(requested.as_slice(), None)];
+    if let Some((member, parent)) = requested.split_last()
+        && !parent.is_empty()
+    {
+        lookups.push((parent, Some(*member)));
+    }
+    for (path, member) in lookups {
+        for (page, page_item) in pages
+            .iter()
+            .filter(|(_, page_item)| ends_with(page_item, path))
+        {
+            let Some(link) = source_link(page, member)? else {
+                continue;
+            };
+            let item = match member {
+                Some(member) => format!("{}::{}", page_item, member),
+                None => page_item.clone(),
+            };
+            if let Some(source) = read_source(doc_dir, page, &link, item, max_lines)? {
+                return Ok(Some(source));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Item pages of the crate with their item paths, shortest path first so
+/// canonical pages win over re-exports
+fn item_pages(
+    crate_dir: &Path,
+    crate_name: &str,
+) -> Result<Vec<(PathBuf, String)>, DocLoaderError> {
+    let mut pages = Vec::new();
+    for entry in WalkDir::new(crate_dir) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(crate_dir).unwrap_or(entry.path());
+        if let Some(item) = doc_loader::item_path(crate_name, relative) {
+            pages.push((entry.into_path(), item));
+        }
+    }
+    pages.sort_by(|a, b| {
+        let depth = |item: &str| item.matches("::").count();
+        depth(&a.1).cmp(&depth(&b.1)).then_with(|| a.0.cmp(&b.0))
+    });
+    Ok(pages)
+}
+
+/// Returns true if the path of `item` ends with the segments of `requested`
+fn ends_with(item: &str, requested: &[&str]) -> bool {
+    let segments: Vec<&str> = item.split("::").collect();
+    match requested {
+        // The crate itself
+        [] => segments.len() == 1,
+        _ => segments.ends_with(requested),
+    }
+}
+
+/// Returns the target of the "Source" link of the page's item, or of its
+/// associated item `member`
+fn source_link(page: &Path, member: Option<&str>) -> Result<Option<String>, DocLoaderError> {
+    let selectors = match member {
+        Some(member) => ASSOCIATED_KINDS
+            .iter()
+            .map(|kind| format!("#main-content [id=\"{}.{}\"] a.src", kind, member))
+            .collect::<Vec<_>>()
+            .join(", "),
+        // `a.srclink` in rustdoc releases before 1.72
+        None => {
+            "#main-content .main-heading a.src, #main-content .out-of-band a.srclink".to_string()
+        }
+    };
+    let selector =
+        Selector::parse(&selectors).map_err(|e| DocLoaderError::Selector(e.to_string()))?;
+    let html = fs::read_to_string(page)?;
+    let page = Html::parse_document(&html);
+    Ok(page
+        .select(&selector)
+        .next()
+        .and_then(|link| link.value().attr("href"))
+        .map(str::to_string))
+}
+
+/// Reads the lines a source link points to, e.g.
+/// `../src/demo/lib.rs.html#7-10`, from the pretty-printed source page
+fn read_source(
+    doc_dir: &Path,
+    page: &Path,
+    link: &str,
+    item: String,
+    max_lines: usize,
+) -> Result<Option<ItemSource>, DocLoaderError> {
+    let (target, fragment) = link.split_once('#').unwrap_or((link, ""));
+    // Sources outside these docs, e.g. the standard library on doc.rust-lang.org
+    if target.contains("://") {
+        return Ok(None);
+    }
+    let Some(source_page) = resolve_link(page, target) else {
+        return Ok(None);
+    };
+    let Ok(file) = source_page.strip_prefix(doc_dir.join("src")) else {
+        return Ok(None);
+    };
+    if !source_page.is_file() {
+        return Ok(None);
+    }
+
+    let mut span = fragment
+        .splitn(2, '-')
+        .map(|line| line.parse::<usize>().ok());
+    let start = span.next().flatten().unwrap_or(1).max(1);
+    let code_lines = source_lines(&fs::read_to_string(&source_page)?)?;
+    // Without a line span the link is to the whole file, e.g. a module's
+    let default_end = if fragment.is_empty() {
+        code_lines.len()
+    } else {
+        start
+    };
+    let end = span
+        .next()
+        .flatten()
+        .unwrap_or(default_end)
+        .clamp(start, code_lines.len().max(start));
+
+    let shown_end = end.min(start + max_lines.max(1) - 1);
+    let code = code_lines
+        .get(start - 1..shown_end.min(code_lines.len()))
+        .unwrap_or_default()
+        .join("\n");
+    // Drop the crate directory and the `.html` suffix of the source page
+    let file: PathBuf = file.components().skip(1).collect();
+    Ok(Some(ItemSource {
+        item,
+        file: platform_paths::portable_path(&file)
+            .trim_end_matches(".html")
+            .to_string(),
+        lines: (start, end),
+        code,
+        truncated: shown_end < end,
+    }))
+}
+
+/// Resolves a relative link from `page` without touching the file system
+fn resolve_link(page: &Path, target: &str) -> Option<PathBuf> {
+    let mut resolved = page.parent()?.to_path_buf();
+    for component in Path::new(target).components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(segment) => resolved.push(segment),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Lines of code of a rustdoc source page, without the line numbers
+fn source_lines(html: &str) -> Result<Vec<String>, DocLoaderError> {
+    let selector =
+        Selector::parse("pre.rust").map_err(|e| DocLoaderError::Selector(e.to_string()))?;
+    let page = Html::parse_document(html);
+    let mut code = String::new();
+    if let Some(pre) = page.select(&selector).next() {
+        collect_code(pre, &mut code);
+    }
+    Ok(code.lines().map(str::to_string).collect())
+}
+
+/// Appends the text of `element`, skipping line-number anchors
+fn collect_code(element: ElementRef, code: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => code.push_str(text),
+            Node::Element(child_element) if child_element.attr("data-nosnippet").is_none() => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    collect_code(child, code);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Renders the source as Markdown
+pub fn format_item_source(source: &ItemSource) -> String {
+    let (start, end) = source.lines;
+    let mut text = format!(
+        "Source of `{}` ({}, lines {}-{}):\n\n```rust\n{}\n```",
+        source.item, source.file, start, end, source.code
+    );
+    if source.truncated {
+        text.push_str(&format!(
+            "\n\nTruncated after {} lines.",
+            source.code.lines().count()
+        ));
+    }
+    text
+}
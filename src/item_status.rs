@@ -0,0 +1,84 @@
+//! Reads whether the item a rustdoc page documents is deprecated, unstable
+//! or only available with certain features or on certain platforms.
+//!
+//! rustdoc renders `#[deprecated]`, `#[unstable]` and `#[doc(cfg(...))]` as
+//! banners below the item's declaration. They are part of the extracted text,
+//! but easy for the LLM to overlook in a long page, so answers name them
+//! explicitly and the tool warns when a matched item has one. As with the
+//! headings in [`citations`](crate::citations), they are read from the page's
+//! HTML when needed.
+
+use scraper::{ElementRef, Html, Selector};
+
+/// Banners of the item a page documents; methods' banners aren't included
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemStatus {
+    /// e.g. "Deprecated since 1.2.0: use `connect` instead"
+    pub deprecated: Option<String>,
+    /// e.g. "This is a nightly-only experimental API. (`allocator_api` #32838)"
+    pub unstable: Option<String>,
+    /// e.g. "Available on crate feature `full` only."
+    pub availability: Option<String>,
+}
+
+impl ItemStatus {
+    /// Reads the banners of the page's item
+    pub fn from_page(page: &Html) -> Self {
+        // Only the item's own banners are direct children of the main content
+        let Ok(selector) = Selector::parse("section#main-content > .item-info .stab") else {
+            return Self::default();
+        };
+        let mut status = Self::default();
+        for banner in page.select(&selector) {
+            let class = |name| banner.value().classes().any(|class| class == name);
+            let slot = if class("deprecated") {
+                &mut status.deprecated
+            } else if class("unstable") {
+                &mut status.unstable
+            } else if class("portability") {
+                &mut status.availability
+            } else {
+                continue;
+            };
+            let text = banner_text(banner);
+            if slot.is_none() && !text.is_empty() {
+                *slot = Some(text);
+            }
+        }
+        status
+    }
+
+    /// Parses `html` and reads the banners of its item
+    pub fn from_html(html: &str) -> Self {
+        Self::from_page(&Html::parse_document(html))
+    }
+
+    /// Returns true if the item has none of the banners
+    pub fn is_empty(&self) -> bool {
+        self.deprecated.is_none() && self.unstable.is_none() && self.availability.is_none()
+    }
+
+    /// One line per banner, e.g. for the LLM context
+    pub fn notes(&self) -> Vec<&str> {
+        [&self.deprecated, &self.unstable, &self.availability]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Text of a banner on one line, without its emoji
+fn banner_text(banner: ElementRef) -> String {
+    let text: String = banner
+        .descendants()
+        .filter(|node| {
+            !node.ancestors().any(|ancestor| {
+                ElementRef::wrap(ancestor)
+                    .is_some_and(|element| element.value().classes().any(|c| c == "emoji"))
+            })
+        })
+        .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
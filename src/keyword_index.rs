@@ -0,0 +1,210 @@
+//! Inverted index of the words in a crate's documents.
+//!
+//! Built once when a crate is indexed and stored next to its embeddings in
+//! the global cache, so keyword lookups and BM25 ranking don't read or scan
+//! document text at query time. Words are Rust identifiers; `::` paths are
+//! indexed both whole and by their parts, so `Widget::resize` is found in a
+//! document mentioning `demo::Widget::resize`.
+
+use bincode::{Decode, Encode};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::platform_paths;
+use crate::utils::with_context;
+
+/// BM25 term frequency saturation
+const K1: f32 = 1.2;
+
+/// BM25 document length normalization
+const B: f32 = 0.75;
+
+/// Longest `::` path whose sub-paths are indexed, in segments
+const MAX_PATH_SEGMENTS: usize = 6;
+
+/// Documents containing each term of a crate, and how often
+#[derive(Debug, Clone, Default, PartialEq, Encode, Decode)]
+pub struct KeywordIndex {
+    /// Path of every document, by document number
+    paths: Vec<String>,
+    /// Number of words in every document, by document number
+    lengths: Vec<u32>,
+    /// Documents containing each term as `(document number, occurrences)`,
+    /// by ascending document number
+    postings: HashMap<String, Vec<(u32, u32)>>,
+}
+
+impl KeywordIndex {
+    /// Indexes the words of `documents`, given as `(path, text)` pairs
+    pub fn new<'a>(documents: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut index = Self::default();
+        for (number, (path, text)) in documents.into_iter().enumerate() {
+            let mut counts: HashMap<String, u32> = HashMap::new();
+            let mut length = 0;
+            for term in terms(text) {
+                if !term.contains("::") {
+                    length += 1;
+                }
+                *counts.entry(term).or_default() += 1;
+            }
+            for (term, count) in counts {
+                index
+                    .postings
+                    .entry(term)
+                    .or_default()
+                    .push((number as u32, count));
+            }
+            index.paths.push(path.to_string());
+            index.lengths.push(length);
+        }
+        index
+    }
+
+    /// Number of indexed documents
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Returns true if no documents are indexed
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Paths of the indexed documents, in the order they were indexed
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Number of documents containing `term`
+    pub fn document_frequency(&self, term: &str) -> usize {
+        self.postings.get(term).map_or(0, Vec::len)
+    }
+
+    /// Rarity of `identifier` between 0 (in every document) and 1 (in none);
+    /// paths are as rare as their last segment
+    pub fn idf(&self, identifier: &str) -> f32 {
+        if self.paths.is_empty() {
+            return 0.0;
+        }
+        let name = identifier.rsplit("::").next().unwrap_or(identifier);
+        let documents = self.paths.len() as f32;
+        let frequency = self.document_frequency(name) as f32;
+        ((documents + 1.0) / (frequency + 1.0)).ln() / (documents + 1.0).ln()
+    }
+
+    /// Returns true if the document at `path` contains `term` as a whole word
+    /// or `::` path
+    pub fn contains(&self, path: &str, term: &str) -> bool {
+        let Some(number) = self.paths.iter().position(|p| p == path) else {
+            return false;
+        };
+        self.postings.get(term).is_some_and(|postings| {
+            postings
+                .binary_search_by_key(&(number as u32), |(doc, _)| *doc)
+                .is_ok()
+        })
+    }
+
+    /// Ranks the documents containing any word of `query` by BM25, best
+    /// first. At most `limit` documents are returned.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(&str, f32)> {
+        if self.paths.is_empty() {
+            return Vec::new();
+        }
+        let documents = self.paths.len() as f32;
+        let average_length =
+            (self.lengths.iter().map(|&l| l as f32).sum::<f32>() / documents).max(1.0);
+
+        let mut query_terms: Vec<String> = terms(query).collect();
+        query_terms.sort();
+        query_terms.dedup();
+
+        let mut scores: HashMap<u32, f32> = HashMap::new();
+        for term in &query_terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+            let frequency = postings.len() as f32;
+            let idf = ((documents - frequency + 0.5) / (frequency + 0.5) + 1.0).ln();
+            for &(doc, count) in postings {
+                let count = count as f32;
+                let length = self.lengths[doc as usize] as f32;
+                let saturation =
+                    count * (K1 + 1.0) / (count + K1 * (1.0 - B + B * length / average_length));
+                *scores.entry(doc).or_default() += idf * saturation;
+            }
+        }
+
+        let mut ranked: Vec<(&str, f32)> = scores
+            .into_iter()
+            .map(|(doc, score)| (self.paths[doc as usize].as_str(), score))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        ranked.truncate(limit);
+        ranked
+    }
+
+    /// Reads an index written by [`KeywordIndex::save`], returning `None` if
+    /// there is none
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let path = platform_paths::long_path(path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let context = || format!("Failed to read keyword index {}", path.display());
+        let bytes = with_context(fs::read(&path), context)?;
+        let (index, _) = with_context(
+            bincode::decode_from_slice(&bytes, bincode::config::standard()),
+            context,
+        )?;
+        Ok(Some(index))
+    }
+
+    /// Writes the index with bincode
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = bincode::encode_to_vec(self, bincode::config::standard())?;
+        with_context(fs::write(platform_paths::long_path(path), bytes), || {
+            format!("Failed to write keyword index {}", path.display())
+        })
+    }
+}
+
+/// Terms of a text as indexed: every identifier, and every run of two or
+/// more `::`-separated identifiers in a path
+pub fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(is_word_char(c) || c == ':'))
+        .flat_map(|token| {
+            let token = token.trim_matches(':');
+            let segments: Vec<&str> = token.split("::").collect();
+            let mut terms: Vec<String> = token
+                .split(':')
+                .filter(|word| is_identifier(word))
+                .map(str::to_string)
+                .collect();
+            if segments.len() > 1
+                && segments.len() <= MAX_PATH_SEGMENTS
+                && segments.iter().all(|segment| is_identifier(segment))
+            {
+                for start in 0..segments.len() {
+                    for end in start + 2..=segments.len() {
+                        terms.push(segments[start..end].join("::"));
+                    }
+                }
+            }
+            terms
+        })
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_identifier(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && token.chars().all(is_word_char)
+}
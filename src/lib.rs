@@ -1,16 +1,74 @@
 // Export modules for use in examples and tests
+pub mod ann_index;
+pub mod answer_format;
+pub mod api_key;
+pub mod audit_log;
+pub mod boilerplate;
+pub mod budget;
+pub mod bundle;
+pub mod cache_manager;
+pub mod cargo_project;
+pub mod cassette;
+pub mod chunk_context;
+pub mod chunk_inspector;
+pub mod citations;
+pub mod code_examples;
+pub mod confidence;
+pub mod config;
+pub mod crate_discovery;
+pub mod crate_features;
+pub mod crate_sources;
+pub mod crate_spec;
+pub mod doc_freshness;
+pub mod doc_generator;
 pub mod doc_loader;
+pub mod doc_watcher;
+pub mod doc_workspace;
+pub mod docs_index;
+pub mod docs_links;
+pub mod doctor;
 pub mod document_chunker;
+pub mod document_store;
 pub mod embedding_cache_service;
 pub mod embeddings;
 pub mod error;
+pub mod error_data;
+pub mod extractive;
+pub mod global_cache;
+pub mod identifier_boost;
+pub mod index_manifest;
+pub mod ingest;
+pub mod item_finder;
+pub mod item_source;
+pub mod item_status;
+pub mod keyword_index;
+pub mod logging;
+pub mod markdown_export;
+pub mod mock;
+pub mod openai_client;
+pub mod page_text;
+pub mod parquet_export;
+pub mod platform_paths;
+pub mod question_router;
+pub mod redaction;
+pub mod retrieval_debug;
 pub mod server;
+pub mod source_loader;
+pub mod state;
+pub mod telemetry;
 pub mod utils;
+pub mod vector_store;
+
+// Test module
+#[cfg(test)]
+mod tests;
 
 // Re-export commonly used types for convenience
 pub use doc_loader::Document;
+pub use docs_index::{DocsIndex, SearchHit};
 pub use document_chunker::{Chunk, DocumentChunker};
 pub use embedding_cache_service::EmbeddingCacheService;
 pub use embeddings::{Embedding, EmbeddingProvider};
 pub use error::{Result, ServerError};
+pub use global_cache::GlobalCache;
 pub use server::RustDocsServer;
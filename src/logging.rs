@@ -0,0 +1,107 @@
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::error::{Result, ServerError};
+
+/// Target used for events about MCP log delivery itself; never mirrored to
+/// the client so a broken connection can't cause a feedback loop
+pub const MCP_LOG_TARGET: &str = "rustdocs_mcp_server::mcp_log";
+
+/// A warn/error event captured for mirroring to MCP logging notifications
+#[derive(Debug, Clone)]
+pub struct MirroredEvent {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+// Filled by `init`; taken once by the server that forwards the events
+static MIRROR_RECEIVER: OnceLock<Mutex<Option<UnboundedReceiver<MirroredEvent>>>> = OnceLock::new();
+
+/// Installs the global tracing subscriber.
+///
+/// Events go to stderr (stdout carries the MCP protocol) and, if `log_file` is
+/// given, are appended to that file as well. `filter` accepts a level
+/// (`debug`) or `RUST_LOG`-style directives (`info,rustdocs_mcp_server=trace`).
+pub fn init(filter: &str, log_file: Option<&Path>) -> Result<()> {
+    let env_filter = EnvFilter::try_new(filter)
+        .map_err(|e| ServerError::Config(format!("Invalid log level '{}': {}", filter, e)))?;
+
+    let file_layer = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file)),
+            )
+        }
+        None => None,
+    };
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let _ = MIRROR_RECEIVER.set(Mutex::new(Some(receiver)));
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .with(McpMirrorLayer { sender })
+        .try_init()
+        .map_err(|e| ServerError::Config(format!("Failed to initialize logging: {}", e)))
+}
+
+/// Takes the stream of warn/error events to mirror to the MCP client.
+/// Returns `None` if logging wasn't initialized or the stream was already taken.
+pub fn take_mirrored_events() -> Option<UnboundedReceiver<MirroredEvent>> {
+    MIRROR_RECEIVER.get()?.lock().ok()?.take()
+}
+
+/// Forwards warn and error events to a channel consumed by the server
+struct McpMirrorLayer {
+    sender: UnboundedSender<MirroredEvent>,
+}
+
+impl<S: Subscriber> Layer<S> for McpMirrorLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > Level::WARN || metadata.target() == MCP_LOG_TARGET {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        // Nobody listens before the server starts; dropping those events is fine
+        let _ = self.sender.send(MirroredEvent {
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+        });
+    }
+}
+
+/// Renders an event's message followed by its other fields as `key=value`
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.message);
+            let _ = write!(self.message, "{:?}{}", value, fields);
+        } else {
+            let _ = write!(self.message, " {}={:?}", field.name(), value);
+        }
+    }
+}
@@ -1,86 +1,503 @@
-// Declare modules
-mod doc_loader;
-mod document_chunker;
-mod embedding_cache_service;
-mod embeddings;
-mod error;
-mod server;
-
-// Test module
-#[cfg(test)]
-mod tests;
-
-// Use necessary items from modules and crates
-use crate::{embeddings::OPENAI_CLIENT, error::ServerError, server::RustDocsServer};
-use async_openai::{Client as OpenAIClient, config::OpenAIConfig};
-use clap::Parser;
-// Import rmcp items needed for the new approach
-use rmcp::{
-    ServiceExt,           // Import the ServiceExt trait for .serve() and .waiting()
-    transport::io::stdio, // Use the standard stdio transport
+// Use necessary items from the library crate
+use clap::{Parser, Subcommand, ValueEnum};
+use rustdocs_mcp_server::{
+    bundle, cache_manager, chunk_inspector,
+    config::Config,
+    crate_spec::CrateSpec,
+    doc_loader,
+    doc_workspace::{self, DocWorkspace},
+    doctor,
+    error::ServerError,
+    global_cache::{CacheKey, LOCAL_VERSION},
+    ingest, logging, markdown_export, parquet_export,
+    server::{self, RustDocsServer},
+    telemetry::Telemetry,
 };
-use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tracing::info;
 
 // --- CLI Argument Parsing ---
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "MCP server for querying Rust crate documentation", long_about = None)]
 struct Cli {
-    // No required arguments - server will use locally available crate docs
+    /// Maintenance command to run instead of starting the server
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Watch the doc directory and re-index crates in the background when their docs change
+    #[arg(long)]
+    watch: bool,
+
+    /// Serve only this crate (`name` or `name@version_req`) and pre-load it at startup
+    #[arg(long = "crate", value_name = "SPEC")]
+    crate_spec: Option<String>,
+
+    /// Features the crate's documentation was generated with (requires --crate)
+    #[arg(short = 'F', long, value_delimiter = ',', requires = "crate_spec")]
+    features: Vec<String>,
+
+    /// Project directory relative doc paths resolve against when the client
+    /// doesn't report workspace roots (defaults to the current directory)
+    #[arg(long, value_name = "DIR")]
+    project_dir: Option<PathBuf>,
+
+    /// Refuse to start when a startup check fails instead of running degraded
+    #[arg(long)]
+    strict: bool,
+
+    /// Forbid network calls; answer from cached embeddings with raw documentation
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Configuration file to use instead of ./rustdocs-mcp.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Directory containing `cargo doc` output (overrides docs.dir)
+    #[arg(long, global = true)]
+    doc_dir: Option<PathBuf>,
+
+    /// Embedding model (overrides embedding.model and EMBEDDING_MODEL)
+    #[arg(long, global = true)]
+    embedding_model: Option<String>,
+
+    /// Chat model used to answer questions (overrides llm.model and LLM_MODEL)
+    #[arg(long, global = true)]
+    llm_model: Option<String>,
+
+    /// Log verbosity: error, warn, info, debug, trace or RUST_LOG-style directives
+    #[arg(long, global = true, value_name = "LEVEL")]
+    log_level: Option<String>,
+
+    /// Also append logs to this file
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Loads the configuration files and applies the CLI overrides on top
+    fn load_config(&self) -> Result<Config, ServerError> {
+        let mut config = Config::load(self.config.as_deref())?;
+        if self.offline {
+            config.offline = true;
+        }
+        if self.strict {
+            config.strict = true;
+        }
+        if let Some(dir) = &self.doc_dir {
+            config.docs.dir = dir.clone();
+        }
+        if let Some(model) = &self.embedding_model {
+            config.embedding.model = model.clone();
+        }
+        if let Some(model) = &self.llm_model {
+            config.llm.model = model.clone();
+        }
+        if let Some(level) = &self.log_level {
+            config.logging.level = level.clone();
+        }
+        if let Some(file) = &self.log_file {
+            config.logging.file = Some(file.clone());
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect and maintain the embedding caches
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Diagnose the environment and print actionable fixes
+    Doctor,
+    /// Print the effective configuration as TOML
+    Config,
+    /// Write a crate's documents, chunks and embeddings to a JSONL bundle, or
+    /// its chunk embeddings to a Parquet table
+    Export {
+        /// Crate to export, as named in the doc directory
+        crate_name: String,
+        /// Cached version to export
+        #[arg(long, default_value = LOCAL_VERSION)]
+        version: String,
+        /// Features the cached index was built with
+        #[arg(short = 'F', long, value_delimiter = ',')]
+        features: Vec<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Jsonl)]
+        format: ExportFormat,
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Load a JSONL bundle created by `export` into the local caches
+    Import {
+        /// Bundle file to import
+        input: PathBuf,
+    },
+    /// Add Markdown or text documents, e.g. design docs or a wiki export, as a
+    /// pseudo-crate the tools can query
+    Ingest {
+        /// Name to query the documents under, like a crate name
+        name: String,
+        /// Files, or directories searched for .md, .markdown and .txt files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Embed the documents now instead of on the first query
+        #[arg(long)]
+        index: bool,
+    },
+    /// Show how the documentation of crates is processed
+    Inspect {
+        #[command(subcommand)]
+        action: InspectAction,
+    },
+    /// Summarize the local telemetry recorded with telemetry.enabled
+    Stats {
+        #[command(subcommand)]
+        action: StatsAction,
+    },
+    /// Write a crate's extracted documentation as one llms.txt-style Markdown file
+    LlmsTxt {
+        /// Crate to render, as named in the doc directory
+        crate_name: String,
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// File formats written by `export`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ExportFormat {
+    /// JSONL bundle that `import` can load back
+    Jsonl,
+    /// Parquet table with one row per chunk: crate, version, path, chunk_id, text, model, vector
+    Parquet,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Show size and entry counts per crate and embedding model
+    Stats,
+    /// Remove every cached embedding and crate index
+    Clear,
+    /// Remove chunk embeddings not referenced by any cached crate index and
+    /// enforce cache.max_size_mb
+    Prune,
+    /// Rename chunk embeddings cached under an older chunk id scheme to
+    /// their current ids
+    Migrate,
+    /// Remove stale cache entries and orphaned chunk embeddings, rewrite
+    /// crate indexes, rebuild ANN indexes and vacuum the telemetry database,
+    /// reporting the space reclaimed
+    Compact,
+    /// Read back every cache entry and report corrupt ones
+    Verify {
+        /// Delete entries that fail verification
+        #[arg(long)]
+        fix: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum StatsAction {
+    /// Print query latencies, cache hit rates and retrieval scores per crate
+    Report {
+        /// Only include the last N days
+        #[arg(long, value_name = "N")]
+        days: Option<u64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum InspectAction {
+    /// Print the chunk boundaries, sizes and ids of a crate's pages and how
+    /// stable the chunks are, to tune the [chunker] settings
+    Chunks {
+        /// Crate to inspect, as named in the doc directory
+        crate_name: String,
+        /// Directory containing `cargo doc` output (defaults to the configured doc roots)
+        doc_path: Option<PathBuf>,
+        /// Print only the summary statistics
+        #[arg(long)]
+        summary: bool,
+        /// Minimum chunk size in bytes (overrides chunker.min_chunk_size)
+        #[arg(long, value_name = "BYTES")]
+        min_size: Option<usize>,
+        /// Target chunk size in bytes (overrides chunker.target_chunk_size)
+        #[arg(long, value_name = "BYTES")]
+        target_size: Option<usize>,
+        /// Maximum chunk size in bytes (overrides chunker.max_chunk_size)
+        #[arg(long, value_name = "BYTES")]
+        max_size: Option<usize>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), ServerError> {
     // Load .env file if present
-    if let Ok(path) = dotenvy::dotenv() {
-        eprintln!("Loaded environment from: {}", path.display());
+    let dotenv_path = dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    let config = cli.load_config()?;
+    logging::init(&config.logging.level, config.logging.file.as_deref())?;
+    if let Some(path) = dotenv_path {
+        info!("Loaded environment from: {}", path.display());
+    }
+
+    match cli.command {
+        Some(Command::Cache { action }) => run_cache_command(action, &config),
+        Some(Command::Doctor) => run_doctor(&config).await,
+        Some(Command::Config) => {
+            print!("{}", config.to_toml()?);
+            Ok(())
+        }
+        Some(Command::Export {
+            crate_name,
+            version,
+            features,
+            format,
+            output,
+        }) => {
+            let key = CacheKey {
+                crate_name,
+                version,
+                features,
+            };
+            run_export(&config, &key, format, output)
+        }
+        Some(Command::Import { input }) => {
+            let reader = BufReader::new(File::open(&input)?);
+            let report = bundle::import_bundle(
+                reader,
+                &config.global_cache()?,
+                &config.chunk_cache_dir()?,
+                &config.chunker(),
+            )?;
+            println!("{}", report);
+            Ok(())
+        }
+        Some(Command::Ingest { name, paths, index }) => {
+            run_ingest(config, &name, &paths, index).await
+        }
+        Some(Command::Inspect { action }) => run_inspect_command(action, config),
+        Some(Command::Stats { action }) => run_stats_command(action, &config),
+        Some(Command::LlmsTxt { crate_name, output }) => run_llms_txt(&config, &crate_name, output),
+        None => {
+            let crate_scope = match cli.crate_spec {
+                Some(spec) => Some(CrateSpec::parse(&spec, cli.features)?),
+                None => None,
+            };
+            let project_dir = cli.project_dir.map(std::path::absolute).transpose()?;
+            server::serve_stdio(config, project_dir, crate_scope, cli.watch).await
+        }
     }
+}
 
-    // Parse CLI Arguments - now just a simple parse with no required args
-    let _cli = Cli::parse();
+/// Exports a cached crate to a bundle or Parquet file, or stdout if no file is given
+fn run_export(
+    config: &Config,
+    key: &CacheKey,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> Result<(), ServerError> {
+    let global = config.global_cache()?;
+    let chunk_dir = config.chunk_cache_dir()?;
+    let chunker = config.chunker();
 
-    // Initialize OpenAI Client
-    let openai_client = if let Ok(api_base) = env::var("OPENAI_API_BASE") {
-        let config = OpenAIConfig::new().with_api_base(api_base);
-        OpenAIClient::with_config(config)
-    } else {
-        OpenAIClient::new()
+    let report = match (format, &output) {
+        (ExportFormat::Jsonl, Some(path)) => {
+            let writer = io::BufWriter::new(File::create(path)?);
+            bundle::export_crate(&global, &chunk_dir, &chunker, key, writer)?
+        }
+        (ExportFormat::Jsonl, None) => {
+            bundle::export_crate(&global, &chunk_dir, &chunker, key, io::stdout().lock())?
+        }
+        (ExportFormat::Parquet, Some(path)) => {
+            let writer = io::BufWriter::new(File::create(path)?);
+            parquet_export::export_parquet(&global, &chunk_dir, &chunker, key, writer)?
+        }
+        (ExportFormat::Parquet, None) => {
+            parquet_export::export_parquet(&global, &chunk_dir, &chunker, key, io::stdout())?
+        }
     };
-    OPENAI_CLIENT
-        .set(openai_client.clone())
-        .expect("Failed to set OpenAI client");
-
-    // Check if the target/doc directory exists
-    let target_doc_path = std::path::Path::new("./target/doc");
-    if !target_doc_path.exists() {
-        eprintln!(
-            "Warning: ./target/doc directory not found. Run 'cargo doc' to generate documentation for local crates."
+    // Keep stdout clean for the export itself
+    eprintln!("{}", report);
+    Ok(())
+}
+
+/// Writes the documents at `paths` into the global cache as the pseudo-crate
+/// `name`, embedding them right away with `index`
+async fn run_ingest(
+    config: Config,
+    name: &str,
+    paths: &[PathBuf],
+    index: bool,
+) -> Result<(), ServerError> {
+    let documents = ingest::read_documents(paths)?;
+    if documents.is_empty() {
+        return Err(ServerError::Config(
+            "No .md, .markdown or .txt files found to ingest".to_string(),
+        ));
+    }
+    let report =
+        ingest::ingest_documents(&config.global_cache()?.ingested_dir(), name, &documents)?;
+    println!("{}", report);
+    if index {
+        let server = RustDocsServer::with_config(String::new(), config)?;
+        let index = server.index_crate(&report.crate_name).await?;
+        println!(
+            "Indexed {} documents of '{}'",
+            index.documents.len(),
+            index.crate_name
         );
     }
+    Ok(())
+}
 
-    // Create a simple startup message
-    let startup_message = "Rust Docs MCP server initialized. Use the query_rust_docs tool to query documentation for any crate that has been generated with 'cargo doc'.".to_string();
+/// Renders a crate's docs from the doc roots as Markdown
+fn run_llms_txt(
+    config: &Config,
+    crate_name: &str,
+    output: Option<PathBuf>,
+) -> Result<(), ServerError> {
+    let roots = config.docs.doc_roots();
+    let (root, documents) = doc_loader::load_documents_from_roots(&roots, crate_name)?;
+    let markdown = markdown_export::render_llms_txt(crate_name, &documents);
+    match &output {
+        Some(path) => std::fs::write(path, markdown)?,
+        None => io::stdout().lock().write_all(markdown.as_bytes())?,
+    }
+    // Keep stdout clean for the Markdown itself
+    eprintln!(
+        "Rendered {} pages of '{}' from {}",
+        documents.len(),
+        crate_name,
+        root.dir.display()
+    );
+    Ok(())
+}
 
-    // Create the service instance with simplified constructor
-    let service = RustDocsServer::new(startup_message)?;
+/// Runs an `inspect` subcommand
+fn run_inspect_command(action: InspectAction, mut config: Config) -> Result<(), ServerError> {
+    match action {
+        InspectAction::Chunks {
+            crate_name,
+            doc_path,
+            summary,
+            min_size,
+            target_size,
+            max_size,
+        } => {
+            let chunker = &mut config.chunker;
+            chunker.min_chunk_size = min_size.unwrap_or(chunker.min_chunk_size);
+            chunker.target_chunk_size = target_size.unwrap_or(chunker.target_chunk_size);
+            chunker.max_chunk_size = max_size.unwrap_or(chunker.max_chunk_size);
+            config.validate()?;
 
-    // Start the server via stdio
-    eprintln!("Rust Docs MCP server starting via stdio...");
+            let documents = match &doc_path {
+                Some(dir) => doc_loader::load_documents_from_doc_dir(dir, &crate_name)?,
+                None => {
+                    let roots = config.docs.doc_roots();
+                    doc_loader::load_documents_from_roots(&roots, &crate_name)?.1
+                }
+            };
+            let report =
+                chunk_inspector::inspect_chunks(&crate_name, &config.chunker(), &documents);
+            if !summary {
+                println!("{}", report.format_pages());
+            }
+            print!("{}", report);
+        }
+    }
+    Ok(())
+}
 
-    // Serve the server
-    let server_handle = service.serve(stdio()).await.map_err(|e| {
-        eprintln!("Failed to start server: {:?}", e);
-        ServerError::McpRuntime(e.to_string())
-    })?;
+/// Runs a `cache` maintenance subcommand against both cache locations
+fn run_cache_command(action: CacheAction, config: &Config) -> Result<(), ServerError> {
+    let chunk_dir = config.chunk_cache_dir()?;
+    let global = config.global_cache()?;
+    let chunker = config.chunker();
+
+    match action {
+        CacheAction::Stats => print!("{}", cache_manager::stats(&chunk_dir, &global, &chunker)),
+        CacheAction::Clear => println!("{}", cache_manager::clear(&chunk_dir, &global)?),
+        CacheAction::Prune => {
+            println!("{}", cache_manager::migrate(&chunk_dir, &chunker)?);
+            println!("{}", cache_manager::prune(&chunk_dir, &global, &chunker)?);
+            let removed = DocWorkspace::in_cache_root(global.root())?
+                .cleanup_stale(doc_workspace::DEFAULT_STALE_AFTER)?;
+            println!("Removed {} stale doc generation workspaces", removed);
+            if let Some(max_size_mb) = config.cache.max_size_mb {
+                let evicted =
+                    cache_manager::enforce_size_limit(&chunk_dir, max_size_mb * 1024 * 1024)?;
+                println!(
+                    "Evicted {} chunk embeddings ({}) to stay under {} MB",
+                    evicted.entries,
+                    cache_manager::format_bytes(evicted.bytes),
+                    max_size_mb
+                );
+            }
+        }
+        CacheAction::Migrate => {
+            println!("{}", cache_manager::migrate(&chunk_dir, &chunker)?)
+        }
+        CacheAction::Compact => {
+            let telemetry = config.telemetry_path()?;
+            print!(
+                "{}",
+                cache_manager::compact(&chunk_dir, &global, &chunker, Some(&telemetry))?
+            )
+        }
+        CacheAction::Verify { fix } => {
+            print!("{}", cache_manager::verify(&chunk_dir, &global, fix)?)
+        }
+    }
+    Ok(())
+}
 
-    eprintln!("Rust Docs MCP server running...");
+/// Runs a `stats` subcommand against the telemetry database
+fn run_stats_command(action: StatsAction, config: &Config) -> Result<(), ServerError> {
+    let path = config.telemetry_path()?;
+    match action {
+        StatsAction::Report { days } => {
+            if !path.is_file() {
+                println!(
+                    "No telemetry recorded at {}. Set `telemetry.enabled = true` to record queries.",
+                    path.display()
+                );
+                return Ok(());
+            }
+            let since =
+                days.map(|days| SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60));
+            print!("{}", Telemetry::open(&path)?.report(since)?);
+        }
+    }
+    Ok(())
+}
 
-    // Wait for the server to complete
-    server_handle.waiting().await.map_err(|e| {
-        eprintln!("Server encountered an error while running: {:?}", e);
-        ServerError::McpRuntime(e.to_string())
-    })?;
+/// Runs the environment diagnostics and exits non-zero if any check failed
+async fn run_doctor(config: &Config) -> Result<(), ServerError> {
+    let checks = doctor::run_diagnostics(config).await;
+    for check in &checks {
+        println!("{}", check);
+    }
 
-    eprintln!("Rust Docs MCP server stopped.");
+    let failures = checks
+        .iter()
+        .filter(|check| check.status == doctor::CheckStatus::Fail)
+        .count();
+    if failures > 0 {
+        println!("\n{} check(s) failed", failures);
+        std::process::exit(1);
+    }
+    println!("\nAll checks passed");
     Ok(())
 }
@@ -0,0 +1,181 @@
+//! Renders a crate's extracted documentation as a single Markdown file in the
+//! [llms.txt](https://llmstxt.org) layout, for pasting whole-crate context into
+//! other tools.
+
+use std::collections::BTreeMap;
+
+use crate::doc_loader::Document;
+
+/// File name under which the export is published, e.g. `crate://serde/llms.txt`
+pub const LLMS_TXT_NAME: &str = "llms.txt";
+
+/// Sections of the export in output order, keyed by rustdoc page prefix
+const SECTIONS: &[(&str, &str)] = &[
+    ("mod", "Modules"),
+    ("macro", "Macros"),
+    ("attr", "Macros"),
+    ("derive", "Macros"),
+    ("struct", "Structs"),
+    ("enum", "Enums"),
+    ("union", "Unions"),
+    ("trait", "Traits"),
+    ("traitalias", "Traits"),
+    ("fn", "Functions"),
+    ("type", "Type Aliases"),
+    ("constant", "Constants"),
+    ("static", "Statics"),
+    ("primitive", "Primitives"),
+    ("keyword", "Keywords"),
+];
+
+/// Heading for pages rustdoc names in some other way
+const OTHER_SECTION: &str = "Other Pages";
+
+/// A documented item: its section, qualified name and text
+struct Item<'a> {
+    section: &'static str,
+    name: String,
+    content: &'a str,
+}
+
+/// Renders `documents` (as loaded by `doc_loader`) as one Markdown file: a
+/// title, a summary line, the crate overview, then one section per item kind
+/// with an entry per page.
+pub fn render_llms_txt(crate_name: &str, documents: &[Document]) -> String {
+    let mut overview = None;
+    let mut sections: BTreeMap<usize, Vec<Item>> = BTreeMap::new();
+    for doc in documents {
+        if doc.path == "index.html" {
+            overview = Some(doc.content.as_str());
+            continue;
+        }
+        let item = classify(crate_name, doc);
+        sections
+            .entry(section_order(item.section))
+            .or_default()
+            .push(item);
+    }
+
+    let mut out = format!("# {}\n\n", crate_name);
+    out.push_str(&format!(
+        "> API documentation for the Rust crate `{}`, extracted from rustdoc output ({} pages).\n",
+        crate_name,
+        documents.len()
+    ));
+    if let Some(overview) = overview {
+        out.push_str("\n## Overview\n\n");
+        out.push_str(overview.trim());
+        out.push('\n');
+    }
+    for mut items in sections.into_values() {
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        out.push_str(&format!("\n## {}\n", items[0].section));
+        for item in items {
+            out.push_str(&format!("\n### `{}`\n\n", item.name));
+            out.push_str(item.content.trim());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Renders the `crate://<name>` overview of a crate: its front page, its
+/// modules and how many items of each kind it documents, pointing at the
+/// `llms.txt` export for everything else
+pub fn render_crate_overview(
+    crate_name: &str,
+    version: Option<&str>,
+    documents: &[Document],
+) -> String {
+    let mut overview = None;
+    let mut modules = Vec::new();
+    let mut counts: BTreeMap<usize, (&'static str, usize)> = BTreeMap::new();
+    for doc in documents {
+        let item = classify(crate_name, doc);
+        if item.section == "Modules" {
+            if doc.path == "index.html" {
+                overview = Some(doc.content.as_str());
+            }
+            modules.push(item.name);
+            continue;
+        }
+        let order = section_order(item.section);
+        counts.entry(order).or_insert((item.section, 0)).1 += 1;
+    }
+    modules.sort();
+    let items: usize = counts.values().map(|(_, count)| count).sum();
+
+    let mut out = match version {
+        Some(version) => format!("# {} {}\n\n", crate_name, version),
+        None => format!("# {}\n\n", crate_name),
+    };
+    out.push_str(&format!(
+        "> Rust crate `{}`: {} documented items in {} modules. The full documentation is at `crate://{}/{}`.\n",
+        crate_name,
+        items,
+        modules.len(),
+        crate_name,
+        LLMS_TXT_NAME
+    ));
+    if let Some(overview) = overview {
+        out.push_str("\n## Overview\n\n");
+        out.push_str(overview.trim());
+        out.push('\n');
+    }
+    if !modules.is_empty() {
+        out.push_str("\n## Modules\n\n");
+        for module in &modules {
+            out.push_str(&format!("- `{}`\n", module));
+        }
+    }
+    if !counts.is_empty() {
+        out.push_str("\n## Items\n\n| Kind | Count |\n|---|---|\n");
+        for (section, count) in counts.values() {
+            out.push_str(&format!("| {} | {} |\n", section, count));
+        }
+    }
+    out
+}
+
+/// Position of a section in the output, with unknown ones last
+fn section_order(section: &str) -> usize {
+    SECTIONS
+        .iter()
+        .position(|(_, title)| *title == section)
+        .unwrap_or(SECTIONS.len())
+}
+
+/// Works out an item's section and path-qualified name from its page path,
+/// e.g. `de/struct.Error.html` is the struct `crate::de::Error`
+fn classify<'a>(crate_name: &str, doc: &'a Document) -> Item<'a> {
+    let path = doc.path.replace('\\', "/");
+    let (dir, file) = match path.rsplit_once('/') {
+        Some((dir, file)) => (Some(dir), file),
+        None => (None, path.as_str()),
+    };
+    let mut segments = vec![crate_name.to_string()];
+    segments.extend(
+        dir.into_iter()
+            .flat_map(|dir| dir.split('/').map(str::to_string)),
+    );
+
+    let stem = file.strip_suffix(".html").unwrap_or(file);
+    let (section, name) = if stem == "index" {
+        ("Modules", None)
+    } else {
+        match stem.split_once('.') {
+            Some((kind, name)) => match SECTIONS.iter().find(|(prefix, _)| *prefix == kind) {
+                Some((_, title)) => (*title, Some(name)),
+                None => (OTHER_SECTION, Some(stem)),
+            },
+            None => (OTHER_SECTION, Some(stem)),
+        }
+    };
+    segments.extend(name.map(str::to_string));
+
+    Item {
+        section,
+        name: segments.join("::"),
+        content: &doc.content,
+    }
+}
@@ -0,0 +1,99 @@
+//! Deterministic stand-ins for the OpenAI embedding and chat APIs.
+//!
+//! Selected by setting `embedding.provider` or `llm.provider` to `mock` (or the
+//! `EMBEDDING_PROVIDER`/`LLM_PROVIDER` environment variables), so the whole
+//! pipeline, including `query_rust_docs`, runs without network access or an
+//! API key.
+
+use crate::doc_loader::Document;
+use crate::embeddings::{Embedding, EmbeddingProvider};
+
+/// Provider name selecting the mock backends
+pub const MOCK_PROVIDER: &str = "mock";
+
+/// Model name recorded for mock embeddings, so they never mix with real ones
+pub const MOCK_MODEL: &str = "mock";
+
+/// Dimensions of mock embedding vectors
+pub const MOCK_DIMENSIONS: usize = 256;
+
+/// Embeds text as a normalized bag of hashed, lowercased words.
+///
+/// The same text always yields the same vector, and texts sharing words score
+/// as similar, so retrieval behaves sensibly in tests.
+#[derive(Debug, Clone)]
+pub struct MockEmbeddingBackend {
+    dimensions: usize,
+}
+
+impl Default for MockEmbeddingBackend {
+    fn default() -> Self {
+        Self::new(MOCK_DIMENSIONS)
+    }
+}
+
+impl MockEmbeddingBackend {
+    /// Creates a backend producing vectors with `dimensions` entries
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    /// Returns the embedding of `text`
+    pub fn embed(&self, text: &str) -> Embedding {
+        let mut vector = vec![0.0f32; self.dimensions];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| !word.is_empty())
+        {
+            let hash = fnv1a(&word.to_lowercase());
+            vector[(hash % self.dimensions as u64) as usize] += 1.0;
+        }
+
+        let magnitude: f32 = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if magnitude > 0.0 {
+            for value in &mut vector {
+                *value /= magnitude;
+            }
+        }
+        Embedding::new(vector, EmbeddingProvider::Mock, MOCK_MODEL.to_string())
+    }
+}
+
+/// Answers questions by echoing them along with the documents that would
+/// have been sent to the LLM
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockChatProvider;
+
+impl MockChatProvider {
+    /// Returns a deterministic answer naming the question, context documents
+    /// and requested answer language
+    pub fn complete(
+        &self,
+        crate_name: &str,
+        question: &str,
+        language: Option<&str>,
+        context: &[&Document],
+    ) -> String {
+        let sources: Vec<&str> = context.iter().map(|doc| doc.path.as_str()).collect();
+        let mut answer = format!(
+            "Mock answer about '{}' for: {}\nSources: {}",
+            crate_name,
+            question,
+            sources.join(", ")
+        );
+        if let Some(language) = language {
+            answer.push_str(&format!("\nLanguage: {}", language));
+        }
+        answer
+    }
+}
+
+/// 64-bit FNV-1a, used instead of `DefaultHasher` because its output is
+/// stable across Rust releases
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,328 @@
+//! Client for OpenAI-compatible HTTP APIs, used for embeddings, chat
+//! completions and the doctor's connectivity check. The base URL, extra
+//! headers, proxy, timeout and retries are set up here once, and failures are
+//! classified the same way for every endpoint.
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use reqwest::{Client, Method, Proxy};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::cassette::{Cassette, CassetteMode, Interaction};
+use crate::config::{DEFAULT_API_BASE, HttpSettings, OpenAiSettings};
+use crate::error::{ProviderErrorKind, Result, ServerError};
+
+/// Delay before the first retry, doubled for every further one
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest wait between two attempts, even if the provider hints at more
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// An OpenAI-compatible API at one base URL. Clones share the API key, so
+/// replacing it on one replaces it on all.
+#[derive(Debug, Clone)]
+pub struct OpenAiClient {
+    http: Client,
+    // Without the trailing slash
+    api_base: String,
+    // Replaced when the key is rotated while the server runs
+    api_key: Arc<RwLock<String>>,
+    // Sent with every request on top of the authorization header
+    headers: HeaderMap,
+    // Longest wait for the response, and then for its body
+    read_timeout: Duration,
+    max_retries: u32,
+    // Records or replays the requests, if `http.cassette` is set
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl OpenAiClient {
+    /// Creates a client for the API at `api_base` (OpenAI's by default)
+    pub fn new(
+        api_key: String,
+        api_base: Option<&str>,
+        headers: &BTreeMap<String, String>,
+        settings: &HttpSettings,
+    ) -> Result<Self> {
+        let mut http = Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_secs))
+            .connect_timeout(Duration::from_secs(settings.connect_timeout_secs));
+        if let Some(proxy) = &settings.proxy {
+            http = http.proxy(parse_proxy(proxy)?);
+        }
+        Ok(Self {
+            http: http.build()?,
+            api_base: api_base
+                .unwrap_or(DEFAULT_API_BASE)
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: Arc::new(RwLock::new(api_key)),
+            headers: header_map(headers)?,
+            read_timeout: Duration::from_secs(settings.read_timeout_secs),
+            max_retries: settings.max_retries,
+            cassette: settings
+                .cassette
+                .as_deref()
+                .map(|path| Cassette::shared(path, settings.cassette_mode))
+                .transpose()?,
+        })
+    }
+
+    /// Records requests to, or replays them from, `cassette`
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Attributes requests to the configured organization and project
+    pub fn with_account(mut self, settings: &OpenAiSettings) -> Result<Self> {
+        self.headers.extend(header_map(&settings.headers())?);
+        Ok(self)
+    }
+
+    /// Sends `api_key` with all further requests
+    pub fn set_api_key(&self, api_key: String) {
+        if let Ok(mut current) = self.api_key.write() {
+            *current = api_key;
+        }
+    }
+
+    /// Returns the base URL requests are sent to
+    pub fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    /// Embeds `input` with `model`, returning the vector and the name of the
+    /// model the provider used. `input_type` is sent as an extra
+    /// `(field, value)` pair for providers that embed documents and queries
+    /// differently.
+    pub async fn embed(
+        &self,
+        model: &str,
+        input: &str,
+        input_type: Option<(&str, &str)>,
+    ) -> Result<(Vec<f32>, String)> {
+        #[derive(Deserialize)]
+        struct EmbeddingData {
+            embedding: Vec<f32>,
+        }
+
+        #[derive(Deserialize)]
+        struct EmbeddingResponse {
+            data: Vec<EmbeddingData>,
+            model: String,
+        }
+
+        let mut request = json!({ "input": input, "model": model });
+        if let Some((field, value)) = input_type {
+            request[field] = json!(value);
+        }
+        let response: EmbeddingResponse = self.post("embeddings", &request).await?;
+        match response.data.into_iter().next() {
+            Some(data) => Ok((data.embedding, response.model)),
+            None => Err(ServerError::Provider {
+                kind: ProviderErrorKind::Other,
+                status: None,
+                message: "No embedding data received from OpenAI".to_string(),
+                retry_after: None,
+            }),
+        }
+    }
+
+    /// Asks `model` to reply to `user` following the `system` prompt. Returns
+    /// `None` if the reply has no text.
+    pub async fn chat(&self, model: &str, system: &str, user: &str) -> Result<Option<String>> {
+        #[derive(Deserialize)]
+        struct Message {
+            content: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Choice {
+            message: Message,
+        }
+
+        #[derive(Deserialize)]
+        struct ChatResponse {
+            choices: Vec<Choice>,
+        }
+
+        let request = json!({
+            "model": model,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": user },
+            ],
+        });
+        let response: ChatResponse = self.post("chat/completions", &request).await?;
+        Ok(response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content))
+    }
+
+    /// Lists the provider's models once, without retrying, to check that the
+    /// API is reachable and accepts the key
+    pub async fn check_access(&self) -> Result<()> {
+        self.send::<Value>(Method::GET, "models", None)
+            .await
+            .map(|_| ())
+    }
+
+    /// POSTs `body` to `path` under the API base, retrying failures that may
+    /// succeed later
+    async fn post<R: DeserializeOwned>(&self, path: &str, body: &Value) -> Result<R> {
+        let url = format!("{}/{}", self.api_base, path);
+        let mut attempt = 0;
+        loop {
+            debug!(url = %url, attempt, "Sending provider request");
+            match self.send(Method::POST, path, Some(body)).await {
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    let delay = retry_delay(&e, attempt);
+                    warn!("Request to {} failed ({}); retrying in {:?}", url, e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends a request with the key and extra headers, or replays it from the
+    /// cassette, classifying failures
+    async fn send<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<R> {
+        let reply = match &self.cassette {
+            Some(cassette) if cassette.mode() == CassetteMode::Replay => {
+                cassette.replay(method.as_str(), path, body)?
+            }
+            cassette => {
+                let reply = self.fetch(method, path, body).await?;
+                if let Some(cassette) = cassette {
+                    cassette.record(reply.clone())?;
+                }
+                reply
+            }
+        };
+
+        let text = reply.body();
+        if !(200..300).contains(&reply.status) {
+            return Err(ServerError::from_api_response(
+                reply.status,
+                reply.retry_after(),
+                &text,
+            ));
+        }
+        serde_json::from_str(&text).map_err(|e| ServerError::Provider {
+            kind: ProviderErrorKind::Other,
+            status: Some(reply.status),
+            message: format!("Unexpected response from {}/{}: {}", self.api_base, path, e),
+            retry_after: None,
+        })
+    }
+
+    /// Sends a request to the provider, returning the exchange as recorded
+    async fn fetch(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Interaction> {
+        let api_key = self
+            .api_key
+            .read()
+            .map(|key| key.clone())
+            .unwrap_or_default();
+        let url = format!("{}/{}", self.api_base, path);
+        let mut request = self
+            .http
+            .request(method.clone(), &url)
+            .headers(self.headers.clone())
+            .bearer_auth(api_key);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = self
+            .read(request.send())
+            .await?
+            .map_err(ServerError::from_reqwest)?;
+
+        let status = response.status();
+        // Retry-After is given in seconds by the OpenAI API
+        let retry_after_secs = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        let text = self.read(response.text()).await?;
+        // Failures are classified by their status if their body can't be read
+        let text = if status.is_success() {
+            text.map_err(ServerError::from_reqwest)?
+        } else {
+            text.unwrap_or_default()
+        };
+        Ok(Interaction {
+            method: method.as_str().to_string(),
+            path: path.to_string(),
+            request: body.cloned(),
+            status: status.as_u16(),
+            // Retry hints only matter to failed requests
+            retry_after_secs: retry_after_secs.filter(|_| !status.is_success()),
+            response: serde_json::from_str(&text).unwrap_or(Value::String(text)),
+        })
+    }
+
+    /// Waits for `future` at most the read timeout, so a proxy that accepts
+    /// the connection but never answers doesn't block forever
+    async fn read<T>(&self, future: impl Future<Output = T>) -> Result<T> {
+        tokio::time::timeout(self.read_timeout, future)
+            .await
+            .map_err(|_| ServerError::Provider {
+                kind: ProviderErrorKind::Network,
+                status: None,
+                message: format!(
+                    "No response from {} within {}s",
+                    self.api_base,
+                    self.read_timeout.as_secs()
+                ),
+                retry_after: None,
+            })
+    }
+}
+
+/// Waits as long as the provider asked, or backs off exponentially
+fn retry_delay(error: &ServerError, attempt: u32) -> Duration {
+    let hinted = match error {
+        ServerError::Provider { retry_after, .. } => *retry_after,
+        _ => None,
+    };
+    hinted
+        .unwrap_or_else(|| INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(attempt)))
+        .min(MAX_BACKOFF)
+}
+
+/// Parses configured extra headers, failing on names or values that aren't
+/// valid in HTTP
+pub fn header_map(headers: &BTreeMap<String, String>) -> Result<HeaderMap> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| ServerError::Config(format!("Invalid header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value).map_err(|e| {
+            ServerError::Config(format!("Invalid value of header '{}': {}", name, e))
+        })?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+/// Parses the configured proxy URL
+pub fn parse_proxy(proxy: &str) -> Result<Proxy> {
+    Proxy::all(proxy)
+        .map_err(|e| ServerError::Config(format!("Invalid http.proxy '{}': {}", proxy, e)))
+}
@@ -0,0 +1,87 @@
+//! Extracts the text of rustdoc pages without the interface chrome rustdoc
+//! puts around the documentation.
+//!
+//! Every item and method carries `Source` links, `§` anchors, `[−]` toggles,
+//! `Run` buttons on examples and `1.0.0` since-version labels, and item lists
+//! repeat `Deprecated` or `Experimental` badges. Repeated on every page, this
+//! text makes unrelated chunks look alike to the embedding model. It is left
+//! out of the extracted content; the version the page's item is stable since
+//! is read separately, for the page's citation.
+//!
+//! The loader, [`citations`](crate::citations) and
+//! [`code_examples`](crate::code_examples) all extract text this way, so
+//! lines found in the HTML can be matched to the extracted content.
+
+use scraper::{ElementRef, Html, Selector};
+
+/// Classes of elements that hold interface text rather than documentation
+const CHROME_CLASSES: &[&str] = &[
+    // `1.0.0 · Source` to the right of items, `[−][src]` in older rustdoc
+    "rightside",
+    "out-of-band",
+    "src",
+    "srclink",
+    "since",
+    // `§` next to headings
+    "anchor",
+    "doc-anchor",
+    // `[−]` and `Expand description` toggles
+    "collapse-toggle",
+    "hideme",
+    // `Run` links and the `ⓘ` next to examples that don't compile or panic
+    "test-arrow",
+    "tooltip",
+];
+
+/// Classes of the item lists of module pages, whose stability badges are chrome
+const ITEM_LIST_CLASSES: &[&str] = &["item-table", "item-name", "item-left"];
+
+/// Text of `element` without interface chrome: one line per text node,
+/// trimmed, leaving out empty ones
+pub fn extract(element: ElementRef) -> String {
+    let mut parts = Vec::new();
+    collect(element, false, &mut parts);
+    parts.join("\n")
+}
+
+/// The Rust version the page's item is stable since, as shown next to its
+/// title (only standard library docs have one)
+pub fn page_since(page: &Html) -> Option<String> {
+    let selector = Selector::parse(
+        "section#main-content .main-heading .since, section#main-content .out-of-band .since",
+    )
+    .ok()?;
+    let text: String = page.select(&selector).next()?.text().collect();
+    // Some releases render "since 1.0.0" rather than just the version
+    let version = text.trim().trim_start_matches("since").trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+fn collect<'a>(element: ElementRef<'a>, in_item_list: bool, parts: &mut Vec<&'a str>) {
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            let text: &'a str = text;
+            let text = text.trim();
+            if !text.is_empty() {
+                parts.push(text);
+            }
+        } else if let Some(child) = ElementRef::wrap(child) {
+            let in_item_list = in_item_list
+                || child
+                    .value()
+                    .classes()
+                    .any(|class| ITEM_LIST_CLASSES.contains(&class));
+            if !is_chrome(child, in_item_list) {
+                collect(child, in_item_list, parts);
+            }
+        }
+    }
+}
+
+fn is_chrome(element: ElementRef, in_item_list: bool) -> bool {
+    let value = element.value();
+    value.name() == "button"
+        || value
+            .classes()
+            .any(|class| CHROME_CLASSES.contains(&class) || (in_item_list && class == "stab"))
+}
@@ -0,0 +1,123 @@
+//! Exports a cached crate's chunk embeddings as a Parquet table, one row per
+//! chunk, so the corpus can be queried with DuckDB or pandas or loaded into an
+//! external vector database.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::types::Float32Type;
+use arrow_array::{ArrayRef, ListArray, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::bundle::{ExportReport, load_cached_index, read_cached_chunk};
+use crate::document_chunker::DocumentChunker;
+use crate::error::Result;
+use crate::global_cache::{CacheKey, CachedCrateIndex, GlobalCache};
+
+/// Rows buffered before a record batch is written
+const ROWS_PER_BATCH: usize = 1024;
+
+/// Schema of the exported table: `crate`, `version`, `path`, `chunk_id`,
+/// `text`, `model` and the embedding as a `vector` list of floats
+pub fn schema() -> SchemaRef {
+    let text = |name: &str| Field::new(name, DataType::Utf8, false);
+    Arc::new(Schema::new(vec![
+        text("crate"),
+        text("version"),
+        text("path"),
+        text("chunk_id"),
+        text("text"),
+        text("model"),
+        Field::new(
+            "vector",
+            DataType::List(Arc::new(Field::new_list_field(DataType::Float32, true))),
+            false,
+        ),
+    ]))
+}
+
+/// A chunk waiting to be written
+struct Row<'a> {
+    path: &'a str,
+    chunk_id: String,
+    text: String,
+    model: String,
+    vector: Vec<f32>,
+}
+
+/// Writes the chunk embeddings of a cached crate as a Snappy-compressed Parquet file
+pub fn export_parquet(
+    global: &GlobalCache,
+    chunk_dir: &Path,
+    chunker: &DocumentChunker,
+    key: &CacheKey,
+    writer: impl Write + Send,
+) -> Result<ExportReport> {
+    let index = load_cached_index(global, key)?;
+    let schema = schema();
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(writer, Arc::clone(&schema), Some(properties))?;
+
+    let mut report = ExportReport {
+        documents: index.documents.len(),
+        ..ExportReport::default()
+    };
+    let mut rows = Vec::with_capacity(ROWS_PER_BATCH);
+    for doc in &index.documents {
+        for chunk in chunker.embeddable_chunks(&doc.content) {
+            let Some(cached) = read_cached_chunk(chunk_dir, &chunk.id) else {
+                report.missing_chunks += 1;
+                continue;
+            };
+            rows.push(Row {
+                path: &doc.path,
+                chunk_id: chunk.id,
+                text: cached.document,
+                model: cached.model,
+                vector: cached.vector,
+            });
+            report.chunks += 1;
+            if rows.len() == ROWS_PER_BATCH {
+                writer.write(&record_batch(&schema, &index, &rows)?)?;
+                rows.clear();
+            }
+        }
+    }
+    if !rows.is_empty() {
+        writer.write(&record_batch(&schema, &index, &rows)?)?;
+    }
+
+    writer.close()?;
+    Ok(report)
+}
+
+fn record_batch(schema: &SchemaRef, index: &CachedCrateIndex, rows: &[Row]) -> Result<RecordBatch> {
+    let repeated =
+        |value: &str| -> ArrayRef { Arc::new(StringArray::from(vec![value; rows.len()])) };
+    let column = |value: for<'r> fn(&'r Row<'r>) -> &'r str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(rows.iter().map(value)))
+    };
+    let vectors = ListArray::from_iter_primitive::<Float32Type, _, _>(
+        rows.iter()
+            .map(|row| Some(row.vector.iter().copied().map(Some))),
+    );
+
+    Ok(RecordBatch::try_new(
+        Arc::clone(schema),
+        vec![
+            repeated(&index.crate_name),
+            repeated(&index.version),
+            column(|row| row.path),
+            column(|row| &row.chunk_id),
+            column(|row| &row.text),
+            column(|row| &row.model),
+            Arc::new(vectors),
+        ],
+    )?)
+}
@@ -0,0 +1,190 @@
+//! Where the server keeps its files on each platform, and path handling that
+//! behaves the same on Windows and Unix.
+//!
+//! Every per-user directory is resolved by [`ProjectDirs`], names derived
+//! from crates, versions or features go through [`safe_file_name`] before
+//! they become path components, and paths handed to the file system go
+//! through [`long_path`] so deep cache entries work on Windows too.
+
+#[cfg(windows)]
+use std::path::Component;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, ServerError};
+
+/// Directory name of the configuration under the platform config directory
+const CONFIG_DIR_NAME: &str = "rustdocs-mcp";
+
+/// Directory name of the global crate cache under the platform data directory
+const DATA_DIR_NAME: &str = "rustdocs-mcp-server";
+
+/// Directory name of the chunk embedding cache
+const EMBEDDING_CACHE_DIR_NAME: &str = "rust-doc-embedding-cache";
+
+/// Subdirectory of the XDG cache directory holding chunk embeddings
+#[cfg(not(windows))]
+const XDG_EMBEDDING_CACHE_DIR_NAME: &str = "embeddings";
+
+/// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Paths this long or longer need the `\\?\` prefix on Windows
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// The server's per-user directories on this platform:
+///
+/// | | Linux and macOS | Windows |
+/// |---|---|---|
+/// | config | `$XDG_CONFIG_HOME/rustdocs-mcp` (`~/Library/Application Support` on macOS) | `%APPDATA%\rustdocs-mcp` |
+/// | data | `$XDG_DATA_HOME/rustdocs-mcp-server` | `%LOCALAPPDATA%\rustdocs-mcp-server` |
+/// | embedding cache | `$XDG_CACHE_HOME/rustdocs-mcp-server/embeddings` | `%LOCALAPPDATA%\rust-doc-embedding-cache` |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectDirs {
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    embedding_cache_dir: PathBuf,
+    legacy_embedding_cache_dir: Option<PathBuf>,
+}
+
+impl ProjectDirs {
+    /// Resolves the directories of the current user
+    pub fn new() -> Result<Self> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| ServerError::Config("Could not find the config directory".to_string()))?
+            .join(CONFIG_DIR_NAME);
+        Ok(Self {
+            config_dir,
+            data_dir: data_dir()?,
+            embedding_cache_dir: embedding_cache_dir()?,
+            legacy_embedding_cache_dir: legacy_embedding_cache_dir(),
+        })
+    }
+
+    /// Directory of the per-user configuration file
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    /// Root of the global crate cache
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Directory of the chunk embedding cache
+    pub fn embedding_cache_dir(&self) -> &Path {
+        &self.embedding_cache_dir
+    }
+
+    /// Where earlier versions kept the chunk embedding cache, if elsewhere
+    pub fn legacy_embedding_cache_dir(&self) -> Option<&Path> {
+        self.legacy_embedding_cache_dir.as_deref()
+    }
+}
+
+#[cfg(not(windows))]
+fn data_dir() -> Result<PathBuf> {
+    let dirs = xdg::BaseDirectories::with_prefix(DATA_DIR_NAME)
+        .map_err(|e| ServerError::Config(format!("Failed to resolve XDG directories: {}", e)))?;
+    Ok(dirs.get_data_home())
+}
+
+#[cfg(windows)]
+fn data_dir() -> Result<PathBuf> {
+    dirs::data_local_dir()
+        .map(|dir| dir.join(DATA_DIR_NAME))
+        .ok_or_else(|| ServerError::Config("Could not find local data directory".to_string()))
+}
+
+#[cfg(not(windows))]
+fn embedding_cache_dir() -> Result<PathBuf> {
+    let dirs = xdg::BaseDirectories::with_prefix(DATA_DIR_NAME)
+        .map_err(|e| ServerError::Config(format!("Failed to resolve XDG directories: {}", e)))?;
+    Ok(dirs.get_cache_home().join(XDG_EMBEDDING_CACHE_DIR_NAME))
+}
+
+#[cfg(windows)]
+fn embedding_cache_dir() -> Result<PathBuf> {
+    dirs::cache_dir()
+        .map(|dir| dir.join(EMBEDDING_CACHE_DIR_NAME))
+        .ok_or_else(|| ServerError::Config("Could not find the cache directory".to_string()))
+}
+
+#[cfg(not(windows))]
+fn legacy_embedding_cache_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|dir| dir.join(format!(".{}", EMBEDDING_CACHE_DIR_NAME)))
+}
+
+#[cfg(windows)]
+fn legacy_embedding_cache_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Turns `name` into a file name that's valid on every platform: characters
+/// other than ASCII letters, digits, `.`, `-` and `_` become `_`, and names
+/// Windows reserves (`CON`, `aux.html`, names ending in a dot, ...) get a
+/// `_` appended to their first part
+pub fn safe_file_name(name: &str) -> String {
+    let mut safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if safe.is_empty() || safe.ends_with('.') {
+        safe.push('_');
+    }
+    let stem_len = safe.find('.').unwrap_or(safe.len());
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(&safe[..stem_len]))
+    {
+        safe.insert(stem_len, '_');
+    }
+    safe
+}
+
+/// Renders a relative path with `/` separators, so document paths and cache
+/// keys derived from them are the same on every platform
+pub fn portable_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns a path the file system accepts however long it is: on Windows,
+/// absolute paths of `MAX_PATH` (260) characters or more get the `\\?\`
+/// prefix; elsewhere paths are returned as they are
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    let normalized = path
+        .components()
+        .all(|component| !matches!(component, Component::CurDir | Component::ParentDir));
+    if text.len() < MAX_PATH || !path.is_absolute() || !normalized || text.starts_with(r"\\?\") {
+        return path.to_path_buf();
+    }
+    // Verbatim paths are taken literally, so they must only use backslashes
+    let text = text.replace('/', r"\");
+    match text.strip_prefix(r"\\") {
+        Some(share) => PathBuf::from(format!(r"\\?\UNC\{}", share)),
+        None => PathBuf::from(format!(r"\\?\{}", text)),
+    }
+}
+
+/// Returns a path the file system accepts however long it is: on Windows,
+/// absolute paths of `MAX_PATH` (260) characters or more get the `\\?\`
+/// prefix; elsewhere paths are returned as they are
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
@@ -0,0 +1,118 @@
+//! Routes questions to the cheapest source that can answer them.
+//!
+//! Most questions need semantic retrieval and an LLM, but two common kinds
+//! don't: asking where an item is (`HashMap::entry`, "where is `StreamExt`
+//! defined?") is answered exactly by the item index, and asking which version
+//! of a crate the project uses by Cargo's metadata. Classification is a few
+//! cheap string checks; questions it isn't sure about take the full path.
+
+/// Where a question is answered from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    /// The question only names an item, given here as its path or name
+    ItemLookup(String),
+    /// The question asks which version of the crate is used
+    Version,
+    /// Everything else: semantic retrieval and the LLM
+    Semantic,
+}
+
+/// Phrases asking where an item is, followed by its name
+const LOOKUP_PREFIXES: &[&str] = &[
+    "where is ",
+    "where's ",
+    "where are ",
+    "find ",
+    "locate ",
+    "full path of ",
+    "path of ",
+    "path to ",
+    "which module is ",
+    "which module contains ",
+    "what module is ",
+];
+
+/// Phrases after an item's name that keep the question a lookup
+const LOOKUP_SUFFIXES: &[&str] = &[" defined", " declared", " located", " exported", " in"];
+
+/// Phrases asking for the version of a crate
+const VERSION_PHRASES: &[&str] = &[
+    "what version",
+    "which version",
+    "version of",
+    "version do",
+    "version am",
+    "version are",
+    "what release",
+    "which release",
+];
+
+/// Phrases making a version question about an item's history, which the
+/// docs answer
+const HISTORY_PHRASES: &[&str] = &["since", "added", "introduced", "stable", "deprecated"];
+
+/// Decides where `question` is answered from
+pub fn classify(question: &str) -> Route {
+    let text = question.trim().trim_end_matches(['?', '.', '!']).trim();
+    let lower = text.to_lowercase();
+
+    if VERSION_PHRASES.iter().any(|phrase| lower.contains(phrase))
+        && !HISTORY_PHRASES.iter().any(|phrase| lower.contains(phrase))
+    {
+        return Route::Version;
+    }
+
+    let mut candidate = text;
+    if let Some(rest) = LOOKUP_PREFIXES
+        .iter()
+        .find_map(|prefix| strip_prefix_ignore_case(text, prefix))
+    {
+        // "Where is `Foo` defined" and the like
+        candidate = LOOKUP_SUFFIXES
+            .iter()
+            .find_map(|suffix| strip_suffix_ignore_case(rest, suffix))
+            .unwrap_or(rest)
+            .trim();
+        // "where is the `Foo` struct"
+        candidate = candidate.strip_prefix("the ").unwrap_or(candidate);
+        for kind in [" struct", " trait", " enum", " function", " macro", " type"] {
+            candidate = candidate.strip_suffix(kind).unwrap_or(candidate);
+        }
+    }
+    let item = candidate
+        .trim()
+        .trim_matches('`')
+        .trim_end_matches("()")
+        .trim_end_matches('!');
+    if is_item_path(item) {
+        Route::ItemLookup(item.to_string())
+    } else {
+        Route::Semantic
+    }
+}
+
+/// Returns true for a name or `::` path of Rust identifiers
+fn is_item_path(text: &str) -> bool {
+    !text.is_empty()
+        && text.split("::").all(|segment| {
+            segment
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
+
+fn strip_suffix_ignore_case<'a>(text: &'a str, suffix: &str) -> Option<&'a str> {
+    let start = text.len().checked_sub(suffix.len())?;
+    let tail = text.get(start..)?;
+    tail.eq_ignore_ascii_case(suffix).then(|| &text[..start])
+}
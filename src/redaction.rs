@@ -0,0 +1,77 @@
+//! Scrubs text before it is sent to remote embedding and chat providers.
+//! The original text stays in the local caches; only the request bodies are
+//! redacted.
+
+use regex::Regex;
+use std::borrow::Cow;
+
+use crate::config::RedactionSettings;
+use crate::error::{Result, ServerError};
+
+/// Text substituted for a match when a rule doesn't name its own
+pub const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+
+/// Patterns enabled by `redaction.secrets`: common API keys, tokens and
+/// private key blocks
+const SECRET_PATTERNS: &[&str] = &[
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\bsk-[A-Za-z0-9_-]{20,}",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+    r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]{16,}=*",
+];
+
+/// Compiled redaction rules, applied in order
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    /// Compiles the configured rules, after the built-in secret patterns if enabled
+    pub fn new(settings: &RedactionSettings) -> Result<Self> {
+        let builtin = if settings.secrets {
+            SECRET_PATTERNS
+        } else {
+            &[]
+        };
+        let builtin = builtin
+            .iter()
+            .map(|pattern| (*pattern, DEFAULT_REPLACEMENT));
+        let configured = settings.rules.iter().map(|rule| {
+            (
+                rule.pattern.as_str(),
+                rule.replacement.as_deref().unwrap_or(DEFAULT_REPLACEMENT),
+            )
+        });
+
+        let rules = builtin
+            .chain(configured)
+            .map(|(pattern, replacement)| {
+                let regex = Regex::new(pattern).map_err(|e| {
+                    ServerError::Config(format!("Invalid redaction pattern '{}': {}", pattern, e))
+                })?;
+                Ok((regex, replacement.to_string()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Returns true if any rule is configured
+    pub fn is_active(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Returns `text` with every match replaced, borrowing it if nothing matched.
+    /// Replacements may refer to capture groups as `$1` or `${name}`.
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut redacted = Cow::Borrowed(text);
+        for (regex, replacement) in &self.rules {
+            if let Cow::Owned(replaced) = regex.replace_all(&redacted, replacement.as_str()) {
+                redacted = Cow::Owned(replaced);
+            }
+        }
+        redacted
+    }
+}
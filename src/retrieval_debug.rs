@@ -0,0 +1,133 @@
+//! Explains how an answer came about, for the `debug_retrieval` tool: the
+//! documents ranked for the question with their scores, why the ones left
+//! out of the answer were left out, and the prompt the LLM is sent.
+
+use std::fmt;
+
+/// Characters of a candidate's text shown in reports
+pub const PREVIEW_CHARS: usize = 160;
+
+/// What became of a document ranked for the question
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Sent to the LLM, taking up this many tokens of the context
+    Sent { tokens: usize },
+    /// Its similarity is below `retrieval.min_score`
+    BelowMinScore,
+    /// Ranked below the `top_k` documents answered from
+    BeyondTopK,
+    /// Among the `top_k`, but better matches used up the context budget
+    OverBudget,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Sent { tokens } => write!(f, "sent, {} tokens", tokens),
+            Outcome::BelowMinScore => f.write_str("filtered: below min_score"),
+            Outcome::BeyondTopK => f.write_str("filtered: beyond top_k"),
+            Outcome::OverBudget => f.write_str("filtered: context budget used up"),
+        }
+    }
+}
+
+/// A document ranked for the question
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub crate_name: String,
+    pub path: String,
+    /// Similarity of the document's embedding to the question's
+    pub similarity: f32,
+    /// Score it was ranked by: the similarity plus any identifier boost
+    pub score: f32,
+    /// The start of its text, whitespace collapsed
+    pub preview: String,
+    pub outcome: Outcome,
+}
+
+/// How a question is answered from a crate's documentation
+#[derive(Debug, Clone)]
+pub struct RetrievalReport {
+    pub crate_name: String,
+    pub question: String,
+    pub top_k: usize,
+    pub min_score: f32,
+    /// Tokens of documentation the LLM may be sent, if limited
+    pub context_tokens: Option<usize>,
+    /// Best first
+    pub candidates: Vec<Candidate>,
+    /// How the answer is produced from the documents sent, e.g. `LLM
+    /// (gpt-4o-mini)` or `extractive`
+    pub answer_mode: String,
+    /// System and user prompts the LLM is sent, if it is asked
+    pub prompt: Option<(String, String)>,
+    /// Whether the `[redaction]` rules were applied to the prompt
+    pub redacted: bool,
+}
+
+/// The first `max_chars` characters of `text` with runs of whitespace
+/// collapsed into single spaces, ending in `…` if cut
+pub fn preview(text: &str, max_chars: usize) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}…", &collapsed[..cut]),
+        None => collapsed,
+    }
+}
+
+impl fmt::Display for RetrievalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Retrieval for '{}': {}", self.crate_name, self.question)?;
+        let budget = match self.context_tokens {
+            Some(tokens) => format!("{} tokens", tokens),
+            None => "unlimited".to_string(),
+        };
+        writeln!(
+            f,
+            "top_k {}, min_score {:.3}, context budget {}",
+            self.top_k, self.min_score, budget
+        )?;
+        writeln!(f, "Answer: {}", self.answer_mode)?;
+
+        writeln!(f, "\nCandidates ({}):", self.candidates.len())?;
+        if self.candidates.is_empty() {
+            writeln!(f, "  none, the crate has no searchable documents")?;
+        }
+        for (rank, candidate) in self.candidates.iter().enumerate() {
+            let boost = candidate.score - candidate.similarity;
+            let boost = if boost.abs() > f32::EPSILON {
+                format!(
+                    " (similarity {:.3} + boost {:.3})",
+                    candidate.similarity, boost
+                )
+            } else {
+                String::new()
+            };
+            writeln!(
+                f,
+                "{:>3}. {:.3}{} {}: {} [{}]",
+                rank + 1,
+                candidate.score,
+                boost,
+                candidate.crate_name,
+                candidate.path,
+                candidate.outcome
+            )?;
+            if !candidate.preview.is_empty() {
+                writeln!(f, "     {}", candidate.preview)?;
+            }
+        }
+
+        match &self.prompt {
+            Some((system, user)) => {
+                let redacted = if self.redacted { ", redacted" } else { "" };
+                write!(
+                    f,
+                    "\nSystem prompt:\n{}\n\nUser prompt{}:\n{}",
+                    system, redacted, user
+                )
+            }
+            None => write!(f, "\nNo prompt: the LLM isn't asked"),
+        }
+    }
+}
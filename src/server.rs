@@ -1,22 +1,63 @@
 use crate::{
-    doc_loader::{self, Document},
+    ann_index::{AnnIndex, AnnIndexes},
+    answer_format::{Answer, AnswerFormat},
+    api_key,
+    audit_log::{self, AuditEvent, AuditLog, AuditMatch, QueryEvent, ToolCallEvent},
+    boilerplate,
+    budget::Spend,
+    cache_manager,
+    cargo_project,
+    chunk_context,
+    citations,
+    code_examples,
+    confidence::{self, Confidence},
+    config::{Config, DocBackend, DocRootSettings, DocsSettings},
+    crate_discovery::{self, DiscoveredCrate},
+    crate_features,
+    crate_spec::{CrateSpec, canonical_crate_name, normalize_crate_name},
+    doc_generator::{DocGenerator, GenerationMethod},
+    doc_loader::{self, DocLoaderError, Document},
+    doc_watcher::DocWatcher,
+    doc_workspace::{DEFAULT_STALE_AFTER, DocWorkspace},
+    docs_links,
+    doctor::{self, CheckStatus, StartupMode},
+    document_store::DocumentStore,
     embedding_cache_service::EmbeddingCacheService,
-    embeddings::{Embedding, OPENAI_CLIENT, cosine_similarity},
+    embeddings::{CachedDocumentEmbedding, Embedding, EmbeddingProvider},
     error::ServerError, // Keep ServerError for ::new()
+    error_data::{self, Suggestion},
+    extractive,
+    global_cache::{self, CacheKey, CachedCrateIndex, GlobalCache},
+    identifier_boost,
+    index_manifest::IndexManifest,
+    ingest,
+    item_finder,
+    item_source,
+    item_status::ItemStatus,
+    keyword_index::KeywordIndex,
+    logging::{self, MCP_LOG_TARGET},
+    markdown_export::{self, LLMS_TXT_NAME},
+    mock::{MOCK_PROVIDER, MockChatProvider},
+    openai_client::OpenAiClient,
+    question_router::{self, Route},
+    retrieval_debug::{self, Candidate, Outcome, RetrievalReport},
+    source_loader,
+    state::{CrateIndex, ServerState, SkippedDocument},
+    telemetry::{IndexRecord, QueryRecord, Telemetry},
+    utils,
+    vector_store::VectorStore,
 };
-use async_openai::{
-    types::{
-        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-        CreateChatCompletionRequestArgs,
-    },
-    // Client as OpenAIClient, // Removed unused import
-};
-use rmcp::model::AnnotateAble; // Import trait for .no_annotation()
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
 use rmcp::{
     Error as McpError,
     Peer,
     ServerHandler, // Import necessary rmcp items
+    ServiceExt,
+    handler::server::tool::ToolCallContext,
     model::{
+        Annotations,
+        CallToolRequestParam,
         CallToolResult,
         Content,
         GetPromptRequestParam,
@@ -25,6 +66,7 @@ use rmcp::{
         ListPromptsResult, // Removed EmptyObject, ErrorCode
         ListResourceTemplatesResult,
         ListResourcesResult,
+        ListToolsResult,
         LoggingLevel, // Uncommented ListToolsResult
         LoggingMessageNotification,
         LoggingMessageNotificationMethod,
@@ -33,67 +75,721 @@ use rmcp::{
         PaginatedRequestParam,
         ProtocolVersion,
         RawResource,
+        RawResourceTemplate,
         /* Prompt, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole, */ // Removed Prompt types
         ReadResourceRequestParam,
         ReadResourceResult,
         Resource,
         ResourceContents,
+        ResourceTemplate,
+        ResourceUpdatedNotificationParam,
         ServerCapabilities,
         ServerInfo,
         ServerNotification,
+        SubscribeRequestParam,
+        Tool,
+        UnsubscribeRequestParam,
     },
     service::{RequestContext, RoleServer},
     tool,
+    transport::io::stdio,
 };
 use schemars::JsonSchema; // Import JsonSchema
 use serde::Deserialize; // Import Deserialize
-use serde_json::json;
-use std::{/* borrow::Cow, */ env, sync::Arc}; // Removed borrow::Cow
+use serde_json::{Value, json};
+use std::{
+    /* borrow::Cow, */ collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime},
+}; // Removed borrow::Cow
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Level, debug, error, info, info_span, instrument, warn};
+
+/// Appended to every tool's description. rmcp doesn't carry MCP tool
+/// annotations yet, so `readOnlyHint` and `idempotentHint` are stated here.
+const TOOL_HINTS: &str = "Read-only and idempotent: it never modifies the project, so calls are safe to repeat or retry.";
 
 // --- Argument Struct for the Tool ---
 
+// Examples shown to clients in the argument schemas
+fn example_question() -> &'static str {
+    "How do I run a blocking function from async code?"
+}
+
+fn example_crate_name() -> &'static str {
+    "tokio"
+}
+
+fn example_versioned_crate_name() -> &'static str {
+    "serde@1.0.219"
+}
+
+fn example_language() -> &'static str {
+    "Japanese"
+}
+
+fn example_item() -> &'static str {
+    "StreamExt::buffer_unordered"
+}
+
+fn example_source_item() -> &'static str {
+    "Client::send"
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 struct QueryRustDocsArgs {
-    #[schemars(description = "The specific question about the crate's API or usage.")]
+    #[schemars(
+        description = "The specific question about the crate's API or usage.",
+        example = "example_question",
+        length(min = 1)
+    )]
+    question: String,
+    #[schemars(
+        description = "The crate name to load documentation from (uses locally generated docs). Use `name@version` to query a specific crates.io release.",
+        example = "example_crate_name",
+        length(min = 1)
+    )]
+    crate_name: String,
+    #[schemars(
+        description = "Name of a configured project whose documentation to search. Omit for the default project."
+    )]
+    #[serde(default)]
+    project: Option<String>,
+    #[schemars(
+        description = "Natural language to write the answer in, e.g. `Japanese` or `German`. Code and identifiers stay unchanged. Omit for the configured default.",
+        example = "example_language"
+    )]
+    #[serde(default)]
+    language: Option<String>,
+    #[schemars(
+        description = "Number of best-matching documents to answer from; more gives fuller answers but takes longer. Omit for the configured default.",
+        range(min = 1)
+    )]
+    #[serde(default)]
+    top_k: Option<usize>,
+    #[schemars(
+        description = "Most tokens of documentation to send to the LLM; fewer is faster and cheaper. Long documents are cut to their most relevant part. Omit for the configured limit.",
+        range(min = 1)
+    )]
+    #[serde(default)]
+    max_context_tokens: Option<usize>,
+    #[schemars(
+        description = "Whether to keep the code examples of the documentation in the context and ask for example code. Defaults to true."
+    )]
+    #[serde(default)]
+    include_examples: Option<bool>,
+    #[schemars(
+        description = "Whether to answer with the documentation's sentences that best match the question, quoted verbatim, instead of a generated summary. Fast, free and never makes things up, but doesn't combine or explain. Omit for the configured default."
+    )]
+    #[serde(default)]
+    extractive: Option<bool>,
+    #[schemars(
+        description = "Shape of the answer: `text` (prose, the default), `markdown`, `json` (answer, links, confidence and warnings as fields) or `code` (only the answer's code blocks)."
+    )]
+    #[serde(default)]
+    format: AnswerFormat,
+    #[schemars(
+        description = "Milliseconds to wait for the answer. If embedding or the LLM would take longer, the best result available by then is returned and marked as partial, e.g. the matched documentation without a summary. Omit for the configured budget.",
+        range(min = 1)
+    )]
+    #[serde(default)]
+    latency_budget_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct BatchQuestion {
+    #[schemars(
+        description = "The specific question about the crate's API or usage.",
+        example = "example_question",
+        length(min = 1)
+    )]
+    question: String,
+    #[schemars(
+        description = "The crate to answer this question from, if not the batch's `crate_name`. Use `name@version` to query a specific crates.io release.",
+        example = "example_versioned_crate_name"
+    )]
+    #[serde(default)]
+    crate_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct QueryRustDocsBatchArgs {
+    #[schemars(
+        description = "The questions to answer, at most 10.",
+        length(min = 1, max = 10)
+    )]
+    questions: Vec<BatchQuestion>,
+    #[schemars(
+        description = "The crate to answer questions from when they don't name their own. Use `name@version` to query a specific crates.io release.",
+        example = "example_crate_name"
+    )]
+    #[serde(default)]
+    crate_name: Option<String>,
+    #[schemars(
+        description = "Name of a configured project whose documentation to search. Omit for the default project."
+    )]
+    #[serde(default)]
+    project: Option<String>,
+    #[schemars(
+        description = "Natural language to write the answers in, e.g. `Japanese` or `German`. Code and identifiers stay unchanged. Omit for the configured default.",
+        example = "example_language"
+    )]
+    #[serde(default)]
+    language: Option<String>,
+    #[schemars(
+        description = "Milliseconds to wait for the answers. Questions not answered by then get the best result available, marked as partial. Omit for the configured budget.",
+        range(min = 1)
+    )]
+    #[serde(default)]
+    latency_budget_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListCrateFeaturesArgs {
+    #[schemars(
+        description = "The crate whose Cargo features to list.",
+        example = "example_crate_name",
+        length(min = 1)
+    )]
+    crate_name: String,
+    #[schemars(
+        description = "Name of a configured project whose dependencies to look in. Omit for the default project."
+    )]
+    #[serde(default)]
+    project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FindItemArgs {
+    #[schemars(
+        description = "Name or path of the item, e.g. `Bytes`, `io::copy` or `StreamExt::buffer_unordered`.",
+        example = "example_item",
+        length(min = 1)
+    )]
+    item: String,
+    #[schemars(
+        description = "Name of a configured project whose documentation to search. Omit for the default project."
+    )]
+    #[serde(default)]
+    project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct IndexStatusArgs {
+    #[schemars(
+        description = "The crate whose index to describe. Omit for every indexed crate.",
+        example = "example_crate_name"
+    )]
+    #[serde(default)]
+    crate_name: Option<String>,
+    #[schemars(
+        description = "Name of a configured project whose indexes to describe. Omit for the default project."
+    )]
+    #[serde(default)]
+    project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GetSourceArgs {
+    #[schemars(
+        description = "The crate the item belongs to.",
+        example = "example_crate_name",
+        length(min = 1)
+    )]
+    crate_name: String,
+    #[schemars(
+        description = "Path of the item, e.g. `Client`, `de::from_str` or `Client::send` for a method.",
+        example = "example_source_item",
+        length(min = 1)
+    )]
+    item: String,
+    #[schemars(
+        description = "Name of a configured project whose documentation to search. Omit for the default project."
+    )]
+    #[serde(default)]
+    project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct DebugRetrievalArgs {
+    #[schemars(
+        description = "The question whose answer to explain.",
+        example = "example_question",
+        length(min = 1)
+    )]
     question: String,
     #[schemars(
-        description = "The crate name to load documentation from (uses locally generated docs)."
+        description = "The crate the question is about. Use `name@version` for a specific crates.io release.",
+        example = "example_crate_name",
+        length(min = 1)
     )]
     crate_name: String,
+    #[schemars(
+        description = "Name of a configured project whose documentation to search. Omit for the default project."
+    )]
+    #[serde(default)]
+    project: Option<String>,
+    #[schemars(
+        description = "Number of best-matching documents to answer from, as for query_rust_docs. Omit for the configured default.",
+        range(min = 1)
+    )]
+    #[serde(default)]
+    top_k: Option<usize>,
+    #[schemars(
+        description = "Most tokens of documentation to send to the LLM, as for query_rust_docs. Omit for the configured limit.",
+        range(min = 1)
+    )]
+    #[serde(default)]
+    max_context_tokens: Option<usize>,
+    #[schemars(
+        description = "Whether to show the prompt with the configured redaction rules applied, as the LLM is sent it. Defaults to true."
+    )]
+    #[serde(default)]
+    redact: Option<bool>,
 }
 
 // --- Main Server Struct ---
 
-// No longer needs ServerState, holds data directly
+/// Documentation generated for a specific crates.io release, kept in the global cache
+#[derive(Debug, Clone)]
+struct VersionedDocs {
+    doc_dir: PathBuf,
+    key: CacheKey,
+}
+
 #[derive(Clone)] // Add Clone for tool macro requirements
 pub struct RustDocsServer {
+    config: Arc<Config>,                                 // Effective configuration
     embedding_cache_service: Arc<EmbeddingCacheService>, // Embedding cache service
-    peer: Arc<Mutex<Option<Peer<RoleServer>>>>,          // Uses tokio::sync::Mutex
-    startup_message: Arc<Mutex<Option<String>>>,         // Keep the message itself
+    chat_client: Arc<OpenAiClient>,                      // Chat completions API
+    global_cache: Option<Arc<GlobalCache>>, // Per-crate document/embedding cache, if available
+    ann_indexes: Arc<AnnIndexes>,           // HNSW graphs of large crates opened by queries
+    crate_scope: Option<Arc<CrateSpec>>,    // Only this crate may be queried, if set
+    degraded: Option<Arc<str>>,             // Why startup fell back to retrieval only
+    versioned_docs: Arc<RwLock<HashMap<String, VersionedDocs>>>, // Extra doc roots by crate dir name
+    indexing_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>, // Serializes indexing per crate
+    state: Arc<ServerState>, // Crates indexed in memory, reused across queries
+    telemetry: Option<Arc<Telemetry>>, // Local query statistics, if enabled
+    audit_log: Option<Arc<AuditLog>>, // Tool calls and answers, if enabled
+    project_root: Arc<RwLock<Option<PathBuf>>>, // Project reported by the client's roots
+    project_states: Arc<HashMap<String, Arc<ServerState>>>, // Indexes of the `[[projects]]`
+    peer: Arc<Mutex<Option<Peer<RoleServer>>>>, // Uses tokio::sync::Mutex
+    subscriptions: Arc<Mutex<HashSet<String>>>, // Resource URIs the client subscribed to
+    startup_message: Arc<Mutex<Option<String>>>, // Keep the message itself
     startup_message_sent: Arc<Mutex<bool>>, // Flag to track if sent (using tokio::sync::Mutex)
-                                            // tool_name and info are handled by ServerHandler/macros now
+                             // tool_name and info are handled by ServerHandler/macros now
+}
+
+/// Per-query overrides of the retrieval and answer settings, as taken by
+/// `query_rust_docs`. Unset options fall back to the configuration.
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Natural language to write the answer in, else `llm.answer_language`
+    pub language: Option<String>,
+    /// Documents answered from, else `retrieval.top_k`; capped at
+    /// `retrieval.max_top_k`
+    pub top_k: Option<usize>,
+    /// Tokens of documentation sent to the LLM; capped at
+    /// `retrieval.max_context_tokens`
+    pub max_context_tokens: Option<usize>,
+    /// Keep code examples in the context and ask for example code (default)
+    pub include_examples: Option<bool>,
+    /// Quote the best matching sentences instead of generating an answer,
+    /// else `llm.extractive`
+    pub extractive: Option<bool>,
+    /// Shape of the returned answer, prose by default
+    pub format: AnswerFormat,
+    /// Time to wait for the answer before returning the best result so far,
+    /// else `retrieval.latency_budget_ms`
+    pub latency_budget: Option<Duration>,
+}
+
+impl QueryOptions {
+    /// Options that only set the answer's language
+    pub fn with_language(language: Option<&str>) -> Self {
+        Self {
+            language: language.map(str::to_string),
+            ..Self::default()
+        }
+    }
+}
+
+/// Where a query's time went before its documents were searched, for
+/// telemetry, and when the answer is due
+struct QueryTiming {
+    started: Instant,
+    load: Duration,
+    embed: Duration,
+    question_cached: bool,
+    /// End of the query's latency budget, if it has one
+    deadline: Option<Instant>,
+}
+
+/// The index and companion indexes of the crate a batched question is about,
+/// and how long loading them took
+type LoadedQuery = (
+    Result<(Arc<CrateIndex>, Vec<Arc<CrateIndex>>), McpError>,
+    Duration,
+);
+
+/// A document ranked for a question
+struct RankedMatch<'a> {
+    path: &'a str,
+    /// Similarity of the document's embedding to the question's
+    similarity: f32,
+    /// Score ranked by: the similarity plus any identifier boost
+    score: f32,
 }
 
 impl RustDocsServer {
     // Updated constructor - simplified to only initialize cache service and messaging
     pub fn new(startup_message: String) -> Result<Self, ServerError> {
-        // Get OpenAI API key from environment
-        let openai_api_key = env::var("OPENAI_API_KEY")
-            .map_err(|_| ServerError::MissingEnvVar("OPENAI_API_KEY".to_string()))?;
+        Self::with_config(startup_message, Config::from_env())
+    }
+
+    /// Creates a server using an explicitly loaded configuration
+    pub fn with_config(startup_message: String, config: Config) -> Result<Self, ServerError> {
+        // Get the OpenAI API key; offline mode and mock providers never use it
+        let openai_api_key = match api_key::load_api_key(&config.openai)? {
+            Some(key) => key,
+            None if !config.requires_api_key() => String::new(),
+            None => return Err(ServerError::MissingEnvVar("OPENAI_API_KEY".to_string())),
+        };
+
+        // Embeddings and chat share the HTTP settings but may use different endpoints
+        let chat_client = OpenAiClient::new(
+            openai_api_key.clone(),
+            config.llm.api_base.as_deref(),
+            &config.llm.headers,
+            &config.http,
+        )?
+        .with_account(&config.openai)?;
+        let embedding_cache_service = EmbeddingCacheService::with_config(openai_api_key, &config)?;
+
+        // The global cache only speeds up re-indexing, so run without it if unavailable
+        let global_cache = match &config.cache.global_dir {
+            Some(dir) => GlobalCache::with_root(dir.clone()),
+            None => GlobalCache::new(),
+        };
+        let global_cache = match global_cache {
+            Ok(cache) => Some(Arc::new(cache)),
+            Err(e) => {
+                warn!("Global crate cache unavailable: {}", e);
+                None
+            }
+        };
+
+        // Drop shim projects left behind by generations that haven't run in a while
+        if let Some(global_cache) = &global_cache {
+            match DocWorkspace::in_cache_root(global_cache.root())
+                .and_then(|workspace| workspace.cleanup_stale(DEFAULT_STALE_AFTER))
+            {
+                Ok(0) => {}
+                Ok(removed) => info!("Removed {} stale doc generation workspaces", removed),
+                Err(e) => warn!("Failed to clean up doc generation workspaces: {}", e),
+            }
+        }
 
-        // Initialize the embedding cache service
-        let embedding_cache_service = EmbeddingCacheService::new(openai_api_key)?;
+        // Telemetry is optional, so serve without it if the database can't be opened
+        let telemetry = if config.telemetry.enabled {
+            match config
+                .telemetry_path()
+                .and_then(|path| Telemetry::open(&path))
+            {
+                Ok(telemetry) => Some(Arc::new(telemetry)),
+                Err(e) => {
+                    warn!("Telemetry unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let audit_log = if config.audit.enabled {
+            match config
+                .audit_path()
+                .and_then(|path| AuditLog::open(&path, config.audit.max_answer_chars))
+            {
+                Ok(audit_log) => Some(Arc::new(audit_log)),
+                Err(e) => {
+                    warn!("Audit log unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let project_states = config
+            .projects
+            .iter()
+            .map(|project| (project.name.clone(), Arc::new(ServerState::new())))
+            .collect();
+
+        let max_open_ann_indexes = config.retrieval.max_open_ann_indexes;
 
         // Keep ServerError for potential future init errors
         Ok(Self {
+            config: Arc::new(config),
             embedding_cache_service: Arc::new(embedding_cache_service),
+            chat_client: Arc::new(chat_client),
+            global_cache,
+            ann_indexes: Arc::new(AnnIndexes::new(max_open_ann_indexes)),
+            crate_scope: None,
+            degraded: None,
+            versioned_docs: Arc::new(RwLock::new(HashMap::new())),
+            indexing_locks: Arc::new(Mutex::new(HashMap::new())),
+            state: Arc::new(ServerState::new()),
+            telemetry,
+            audit_log,
+            project_root: Arc::new(RwLock::new(None)),
+            project_states: Arc::new(project_states),
             peer: Arc::new(Mutex::new(None)), // Uses tokio::sync::Mutex
+            subscriptions: Arc::new(Mutex::new(HashSet::new())),
             startup_message: Arc::new(Mutex::new(Some(startup_message))), // Initialize message
             startup_message_sent: Arc::new(Mutex::new(false)), // Initialize flag to false
         })
     }
 
+    /// Resolves relative doc paths against `root` until the client reports its
+    /// workspace roots
+    pub fn with_project_root(self, root: PathBuf) -> Self {
+        if let Ok(mut current) = self.project_root.write() {
+            *current = Some(root);
+        }
+        self
+    }
+
+    /// Tells clients that answers are raw documentation when running offline
+    fn mode_instructions(&self) -> String {
+        match (&self.degraded, self.config.offline) {
+            (Some(reason), _) => format!(
+                " The server is running in degraded retrieval-only mode ({}): answers are the \
+                 raw text of the best-matching documentation pages instead of a summary, and \
+                 only crates whose embeddings are already cached can be queried.",
+                reason
+            ),
+            (None, true) => " The server is running in offline mode: answers are the raw text \
+                 of the best-matching documentation pages instead of a summary, and only crates \
+                 whose embeddings are already cached can be queried."
+                .to_string(),
+            (None, false) => String::new(),
+        }
+    }
+
+    /// Tells clients which projects the `project` argument accepts, if any
+    fn projects_instructions(&self) -> String {
+        if self.config.projects.is_empty() {
+            return String::new();
+        }
+        let names: Vec<&str> = self
+            .config
+            .projects
+            .iter()
+            .map(|project| project.name.as_str())
+            .collect();
+        format!(
+            " Pass 'project' to query one of these projects instead of the default one: {}.",
+            names.join(", ")
+        )
+    }
+
+    /// Returns a view of the server for the configured project `name`: doc
+    /// paths resolve against its directory and its crates are indexed apart
+    /// from every other project's
+    pub fn for_project(&self, name: &str) -> Result<Self, McpError> {
+        let (Some(project), Some(state)) =
+            (self.config.project(name), self.project_states.get(name))
+        else {
+            let available: Vec<&str> = self
+                .config
+                .projects
+                .iter()
+                .map(|project| project.name.as_str())
+                .collect();
+            return Err(McpError::invalid_params(
+                format!("Unknown project '{}'", name),
+                Some(json!({ "available_projects": available })),
+            ));
+        };
+        let mut view = self.clone();
+        view.project_root = Arc::new(RwLock::new(Some(project.dir.clone())));
+        view.state = Arc::clone(state);
+        Ok(view)
+    }
+
+    /// Restricts the server to a single crate; queries for other crates are rejected
+    pub fn with_crate_scope(mut self, spec: CrateSpec) -> Self {
+        self.crate_scope = Some(Arc::new(spec));
+        self
+    }
+
+    /// Records that the server runs retrieval only because a startup check
+    /// failed, so clients are told why answers are raw documentation
+    pub fn with_degraded_mode(mut self, reason: &str) -> Self {
+        self.degraded = Some(Arc::from(reason));
+        self
+    }
+
+    /// Returns the crate this server is restricted to, if any
+    pub fn crate_scope(&self) -> Option<&CrateSpec> {
+        self.crate_scope.as_deref()
+    }
+
+    /// Loads the scoped crate once so its embeddings are cached before the first query.
+    /// Returns the number of documents indexed, or `None` without a crate scope.
+    pub async fn preload_scoped_crate(&self) -> Result<Option<usize>, ServerError> {
+        let Some(spec) = self.crate_scope.clone() else {
+            return Ok(None);
+        };
+        let index = self
+            .load_custom_crate_docs(&spec.doc_name(), &CancellationToken::new())
+            .await
+            .map_err(|e| ServerError::McpRuntime(e.message.to_string()))?;
+        Ok(Some(index.documents.len()))
+    }
+
+    /// Indexes `crate_name`, or reuses its in-memory index, without answering a query
+    pub async fn index_crate(&self, crate_name: &str) -> Result<Arc<CrateIndex>, ServerError> {
+        let to_server_error = |e: McpError| ServerError::McpRuntime(e.message.to_string());
+        self.check_crate_scope(crate_name)
+            .map_err(to_server_error)?;
+        let crate_name = self
+            .resolve_crate_name(crate_name)
+            .map_err(to_server_error)?;
+        self.load_custom_crate_docs(&crate_name, &CancellationToken::new())
+            .await
+            .map_err(to_server_error)
+    }
+
+    /// Returns the `k` documents of `index` most similar to `query`, best first,
+    /// with their similarity scores
+    pub async fn search_index(
+        &self,
+        index: &CrateIndex,
+        query: &str,
+        k: usize,
+    ) -> Result<Vec<(Document, f32)>, ServerError> {
+        let query_embedding = self
+            .embedding_cache_service
+            .get_question_embedding(query)
+            .await?;
+        let mut hits = Vec::new();
+        for (path, score) in self.find_best_matches(query, &query_embedding, index, k) {
+            let doc = utils::with_context(index.documents.get(path), || {
+                format!("Failed to read document text of {}", path)
+            })?;
+            if let Some(doc) = doc {
+                hits.push((doc, score));
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Writes the crates indexed in memory to the global cache, so the next
+    /// start can restore them. Returns the number of crates written.
+    pub fn save_snapshot(&self) -> Result<usize, ServerError> {
+        match &self.global_cache {
+            Some(global_cache) => self.state.save_snapshot(&global_cache.snapshot_path()),
+            None => Ok(0),
+        }
+    }
+
+    /// Restores the crates indexed before the last stop whose docs are
+    /// unchanged and which were embedded with the current provider, model and
+    /// chunker. Returns the number of crates restored.
+    pub fn restore_snapshot(&self) -> Result<usize, ServerError> {
+        let Some(global_cache) = &self.global_cache else {
+            return Ok(0);
+        };
+        let chunker = self.embedding_cache_service.chunker();
+        let model = self.embedding_cache_service.model();
+        self.state.load_snapshot(
+            &global_cache.snapshot_path(),
+            &self.config.memory,
+            |index| {
+                index.manifest.as_ref().is_some_and(|manifest| {
+                    manifest.embedding_provider == self.config.embedding.provider
+                        && manifest.embedding_model == model
+                        && manifest.chunker.min_chunk_size == chunker.min_chunk_size()
+                        && manifest.chunker.target_chunk_size == chunker.target_chunk_size()
+                        && manifest.chunker.max_chunk_size == chunker.max_chunk_size()
+                })
+            },
+        )
+    }
+
+    /// Indexes the crates listed in `docs.preload` in a background task so the
+    /// first query for them doesn't pay the indexing cost. Progress is logged
+    /// and crates outside the crate scope are skipped.
+    pub fn spawn_preload(&self) {
+        if self.config.docs.preload.is_empty() {
+            return;
+        }
+        let server = self.clone();
+        tokio::spawn(async move {
+            let crates = server.preload_targets();
+            let total = crates.len();
+            info!("Preloading {} crates in the background", total);
+            for (i, crate_name) in crates.iter().enumerate() {
+                match server
+                    .load_custom_crate_docs(crate_name, &CancellationToken::new())
+                    .await
+                {
+                    Ok(index) => info!(
+                        "Preloaded crate '{}' ({} documents, {}/{})",
+                        crate_name,
+                        index.documents.len(),
+                        i + 1,
+                        total
+                    ),
+                    Err(e) => warn!("Failed to preload crate '{}': {}", crate_name, e.message),
+                }
+            }
+        });
+    }
+
+    /// Crates named in `docs.preload`, with `"*"` expanded to every documented crate
+    fn preload_targets(&self) -> Vec<String> {
+        let available = self.get_available_crates();
+        let mut targets: Vec<String> = Vec::new();
+        for entry in &self.config.docs.preload {
+            let names = if entry == "*" {
+                available.clone()
+            } else {
+                vec![canonical_crate_name(entry)]
+            };
+            for name in names {
+                let in_scope = self
+                    .crate_scope
+                    .as_ref()
+                    .is_none_or(|spec| spec.matches(&name));
+                if in_scope && !targets.contains(&name) {
+                    targets.push(name);
+                }
+            }
+        }
+        targets
+    }
+
+    /// Rejects crates outside the crate scope with an explanation
+    fn check_crate_scope(&self, crate_name: &str) -> Result<(), McpError> {
+        match &self.crate_scope {
+            Some(spec) if !spec.matches(crate_name) => Err(McpError::invalid_params(
+                format!(
+                    "This server only serves documentation for '{}' (started with --crate {}). \
+                     Ask about '{}' instead, or start the server without --crate to query '{}'.",
+                    spec.name, spec, spec.name, crate_name
+                ),
+                Some(json!({ "crate_name": crate_name, "allowed": spec.name })),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     // Helper function to send log messages via MCP notification (remains mostly the same)
     pub fn send_log(&self, level: LoggingLevel, message: String) {
         let peer_arc = Arc::clone(&self.peer);
@@ -112,286 +808,3441 @@ impl RustDocsServer {
                 let server_notification =
                     ServerNotification::LoggingMessageNotification(log_notification);
                 if let Err(e) = peer.send_notification(server_notification).await {
-                    eprintln!("Failed to send MCP log notification: {}", e);
+                    debug!(target: MCP_LOG_TARGET, "Failed to send MCP log notification: {}", e);
+                }
+            } else {
+                debug!(target: MCP_LOG_TARGET, "Log task ran but MCP peer was not connected.");
+            }
+        });
+    }
+
+    /// Mirrors warn and error events from the tracing subscriber to the client
+    /// as MCP logging notifications. Does nothing if logging wasn't initialized.
+    pub fn forward_log_events(&self) {
+        let Some(mut events) = logging::take_mirrored_events() else {
+            return;
+        };
+        let server = self.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                let level = if event.level == Level::ERROR {
+                    LoggingLevel::Error
+                } else {
+                    LoggingLevel::Warning
+                };
+                server.send_log(level, event.message);
+            }
+        });
+    }
+
+    /// Tells the connected client that the set of available crates changed
+    async fn notify_resource_list_changed(&self) {
+        let peer = self.peer.lock().await.clone();
+        if let Some(peer) = peer
+            && let Err(e) = peer.notify_resource_list_changed().await
+        {
+            warn!("Failed to send resource list changed notification: {}", e);
+        }
+    }
+
+    /// Tells the client that the resources of `crate_name` changed, if it
+    /// subscribed to any of them
+    async fn notify_crate_updated(&self, crate_name: &str) {
+        let uris: Vec<String> = self
+            .subscriptions
+            .lock()
+            .await
+            .iter()
+            .filter(|uri| {
+                Self::resource_crate_name(uri).is_some_and(|name| {
+                    normalize_crate_name(name) == normalize_crate_name(crate_name)
+                })
+            })
+            .cloned()
+            .collect();
+        if uris.is_empty() {
+            return;
+        }
+        let peer = self.peer.lock().await.clone();
+        let Some(peer) = peer else {
+            return;
+        };
+        for uri in uris {
+            debug!("Resource {} updated; notifying client", uri);
+            if let Err(e) = peer
+                .notify_resource_updated(ResourceUpdatedNotificationParam { uri })
+                .await
+            {
+                warn!("Failed to send resource updated notification: {}", e);
+            }
+        }
+    }
+
+    /// Reads the API key again from its configured source and uses it for all
+    /// further embedding and chat requests
+    pub fn reload_api_key(&self) -> Result<(), ServerError> {
+        let key = api_key::load_api_key(&self.config.openai)?
+            .ok_or_else(|| ServerError::MissingEnvVar("OPENAI_API_KEY".to_string()))?;
+        self.chat_client.set_api_key(key.clone());
+        self.embedding_cache_service.set_api_key(key);
+        Ok(())
+    }
+
+    /// Reloads the API key whenever the process receives `SIGHUP`, so keys can
+    /// be rotated without restarting the server
+    #[cfg(unix)]
+    pub fn spawn_key_reloader(&self) -> Result<(), ServerError> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangups = signal(SignalKind::hangup())?;
+        let server = self.clone();
+        tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                match server.reload_api_key() {
+                    Ok(()) => info!(
+                        "Reloaded the API key from {}",
+                        api_key::describe_source(&server.config.openai)
+                    ),
+                    Err(e) => warn!("Keeping the current API key: {}", e),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Polls the doc roots and tells the client to refresh its resource list
+    /// when crates appear, disappear or are regenerated (e.g. after `cargo doc`),
+    /// and that the resources of those crates it subscribed to were updated
+    pub fn spawn_resource_monitor(&self, interval: Duration) {
+        let server = self.clone();
+        tokio::spawn(async move {
+            let Some(mut known) = server.crate_listing_blocking().await else {
+                return;
+            };
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            ticker.tick().await; // The first tick completes immediately
+            loop {
+                ticker.tick().await;
+                let Some(current) = server.crate_listing_blocking().await else {
+                    continue;
+                };
+                if current != known {
+                    debug!("Documented crates changed; notifying client");
+                    server.notify_resource_list_changed().await;
+                    // Crates that appeared, disappeared or were regenerated
+                    let changed: HashSet<&String> = current
+                        .iter()
+                        .filter(|entry| !known.contains(entry))
+                        .chain(known.iter().filter(|entry| !current.contains(entry)))
+                        .map(|(name, _)| name)
+                        .collect();
+                    for crate_name in changed {
+                        server.notify_crate_updated(crate_name).await;
+                    }
+                    known = current;
+                }
+            }
+        });
+    }
+
+    /// Lists the documented crates like [`crate_listing`](Self::crate_listing)
+    /// on a blocking thread, as discovering them reads the doc roots. `None`
+    /// if discovery panicked.
+    async fn crate_listing_blocking(&self) -> Option<Vec<(String, Option<SystemTime>)>> {
+        let server = self.clone();
+        match tokio::task::spawn_blocking(move || server.crate_listing()).await {
+            Ok(listing) => Some(listing),
+            Err(e) => {
+                warn!("Failed to list documented crates: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Documented crates and when their docs were generated, in a stable order
+    fn crate_listing(&self) -> Vec<(String, Option<SystemTime>)> {
+        let mut listing: Vec<_> = self
+            .discover_crates()
+            .into_iter()
+            .map(|discovered| (discovered.name, discovered.generated_at))
+            .collect();
+        listing.sort();
+        listing
+    }
+
+    /// Watches the documentation directory and re-indexes crates whose docs change.
+    /// Unchanged documents keep their cached embeddings, so only edits cost API calls.
+    pub fn spawn_doc_watcher(&self, doc_dir: &Path) -> Result<(), ServerError> {
+        let mut watcher = DocWatcher::new(doc_dir, Duration::from_secs(2))?;
+        let server = self.clone();
+
+        tokio::spawn(async move {
+            while let Some(crates) = watcher.next_batch().await {
+                for crate_name in &crates {
+                    // Deleted crates only need the resource list refresh below
+                    if !watcher
+                        .doc_dir()
+                        .join(crate_name)
+                        .join("index.html")
+                        .is_file()
+                    {
+                        server.state.remove(crate_name);
+                        server.notify_crate_updated(crate_name).await;
+                        continue;
+                    }
+                    server.state.remove(crate_name);
+                    match server
+                        .load_custom_crate_docs(crate_name, &CancellationToken::new())
+                        .await
+                    {
+                        Ok(index) => server.send_log(
+                            LoggingLevel::Info,
+                            format!(
+                                "Re-indexed {} documents for crate '{}' after docs changed",
+                                index.documents.len(),
+                                crate_name
+                            ),
+                        ),
+                        Err(e) => warn!("Failed to re-index crate '{}': {}", crate_name, e.message),
+                    }
+                }
+                server.notify_resource_list_changed().await;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Doc settings with relative paths resolved against the project the
+    /// client reported, or the current directory before it has
+    fn docs(&self) -> DocsSettings {
+        let mut docs = match self.project_root.read().ok().and_then(|root| root.clone()) {
+            Some(root) => self.config.docs.rebased(&root),
+            None => self.config.docs.clone(),
+        };
+        // Documents fed in with `ingest` are served like one more doc root
+        if let Some(global_cache) = &self.global_cache
+            && !docs
+                .roots
+                .iter()
+                .any(|root| root.name == ingest::INGESTED_ROOT_NAME)
+        {
+            docs.roots.push(DocRootSettings {
+                name: ingest::INGESTED_ROOT_NAME.to_string(),
+                dir: global_cache.ingested_dir(),
+            });
+        }
+        docs
+    }
+
+    /// Returns the project directory reported by the client, if any
+    pub fn project_root(&self) -> Option<PathBuf> {
+        self.project_root.read().ok().and_then(|root| root.clone())
+    }
+
+    /// Resolves relative doc paths against `root` from now on. Crates indexed
+    /// from the previous location are dropped and the client is told to
+    /// refresh its resource list.
+    pub async fn set_project_root(&self, root: PathBuf) {
+        if self.project_root().as_ref() == Some(&root) {
+            return;
+        }
+        if let Ok(mut current) = self.project_root.write() {
+            *current = Some(root.clone());
+        }
+        self.state.clear();
+        info!("Using project directory {}", root.display());
+        self.send_log(
+            LoggingLevel::Info,
+            format!("Using project directory {}", root.display()),
+        );
+        self.notify_resource_list_changed().await;
+    }
+
+    /// Asks the client for its workspace roots and uses the Cargo project
+    /// among them, if the client supports roots
+    async fn sync_project_root(&self) {
+        let peer = self.peer.lock().await.clone();
+        let Some(peer) = peer.filter(|peer| peer.peer_info().capabilities.roots.is_some()) else {
+            return;
+        };
+        let roots = match peer.list_roots().await {
+            Ok(result) => result.roots,
+            Err(e) => {
+                warn!("Failed to list client roots: {}", e);
+                return;
+            }
+        };
+        let uris: Vec<String> = roots.into_iter().map(|root| root.uri).collect();
+        let project = tokio::task::spawn_blocking(move || {
+            cargo_project::project_from_roots(uris.iter().map(String::as_str))
+        })
+        .await;
+        match project {
+            Ok(Some(project)) => self.set_project_root(project.root).await,
+            Ok(None) => debug!("No usable project directory among the client's roots"),
+            Err(e) => warn!("Failed to inspect client roots: {}", e),
+        }
+    }
+
+    // Find all crates with docs in the cargo doc directory and generated doc roots
+    fn discover_crates(&self) -> Vec<DiscoveredCrate> {
+        let mut crates: Vec<DiscoveredCrate> =
+            crate_discovery::discover_in_roots(&self.docs().doc_roots())
+                .into_iter()
+                .filter(|discovered| {
+                    self.crate_scope
+                        .as_ref()
+                        .is_none_or(|spec| spec.matches(&discovered.name))
+                })
+                .collect();
+
+        if let Ok(versioned) = self.versioned_docs.read() {
+            for (crate_name, docs) in versioned.iter() {
+                // Registered releases take precedence over the project's docs
+                crates.retain(|discovered| discovered.name != *crate_name);
+                if let Some(mut discovered) =
+                    crate_discovery::discover_available_crates(&docs.doc_dir)
+                        .into_iter()
+                        .find(|discovered| discovered.name == *crate_name)
+                {
+                    discovered.version = Some(docs.key.version.clone());
+                    crates.push(discovered);
+                }
+            }
+        }
+        crates
+    }
+
+    /// Rejects crate names that aren't valid crates.io names before they are
+    /// joined into doc and manifest paths
+    fn validate_crate_name(crate_name: &str) -> Result<(), McpError> {
+        CrateSpec::parse(crate_name, Vec::new())
+            .map(drop)
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))
+    }
+
+    /// Maps a requested crate name to an available one, tolerating `-`/`_`
+    /// differences and typos. Unknown or ambiguous names fail with the closest
+    /// available names, unless docs for unknown crates can be generated.
+    fn resolve_crate_name(&self, requested: &str) -> Result<String, McpError> {
+        let available = self.get_available_crates();
+        let matches = crate_discovery::find_matching_crate_names(requested, &available);
+        if let [resolved] = matches.as_slice() {
+            if normalize_crate_name(resolved) != normalize_crate_name(requested) {
+                info!("Resolved crate name '{}' to '{}'", requested, resolved);
+            }
+            // Later lookups of the same spelling find the index directly
+            self.state.add_alias(requested, resolved);
+            return Ok(resolved.clone());
+        }
+        if matches.is_empty() && self.config.docs.auto_generate {
+            Self::validate_crate_name(requested)?;
+            return Ok(canonical_crate_name(requested));
+        }
+
+        let (reason, suggestions) = if matches.is_empty() {
+            (
+                "is not available",
+                crate_discovery::closest_crate_names(requested, &available, 5),
+            )
+        } else {
+            ("is ambiguous", matches)
+        };
+        let hint = if suggestions.is_empty() {
+            "No crate documentation is available; run 'cargo doc' first.".to_string()
+        } else {
+            format!("Did you mean: {}?", suggestions.join(", "))
+        };
+        Err(McpError::invalid_params(
+            format!("Crate '{}' {}. {}", requested, reason, hint),
+            Some(json!({ "crate_name": requested, "suggestions": suggestions })),
+        ))
+    }
+
+    // Names of all crates with available documentation
+    fn get_available_crates(&self) -> Vec<String> {
+        self.discover_crates()
+            .into_iter()
+            .map(|discovered| discovered.name)
+            .collect()
+    }
+
+    /// Documented crates, with versions from the project's dependency graph
+    async fn discover_crates_with_versions(&self) -> Vec<DiscoveredCrate> {
+        let mut crates = self.discover_crates();
+        match crate_discovery::package_versions(&self.docs().project_dir).await {
+            Ok(versions) => crate_discovery::apply_versions(&mut crates, &versions),
+            Err(e) => debug!("Crate versions unavailable: {}", e),
+        }
+        crates
+    }
+
+    /// Returns the crate a `crate://<name>` or `crate://<name>/llms.txt`
+    /// resource belongs to
+    fn resource_crate_name(uri: &str) -> Option<&str> {
+        let rest = uri.strip_prefix("crate://")?;
+        let name = rest
+            .strip_suffix(&format!("/{}", LLMS_TXT_NAME))
+            .unwrap_or(rest);
+        (!name.is_empty() && !name.contains('/')).then_some(name)
+    }
+
+    /// Returns the documented crate a resource URI names, however the name
+    /// is spelled, e.g. `serde_json` for `crate://serde-json`
+    fn resource_crate(&self, name: &str) -> Option<String> {
+        let normalized = normalize_crate_name(name);
+        self.get_available_crates()
+            .into_iter()
+            .find(|available| normalize_crate_name(available) == normalized)
+    }
+
+    /// Describes a crate's documentation as a resource, with its version and
+    /// when the docs were generated
+    fn crate_resource(discovered: &DiscoveredCrate, stale: bool) -> Resource {
+        let mut description = match &discovered.version {
+            Some(version) => format!("Documentation for {} {}", discovered.name, version),
+            None => format!("Documentation for {}", discovered.name),
+        };
+        let generated_at = discovered.generated_at.map(DateTime::<Utc>::from);
+        if let Some(generated_at) = generated_at {
+            description.push_str(&format!(
+                ", generated {}",
+                generated_at.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+        if stale {
+            description.push_str(" (may be stale: Cargo.lock changed since)");
+        }
+
+        let mut raw = RawResource::new(format!("crate://{}", discovered.name), &discovered.name);
+        raw.description = Some(description);
+        raw.mime_type = Some("text/markdown".to_string());
+        Resource::new(
+            raw,
+            generated_at.map(|timestamp| Annotations {
+                timestamp: Some(timestamp),
+                ..Default::default()
+            }),
+        )
+    }
+}
+
+// --- Tool Implementation ---
+
+#[tool(tool_box)] // Add tool_box here as well, mirroring the example
+// Tool methods go in a regular impl block
+impl RustDocsServer {
+    // Define the tool using the tool macro
+    // Name removed; will be handled dynamically by overriding list_tools/get_tool
+    /// Try to send the startup message if it hasn't been sent yet
+    async fn try_send_startup_message(&self) {
+        let mut sent_guard = self.startup_message_sent.lock().await;
+        if !*sent_guard {
+            let mut msg_guard = self.startup_message.lock().await;
+            if let Some(message) = msg_guard.take() {
+                self.send_log(LoggingLevel::Info, message);
+                *sent_guard = true;
+            }
+            drop(msg_guard);
+        }
+        drop(sent_guard);
+    }
+
+    /// Creates a doc generator whose shim projects live in the global cache's
+    /// workspace directory, or in the system temp directory without a global cache.
+    /// `cargo doc` output is forwarded to the client and the run is killed when
+    /// `cancel` fires.
+    fn doc_generator(&self, cancel: &CancellationToken) -> std::io::Result<DocGenerator> {
+        let workspace = match &self.global_cache {
+            Some(global_cache) => DocWorkspace::in_cache_root(global_cache.root())?,
+            None => DocWorkspace::new(std::env::temp_dir().join("rustdocs-mcp-workspaces"))?,
+        };
+        let server = self.clone();
+        let docs = self.docs();
+        Ok(
+            DocGenerator::new(&docs.project_dir, &docs.dir, workspace, self.config.offline)
+                .with_timeout(Duration::from_secs(self.config.docs.generate_timeout_secs))
+                .with_cancellation(cancel.clone())
+                .with_progress(Arc::new(move |line| {
+                    server.send_log(LoggingLevel::Info, format!("cargo doc: {}", line.trim()))
+                })),
+        )
+    }
+
+    /// Runs `cargo doc` for a crate whose documentation is missing, reporting
+    /// progress to the client
+    async fn generate_crate_docs(
+        &self,
+        crate_name: &str,
+        cancel: &CancellationToken,
+    ) -> Result<(), DocLoaderError> {
+        let mut features = self
+            .crate_scope
+            .as_ref()
+            .filter(|spec| spec.matches(crate_name))
+            .map(|spec| spec.features.clone())
+            .unwrap_or_default();
+        features.extend(self.config.docs.crate_features(crate_name));
+        features.sort();
+        features.dedup();
+        let generator = self
+            .doc_generator(cancel)?
+            .with_rustdoc_options(self.config.docs.rustdoc_options(crate_name));
+
+        self.send_log(
+            LoggingLevel::Info,
+            format!(
+                "Documentation for '{}' not found; running cargo doc (this may take a while)",
+                crate_name
+            ),
+        );
+        let method = generator.generate(crate_name, &features).await?;
+
+        let source = match method {
+            GenerationMethod::Project => "the project",
+            GenerationMethod::Shim => "a shim project",
+        };
+        self.send_log(
+            LoggingLevel::Info,
+            format!(
+                "Generated documentation for '{}' from {}",
+                crate_name, source
+            ),
+        );
+        Ok(())
+    }
+
+    /// Generates documentation for the crates.io release matching `spec` (unless
+    /// it is already cached) and registers it as an additional doc root, so the
+    /// crate can be queried without being a dependency of the project.
+    /// Returns the doc root.
+    pub async fn add_versioned_crate(
+        &self,
+        spec: &CrateSpec,
+        cancel: &CancellationToken,
+    ) -> Result<PathBuf, ServerError> {
+        let global_cache = self.global_cache.as_ref().ok_or_else(|| {
+            ServerError::Config(
+                "The global cache is required to store generated documentation".to_string(),
+            )
+        })?;
+        let key = spec.cache_key();
+        let doc_dir = global_cache.docs_dir(&key);
+
+        if !doc_dir.join(spec.doc_name()).join("index.html").is_file() {
+            self.send_log(
+                LoggingLevel::Info,
+                format!(
+                    "Generating documentation for {} (this may take a while)",
+                    spec
+                ),
+            );
+            self.doc_generator(cancel)?
+                .with_rustdoc_options(self.config.docs.rustdoc_options(&spec.name))
+                .generate_versioned(spec, &doc_dir)
+                .await?;
+            self.send_log(
+                LoggingLevel::Info,
+                format!("Generated documentation for {}", spec),
+            );
+        }
+
+        if let Ok(mut versioned) = self.versioned_docs.write() {
+            versioned.insert(
+                spec.doc_name(),
+                VersionedDocs {
+                    doc_dir: doc_dir.clone(),
+                    key,
+                },
+            );
+        }
+        // Any crate of the same name indexed from another doc root is superseded
+        self.state.remove(&spec.doc_name());
+        self.notify_resource_list_changed().await;
+        Ok(doc_dir)
+    }
+
+    /// Returns true if docs for exactly this crate version are registered
+    fn is_registered(&self, spec: &CrateSpec) -> bool {
+        self.versioned_docs.read().is_ok_and(|versioned| {
+            versioned
+                .get(&spec.doc_name())
+                .is_some_and(|docs| docs.key.version == spec.cache_key().version)
+        })
+    }
+
+    /// Reads a crate's documents from its registered versioned docs or the doc roots
+    fn load_crate_documents(&self, crate_name: &str) -> Result<Vec<Document>, DocLoaderError> {
+        let versioned = self
+            .versioned_docs
+            .read()
+            .ok()
+            .and_then(|versioned| versioned.get(&canonical_crate_name(crate_name)).cloned());
+        match versioned {
+            Some(versioned) => {
+                doc_loader::load_documents_from_doc_dir(&versioned.doc_dir, crate_name)
+            }
+            None => doc_loader::load_documents_from_roots(&self.docs().doc_roots(), crate_name)
+                .map(|(_, documents)| documents),
+        }
+    }
+
+    /// Returns the lock held while a crate is being indexed
+    async fn indexing_lock(&self, crate_name: &str) -> Arc<Mutex<()>> {
+        let mut locks = self.indexing_locks.lock().await;
+        Arc::clone(locks.entry(canonical_crate_name(crate_name)).or_default())
+    }
+
+    /// Drops the indexing lock of a crate once no other request holds or
+    /// waits for it, so locks don't pile up for every crate ever queried
+    async fn release_indexing_lock(&self, crate_name: &str, lock: Arc<Mutex<()>>) {
+        let mut locks = self.indexing_locks.lock().await;
+        let key = canonical_crate_name(crate_name);
+        // The map holds one reference and this request the other
+        if locks.get(&key).is_some_and(|held| Arc::ptr_eq(held, &lock))
+            && Arc::strong_count(&lock) == 2
+        {
+            locks.remove(&key);
+        }
+    }
+
+    /// Load documentation and embeddings for a custom crate. Large pages not
+    /// embedded yet are left to a background task, and the index returned
+    /// lists them as pending.
+    async fn load_custom_crate_docs(
+        &self,
+        crate_name: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Arc<CrateIndex>, McpError> {
+        self.index_crate_docs(crate_name, cancel, true).await
+    }
+
+    /// Indexes a crate, leaving large pages that aren't embedded yet to
+    /// [`spawn_pending_indexing`](Self::spawn_pending_indexing) if
+    /// `defer_large` is set
+    #[instrument(name = "index_crate", skip(self, cancel))]
+    async fn index_crate_docs(
+        &self,
+        crate_name: &str,
+        cancel: &CancellationToken,
+        defer_large: bool,
+    ) -> Result<Arc<CrateIndex>, McpError> {
+        // Concurrent requests for the same crate wait for the first one to
+        // finish indexing and then reuse its cached embeddings instead of
+        // embedding every document again
+        let lock = self.indexing_lock(crate_name).await;
+        let indexed = self
+            .index_crate_docs_locked(crate_name, cancel, defer_large, &lock)
+            .await;
+        self.release_indexing_lock(crate_name, lock).await;
+        indexed
+    }
+
+    /// Indexes a crate as [`index_crate_docs`](Self::index_crate_docs) does
+    /// once it holds the crate's indexing `lock`
+    async fn index_crate_docs_locked(
+        &self,
+        crate_name: &str,
+        cancel: &CancellationToken,
+        defer_large: bool,
+        lock: &Mutex<()>,
+    ) -> Result<Arc<CrateIndex>, McpError> {
+        let _guard = match lock.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                // Large pages being embedded in the background don't hold up
+                // queries answerable from the rest of the crate
+                if defer_large
+                    && let Some(index) = self.state.get(crate_name)
+                    && !index.pending.is_empty()
+                {
+                    return Ok(index);
+                }
+                self.send_log(
+                    LoggingLevel::Info,
+                    format!(
+                        "Waiting for crate '{}' to finish indexing in another request",
+                        crate_name
+                    ),
+                );
+                lock.lock().await
+            }
+        };
+
+        // Reuse the in-memory index unless the docs were regenerated since or
+        // some documents failed to embed and should be retried
+        if let Some(index) = self.state.get(crate_name)
+            && index.skipped.is_empty()
+            && (defer_large || index.pending.is_empty())
+        {
+            return Ok(index);
+        }
+
+        let started = Instant::now();
+        self.send_log(
+            LoggingLevel::Info,
+            format!("Loading local documentation for crate '{}'", crate_name),
+        );
+
+        let versioned = self
+            .versioned_docs
+            .read()
+            .ok()
+            .and_then(|versioned| versioned.get(&canonical_crate_name(crate_name)).cloned());
+        // Pages unchanged since the crate was last indexed aren't parsed again
+        let previous_index = self.state.loaded(crate_name);
+        let unchanged = previous_index
+            .as_ref()
+            .map(|previous| previous.unchanged_pages())
+            .unwrap_or_default();
+        if !unchanged.is_empty() {
+            debug!(
+                "Reusing the text of {} unchanged pages of crate '{}'",
+                unchanged.len(),
+                crate_name
+            );
+        }
+        // Load documents from the doc roots, generating them first if allowed
+        let settings = self.docs();
+        let loaded = match versioned {
+            _ if settings.backend(crate_name) == DocBackend::Source => {
+                source_loader::load_crate_documents(crate_name, None, &settings)
+                    .await
+                    .map(|(dir, docs)| (CacheKey::sources(crate_name), dir, docs))
+            }
+            Some(versioned) => doc_loader::load_changed_documents_from_doc_dir(
+                &versioned.doc_dir,
+                crate_name,
+                &unchanged,
+            )
+            .map(|docs| (versioned.key, versioned.doc_dir, docs)),
+            None => {
+                match doc_loader::load_changed_documents_from_roots(
+                    &settings.doc_roots(),
+                    crate_name,
+                    &unchanged,
+                ) {
+                    Ok((root, docs)) => Ok((root.cache_key(crate_name), root.dir.clone(), docs)),
+                    Err(DocLoaderError::DocNotFound(_)) if settings.auto_generate => self
+                        .generate_crate_docs(crate_name, cancel)
+                        .await
+                        .and_then(|_| {
+                            doc_loader::load_documents_from_doc_dir(&settings.dir, crate_name)
+                        })
+                        .map(|docs| (CacheKey::local(crate_name), settings.dir.clone(), docs)),
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        let (cache_key, doc_dir, docs) = loaded.map_err(|e| {
+            let not_found = matches!(e, DocLoaderError::DocNotFound(_));
+            let error = McpError::internal_error(
+                format!(
+                    "Failed to load local documentation of crate '{}': {}",
+                    crate_name, e
+                ),
+                None,
+            );
+            let error = error_data::with_crate(error, crate_name);
+            if not_found {
+                error_data::with_suggestion(error, Suggestion::GenerateDocs)
+            } else {
+                error
+            }
+        })?;
+
+        let fingerprint = unchanged.fingerprint(&doc_dir.join(canonical_crate_name(crate_name)));
+
+        if docs.is_empty() {
+            let error = McpError::internal_error(
+                format!(
+                    "No documentation found for crate '{}'. Run 'cargo doc --package {}' first.",
+                    crate_name, crate_name
+                ),
+                None,
+            );
+            let error = error_data::with_crate(error, crate_name);
+            return Err(error_data::with_suggestion(error, Suggestion::GenerateDocs));
+        }
+
+        let model = self.embedding_cache_service.model().to_string();
+        let manifest = IndexManifest::new(
+            crate_name,
+            &docs,
+            self.embedding_cache_service.chunker(),
+            &self.config.embedding.provider,
+            &model,
+        );
+
+        // Reuse document embeddings from the global cache when they were made
+        // the same way and the document's content is unchanged
+        let mut cached_vectors: HashMap<String, CachedDocumentEmbedding> = HashMap::new();
+        // The stored keyword index is reused only when nothing changed at all
+        let mut unchanged = false;
+        if let Some(global_cache) = &self.global_cache {
+            let previous = IndexManifest::load(&global_cache.manifest_path(&cache_key))
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring unreadable manifest of '{}': {}", crate_name, e);
+                    None
+                });
+            let changes = previous
+                .as_ref()
+                .map(|previous| manifest.changes_since(previous))
+                .unwrap_or_default();
+            unchanged = previous.is_some() && changes.is_empty();
+            if !changes.is_empty() {
+                self.send_log(
+                    LoggingLevel::Info,
+                    format!(
+                        "Rebuilding the index of crate '{}': {} changed since it was built",
+                        crate_name,
+                        changes.join(", ")
+                    ),
+                );
+            }
+            let compatible =
+                previous.is_none_or(|previous| manifest.embeddings_compatible(&previous));
+            match global_cache.load(&cache_key) {
+                Ok(Some(index)) if index.model == model && compatible => {
+                    cached_vectors = index
+                        .documents
+                        .into_iter()
+                        .map(|doc| (doc.path.clone(), doc))
+                        .collect();
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Ignoring unreadable global cache entry for '{}': {}",
+                    crate_name, e
+                ),
+            }
+        }
+        // The index being replaced has the vectors of the documents it shares
+        // with this one, e.g. when there is no global cache
+        if let Some(previous) = &previous_index
+            && previous
+                .manifest
+                .as_ref()
+                .is_some_and(|previous| manifest.embeddings_compatible(previous))
+        {
+            match previous.documents.documents() {
+                Ok(previous_docs) => {
+                    for doc in previous_docs {
+                        if cached_vectors.contains_key(&doc.path)
+                            || previous.is_quarantined(&doc.path)
+                        {
+                            continue;
+                        }
+                        if let Some(embedding) = previous.embeddings.get(&doc.path) {
+                            let cached = CachedDocumentEmbedding {
+                                path: doc.path.clone(),
+                                content: doc.content,
+                                vector: embedding.values,
+                            };
+                            cached_vectors.insert(doc.path, cached);
+                        }
+                    }
+                }
+                Err(e) => warn!(
+                    "Ignoring the previous index of '{}', whose text can't be read: {}",
+                    crate_name, e
+                ),
+            }
+        }
+
+        // Use embedding cache service to get or generate embeddings
+        self.send_log(
+            LoggingLevel::Info,
+            format!("Using embedding cache service for crate '{}'", crate_name),
+        );
+
+        // Cached vectors of another length than most are re-embedded, so a
+        // quarantined document doesn't get its unusable vector back
+        let mut lengths: HashMap<usize, usize> = HashMap::new();
+        for cached in cached_vectors.values() {
+            *lengths.entry(cached.vector.len()).or_default() += 1;
+        }
+        let usual_length = lengths
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(length, _)| length);
+        let large_document = self
+            .config
+            .docs
+            .large_document_bytes()
+            .filter(|_| defer_large);
+        let mut embeddings: Vec<Option<Embedding>> = Vec::with_capacity(docs.len());
+        let mut pending = Vec::new();
+        let mut deferred = Vec::new();
+        // Earlier text of edited documents, whose unchanged chunks are reused
+        let mut edited = Vec::new();
+        for (i, doc) in docs.iter().enumerate() {
+            match cached_vectors.remove(&doc.path) {
+                Some(cached)
+                    if cached.content == doc.content
+                        && Some(cached.vector.len()) == usual_length =>
+                {
+                    embeddings.push(Some(Embedding::new(
+                        cached.vector,
+                        EmbeddingProvider::OpenAI,
+                        model.clone(),
+                    )));
+                }
+                cached => {
+                    if let Some(cached) = cached
+                        && cached.content != doc.content
+                    {
+                        edited.push((i, cached.content));
+                    }
+                    embeddings.push(None);
+                    if large_document.is_some_and(|large| doc.content.len() > large) {
+                        deferred.push(i);
+                    } else {
+                        pending.push(i);
+                    }
+                }
+            }
+        }
+        // A crate made only of large pages can't be searched without them
+        if deferred.len() == docs.len() {
+            pending.append(&mut deferred);
+        }
+        let deferred: Vec<String> = deferred.iter().map(|&i| docs[i].path.clone()).collect();
+
+        // Get the remaining embeddings from the chunk cache or generate them,
+        // several documents at a time. Documents that fail to embed are
+        // skipped so one oversized page or transient error doesn't fail the query.
+        let contents: Vec<&str> = pending.iter().map(|&i| docs[i].content.as_str()).collect();
+        let (hits_before, misses_before) = self.embedding_cache_service.chunk_cache_counts();
+        let generated = self
+            .embedding_cache_service
+            .try_get_embeddings(&contents)
+            .await;
+        // Counts are shared with concurrent indexing, so these are approximate
+        let (hits, misses) = self.embedding_cache_service.chunk_cache_counts();
+        if !edited.is_empty() {
+            let (mut kept, mut added, mut removed) = (0, 0, 0);
+            for (i, old) in &edited {
+                let changes = self
+                    .embedding_cache_service
+                    .chunk_changes(old, &docs[*i].content);
+                kept += changes.0;
+                added += changes.1;
+                removed += changes.2;
+            }
+            self.send_log(
+                LoggingLevel::Info,
+                format!(
+                    "Re-embedding {} edited documents of crate '{}': {} chunks unchanged, \
+                     {} new, {} removed",
+                    edited.len(),
+                    crate_name,
+                    kept,
+                    added,
+                    removed
+                ),
+            );
+        }
+        let (indexed, reused) = (docs.len(), docs.len() - pending.len() - deferred.len());
+        let mut skipped = Vec::new();
+        let mut first_error = None;
+        for (i, result) in pending.into_iter().zip(generated) {
+            match result {
+                Ok(embedding) => embeddings[i] = Some(embedding),
+                Err(e) => {
+                    warn!("Skipping document '{}': {}", docs[i].path, e);
+                    skipped.push(SkippedDocument {
+                        path: docs[i].path.clone(),
+                        reason: e.to_string(),
+                    });
+                    first_error.get_or_insert((docs[i].path.clone(), e));
+                }
+            }
+        }
+        let (docs, array_embeddings): (Vec<Document>, Vec<(String, Embedding)>) = docs
+            .into_iter()
+            .zip(embeddings)
+            .filter_map(|(doc, embedding)| {
+                let embedding = embedding?;
+                let path = doc.path.clone();
+                Some((doc, (path, embedding)))
+            })
+            .unzip();
+        if let Some((path, e)) = first_error {
+            if docs.is_empty() {
+                let error = self
+                    .embedding_error(e, &format!("Failed to get embedding for document {}", path));
+                return Err(error_data::with_doc_path(error, &path));
+            }
+            self.send_log(
+                LoggingLevel::Warning,
+                format!(
+                    "Skipped {} documents of crate '{}' that could not be embedded",
+                    skipped.len(),
+                    crate_name
+                ),
+            );
+        }
+
+        // A partial index is cached once its large pages are embedded too
+        if deferred.is_empty()
+            && let Some(global_cache) = &self.global_cache
+        {
+            let index = CachedCrateIndex {
+                crate_name: cache_key.crate_name.clone(),
+                version: cache_key.version.clone(),
+                features: cache_key.features.clone(),
+                model,
+                documents: docs
+                    .iter()
+                    .zip(&array_embeddings)
+                    .map(|(doc, (_, embedding))| CachedDocumentEmbedding {
+                        path: doc.path.clone(),
+                        content: doc.content.clone(),
+                        vector: embedding.values.clone(),
+                    })
+                    .collect(),
+            };
+            // Chunks shared with other crates are embedded once; listing them
+            // keeps `cache prune` from dropping them while any crate uses them
+            let mut chunk_keys: Vec<String> = docs
+                .iter()
+                .flat_map(|doc| self.embedding_cache_service.chunk_keys(&doc.content))
+                .collect();
+            chunk_keys.sort_unstable();
+            chunk_keys.dedup();
+            let previous_chunks =
+                global_cache::read_chunk_refs(&global_cache.index_path(&cache_key))
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default();
+            match global_cache
+                .store(&cache_key, &index)
+                .and_then(|_| manifest.save(&global_cache.manifest_path(&cache_key)))
+                .and_then(|_| global_cache.store_chunk_refs(&cache_key, &chunk_keys))
+            {
+                Ok(()) => {
+                    let dropped =
+                        self.drop_unused_chunks(global_cache, &previous_chunks, &chunk_keys);
+                    if dropped > 0 {
+                        debug!(
+                            "Dropped {} cached chunks crate '{}' no longer uses",
+                            dropped, crate_name
+                        );
+                    }
+                }
+                Err(e) => warn!("Failed to update global cache for '{}': {}", crate_name, e),
+            }
+        }
+
+        let keywords = self.keyword_index(&cache_key, &docs, unchanged);
+        let ann_file = self.ann_index(&cache_key, &array_embeddings, &manifest);
+        // Only the text of matched documents is needed from here on
+        let memory = &self.config.memory;
+        let documents = DocumentStore::new(docs, memory.max_document_bytes(), &memory.spill_dir())
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to store document text: {}", e), None)
+            })?;
+        let vectors = VectorStore::new(
+            array_embeddings,
+            memory.max_vector_bytes(),
+            &memory.spill_dir(),
+        )
+        .map_err(|e| {
+            McpError::internal_error(format!("Failed to store embeddings: {}", e), None)
+        })?;
+
+        if let Some(telemetry) = &self.telemetry {
+            let record = IndexRecord {
+                crate_name: crate_name.to_string(),
+                documents: indexed,
+                reused,
+                chunk_hits: hits - hits_before,
+                chunk_misses: misses - misses_before,
+                duration: started.elapsed(),
+            };
+            if let Err(e) = telemetry.record_index(&record) {
+                warn!("Failed to record indexing telemetry: {}", e);
+            }
+        }
+
+        let mut index = CrateIndex::new(crate_name, &doc_dir, documents, vectors);
+        if let Some(ann_file) = ann_file {
+            index = index.with_ann_file(ann_file);
+        }
+        let index = self.state.insert(
+            index
+                .with_keywords(keywords)
+                .with_skipped(skipped)
+                .with_pending(deferred)
+                .with_manifest(manifest)
+                .with_fingerprint(
+                    fingerprint,
+                    Duration::from_secs(self.config.docs.freshness_interval_secs),
+                ),
+        );
+        if !index.pending.is_empty() {
+            self.send_log(
+                LoggingLevel::Info,
+                format!(
+                    "Embedding {} large documents of crate '{}' in the background; \
+                     until then they are not searched",
+                    index.pending.len(),
+                    crate_name
+                ),
+            );
+            self.spawn_pending_indexing(crate_name);
+        }
+        self.notify_crate_updated(crate_name).await;
+        Ok(index)
+    }
+
+    /// Removes the chunk cache entries of a crate's `previous` index that its
+    /// `current` one doesn't use, unless another cached crate still does.
+    /// Returns the number removed.
+    fn drop_unused_chunks(
+        &self,
+        global_cache: &GlobalCache,
+        previous: &[String],
+        current: &[String],
+    ) -> usize {
+        let current: HashSet<&String> = current.iter().collect();
+        let removed: Vec<&String> = previous
+            .iter()
+            .filter(|key| !current.contains(key))
+            .collect();
+        if removed.is_empty() {
+            return 0;
+        }
+        let referenced = match cache_manager::reference_counts(
+            global_cache,
+            self.embedding_cache_service.chunker(),
+        ) {
+            Ok(referenced) => referenced,
+            Err(e) => {
+                warn!("Keeping chunks that may be unused: {}", e);
+                return 0;
+            }
+        };
+        let unused: Vec<String> = removed
+            .into_iter()
+            .filter(|key| !referenced.contains_key(*key))
+            .cloned()
+            .collect();
+        self.embedding_cache_service.remove_chunks(&unused)
+    }
+
+    /// Indexes a crate again in a background task, embedding the large pages
+    /// its index left pending. Queries are answered from the partial index
+    /// meanwhile; if indexing fails, it is dropped so the next query retries.
+    fn spawn_pending_indexing(&self, crate_name: &str) {
+        let server = self.clone();
+        let crate_name = crate_name.to_string();
+        tokio::spawn(async move {
+            let started = Instant::now();
+            match server
+                .index_crate_docs(&crate_name, &CancellationToken::new(), false)
+                .await
+            {
+                Ok(index) => info!(
+                    "Finished indexing crate '{}' in the background ({} documents, {:.1}s)",
+                    crate_name,
+                    index.documents.len(),
+                    started.elapsed().as_secs_f64()
+                ),
+                Err(e) => {
+                    warn!(
+                        "Failed to index the large documents of crate '{}': {}",
+                        crate_name, e.message
+                    );
+                    server.state.remove(&crate_name);
+                }
+            }
+        });
+    }
+
+    /// Returns the keyword index of `docs`, read from the global cache if the
+    /// crate's docs are `unchanged` since it was stored, built (and stored)
+    /// otherwise
+    fn keyword_index(
+        &self,
+        cache_key: &CacheKey,
+        docs: &[Document],
+        unchanged: bool,
+    ) -> KeywordIndex {
+        let Some(global_cache) = &self.global_cache else {
+            return KeywordIndex::new(
+                docs.iter()
+                    .map(|doc| (doc.path.as_str(), doc.content.as_str())),
+            );
+        };
+        let path = global_cache.keyword_index_path(cache_key);
+        if unchanged {
+            match KeywordIndex::load(&path) {
+                // Documents that failed to embed are missing from the index
+                Ok(Some(keywords))
+                    if keywords.paths().iter().eq(docs.iter().map(|doc| &doc.path)) =>
+                {
+                    return keywords;
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Ignoring unreadable keyword index of '{}': {}",
+                    cache_key.crate_name, e
+                ),
+            }
+        }
+        let keywords = KeywordIndex::new(
+            docs.iter()
+                .map(|doc| (doc.path.as_str(), doc.content.as_str())),
+        );
+        if let Err(e) = keywords.save(&path) {
+            warn!(
+                "Failed to store the keyword index of '{}': {}",
+                cache_key.crate_name, e
+            );
+        }
+        keywords
+    }
+
+    /// Returns where the HNSW graph of a crate's `embeddings` is stored, built
+    /// (or rebuilt) unless the stored one matches `manifest`. `None` for
+    /// crates too small to need one or without a global cache.
+    fn ann_index(
+        &self,
+        cache_key: &CacheKey,
+        embeddings: &[(String, Embedding)],
+        manifest: &IndexManifest,
+    ) -> Option<PathBuf> {
+        let min_documents = self.config.retrieval.ann_min_documents;
+        if min_documents == 0 || embeddings.len() < min_documents {
+            return None;
+        }
+        let global_cache = self.global_cache.as_ref()?;
+        let path = global_cache.ann_index_path(cache_key);
+        if AnnIndex::is_current(
+            &path,
+            &manifest.content_hash,
+            &manifest.embedding_model,
+            embeddings.len(),
+        ) {
+            return Some(path);
+        }
+        // Queries still searching the previous graph keep their mapping
+        self.ann_indexes.close(&path);
+        let started = Instant::now();
+        match AnnIndex::build(
+            embeddings,
+            &manifest.content_hash,
+            &manifest.embedding_model,
+            &path,
+        ) {
+            Ok(()) => {
+                debug!(
+                    "Built the ANN index of '{}' in {:.1}s",
+                    cache_key.crate_name,
+                    started.elapsed().as_secs_f64()
+                );
+                Some(path)
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to build the ANN index of '{}', scanning its vectors instead: {}",
+                    cache_key.crate_name, e
+                );
+                None
+            }
+        }
+    }
+
+    /// Scores the vectors an HNSW graph finds closest to the question, if
+    /// `index` has a graph matching its vectors. `None` means every vector
+    /// should be scored instead.
+    fn approximate_matches<'a>(
+        &self,
+        question_embedding: &Embedding,
+        index: &'a CrateIndex,
+        count: usize,
+        min_score: f32,
+    ) -> Option<Vec<(&'a str, f32)>> {
+        let ann = self.ann_indexes.get(index.ann_file.as_deref()?)?;
+        let manifest = index.manifest.as_ref()?;
+        if !ann.matches(
+            &manifest.content_hash,
+            &manifest.embedding_model,
+            index.embeddings.len(),
+        ) {
+            return None;
+        }
+        let hits = match ann.search(question_embedding, count) {
+            Ok(hits) => hits,
+            Err(e) => {
+                warn!("ANN search of '{}' failed: {}", index.crate_name, e);
+                return None;
+            }
+        };
+        Some(
+            hits.into_iter()
+                .filter(|(_, score)| *score >= min_score)
+                .filter_map(|(key, score)| {
+                    // Keys are positions in the vectors the graph was built from
+                    let path = index.embeddings.path(key)?;
+                    (ann.path(key) == Some(path)).then_some((path, score))
+                })
+                .collect(),
+        )
+    }
+
+    /// Find the best matching documents for a given question embedding, best first.
+    /// Returns at most `top_k` matches scoring at least `retrieval.min_score`.
+    /// The best few are then boosted for mentioning the question's identifiers.
+    fn find_best_matches<'a>(
+        &self,
+        question: &str,
+        question_embedding: &Embedding,
+        index: &'a CrateIndex,
+        top_k: usize,
+    ) -> Vec<(&'a str, f32)> {
+        self.rank_matches(
+            question,
+            question_embedding,
+            index,
+            top_k,
+            self.config.retrieval.min_score,
+        )
+        .into_iter()
+        .map(|ranked| (ranked.path, ranked.score))
+        .collect()
+    }
+
+    /// Ranks the documents of `index` whose similarity to the question is at
+    /// least `min_score`, as [`find_best_matches`](Self::find_best_matches)
+    /// does, keeping each one's similarity apart from its boosted score
+    #[instrument(name = "retrieve", level = "debug", skip_all, fields(candidates = index.embeddings.len()))]
+    fn rank_matches<'a>(
+        &self,
+        question: &str,
+        question_embedding: &Embedding,
+        index: &'a CrateIndex,
+        top_k: usize,
+        min_score: f32,
+    ) -> Vec<RankedMatch<'a>> {
+        // Documents this far down the ranking can be boosted into the results
+        const BOOST_CANDIDATES: usize = 20;
+        let retrieval = &self.config.retrieval;
+
+        // Vectors of another length, e.g. from a changed model or a corrupted
+        // cache entry, can't be compared with the question's
+        let dimensions = question_embedding.dimensions;
+        let mismatched = index.embeddings.mismatched(dimensions);
+        if !mismatched.is_empty() {
+            self.quarantine_embeddings(index, &mismatched, dimensions);
+        }
+
+        // Large crates are searched through their HNSW graph, if they have one
+        let matches = match self.approximate_matches(
+            question_embedding,
+            index,
+            top_k.max(BOOST_CANDIDATES),
+            min_score,
+        ) {
+            Some(matches) => matches,
+            None => index.embeddings.scores(question_embedding, min_score),
+        };
+        let mut matches: Vec<RankedMatch<'a>> = matches
+            .into_iter()
+            .map(|(path, similarity)| RankedMatch {
+                path,
+                similarity,
+                score: similarity,
+            })
+            .collect();
+        matches.par_sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let identifiers = identifier_boost::question_identifiers(question);
+        if retrieval.identifier_boost > 0.0 && !identifiers.is_empty() {
+            matches.truncate(top_k.max(BOOST_CANDIDATES));
+            for ranked in &mut matches {
+                let item_path = doc_loader::item_path(&index.crate_name, Path::new(ranked.path));
+                let boost = identifier_boost::indexed_score(
+                    &index.keywords,
+                    &identifiers,
+                    ranked.path,
+                    item_path.as_deref(),
+                );
+                ranked.score += retrieval.identifier_boost * boost;
+            }
+            matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+
+        matches.truncate(top_k);
+        matches
+    }
+
+    /// Cuts a matched document longer than `budget` tokens down to the chunk
+    /// most similar to the question and the neighboring chunks that fit the
+    /// budget, returning it with its token count. The chunk embeddings were
+    /// cached when the document was indexed; if any can't be had, the whole
+    /// document is kept.
+    async fn focus_document(
+        &self,
+        doc: Document,
+        question_embedding: &Embedding,
+        budget: usize,
+    ) -> (Document, usize) {
+        let chunker = self.embedding_cache_service.chunker();
+        let chunks = chunker.chunk_document(&doc.content);
+        let total = chunks.iter().map(|chunk| chunk.tokens).sum();
+        if total <= budget || chunks.len() < 2 {
+            return (doc, total);
+        }
+
+        // Boilerplate chunks weren't embedded, and can't be the best match
+        let mut best: Option<(usize, f32)> = None;
+        for (i, chunk) in chunks.iter().enumerate() {
+            if chunker.skips_boilerplate() && boilerplate::is_boilerplate(&chunk.content) {
+                continue;
+            }
+            let embedding = match self
+                .embedding_cache_service
+                .get_embedding_for_chunk(&chunk.content)
+                .await
+            {
+                Ok(embedding) => embedding,
+                Err(e) => {
+                    debug!(path = %doc.path, "Sending whole document, chunk embedding unavailable: {}", e);
+                    return (doc, total);
+                }
+            };
+            let score = question_embedding.similarity(&embedding);
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((i, score));
+            }
+        }
+        let Some((best, score)) = best else {
+            return (doc, total);
+        };
+
+        let tokens: Vec<usize> = chunks.iter().map(|chunk| chunk.tokens).collect();
+        let range = chunk_context::expand(&tokens, best, budget);
+        debug!(path = %doc.path, best, score, chunks = ?range, of = chunks.len(), "Focused long document");
+        let document = Document {
+            path: doc.path,
+            content: chunk_context::assemble(&chunks, range.clone()),
+        };
+        (document, chunk_context::assembled_tokens(&chunks, range))
+    }
+
+    /// Number of documents to answer a query about `crate_name` from and the
+    /// tokens of documentation it may send to the LLM, if limited, with the
+    /// query's options capped at the configured limits
+    fn query_limits(
+        &self,
+        crate_name: &str,
+        options: &QueryOptions,
+    ) -> Result<(usize, Option<usize>), McpError> {
+        let retrieval = &self.config.retrieval;
+        if options.top_k == Some(0) || options.max_context_tokens == Some(0) {
+            return Err(McpError::invalid_params(
+                "top_k and max_context_tokens must be at least 1",
+                None,
+            ));
+        }
+        let top_k = options
+            .top_k
+            .map_or(self.config.top_k(crate_name), |top_k| {
+                top_k.min(retrieval.max_top_k)
+            });
+        let context_tokens = match (options.max_context_tokens, retrieval.max_context_tokens) {
+            (Some(tokens), 0) => Some(tokens),
+            (Some(tokens), cap) => Some(tokens.min(cap)),
+            (None, 0) => None,
+            (None, cap) => Some(cap),
+        };
+        Ok((top_k, context_tokens))
+    }
+
+    /// Leaves documents whose embeddings don't have `dimensions` values out
+    /// of the crate's searches, moving the chunk cache entries responsible
+    /// aside so they are regenerated. Logs each document once.
+    fn quarantine_embeddings(&self, index: &CrateIndex, paths: &[&str], dimensions: usize) {
+        let newly = index.quarantine(paths);
+        if newly.is_empty() {
+            return;
+        }
+        let mut files = Vec::new();
+        for path in &newly {
+            if let Ok(Some(doc)) = index.documents.get(path) {
+                files.extend(
+                    self.embedding_cache_service
+                        .quarantine_chunks(&doc.content, dimensions),
+                );
+            }
+        }
+        let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        warn!(
+            crate_name = %index.crate_name,
+            documents = ?newly,
+            cache_files = ?files,
+            "Skipping embeddings without {} dimensions",
+            dimensions
+        );
+        self.send_log(
+            LoggingLevel::Warning,
+            format!(
+                "Skipped {} documents of crate '{}' whose embeddings don't have {} dimensions \
+                 (quarantined cache files: {}); they are re-embedded when the crate is next indexed",
+                newly.len(),
+                index.crate_name,
+                dimensions,
+                if files.is_empty() {
+                    "none".to_string()
+                } else {
+                    files.join(", ")
+                }
+            ),
+        );
+    }
+
+    /// Converts a failure to embed into an MCP error naming the embedding provider
+    fn embedding_error(&self, error: ServerError, context: &str) -> McpError {
+        error_data::with_provider(
+            error.into_mcp_error(context),
+            &self.config.embedding.provider,
+        )
+    }
+
+    /// Format the matched documents verbatim, used instead of the LLM when
+    /// offline or out of budget; `reason` says which. Documents of companion
+    /// crates are headed with their crate's name.
+    fn format_raw_context(
+        reason: &str,
+        index: &CrateIndex,
+        matched_docs: &[(&CrateIndex, &Document)],
+    ) -> String {
+        let sections: Vec<String> = matched_docs
+            .iter()
+            .map(|(source, doc)| {
+                format!(
+                    "{}\n\n{}",
+                    Self::document_heading(index, source, doc),
+                    doc.content
+                )
+            })
+            .collect();
+        format!(
+            "{}: showing the most relevant documentation without summarization.\n\n{}",
+            reason,
+            sections.join("\n\n---\n\n")
+        )
+    }
+
+    /// Extractive answer: the sentences of the matched documents that best
+    /// match the question, quoted under their document's heading. Falls back
+    /// to the documents themselves if no sentence shares a word with the
+    /// question.
+    fn format_excerpts(
+        question: &str,
+        index: &CrateIndex,
+        matched_docs: &[(&CrateIndex, &Document)],
+    ) -> String {
+        let texts: Vec<&str> = matched_docs
+            .iter()
+            .map(|(_, doc)| doc.content.as_str())
+            .collect();
+        let excerpts = extractive::excerpts(question, &texts, extractive::DEFAULT_EXCERPTS);
+        if excerpts.is_empty() {
+            return Self::format_raw_context(
+                "No sentence matches the question",
+                index,
+                matched_docs,
+            );
+        }
+        let mut text = "Quoted from the documentation, without summarization:".to_string();
+        let mut previous = None;
+        for excerpt in &excerpts {
+            if previous != Some(excerpt.document) {
+                let (source, doc) = matched_docs[excerpt.document];
+                text.push_str(&format!(
+                    "\n\n{}",
+                    Self::document_heading(index, source, doc)
+                ));
+                previous = Some(excerpt.document);
+            }
+            text.push_str(&format!("\n\n> {}", excerpt.text));
+        }
+        text
+    }
+
+    /// Heading of a matched document in raw or quoted answers, naming its
+    /// crate if it is a companion's
+    fn document_heading(index: &CrateIndex, source: &CrateIndex, doc: &Document) -> String {
+        if source.crate_name == index.crate_name {
+            format!("## {}", doc.path)
+        } else {
+            format!("## {}: {}", source.crate_name, doc.path)
+        }
+    }
+
+    /// Warnings for answers drawing on deprecated, unstable or feature-gated
+    /// items, one per banner
+    fn status_warnings(matched_docs: &[(&CrateIndex, &Document)]) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for (source, doc) in matched_docs {
+            let Ok(html) = fs::read_to_string(source.page_file(&doc.path)) else {
+                continue;
+            };
+            let status = ItemStatus::from_html(&html);
+            let item = doc_loader::item_path(&source.crate_name, Path::new(&doc.path))
+                .unwrap_or_else(|| doc.path.clone());
+            for (kind, note) in [
+                ("is deprecated", &status.deprecated),
+                ("is unstable", &status.unstable),
+                ("is feature-gated", &status.availability),
+            ] {
+                if let Some(note) = note {
+                    warnings.push(format!("`{}` {}: {}", item, kind, note));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Warning for answers when some documents couldn't be embedded
+    fn skipped_warning(skipped: &[SkippedDocument]) -> String {
+        const LISTED: usize = 5;
+        let mut paths: Vec<&str> = skipped
+            .iter()
+            .take(LISTED)
+            .map(|doc| doc.path.as_str())
+            .collect();
+        let more = skipped.len().saturating_sub(LISTED);
+        let more = if more > 0 {
+            format!(" and {} more", more)
+        } else {
+            String::new()
+        };
+        paths.sort_unstable();
+        format!(
+            "{} documents could not be embedded and were not searched: {}{}",
+            skipped.len(),
+            paths.join(", "),
+            more
+        )
+    }
+
+    /// Notice for answers from a crate whose large pages are still being
+    /// embedded in the background
+    fn pending_warning(pending: &[String]) -> String {
+        const LISTED: usize = 5;
+        let mut paths: Vec<&str> = pending.iter().take(LISTED).map(String::as_str).collect();
+        paths.sort_unstable();
+        let more = pending.len().saturating_sub(LISTED);
+        format!(
+            "{} large documents are still being indexed and were not searched; ask again shortly to include them: {}{}",
+            pending.len(),
+            paths.join(", "),
+            if more > 0 {
+                format!(" and {} more", more)
+            } else {
+                String::new()
+            }
+        )
+    }
+
+    /// The system and user prompts the LLM is sent to answer `question`
+    /// from the matched documents, with the `[redaction]` rules applied to
+    /// the user prompt if `redact` is set
+    fn llm_prompts(
+        &self,
+        matched_docs: &[(&CrateIndex, &Document)],
+        question: &str,
+        options: &QueryOptions,
+        index: &CrateIndex,
+        redact: bool,
+    ) -> (String, String) {
+        let crate_name = index.crate_name.as_str();
+        let language = options
+            .language
+            .as_deref()
+            .or(self.config.llm.answer_language.as_deref());
+        let template = self
+            .config
+            .crate_settings(crate_name)
+            .and_then(|settings| settings.prompt_template.as_deref());
+        let mut system_prompt = match template {
+            Some(template) => template.replace("{crate}", crate_name),
+            None => format!(
+                "You are an expert technical assistant for the Rust crate '{}'. \
+                 Answer the user's question based *only* on the provided context. \
+                 If the context does not contain the answer, say so. \
+                 Do not make up information. Be clear, concise, and comprehensive. \
+                 Each context document starts with a [Source: ...] line naming its item, and its headings \
+                 are replaced by [Section: ...] lines; cite the item and section your answer relies on, \
+                 e.g. \"per the `tokio::select!` docs, Fairness section\". [Status: ...] lines say an item \
+                 is deprecated, unstable or only available with some features or platforms; when you \
+                 recommend such an item, say so and name the replacement or the feature to enable.",
+                crate_name
+            ),
+        };
+        if options.include_examples.unwrap_or(true) {
+            system_prompt.push_str(" Provide example usage code when possible.");
+        } else {
+            system_prompt.push_str(" Do not include example code unless the question asks for it.");
+        }
+        if let Some(version) = &index.version {
+            system_prompt.push_str(&format!(
+                " The context documents version {} of the crate; when APIs differ \
+                 between versions, answer for this one.",
+                version
+            ));
+        }
+        if let Some(features) = &index.features {
+            system_prompt.push_str(&format!(
+                " The docs were generated with {} of the crate. Items gated behind other \
+                 features are missing from the context; when the question asks for one, \
+                 say which feature may need to be enabled rather than that it doesn't exist.",
+                crate_features::describe_enabled(features)
+            ));
+        }
+        if let Some(language) = language {
+            // Asked for here rather than in the question, so retrieval still
+            // matches the question against the (English) documentation
+            system_prompt.push_str(&format!(
+                " Write the answer in {}. Keep code, identifiers, paths and \
+                 error messages exactly as they appear in the context.",
+                language
+            ));
+        }
+        if self.config.llm.self_assess {
+            system_prompt.push_str(
+                " End your answer with a final line `Confidence: high`, `Confidence: medium` \
+                 or `Confidence: low`, rating how completely the context answers the question.",
+            );
+        }
+
+        // Label each document with its item and headings so answers can cite them
+        let context = matched_docs
+            .iter()
+            .map(|(source, doc)| {
+                let html = fs::read_to_string(source.page_file(&doc.path)).ok();
+                citations::cite_document(&source.crate_name, doc, html.as_deref())
+            })
+            .collect::<Vec<_>>()
+            .join("\n---\n");
+        let user_prompt = format!("Context:\n---\n{}\n---\n\nQuestion: {}", context, question);
+        if redact {
+            let redactor = self.embedding_cache_service.redactor();
+            return (system_prompt, redactor.redact(&user_prompt).into_owned());
+        }
+        (system_prompt, user_prompt)
+    }
+
+    /// Generate a response using the LLM based on matched document context
+    #[instrument(name = "llm", skip(self, matched_docs, question), fields(model = %self.config.llm_model(&index.crate_name), documents = matched_docs.len()))]
+    async fn generate_llm_response(
+        &self,
+        matched_docs: &[(&CrateIndex, &Document)],
+        question: &str,
+        options: &QueryOptions,
+        index: &CrateIndex,
+    ) -> Result<String, McpError> {
+        let crate_name = index.crate_name.as_str();
+        let language = options
+            .language
+            .as_deref()
+            .or(self.config.llm.answer_language.as_deref());
+        if self.config.llm.provider == MOCK_PROVIDER {
+            let docs: Vec<&Document> = matched_docs.iter().map(|(_, doc)| *doc).collect();
+            return Ok(MockChatProvider.complete(crate_name, question, language, &docs));
+        }
+
+        let (system_prompt, user_prompt) =
+            self.llm_prompts(matched_docs, question, options, index, true);
+
+        let budget = self.embedding_cache_service.budget();
+        let answer = async {
+            budget.check()?;
+            self.chat_client
+                .chat(
+                    self.config.llm_model(crate_name),
+                    &system_prompt,
+                    &user_prompt,
+                )
+                .await
+        }
+        .await
+        .map_err(|e| {
+            let error = e.into_mcp_error("OpenAI chat API error");
+            error_data::with_provider(error, &self.config.llm.provider)
+        })?;
+        budget.charge(Spend::Chat {
+            input_tokens: (chunk_context::count_tokens(&system_prompt)
+                + chunk_context::count_tokens(&user_prompt)) as u64,
+            output_tokens: answer.as_deref().map_or(0, chunk_context::count_tokens) as u64,
+        });
+        Ok(answer.unwrap_or_else(|| "Error: No response from LLM.".to_string()))
+    }
+
+    #[tool(
+        description = "Query documentation for a specific Rust crate using semantic search and LLM summarization."
+    )]
+    async fn query_rust_docs(
+        &self,
+        #[tool(aggr)] // Aggregate arguments into the struct
+        args: QueryRustDocsArgs,
+        // Cancelled when the client cancels the request; stops doc generation
+        cancel: CancellationToken,
+    ) -> Result<CallToolResult, McpError> {
+        // Send startup message if not already sent
+        self.try_send_startup_message().await;
+
+        let server = match &args.project {
+            Some(project) => self.for_project(project)?,
+            None => self.clone(),
+        };
+        let options = QueryOptions {
+            language: args.language,
+            top_k: args.top_k,
+            max_context_tokens: args.max_context_tokens,
+            include_examples: args.include_examples,
+            extractive: args.extractive,
+            format: args.format,
+            latency_budget: args.latency_budget_ms.map(Duration::from_millis),
+        };
+        let text = server
+            .answer_query_with(&args.crate_name, &args.question, &options, &cancel)
+            .await?;
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "Ask several questions about Rust crates at once, e.g. 3-5 related questions. Faster than separate query_rust_docs calls since documentation is loaded once and questions are embedded together. Returns one answer per question, in order."
+    )]
+    async fn query_rust_docs_batch(
+        &self,
+        #[tool(aggr)] args: QueryRustDocsBatchArgs,
+        cancel: CancellationToken,
+    ) -> Result<CallToolResult, McpError> {
+        const MAX_QUESTIONS: usize = 10;
+        self.try_send_startup_message().await;
+
+        if args.questions.is_empty() || args.questions.len() > MAX_QUESTIONS {
+            return Err(McpError::invalid_params(
+                format!(
+                    "questions must hold between 1 and {} questions, got {}",
+                    MAX_QUESTIONS,
+                    args.questions.len()
+                ),
+                None,
+            ));
+        }
+        let queries = args
+            .questions
+            .iter()
+            .enumerate()
+            .map(|(i, question)| {
+                let crate_name = question
+                    .crate_name
+                    .as_deref()
+                    .or(args.crate_name.as_deref())
+                    .ok_or_else(|| {
+                        McpError::invalid_params(
+                            format!(
+                                "question {} has no crate_name and no default is given",
+                                i + 1
+                            ),
+                            None,
+                        )
+                    })?;
+                Ok((crate_name, question.question.as_str()))
+            })
+            .collect::<Result<Vec<_>, McpError>>()?;
+
+        let server = match &args.project {
+            Some(project) => self.for_project(project)?,
+            None => self.clone(),
+        };
+        let options = QueryOptions {
+            latency_budget: args.latency_budget_ms.map(Duration::from_millis),
+            ..QueryOptions::with_language(args.language.as_deref())
+        };
+        let answers = server
+            .answer_queries_with(&queries, &options, &cancel)
+            .await;
+        let contents = queries
+            .iter()
+            .zip(answers)
+            .enumerate()
+            .map(|(i, ((_, question), answer))| {
+                let answer = answer.unwrap_or_else(|e| format!("Error: {}", e.message));
+                Content::text(format!("Question {}: {}\n\n{}", i + 1, question, answer))
+            })
+            .collect();
+        Ok(CallToolResult::success(contents))
+    }
+
+    #[tool(
+        description = "List the Cargo features of a Rust crate with their documentation and the items each one gates. Use it to find out which feature is needed for an item."
+    )]
+    async fn list_crate_features(
+        &self,
+        #[tool(aggr)] args: ListCrateFeaturesArgs,
+    ) -> Result<CallToolResult, McpError> {
+        self.try_send_startup_message().await;
+
+        let server = match &args.project {
+            Some(project) => self.for_project(project)?,
+            None => self.clone(),
+        };
+        let text = server.crate_features(&args.crate_name).await?;
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    /// Describes the features of `crate_name` from its `Cargo.toml`, found
+    /// with `cargo metadata`, and its generated documentation, as the
+    /// `list_crate_features` tool does
+    pub async fn crate_features(&self, crate_name: &str) -> Result<String, McpError> {
+        Self::validate_crate_name(crate_name)?;
+        self.check_crate_scope(crate_name)?;
+        let docs = self.docs();
+        // Dependencies without generated docs still have a manifest
+        let resolved = self.resolve_crate_name(crate_name);
+        let doc_name = canonical_crate_name(resolved.as_deref().unwrap_or(crate_name));
+
+        let manifest = match crate_discovery::package_manifest(&docs.project_dir, &doc_name).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!("Could not locate the manifest of '{}': {}", doc_name, e);
+                None
+            }
+        };
+        let doc_dir = docs
+            .doc_roots()
+            .into_iter()
+            .map(|root| root.dir.join(&doc_name))
+            .find(|dir| dir.join("index.html").is_file());
+        if manifest.is_none() && doc_dir.is_none() {
+            resolved?;
+            return Err(McpError::invalid_params(
+                format!(
+                    "Neither a Cargo.toml nor documentation was found for crate '{}'",
+                    crate_name
+                ),
+                None,
+            ));
+        }
+
+        let name = doc_name.clone();
+        let features = tokio::task::spawn_blocking(move || {
+            let mut features = match manifest {
+                Some(path) => crate_features::read_manifest_features(&path)?,
+                None => Vec::new(),
+            };
+            if let Some(dir) = doc_dir {
+                crate_features::attach_gated_items(
+                    &mut features,
+                    crate_features::gated_items(&dir, &name)?,
+                );
+            }
+            Ok::<_, DocLoaderError>(features)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        const LISTED_ITEMS: usize = 20;
+        Ok(crate_features::format_features(
+            &doc_name,
+            &features,
+            LISTED_ITEMS,
+        ))
+    }
+
+    #[tool(
+        description = "Find which documented Rust crates provide an item, e.g. where `StreamExt::buffer_unordered` comes from. Returns the full paths of matching items and their crates."
+    )]
+    async fn find_item(
+        &self,
+        #[tool(aggr)] args: FindItemArgs,
+    ) -> Result<CallToolResult, McpError> {
+        self.try_send_startup_message().await;
+
+        let server = match &args.project {
+            Some(project) => self.for_project(project)?,
+            None => self.clone(),
+        };
+        let text = server.locate_item(&args.item).await?;
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    /// Lists the items of every documented crate whose path ends with `item`,
+    /// as the `find_item` tool does
+    pub async fn locate_item(&self, item: &str) -> Result<String, McpError> {
+        const MAX_ITEMS: usize = 20;
+
+        if item.trim().is_empty() {
+            return Err(McpError::invalid_params("item must not be empty", None));
+        }
+        let crates: Vec<(String, PathBuf)> = self
+            .discover_crates()
+            .into_iter()
+            .map(|discovered| (discovered.name, discovered.doc_dir))
+            .collect();
+        let query = item.to_string();
+        let found = tokio::task::spawn_blocking(move || {
+            let crates: Vec<(String, &Path)> = crates
+                .iter()
+                .map(|(name, dir)| (name.clone(), dir.as_path()))
+                .collect();
+            item_finder::find_items(&crates, &query, MAX_ITEMS)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        if found.is_empty() {
+            return Err(McpError::invalid_params(
+                format!(
+                    "No documented crate provides '{}'. Only crates with generated docs are \
+                     searched; run 'cargo doc' to document more.",
+                    item.trim()
+                ),
+                Some(json!({ "item": item })),
+            ));
+        }
+        Ok(item_finder::format_found_items(item, &found))
+    }
+
+    #[tool(
+        description = "Describe what the indexes of Rust crates were built from (documentation hash, chunker settings, embedding model, server version) and whether they are still current."
+    )]
+    async fn index_status(
+        &self,
+        #[tool(aggr)] args: IndexStatusArgs,
+    ) -> Result<CallToolResult, McpError> {
+        self.try_send_startup_message().await;
+
+        let server = match &args.project {
+            Some(project) => self.for_project(project)?,
+            None => self.clone(),
+        };
+        let text = server.describe_indexes(args.crate_name.as_deref())?;
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    /// Describes the manifest and freshness of the index of `crate_name`, or
+    /// of every indexed crate, as the `index_status` tool does
+    pub fn describe_indexes(&self, crate_name: Option<&str>) -> Result<String, McpError> {
+        let indexes = match crate_name {
+            Some(requested) => {
+                Self::validate_crate_name(requested)?;
+                let index = match self.state.loaded(requested) {
+                    Some(index) => Some(index),
+                    None => self.state.loaded(&self.resolve_crate_name(requested)?),
+                };
+                let index = index.ok_or_else(|| {
+                    McpError::invalid_params(
+                        format!(
+                            "Crate '{}' isn't indexed yet; it is indexed the first time it is queried.",
+                            requested
+                        ),
+                        Some(json!({ "crate_name": requested })),
+                    )
+                })?;
+                vec![index]
+            }
+            None => self
+                .state
+                .crate_names()
+                .iter()
+                .filter_map(|name| self.state.loaded(name))
+                .collect(),
+        };
+        if indexes.is_empty() {
+            return Ok(
+                "No crate is indexed yet; crates are indexed the first time they are queried."
+                    .to_string(),
+            );
+        }
+
+        let sections: Vec<String> = indexes
+            .iter()
+            .map(|index| {
+                let mut text = match &index.manifest {
+                    Some(manifest) => manifest.to_string(),
+                    None => format!("Crate: {}", index.crate_name),
+                };
+                if let Some(version) = &index.version {
+                    text.push_str(&format!("\nCrate version: {}", version));
+                }
+                if let Some(features) = &index.features {
+                    text.push_str(&format!(
+                        "\nGenerated with: {}",
+                        crate_features::describe_enabled(features)
+                    ));
+                }
+                text.push_str(&format!(
+                    "\nIndexed documents: {} ({} skipped)",
+                    index.embeddings.len(),
+                    index.skipped.len()
+                ));
+                if !index.pending.is_empty() {
+                    text.push_str(&format!(
+                        "\nStill indexing: {} large documents",
+                        index.pending.len()
+                    ));
+                }
+                text.push_str(if index.is_current() {
+                    "\nStatus: current"
+                } else {
+                    "\nStatus: stale, the docs changed since indexing; the next query re-indexes the changed pages"
+                });
+                text
+            })
+            .collect();
+        Ok(sections.join("\n\n"))
+    }
+
+    #[tool(
+        description = "Return the source code of an item of a Rust crate, e.g. when its documentation doesn't explain how it behaves."
+    )]
+    async fn get_source(
+        &self,
+        #[tool(aggr)] args: GetSourceArgs,
+    ) -> Result<CallToolResult, McpError> {
+        self.try_send_startup_message().await;
+
+        let server = match &args.project {
+            Some(project) => self.for_project(project)?,
+            None => self.clone(),
+        };
+        let text = server.item_source(&args.crate_name, &args.item).await?;
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    /// Returns the source of `item` in `crate_name` from the crate's generated
+    /// docs, as the `get_source` tool does
+    pub async fn item_source(&self, crate_name: &str, item: &str) -> Result<String, McpError> {
+        const MAX_LINES: usize = 400;
+
+        Self::validate_crate_name(crate_name)?;
+        self.check_crate_scope(crate_name)?;
+        let crate_name = self.resolve_crate_name(crate_name)?;
+        let versioned = self
+            .versioned_docs
+            .read()
+            .ok()
+            .and_then(|versioned| versioned.get(&crate_name).map(|docs| docs.doc_dir.clone()));
+        let doc_dir = versioned
+            .or_else(|| {
+                self.docs()
+                    .doc_roots()
+                    .into_iter()
+                    .map(|root| root.dir)
+                    .find(|dir| dir.join(&crate_name).join("index.html").is_file())
+            })
+            .ok_or_else(|| {
+                McpError::invalid_params(
+                    format!(
+                        "No documentation found for crate '{}'. Run 'cargo doc --package {}' first.",
+                        crate_name, crate_name
+                    ),
+                    None,
+                )
+            })?;
+
+        let (name, requested) = (crate_name.clone(), item.to_string());
+        let source = tokio::task::spawn_blocking(move || {
+            item_source::find_item_source(&doc_dir, &name, &requested, MAX_LINES)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        match source {
+            Some(source) => Ok(item_source::format_item_source(&source)),
+            None => Err(McpError::invalid_params(
+                format!(
+                    "No source found for '{}' in crate '{}'. Use the item's path as documented, \
+                     e.g. `Type`, `module::function` or `Type::method`.",
+                    item, crate_name
+                ),
+                Some(json!({ "crate_name": crate_name, "item": item })),
+            )),
+        }
+    }
+
+    #[tool(
+        description = "Explain how query_rust_docs answers a question: every document ranked for it with its similarity and score, which were filtered out by min_score, top_k or the context budget, and the prompt sent to the LLM. Use it to find out why an answer was bad."
+    )]
+    async fn debug_retrieval(
+        &self,
+        #[tool(aggr)] args: DebugRetrievalArgs,
+        cancel: CancellationToken,
+    ) -> Result<CallToolResult, McpError> {
+        self.try_send_startup_message().await;
+
+        let server = match &args.project {
+            Some(project) => self.for_project(project)?,
+            None => self.clone(),
+        };
+        let options = QueryOptions {
+            top_k: args.top_k,
+            max_context_tokens: args.max_context_tokens,
+            ..QueryOptions::default()
+        };
+        let report = server
+            .explain_retrieval(
+                &args.crate_name,
+                &args.question,
+                &options,
+                args.redact.unwrap_or(true),
+                &cancel,
+            )
+            .await?;
+        Ok(CallToolResult::success(vec![Content::text(
+            report.to_string(),
+        )]))
+    }
+
+    /// Retrieves the documents for `question` as
+    /// [`answer_query_with`](Self::answer_query_with) does, without
+    /// answering it, and reports every document ranked along the way, as
+    /// the `debug_retrieval` tool does
+    pub async fn explain_retrieval(
+        &self,
+        crate_name: &str,
+        question: &str,
+        options: &QueryOptions,
+        redact: bool,
+        cancel: &CancellationToken,
+    ) -> Result<RetrievalReport, McpError> {
+        // Documents ranked below those answered from that are still reported
+        const CANDIDATES: usize = 20;
+
+        // Invalid options are rejected before the crate is loaded
+        self.query_limits(crate_name, options)?;
+        let (index, question) = self.prepare_query(crate_name, question, cancel).await?;
+        let (top_k, context_tokens) = self.query_limits(&index.crate_name, options)?;
+        let companions = self.load_companions(&index, cancel).await;
+        let question_embedding = self
+            .embedding_cache_service
+            .get_question_embedding(question)
+            .await
+            .map_err(|e| self.embedding_error(e, "Failed to get embedding for question"))?;
+
+        // The documents answered from, and what became of them
+        let selected =
+            self.search_with_companions(&index, &companions, question, &question_embedding, top_k);
+        let mut matched = Vec::with_capacity(selected.len());
+        for (source, path, _) in &selected {
+            matched.extend(Self::document_text(source, path)?.map(|doc| (*source, doc)));
+        }
+        let focused = self
+            .fit_context(matched, &question_embedding, options, context_tokens)
+            .await;
+        let outcome = |source: &CrateIndex, path: &str| {
+            focused
+                .iter()
+                .find(|(sent, doc, _)| sent.crate_name == source.crate_name && doc.path == path)
+                .map(|(_, _, tokens)| Outcome::Sent { tokens: *tokens })
+        };
+
+        // Every document near the top of the ranking, including those below
+        // min_score
+        let min_score = self.config.retrieval.min_score;
+        let count = top_k.max(CANDIDATES);
+        let mut ranked: Vec<(&CrateIndex, RankedMatch)> = std::iter::once(index.as_ref())
+            .chain(companions.iter().map(Arc::as_ref))
+            .flat_map(|source| {
+                self.rank_matches(question, &question_embedding, source, count, f32::MIN)
+                    .into_iter()
+                    .map(move |ranked| (source, ranked))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.score.total_cmp(&a.1.score));
+        ranked.truncate(count);
+        let mut candidates = Vec::with_capacity(ranked.len());
+        for (source, ranked) in ranked {
+            let is_selected = selected.iter().any(|(chosen, path, _)| {
+                chosen.crate_name == source.crate_name && *path == ranked.path
+            });
+            let outcome = match outcome(source, ranked.path) {
+                Some(sent) => sent,
+                None if is_selected => Outcome::OverBudget,
+                None if ranked.similarity < min_score => Outcome::BelowMinScore,
+                None => Outcome::BeyondTopK,
+            };
+            let text = Self::document_text(source, ranked.path)?;
+            candidates.push(Candidate {
+                crate_name: source.crate_name.clone(),
+                path: ranked.path.to_string(),
+                similarity: ranked.similarity,
+                score: ranked.score,
+                preview: text.map_or_else(String::new, |doc| {
+                    retrieval_debug::preview(&doc.content, retrieval_debug::PREVIEW_CHARS)
+                }),
+                outcome,
+            });
+        }
+
+        // The answer is made the way answer_from_index makes it
+        let matched_docs: Vec<(&CrateIndex, &Document)> = focused
+            .iter()
+            .map(|(source, doc, _)| (*source, doc))
+            .collect();
+        let (answer_mode, asks_llm) = if matched_docs.is_empty() {
+            ("none, no document matched".to_string(), false)
+        } else if options.extractive.unwrap_or(self.config.llm.extractive) {
+            (
+                "extractive, sentences quoted from the documents".to_string(),
+                false,
+            )
+        } else if self.config.offline {
+            ("the documents themselves, offline".to_string(), false)
+        } else if self.config.llm.provider == MOCK_PROVIDER {
+            // Shown with the prompt a real LLM would be sent
+            ("mock LLM".to_string(), true)
+        } else if self.embedding_cache_service.budget().is_exhausted() {
+            (
+                "the documents themselves, the provider budget is exhausted".to_string(),
+                false,
+            )
+        } else {
+            (
+                format!("LLM ({})", self.config.llm_model(&index.crate_name)),
+                true,
+            )
+        };
+        let prompt =
+            asks_llm.then(|| self.llm_prompts(&matched_docs, question, options, &index, redact));
+
+        Ok(RetrievalReport {
+            crate_name: index.crate_name.clone(),
+            question: question.to_string(),
+            top_k,
+            min_score,
+            context_tokens,
+            candidates,
+            answer_mode,
+            prompt,
+            redacted: redact,
+        })
+    }
+
+    /// The tools offered to clients. Their descriptions end in the
+    /// read-only and idempotent hints, and `project` arguments are limited to
+    /// the configured project names.
+    pub fn tools(&self) -> Vec<Tool> {
+        let mut projects: Vec<Value> = self
+            .config
+            .projects
+            .iter()
+            .map(|project| json!(project.name))
+            .collect();
+        if !projects.is_empty() {
+            // Omitting `project` selects the default one
+            projects.push(Value::Null);
+        }
+
+        Self::tool_box()
+            .list()
+            .into_iter()
+            .filter(|tool| self.tool_enabled(&tool.name))
+            .map(|mut tool| {
+                tool.description = format!("{} {}", tool.description, TOOL_HINTS).into();
+                let mut schema = (*tool.input_schema).clone();
+                if !projects.is_empty()
+                    && let Some(Value::Object(project)) = schema
+                        .get_mut("properties")
+                        .and_then(|properties| properties.get_mut("project"))
+                {
+                    project.insert("enum".to_string(), Value::Array(projects.clone()));
+                }
+                tool.input_schema = Arc::new(schema);
+                tool
+            })
+            .collect()
+    }
+
+    /// Returns true if the tool named `name` is offered to clients
+    fn tool_enabled(&self, name: &str) -> bool {
+        match name {
+            "get_source" => self.config.tools.get_source,
+            "debug_retrieval" => self.config.tools.debug_retrieval,
+            _ => true,
+        }
+    }
+
+    /// Runs the tool a `tools/call` request names, if it is enabled
+    async fn dispatch_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if !self.tool_enabled(&request.name) {
+            let error = McpError::invalid_params(
+                format!(
+                    "Tool '{}' is not enabled; set `{}` in the [tools] configuration",
+                    request.name, request.name
+                ),
+                None,
+            );
+            let error = error_data::with_suggestion(error, Suggestion::ChangeConfig);
+            return Err(error_data::finish(error));
+        }
+        // Errors name the crate asked about, unless they already name another
+        let crate_name = request
+            .arguments
+            .as_ref()
+            .and_then(|arguments| arguments.get("crate_name"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let context = ToolCallContext::new(self, request, context);
+        Self::tool_box().call(context).await.map_err(|error| {
+            let error = match &crate_name {
+                Some(crate_name) => error_data::with_crate(error, crate_name),
+                None => error,
+            };
+            error_data::finish(error)
+        })
+    }
+
+    /// Answers `question` from the documentation of `crate_name` (`name` or
+    /// `name@version`), as the `query_rust_docs` tool does. The answer is
+    /// written in `language` if given, else in `llm.answer_language`.
+    pub async fn answer_query(
+        &self,
+        crate_name: &str,
+        question: &str,
+        language: Option<&str>,
+        cancel: &CancellationToken,
+    ) -> Result<String, McpError> {
+        let options = QueryOptions::with_language(language);
+        self.answer_query_with(crate_name, question, &options, cancel)
+            .await
+    }
+
+    /// Answers `question` like [`answer_query`](Self::answer_query), with
+    /// the query's own retrieval and answer options
+    pub async fn answer_query_with(
+        &self,
+        crate_name: &str,
+        question: &str,
+        options: &QueryOptions,
+        cancel: &CancellationToken,
+    ) -> Result<String, McpError> {
+        let started = Instant::now();
+        let question = self.validate_question(question)?;
+        self.query_limits(crate_name, options)?;
+        if self.config.retrieval.route_questions
+            && let Some(answer) = self.answer_routed(crate_name, question).await?
+        {
+            return Ok(answer);
+        }
+        let question_cached = self.embedding_cache_service.is_question_cached(question);
+        let deadline = self.query_deadline(started, options);
+        // Embed the question while the crate loads, which may mean indexing
+        // it or generating its docs
+        let loading = async {
+            let loaded = match deadline {
+                // Loading goes on after the budget runs out, so the crate is
+                // indexed by the time it is asked about again
+                Some(deadline) => {
+                    let server = self.clone();
+                    let (crate_name, question) = (crate_name.to_string(), question.to_string());
+                    let cancel = cancel.clone();
+                    let loading = tokio::spawn(async move {
+                        let prepared = server
+                            .prepare_query(&crate_name, &question, &cancel)
+                            .await
+                            .map(|(index, _)| index);
+                        let companions = match &prepared {
+                            Ok(index) => server.load_companions(index, &cancel).await,
+                            Err(_) => Vec::new(),
+                        };
+                        (prepared, companions)
+                    });
+                    match tokio::time::timeout_at(deadline.into(), loading).await {
+                        Ok(Ok(loaded)) => Some(loaded),
+                        Ok(Err(e)) => Some((
+                            Err(McpError::internal_error(
+                                format!("Loading task failed: {}", e),
+                                None,
+                            )),
+                            Vec::new(),
+                        )),
+                        Err(_) => None,
+                    }
+                }
+                None => {
+                    let prepared = self
+                        .prepare_query(crate_name, question, cancel)
+                        .await
+                        .map(|(index, _)| index);
+                    let companions = match &prepared {
+                        Ok(index) => self.load_companions(index, cancel).await,
+                        Err(_) => Vec::new(),
+                    };
+                    Some((prepared, companions))
+                }
+            };
+            (loaded, started.elapsed())
+        };
+        let embedding = async {
+            let embedding = match deadline {
+                // Embedding goes on after the budget runs out, so the
+                // question is cached for the next time it is asked
+                Some(deadline) => {
+                    let service = Arc::clone(&self.embedding_cache_service);
+                    let owned = question.to_string();
+                    let embedding = tokio::spawn(
+                        async move { service.get_question_embedding(&owned).await }
+                            .instrument(info_span!("embed_question")),
+                    );
+                    match tokio::time::timeout_at(deadline.into(), embedding).await {
+                        Ok(Ok(embedding)) => Some(embedding),
+                        Ok(Err(e)) => Some(Err(ServerError::McpRuntime(format!(
+                            "Embedding task failed: {}",
+                            e
+                        )))),
+                        Err(_) => None,
+                    }
+                }
+                None => Some(
+                    self.embedding_cache_service
+                        .get_question_embedding(question)
+                        .instrument(info_span!("embed_question"))
+                        .await,
+                ),
+            };
+            (embedding, started.elapsed())
+        };
+        let ((loaded, load), (embedding, embed)) = tokio::join!(loading, embedding);
+        let Some((prepared, companions)) = loaded else {
+            return Ok(self.still_loading_answer(crate_name, options, load));
+        };
+        let index = prepared?;
+        let timing = QueryTiming {
+            started,
+            load,
+            embed,
+            question_cached,
+            deadline,
+        };
+        let Some(embedding) = embedding else {
+            return self.answer_from_keywords(&index, question, options, timing);
+        };
+        let question_embedding = embedding
+            .map_err(|e| self.embedding_error(e, "Failed to get embedding for question"))?;
+        self.answer_from_index(
+            &index,
+            &companions,
+            question,
+            &question_embedding,
+            options,
+            timing,
+        )
+        .await
+    }
+
+    /// When the latency budget of a query started at `started` runs out: its
+    /// own budget, else `retrieval.latency_budget_ms`. `None` waits.
+    fn query_deadline(&self, started: Instant, options: &QueryOptions) -> Option<Instant> {
+        options
+            .latency_budget
+            .or_else(|| match self.config.retrieval.latency_budget_ms {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            })
+            .map(|budget| started + budget)
+    }
+
+    /// Answers `question` from the item index or Cargo's metadata if the
+    /// [`question_router`] sends it there and they know the answer. `None`
+    /// leaves it to semantic retrieval and the LLM, as do questions about
+    /// specific releases or crates that aren't documented yet.
+    async fn answer_routed(
+        &self,
+        crate_name: &str,
+        question: &str,
+    ) -> Result<Option<String>, McpError> {
+        const MAX_ITEMS: usize = 20;
+
+        let route = question_router::classify(question);
+        if route == Route::Semantic {
+            return Ok(None);
+        }
+        let requested = CrateSpec::parse(crate_name, Vec::new())
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        self.check_crate_scope(&requested.name)?;
+        if requested.version_req.is_some() {
+            return Ok(None);
+        }
+        let crate_name = match &self.crate_scope {
+            Some(spec) => spec.doc_name(),
+            None => match self.resolve_crate_name(&requested.name) {
+                Ok(resolved) => resolved,
+                Err(_) => return Ok(None),
+            },
+        };
+        let Some(discovered) = self
+            .discover_crates()
+            .into_iter()
+            .find(|discovered| discovered.name == crate_name)
+        else {
+            return Ok(None);
+        };
+
+        let answer = match route {
+            Route::ItemLookup(item) => {
+                let query = item.clone();
+                let found = tokio::task::spawn_blocking(move || {
+                    let crates = [(discovered.name.clone(), discovered.doc_dir.as_path())];
+                    item_finder::find_items(&crates, &query, MAX_ITEMS)
+                })
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                match found {
+                    Ok(found) if !found.is_empty() => {
+                        Some(item_finder::format_found_items(&item, &found))
+                    }
+                    Ok(_) => None,
+                    Err(e) => {
+                        debug!("Item lookup in '{}' failed: {}", crate_name, e);
+                        None
+                    }
+                }
+            }
+            Route::Version => self.describe_versions(&discovered).await,
+            Route::Semantic => None,
+        };
+        if answer.is_some() {
+            info!(crate_name = %crate_name, question = %question, "Answered routed query");
+        }
+        Ok(answer)
+    }
+
+    /// Describes the versions of a crate the project's `Cargo.lock` (or
+    /// `cargo metadata`) resolves and the one its docs were generated for.
+    /// `None` if neither is known.
+    async fn describe_versions(&self, discovered: &DiscoveredCrate) -> Option<String> {
+        let project_dir = self.docs().project_dir;
+        let mut versions = crate_discovery::locked_versions(&project_dir, &discovered.name);
+        if versions.is_empty() {
+            match crate_discovery::package_versions(&project_dir).await {
+                Ok(resolved) => {
+                    versions = resolved
+                        .get(&discovered.name)
+                        .map(|joined| joined.split(", ").map(str::to_string).collect())
+                        .unwrap_or_default();
+                }
+                Err(e) => debug!("Crate versions unavailable: {}", e),
+            }
+        }
+
+        let mut text = match versions.as_slice() {
+            [] => String::new(),
+            [version] => format!(
+                "The project uses `{}` version {}.",
+                discovered.name, version
+            ),
+            versions => format!(
+                "The project's dependency graph resolves `{}` at versions {}.",
+                discovered.name,
+                versions.join(", ")
+            ),
+        };
+        let documented = discovered.version.clone().or_else(|| {
+            doc_loader::documented_version(
+                &discovered.doc_dir.join(&discovered.name).join("index.html"),
+            )
+        });
+        if let Some(documented) = documented
+            && !versions.contains(&documented)
+        {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(&format!(
+                "The documentation was generated for version {}.",
+                documented
+            ));
+        }
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Answers several `(crate_name, question)` pairs, as the
+    /// `query_rust_docs_batch` tool does. Each crate is loaded once and the
+    /// questions are embedded together; answers are generated concurrently
+    /// and returned in order, each failing on its own. Questions are routed
+    /// like [`answer_query`](Self::answer_query)'s.
+    pub async fn answer_queries(
+        &self,
+        queries: &[(&str, &str)],
+        language: Option<&str>,
+        cancel: &CancellationToken,
+    ) -> Vec<Result<String, McpError>> {
+        let options = QueryOptions::with_language(language);
+        self.answer_queries_with(queries, &options, cancel).await
+    }
+
+    /// Answers several questions like [`answer_queries`](Self::answer_queries),
+    /// with the batch's own options. The latency budget covers the whole batch.
+    pub async fn answer_queries_with(
+        &self,
+        queries: &[(&str, &str)],
+        options: &QueryOptions,
+        cancel: &CancellationToken,
+    ) -> Vec<Result<String, McpError>> {
+        let started = Instant::now();
+        let deadline = self.query_deadline(started, options);
+        // Answers of questions the router sends away from retrieval
+        let mut routed = Vec::with_capacity(queries.len());
+        for (crate_name, question) in queries {
+            let answer = match self.validate_question(question) {
+                Ok(question) if self.config.retrieval.route_questions => {
+                    self.answer_routed(crate_name, question).await.transpose()
+                }
+                _ => None,
+            };
+            routed.push(answer);
+        }
+        let pending: Vec<(&str, &str)> = queries
+            .iter()
+            .zip(&routed)
+            .filter(|(_, routed)| routed.is_none())
+            .map(|(query, _)| *query)
+            .collect();
+        let valid: Vec<bool> = pending
+            .iter()
+            .map(|(_, question)| self.validate_question(question).is_ok())
+            .collect();
+        let questions: Vec<&str> = pending
+            .iter()
+            .zip(&valid)
+            .filter(|(_, valid)| **valid)
+            .map(|((_, question), _)| question.trim())
+            .collect();
+        let mut cached = questions
+            .iter()
+            .map(|question| self.embedding_cache_service.is_question_cached(question))
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        // Embed the questions while the crates load
+        let loading = async {
+            match deadline {
+                // Loading goes on after the budget runs out, so the crates
+                // are indexed by the time they are asked about again
+                Some(deadline) => {
+                    let server = self.clone();
+                    let owned: Vec<(String, String)> = pending
+                        .iter()
+                        .map(|(crate_name, question)| {
+                            (crate_name.to_string(), question.to_string())
+                        })
+                        .collect();
+                    let cancel = cancel.clone();
+                    let loading = tokio::spawn(async move {
+                        let queries: Vec<(&str, &str)> = owned
+                            .iter()
+                            .map(|(crate_name, question)| (crate_name.as_str(), question.as_str()))
+                            .collect();
+                        server.load_queries(&queries, &cancel).await
+                    });
+                    match tokio::time::timeout_at(deadline.into(), loading).await {
+                        Ok(Ok(loaded)) => loaded.into_iter().map(Some).collect(),
+                        Ok(Err(e)) => pending
+                            .iter()
+                            .map(|_| {
+                                let error = McpError::internal_error(
+                                    format!("Loading task failed: {}", e),
+                                    None,
+                                );
+                                Some((Err(error), started.elapsed()))
+                            })
+                            .collect(),
+                        Err(_) => pending.iter().map(|_| None).collect(),
+                    }
+                }
+                None => self
+                    .load_queries(&pending, cancel)
+                    .await
+                    .into_iter()
+                    .map(Some)
+                    .collect::<Vec<_>>(),
+            }
+        };
+        let embedding = async {
+            let embeddings = match deadline {
+                // Embedding goes on after the budget runs out, so the
+                // questions are cached for the next time they are asked
+                Some(deadline) => {
+                    let service = Arc::clone(&self.embedding_cache_service);
+                    let owned: Vec<String> = questions.iter().map(|q| q.to_string()).collect();
+                    let embedding = tokio::spawn(
+                        async move {
+                            let questions: Vec<&str> = owned.iter().map(String::as_str).collect();
+                            service.get_question_embeddings(&questions).await
+                        }
+                        .instrument(info_span!("embed_questions", count = questions.len())),
+                    );
+                    match tokio::time::timeout_at(deadline.into(), embedding).await {
+                        Ok(Ok(embeddings)) => Some(embeddings),
+                        Ok(Err(e)) => Some(
+                            questions
+                                .iter()
+                                .map(|_| {
+                                    Err(ServerError::McpRuntime(format!(
+                                        "Embedding task failed: {}",
+                                        e
+                                    )))
+                                })
+                                .collect(),
+                        ),
+                        Err(_) => None,
+                    }
+                }
+                None => Some(
+                    self.embedding_cache_service
+                        .get_question_embeddings(&questions)
+                        .instrument(info_span!("embed_questions", count = questions.len()))
+                        .await,
+                ),
+            };
+            // The questions are embedded together, so each is charged the whole batch
+            (embeddings, started.elapsed())
+        };
+        let (loaded, (embeddings, embed)) = tokio::join!(loading, embedding);
+        let embedded = embeddings.is_some();
+        let mut embeddings = embeddings.into_iter().flatten();
+
+        let answering: Vec<_> = loaded
+            .into_iter()
+            .zip(&pending)
+            .zip(valid)
+            .map(|((loaded, (crate_name, question)), valid)| {
+                let embedding = (valid && embedded).then(|| embeddings.next()).flatten();
+                let question_cached = valid && cached.next().unwrap_or(false);
+                async move {
+                    let Some((prepared, load)) = loaded else {
+                        return Ok(self.still_loading_answer(
+                            crate_name,
+                            options,
+                            started.elapsed(),
+                        ));
+                    };
+                    let (index, companions) = prepared?;
+                    let question = question.trim();
+                    let timing = QueryTiming {
+                        started,
+                        load,
+                        embed,
+                        question_cached,
+                        deadline,
+                    };
+                    if !embedded {
+                        return self.answer_from_keywords(&index, question, options, timing);
+                    }
+                    let question_embedding = embedding
+                        .ok_or_else(|| {
+                            McpError::internal_error("Missing question embedding", None)
+                        })?
+                        .map_err(|e| {
+                            self.embedding_error(e, "Failed to get embedding for question")
+                        })?;
+                    self.answer_from_index(
+                        &index,
+                        &companions,
+                        question,
+                        &question_embedding,
+                        options,
+                        timing,
+                    )
+                    .await
                 }
-            } else {
-                eprintln!("Log task ran but MCP peer was not connected.");
-            }
-        });
+            })
+            .collect();
+        let mut answered = futures::future::join_all(answering).await.into_iter();
+        routed
+            .into_iter()
+            .map(|routed| match routed {
+                Some(answer) => answer,
+                None => answered
+                    .next()
+                    .unwrap_or_else(|| Err(McpError::internal_error("Missing answer", None))),
+            })
+            .collect()
     }
 
-    // Helper for creating simple text resources
-    fn _create_resource_text(&self, uri: &str, name: &str) -> Resource {
-        RawResource::new(uri, name.to_string()).no_annotation()
+    /// Loads the index and companions of the crate each `(crate_name,
+    /// question)` pair is about, with the time each took. Crates are loaded
+    /// one after another so questions about the same crate share its index.
+    async fn load_queries(
+        &self,
+        queries: &[(&str, &str)],
+        cancel: &CancellationToken,
+    ) -> Vec<LoadedQuery> {
+        let mut loaded = Vec::with_capacity(queries.len());
+        for (crate_name, question) in queries {
+            let loading = Instant::now();
+            let prepared = match self.prepare_query(crate_name, question, cancel).await {
+                Ok((index, _)) => {
+                    let companions = self.load_companions(&index, cancel).await;
+                    Ok((index, companions))
+                }
+                Err(e) => Err(e),
+            };
+            loaded.push((prepared, loading.elapsed()));
+        }
+        loaded
     }
 
-    // Find all available crates in the cargo doc directory
-    fn get_available_crates(&self) -> Vec<String> {
-        let target_doc_path = std::path::Path::new("./target/doc");
-
-        // If the doc directory doesn't exist, return empty list
-        if !target_doc_path.exists() || !target_doc_path.is_dir() {
-            return Vec::new();
+    /// Rejects empty and overlong questions. Returns the trimmed question.
+    fn validate_question<'q>(&self, question: &'q str) -> Result<&'q str, McpError> {
+        let question = question.trim();
+        if question.is_empty() {
+            return Err(McpError::invalid_params("question must not be empty", None));
         }
-
-        // Read the directory and collect crate names
-        match std::fs::read_dir(target_doc_path) {
-            Ok(entries) => {
-                entries
-                    .filter_map(Result::ok)
-                    .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-                    .filter_map(|entry| {
-                        // Check if this directory has an index.html file (indicating a proper doc dir)
-                        let path = entry.path();
-                        let has_index = path.join("index.html").exists();
-
-                        if has_index {
-                            entry.file_name().to_str().map(String::from)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect()
-            }
-            Err(_) => Vec::new(),
+        let max_chars = self.config.retrieval.max_question_chars;
+        let question_chars = question.chars().count();
+        if question_chars > max_chars {
+            return Err(McpError::invalid_params(
+                format!(
+                    "question is {} characters long; the limit is {}",
+                    question_chars, max_chars
+                ),
+                Some(json!({ "length": question_chars, "max_question_chars": max_chars })),
+            ));
         }
+        Ok(question)
     }
-}
 
-// --- Tool Implementation ---
+    /// Validates a question and loads the index of the crate it is about,
+    /// generating versioned docs if needed. Returns the trimmed question.
+    async fn prepare_query<'q>(
+        &self,
+        crate_name: &str,
+        question: &'q str,
+        cancel: &CancellationToken,
+    ) -> Result<(Arc<CrateIndex>, &'q str), McpError> {
+        let question = self.validate_question(question)?;
 
-#[tool(tool_box)] // Add tool_box here as well, mirroring the example
-// Tool methods go in a regular impl block
-impl RustDocsServer {
-    // Define the tool using the tool macro
-    // Name removed; will be handled dynamically by overriding list_tools/get_tool
-    /// Try to send the startup message if it hasn't been sent yet
-    async fn try_send_startup_message(&self) {
-        let mut sent_guard = self.startup_message_sent.lock().await;
-        if !*sent_guard {
-            let mut msg_guard = self.startup_message.lock().await;
-            if let Some(message) = msg_guard.take() {
-                self.send_log(LoggingLevel::Info, message);
-                *sent_guard = true;
+        // `name@version` asks for a specific crates.io release
+        let requested = CrateSpec::parse(crate_name, Vec::new())
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        self.check_crate_scope(&requested.name)?;
+        if requested.version_req.is_some() && !self.is_registered(&requested) {
+            if !self.config.docs.auto_generate {
+                let error = McpError::invalid_params(
+                    format!(
+                        "Documentation for '{}' is not available. Set `auto_generate = true` in the \
+                         [docs] configuration to generate docs for specific crate versions.",
+                        crate_name
+                    ),
+                    None,
+                );
+                return Err(error_data::with_suggestion(error, Suggestion::ChangeConfig));
             }
-            drop(msg_guard);
+            self.add_versioned_crate(&requested, cancel)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
         }
-        drop(sent_guard);
-    }
 
-    /// Load documentation and embeddings for a custom crate
-    async fn load_custom_crate_docs(
-        &self,
-        crate_name: &str,
-    ) -> Result<(String, Vec<Document>, Vec<(String, Embedding)>), McpError> {
+        // Load documentation and embeddings for the specified crate
+        let crate_name = match &self.crate_scope {
+            Some(spec) => spec.doc_name(),
+            None if requested.version_req.is_some() => requested.doc_name(),
+            None => self.resolve_crate_name(&requested.name)?,
+        };
+        let index = self.load_custom_crate_docs(&crate_name, cancel).await?;
+
+        // Log received query locally and via MCP
+        info!(crate_name = %index.crate_name, question = %question, "Received query");
         self.send_log(
             LoggingLevel::Info,
-            format!("Loading local documentation for crate '{}'", crate_name),
+            format!(
+                "Received query for crate '{}': {}",
+                index.crate_name, question
+            ),
         );
 
-        // Load documents from cargo doc
-        let docs = doc_loader::load_documents_from_cargo_doc(crate_name).map_err(|e| {
-            McpError::internal_error(format!("Failed to load local documentation: {}", e), None)
-        })?;
+        Ok((index, question))
+    }
 
-        if docs.is_empty() {
-            return Err(McpError::internal_error(
-                format!(
-                    "No documentation found for crate '{}'. Run 'cargo doc --package {}' first.",
-                    crate_name, crate_name
+    /// Loads the crates configured as `companions` of the crate, e.g.
+    /// `tokio-util` for `tokio`, to be searched along with it. Companions
+    /// that can't be loaded are skipped, and none are searched when the
+    /// server is restricted to one crate.
+    async fn load_companions(
+        &self,
+        index: &CrateIndex,
+        cancel: &CancellationToken,
+    ) -> Vec<Arc<CrateIndex>> {
+        if self.crate_scope.is_some() {
+            return Vec::new();
+        }
+        let Some(settings) = self.config.docs.for_crate(&index.crate_name) else {
+            return Vec::new();
+        };
+        let mut companions: Vec<Arc<CrateIndex>> = Vec::new();
+        for name in &settings.companions {
+            let loaded = match self.resolve_crate_name(name) {
+                Ok(resolved) => self.load_custom_crate_docs(&resolved, cancel).await,
+                Err(e) => Err(e),
+            };
+            match loaded {
+                Ok(companion)
+                    if companion.crate_name != index.crate_name
+                        && !companions
+                            .iter()
+                            .any(|c| c.crate_name == companion.crate_name) =>
+                {
+                    companions.push(companion)
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Not searching companion crate '{}' of '{}': {}",
+                    name, index.crate_name, e.message
                 ),
-                None,
-            ));
+            }
         }
+        companions
+    }
 
-        // Use embedding cache service to get or generate embeddings
-        let mut array_embeddings = Vec::new();
-        self.send_log(
-            LoggingLevel::Info,
-            format!("Using embedding cache service for crate '{}'", crate_name),
-        );
+    /// Answers a prepared question from the crate's index and those of its
+    /// companion crates
+    async fn answer_from_index(
+        &self,
+        index: &CrateIndex,
+        companions: &[Arc<CrateIndex>],
+        question: &str,
+        question_embedding: &Embedding,
+        options: &QueryOptions,
+        timing: QueryTiming,
+    ) -> Result<String, McpError> {
+        // Find the best matching documents, reading back only their text. A
+        // few more scores than needed are looked at to rate the retrieval.
+        let searching = Instant::now();
+        let (top_k, context_tokens) = self.query_limits(&index.crate_name, options)?;
+        let sample = top_k.max(confidence::SCORE_SAMPLE);
+        let mut best_matches =
+            self.search_with_companions(index, companions, question, question_embedding, sample);
+        let scores: Vec<f32> = best_matches.iter().map(|(_, _, score)| *score).collect();
+        let mut confidence = Confidence::from_scores(&scores);
+        best_matches.truncate(top_k);
+        let mut matched = Vec::with_capacity(best_matches.len());
+        for (source, best_path, score) in &best_matches {
+            debug!(crate_name = %source.crate_name, path = %best_path, score, "Best match found");
+            let doc = Self::document_text(source, best_path)?;
+            matched.extend(doc.map(|doc| (*source, doc)));
+        }
 
-        for doc in &docs {
-            // Get embedding from cache or generate new one
-            match self
-                .embedding_cache_service
-                .get_embedding(&doc.content)
-                .await
-            {
-                Ok(embedding) => {
-                    array_embeddings.push((doc.path.clone(), embedding));
+        let focused = self
+            .fit_context(matched, question_embedding, options, context_tokens)
+            .await;
+        let sent_tokens = focused.iter().map(|(_, _, tokens)| tokens).sum();
+        let matched_docs: Vec<(&CrateIndex, &Document)> = focused
+            .iter()
+            .map(|(source, doc, _)| (*source, doc))
+            .collect();
+        let search = searching.elapsed();
+
+        let generating = Instant::now();
+        let mut partial = false;
+        let mut llm_model = None;
+        let response_text = if matched_docs.is_empty() {
+            "Could not find any relevant document context.".to_string()
+        } else if options.extractive.unwrap_or(self.config.llm.extractive) {
+            Self::format_excerpts(question, index, &matched_docs)
+        } else if self.config.offline {
+            Self::format_raw_context("Offline mode", index, &matched_docs)
+        } else if self.config.llm.provider != MOCK_PROVIDER
+            && self.embedding_cache_service.budget().is_exhausted()
+        {
+            Self::format_raw_context("Provider budget exhausted", index, &matched_docs)
+        } else {
+            let generation = self.generate_llm_response(&matched_docs, question, options, index);
+            let answer = match timing.deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline.into(), generation)
+                    .await
+                    .ok(),
+                None => Some(generation.await),
+            };
+            match answer.transpose()? {
+                None => {
+                    partial = true;
+                    Self::format_raw_context("Latency budget exceeded", index, &matched_docs)
                 }
-                Err(e) => {
-                    return Err(McpError::internal_error(
-                        format!("Failed to get embedding for document: {}", e),
-                        None,
-                    ));
+                Some(answer) => {
+                    llm_model = Some(self.config.llm_model(&index.crate_name).to_string());
+                    match confidence::split_assessment(&answer)
+                        .filter(|_| self.config.llm.self_assess)
+                    {
+                        Some((body, assessed)) => {
+                            confidence = confidence.with_assessment(assessed);
+                            body.to_string()
+                        }
+                        None => answer,
+                    }
                 }
             }
+        };
+        let generate = generating.elapsed();
+        self.record_query(QueryRecord {
+            crate_name: index.crate_name.clone(),
+            load: timing.load,
+            embed: timing.embed,
+            search,
+            generate,
+            total: timing.started.elapsed(),
+            question_cached: timing.question_cached,
+            top_score: scores.first().copied(),
+        });
+        if self.audit_log.is_some() {
+            let matches = best_matches
+                .iter()
+                .map(|(source, path, score)| AuditMatch {
+                    crate_name: source.crate_name.clone(),
+                    path: path.to_string(),
+                    score: *score,
+                })
+                .collect();
+            self.audit(AuditEvent::Query(QueryEvent {
+                crate_name: index.crate_name.clone(),
+                question: question.to_string(),
+                retrieval: "semantic".to_string(),
+                matches,
+                embedding_model: self.config.embedding.model.clone(),
+                llm_model,
+                context_tokens: sent_tokens,
+                answer_tokens: chunk_context::count_tokens(&response_text),
+                latency_ms: audit_log::millis(timing.started.elapsed()),
+                partial,
+                answer: response_text.clone(),
+            }));
         }
+        let links = if self.config.llm.docs_links && !self.config.offline {
+            docs_links::item_links(
+                &response_text,
+                &index.crate_name,
+                index.version.as_deref(),
+                index.keywords.paths(),
+            )
+        } else {
+            Vec::new()
+        };
 
-        Ok((crate_name.to_string(), docs, array_embeddings))
+        // Format and return the result, noting any documents left out of the search
+        let searched = match companions {
+            [] => index.crate_name.clone(),
+            _ => format!(
+                "{} (with {})",
+                index.crate_name,
+                companions
+                    .iter()
+                    .map(|companion| companion.crate_name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut warnings = Self::status_warnings(&matched_docs);
+        if !index.skipped.is_empty() {
+            warnings.push(Self::skipped_warning(&index.skipped));
+        }
+        if !index.pending.is_empty() {
+            warnings.push(Self::pending_warning(&index.pending));
+        }
+        if partial {
+            warnings.push(Self::partial_warning(timing.started.elapsed()));
+        }
+        let answer = Answer {
+            searched,
+            body: response_text,
+            links,
+            confidence,
+            features: index.features.clone(),
+            stale_warning: self.stale_docs_warning(index),
+            warnings,
+            partial,
+        };
+        Ok(options.format.formatter().format(&answer))
     }
 
-    /// Find the best matching document for a given question embedding
-    fn find_best_match<'a>(
+    /// The `count` best matches for the question among the documents of
+    /// `index` and its companion crates, best first
+    fn search_with_companions<'i>(
         &self,
+        index: &'i CrateIndex,
+        companions: &'i [Arc<CrateIndex>],
+        question: &str,
         question_embedding: &Embedding,
-        embeddings: &'a [(String, Embedding)],
-    ) -> Option<(&'a str, f32)> {
-        let question_vector = question_embedding.to_array();
+        count: usize,
+    ) -> Vec<(&'i CrateIndex, &'i str, f32)> {
+        let mut best_matches: Vec<(&CrateIndex, &str, f32)> = std::iter::once(index)
+            .chain(companions.iter().map(Arc::as_ref))
+            .flat_map(|source| {
+                self.find_best_matches(question, question_embedding, source, count)
+                    .into_iter()
+                    .map(move |(path, score)| (source, path, score))
+            })
+            .collect();
+        best_matches.sort_by(|a, b| b.2.total_cmp(&a.2));
+        best_matches.truncate(count);
+        best_matches
+    }
 
-        let mut best_match: Option<(&str, f32)> = None;
-        for (path, doc_embedding) in embeddings {
-            let doc_vector = doc_embedding.to_array();
-            let score = cosine_similarity(question_vector.view(), doc_vector.view());
-            if best_match.is_none() || score > best_match.unwrap().1 {
-                best_match = Some((path, score));
+    /// Reads back the text of the document at `path` of `source`; `None` if
+    /// it has none
+    fn document_text(source: &CrateIndex, path: &str) -> Result<Option<Document>, McpError> {
+        source.documents.get(path).map_err(|e| {
+            let error = McpError::internal_error(
+                format!("Failed to read document text of {}: {}", path, e),
+                None,
+            );
+            let error = error_data::with_doc_path(error, path);
+            error_data::with_crate(error, &source.crate_name)
+        })
+    }
+
+    /// Fits the matched documents into the context budget, best first,
+    /// returning those sent with their token counts. The best one is always
+    /// sent, cut down if need be; the rest stop once the budget is used up.
+    async fn fit_context<'i>(
+        &self,
+        matched: Vec<(&'i CrateIndex, Document)>,
+        question_embedding: &Embedding,
+        options: &QueryOptions,
+        context_tokens: Option<usize>,
+    ) -> Vec<(&'i CrateIndex, Document, usize)> {
+        let document_tokens = self.config.retrieval.max_document_tokens;
+        let mut remaining = context_tokens;
+        let mut focused = Vec::with_capacity(matched.len());
+        for (source, mut doc) in matched {
+            if remaining == Some(0) {
+                break;
+            }
+            if !options.include_examples.unwrap_or(true)
+                && let Ok(html) = fs::read_to_string(source.page_file(&doc.path))
+            {
+                doc.content = code_examples::strip_examples(&doc.content, &html);
             }
+            let budget = match (document_tokens, remaining) {
+                (0, remaining) => remaining,
+                (tokens, Some(remaining)) => Some(tokens.min(remaining)),
+                (tokens, None) => Some(tokens),
+            };
+            let (doc, tokens) = match budget {
+                Some(budget) => self.focus_document(doc, question_embedding, budget).await,
+                None => {
+                    let tokens = chunk_context::count_tokens(&doc.content);
+                    (doc, tokens)
+                }
+            };
+            if let Some(remaining) = &mut remaining {
+                *remaining = remaining.saturating_sub(tokens);
+            }
+            focused.push((source, doc, tokens));
         }
-
-        best_match
+        focused
     }
 
-    /// Generate a response using the LLM based on matched document context
-    async fn generate_llm_response(
+    /// Answers from the crate's keyword index when the latency budget ran out
+    /// before the question was embedded: the documents sharing the most rare
+    /// words with the question, without summarization
+    fn answer_from_keywords(
         &self,
-        matched_doc: &Document,
+        index: &CrateIndex,
         question: &str,
-        crate_name: &str,
+        options: &QueryOptions,
+        timing: QueryTiming,
     ) -> Result<String, McpError> {
-        let system_prompt = format!(
-            "You are an expert technical assistant for the Rust crate '{}'. \
-             Answer the user's question based *only* on the provided context. \
-             If the context does not contain the answer, say so. \
-             Do not make up information. Be clear, concise, and comprehensive providing example usage code when possible.",
-            crate_name
-        );
-
-        let user_prompt = format!(
-            "Context:\n---\n{}\n---\n\nQuestion: {}",
-            matched_doc.content, question
-        );
-
-        let llm_model: String =
-            env::var("LLM_MODEL").unwrap_or_else(|_| "gpt-4o-mini-2024-07-18".to_string());
-
-        let chat_request = CreateChatCompletionRequestArgs::default()
-            .model(llm_model)
-            .messages(vec![
-                ChatCompletionRequestSystemMessageArgs::default()
-                    .content(system_prompt)
-                    .build()
-                    .map_err(|e| {
-                        McpError::internal_error(
-                            format!("Failed to build system message: {}", e),
-                            None,
-                        )
-                    })?
-                    .into(),
-                ChatCompletionRequestUserMessageArgs::default()
-                    .content(user_prompt)
-                    .build()
-                    .map_err(|e| {
-                        McpError::internal_error(
-                            format!("Failed to build user message: {}", e),
-                            None,
-                        )
-                    })?
-                    .into(),
-            ])
-            .build()
-            .map_err(|e| {
-                McpError::internal_error(format!("Failed to build chat request: {}", e), None)
-            })?;
-
-        // Get the OpenAI client
-        let client = OPENAI_CLIENT
-            .get()
-            .ok_or_else(|| McpError::internal_error("OpenAI client not initialized", None))?;
-
-        let chat_response =
-            client.chat().create(chat_request).await.map_err(|e| {
-                McpError::internal_error(format!("OpenAI chat API error: {}", e), None)
-            })?;
+        let searching = Instant::now();
+        let (top_k, _) = self.query_limits(&index.crate_name, options)?;
+        let hits = index.keywords.search(question, top_k);
+        let mut matched = Vec::new();
+        for &(path, score) in &hits {
+            debug!(crate_name = %index.crate_name, path, score, "Keyword match found");
+            if let Ok(Some(doc)) = index.documents.get(path) {
+                matched.push(doc);
+            }
+        }
+        let matched_docs: Vec<(&CrateIndex, &Document)> =
+            matched.iter().map(|doc| (index, doc)).collect();
+        let body = if matched_docs.is_empty() {
+            "Could not find any relevant document context.".to_string()
+        } else {
+            Self::format_raw_context("Latency budget exceeded", index, &matched_docs)
+        };
+        self.record_query(QueryRecord {
+            crate_name: index.crate_name.clone(),
+            load: timing.load,
+            embed: timing.embed,
+            search: searching.elapsed(),
+            generate: Duration::ZERO,
+            total: timing.started.elapsed(),
+            question_cached: timing.question_cached,
+            top_score: None,
+        });
+        if self.audit_log.is_some() {
+            let matches = hits
+                .iter()
+                .map(|(path, score)| AuditMatch {
+                    crate_name: index.crate_name.clone(),
+                    path: path.to_string(),
+                    score: *score,
+                })
+                .collect();
+            self.audit(AuditEvent::Query(QueryEvent {
+                crate_name: index.crate_name.clone(),
+                question: question.to_string(),
+                retrieval: "keywords".to_string(),
+                matches,
+                embedding_model: self.config.embedding.model.clone(),
+                llm_model: None,
+                context_tokens: Self::context_tokens(&matched_docs),
+                answer_tokens: chunk_context::count_tokens(&body),
+                latency_ms: audit_log::millis(timing.started.elapsed()),
+                partial: true,
+                answer: body.clone(),
+            }));
+        }
 
-        Ok(chat_response
-            .choices
-            .first()
-            .and_then(|choice| choice.message.content.clone())
-            .unwrap_or_else(|| "Error: No response from LLM.".to_string()))
+        let mut warnings = Self::status_warnings(&matched_docs);
+        warnings.push(Self::partial_warning(timing.started.elapsed()));
+        let answer = Answer {
+            searched: index.crate_name.clone(),
+            body,
+            links: Vec::new(),
+            // Keyword scores say nothing about semantic similarity
+            confidence: Confidence::from_scores(&[]),
+            features: index.features.clone(),
+            stale_warning: self.stale_docs_warning(index),
+            warnings,
+            partial: true,
+        };
+        Ok(options.format.formatter().format(&answer))
     }
 
-    #[tool(
-        description = "Query documentation for a specific Rust crate using semantic search and LLM summarization."
-    )]
-    async fn query_rust_docs(
+    /// Partial answer for a query whose crate was still loading, e.g. being
+    /// indexed, when the latency budget ran out after `elapsed`
+    fn still_loading_answer(
         &self,
-        #[tool(aggr)] // Aggregate arguments into the struct
-        args: QueryRustDocsArgs,
-    ) -> Result<CallToolResult, McpError> {
-        // Send startup message if not already sent
-        self.try_send_startup_message().await;
-
-        let question = &args.question;
-        let crate_name = &args.crate_name;
-
-        // Load documentation and embeddings for the specified crate
-        let (crate_name, documents, embeddings) = self.load_custom_crate_docs(crate_name).await?;
+        crate_name: &str,
+        options: &QueryOptions,
+        elapsed: Duration,
+    ) -> String {
+        let answer = Answer {
+            searched: crate_name.to_string(),
+            body: format!(
+                "The documentation of '{}' is still being indexed; ask again shortly.",
+                crate_name
+            ),
+            links: Vec::new(),
+            confidence: Confidence::from_scores(&[]),
+            features: None,
+            stale_warning: None,
+            warnings: vec![Self::partial_warning(elapsed)],
+            partial: true,
+        };
+        options.format.formatter().format(&answer)
+    }
 
-        // Log received query via MCP
-        self.send_log(
-            LoggingLevel::Info,
-            format!("Received query for crate '{}': {}", crate_name, question),
-        );
+    /// Warning for answers cut short by the latency budget after `elapsed`
+    fn partial_warning(elapsed: Duration) -> String {
+        format!(
+            "the latency budget ran out after {} ms; this is the best result available in \
+             time, not a complete answer",
+            elapsed.as_millis()
+        )
+    }
 
-        // Generate embedding for the question
-        let question_embedding = self
-            .embedding_cache_service
-            .get_embedding(question)
-            .await
-            .map_err(|e| {
-                McpError::internal_error(
-                    format!("Failed to get embedding for question: {}", e),
-                    None,
-                )
-            })?;
+    /// Stores a query's statistics if telemetry is enabled
+    fn record_query(&self, record: QueryRecord) {
+        if let Some(telemetry) = &self.telemetry
+            && let Err(e) = telemetry.record_query(&record)
+        {
+            warn!("Failed to record query telemetry: {}", e);
+        }
+    }
 
-        // Find the best matching document
-        let response_text = match self.find_best_match(&question_embedding, &embeddings) {
-            Some((best_path, score)) => {
-                eprintln!("Best match found: {} (score: {})", best_path, score);
+    /// Tokens of the documents sent as a query's context
+    fn context_tokens(matched_docs: &[(&CrateIndex, &Document)]) -> usize {
+        matched_docs
+            .iter()
+            .map(|(_, doc)| chunk_context::count_tokens(&doc.content))
+            .sum()
+    }
 
-                if let Some(doc) = documents.iter().find(|doc| doc.path == best_path) {
-                    self.generate_llm_response(doc, question, &crate_name)
-                        .await?
-                } else {
-                    "Error: Could not find content for best matching document.".to_string()
-                }
-            }
-            None => "Could not find any relevant document context.".to_string(),
-        };
+    /// Appends `event` to the audit log if it is enabled
+    fn audit(&self, event: AuditEvent) {
+        if let Some(audit_log) = &self.audit_log
+            && let Err(e) = audit_log.record(event)
+        {
+            warn!("Failed to write audit log: {}", e);
+        }
+    }
 
-        // Format and return the result
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "From {} docs: {}",
-            crate_name, response_text
-        ))]))
+    /// Warning for answers from docs generated for another version of the
+    /// crate than the project's `Cargo.lock` resolves. Docs of an explicitly
+    /// requested `name@version` are never stale.
+    fn stale_docs_warning(&self, index: &CrateIndex) -> Option<String> {
+        let documented = index.version.as_deref()?;
+        let pinned = self
+            .versioned_docs
+            .read()
+            .is_ok_and(|versioned| versioned.contains_key(&index.crate_name));
+        if pinned {
+            return None;
+        }
+        let locked = crate_discovery::locked_versions(&self.docs().project_dir, &index.crate_name);
+        if locked.is_empty() || locked.iter().any(|version| version == documented) {
+            return None;
+        }
+        Some(format!(
+            "these docs were generated for {} {}, but Cargo.lock resolves {}. \
+             They may be stale; regenerate them with `cargo doc --package {}`.",
+            index.crate_name,
+            documented,
+            locked.join(", "),
+            index.crate_name
+        ))
     }
 }
 
 // --- ServerHandler Implementation ---
 
-#[tool(tool_box)] // Use imported tool macro directly
 impl ServerHandler for RustDocsServer {
     fn get_info(&self) -> ServerInfo {
         // Define capabilities using the builder
         let capabilities = ServerCapabilities::builder()
-            .enable_tools() // Enable tools capability
             .enable_logging() // Enable logging capability
+            .enable_resources() // Crates are exposed as crate://<name> resources
+            .enable_resources_list_changed() // Sent when documented crates change
+            .enable_resources_subscribe() // Updates of crate:// resources
+            .enable_tools() // Enable tools capability
             // Add other capabilities like resources, prompts if needed later
             .build();
 
+        // Provide instructions for using the server
+        let mut instructions = match &self.crate_scope {
+            Some(spec) => format!(
+                "This server provides tools to query the documentation of the Rust crate '{}'. \
+                 Use the 'query_rust_docs' tool with a specific question and crate_name '{}' to get information \
+                 about the crate's API, usage, and examples, derived from its official documentation.",
+                spec.name, spec.name
+            ),
+            None => "This server provides tools to query Rust crate documentation. \
+             Use the 'query_rust_docs' tool with a specific question and crate name to get information \
+             about the crate's API, usage, and examples, derived from its official documentation. \
+             The crate documentation must be locally generated first using 'cargo doc --package <crate_name>'.".to_string(),
+        };
+        instructions.push_str(&self.projects_instructions());
+        instructions.push_str(&self.mode_instructions());
+
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05, // Use latest known version
             capabilities,
@@ -399,14 +4250,28 @@ impl ServerHandler for RustDocsServer {
                 name: "rust-docs-mcp-server".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
             },
-            // Provide instructions for using the server
-            instructions: Some(
-                "This server provides tools to query Rust crate documentation. \
-                 Use the 'query_rust_docs' tool with a specific question and crate name to get information \
-                 about the crate's API, usage, and examples, derived from its official documentation. \
-                 The crate documentation must be locally generated first using 'cargo doc --package <crate_name>'.".to_string()
-            ),
+            instructions: Some(instructions),
+        }
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleServer>> {
+        self.peer.try_lock().ok().and_then(|peer| peer.clone())
+    }
+
+    fn set_peer(&mut self, peer: Peer<RoleServer>) {
+        // Called once while the service starts, before any task can hold the lock
+        if let Ok(mut guard) = self.peer.try_lock() {
+            *guard = Some(peer);
         }
+
+        // The handshake is complete by now (rmcp consumes the `initialized`
+        // notification itself), so the client can be asked for its roots
+        let server = self.clone();
+        tokio::spawn(async move { server.sync_project_root().await });
+    }
+
+    async fn on_roots_list_changed(&self) {
+        self.sync_project_root().await;
     }
 
     // --- Placeholder Implementations for other ServerHandler methods ---
@@ -417,14 +4282,16 @@ impl ServerHandler for RustDocsServer {
         _request: PaginatedRequestParam,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListResourcesResult, McpError> {
-        // Get all available crates from the cargo doc directory
-        let available_crates = self.get_available_crates();
+        let crates = self.discover_crates_with_versions().await;
 
-        // Create resources for each available crate
-        let resources = available_crates
+        // Only the project's own docs go stale when its lockfile changes
+        let docs = self.docs();
+        let lock_modified = crate_discovery::lockfile_modified(&docs.project_dir);
+        let resources = crates
             .iter()
-            .map(|crate_name| {
-                self._create_resource_text(&format!("crate://{}", crate_name), crate_name)
+            .map(|discovered| {
+                let stale = discovered.doc_dir == docs.dir && discovered.is_stale(lock_modified);
+                Self::crate_resource(discovered, stale)
             })
             .collect();
 
@@ -439,20 +4306,61 @@ impl ServerHandler for RustDocsServer {
         request: ReadResourceRequestParam,
         _context: RequestContext<RoleServer>,
     ) -> Result<ReadResourceResult, McpError> {
+        // crate://<name>/llms.txt is the crate's docs as a single Markdown file
+        if let Some(crate_name) = request
+            .uri
+            .strip_prefix("crate://")
+            .and_then(|rest| rest.strip_suffix(&format!("/{}", LLMS_TXT_NAME)))
+        {
+            let Some(crate_name) = self.resource_crate(crate_name) else {
+                return Err(McpError::resource_not_found(
+                    format!("Crate documentation not found: {}", crate_name),
+                    Some(json!({ "uri": request.uri })),
+                ));
+            };
+            let crate_name = crate_name.as_str();
+            let documents = self.load_crate_documents(crate_name).map_err(|e| {
+                McpError::internal_error(format!("Failed to load documentation: {}", e), None)
+            })?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: request.uri.clone(),
+                    mime_type: Some("text/markdown".to_string()),
+                    text: markdown_export::render_llms_txt(crate_name, &documents),
+                }],
+            });
+        }
+
         // Check if the URI matches our crate URI format
-        if let Some(crate_name) = request.uri.strip_prefix("crate://") {
+        if let Some(requested) = request.uri.strip_prefix("crate://") {
             // Check if this crate's documentation exists
-            let available_crates = self.get_available_crates();
-
-            if available_crates.contains(&crate_name.to_string()) {
+            if let Some(crate_name) = self.resource_crate(requested) {
+                let crate_name = crate_name.as_str();
+                let documents = self.load_crate_documents(crate_name).map_err(|e| {
+                    McpError::internal_error(format!("Failed to load documentation: {}", e), None)
+                })?;
+                let version = self
+                    .discover_crates_with_versions()
+                    .await
+                    .into_iter()
+                    .find(|discovered| discovered.name == crate_name)
+                    .and_then(|discovered| discovered.version);
                 Ok(ReadResourceResult {
-                    contents: vec![ResourceContents::text(crate_name, &request.uri)],
+                    contents: vec![ResourceContents::TextResourceContents {
+                        uri: request.uri.clone(),
+                        mime_type: Some("text/markdown".to_string()),
+                        text: markdown_export::render_crate_overview(
+                            crate_name,
+                            version.as_deref(),
+                            &documents,
+                        ),
+                    }],
                 })
             } else {
                 Err(McpError::resource_not_found(
                     format!(
                         "Crate documentation not found: {}. Run 'cargo doc --package {}' first.",
-                        crate_name, crate_name
+                        requested, requested
                     ),
                     Some(json!({ "uri": request.uri })),
                 ))
@@ -465,6 +4373,77 @@ impl ServerHandler for RustDocsServer {
         }
     }
 
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        // Crates may be subscribed to before their docs exist, to hear when
+        // they are generated
+        if Self::resource_crate_name(&request.uri).is_none() {
+            return Err(McpError::resource_not_found(
+                format!("Invalid resource URI format: {}", request.uri),
+                Some(json!({ "uri": request.uri })),
+            ));
+        }
+        debug!("Client subscribed to {}", request.uri);
+        self.subscriptions.lock().await.insert(request.uri);
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.subscriptions.lock().await.remove(&request.uri);
+        Ok(())
+    }
+
+    // Written out instead of derived with `#[tool(tool_box)]` to leave out
+    // tools that aren't enabled
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: self.tools(),
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let started = Instant::now();
+        let audited = self
+            .audit_log
+            .is_some()
+            .then(|| (request.name.to_string(), request.arguments.clone()));
+        let result = self.dispatch_tool(request, context).await;
+        if let Some((tool, arguments)) = audited {
+            self.audit(AuditEvent::ToolCall(ToolCallEvent {
+                tool,
+                arguments: arguments.map_or(Value::Null, Value::Object),
+                latency_ms: audit_log::millis(started.elapsed()),
+                result: result.as_ref().ok().map(|result| {
+                    result
+                        .content
+                        .iter()
+                        .filter_map(|content| content.raw.as_text())
+                        .map(|text| text.text.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }),
+                error: result.as_ref().err().map(|e| e.message.to_string()),
+            }));
+        }
+        result
+    }
+
     async fn list_prompts(
         &self,
         _request: PaginatedRequestParam,
@@ -493,9 +4472,151 @@ impl ServerHandler for RustDocsServer {
         _request: PaginatedRequestParam,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListResourceTemplatesResult, McpError> {
+        let llms_txt = RawResourceTemplate {
+            uri_template: format!("crate://{{crate_name}}/{}", LLMS_TXT_NAME),
+            name: "Crate documentation as Markdown".to_string(),
+            description: Some(
+                "All extracted documentation of a crate in one llms.txt-style Markdown file"
+                    .to_string(),
+            ),
+            mime_type: Some("text/markdown".to_string()),
+        };
         Ok(ListResourceTemplatesResult {
             next_cursor: None,
-            resource_templates: Vec::new(), // No templates defined yet
+            resource_templates: vec![ResourceTemplate {
+                raw: llms_txt,
+                annotations: None,
+            }],
         })
     }
 }
+
+// --- Stdio Entry Point ---
+
+/// Starts the MCP server over stdio and runs it until the client disconnects
+///
+/// Relative doc paths resolve against `project_dir` until the client reports
+/// its workspace roots, or against the current directory without one.
+pub async fn serve_stdio(
+    mut config: Config,
+    project_dir: Option<PathBuf>,
+    crate_scope: Option<CrateSpec>,
+    watch: bool,
+) -> Result<(), ServerError> {
+    let target_doc_path = match &project_dir {
+        Some(dir) => config.docs.rebased(dir).dir,
+        None => config.docs.dir.clone(),
+    };
+    let poll_interval = Duration::from_secs(config.docs.poll_interval_secs);
+
+    // Check the doc directory, caches and provider before serving anything
+    let checks = doctor::startup_checks(&config, &target_doc_path).await;
+    for check in checks
+        .iter()
+        .filter(|check| check.status != CheckStatus::Ok)
+    {
+        warn!("Startup check: {}", check);
+    }
+    let mode = doctor::startup_mode(&checks, config.strict)?;
+    if let StartupMode::RetrievalOnly { reason } = &mode {
+        warn!(
+            "Starting in retrieval-only mode ({}); pass --strict to fail instead.",
+            reason
+        );
+        config.offline = true;
+    } else if config.offline {
+        info!(
+            "Offline mode: only cached embeddings are used and answers contain raw documentation."
+        );
+    }
+
+    // Create a simple startup message
+    let startup_message = "Rust Docs MCP server initialized. Use the query_rust_docs tool to query documentation for any crate that has been generated with 'cargo doc'.".to_string();
+
+    // Create the service instance with simplified constructor
+    let snapshot = config.cache.snapshot;
+    let mut service = RustDocsServer::with_config(startup_message, config)?;
+    if let StartupMode::RetrievalOnly { reason } = &mode {
+        service = service.with_degraded_mode(reason);
+    }
+    if let Some(dir) = project_dir {
+        service = service.with_project_root(dir);
+    }
+    if snapshot {
+        match service.restore_snapshot() {
+            Ok(0) => {}
+            Ok(count) => info!("Restored {} indexed crates from the last run", count),
+            Err(e) => warn!("Ignoring unreadable index snapshot: {}", e),
+        }
+    }
+
+    if let Some(spec) = crate_scope {
+        if spec.version_req.is_some() {
+            // A specific release gets its own docs, independent of the project
+            let doc_root = service
+                .add_versioned_crate(&spec, &CancellationToken::new())
+                .await?;
+            info!(
+                "Using documentation for {} from {}",
+                spec,
+                doc_root.display()
+            );
+        } else if !spec.features.is_empty() {
+            info!(
+                "Documentation is read from {}; make sure it was generated for {}.",
+                target_doc_path.display(),
+                spec
+            );
+        }
+        let crate_name = spec.name.clone();
+        service = service.with_crate_scope(spec);
+        if let Some(count) = service.preload_scoped_crate().await? {
+            info!("Pre-loaded {} documents for crate '{}'", count, crate_name);
+        }
+    }
+
+    service.spawn_preload();
+    #[cfg(unix)]
+    service.spawn_key_reloader()?;
+    if !poll_interval.is_zero() {
+        service.spawn_resource_monitor(poll_interval);
+    }
+    if watch {
+        service.spawn_doc_watcher(&target_doc_path)?;
+        info!(
+            "Watching {} for documentation changes",
+            target_doc_path.display()
+        );
+    }
+
+    // Mirror warnings and errors to the client once it connects
+    service.forward_log_events();
+
+    // Start the server via stdio
+    info!("Rust Docs MCP server starting via stdio...");
+
+    // Serve the server, keeping a handle on its state for the snapshot
+    let running = service.clone();
+    let server_handle = service.serve(stdio()).await.map_err(|e| {
+        error!("Failed to start server: {:?}", e);
+        ServerError::McpRuntime(e.to_string())
+    })?;
+
+    info!("Rust Docs MCP server running...");
+
+    // Wait for the server to complete
+    server_handle.waiting().await.map_err(|e| {
+        error!("Server encountered an error while running: {:?}", e);
+        ServerError::McpRuntime(e.to_string())
+    })?;
+
+    if snapshot {
+        match running.save_snapshot() {
+            Ok(count) => info!("Saved {} indexed crates for the next run", count),
+            Err(e) => warn!("Failed to save the index snapshot: {}", e),
+        }
+    }
+
+    info!("Rust Docs MCP server stopped.");
+    Ok(())
+}
@@ -0,0 +1,376 @@
+//! Builds documents from a crate's Rust sources instead of rustdoc output.
+//!
+//! Each item parsed out of the sources with `syn` becomes a document keyed by
+//! its path, e.g. `my_crate::de::from_str`, holding the item's signature as
+//! written and its doc comments. Unlike scraping HTML this needs no
+//! `cargo doc` run, so it also works for crates that don't build, and it can
+//! include private items. Selected per crate with `backend = "source"` in
+//! `[docs.crates.<name>]`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::{Attribute, ImplItem, Item, TraitItem, Visibility};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::config::DocsSettings;
+use crate::crate_discovery;
+use crate::crate_sources;
+use crate::doc_loader::{DocLoaderError, Document};
+
+/// Loads the documents of `crate_name` from its sources: the configured
+/// `source_dir`, else the registry release matching `version` if given, else
+/// those [`locate_sources`] finds. Private items are included if the crate's
+/// docs would be generated with them. Returns the source directory as well.
+pub async fn load_crate_documents(
+    crate_name: &str,
+    version: Option<&str>,
+    settings: &DocsSettings,
+) -> Result<(PathBuf, Vec<Document>), DocLoaderError> {
+    let configured = settings
+        .for_crate(crate_name)
+        .and_then(|crate_settings| crate_settings.source_dir.clone());
+    let crate_dir = match (configured, version) {
+        (Some(dir), _) => Some(dir),
+        (None, Some(version)) => crate_sources::cargo_home().and_then(|cargo_home| {
+            crate_sources::find_registry_sources(&cargo_home, crate_name, Some(version))
+        }),
+        (None, None) => locate_sources(&settings.project_dir, crate_name).await,
+    }
+    .ok_or_else(|| {
+        DocLoaderError::DocNotFound(format!(
+            "No sources of {} in the project or cargo's registry; set source_dir in [docs.crates.{}]",
+            crate_name, crate_name
+        ))
+    })?;
+    let private_items = settings.rustdoc_options(crate_name).document_private_items;
+    let documents = load_documents(&crate_dir, crate_name, private_items)?;
+    Ok((crate_dir, documents))
+}
+
+/// Finds the sources of `crate_name`: the package of that name in the
+/// project's dependency graph, which includes its own workspace members,
+/// else the newest release unpacked in cargo's registry
+pub async fn locate_sources(project_dir: &Path, crate_name: &str) -> Option<PathBuf> {
+    let manifest = crate_discovery::package_manifest(project_dir, crate_name)
+        .await
+        .map_err(|e| debug!("No cargo metadata for {}: {}", crate_name, e))
+        .ok()
+        .flatten();
+    match manifest.as_deref().and_then(Path::parent) {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => {
+            crate_sources::find_registry_sources(&crate_sources::cargo_home()?, crate_name, None)
+        }
+    }
+}
+
+/// Parses the sources under `crate_dir/src` into one document per item,
+/// each keyed by the item's path. Only public items are included unless
+/// `private_items` is set. Files that don't parse are skipped.
+pub fn load_documents(
+    crate_dir: &Path,
+    crate_name: &str,
+    private_items: bool,
+) -> Result<Vec<Document>, DocLoaderError> {
+    let src_dir = crate_dir.join("src");
+    if !src_dir.is_dir() {
+        return Err(DocLoaderError::DocNotFound(format!(
+            "No sources of {} in {}",
+            crate_name,
+            crate_dir.display()
+        )));
+    }
+
+    let mut collector = ItemCollector {
+        private_items,
+        documents: Vec::new(),
+        by_path: HashMap::new(),
+    };
+    for entry in WalkDir::new(&src_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let relative = path.strip_prefix(&src_dir).unwrap_or(path);
+        let Some(module) = crate_sources::module_path(crate_name, relative) else {
+            continue;
+        };
+        let source = fs::read_to_string(path)?;
+        let file = match syn::parse_file(&source) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Skipping {}, it doesn't parse: {}", path.display(), e);
+                continue;
+            }
+        };
+        collector.add("Module", &module, String::new(), &file.attrs);
+        collector.collect(&source, &file.items, &module);
+    }
+
+    if collector.documents.is_empty() {
+        return Err(DocLoaderError::DocNotFound(format!(
+            "No items in the sources of {} in {}",
+            crate_name,
+            crate_dir.display()
+        )));
+    }
+    Ok(collector.documents)
+}
+
+/// Gathers the documents of the items of a crate's source files
+struct ItemCollector {
+    private_items: bool,
+    documents: Vec<Document>,
+    /// Index of the document of each item path in `documents`
+    by_path: HashMap<String, usize>,
+}
+
+impl ItemCollector {
+    fn visible(&self, vis: &Visibility) -> bool {
+        self.private_items || matches!(vis, Visibility::Public(_))
+    }
+
+    /// Adds the documents of the visible `items` of `module`, whose text is
+    /// `source`, including the members of traits and impls and the items of
+    /// inline modules
+    fn collect(&mut self, source: &str, items: &[Item], module: &str) {
+        for item in items {
+            let path = |name: &dyn std::fmt::Display| format!("{}::{}", module, name);
+            let whole =
+                |attrs: &[Attribute]| signature(source, attrs, item.span(), end_of(item.span()));
+            match item {
+                Item::Fn(item) if self.visible(&item.vis) => {
+                    let sig = signature(source, &item.attrs, item.span(), end_of(item.sig.span()));
+                    self.add("Function", &path(&item.sig.ident), sig, &item.attrs);
+                }
+                Item::Struct(item) if self.visible(&item.vis) => self.add(
+                    "Struct",
+                    &path(&item.ident),
+                    whole(&item.attrs),
+                    &item.attrs,
+                ),
+                Item::Enum(item) if self.visible(&item.vis) => {
+                    self.add("Enum", &path(&item.ident), whole(&item.attrs), &item.attrs)
+                }
+                Item::Union(item) if self.visible(&item.vis) => {
+                    self.add("Union", &path(&item.ident), whole(&item.attrs), &item.attrs)
+                }
+                Item::Type(item) if self.visible(&item.vis) => {
+                    self.add("Type", &path(&item.ident), whole(&item.attrs), &item.attrs)
+                }
+                Item::Const(item) if self.visible(&item.vis) => self.add(
+                    "Constant",
+                    &path(&item.ident),
+                    whole(&item.attrs),
+                    &item.attrs,
+                ),
+                Item::Static(item) if self.visible(&item.vis) => self.add(
+                    "Static",
+                    &path(&item.ident),
+                    whole(&item.attrs),
+                    &item.attrs,
+                ),
+                Item::Macro(item) if item.mac.path.is_ident("macro_rules") => {
+                    let Some(ident) = &item.ident else {
+                        continue;
+                    };
+                    // Exported macros live at the crate root
+                    let exported = item
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path().is_ident("macro_export"));
+                    let macro_path = if exported {
+                        let root = module.split("::").next().unwrap_or(module);
+                        format!("{}::{}", root, ident)
+                    } else if self.private_items {
+                        path(ident)
+                    } else {
+                        continue;
+                    };
+                    self.add("Macro", &macro_path, whole(&item.attrs), &item.attrs);
+                }
+                Item::Trait(item) if self.visible(&item.vis) => {
+                    let sig = signature(
+                        source,
+                        &item.attrs,
+                        item.span(),
+                        item.brace_token.span.open().byte_range().start,
+                    );
+                    let trait_path = path(&item.ident);
+                    self.add("Trait", &trait_path, sig, &item.attrs);
+                    for member in &item.items {
+                        let (kind, name, end, attrs) = match member {
+                            TraitItem::Fn(member) => (
+                                "Method",
+                                &member.sig.ident,
+                                member.sig.span(),
+                                &member.attrs,
+                            ),
+                            TraitItem::Type(member) => (
+                                "Associated type",
+                                &member.ident,
+                                member.span(),
+                                &member.attrs,
+                            ),
+                            TraitItem::Const(member) => (
+                                "Associated constant",
+                                &member.ident,
+                                member.span(),
+                                &member.attrs,
+                            ),
+                            _ => continue,
+                        };
+                        let sig = signature(source, attrs, member.span(), end_of(end));
+                        self.add(kind, &format!("{}::{}", trait_path, name), sig, attrs);
+                    }
+                }
+                Item::Impl(item) => {
+                    let syn::Type::Path(self_ty) = item.self_ty.as_ref() else {
+                        continue;
+                    };
+                    let Some(type_name) = self_ty.path.segments.last().map(|s| &s.ident) else {
+                        continue;
+                    };
+                    // Members of trait impls are documented on the trait, so
+                    // only those with docs of their own are worth a document
+                    let in_trait = item.trait_.is_some();
+                    for member in &item.items {
+                        let (kind, name, vis, end, attrs) = match member {
+                            ImplItem::Fn(member) => (
+                                "Method",
+                                &member.sig.ident,
+                                &member.vis,
+                                member.sig.span(),
+                                &member.attrs,
+                            ),
+                            ImplItem::Type(member) => (
+                                "Associated type",
+                                &member.ident,
+                                &member.vis,
+                                member.span(),
+                                &member.attrs,
+                            ),
+                            ImplItem::Const(member) => (
+                                "Associated constant",
+                                &member.ident,
+                                &member.vis,
+                                member.span(),
+                                &member.attrs,
+                            ),
+                            _ => continue,
+                        };
+                        let wanted = if in_trait {
+                            !crate_sources::doc_text(attrs).is_empty()
+                        } else {
+                            self.visible(vis)
+                        };
+                        if wanted {
+                            let sig = signature(source, attrs, member.span(), end_of(end));
+                            let member_path = path(&format!("{}::{}", type_name, name));
+                            self.add(kind, &member_path, sig, attrs);
+                        }
+                    }
+                }
+                Item::Mod(item) if self.visible(&item.vis) && !is_test_only(&item.attrs) => {
+                    let mod_path = path(&item.ident);
+                    let end = match &item.content {
+                        Some((brace, _)) => brace.span.open().byte_range().start,
+                        None => end_of(item.span()),
+                    };
+                    let sig = signature(source, &item.attrs, item.span(), end);
+                    self.add("Module", &mod_path, sig, &item.attrs);
+                    if let Some((_, items)) = &item.content {
+                        self.collect(source, items, &mod_path);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds the document of the item at `path`, titled by its kind, with its
+    /// signature and docs. Items sharing a path, like cfg-gated variants of
+    /// a function, share a document.
+    fn add(&mut self, kind: &str, path: &str, signature: String, attrs: &[Attribute]) {
+        let docs = crate_sources::doc_text(attrs);
+        if signature.is_empty() && docs.is_empty() {
+            return;
+        }
+        let mut section = format!("{} `{}`", kind, path);
+        if !signature.is_empty() {
+            section.push_str(&format!("\n\n```rust\n{}\n```", signature));
+        }
+        if !docs.is_empty() {
+            section.push_str(&format!("\n\n{}", docs));
+        }
+        match self.by_path.get(path) {
+            Some(&i) => {
+                let content = &mut self.documents[i].content;
+                content.push_str("\n\n");
+                content.push_str(&section);
+            }
+            None => {
+                self.by_path.insert(path.to_string(), self.documents.len());
+                self.documents.push(Document {
+                    path: path.to_string(),
+                    content: section,
+                });
+            }
+        }
+    }
+}
+
+/// The source text of an item from after its doc comments (keeping other
+/// attributes such as derives) up to the byte offset `end`, e.g. the end of
+/// a function's signature, leaving out its body. Continuation lines lose the
+/// indentation of the item's first line.
+fn signature(source: &str, attrs: &[Attribute], item: Span, end: usize) -> String {
+    let start = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .map(|attr| attr.span().byte_range().end)
+        .max()
+        .unwrap_or(item.byte_range().start);
+    let Some(text) = source.get(start..end.max(start)) else {
+        return String::new();
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let offset = text.as_ptr() as usize - source.as_ptr() as usize;
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let indent = source[line_start..offset].len();
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                return line;
+            }
+            let whitespace = line.len() - line.trim_start().len();
+            &line[whitespace.min(indent)..]
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn end_of(span: Span) -> usize {
+    span.byte_range().end
+}
+
+/// Returns true for `#[cfg(test)]` items
+fn is_test_only(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|ident| ident == "test")
+    })
+}
@@ -0,0 +1,492 @@
+use bincode::{Decode, Encode};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::MemorySettings;
+use crate::crate_spec::{canonical_crate_name, normalize_crate_name};
+use crate::doc_freshness::{DocFingerprint, UnchangedPages};
+use crate::doc_generator;
+use crate::doc_loader::{self, Document};
+use crate::document_store::DocumentStore;
+use crate::embeddings::Embedding;
+use crate::error::Result;
+use crate::index_manifest::IndexManifest;
+use crate::keyword_index::KeywordIndex;
+use crate::platform_paths;
+use crate::utils::with_context;
+use crate::vector_store::VectorStore;
+
+/// A crate indexed in memory: its documents and their embeddings
+#[derive(Debug)]
+pub struct CrateIndex {
+    /// Name of the crate's directory in the doc output
+    pub crate_name: String,
+    /// Text of the crate's documents
+    pub documents: DocumentStore,
+    /// Embedding of every document, keyed by document path
+    pub embeddings: VectorStore,
+    /// Inverted index of the documents' words, to boost documents
+    /// mentioning a question's identifiers
+    pub keywords: KeywordIndex,
+    /// Documents left out of the index because they couldn't be embedded
+    pub skipped: Vec<SkippedDocument>,
+    /// Paths of large documents still being embedded in the background;
+    /// searches don't cover them until the crate is indexed again
+    pub pending: Vec<String>,
+    /// Crate version the docs were generated from, if rustdoc recorded it
+    pub version: Option<String>,
+    /// Cargo features the docs were generated with, if this server generated
+    /// them; empty for the default features
+    pub features: Option<Vec<String>>,
+    /// The inputs the index was built from
+    pub manifest: Option<IndexManifest>,
+    /// HNSW graph of the embeddings, for crates large enough to have one
+    pub ann_file: Option<PathBuf>,
+    /// The crate's `index.html`, used to notice regenerated docs
+    index_file: PathBuf,
+    /// Modification time of `index_file` when the crate was indexed
+    generated_at: Option<SystemTime>,
+    /// The crate's pages when they were read, compared with the doc
+    /// directory at most once per `freshness_interval` to notice edits
+    fingerprint: Option<DocFingerprint>,
+    freshness_interval: Duration,
+    /// When the pages were last compared, and whether they were unchanged
+    freshness: Mutex<Option<(Instant, bool)>>,
+    /// Documents whose embeddings turned out unusable, e.g. of the wrong
+    /// dimensionality; they are left out of searches and snapshots
+    quarantined: Mutex<HashSet<String>>,
+}
+
+impl CrateIndex {
+    /// Creates the index of a crate documented in `doc_dir`
+    pub fn new(
+        crate_name: &str,
+        doc_dir: &Path,
+        documents: DocumentStore,
+        embeddings: VectorStore,
+    ) -> Self {
+        let crate_name = canonical_crate_name(crate_name);
+        let index_file = doc_dir.join(&crate_name).join("index.html");
+        let generated_at = modified(&index_file);
+        let version = doc_loader::documented_version(&index_file);
+        let features = doc_generator::recorded_features(&index_file);
+        Self {
+            crate_name,
+            documents,
+            embeddings,
+            keywords: KeywordIndex::default(),
+            skipped: Vec::new(),
+            pending: Vec::new(),
+            version,
+            features,
+            manifest: None,
+            ann_file: None,
+            index_file,
+            generated_at,
+            fingerprint: None,
+            freshness_interval: Duration::ZERO,
+            freshness: Mutex::default(),
+            quarantined: Mutex::default(),
+        }
+    }
+
+    /// Returns the HTML file of the document at `path`
+    pub fn page_file(&self, path: &str) -> PathBuf {
+        self.index_file.with_file_name("").join(path)
+    }
+
+    /// Records documents that were left out of the index
+    pub fn with_skipped(mut self, skipped: Vec<SkippedDocument>) -> Self {
+        self.skipped = skipped;
+        self
+    }
+
+    /// Records documents left out until they are embedded in the background
+    pub fn with_pending(mut self, pending: Vec<String>) -> Self {
+        self.pending = pending;
+        self
+    }
+
+    /// Records the inverted index of the documents' words
+    pub fn with_keywords(mut self, keywords: KeywordIndex) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Records the pages the index was read from, so edits to any of them,
+    /// checked for at most once per `interval`, make the index stale.
+    /// Without a fingerprint only `index.html` is checked.
+    pub fn with_fingerprint(mut self, fingerprint: DocFingerprint, interval: Duration) -> Self {
+        self.fingerprint = Some(fingerprint);
+        self.freshness_interval = interval;
+        self
+    }
+
+    /// Records where the HNSW graph of the embeddings is stored
+    pub fn with_ann_file(mut self, ann_file: PathBuf) -> Self {
+        self.ann_file = Some(ann_file);
+        self
+    }
+
+    /// Records the inputs the index was built from
+    pub fn with_manifest(mut self, manifest: IndexManifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Leaves the documents at `paths` out of future searches and snapshots.
+    /// Returns the ones that weren't quarantined already.
+    pub fn quarantine<'a>(&self, paths: &[&'a str]) -> Vec<&'a str> {
+        let mut quarantined = self.quarantined.lock().unwrap_or_else(|e| e.into_inner());
+        paths
+            .iter()
+            .copied()
+            .filter(|path| quarantined.insert(path.to_string()))
+            .collect()
+    }
+
+    /// Returns true if the document at `path` was quarantined
+    pub fn is_quarantined(&self, path: &str) -> bool {
+        self.quarantined
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(path)
+    }
+
+    /// Returns true unless the crate's docs were regenerated, edited or
+    /// removed since indexing
+    pub fn is_current(&self) -> bool {
+        match (self.generated_at, modified(&self.index_file)) {
+            (Some(indexed), Some(current)) => indexed == current && self.pages_unchanged(),
+            _ => false,
+        }
+    }
+
+    /// Returns false if a page was edited, added or removed since indexing.
+    /// Only file metadata is read, and a result is reused for
+    /// `freshness_interval`; once changed, the index stays stale.
+    fn pages_unchanged(&self) -> bool {
+        let Some(fingerprint) = &self.fingerprint else {
+            return true;
+        };
+        let mut freshness = self.freshness.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((checked, unchanged)) = *freshness
+            && (!unchanged || checked.elapsed() < self.freshness_interval)
+        {
+            return unchanged;
+        }
+        let unchanged = DocFingerprint::scan(&self.crate_dir()) == *fingerprint;
+        *freshness = Some((Instant::now(), unchanged));
+        unchanged
+    }
+
+    /// Pages whose files are unchanged since the index was built, so
+    /// indexing the crate again reads only the others; none without a
+    /// fingerprint
+    pub fn unchanged_pages(&self) -> UnchangedPages {
+        let Some(fingerprint) = &self.fingerprint else {
+            return UnchangedPages::default();
+        };
+        let Ok(documents) = self.documents.documents() else {
+            return UnchangedPages::default();
+        };
+        UnchangedPages::new(&self.crate_dir(), fingerprint, documents).excluding(
+            self.skipped
+                .iter()
+                .map(|doc| doc.path.as_str())
+                .chain(self.pending.iter().map(String::as_str)),
+        )
+    }
+
+    /// The crate's directory in the doc output
+    fn crate_dir(&self) -> PathBuf {
+        self.index_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+}
+
+/// Everything needed to rebuild a [`CrateIndex`] without re-reading its docs
+#[derive(Encode, Decode)]
+struct CrateSnapshot {
+    crate_name: String,
+    index_file: String,
+    /// Modification time of `index_file` as seconds and nanoseconds since the epoch
+    generated_at: Option<(u64, u32)>,
+    fingerprint: Option<DocFingerprint>,
+    freshness_interval_ms: u64,
+    version: Option<String>,
+    features: Option<Vec<String>>,
+    /// The manifest as JSON
+    manifest: Option<String>,
+    ann_file: Option<String>,
+    /// `(path, text)` of every document
+    documents: Vec<(String, String)>,
+    embeddings: Vec<(String, Embedding)>,
+    keywords: KeywordIndex,
+    /// `(path, reason)` of every skipped document
+    skipped: Vec<(String, String)>,
+}
+
+/// The crates a server had indexed, written when it stops
+#[derive(Encode, Decode)]
+struct Snapshot {
+    /// Version of the server that wrote the snapshot; others ignore it
+    server_version: String,
+    crates: Vec<CrateSnapshot>,
+}
+
+impl CrateSnapshot {
+    fn new(index: &CrateIndex) -> Result<Self> {
+        Ok(Self {
+            crate_name: index.crate_name.clone(),
+            index_file: index.index_file.to_string_lossy().into_owned(),
+            generated_at: index
+                .generated_at
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| (since.as_secs(), since.subsec_nanos())),
+            fingerprint: index.fingerprint.clone(),
+            freshness_interval_ms: index.freshness_interval.as_millis() as u64,
+            version: index.version.clone(),
+            features: index.features.clone(),
+            manifest: index
+                .manifest
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            ann_file: index
+                .ann_file
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            documents: index
+                .documents
+                .documents()?
+                .into_iter()
+                .map(|doc| (doc.path, doc.content))
+                .collect(),
+            embeddings: index
+                .embeddings
+                .embeddings()
+                .into_iter()
+                .filter(|(path, _)| !index.is_quarantined(path))
+                .collect(),
+            keywords: index.keywords.clone(),
+            skipped: index
+                .skipped
+                .iter()
+                .map(|doc| (doc.path.clone(), doc.reason.clone()))
+                .collect(),
+        })
+    }
+
+    /// Rebuilds the index, or returns `None` if its docs changed since the
+    /// snapshot was taken
+    fn restore(self, memory: &MemorySettings) -> Result<Option<CrateIndex>> {
+        let index_file = PathBuf::from(self.index_file);
+        let generated_at = self
+            .generated_at
+            .map(|(secs, nanos)| UNIX_EPOCH + Duration::new(secs, nanos));
+        if generated_at.is_none() || generated_at != modified(&index_file) {
+            return Ok(None);
+        }
+        let documents = self
+            .documents
+            .into_iter()
+            .map(|(path, content)| Document { path, content })
+            .collect();
+        let spill_dir = memory.spill_dir();
+        Ok(Some(CrateIndex {
+            crate_name: self.crate_name,
+            documents: DocumentStore::new(documents, memory.max_document_bytes(), &spill_dir)?,
+            embeddings: VectorStore::new(self.embeddings, memory.max_vector_bytes(), &spill_dir)?,
+            keywords: self.keywords,
+            skipped: self
+                .skipped
+                .into_iter()
+                .map(|(path, reason)| SkippedDocument { path, reason })
+                .collect(),
+            pending: Vec::new(),
+            version: self.version,
+            features: self.features,
+            manifest: self
+                .manifest
+                .map(|json| serde_json::from_str(&json))
+                .transpose()?,
+            ann_file: self.ann_file.map(PathBuf::from),
+            index_file,
+            generated_at,
+            fingerprint: self.fingerprint,
+            freshness_interval: Duration::from_millis(self.freshness_interval_ms),
+            freshness: Mutex::default(),
+            quarantined: Mutex::default(),
+        }))
+    }
+}
+
+/// A document that couldn't be embedded, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedDocument {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Crates indexed by the server, shared by the tool handlers so each crate
+/// is loaded and embedded once rather than on every query
+#[derive(Debug, Default)]
+pub struct ServerState {
+    crates: RwLock<HashMap<String, Arc<CrateIndex>>>,
+    /// Name of the index each spelling of a crate name refers to, keyed by
+    /// the normalized spelling, so `serde-json`, `serde_json` and
+    /// `Serde_JSON` all find the same index
+    aliases: RwLock<HashMap<String, String>>,
+}
+
+impl ServerState {
+    /// Creates an empty state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of `crate_name` if it is loaded and still current
+    pub fn get(&self, crate_name: &str) -> Option<Arc<CrateIndex>> {
+        let key = self.index_name(crate_name);
+        let crates = self.crates.read().ok()?;
+        crates.get(&key).filter(|index| index.is_current()).cloned()
+    }
+
+    /// Returns the index of `crate_name` if it is loaded, even if its docs
+    /// changed since
+    pub fn loaded(&self, crate_name: &str) -> Option<Arc<CrateIndex>> {
+        let key = self.index_name(crate_name);
+        let crates = self.crates.read().ok()?;
+        crates.get(&key).cloned()
+    }
+
+    /// Stores `index`, replacing any earlier index of the same crate
+    pub fn insert(&self, index: CrateIndex) -> Arc<CrateIndex> {
+        let index = Arc::new(index);
+        self.add_alias(&index.crate_name, &index.crate_name);
+        if let Ok(mut crates) = self.crates.write() {
+            crates.insert(index.crate_name.clone(), Arc::clone(&index));
+        }
+        index
+    }
+
+    /// Records that `alias` names the crate indexed as `crate_name`, e.g. a
+    /// name that was resolved to a differently spelled crate
+    pub fn add_alias(&self, alias: &str, crate_name: &str) {
+        if let Ok(mut aliases) = self.aliases.write() {
+            aliases.insert(
+                normalize_crate_name(alias),
+                canonical_crate_name(crate_name),
+            );
+        }
+    }
+
+    /// Name of the index `crate_name` refers to: the crate it is an alias
+    /// of, else its canonical spelling
+    pub fn index_name(&self, crate_name: &str) -> String {
+        self.aliases
+            .read()
+            .ok()
+            .and_then(|aliases| aliases.get(&normalize_crate_name(crate_name)).cloned())
+            .unwrap_or_else(|| canonical_crate_name(crate_name))
+    }
+
+    /// Drops the index of `crate_name` so the next query reloads it
+    pub fn remove(&self, crate_name: &str) -> Option<Arc<CrateIndex>> {
+        let key = self.index_name(crate_name);
+        self.crates.write().ok()?.remove(&key)
+    }
+
+    /// Drops every index, e.g. after the doc directory moved
+    pub fn clear(&self) {
+        if let Ok(mut crates) = self.crates.write() {
+            crates.clear();
+        }
+    }
+
+    /// Writes every loaded crate to `path` so a later server can restore them
+    /// with [`ServerState::load_snapshot`]. Crates still embedding documents
+    /// in the background are left out. Returns the number of crates written.
+    pub fn save_snapshot(&self, path: &Path) -> Result<usize> {
+        let indexes: Vec<Arc<CrateIndex>> = self
+            .crates
+            .read()
+            .map(|crates| {
+                crates
+                    .values()
+                    .filter(|index| index.pending.is_empty())
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let snapshot = Snapshot {
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            crates: indexes
+                .iter()
+                .map(|index| CrateSnapshot::new(index))
+                .collect::<Result<_>>()?,
+        };
+        let bytes = bincode::encode_to_vec(&snapshot, bincode::config::standard())?;
+        with_context(fs::write(platform_paths::long_path(path), bytes), || {
+            format!("Failed to write snapshot {}", path.display())
+        })?;
+        Ok(snapshot.crates.len())
+    }
+
+    /// Restores the crates written by [`ServerState::save_snapshot`] whose
+    /// docs are unchanged since and which `accept` approves, e.g. because
+    /// they were embedded with the current model. Returns the number of
+    /// crates restored; a missing snapshot, or one written by another server
+    /// version, restores none.
+    pub fn load_snapshot(
+        &self,
+        path: &Path,
+        memory: &MemorySettings,
+        accept: impl Fn(&CrateIndex) -> bool,
+    ) -> Result<usize> {
+        let path = platform_paths::long_path(path);
+        if !path.is_file() {
+            return Ok(0);
+        }
+        let context = || format!("Failed to read snapshot {}", path.display());
+        let bytes = with_context(fs::read(&path), context)?;
+        let (snapshot, _): (Snapshot, _) = with_context(
+            bincode::decode_from_slice(&bytes, bincode::config::standard()),
+            context,
+        )?;
+        if snapshot.server_version != env!("CARGO_PKG_VERSION") {
+            return Ok(0);
+        }
+        let mut restored = 0;
+        for crate_snapshot in snapshot.crates {
+            if let Some(index) = crate_snapshot.restore(memory)?
+                && accept(&index)
+            {
+                self.insert(index);
+                restored += 1;
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Names of the crates currently held in memory, sorted
+    pub fn crate_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .crates
+            .read()
+            .map(|crates| crates.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
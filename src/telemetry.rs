@@ -0,0 +1,384 @@
+//! Opt-in statistics about how the server performs, kept on this machine.
+//!
+//! With `telemetry.enabled`, every query records where its time went
+//! (loading the crate's index, embedding the question, searching, generating
+//! the answer), whether the question embedding came from memory and how well
+//! the best document scored; every index build records how many documents
+//! and chunks were reused from the caches. Records go to a local SQLite
+//! database and nothing is sent anywhere. `stats report` summarizes them per
+//! crate, e.g. to tune `retrieval.min_score` or the cache sizes.
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::Result;
+use crate::platform_paths::{self, ProjectDirs};
+use crate::utils::ensure_dir_exists;
+
+/// File name of the database in the XDG data directory
+const TELEMETRY_FILE_NAME: &str = "telemetry.sqlite3";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS queries (
+        recorded_at INTEGER NOT NULL,
+        crate_name TEXT NOT NULL,
+        load_ms REAL NOT NULL,
+        embed_ms REAL NOT NULL,
+        search_ms REAL NOT NULL,
+        generate_ms REAL NOT NULL,
+        total_ms REAL NOT NULL,
+        question_cached INTEGER NOT NULL,
+        top_score REAL
+    );
+    CREATE TABLE IF NOT EXISTS index_builds (
+        recorded_at INTEGER NOT NULL,
+        crate_name TEXT NOT NULL,
+        documents INTEGER NOT NULL,
+        reused INTEGER NOT NULL,
+        chunk_hits INTEGER NOT NULL,
+        chunk_misses INTEGER NOT NULL,
+        duration_ms REAL NOT NULL
+    );
+";
+
+/// One answered query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryRecord {
+    pub crate_name: String,
+    /// Loading (or indexing) the crate
+    pub load: Duration,
+    /// Embedding the question
+    pub embed: Duration,
+    /// Ranking the documents and reading back the best ones
+    pub search: Duration,
+    /// Waiting for the LLM's answer
+    pub generate: Duration,
+    pub total: Duration,
+    /// Whether the question's embedding was already in memory
+    pub question_cached: bool,
+    /// Score of the best document, if any passed `retrieval.min_score`
+    pub top_score: Option<f32>,
+}
+
+/// One build of a crate's index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexRecord {
+    pub crate_name: String,
+    pub documents: usize,
+    /// Documents whose embeddings were reused from the global crate cache
+    pub reused: usize,
+    /// Chunk embeddings read from the chunk cache
+    pub chunk_hits: u64,
+    /// Chunk embeddings that had to be generated
+    pub chunk_misses: u64,
+    pub duration: Duration,
+}
+
+/// Local telemetry database
+#[derive(Debug)]
+pub struct Telemetry {
+    connection: Mutex<Connection>,
+}
+
+impl Telemetry {
+    /// Opens the database at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        let path = platform_paths::long_path(path);
+        if let Some(parent) = path.parent() {
+            ensure_dir_exists(parent)?;
+        }
+        let connection = Connection::open(&path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Records an answered query
+    pub fn record_query(&self, record: &QueryRecord) -> Result<()> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        connection.execute(
+            "INSERT INTO queries (recorded_at, crate_name, load_ms, embed_ms, search_ms, \
+             generate_ms, total_ms, question_cached, top_score) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                unix_secs(SystemTime::now()),
+                record.crate_name,
+                millis(record.load),
+                millis(record.embed),
+                millis(record.search),
+                millis(record.generate),
+                millis(record.total),
+                record.question_cached,
+                record.top_score.map(f64::from),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records a build of a crate's index
+    pub fn record_index(&self, record: &IndexRecord) -> Result<()> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        connection.execute(
+            "INSERT INTO index_builds (recorded_at, crate_name, documents, reused, \
+             chunk_hits, chunk_misses, duration_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                unix_secs(SystemTime::now()),
+                record.crate_name,
+                record.documents as i64,
+                record.reused as i64,
+                record.chunk_hits as i64,
+                record.chunk_misses as i64,
+                millis(record.duration),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Rebuilds the database file, defragmenting it and dropping free pages
+    pub fn vacuum(&self) -> Result<()> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        connection.execute_batch("VACUUM")?;
+        Ok(())
+    }
+
+    /// Summarizes what was recorded since `since` (everything if `None`),
+    /// per crate
+    pub fn report(&self, since: Option<SystemTime>) -> Result<TelemetryReport> {
+        let cutoff = since.map_or(0, unix_secs);
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let mut crates: Vec<CrateReport> = Vec::new();
+
+        let mut statement = connection.prepare(
+            "SELECT crate_name, load_ms, embed_ms, search_ms, generate_ms, total_ms, \
+             question_cached, top_score FROM queries WHERE recorded_at >= ?1 \
+             ORDER BY crate_name",
+        )?;
+        let rows = statement.query_map(params![cutoff], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                [
+                    row.get::<_, f64>(1)?,
+                    row.get::<_, f64>(2)?,
+                    row.get::<_, f64>(3)?,
+                    row.get::<_, f64>(4)?,
+                ],
+                row.get::<_, f64>(5)?,
+                row.get::<_, bool>(6)?,
+                row.get::<_, Option<f64>>(7)?,
+            ))
+        })?;
+        let mut totals: Vec<Vec<f64>> = Vec::new();
+        let mut scores: Vec<Vec<f64>> = Vec::new();
+        for row in rows {
+            let (crate_name, phases, total, question_cached, top_score) = row?;
+            let i = crate_entry(&mut crates, &crate_name);
+            if totals.len() < crates.len() {
+                totals.resize(crates.len(), Vec::new());
+                scores.resize(crates.len(), Vec::new());
+            }
+            let report = &mut crates[i];
+            report.queries += 1;
+            for (sum, phase) in report.phase_ms.iter_mut().zip(phases) {
+                *sum += phase;
+            }
+            report.question_cache_hits += usize::from(question_cached);
+            match top_score {
+                Some(score) => scores[i].push(score),
+                None => report.unmatched += 1,
+            }
+            totals[i].push(total);
+        }
+        for (i, report) in crates.iter_mut().enumerate() {
+            let queries = report.queries as f64;
+            for phase in &mut report.phase_ms {
+                *phase /= queries;
+            }
+            totals[i].sort_by(f64::total_cmp);
+            report.total_p50_ms = percentile(&totals[i], 0.5);
+            report.total_p95_ms = percentile(&totals[i], 0.95);
+            report.mean_top_score = (!scores[i].is_empty())
+                .then(|| scores[i].iter().sum::<f64>() / scores[i].len() as f64);
+        }
+
+        let mut statement = connection.prepare(
+            "SELECT crate_name, COUNT(*), SUM(documents), SUM(reused), SUM(chunk_hits), \
+             SUM(chunk_misses), AVG(duration_ms) FROM index_builds WHERE recorded_at >= ?1 \
+             GROUP BY crate_name",
+        )?;
+        let rows = statement.query_map(params![cutoff], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, f64>(6)?,
+            ))
+        })?;
+        for row in rows {
+            let (crate_name, builds, documents, reused, hits, misses, mean_ms) = row?;
+            let i = crate_entry(&mut crates, &crate_name);
+            let report = &mut crates[i];
+            report.index_builds = builds as usize;
+            report.documents_indexed = documents as u64;
+            report.documents_reused = reused as u64;
+            report.chunk_hits = hits as u64;
+            report.chunk_misses = misses as u64;
+            report.index_ms = mean_ms;
+        }
+
+        crates.sort_by(|a, b| a.crate_name.cmp(&b.crate_name));
+        Ok(TelemetryReport { since, crates })
+    }
+}
+
+/// Statistics of one crate
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrateReport {
+    pub crate_name: String,
+    pub queries: usize,
+    /// Median whole query, in milliseconds
+    pub total_p50_ms: f64,
+    /// 95th percentile of whole queries, in milliseconds
+    pub total_p95_ms: f64,
+    /// Mean time spent loading, embedding, searching and generating, in
+    /// milliseconds
+    pub phase_ms: [f64; 4],
+    /// Queries whose question embedding was already in memory
+    pub question_cache_hits: usize,
+    /// Mean score of the best document, over queries that matched any
+    pub mean_top_score: Option<f64>,
+    /// Queries no document scored at least `retrieval.min_score` for
+    pub unmatched: usize,
+    pub index_builds: usize,
+    /// Mean index build, in milliseconds
+    pub index_ms: f64,
+    pub documents_indexed: u64,
+    pub documents_reused: u64,
+    pub chunk_hits: u64,
+    pub chunk_misses: u64,
+}
+
+/// Summary printed by `stats report`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TelemetryReport {
+    pub since: Option<SystemTime>,
+    pub crates: Vec<CrateReport>,
+}
+
+impl fmt::Display for TelemetryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.since {
+            Some(since) => writeln!(
+                f,
+                "Telemetry since {}",
+                DateTime::<Utc>::from(since).format("%Y-%m-%d %H:%M UTC")
+            )?,
+            None => writeln!(f, "All recorded telemetry")?,
+        }
+        if self.crates.is_empty() {
+            return writeln!(
+                f,
+                "  Nothing recorded. Set `telemetry.enabled = true` to record queries."
+            );
+        }
+        for report in &self.crates {
+            writeln!(f, "{}", report.crate_name)?;
+            if report.queries > 0 {
+                let [load, embed, search, generate] = report.phase_ms;
+                writeln!(
+                    f,
+                    "  {} queries: p50 {:.0} ms, p95 {:.0} ms (on average load {:.0} ms, \
+                     embed {:.0} ms, search {:.0} ms, answer {:.0} ms)",
+                    report.queries,
+                    report.total_p50_ms,
+                    report.total_p95_ms,
+                    load,
+                    embed,
+                    search,
+                    generate
+                )?;
+                writeln!(
+                    f,
+                    "  Question cache: {}/{} hits ({})",
+                    report.question_cache_hits,
+                    report.queries,
+                    percent(report.question_cache_hits as u64, report.queries as u64)
+                )?;
+                match report.mean_top_score {
+                    Some(score) => writeln!(
+                        f,
+                        "  Retrieval: mean top score {:.2}, {} queries without a match",
+                        score, report.unmatched
+                    )?,
+                    None => writeln!(f, "  Retrieval: no query matched any document")?,
+                }
+            }
+            if report.index_builds > 0 {
+                let chunks = report.chunk_hits + report.chunk_misses;
+                writeln!(
+                    f,
+                    "  Indexing: {} builds, {:.0} ms on average; {}/{} documents reused, \
+                     chunk cache {}/{} hits ({})",
+                    report.index_builds,
+                    report.index_ms,
+                    report.documents_reused,
+                    report.documents_indexed,
+                    report.chunk_hits,
+                    chunks,
+                    percent(report.chunk_hits, chunks)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolves the platform default location of the database
+pub fn default_path() -> Result<PathBuf> {
+    Ok(ProjectDirs::new()?.data_dir().join(TELEMETRY_FILE_NAME))
+}
+
+/// Returns the index of `crate_name`'s report, adding an empty one if needed
+fn crate_entry(crates: &mut Vec<CrateReport>, crate_name: &str) -> usize {
+    match crates.iter().position(|c| c.crate_name == crate_name) {
+        Some(i) => i,
+        None => {
+            crates.push(CrateReport {
+                crate_name: crate_name.to_string(),
+                ..CrateReport::default()
+            });
+            crates.len() - 1
+        }
+    }
+}
+
+/// Value at `share` of the way through `sorted`, or 0 if it is empty
+fn percentile(sorted: &[f64], share: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    sorted[((sorted.len() - 1) as f64 * share).round() as usize]
+}
+
+fn percent(part: u64, whole: u64) -> String {
+    if whole == 0 {
+        return "n/a".to_string();
+    }
+    format!("{:.0}%", part as f64 * 100.0 / whole as f64)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() as i64)
+}
@@ -2,6 +2,7 @@
 mod module_tests {
     use crate::doc_loader::load_documents_from_cargo_doc;
     use crate::embedding_cache_service::EmbeddingCacheService;
+    use crate::platform_paths::ProjectDirs;
     use std::env;
 
     // Helper function to set up test environment
@@ -57,8 +58,8 @@ mod module_tests {
 
         // We're only testing if the service initializes correctly
         // Since cache_dir is private, let's just make sure we can determine the cache path
-        let home_dir = dirs::home_dir().expect("Could not find home directory");
-        let expected_cache_dir = home_dir.join(".rust-doc-embedding-cache");
+        let dirs = ProjectDirs::new().expect("Could not resolve the cache directory");
+        let expected_cache_dir = dirs.embedding_cache_dir();
         assert!(expected_cache_dir.exists(), "Cache directory should exist at {}", expected_cache_dir.display());
     }
 
@@ -2,6 +2,7 @@ use std::fs;
 use std::io::{Error, ErrorKind};
 use std::path::Path;
 
+use crate::doc_loader::DocLoaderError;
 use crate::error::{Result, ServerError};
 
 /// Safely creates a directory and all parent directories if they don't exist
@@ -20,23 +21,58 @@ pub fn ensure_dir_exists(path: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-/// Wraps a Result with additional context in the error message
+/// Wraps a Result with additional context in the error message, typically
+/// naming the file, crate or chunk involved. Errors keep their variant where
+/// it matters to callers (I/O errors their kind, provider errors their
+/// retryability); file format errors become `InvalidData` I/O errors.
 pub fn with_context<T, E: Into<ServerError>, C: FnOnce() -> String>(
     result: std::result::Result<T, E>,
     context: C,
 ) -> Result<T> {
     result.map_err(|e| {
         let err: ServerError = e.into();
+        let context = context();
         match err {
             ServerError::Io(io_err) => ServerError::Io(Error::new(
                 io_err.kind(),
-                format!("{}: {}", context(), io_err),
+                format!("{}: {}", context, io_err),
             )),
+            ServerError::DocLoader(DocLoaderError::Io(io_err)) => {
+                ServerError::DocLoader(DocLoaderError::Io(Error::new(
+                    io_err.kind(),
+                    format!("{}: {}", context, io_err),
+                )))
+            }
             ServerError::DocLoader(doc_err) => ServerError::DocLoader(doc_err),
-            ServerError::Json(json_err) => ServerError::Json(json_err),
-            ServerError::OpenAI(openai_err) => ServerError::OpenAI(openai_err),
+            ServerError::Json(_) | ServerError::Bincode(_) | ServerError::BincodeDecode(_) => {
+                ServerError::Io(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}: {}", context, err),
+                ))
+            }
+            ServerError::EmbeddingCache { path, reason } => ServerError::EmbeddingCache {
+                path,
+                reason: format!("{}: {}", context, reason),
+            },
+            ServerError::Provider {
+                kind,
+                status,
+                message,
+                retry_after,
+            } => ServerError::Provider {
+                kind,
+                status,
+                message: format!("{}: {}", context, message),
+                retry_after,
+            },
+            ServerError::Offline(message) => {
+                ServerError::Offline(format!("{}: {}", context, message))
+            }
+            ServerError::BudgetExceeded(message) => {
+                ServerError::BudgetExceeded(format!("{}: {}", context, message))
+            }
             // Handle other error types
-            _ => ServerError::Config(format!("{}: {:?}", context(), err)),
+            _ => ServerError::Config(format!("{}: {}", context, err)),
         }
     })
 }
@@ -0,0 +1,327 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+use rayon::prelude::*;
+use tempfile::NamedTempFile;
+use tracing::debug;
+
+use crate::embeddings::{Embedding, EmbeddingProvider};
+
+/// Identifies a vector file, followed by its format version
+const MAGIC: &[u8; 8] = b"RDMCPVEC";
+const VERSION: u32 = 1;
+/// Vector data starts at a multiple of this, so vectors start on cache lines
+const ALIGNMENT: usize = 64;
+/// Fewest vectors scored by one thread, so small crates aren't split up
+const SCORES_PER_THREAD: usize = 512;
+
+/// Where the embeddings of a loaded crate's documents live.
+///
+/// Scoring a question reads every vector once. Crates whose vectors exceed
+/// the memory budget are therefore written to a flat file of little-endian
+/// `f32`s that is memory-mapped and scored in place, leaving it to the page
+/// cache which parts stay resident.
+#[derive(Debug, Clone)]
+pub enum VectorStore {
+    /// All vectors are held in memory
+    InMemory(Vec<(String, Embedding)>),
+    /// Vectors are mapped from a temporary file, removed when the last clone
+    /// of the store is dropped
+    Mapped(Arc<VectorFile>),
+}
+
+/// Vectors written to disk and mapped into memory.
+///
+/// The file starts with a header: [`MAGIC`], the format version, the vector
+/// count and, for each vector, its document path, byte offset, dimensions
+/// and norm. The vectors follow, starting at an [`ALIGNMENT`] boundary.
+#[derive(Debug)]
+pub struct VectorFile {
+    _file: NamedTempFile,
+    map: Mmap,
+    provider: EmbeddingProvider,
+    model: String,
+    entries: Vec<MappedVector>,
+}
+
+#[derive(Debug)]
+struct MappedVector {
+    path: String,
+    offset: usize,
+    dimensions: usize,
+    norm: f32,
+}
+
+impl MappedVector {
+    fn len(&self) -> usize {
+        self.dimensions * size_of::<f32>()
+    }
+}
+
+impl VectorStore {
+    /// Keeps `embeddings` in memory unless their vectors take more than
+    /// `max_bytes`, in which case they are written to a file in `spill_dir`
+    /// and mapped
+    pub fn new(
+        embeddings: Vec<(String, Embedding)>,
+        max_bytes: Option<u64>,
+        spill_dir: &Path,
+    ) -> io::Result<Self> {
+        let total: u64 = embeddings
+            .iter()
+            .map(|(_, embedding)| (embedding.values.len() * size_of::<f32>()) as u64)
+            .sum();
+        match max_bytes {
+            Some(max_bytes) if total > max_bytes && !embeddings.is_empty() => {
+                debug!(
+                    "Mapping {} vectors ({} bytes) from disk to stay under {} bytes",
+                    embeddings.len(),
+                    total,
+                    max_bytes
+                );
+                VectorFile::write(&embeddings, spill_dir).map(|file| Self::Mapped(Arc::new(file)))
+            }
+            _ => Ok(Self::InMemory(embeddings)),
+        }
+    }
+
+    /// Number of vectors in the store
+    pub fn len(&self) -> usize {
+        match self {
+            Self::InMemory(embeddings) => embeddings.len(),
+            Self::Mapped(file) => file.entries.len(),
+        }
+    }
+
+    /// Returns true if the store holds no vectors
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the vectors are mapped from disk
+    pub fn is_mapped(&self) -> bool {
+        matches!(self, Self::Mapped(_))
+    }
+
+    /// Bytes of vector values held on the heap
+    pub fn memory_bytes(&self) -> usize {
+        match self {
+            Self::InMemory(embeddings) => embeddings
+                .iter()
+                .map(|(_, embedding)| embedding.values.len() * size_of::<f32>())
+                .sum(),
+            Self::Mapped(_) => 0,
+        }
+    }
+
+    /// Paths of the documents whose vectors don't have `dimensions` values
+    pub fn mismatched(&self, dimensions: usize) -> Vec<&str> {
+        match self {
+            Self::InMemory(embeddings) => embeddings
+                .iter()
+                .filter(|(_, embedding)| embedding.dimensions != dimensions)
+                .map(|(path, _)| path.as_str())
+                .collect(),
+            Self::Mapped(file) => file
+                .entries
+                .iter()
+                .filter(|entry| entry.dimensions != dimensions)
+                .map(|entry| entry.path.as_str())
+                .collect(),
+        }
+    }
+
+    /// Cosine similarity of `question` with every vector of its dimensions,
+    /// keeping documents scoring at least `min_score`, in no particular order
+    pub fn scores(&self, question: &Embedding, min_score: f32) -> Vec<(&str, f32)> {
+        let dimensions = question.dimensions;
+        match self {
+            Self::InMemory(embeddings) => embeddings
+                .par_iter()
+                .with_min_len(SCORES_PER_THREAD)
+                .filter(|(_, embedding)| embedding.dimensions == dimensions)
+                .map(|(path, embedding)| (path.as_str(), question.similarity(embedding)))
+                .filter(|(_, score)| *score >= min_score)
+                .collect(),
+            Self::Mapped(file) => file
+                .entries
+                .par_iter()
+                .with_min_len(SCORES_PER_THREAD)
+                .filter(|entry| entry.dimensions == dimensions)
+                .map(|entry| (entry.path.as_str(), file.similarity(question, entry)))
+                .filter(|(_, score)| *score >= min_score)
+                .collect(),
+        }
+    }
+
+    /// Path of the document whose vector is at `position`
+    pub fn path(&self, position: usize) -> Option<&str> {
+        match self {
+            Self::InMemory(embeddings) => embeddings.get(position).map(|(path, _)| path.as_str()),
+            Self::Mapped(file) => file.entries.get(position).map(|entry| entry.path.as_str()),
+        }
+    }
+
+    /// Returns the embedding of the document at `path`, reading mapped
+    /// values into memory
+    pub fn get(&self, path: &str) -> Option<Embedding> {
+        match self {
+            Self::InMemory(embeddings) => embeddings
+                .iter()
+                .find(|(doc_path, _)| doc_path == path)
+                .map(|(_, embedding)| embedding.clone()),
+            Self::Mapped(file) => file
+                .entries
+                .iter()
+                .find(|entry| entry.path == path)
+                .map(|entry| file.embedding(entry)),
+        }
+    }
+
+    /// Returns every embedding in the store, reading mapped values into memory
+    pub fn embeddings(&self) -> Vec<(String, Embedding)> {
+        match self {
+            Self::InMemory(embeddings) => embeddings.clone(),
+            Self::Mapped(file) => file
+                .entries
+                .iter()
+                .map(|entry| (entry.path.clone(), file.embedding(entry)))
+                .collect(),
+        }
+    }
+}
+
+impl VectorFile {
+    fn write(embeddings: &[(String, Embedding)], spill_dir: &Path) -> io::Result<Self> {
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&(embeddings.len() as u64).to_le_bytes());
+        for (path, _) in embeddings {
+            // Offsets are filled in once the header's length is known
+            header.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            header.extend_from_slice(path.as_bytes());
+            header.extend_from_slice(&[0; 16]);
+        }
+        let data_start = header.len().next_multiple_of(ALIGNMENT);
+
+        let mut position = MAGIC.len() + 4 + 8;
+        let mut offset = data_start;
+        for (path, embedding) in embeddings {
+            position += 4 + path.len();
+            header[position..position + 8].copy_from_slice(&(offset as u64).to_le_bytes());
+            header[position + 8..position + 12]
+                .copy_from_slice(&(embedding.values.len() as u32).to_le_bytes());
+            header[position + 12..position + 16].copy_from_slice(&embedding.norm.to_le_bytes());
+            position += 16;
+            offset += embedding.values.len() * size_of::<f32>();
+        }
+        header.resize(data_start, 0);
+
+        let mut file = tempfile::Builder::new()
+            .prefix("rustdocs-mcp-vectors-")
+            .tempfile_in(spill_dir)?;
+        {
+            let mut writer = io::BufWriter::new(file.as_file_mut());
+            writer.write_all(&header)?;
+            for (_, embedding) in embeddings {
+                for value in &embedding.values {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+            writer.flush()?;
+        }
+
+        let (_, first) = &embeddings[0];
+        Self::open(file, first.provider, first.model.clone())
+    }
+
+    /// Maps `file` and reads the vector table from its header
+    fn open(file: NamedTempFile, provider: EmbeddingProvider, model: String) -> io::Result<Self> {
+        let handle = File::open(file.path())?;
+        // SAFETY: the file is a private temporary file this process created
+        // and never writes to again; it is removed only after the map is
+        // dropped, since both live in this struct
+        let map = unsafe { Mmap::map(&handle)? };
+        let entries = parse_header(&map).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Malformed vector file {}", file.path().display()),
+            )
+        })?;
+        Ok(Self {
+            _file: file,
+            map,
+            provider,
+            model,
+            entries,
+        })
+    }
+
+    /// The mapped values of `entry`
+    fn values(&self, entry: &MappedVector) -> impl Iterator<Item = f32> + '_ {
+        self.map[entry.offset..entry.offset + entry.len()]
+            .chunks_exact(size_of::<f32>())
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn similarity(&self, question: &Embedding, entry: &MappedVector) -> f32 {
+        if question.norm == 0.0 || entry.norm == 0.0 {
+            return 0.0;
+        }
+        let dot_product: f32 = question
+            .values
+            .iter()
+            .zip(self.values(entry))
+            .map(|(a, b)| a * b)
+            .sum();
+        dot_product / (question.norm * entry.norm)
+    }
+
+    fn embedding(&self, entry: &MappedVector) -> Embedding {
+        Embedding::with_norm(
+            self.values(entry).collect(),
+            entry.norm,
+            self.provider,
+            self.model.clone(),
+        )
+    }
+}
+
+/// Reads the vector table of a vector file, or `None` if it is malformed
+fn parse_header(bytes: &[u8]) -> Option<Vec<MappedVector>> {
+    let mut reader = bytes.strip_prefix(MAGIC.as_slice())?;
+    if u32::from_le_bytes(take(&mut reader)?) != VERSION {
+        return None;
+    }
+    let count = u64::from_le_bytes(take(&mut reader)?) as usize;
+    let mut entries = Vec::with_capacity(count.min(bytes.len()));
+    for _ in 0..count {
+        let path_len = u32::from_le_bytes(take(&mut reader)?) as usize;
+        let path = reader.get(..path_len)?;
+        reader = &reader[path_len..];
+        let entry = MappedVector {
+            path: String::from_utf8(path.to_vec()).ok()?,
+            offset: u64::from_le_bytes(take(&mut reader)?) as usize,
+            dimensions: u32::from_le_bytes(take(&mut reader)?) as usize,
+            norm: f32::from_le_bytes(take(&mut reader)?),
+        };
+        if !entry.offset.is_multiple_of(size_of::<f32>())
+            || entry.offset.checked_add(entry.len())? > bytes.len()
+        {
+            return None;
+        }
+        entries.push(entry);
+    }
+    Some(entries)
+}
+
+/// Takes the next `N` bytes from `reader`
+fn take<const N: usize>(reader: &mut &[u8]) -> Option<[u8; N]> {
+    let (bytes, rest) = reader.split_first_chunk::<N>()?;
+    *reader = rest;
+    Some(*bytes)
+}
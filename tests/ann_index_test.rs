@@ -0,0 +1,93 @@
+use rustdocs_mcp_server::ann_index::{AnnIndex, AnnIndexes};
+use rustdocs_mcp_server::embeddings::{Embedding, EmbeddingProvider};
+use tempfile::tempdir;
+
+fn embedding(values: Vec<f32>) -> Embedding {
+    Embedding::new(values, EmbeddingProvider::OpenAI, "test-model".to_string())
+}
+
+fn embeddings() -> Vec<(String, Embedding)> {
+    vec![
+        ("a.html".to_string(), embedding(vec![1.0, 0.0, 0.0])),
+        ("b.html".to_string(), embedding(vec![0.0, 1.0, 0.0])),
+        ("c.html".to_string(), embedding(vec![0.0, 0.0, 1.0])),
+        ("d.html".to_string(), embedding(vec![0.7, 0.7, 0.0])),
+    ]
+}
+
+#[test]
+fn test_built_index_finds_the_most_similar_vectors() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ann.usearch");
+    AnnIndex::build(&embeddings(), "hash", "test-model", &path).unwrap();
+
+    let index = AnnIndex::open(&path).unwrap().unwrap();
+    let hits = index.search(&embedding(vec![0.9, 0.1, 0.0]), 2).unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(index.path(hits[0].0), Some("a.html"));
+    assert_eq!(index.path(hits[1].0), Some("d.html"));
+    assert!(hits[0].1 > hits[1].1);
+    assert!((hits[0].1 - 0.994).abs() < 0.01);
+
+    // Questions of other dimensions match nothing
+    let hits = index.search(&embedding(vec![1.0, 0.0]), 2).unwrap();
+    assert!(hits.is_empty());
+}
+
+#[test]
+fn test_index_is_current_only_for_the_same_inputs() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ann.usearch");
+    assert!(!AnnIndex::is_current(&path, "hash", "test-model", 4));
+    assert!(AnnIndex::open(&path).unwrap().is_none());
+
+    AnnIndex::build(&embeddings(), "hash", "test-model", &path).unwrap();
+    assert!(AnnIndex::is_current(&path, "hash", "test-model", 4));
+    assert!(!AnnIndex::is_current(&path, "other", "test-model", 4));
+    assert!(!AnnIndex::is_current(&path, "hash", "other-model", 4));
+    assert!(!AnnIndex::is_current(&path, "hash", "test-model", 3));
+
+    let index = AnnIndex::open(&path).unwrap().unwrap();
+    assert!(index.matches("hash", "test-model", 4));
+    assert!(!index.matches("hash", "test-model", 5));
+}
+
+#[test]
+fn test_headers_of_other_versions_are_ignored() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ann.usearch");
+    AnnIndex::build(&embeddings(), "hash", "test-model", &path).unwrap();
+
+    let header = dir.path().join("ann.header");
+    let mut bytes = std::fs::read(&header).unwrap();
+    bytes[8] = 99;
+    std::fs::write(&header, bytes).unwrap();
+    assert!(!AnnIndex::is_current(&path, "hash", "test-model", 4));
+    assert!(AnnIndex::open(&path).unwrap().is_none());
+
+    std::fs::write(&header, b"not a header").unwrap();
+    assert!(AnnIndex::open(&path).is_err());
+}
+
+#[test]
+fn test_least_recently_searched_index_is_closed() {
+    let dir = tempdir().unwrap();
+    let paths: Vec<_> = (0..3)
+        .map(|i| dir.path().join(format!("{}.usearch", i)))
+        .collect();
+    for path in &paths {
+        AnnIndex::build(&embeddings(), "hash", "test-model", path).unwrap();
+    }
+
+    let indexes = AnnIndexes::new(2);
+    assert!(indexes.is_empty());
+    assert!(indexes.get(&paths[0]).is_some());
+    assert!(indexes.get(&paths[1]).is_some());
+    assert!(indexes.get(&paths[2]).is_some());
+    assert_eq!(indexes.len(), 2);
+
+    indexes.close(&paths[2]);
+    assert_eq!(indexes.len(), 1);
+    assert!(indexes.get(&dir.path().join("missing.usearch")).is_none());
+    assert_eq!(indexes.len(), 1);
+}
@@ -0,0 +1,131 @@
+use rustdocs_mcp_server::answer_format::{Answer, AnswerFormat, code_blocks};
+use rustdocs_mcp_server::confidence::Confidence;
+use rustdocs_mcp_server::docs_links::ItemLink;
+use serde_json::Value;
+
+fn answer() -> Answer {
+    Answer {
+        searched: "demo".to_string(),
+        body: "Call `Widget::resize`:\n\n```rust\nwidget.resize(2);\n```".to_string(),
+        links: vec![ItemLink {
+            name: "Widget::resize".to_string(),
+            url: "https://docs.rs/demo/latest/demo/struct.Widget.html#method.resize".to_string(),
+        }],
+        confidence: Confidence::from_scores(&[0.8]),
+        features: Some(vec!["rt".to_string()]),
+        stale_warning: Some("these docs were generated for demo 0.1.0".to_string()),
+        warnings: vec!["`demo::Widget` is deprecated: use `Gadget`".to_string()],
+        partial: false,
+    }
+}
+
+#[test]
+fn test_text_format_is_prose_with_confidence_and_warnings() {
+    let answer = answer();
+    let text = AnswerFormat::Text.formatter().format(&answer);
+    assert_eq!(
+        text,
+        format!(
+            "Warning: these docs were generated for demo 0.1.0\n\n\
+             From demo docs: {}\n\nDocs:\n- `Widget::resize`: {}\n\n\
+             Confidence: {}\nDocs generated with the default features plus `rt`\n\n\
+             Warning: `demo::Widget` is deprecated: use `Gadget`",
+            answer.body, answer.links[0].url, answer.confidence
+        )
+    );
+}
+
+#[test]
+fn test_markdown_format_links_items_and_quotes_warnings() {
+    let text = AnswerFormat::Markdown.formatter().format(&answer());
+    assert!(text.starts_with("> **Warning:** these docs were generated"));
+    assert!(text.contains("## From demo docs\n\nCall `Widget::resize`"));
+    assert!(text.contains("```rust\nwidget.resize(2);\n```"));
+    assert!(text.contains(
+        "- [`Widget::resize`](https://docs.rs/demo/latest/demo/struct.Widget.html#method.resize)"
+    ));
+    assert!(text.contains("**Docs generated with:** the default features plus `rt`"));
+    assert!(text.ends_with("> **Warning:** `demo::Widget` is deprecated: use `Gadget`"));
+}
+
+#[test]
+fn test_json_format_has_the_answer_as_fields() {
+    let answer = answer();
+    let text = AnswerFormat::Json.formatter().format(&answer);
+    let value: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(value["crate"], "demo");
+    assert_eq!(value["answer"], answer.body.as_str());
+    assert_eq!(value["links"][0]["name"], "Widget::resize");
+    assert_eq!(
+        value["confidence"]["level"],
+        answer.confidence.level.to_string()
+    );
+    assert!(value["confidence"]["value"].is_f64());
+    assert_eq!(value["features"][0], "rt");
+    // The stale docs warning comes first
+    assert_eq!(value["warnings"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        value["warnings"][0],
+        "these docs were generated for demo 0.1.0"
+    );
+    assert_eq!(value["partial"], false);
+
+    let partial = Answer {
+        partial: true,
+        ..answer
+    };
+    let value: Value =
+        serde_json::from_str(&AnswerFormat::Json.formatter().format(&partial)).unwrap();
+    assert_eq!(value["partial"], true);
+}
+
+#[test]
+fn test_code_format_keeps_only_code_blocks() {
+    let text = AnswerFormat::Code.formatter().format(&answer());
+    assert_eq!(text, "```rust\nwidget.resize(2);\n```");
+
+    let prose = Answer {
+        body: "Widgets can't be resized.".to_string(),
+        ..answer()
+    };
+    assert_eq!(
+        AnswerFormat::Code.formatter().format(&prose),
+        "No code in the answer from demo docs."
+    );
+}
+
+#[test]
+fn test_unknown_features_are_left_out() {
+    let answer = Answer {
+        features: None,
+        ..answer()
+    };
+    let text = AnswerFormat::Text.formatter().format(&answer);
+    assert!(!text.contains("Docs generated with"));
+    let value: Value =
+        serde_json::from_str(&AnswerFormat::Json.formatter().format(&answer)).unwrap();
+    assert!(value["features"].is_null());
+}
+
+#[test]
+fn test_code_blocks() {
+    let text = "Intro\n\n```rust\nlet a = 1;\n```\n\nThen:\n\n  ~~~\n  b();\n  ~~~\n\n````\n```\nnested\n```\n````\n\n```\nunclosed";
+    assert_eq!(
+        code_blocks(text),
+        vec![
+            "```rust\nlet a = 1;\n```",
+            "  ~~~\n  b();\n  ~~~",
+            "````\n```\nnested\n```\n````",
+            "```\nunclosed\n```",
+        ]
+    );
+    assert!(code_blocks("No code here").is_empty());
+}
+
+#[test]
+fn test_answer_format_names() {
+    let format: AnswerFormat = serde_json::from_str("\"markdown\"").unwrap();
+    assert_eq!(format, AnswerFormat::Markdown);
+    assert_eq!(AnswerFormat::default(), AnswerFormat::Text);
+    assert!(serde_json::from_str::<AnswerFormat>("\"html\"").is_err());
+}
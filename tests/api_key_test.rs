@@ -0,0 +1,68 @@
+use rustdocs_mcp_server::api_key::{describe_source, load_api_key};
+use rustdocs_mcp_server::config::{Config, KeychainEntry, OpenAiSettings};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_api_key_is_read_from_the_configured_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("openai-key");
+    fs::write(&path, "sk-from-file\n").unwrap();
+    let settings = OpenAiSettings {
+        api_key_file: Some(path.clone()),
+        ..OpenAiSettings::default()
+    };
+
+    assert_eq!(
+        load_api_key(&settings).unwrap().as_deref(),
+        Some("sk-from-file")
+    );
+    assert_eq!(
+        describe_source(&settings),
+        format!("file {}", path.display())
+    );
+
+    // A rotated key is picked up by the next load
+    fs::write(&path, "sk-rotated").unwrap();
+    assert_eq!(
+        load_api_key(&settings).unwrap().as_deref(),
+        Some("sk-rotated")
+    );
+
+    fs::write(&path, "  \n").unwrap();
+    assert!(load_api_key(&settings).is_err());
+    fs::remove_file(&path).unwrap();
+    assert!(load_api_key(&settings).is_err());
+}
+
+#[test]
+fn test_openai_section_is_parsed_and_validated() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("rustdocs-mcp.toml");
+    fs::write(
+        &path,
+        r#"
+[openai]
+organization = "org-docs"
+project = "proj_search"
+api_key_keychain = { service = "openai", account = "docs-server" }
+"#,
+    )
+    .unwrap();
+    let mut config = Config::load(Some(&path)).unwrap();
+    config.validate().unwrap();
+    assert_eq!(
+        config.openai.api_key_keychain,
+        Some(KeychainEntry {
+            service: "openai".to_string(),
+            account: "docs-server".to_string(),
+        })
+    );
+    let headers = config.openai.headers();
+    assert_eq!(headers["OpenAI-Organization"], "org-docs");
+    assert_eq!(headers["OpenAI-Project"], "proj_search");
+
+    // The key can come from only one place
+    config.openai.api_key_file = Some(dir.path().join("openai-key"));
+    assert!(config.validate().is_err());
+}
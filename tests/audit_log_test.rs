@@ -0,0 +1,89 @@
+use rustdocs_mcp_server::audit_log::{AuditEvent, AuditLog, AuditMatch, QueryEvent, ToolCallEvent};
+use serde_json::{Value, json};
+use std::fs;
+
+fn read_lines(path: &std::path::Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_events_are_appended_as_json_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join("audit.jsonl");
+    let log = AuditLog::open(&path, 0).unwrap();
+    log.record(AuditEvent::ToolCall(ToolCallEvent {
+        tool: "find_item".to_string(),
+        arguments: json!({ "item": "Widget" }),
+        latency_ms: 1.5,
+        result: Some("- `demo::Widget` (struct)".to_string()),
+        error: None,
+    }))
+    .unwrap();
+    log.record(AuditEvent::Query(QueryEvent {
+        crate_name: "demo".to_string(),
+        question: "How do I resize a Widget?".to_string(),
+        retrieval: "semantic".to_string(),
+        matches: vec![AuditMatch {
+            crate_name: "demo".to_string(),
+            path: "struct.Widget.html".to_string(),
+            score: 0.75,
+        }],
+        embedding_model: "text-embedding-3-small".to_string(),
+        llm_model: None,
+        context_tokens: 12,
+        answer_tokens: 3,
+        latency_ms: 20.0,
+        partial: false,
+        answer: "Call Widget::resize.".to_string(),
+    }))
+    .unwrap();
+
+    // Reopening appends rather than truncating
+    let log = AuditLog::open(&path, 0).unwrap();
+    log.record(AuditEvent::ToolCall(ToolCallEvent {
+        tool: "get_source".to_string(),
+        arguments: Value::Null,
+        latency_ms: 0.1,
+        result: None,
+        error: Some("Tool 'get_source' is not enabled".to_string()),
+    }))
+    .unwrap();
+
+    let lines = read_lines(&path);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["event"], "tool_call");
+    assert_eq!(lines[0]["tool"], "find_item");
+    assert_eq!(lines[0]["arguments"]["item"], "Widget");
+    assert!(lines[0]["recorded_at"].as_str().unwrap().ends_with('Z'));
+    assert_eq!(lines[1]["event"], "query");
+    assert_eq!(lines[1]["matches"][0]["score"], 0.75);
+    assert_eq!(lines[1]["llm_model"], Value::Null);
+    assert_eq!(lines[1]["answer"], "Call Widget::resize.");
+    assert_eq!(lines[2]["error"], "Tool 'get_source' is not enabled");
+    assert_eq!(lines[2]["result"], Value::Null);
+}
+
+#[test]
+fn test_long_results_are_cut_down() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    let log = AuditLog::open(&path, 4).unwrap();
+    for result in ["héllo wörld", "abcd"] {
+        log.record(AuditEvent::ToolCall(ToolCallEvent {
+            tool: "find_item".to_string(),
+            arguments: Value::Null,
+            latency_ms: 1.0,
+            result: Some(result.to_string()),
+            error: None,
+        }))
+        .unwrap();
+    }
+
+    let lines = read_lines(&path);
+    assert_eq!(lines[0]["result"], "héll…");
+    assert_eq!(lines[1]["result"], "abcd");
+}
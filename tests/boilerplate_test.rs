@@ -0,0 +1,78 @@
+use rustdocs_mcp_server::boilerplate::is_boilerplate;
+use rustdocs_mcp_server::document_chunker::DocumentChunker;
+
+const TRAIT_IMPLEMENTATIONS: &str = "Trait Implementations
+§
+impl Clone for Widget
+§
+fn clone(&self) -> Widget
+Returns a copy of the value.
+Read more
+§
+impl Debug for Widget
+§
+fn fmt(&self, f: &mut Formatter<'_>) -> Result
+Formats the value using the given formatter.
+Read more
+Blanket Implementations
+§
+impl<T> Any for T
+where
+T: 'static + ?Sized,
+§
+fn type_id(&self) -> TypeId
+Gets the TypeId of self.
+Read more
+§
+impl<T> From<T> for T
+§
+fn from(t: T) -> T
+Returns the argument unchanged.
+";
+
+const PROSE: &str = "A widget draws itself into a frame buffer and can be resized at any time.
+Resizing keeps the contents anchored at the top left corner, so callers that
+want to centre a widget must move it after calling resize_to.
+";
+
+#[test]
+fn test_implementation_lists_are_boilerplate() {
+    assert!(is_boilerplate(TRAIT_IMPLEMENTATIONS));
+    assert!(is_boilerplate("§\nSource\nRead more\n"));
+}
+
+#[test]
+fn test_prose_is_not_boilerplate() {
+    assert!(!is_boilerplate(PROSE));
+    assert!(!is_boilerplate(""));
+    // An impl list with an explanation written for the crate is kept
+    let explained = format!("{}{}{}", TRAIT_IMPLEMENTATIONS, PROSE, PROSE);
+    assert!(!is_boilerplate(&explained));
+}
+
+#[test]
+fn test_embeddable_chunks_leave_out_boilerplate() {
+    let auto_traits = "§\nimpl Send for Widget\n".repeat(200);
+    let document = format!("{}{}", PROSE.repeat(20), auto_traits);
+    let chunker = DocumentChunker::with_params(200, 400, 800);
+
+    let all = chunker.chunk_document(&document);
+    let kept = chunker.embeddable_chunks(&document);
+    assert!(kept.len() < all.len());
+    assert!(kept.iter().all(|chunk| !is_boilerplate(&chunk.content)));
+
+    let unfiltered = chunker.clone().skipping_boilerplate(false);
+    let ids: Vec<String> = unfiltered
+        .embeddable_chunks(&document)
+        .into_iter()
+        .map(|chunk| chunk.id)
+        .collect();
+    let all_ids: Vec<String> = all.into_iter().map(|chunk| chunk.id).collect();
+    assert_eq!(ids, all_ids);
+
+    // A page of nothing but boilerplate still gets an embedding
+    assert_eq!(
+        chunker.embeddable_chunks(&auto_traits).len(),
+        chunker.chunk_document(&auto_traits).len()
+    );
+}
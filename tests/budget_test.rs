@@ -0,0 +1,61 @@
+use rustdocs_mcp_server::budget::{Spend, UsageBudget};
+use rustdocs_mcp_server::config::{BudgetPeriod, BudgetSettings};
+use rustdocs_mcp_server::error::ServerError;
+
+fn session_budget(max_tokens: u64, max_usd: f64) -> UsageBudget {
+    UsageBudget::new(&BudgetSettings {
+        max_tokens,
+        max_usd,
+        period: BudgetPeriod::Session,
+        ..BudgetSettings::default()
+    })
+}
+
+#[test]
+fn test_token_budget_refuses_requests_once_spent() {
+    let budget = session_budget(1000, 0.0);
+    assert!(budget.check().is_ok());
+
+    budget.charge(Spend::Embedding { tokens: 600 });
+    assert!(budget.check().is_ok());
+    budget.charge(Spend::Chat {
+        input_tokens: 300,
+        output_tokens: 100,
+    });
+    assert_eq!(budget.spent().0, 1000);
+    assert!(budget.is_exhausted());
+    match budget.check() {
+        Err(ServerError::BudgetExceeded(message)) => {
+            assert!(message.contains("1000 of 1000 tokens"), "{}", message);
+            assert!(message.contains("server restarts"), "{}", message);
+        }
+        other => panic!("expected BudgetExceeded, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_dollar_budget_uses_configured_prices() {
+    // $0.15 per million input tokens and $0.60 per million output tokens
+    let budget = session_budget(0, 0.75);
+    budget.charge(Spend::Chat {
+        input_tokens: 1_000_000,
+        output_tokens: 500_000,
+    });
+    let (tokens, usd) = budget.spent();
+    assert_eq!(tokens, 1_500_000);
+    assert!((usd - 0.45).abs() < 1e-9);
+    assert!(budget.check().is_ok());
+
+    budget.charge(Spend::Embedding { tokens: 20_000_000 });
+    assert!(budget.is_exhausted());
+}
+
+#[test]
+fn test_unlimited_budget_only_counts() {
+    let budget = UsageBudget::unlimited();
+    budget.charge(Spend::Embedding {
+        tokens: 1_000_000_000,
+    });
+    assert!(budget.check().is_ok());
+    assert_eq!(budget.spent().0, 1_000_000_000);
+}
@@ -0,0 +1,185 @@
+use rustdocs_mcp_server::{
+    bundle,
+    document_chunker::DocumentChunker,
+    embeddings::CachedDocumentEmbedding,
+    global_cache::{CacheKey, CachedCrateIndex, GlobalCache},
+};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_export_import_round_trip() {
+    let chunker = DocumentChunker::new();
+    let source_chunks = tempdir().unwrap();
+    let source_global_dir = tempdir().unwrap();
+    let source_global = GlobalCache::with_root(source_global_dir.path().to_path_buf()).unwrap();
+
+    // One document whose chunk embedding is cached, one whose isn't
+    let key = CacheKey::local("my_crate");
+    let index = CachedCrateIndex {
+        crate_name: "my_crate".to_string(),
+        version: key.version.clone(),
+        features: Vec::new(),
+        model: "test-model".to_string(),
+        documents: ["cached document", "uncached document"]
+            .iter()
+            .map(|content| CachedDocumentEmbedding {
+                path: format!("{}.html", content.replace(' ', "_")),
+                content: content.to_string(),
+                vector: vec![0.6, 0.8],
+            })
+            .collect(),
+    };
+    source_global.store(&key, &index).unwrap();
+    let chunk_id = chunker.generate_chunk_id("cached document");
+    let json = serde_json::json!({
+        "vector": [0.6, 0.8],
+        "document": "cached document",
+        "model": "test-model",
+        "provider": "OpenAI",
+    });
+    fs::write(source_chunks.path().join(&chunk_id), json.to_string()).unwrap();
+
+    let mut bundle_bytes = Vec::new();
+    let exported = bundle::export_crate(
+        &source_global,
+        source_chunks.path(),
+        &chunker,
+        &key,
+        &mut bundle_bytes,
+    )
+    .expect("Export should succeed");
+    assert_eq!(exported.documents, 2);
+    assert_eq!(exported.chunks, 1);
+    assert_eq!(exported.missing_chunks, 1);
+
+    let target_chunks = tempdir().unwrap();
+    let target_global_dir = tempdir().unwrap();
+    let target_global = GlobalCache::with_root(target_global_dir.path().to_path_buf()).unwrap();
+    let imported = bundle::import_bundle(
+        bundle_bytes.as_slice(),
+        &target_global,
+        target_chunks.path(),
+        &chunker,
+    )
+    .expect("Import should succeed");
+
+    assert_eq!(imported.documents, 2);
+    assert_eq!(imported.chunks, 1);
+    assert!(target_chunks.path().join(&chunk_id).is_file());
+    let restored = target_global
+        .load(&key)
+        .unwrap()
+        .expect("Index should exist");
+    assert_eq!(restored.model, "test-model");
+    assert_eq!(restored.documents.len(), 2);
+}
+
+#[test]
+fn test_import_rejects_tampered_chunks_and_missing_header() {
+    let chunker = DocumentChunker::new();
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    let bundle_text = [
+        r#"{"type":"header","format_version":1,"crate_name":"my_crate","version":"local","features":[],"model":"m"}"#,
+        r#"{"type":"chunk","id":"not-the-hash","document_path":"a.html","content":"text","vector":[1.0],"model":"m","provider":"OpenAI"}"#,
+    ]
+    .join("\n");
+    let report = bundle::import_bundle(bundle_text.as_bytes(), &global, chunk_dir.path(), &chunker)
+        .expect("Import should succeed");
+    assert_eq!(report.rejected_chunks, 1);
+    assert_eq!(fs::read_dir(chunk_dir.path()).unwrap().count(), 0);
+
+    let headless = r#"{"type":"document","path":"a.html","content":"text","vector":[1.0]}"#;
+    assert!(
+        bundle::import_bundle(headless.as_bytes(), &global, chunk_dir.path(), &chunker).is_err()
+    );
+}
+
+#[test]
+fn test_parquet_export_writes_one_row_per_cached_chunk() {
+    use arrow_array::{Array, Float32Array, ListArray, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use rustdocs_mcp_server::parquet_export;
+
+    let chunker = DocumentChunker::new();
+    let chunks = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    let key = CacheKey::local("my_crate");
+    let index = CachedCrateIndex {
+        crate_name: "my_crate".to_string(),
+        version: key.version.clone(),
+        features: Vec::new(),
+        model: "test-model".to_string(),
+        documents: ["cached document", "uncached document"]
+            .iter()
+            .map(|content| CachedDocumentEmbedding {
+                path: format!("{}.html", content.replace(' ', "_")),
+                content: content.to_string(),
+                vector: vec![0.6, 0.8],
+            })
+            .collect(),
+    };
+    global.store(&key, &index).unwrap();
+    let chunk_id = chunker.generate_chunk_id("cached document");
+    let json = serde_json::json!({
+        "vector": [0.6, 0.8],
+        "document": "cached document",
+        "model": "test-model",
+        "provider": "OpenAI",
+    });
+    fs::write(chunks.path().join(&chunk_id), json.to_string()).unwrap();
+
+    let output = tempdir().unwrap();
+    let path = output.path().join("my_crate.parquet");
+    let report = parquet_export::export_parquet(
+        &global,
+        chunks.path(),
+        &chunker,
+        &key,
+        fs::File::create(&path).unwrap(),
+    )
+    .expect("Export should succeed");
+    assert_eq!(report.chunks, 1);
+    assert_eq!(report.missing_chunks, 1);
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(fs::File::open(&path).unwrap())
+        .unwrap()
+        .build()
+        .unwrap();
+    let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.schema(), parquet_export::schema());
+    assert_eq!(batch.num_rows(), 1);
+
+    let text = |name: &str| {
+        batch
+            .column_by_name(name)
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap()
+            .value(0)
+            .to_string()
+    };
+    assert_eq!(text("crate"), "my_crate");
+    assert_eq!(text("path"), "cached_document.html");
+    assert_eq!(text("chunk_id"), chunk_id);
+    assert_eq!(text("text"), "cached document");
+    assert_eq!(text("model"), "test-model");
+
+    let vectors = batch
+        .column_by_name("vector")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    let vector = vectors.value(0);
+    let vector = vector.as_any().downcast_ref::<Float32Array>().unwrap();
+    assert_eq!(vector.values().to_vec(), vec![0.6, 0.8]);
+}
@@ -0,0 +1,398 @@
+use rustdocs_mcp_server::{
+    cache_manager,
+    document_chunker::{self, DocumentChunker},
+    embeddings::CachedDocumentEmbedding,
+    global_cache::{CacheKey, CachedCrateIndex, GlobalCache},
+    telemetry::Telemetry,
+};
+use std::{fs, path::Path};
+use tempfile::tempdir;
+
+// Writes a chunk cache entry in the same JSON format the embedding cache service uses
+fn write_chunk(dir: &Path, content: &str, model: &str) -> String {
+    let id = DocumentChunker::new().generate_chunk_id(content);
+    let json = serde_json::json!({
+        "vector": [0.1, 0.2, 0.3],
+        "document": content,
+        "model": model,
+        "provider": "OpenAI",
+    });
+    fs::write(dir.join(&id), json.to_string()).expect("Failed to write chunk entry");
+    id
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.metadata().unwrap().len())
+        .sum()
+}
+
+fn store_index(global: &GlobalCache, crate_name: &str, contents: &[&str]) {
+    let key = CacheKey::local(crate_name);
+    let index = CachedCrateIndex {
+        crate_name: crate_name.to_string(),
+        version: key.version.clone(),
+        features: Vec::new(),
+        model: "test-model".to_string(),
+        documents: contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| CachedDocumentEmbedding {
+                path: format!("doc{}.html", i),
+                content: content.to_string(),
+                vector: vec![0.1, 0.2, 0.3],
+            })
+            .collect(),
+    };
+    global.store(&key, &index).expect("Failed to store index");
+}
+
+#[test]
+fn test_stats_groups_by_model_and_crate() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    write_chunk(chunk_dir.path(), "first chunk", "model-a");
+    write_chunk(chunk_dir.path(), "second chunk", "model-a");
+    write_chunk(chunk_dir.path(), "third chunk", "model-b");
+    store_index(&global, "my_crate", &["first chunk"]);
+
+    let stats = cache_manager::stats(chunk_dir.path(), &global, &DocumentChunker::new());
+
+    assert_eq!(stats.chunk_total.entries, 3);
+    assert_eq!(stats.chunks_by_model["model-a"].entries, 2);
+    assert_eq!(stats.chunks_by_model["model-b"].entries, 1);
+    assert_eq!(stats.global_total.entries, 1);
+    assert_eq!(stats.crates["my_crate@local"].documents, 1);
+}
+
+#[test]
+fn test_prune_removes_unreferenced_chunks() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    let kept = write_chunk(chunk_dir.path(), "referenced content", "model-a");
+    let orphan = write_chunk(chunk_dir.path(), "orphaned content", "model-a");
+    store_index(&global, "my_crate", &["referenced content"]);
+
+    let report = cache_manager::prune(chunk_dir.path(), &global, &DocumentChunker::new()).unwrap();
+
+    assert_eq!(report.removed.entries, 1);
+    assert!(chunk_dir.path().join(kept).exists());
+    assert!(!chunk_dir.path().join(orphan).exists());
+}
+
+#[test]
+fn test_entries_embedded_with_an_input_type_are_kept_and_verified() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    // Named like the embedding cache service names entries sent with an input type
+    let chunker = DocumentChunker::new();
+    let content = "referenced content";
+    let id = chunker.generate_chunk_id(&format!(
+        "search_document\0{}",
+        chunker.generate_chunk_id(content)
+    ));
+    let json = serde_json::json!({
+        "vector": [0.1, 0.2, 0.3],
+        "document": content,
+        "model": "model-a",
+        "provider": "OpenAI",
+        "input_type": "search_document",
+    });
+    fs::write(chunk_dir.path().join(&id), json.to_string()).unwrap();
+    store_index(&global, "my_crate", &[content]);
+
+    let report = cache_manager::verify(chunk_dir.path(), &global, false).unwrap();
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+    let report = cache_manager::prune(chunk_dir.path(), &global, &DocumentChunker::new()).unwrap();
+    assert_eq!(report.removed.entries, 0);
+    assert!(chunk_dir.path().join(id).exists());
+}
+
+#[test]
+fn test_chunks_are_counted_once_per_referencing_crate() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    let shared = write_chunk(chunk_dir.path(), "license blurb", "model-a");
+    let own = write_chunk(chunk_dir.path(), "only in crate a", "model-a");
+    // Recorded under a key the default chunker wouldn't produce
+    let custom = write_chunk(chunk_dir.path(), "chunked differently", "model-a");
+    store_index(&global, "crate_a", &["license blurb", "only in crate a"]);
+    store_index(&global, "crate_b", &["license blurb"]);
+    store_index(&global, "crate_c", &[]);
+    global
+        .store_chunk_refs(&CacheKey::local("crate_c"), std::slice::from_ref(&custom))
+        .unwrap();
+
+    let counts = cache_manager::reference_counts(&global, &DocumentChunker::new()).unwrap();
+    assert_eq!(counts[&shared], 2);
+    assert_eq!(counts[&own], 1);
+    assert_eq!(counts[&custom], 1);
+    assert_eq!(
+        cache_manager::stats(chunk_dir.path(), &global, &DocumentChunker::new()).shared_chunks,
+        1
+    );
+
+    let report = cache_manager::prune(chunk_dir.path(), &global, &DocumentChunker::new()).unwrap();
+    assert_eq!(report.removed.entries, 0);
+    assert_eq!(report.referenced, 3);
+}
+
+#[test]
+fn test_indexes_without_chunk_lists_are_rechunked_with_the_given_chunker() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    // Split into several chunks by small chunker settings, where the default
+    // settings keep the page whole
+    let chunker = DocumentChunker::with_params(16, 64, 128);
+    let page: String = (0..20)
+        .map(|i| format!("Paragraph {i} explains how resize_{i} scales a widget.\n\n"))
+        .collect();
+    let chunks = chunker.chunk_document(&page);
+    assert!(chunks.len() > 1);
+    let ids: Vec<String> = chunks
+        .iter()
+        .map(|chunk| write_chunk(chunk_dir.path(), &chunk.content, "model-a"))
+        .collect();
+    store_index(&global, "my_crate", &[&page]);
+
+    let counts = cache_manager::reference_counts(&global, &chunker).unwrap();
+    assert_eq!(counts.len(), chunks.len());
+    let report = cache_manager::prune(chunk_dir.path(), &global, &chunker).unwrap();
+    assert_eq!(report.removed.entries, 0);
+    for id in ids {
+        assert!(chunk_dir.path().join(id).exists());
+    }
+}
+
+#[test]
+fn test_prune_skips_without_crate_indexes() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+    let id = write_chunk(chunk_dir.path(), "some content", "model-a");
+
+    let report = cache_manager::prune(chunk_dir.path(), &global, &DocumentChunker::new()).unwrap();
+
+    assert!(report.skipped);
+    assert!(chunk_dir.path().join(id).exists());
+}
+
+#[test]
+fn test_enforce_size_limit_evicts_until_under_limit() {
+    let chunk_dir = tempdir().unwrap();
+    write_chunk(chunk_dir.path(), "first content", "model-a");
+    write_chunk(chunk_dir.path(), "second content", "model-a");
+    write_chunk(chunk_dir.path(), "third content", "model-a");
+    let entry_size = fs::read_dir(chunk_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len())
+        .max()
+        .unwrap();
+
+    let evicted = cache_manager::enforce_size_limit(chunk_dir.path(), entry_size).unwrap();
+
+    assert_eq!(evicted.entries, 2);
+    assert_eq!(fs::read_dir(chunk_dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_verify_detects_and_fixes_corruption() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    write_chunk(chunk_dir.path(), "valid content", "model-a");
+    fs::write(chunk_dir.path().join("not-json"), "garbage").unwrap();
+    // Entry whose file name doesn't match its content hash
+    let id = write_chunk(chunk_dir.path(), "moved content", "model-a");
+    fs::rename(chunk_dir.path().join(id), chunk_dir.path().join("deadbeef")).unwrap();
+
+    let report = cache_manager::verify(chunk_dir.path(), &global, false).unwrap();
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.problems.len(), 2);
+    assert_eq!(report.removed, 0);
+
+    let report = cache_manager::verify(chunk_dir.path(), &global, true).unwrap();
+    assert_eq!(report.removed, 2);
+    assert!(!chunk_dir.path().join("not-json").exists());
+}
+
+#[test]
+fn test_migrate_renames_entries_of_the_unversioned_scheme() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+    let chunker = DocumentChunker::new();
+
+    // Named by the bare SHA-256 of their content, as before ids were versioned
+    let legacy = write_chunk(chunk_dir.path(), "legacy content", "model-a");
+    let legacy_name = document_chunker::chunk_id_hash(&legacy).to_string();
+    fs::rename(
+        chunk_dir.path().join(&legacy),
+        chunk_dir.path().join(&legacy_name),
+    )
+    .unwrap();
+    let current = write_chunk(chunk_dir.path(), "current content", "model-a");
+    fs::write(chunk_dir.path().join("not-a-chunk-id"), "garbage").unwrap();
+
+    // Unmigrated entries are still intact
+    let report = cache_manager::verify(chunk_dir.path(), &global, false).unwrap();
+    assert_eq!(report.problems.len(), 1, "{:?}", report.problems);
+
+    let report = cache_manager::migrate(chunk_dir.path(), &chunker).unwrap();
+    assert_eq!(report.migrated.entries, 1);
+    assert_eq!(report.removed.entries, 1);
+    assert!(chunk_dir.path().join(&legacy).exists());
+    assert!(!chunk_dir.path().join(&legacy_name).exists());
+    assert!(chunk_dir.path().join(&current).exists());
+
+    let report = cache_manager::migrate(chunk_dir.path(), &chunker).unwrap();
+    assert_eq!(report.migrated.entries + report.removed.entries, 0);
+    let report = cache_manager::verify(chunk_dir.path(), &global, false).unwrap();
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+}
+
+#[test]
+fn test_clear_removes_everything() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    write_chunk(chunk_dir.path(), "content", "model-a");
+    store_index(&global, "my_crate", &["content"]);
+
+    // The cache root also holds data that isn't cached crate entries
+    let others = [
+        global_dir.path().join("telemetry.sqlite3"),
+        global_dir.path().join("audit.jsonl"),
+        global.snapshot_path(),
+        global.ingested_dir().join("notes").join("index.html"),
+    ];
+    for path in &others {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "kept").unwrap();
+    }
+
+    let report = cache_manager::clear(chunk_dir.path(), &global).unwrap();
+
+    assert_eq!(report.removed.entries, 2);
+    assert!(global.index_files().is_empty());
+    assert!(global.entry_dirs().is_empty());
+    assert!(!global_dir.path().join("my_crate").exists());
+    assert_eq!(fs::read_dir(chunk_dir.path()).unwrap().count(), 0);
+    for path in &others {
+        assert!(path.is_file(), "{} was removed", path.display());
+    }
+}
+
+#[test]
+fn test_global_cache_round_trip_and_key_layout() {
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+    store_index(&global, "round_trip", &["hello"]);
+
+    let loaded = global
+        .load(&CacheKey::local("round_trip"))
+        .unwrap()
+        .expect("Index should be cached");
+    assert_eq!(loaded.documents.len(), 1);
+    assert_eq!(loaded.documents[0].content, "hello");
+    assert!(global.load(&CacheKey::local("missing")).unwrap().is_none());
+
+    // Feature order must not change the cache location
+    let a = CacheKey {
+        crate_name: "c".to_string(),
+        version: "^1.0".to_string(),
+        features: vec!["x".to_string(), "y".to_string()],
+    };
+    let b = CacheKey {
+        features: vec!["y".to_string(), "x".to_string()],
+        ..a.clone()
+    };
+    assert_eq!(global.index_path(&a), global.index_path(&b));
+    assert_eq!(a.sanitized_version(), "_1.0");
+}
+
+#[test]
+fn test_compact_drops_stale_entries_and_reports_space_reclaimed() {
+    let chunk_dir = tempdir().unwrap();
+    let global_dir = tempdir().unwrap();
+    let global = GlobalCache::with_root(global_dir.path().to_path_buf()).unwrap();
+
+    let kept = write_chunk(chunk_dir.path(), "kept content", "model-a");
+    let orphan = write_chunk(chunk_dir.path(), "orphaned content", "model-a");
+    store_index(&global, "kept", &["kept content"]);
+    store_index(&global, "gone", &["orphaned content"]);
+
+    // An entry whose index was deleted, leaving its HNSW graph behind
+    let gone = CacheKey::local("gone");
+    fs::remove_file(global.index_path(&gone)).unwrap();
+    fs::write(global.ann_index_path(&gone), [0; 32]).unwrap();
+
+    // Generated docs are kept whether or not the crate is indexed
+    let generated = CacheKey {
+        version: "1.0.0".to_string(),
+        ..CacheKey::local("generated")
+    };
+    let generated_page = global.docs_dir(&generated).join("generated/index.html");
+    fs::create_dir_all(generated_page.parent().unwrap()).unwrap();
+    fs::write(&generated_page, "<html>").unwrap();
+
+    // A telemetry database in the cache root, as by default
+    let telemetry = global_dir.path().join("telemetry.sqlite3");
+    Telemetry::open(&telemetry).unwrap();
+
+    // An index file with unused trailing bytes and an interrupted write
+    let index_path = global.index_path(&CacheKey::local("kept"));
+    let mut bytes = fs::read(&index_path).unwrap();
+    let length = bytes.len();
+    bytes.extend_from_slice(&[0; 64]);
+    fs::write(&index_path, bytes).unwrap();
+    fs::write(index_path.with_file_name("ann.tmp"), [0; 32]).unwrap();
+
+    // Ingested pages aren't cache entries
+    let ingested = global.ingested_dir().join("notes").join("a");
+    fs::create_dir_all(&ingested).unwrap();
+
+    let size_before = dir_size(chunk_dir.path()) + dir_size(global_dir.path());
+    let report = cache_manager::compact(
+        chunk_dir.path(),
+        &global,
+        &DocumentChunker::new(),
+        Some(&telemetry),
+    )
+    .unwrap();
+
+    assert_eq!(report.stale.entries, 2);
+    assert_eq!(report.rewritten, 1);
+    assert_eq!(report.pruned.removed.entries, 1);
+    assert!(report.vacuumed);
+    assert_eq!(report.size_before, size_before);
+    assert!(report.reclaimed() > 0);
+    assert_eq!(fs::read(&index_path).unwrap().len(), length);
+    assert!(!global_dir.path().join("gone").exists());
+    assert!(generated_page.is_file());
+    assert!(ingested.is_dir());
+    assert!(chunk_dir.path().join(kept).exists());
+    assert!(!chunk_dir.path().join(orphan).exists());
+    assert!(
+        global
+            .load(&CacheKey::local("kept"))
+            .unwrap()
+            .is_some_and(|index| index.documents.len() == 1)
+    );
+    assert!(report.to_string().contains("Reclaimed"));
+}
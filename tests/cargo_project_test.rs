@@ -0,0 +1,99 @@
+use rustdocs_mcp_server::{
+    cargo_project::{CargoProject, find_manifest_dir, path_from_file_uri, project_from_roots},
+    config::Config,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+#[test]
+fn test_find_manifest_dir_walks_up_from_subdirectories() {
+    let root = tempdir().unwrap();
+    let nested = root.path().join("src").join("bin");
+    fs::create_dir_all(&nested).unwrap();
+    assert_eq!(find_manifest_dir(&nested), None);
+
+    fs::write(
+        root.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\n",
+    )
+    .unwrap();
+    assert_eq!(find_manifest_dir(&nested), Some(root.path().to_path_buf()));
+}
+
+#[test]
+fn test_project_paths_fill_in_defaults_only() {
+    let project = CargoProject::new(PathBuf::from("/work/demo"), None);
+    assert_eq!(project.doc_dir(), Path::new("/work/demo/target/doc"));
+
+    let mut config = Config::default();
+    project.apply_to(&mut config);
+    assert_eq!(config.docs.dir, Path::new("/work/demo/target/doc"));
+    assert_eq!(config.docs.project_dir, Path::new("/work/demo"));
+
+    // Paths chosen in the configuration file win over the detected ones
+    let mut config = Config::default();
+    config.docs.dir = PathBuf::from("/srv/docs");
+    project.apply_to(&mut config);
+    assert_eq!(config.docs.dir, Path::new("/srv/docs"));
+
+    // Generated configurations stay relative to the project where possible
+    let generated = project.generated_config();
+    assert_eq!(generated.docs.dir, Path::new("./target/doc"));
+    assert_eq!(generated.docs.project_dir, Path::new("."));
+    let shared = CargoProject::new(
+        PathBuf::from("/work/demo"),
+        Some(PathBuf::from("/cache/target")),
+    );
+    assert_eq!(
+        shared.generated_config().docs.dir,
+        Path::new("/cache/target/doc")
+    );
+}
+
+#[test]
+fn test_file_uris_convert_to_paths() {
+    assert_eq!(
+        path_from_file_uri("file:///home/me/my%20project"),
+        Some(PathBuf::from("/home/me/my project"))
+    );
+    assert_eq!(
+        path_from_file_uri("file://localhost/srv/app"),
+        Some(PathBuf::from("/srv/app"))
+    );
+    assert_eq!(
+        path_from_file_uri("file:///C:/Users/me/app"),
+        Some(PathBuf::from("C:/Users/me/app"))
+    );
+    assert_eq!(path_from_file_uri("file://server/share"), None);
+    assert_eq!(path_from_file_uri("https://example.com/app"), None);
+}
+
+#[test]
+fn test_project_from_roots_prefers_cargo_projects() {
+    let notes = tempdir().unwrap();
+    let project = tempdir().unwrap();
+    fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n",
+    )
+    .unwrap();
+    let uri = |dir: &Path| format!("file://{}", dir.display());
+
+    let roots = [
+        "https://example.com/remote".to_string(),
+        uri(notes.path()),
+        uri(project.path()),
+    ];
+    let chosen = project_from_roots(roots.iter().map(String::as_str)).unwrap();
+    assert_eq!(
+        chosen.root.canonicalize().unwrap(),
+        project.path().canonicalize().unwrap()
+    );
+
+    // Without a Cargo project, the first existing directory is used
+    let roots = [uri(&notes.path().join("missing")), uri(notes.path())];
+    let chosen = project_from_roots(roots.iter().map(String::as_str)).unwrap();
+    assert_eq!(chosen.root, notes.path());
+    assert_eq!(project_from_roots(["file:///definitely/missing"]), None);
+}
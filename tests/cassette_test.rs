@@ -0,0 +1,120 @@
+use rustdocs_mcp_server::cassette::{Cassette, CassetteMode};
+use rustdocs_mcp_server::config::{Config, HttpSettings};
+use rustdocs_mcp_server::error::{ProviderErrorKind, ServerError};
+use rustdocs_mcp_server::openai_client::OpenAiClient;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::tempdir;
+
+const SYSTEM: &str = "You answer questions about the demo crate.";
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cassettes/openai.json")
+}
+
+/// A client replaying `cassette`; its API base is never connected to
+fn replaying(cassette: &Path) -> OpenAiClient {
+    let settings = HttpSettings {
+        max_retries: 1,
+        ..HttpSettings::default()
+    };
+    let cassette = Cassette::open(cassette, CassetteMode::Replay).unwrap();
+    OpenAiClient::new(
+        String::new(),
+        Some("http://127.0.0.1:9"),
+        &BTreeMap::new(),
+        &settings,
+    )
+    .unwrap()
+    .with_cassette(Arc::new(cassette))
+}
+
+#[tokio::test]
+async fn test_recorded_embeddings_replay_with_their_retries() {
+    let client = replaying(&fixture());
+    // The recorded 429 is retried, then answered by the recorded success
+    let (vector, model) = client
+        .embed("text-embedding-3-small", "How do I resize a Widget?", None)
+        .await
+        .unwrap();
+    assert_eq!(vector.len(), 8);
+    assert_eq!(vector[0], 0.0123);
+    assert_eq!(model, "text-embedding-3-small");
+}
+
+#[tokio::test]
+async fn test_recorded_chat_completions_replay() {
+    let client = replaying(&fixture());
+    let reply = client
+        .chat(
+            "gpt-4o-mini-2024-07-18",
+            SYSTEM,
+            "How do I resize a Widget?",
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(reply.starts_with("Call `Widget::resize`"), "{}", reply);
+
+    let error = client
+        .chat("gpt-4o-mini-2024-07-18", SYSTEM, "Explain everything.")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            ServerError::Provider {
+                kind: ProviderErrorKind::ContextLength,
+                status: Some(400),
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+
+    client.check_access().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_unrecorded_requests_fail_without_a_network_call() {
+    let client = replaying(&fixture());
+    let error = client
+        .embed("text-embedding-3-small", "Something never asked", None)
+        .await
+        .unwrap_err();
+    assert!(!error.is_retryable());
+    assert!(
+        error
+            .to_string()
+            .contains("No recorded response to POST embeddings"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_replay_cassettes_must_exist() {
+    let dir = tempdir().unwrap();
+    let mut config = Config::default();
+    config.http.cassette = Some(dir.path().join("missing.json"));
+    assert!(config.validate().is_err());
+
+    // Recording creates it
+    config.http.cassette_mode = CassetteMode::Record;
+    assert!(config.validate().is_ok());
+    config.http.cassette = Some(fixture());
+    config.http.cassette_mode = CassetteMode::Replay;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_cassette_mode_names() {
+    let settings: HttpSettings = toml::from_str(
+        "cassette = \"tests/fixtures/cassettes/openai.json\"\ncassette_mode = \"record\"",
+    )
+    .unwrap();
+    assert_eq!(settings.cassette_mode, CassetteMode::Record);
+    assert_eq!(HttpSettings::default().cassette_mode, CassetteMode::Replay);
+}
@@ -0,0 +1,72 @@
+use rustdocs_mcp_server::Chunk;
+use rustdocs_mcp_server::chunk_context::{assemble, assembled_tokens, count_tokens, expand};
+use rustdocs_mcp_server::document_chunker::DocumentChunker;
+
+fn chunk(content: &str) -> Chunk {
+    Chunk {
+        id: content.to_string(),
+        content: content.to_string(),
+        tokens: count_tokens(content),
+    }
+}
+
+#[test]
+fn test_expand_prefers_following_chunk_and_respects_budget() {
+    let tokens = [100, 100, 100, 100, 100];
+    // The following chunk is taken first, then the preceding one
+    assert_eq!(expand(&tokens, 2, 200), 2..4);
+    assert_eq!(expand(&tokens, 2, 300), 1..4);
+    assert_eq!(expand(&tokens, 2, 10_000), 0..5);
+    // At the end of the document, growth continues backwards
+    assert_eq!(expand(&tokens, 4, 300), 2..5);
+    // The best chunk is kept even if it alone exceeds the budget
+    assert_eq!(expand(&tokens, 1, 50), 1..2);
+    // A neighbor too large to fit doesn't stop the other side from growing
+    assert_eq!(expand(&[100, 100, 900, 100], 1, 300), 0..2);
+}
+
+#[test]
+fn test_assemble_marks_omitted_text() {
+    let chunks = [
+        chunk("intro\n"),
+        chunk("pub fn new() -> Self\n"),
+        chunk("let x = Widget::new();\n"),
+        chunk("impl Clone for Widget\n"),
+    ];
+    assert_eq!(
+        assemble(&chunks, 1..3),
+        "[...]\npub fn new() -> Self\nlet x = Widget::new();\n[...]\n"
+    );
+    assert_eq!(
+        assemble(&chunks, 0..4),
+        "intro\npub fn new() -> Self\nlet x = Widget::new();\nimpl Clone for Widget\n"
+    );
+    // Chunks can end mid-line; the marker still gets its own line
+    assert_eq!(assemble(&[chunk("a"), chunk("b")], 0..1), "a\n[...]\n");
+}
+
+#[test]
+fn test_count_tokens() {
+    assert_eq!(count_tokens(""), 0);
+    let short = count_tokens("pub fn new() -> Self");
+    assert!(short > 0);
+    assert!(count_tokens(&"pub fn new() -> Self\n".repeat(10)) > short * 5);
+}
+
+#[test]
+fn test_chunks_carry_their_token_counts() {
+    let document = "pub fn new() -> Self\n".repeat(40);
+    let chunks = DocumentChunker::with_params(100, 200, 400).chunk_document(&document);
+    assert!(chunks.len() > 2);
+    for chunk in &chunks {
+        assert_eq!(chunk.tokens, count_tokens(&chunk.content));
+    }
+
+    let estimate = assembled_tokens(&chunks, 1..2);
+    let exact = count_tokens(&assemble(&chunks, 1..2));
+    assert!(estimate.abs_diff(exact) <= 2, "{} vs {}", estimate, exact);
+    assert_eq!(
+        assembled_tokens(&chunks, 0..chunks.len()),
+        chunks.iter().map(|chunk| chunk.tokens).sum::<usize>()
+    );
+}
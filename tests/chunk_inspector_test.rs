@@ -0,0 +1,81 @@
+use rustdocs_mcp_server::chunk_inspector::inspect_chunks;
+use rustdocs_mcp_server::doc_loader::Document;
+use rustdocs_mcp_server::document_chunker::{DocumentChunker, chunk_id_hash};
+
+fn document(path: &str, content: &str) -> Document {
+    Document {
+        path: path.to_string(),
+        content: content.to_string(),
+    }
+}
+
+#[test]
+fn test_inspect_chunks_reports_boundaries_and_stats() {
+    let chunker = DocumentChunker::with_params(10, 20, 40);
+    let long_page: String = (0..30).map(|i| format!("line {} of text\n", i)).collect();
+    let documents = vec![
+        document("index.html", "Short overview."),
+        document("struct.Widget.html", &long_page),
+        // Identical to the overview, so it shares its chunk id
+        document("fn.overview.html", "Short overview."),
+    ];
+
+    let report = inspect_chunks("demo", &chunker, &documents);
+    assert_eq!(report.sizes, (10, 20, 40));
+    assert_eq!(report.pages.len(), 3);
+    assert_eq!(report.pages[0].chunks.len(), 1);
+
+    // Chunks cover their page without gaps
+    let widget = &report.pages[1];
+    assert_eq!(widget.bytes, long_page.len());
+    assert!(widget.chunks.len() > 1);
+    assert_eq!(widget.chunks[0].start, 0);
+    assert_eq!(widget.chunks.last().unwrap().end, long_page.len());
+    for pair in widget.chunks.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start);
+    }
+    for chunk in &widget.chunks {
+        assert_eq!(
+            chunk.id,
+            chunker.generate_chunk_id(&long_page[chunk.start..chunk.end])
+        );
+    }
+
+    assert_eq!(report.chunk_count(), widget.chunks.len() + 2);
+    assert_eq!(report.unique_ids(), report.chunk_count() - 1);
+    assert_eq!(report.edited_chunks, report.chunk_count());
+    assert!(report.stable_chunks < report.edited_chunks);
+
+    let pages = report.format_pages();
+    assert!(
+        pages.starts_with("index.html (15 bytes, 1 chunks)\n"),
+        "{}",
+        pages
+    );
+    assert!(
+        pages.contains(&chunk_id_hash(&widget.chunks[0].id)[..16]),
+        "{}",
+        pages
+    );
+
+    let summary = report.to_string();
+    assert!(summary.starts_with("Chunks of 'demo' (min 10, target 20, max 40 bytes)\n"));
+    assert!(
+        summary.contains("Pages:  3 (1 split into several chunks)"),
+        "{}",
+        summary
+    );
+    assert!(summary.contains("Stability: "), "{}", summary);
+}
+
+#[test]
+fn test_inspect_chunks_without_documents() {
+    let report = inspect_chunks("empty", &DocumentChunker::new(), &[]);
+    assert_eq!(report.chunk_count(), 0);
+    assert_eq!(
+        report.to_string(),
+        "Chunks of 'empty' (min 1000, target 4000, max 8000 bytes)\n\
+         Pages:  0 (0 split into several chunks)\n\
+         Chunks: 0 (0 unique ids)\n"
+    );
+}
@@ -0,0 +1,134 @@
+use rustdocs_mcp_server::citations::cite_document;
+use rustdocs_mcp_server::doc_loader::Document;
+use rustdocs_mcp_server::page_text;
+use scraper::{Html, Selector};
+
+const SELECT_PAGE: &str = r##"<html><body>
+<div class="main-heading"><h1>Macro <a href="index.html">tokio</a>::<a class="macro" href="#">select</a></h1></div>
+<section id="main-content" class="content">
+<pre class="rust item-decl"><code>macro_rules! select { ... }</code></pre>
+<details class="toggle top-doc" open><summary>Expand description</summary><div class="docblock">
+<p>Waits on multiple concurrent branches.</p>
+<h2 id="fairness"><a class="doc-anchor" href="#fairness">§</a>Fairness</h2>
+<p>By default, branches are polled in random order.</p>
+<h3 id="biased"><a class="doc-anchor" href="#biased">§</a>Biased mode</h3>
+<p>Add <code>biased;</code> to poll in order.</p>
+<h2 id="examples"><a class="doc-anchor" href="#examples">§</a>Examples</h2>
+<p>Basic usage.</p>
+</div></details>
+</section></body></html>"##;
+
+const STRUCT_PAGE: &str = r##"<html><body>
+<section id="main-content" class="content">
+<div class="main-heading"><h1>Struct <a href="#">Widget</a></h1></div>
+<p>A widget.</p>
+<h2 id="implementations" class="section-header">Implementations<a href="#implementations" class="anchor">§</a></h2>
+<section class="impl"><h3 class="code-header">impl&lt;T&gt; <a href="#">Widget</a>&lt;T&gt;
+<div class="where">where
+    T: Clone,</div></h3></section>
+<section id="method.new" class="method"><h4 class="code-header">pub fn <a href="#method.new" class="fn">new</a>() -&gt; Self</h4></section>
+<div class="docblock"><p>Creates a widget.</p>
+<h5 id="panics"><a class="doc-anchor" href="#panics">§</a>Panics</h5>
+<p>Never.</p></div>
+</section></body></html>"##;
+
+/// Extracts a page's text the way the doc loader does
+fn extract(path: &str, html: &str) -> Document {
+    let page = Html::parse_document(html);
+    let selector = Selector::parse("section#main-content").unwrap();
+    let content = page
+        .select(&selector)
+        .map(page_text::extract)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Document {
+        path: path.to_string(),
+        content,
+    }
+}
+
+#[test]
+fn test_cite_document_labels_item_and_sections() {
+    let doc = extract("macro.select.html", SELECT_PAGE);
+    let text = cite_document("tokio", &doc, Some(SELECT_PAGE));
+
+    assert!(
+        text.starts_with("[Source: crate `tokio`, item `tokio::select!` (macro.select.html)]\n"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("[Section: Fairness]\nBy default, branches are polled in random order."),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("[Section: Fairness > Biased mode]\nAdd"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("[Section: Examples]\nBasic usage."),
+        "{}",
+        text
+    );
+    assert!(text.contains("Waits on multiple concurrent branches."));
+    assert!(!text.contains('§'), "{}", text);
+}
+
+#[test]
+fn test_cite_document_nests_method_headings() {
+    let doc = extract("widgets/struct.Widget.html", STRUCT_PAGE);
+    let text = cite_document("demo", &doc, Some(STRUCT_PAGE));
+
+    assert!(text.starts_with("[Source: crate `demo`, item `demo::widgets::Widget`"));
+    assert!(text.contains("[Section: Implementations]\n"), "{}", text);
+    assert!(
+        text.contains(
+            "[Section: Implementations > impl<T> Widget<T> where T: Clone, > pub fn new() -> Self > Panics]\nNever."
+        ),
+        "{}",
+        text
+    );
+    assert!(!text.contains("where\n"), "{}", text);
+}
+
+#[test]
+fn test_cite_document_without_html() {
+    let doc = Document {
+        path: "struct.Widget.html".to_string(),
+        content: "Widget\nA widget.".to_string(),
+    };
+    assert_eq!(
+        cite_document("demo", &doc, None),
+        "[Source: crate `demo`, item `demo::Widget` (struct.Widget.html)]\nWidget\nA widget.\n"
+    );
+
+    let doc = Document {
+        path: "all.html".to_string(),
+        content: "All items".to_string(),
+    };
+    assert_eq!(
+        cite_document("demo", &doc, None),
+        "[Source: crate `demo` (all.html)]\nAll items\n"
+    );
+}
+
+#[test]
+fn test_cite_document_names_the_stable_version() {
+    let page = r##"<html><body><section id="main-content" class="content">
+<div class="main-heading"><h1>Struct <span class="struct">Vec</span></h1>
+<span class="out-of-band"><span class="since" title="Stable since Rust version 1.0.0">1.0.0</span> · <a class="src" href="#">Source</a></span></div>
+<p>A contiguous growable array type.</p>
+</section></body></html>"##;
+    let doc = extract("vec/struct.Vec.html", page);
+    let text = cite_document("alloc", &doc, Some(page));
+    assert!(
+        text.starts_with(
+            "[Source: crate `alloc`, item `alloc::vec::Vec` (vec/struct.Vec.html), stable since Rust 1.0.0]\n"
+        ),
+        "{}",
+        text
+    );
+    assert!(!text.contains("Source\n"), "{}", text);
+}
@@ -0,0 +1,45 @@
+use rustdocs_mcp_server::code_examples::{OMITTED_EXAMPLE, strip_examples};
+use rustdocs_mcp_server::page_text;
+use scraper::{Html, Selector};
+
+const PAGE: &str = r##"<html><body>
+<section id="main-content" class="content">
+<pre class="rust item-decl"><code>pub fn resize(width: u32)</code></pre>
+<div class="docblock"><p>Resizes the widget.</p>
+<div class="example-wrap"><pre class="rust rust-example-rendered"><code><span class="kw">let</span> w = Widget::new();
+w.resize(<span class="number">3</span>);</code></pre></div>
+<p>Panics on zero.</p>
+<div class="example-wrap"><pre class="rust rust-example-rendered"><code>w.resize(<span class="number">0</span>);</code></pre></div>
+</div>
+</section></body></html>"##;
+
+/// Extracts a page's text the way the doc loader does
+fn extract(html: &str) -> String {
+    let page = Html::parse_document(html);
+    let selector = Selector::parse("section#main-content").unwrap();
+    page.select(&selector)
+        .map(page_text::extract)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_strip_examples_keeps_signatures_and_prose() {
+    let content = extract(PAGE);
+    assert!(content.contains("Widget::new()"));
+
+    let stripped = strip_examples(&content, PAGE);
+    assert_eq!(
+        stripped,
+        format!(
+            "pub fn resize(width: u32)\nResizes the widget.\n{0}\nPanics on zero.\n{0}",
+            OMITTED_EXAMPLE
+        )
+    );
+}
+
+#[test]
+fn test_strip_examples_leaves_unmatched_text_alone() {
+    let content = "Some other text\nthat the page doesn't contain";
+    assert_eq!(strip_examples(content, PAGE), content);
+}
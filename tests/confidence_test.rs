@@ -0,0 +1,38 @@
+use rustdocs_mcp_server::confidence::{Confidence, ConfidenceLevel, split_assessment};
+
+#[test]
+fn test_confidence_follows_the_score_distribution() {
+    assert_eq!(Confidence::from_scores(&[]).level, ConfidenceLevel::Low);
+    assert_eq!(Confidence::from_scores(&[]).value, 0.0);
+
+    // A strong match that clearly beats the rest
+    let clear = Confidence::from_scores(&[0.65, 0.3, 0.28, 0.25]);
+    assert_eq!(clear.level, ConfidenceLevel::High);
+
+    // Equally strong matches leave it unclear which one answers the question
+    let ambiguous = Confidence::from_scores(&[0.5, 0.49, 0.49]);
+    assert_eq!(ambiguous.level, ConfidenceLevel::Medium);
+    assert!(ambiguous.value < clear.value);
+
+    let weak = Confidence::from_scores(&[0.22, 0.21]);
+    assert_eq!(weak.level, ConfidenceLevel::Low);
+    assert_eq!(weak.to_string(), format!("low ({:.2})", weak.value));
+
+    // The model's own assessment is averaged in
+    let assessed = weak.with_assessment(ConfidenceLevel::High);
+    assert!(assessed.value > weak.value);
+}
+
+#[test]
+fn test_split_assessment_strips_the_trailing_line() {
+    assert_eq!(
+        split_assessment("Use `Widget::resize`.\n\n**Confidence: High**\n"),
+        Some(("Use `Widget::resize`.", ConfidenceLevel::High))
+    );
+    assert_eq!(
+        split_assessment("Confidence: low"),
+        Some(("", ConfidenceLevel::Low))
+    );
+    assert_eq!(split_assessment("Confidence: probably\nUse it."), None);
+    assert_eq!(split_assessment("Confidence: maybe"), None);
+}
@@ -0,0 +1,257 @@
+use rustdocs_mcp_server::config::Config;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+#[test]
+fn test_default_config_is_valid() {
+    let config = Config::default();
+    assert!(config.validate().is_ok());
+    assert_eq!(config.retrieval.top_k, 1);
+    assert_eq!(config.docs.dir, PathBuf::from("./target/doc"));
+}
+
+#[test]
+fn test_load_explicit_file_overrides_defaults() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let path = temp_dir.path().join("rustdocs-mcp.toml");
+    fs::write(
+        &path,
+        r#"
+[chunker]
+target_chunk_size = 2000
+
+[retrieval]
+top_k = 3
+min_score = 0.25
+
+[docs]
+dir = "/tmp/docs"
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(Some(&path)).expect("Config should load");
+
+    assert_eq!(config.chunker.target_chunk_size, 2000);
+    // Keys not present in the file keep their defaults
+    assert_eq!(config.chunker.min_chunk_size, 1000);
+    assert_eq!(config.retrieval.top_k, 3);
+    assert_eq!(config.retrieval.min_score, 0.25);
+    assert_eq!(config.docs.dir, PathBuf::from("/tmp/docs"));
+}
+
+#[test]
+fn test_load_rejects_unknown_keys_and_invalid_values() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    let unknown = temp_dir.path().join("unknown.toml");
+    fs::write(&unknown, "[retrieval]\ntop_n = 3\n").unwrap();
+    assert!(Config::load(Some(&unknown)).is_err());
+
+    let invalid = temp_dir.path().join("invalid.toml");
+    fs::write(&invalid, "[chunker]\nmin_chunk_size = 9000\n").unwrap();
+    assert!(Config::load(Some(&invalid)).is_err());
+
+    let no_questions = temp_dir.path().join("no_questions.toml");
+    fs::write(&no_questions, "[retrieval]\nmax_question_chars = 0\n").unwrap();
+    assert!(Config::load(Some(&no_questions)).is_err());
+
+    let low_cap = temp_dir.path().join("low_cap.toml");
+    fs::write(&low_cap, "[retrieval]\ntop_k = 5\nmax_top_k = 3\n").unwrap();
+    assert!(Config::load(Some(&low_cap)).is_err());
+
+    let negative_boost = temp_dir.path().join("negative_boost.toml");
+    fs::write(&negative_boost, "[retrieval]\nidentifier_boost = -0.1\n").unwrap();
+    assert!(Config::load(Some(&negative_boost)).is_err());
+
+    let negative_price = temp_dir.path().join("negative_price.toml");
+    fs::write(
+        &negative_price,
+        "[budget]\nmax_usd = 5.0\nembedding_usd_per_million = -1.0\n",
+    )
+    .unwrap();
+    assert!(Config::load(Some(&negative_price)).is_err());
+
+    let self_companion = temp_dir.path().join("self_companion.toml");
+    fs::write(
+        &self_companion,
+        "[docs.crates.tokio]\ncompanions = [\"tokio-util\", \"tokio\"]\n",
+    )
+    .unwrap();
+    assert!(Config::load(Some(&self_companion)).is_err());
+
+    let bad_period = temp_dir.path().join("bad_period.toml");
+    fs::write(&bad_period, "[budget]\nperiod = \"weekly\"\n").unwrap();
+    assert!(Config::load(Some(&bad_period)).is_err());
+
+    let duplicate_projects = temp_dir.path().join("duplicate_projects.toml");
+    fs::write(
+        &duplicate_projects,
+        "[[projects]]\nname = \"api\"\ndir = \"/repo/api\"\n\n[[projects]]\nname = \"api\"\ndir = \"/repo/web\"\n",
+    )
+    .unwrap();
+    assert!(Config::load(Some(&duplicate_projects)).is_err());
+
+    let bad_redaction = temp_dir.path().join("bad_redaction.toml");
+    fs::write(
+        &bad_redaction,
+        "[[redaction.rules]]\npattern = \"(unclosed\"\n",
+    )
+    .unwrap();
+    assert!(Config::load(Some(&bad_redaction)).is_err());
+
+    let input_field = temp_dir.path().join("input_field.toml");
+    fs::write(
+        &input_field,
+        "[embedding.input_types]\nfield = \"input\"\ndocument = \"document\"\nquery = \"query\"\n",
+    )
+    .unwrap();
+    assert!(Config::load(Some(&input_field)).is_err());
+
+    assert!(Config::load(Some(&temp_dir.path().join("missing.toml"))).is_err());
+}
+
+#[test]
+fn test_to_toml_round_trips() {
+    let mut config = Config::default();
+    config.retrieval.top_k = 5;
+    config.cache.max_size_mb = Some(512);
+
+    let rendered = config.to_toml().unwrap();
+    let parsed: Config = toml::from_str(&rendered).expect("Rendered config should parse");
+
+    assert_eq!(parsed, config);
+}
+
+#[test]
+fn test_per_crate_doc_settings_extend_global_ones() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let path = temp_dir.path().join("rustdocs-mcp.toml");
+    fs::write(
+        &path,
+        r#"
+[docs]
+rustdoc_flags = ["--cfg", "docsrs"]
+
+[docs.crates.my-internal-crate]
+features = ["full"]
+document_private_items = true
+rustdoc_flags = ["--cfg", "internal"]
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(Some(&path)).expect("Config should load");
+
+    // Lookups ignore `-`/`_` differences
+    let options = config.docs.rustdoc_options("my_internal_crate");
+    assert!(options.document_private_items);
+    assert_eq!(options.flags, ["--cfg", "docsrs", "--cfg", "internal"]);
+    assert_eq!(config.docs.crate_features("my_internal_crate"), ["full"]);
+
+    let other = config.docs.rustdoc_options("serde");
+    assert!(!other.document_private_items);
+    assert_eq!(other.flags, ["--cfg", "docsrs"]);
+    assert!(config.docs.crate_features("serde").is_empty());
+}
+
+#[test]
+fn test_crate_sections_override_query_settings() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let path = temp_dir.path().join("rustdocs-mcp.toml");
+    fs::write(
+        &path,
+        r#"
+[retrieval]
+top_k = 2
+
+[crate.pin-project]
+top_k = 5
+llm_model = "gpt-4o"
+prompt_template = "Explain {crate} carefully."
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(Some(&path)).expect("Config should load");
+
+    // Lookups ignore case and `-`/`_` differences
+    assert_eq!(config.top_k("Pin_Project"), 5);
+    assert_eq!(config.llm_model("pin_project"), "gpt-4o");
+    assert_eq!(
+        config
+            .crate_settings("pin-project")
+            .and_then(|settings| settings.prompt_template.as_deref()),
+        Some("Explain {crate} carefully.")
+    );
+    assert_eq!(config.top_k("serde"), 2);
+    assert_eq!(config.llm_model("serde"), config.llm.model);
+
+    let too_many = temp_dir.path().join("too_many.toml");
+    fs::write(&too_many, "[crate.tower]\ntop_k = 100\n").unwrap();
+    assert!(Config::load(Some(&too_many)).is_err());
+
+    let empty = temp_dir.path().join("empty.toml");
+    fs::write(&empty, "[crate.tower]\nprompt_template = \" \"\n").unwrap();
+    assert!(Config::load(Some(&empty)).is_err());
+}
+
+#[test]
+fn test_doc_roots_follow_the_project_dir_in_order() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let path = temp_dir.path().join("rustdocs-mcp.toml");
+    fs::write(
+        &path,
+        r#"
+[[docs.roots]]
+name = "team"
+dir = "/srv/shared-docs"
+
+[[docs.roots]]
+name = "std"
+dir = "/opt/rust/share/doc/rust/html"
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(Some(&path)).expect("Config should load");
+    let names: Vec<String> = config
+        .docs
+        .doc_roots()
+        .into_iter()
+        .map(|root| root.name)
+        .collect();
+    assert_eq!(names, ["project", "team", "std"]);
+
+    let duplicate = temp_dir.path().join("duplicate.toml");
+    fs::write(
+        &duplicate,
+        "[[docs.roots]]\nname = \"project\"\ndir = \"/srv/docs\"\n",
+    )
+    .unwrap();
+    assert!(Config::load(Some(&duplicate)).is_err());
+}
+
+#[test]
+fn test_rebased_docs_resolve_relative_paths_against_the_project() {
+    use rustdocs_mcp_server::config::DocRootSettings;
+
+    let mut config = Config::default();
+    config.docs.roots = vec![
+        DocRootSettings {
+            name: "vendored".to_string(),
+            dir: PathBuf::from("vendor/doc"),
+        },
+        DocRootSettings {
+            name: "team".to_string(),
+            dir: PathBuf::from("/srv/shared-docs"),
+        },
+    ];
+
+    let docs = config.docs.rebased(std::path::Path::new("/work/demo"));
+    assert_eq!(docs.dir, PathBuf::from("/work/demo/target/doc"));
+    assert_eq!(docs.project_dir, PathBuf::from("/work/demo"));
+    assert_eq!(docs.roots[0].dir, PathBuf::from("/work/demo/vendor/doc"));
+    assert_eq!(docs.roots[1].dir, PathBuf::from("/srv/shared-docs"));
+}
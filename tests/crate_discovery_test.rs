@@ -0,0 +1,151 @@
+use rustdocs_mcp_server::crate_discovery::{self, DocRoot};
+use std::fs;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+#[test]
+fn test_discover_only_lists_directories_with_index() {
+    let doc_dir = tempdir().expect("Failed to create temporary directory");
+    for name in ["serde_json", "anyhow"] {
+        fs::create_dir_all(doc_dir.path().join(name)).unwrap();
+        fs::write(
+            doc_dir.path().join(name).join("index.html"),
+            "<html></html>",
+        )
+        .unwrap();
+    }
+    // Shared assets and search indexes aren't crates
+    fs::create_dir_all(doc_dir.path().join("static.files")).unwrap();
+    fs::write(doc_dir.path().join("search-index.js"), "").unwrap();
+
+    let crates = crate_discovery::discover_available_crates(doc_dir.path());
+    let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["anyhow", "serde_json"]);
+    assert!(crates.iter().all(|c| c.generated_at.is_some()));
+
+    let generated_at = crates[0].generated_at.unwrap();
+    assert!(crates[0].is_stale(Some(generated_at + Duration::from_secs(60))));
+    assert!(!crates[0].is_stale(Some(SystemTime::UNIX_EPOCH)));
+    assert!(!crates[0].is_stale(None));
+
+    assert!(crate_discovery::discover_available_crates(&doc_dir.path().join("missing")).is_empty());
+}
+
+#[tokio::test]
+async fn test_package_versions_come_from_cargo_metadata() {
+    let project = tempdir().expect("Failed to create temporary directory");
+    fs::create_dir_all(project.path().join("src")).unwrap();
+    fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"tiny-docs\"\nversion = \"0.3.1\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    fs::write(project.path().join("src").join("lib.rs"), "").unwrap();
+
+    let versions = crate_discovery::package_versions(project.path())
+        .await
+        .expect("cargo metadata should succeed for a dependency-free project");
+    assert_eq!(versions.get("tiny_docs").map(String::as_str), Some("0.3.1"));
+
+    let manifest = crate_discovery::package_manifest(project.path(), "tiny_docs")
+        .await
+        .unwrap();
+    assert_eq!(
+        manifest.map(|path| path.canonicalize().unwrap()),
+        Some(project.path().join("Cargo.toml").canonicalize().unwrap())
+    );
+    let missing = crate_discovery::package_manifest(project.path(), "serde")
+        .await
+        .unwrap();
+    assert_eq!(missing, None);
+
+    let doc_dir = project.path().join("doc");
+    fs::create_dir_all(doc_dir.join("tiny_docs")).unwrap();
+    fs::write(
+        doc_dir.join("tiny_docs").join("index.html"),
+        "<html></html>",
+    )
+    .unwrap();
+    let mut crates = crate_discovery::discover_available_crates(&doc_dir);
+    crate_discovery::apply_versions(&mut crates, &versions);
+    assert_eq!(crates[0].version.as_deref(), Some("0.3.1"));
+}
+
+#[test]
+fn test_locked_versions_come_from_the_lockfile() {
+    let project = tempdir().expect("Failed to create temporary directory");
+    assert!(crate_discovery::locked_versions(project.path(), "serde").is_empty());
+
+    fs::write(
+        project.path().join("Cargo.lock"),
+        "version = 4\n\n[[package]]\nname = \"rand\"\nversion = \"0.9.0\"\n\n\
+         [[package]]\nname = \"rand\"\nversion = \"0.10.1\"\n\n\
+         [[package]]\nname = \"tokio-util\"\nversion = \"0.7.14\"\n",
+    )
+    .unwrap();
+    assert_eq!(
+        crate_discovery::locked_versions(project.path(), "rand"),
+        vec!["0.9.0", "0.10.1"]
+    );
+    assert_eq!(
+        crate_discovery::locked_versions(project.path(), "tokio_util"),
+        vec!["0.7.14"]
+    );
+    assert!(crate_discovery::locked_versions(project.path(), "serde").is_empty());
+}
+
+#[test]
+fn test_find_matching_crate_names_tolerates_separators_and_typos() {
+    let available: Vec<String> = ["tokio", "tokio_util", "serde", "serde_json"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+
+    assert_eq!(
+        crate_discovery::find_matching_crate_names("tokio-util", &available),
+        ["tokio_util"]
+    );
+    assert_eq!(
+        crate_discovery::find_matching_crate_names("Serde", &available),
+        ["serde"]
+    );
+    assert_eq!(
+        crate_discovery::find_matching_crate_names("serde_jsno", &available),
+        ["serde_json"]
+    );
+    assert!(crate_discovery::find_matching_crate_names("reqwest", &available).is_empty());
+
+    assert_eq!(
+        crate_discovery::closest_crate_names("tokio-utils", &available, 2),
+        ["tokio_util", "tokio"]
+    );
+}
+
+#[test]
+fn test_discover_in_roots_prefers_earlier_roots() {
+    let project = tempdir().expect("Failed to create temporary directory");
+    let shared = tempdir().expect("Failed to create temporary directory");
+    for (root, name) in [
+        (project.path(), "serde"),
+        (shared.path(), "serde"),
+        (shared.path(), "std"),
+    ] {
+        fs::create_dir_all(root.join(name)).unwrap();
+        fs::write(root.join(name).join("index.html"), "<html></html>").unwrap();
+    }
+
+    let roots = [
+        DocRoot::new(
+            crate_discovery::PROJECT_ROOT_NAME,
+            project.path().to_path_buf(),
+        ),
+        DocRoot::new("team", shared.path().to_path_buf()),
+    ];
+    let crates = crate_discovery::discover_in_roots(&roots);
+
+    assert_eq!(crates.len(), 2);
+    assert_eq!(crates[0].name, "serde");
+    assert_eq!(crates[0].doc_dir, project.path());
+    assert_eq!(crates[1].name, "std");
+    assert_eq!(crates[1].doc_dir, shared.path());
+}
@@ -0,0 +1,146 @@
+use rustdocs_mcp_server::crate_features::{
+    CrateFeature, attach_gated_items, format_features, gated_items, parse_manifest_features,
+};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const MANIFEST: &str = r#"
+[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1", optional = true }
+rayon = { version = "1", optional = true }
+
+[features]
+default = ["std"]
+## Use the standard library.
+##
+## Disable for `no_std` builds.
+std = []
+
+#! ### Serialization
+# Derive `Serialize` for all types
+serde = ["dep:serde", "std"]
+full = [
+    # everything
+    "std",
+    "serde",
+]
+"#;
+
+#[test]
+fn test_manifest_features_keep_order_docs_and_implicit_dependencies() {
+    let features = parse_manifest_features(MANIFEST).unwrap();
+    let names: Vec<&str> = features.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["default", "std", "serde", "full", "rayon"]);
+
+    assert_eq!(features[0].doc, None);
+    assert_eq!(features[0].enables, vec!["std"]);
+    assert_eq!(
+        features[1].doc.as_deref(),
+        Some("Use the standard library.\n\nDisable for `no_std` builds.")
+    );
+    // The `#!` heading isn't part of the feature's doc
+    assert_eq!(
+        features[2].doc.as_deref(),
+        Some("Derive `Serialize` for all types")
+    );
+    assert_eq!(features[2].enables, vec!["dep:serde", "std"]);
+    assert_eq!(features[3].doc, None);
+    assert_eq!(features[3].enables, vec!["std", "serde"]);
+    // `rayon` isn't referred to as `dep:rayon`, so it's a feature of its own
+    assert_eq!(features[4].enables, vec!["dep:rayon"]);
+
+    assert!(parse_manifest_features("[features\n").is_err());
+}
+
+fn write_page(dir: &Path, relative: &str, item_info: &str) {
+    let path = dir.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        path,
+        format!(
+            r#"<html><body><section id="main-content" class="content">
+<div class="main-heading"><h1>Item</h1></div>{}
+<details><span class="item-info"><div class="stab portability">Available on
+<strong>crate feature <code>method-only</code></strong> only.</div></span></details>
+</section></body></html>"#,
+            item_info
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_gated_items_come_from_item_level_notes() {
+    let dir = tempdir().unwrap();
+    let note = |features: &str| {
+        format!(
+            r#"<span class="item-info"><div class="stab portability">Available on <strong>{}</strong> only.</div></span>"#,
+            features
+        )
+    };
+    write_page(dir.path(), "index.html", "");
+    write_page(
+        dir.path(),
+        "struct.Widget.html",
+        &note("crate feature <code>serde</code>"),
+    );
+    write_page(
+        dir.path(),
+        "par/index.html",
+        &note("crate features <code>rayon</code> and <code>std</code>"),
+    );
+    write_page(
+        dir.path(),
+        "par/fn.map.html",
+        &note("crate features <code>rayon</code> and <code>std</code>"),
+    );
+    write_page(dir.path(), "fn.plain.html", "");
+    write_page(
+        dir.path(),
+        "all.html",
+        &note("crate feature <code>serde</code>"),
+    );
+
+    let gated = gated_items(dir.path(), "demo").unwrap();
+    assert_eq!(
+        gated.keys().collect::<Vec<_>>(),
+        vec!["rayon", "serde", "std"]
+    );
+    assert_eq!(gated["rayon"], vec!["demo::par", "demo::par::map"]);
+    assert_eq!(gated["serde"], vec!["demo::Widget"]);
+
+    let mut features = parse_manifest_features(MANIFEST).unwrap();
+    attach_gated_items(&mut features, gated);
+    let serde = features.iter().find(|f| f.name == "serde").unwrap();
+    assert_eq!(serde.gated_items, vec!["demo::Widget"]);
+
+    let text = format_features("demo", &features, 1);
+    assert!(text.contains("## `serde`\n\nDerive `Serialize` for all types\n\nEnables: dep:serde, std\n\nRequired by:\n- `demo::Widget`\n"), "{}", text);
+    assert!(text.contains("- `demo::par`\n- and 1 more\n"), "{}", text);
+}
+
+#[test]
+fn test_features_only_known_from_docs_are_listed() {
+    let mut features = Vec::new();
+    attach_gated_items(
+        &mut features,
+        [("tls".to_string(), vec!["net::Tls".to_string()])].into(),
+    );
+    assert_eq!(
+        features,
+        vec![CrateFeature {
+            name: "tls".to_string(),
+            gated_items: vec!["net::Tls".to_string()],
+            ..Default::default()
+        }]
+    );
+    assert_eq!(
+        format_features("net", &[], 5),
+        "Crate 'net' has no Cargo features."
+    );
+}
@@ -0,0 +1,121 @@
+use rustdocs_mcp_server::crate_sources::{find_registry_sources, load_documents_from_sources};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write(path: &Path, text: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn test_find_registry_sources_picks_the_newest_matching_release() {
+    let home = tempdir().unwrap();
+    let index = home
+        .path()
+        .join("registry/src/index.crates.io-6f17d22bba15001f");
+    for dir in [
+        "my-crate-1.2.3",
+        "my-crate-1.10.0",
+        "my-crate-2.0.0-alpha.1",
+        "my-crate-extra-3.0.0",
+    ] {
+        fs::create_dir_all(index.join(dir)).unwrap();
+    }
+
+    let found = |version| find_registry_sources(home.path(), "my_crate", version);
+    assert_eq!(found(None), Some(index.join("my-crate-2.0.0-alpha.1")));
+    assert_eq!(found(Some("1")), Some(index.join("my-crate-1.10.0")));
+    assert_eq!(found(Some("=1.2.3")), Some(index.join("my-crate-1.2.3")));
+    assert_eq!(found(Some("3")), None);
+    assert_eq!(
+        find_registry_sources(home.path(), "my-crate-extra", None),
+        Some(index.join("my-crate-extra-3.0.0"))
+    );
+    assert_eq!(find_registry_sources(home.path(), "other", None), None);
+}
+
+#[test]
+fn test_load_documents_from_sources_reads_public_doc_comments() {
+    let dir = tempdir().unwrap();
+    write(
+        &dir.path().join("src/lib.rs"),
+        r#"
+//! Widgets for everyone.
+
+pub mod shapes;
+
+/// A resizable widget.
+pub struct Widget;
+
+impl Widget {
+    /// Resizes the widget.
+    pub fn resize(&mut self) {}
+
+    /// Not part of the API.
+    fn internal(&self) {}
+}
+
+/// Hidden helper.
+fn helper() {}
+
+/// Builds a widget.
+#[macro_export]
+macro_rules! widget {
+    () => {};
+}
+"#,
+    );
+    write(
+        &dir.path().join("src/shapes/mod.rs"),
+        "//! Shapes widgets take.\n\n/// A circle.\npub struct Circle;\n",
+    );
+    write(
+        &dir.path().join("src/bin/tool.rs"),
+        "//! A binary.\nfn main() {}\n",
+    );
+    write(&dir.path().join("src/broken.rs"), "/// Docs\npub fn (");
+    write(
+        &dir.path().join("src/undocumented.rs"),
+        "pub fn bare() {}\n",
+    );
+
+    let documents = load_documents_from_sources(dir.path(), "my-crate").unwrap();
+    let paths: Vec<&str> = documents.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(paths, ["src/lib.rs", "src/shapes/mod.rs"]);
+
+    let lib = &documents[0].content;
+    assert!(
+        lib.starts_with("Module `my_crate`\n\nWidgets for everyone."),
+        "{}",
+        lib
+    );
+    assert!(
+        lib.contains("Struct `my_crate::Widget`\n\nA resizable widget."),
+        "{}",
+        lib
+    );
+    assert!(
+        lib.contains("Method `my_crate::Widget::resize`\n\nResizes the widget."),
+        "{}",
+        lib
+    );
+    assert!(
+        lib.contains("Macro `my_crate::widget`\n\nBuilds a widget."),
+        "{}",
+        lib
+    );
+    assert!(
+        !lib.contains("Not part of the API") && !lib.contains("Hidden helper"),
+        "{}",
+        lib
+    );
+    assert!(
+        documents[1]
+            .content
+            .contains("Module `my_crate::shapes`\n\nShapes widgets take.")
+    );
+
+    let empty = tempdir().unwrap();
+    assert!(load_documents_from_sources(empty.path(), "my_crate").is_err());
+}
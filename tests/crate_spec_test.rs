@@ -0,0 +1,103 @@
+use rustdocs_mcp_server::{
+    crate_spec::{CrateSpec, canonical_crate_name, normalize_crate_name},
+    global_cache::{CacheKey, GlobalCache},
+};
+use std::fs;
+
+#[test]
+fn test_parse_name_and_version() {
+    let spec = CrateSpec::parse("async-stripe@0.40", vec![]).unwrap();
+    assert_eq!(spec.name, "async-stripe");
+    assert_eq!(spec.version_req.as_deref(), Some("0.40"));
+    assert_eq!(spec.doc_name(), "async_stripe");
+
+    let spec = CrateSpec::parse("serde", vec![]).unwrap();
+    assert_eq!(spec.version_req, None);
+}
+
+#[test]
+fn test_parse_normalizes_features() {
+    let features = vec![
+        "b".to_string(),
+        " a ".to_string(),
+        "b".to_string(),
+        String::new(),
+    ];
+    let spec = CrateSpec::parse("tokio@1", features).unwrap();
+    assert_eq!(spec.features, vec!["a", "b"]);
+    assert_eq!(spec.to_string(), "tokio@1 (features: a,b)");
+}
+
+#[test]
+fn test_parse_rejects_invalid_specs() {
+    assert!(CrateSpec::parse("", vec![]).is_err());
+    assert!(CrateSpec::parse("serde@", vec![]).is_err());
+    assert!(CrateSpec::parse("../etc", vec![]).is_err());
+    assert!(CrateSpec::parse("serde@../../etc", vec![]).is_err());
+    assert!(CrateSpec::parse("serde@1.0/x", vec![]).is_err());
+    assert!(CrateSpec::parse(&"a".repeat(65), vec![]).is_err());
+    assert!(CrateSpec::parse("serde@>=1.0, <2.0.0-beta+build", vec![]).is_ok());
+}
+
+#[test]
+fn test_matches_ignores_dash_underscore_difference() {
+    let spec = CrateSpec::parse("async-stripe", vec![]).unwrap();
+    assert!(spec.matches("async_stripe"));
+    assert!(spec.matches("async-stripe"));
+    assert!(!spec.matches("stripe"));
+    assert!(spec.matches("Async_Stripe"));
+}
+
+#[test]
+fn test_crate_names_are_canonicalized() {
+    assert_eq!(canonical_crate_name(" serde-json "), "serde_json");
+    assert_eq!(canonical_crate_name("Inflector"), "Inflector");
+    assert_eq!(normalize_crate_name("Serde-JSON"), "serde_json");
+
+    let spec = CrateSpec::parse("serde-json@1", vec![]).unwrap();
+    assert_eq!(spec.cache_key().crate_name, "serde_json");
+}
+
+#[test]
+fn test_spellings_of_a_crate_share_a_cache_entry() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let global = GlobalCache::with_root(temp_dir.path().to_path_buf()).unwrap();
+    assert_eq!(
+        global.index_path(&CacheKey::local("serde-json")),
+        global.index_path(&CacheKey::local("serde_json"))
+    );
+
+    // Entries cached under the dashed name before are moved over
+    let legacy = temp_dir.path().join("serde-json");
+    fs::create_dir_all(&legacy).unwrap();
+    assert!(
+        global
+            .load(&CacheKey::local("serde_json"))
+            .unwrap()
+            .is_none()
+    );
+    assert!(!legacy.exists());
+    assert!(temp_dir.path().join("serde_json").is_dir());
+}
+
+#[test]
+fn test_cache_key_separates_versions_and_features() {
+    let plain = CrateSpec::parse("serde@1.0", vec![]).unwrap().cache_key();
+    let derive = CrateSpec::parse("serde@1.0", vec!["derive".to_string()])
+        .unwrap()
+        .cache_key();
+    let latest = CrateSpec::parse("serde", vec![]).unwrap().cache_key();
+
+    assert_eq!(plain.version, "1.0");
+    assert_eq!(latest.version, "*");
+    assert_ne!(plain.features_hash(), derive.features_hash());
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let global = GlobalCache::with_root(temp_dir.path().to_path_buf()).unwrap();
+    assert_ne!(global.docs_dir(&plain), global.docs_dir(&derive));
+    assert!(
+        global
+            .docs_dir(&plain)
+            .starts_with(temp_dir.path().join("serde").join("1.0"))
+    );
+}
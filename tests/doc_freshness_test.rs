@@ -0,0 +1,121 @@
+use rustdocs_mcp_server::{
+    Document, Embedding, EmbeddingProvider,
+    doc_freshness::{DocFingerprint, UnchangedPages},
+    document_store::DocumentStore,
+    state::CrateIndex,
+    vector_store::VectorStore,
+};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::tempdir;
+
+fn document(path: &str, content: &str) -> Document {
+    Document {
+        path: path.to_string(),
+        content: content.to_string(),
+    }
+}
+
+fn write_crate(crate_dir: &Path) {
+    fs::create_dir_all(crate_dir.join("de")).unwrap();
+    fs::write(crate_dir.join("index.html"), "<html>index</html>").unwrap();
+    fs::write(crate_dir.join("struct.Widget.html"), "<html>widget</html>").unwrap();
+    fs::write(crate_dir.join("de/fn.parse.html"), "<html>parse</html>").unwrap();
+    fs::write(crate_dir.join("search.js"), "not a page").unwrap();
+}
+
+#[test]
+fn test_fingerprint_notices_edited_added_and_removed_pages() {
+    let dir = tempdir().expect("Failed to create temporary directory");
+    write_crate(dir.path());
+    let fingerprint = DocFingerprint::scan(dir.path());
+    assert_eq!(fingerprint.len(), 3);
+    assert_eq!(DocFingerprint::scan(dir.path()), fingerprint);
+
+    fs::write(dir.path().join("search.js"), "still not a page").unwrap();
+    assert_eq!(DocFingerprint::scan(dir.path()), fingerprint);
+
+    fs::write(dir.path().join("de/fn.parse.html"), "<html>parse v2</html>").unwrap();
+    let edited = DocFingerprint::scan(dir.path());
+    assert_ne!(edited, fingerprint);
+
+    fs::write(dir.path().join("fn.new.html"), "<html>new</html>").unwrap();
+    let added = DocFingerprint::scan(dir.path());
+    assert_eq!(added.len(), 4);
+    assert_ne!(added, edited);
+
+    fs::remove_file(dir.path().join("fn.new.html")).unwrap();
+    assert_eq!(DocFingerprint::scan(dir.path()), edited);
+    assert!(DocFingerprint::scan(&dir.path().join("missing")).is_empty());
+}
+
+#[test]
+fn test_unchanged_pages_reuse_the_earlier_text() {
+    let dir = tempdir().expect("Failed to create temporary directory");
+    write_crate(dir.path());
+    let fingerprint = DocFingerprint::scan(dir.path());
+    let documents = vec![
+        document("index.html", "Index text"),
+        document("struct.Widget.html", "Widget text"),
+        document("de/fn.parse.html", "Parse text"),
+    ];
+
+    fs::write(
+        dir.path().join("struct.Widget.html"),
+        "<html>widget v2</html>",
+    )
+    .unwrap();
+    let unchanged = UnchangedPages::new(dir.path(), &fingerprint, documents.clone());
+    assert_eq!(unchanged.len(), 2);
+    let reused = unchanged.get(&dir.path().join("de/fn.parse.html"));
+    assert_eq!(reused.unwrap().unwrap().content, "Parse text");
+    assert!(
+        unchanged
+            .get(&dir.path().join("struct.Widget.html"))
+            .is_none()
+    );
+    // Pages not under the crate's directory are always read
+    assert!(unchanged.get(Path::new("/elsewhere/index.html")).is_none());
+
+    // Unchanged pages the earlier index had no text for stay without
+    let unchanged = UnchangedPages::new(dir.path(), &fingerprint, documents[..1].to_vec());
+    assert!(matches!(
+        unchanged.get(&dir.path().join("de/fn.parse.html")),
+        Some(None)
+    ));
+    let unchanged = unchanged.excluding(["de/fn.parse.html"]);
+    assert!(
+        unchanged
+            .get(&dir.path().join("de/fn.parse.html"))
+            .is_none()
+    );
+    assert_eq!(
+        unchanged.fingerprint(dir.path()),
+        DocFingerprint::scan(dir.path())
+    );
+}
+
+#[test]
+fn test_indexes_go_stale_when_any_page_is_edited() {
+    let doc_dir = tempdir().expect("Failed to create temporary directory");
+    let crate_dir = doc_dir.path().join("my_crate");
+    write_crate(&crate_dir);
+    let documents = vec![document("index.html", "Index text")];
+    let store = DocumentStore::new(documents, None, doc_dir.path()).unwrap();
+    let embedding = Embedding::new(vec![1.0], EmbeddingProvider::OpenAI, "model".to_string());
+    let vectors = VectorStore::new(
+        vec![("index.html".to_string(), embedding)],
+        None,
+        doc_dir.path(),
+    )
+    .unwrap();
+    let index = CrateIndex::new("my_crate", doc_dir.path(), store, vectors)
+        .with_fingerprint(DocFingerprint::scan(&crate_dir), Duration::ZERO);
+    assert!(index.is_current());
+    assert_eq!(index.unchanged_pages().len(), 3);
+
+    fs::write(crate_dir.join("de/fn.parse.html"), "<html>parse v2</html>").unwrap();
+    assert!(!index.is_current());
+    assert_eq!(index.unchanged_pages().len(), 2);
+}
@@ -0,0 +1,149 @@
+use rustdocs_mcp_server::doc_generator::{self, DocGenerator, GenerationMethod, RustdocOptions};
+use rustdocs_mcp_server::doc_loader::DocLoaderError;
+use rustdocs_mcp_server::doc_workspace::DocWorkspace;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tempfile::{TempDir, tempdir};
+use tokio_util::sync::CancellationToken;
+
+#[test]
+fn test_shim_manifest_declares_dependency_with_features() {
+    let manifest = doc_generator::shim_manifest_contents(
+        "async-stripe",
+        Some("0.40"),
+        &["runtime-tokio-hyper".to_string()],
+    );
+    let parsed: toml::Table = manifest
+        .parse()
+        .expect("Shim manifest should be valid TOML");
+
+    let dependency = &parsed["dependencies"]["async-stripe"];
+    assert_eq!(dependency["version"].as_str(), Some("0.40"));
+    assert_eq!(
+        dependency["features"].as_array().unwrap()[0].as_str(),
+        Some("runtime-tokio-hyper")
+    );
+    assert!(parsed.contains_key("workspace"));
+}
+
+/// Creates a dependency-free library project named `tiny_docs`
+fn tiny_project() -> TempDir {
+    let project = tempdir().expect("Failed to create temporary directory");
+    fs::create_dir_all(project.path().join("src")).unwrap();
+    fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"tiny_docs\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    fs::write(
+        project.path().join("src").join("lib.rs"),
+        "/// Adds one.\npub fn add_one(x: i32) -> i32 { helper(x) }\n\n\
+         /// Private implementation detail.\nfn helper(x: i32) -> i32 { x + 1 }\n",
+    )
+    .unwrap();
+    project
+}
+
+fn generator_for(project: &TempDir) -> DocGenerator {
+    let doc_dir = project.path().join("target").join("doc");
+    let workspace = DocWorkspace::new(project.path().join("workspaces")).unwrap();
+    DocGenerator::new(project.path(), &doc_dir, workspace, true)
+}
+
+#[tokio::test]
+async fn test_generate_documents_project_crate() {
+    let project = tiny_project();
+    let output = Arc::new(Mutex::new(Vec::<String>::new()));
+    let sink = Arc::clone(&output);
+    let generator = generator_for(&project).with_progress(Arc::new(move |line| {
+        sink.lock().unwrap().push(line.to_string())
+    }));
+
+    let method = generator
+        .generate("tiny_docs", &[])
+        .await
+        .expect("cargo doc should succeed for a dependency-free project");
+
+    assert_eq!(method, GenerationMethod::Project);
+    let doc_dir = project.path().join("target").join("doc");
+    assert!(doc_dir.join("tiny_docs").join("index.html").is_file());
+    assert!(!doc_dir.join("tiny_docs").join("fn.helper.html").exists());
+    assert_eq!(
+        doc_generator::recorded_features(&doc_dir.join("tiny_docs").join("index.html")),
+        Some(Vec::new())
+    );
+    assert!(
+        output
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains("Documenting tiny_docs")),
+        "cargo doc progress should be streamed"
+    );
+}
+
+#[tokio::test]
+async fn test_generate_stops_on_cancellation_and_timeout() {
+    let project = tiny_project();
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let cancelled = generator_for(&project)
+        .with_cancellation(cancel)
+        .generate("tiny_docs", &[])
+        .await;
+    assert!(matches!(cancelled, Err(DocLoaderError::Cancelled)));
+
+    let timed_out = generator_for(&project)
+        .with_timeout(Duration::from_millis(1))
+        .generate("tiny_docs", &[])
+        .await;
+    assert!(matches!(timed_out, Err(DocLoaderError::Timeout(_))));
+}
+
+#[tokio::test]
+async fn test_generate_documents_private_items_when_requested() {
+    let project = tiny_project();
+    let generator = generator_for(&project).with_rustdoc_options(RustdocOptions {
+        document_private_items: true,
+        flags: vec!["--cfg".to_string(), "docsrs".to_string()],
+    });
+
+    generator
+        .generate("tiny_docs", &[])
+        .await
+        .expect("cargo doc should accept the extra rustdoc flags");
+
+    let doc_dir = project.path().join("target").join("doc");
+    assert!(doc_dir.join("tiny_docs").join("fn.helper.html").is_file());
+}
+
+#[test]
+fn test_recorded_features_are_ignored_once_docs_are_rebuilt() {
+    let dir = tempdir().expect("Failed to create temporary directory");
+    let index_file = dir.path().join("index.html");
+    let record = dir.path().join(doc_generator::FEATURES_FILE_NAME);
+    fs::write(&index_file, "<html></html>").unwrap();
+    fs::write(&record, "rt\nmacros\n").unwrap();
+    let generated = SystemTime::now() - Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(&index_file)
+        .unwrap()
+        .set_modified(generated)
+        .unwrap();
+    assert_eq!(
+        doc_generator::recorded_features(&index_file),
+        Some(vec!["rt".to_string(), "macros".to_string()])
+    );
+
+    // `cargo doc` run by hand afterwards, with features we don't know
+    fs::File::options()
+        .write(true)
+        .open(&index_file)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(doc_generator::recorded_features(&index_file), None);
+}
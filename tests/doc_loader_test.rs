@@ -1,3 +1,6 @@
+use rustdocs_mcp_server::config::Config;
+use rustdocs_mcp_server::crate_discovery::DocRoot;
+use rustdocs_mcp_server::crate_spec::CrateSpec;
 use rustdocs_mcp_server::doc_loader;
 use std::fs;
 use std::path::Path;
@@ -71,4 +74,78 @@ fn test_process_html_documents() {
     
     // Clean up temp directory
     temp_dir.close().expect("Failed to clean up temp directory");
-}
\ No newline at end of file
+}
+#[test]
+fn test_load_documents_from_roots_uses_first_root_with_crate() {
+    let project = tempfile::tempdir().expect("Failed to create temp directory");
+    let shared = tempfile::tempdir().expect("Failed to create temp directory");
+    let html = r#"<html><body><section id="main-content" class="content">Shared docs for my_crate.</section></body></html>"#;
+    for root in [project.path(), shared.path()] {
+        fs::create_dir_all(root.join("other_crate")).unwrap();
+        fs::write(root.join("other_crate").join("index.html"), html).unwrap();
+    }
+    fs::create_dir_all(shared.path().join("my_crate")).unwrap();
+    fs::write(shared.path().join("my_crate").join("index.html"), html).unwrap();
+
+    let roots = [
+        DocRoot::new("project", project.path().to_path_buf()),
+        DocRoot::new("team", shared.path().to_path_buf()),
+    ];
+
+    let (root, docs) = doc_loader::load_documents_from_roots(&roots, "my-crate")
+        .expect("Crate should be found in the second root");
+    assert_eq!(root.name, "team");
+    assert!(!docs.is_empty());
+    // Each root caches its crates under its own namespace
+    assert_ne!(root.cache_key("my_crate"), roots[0].cache_key("my_crate"));
+
+    let (root, _) = doc_loader::load_documents_from_roots(&roots, "other_crate").unwrap();
+    assert_eq!(root.name, "project");
+
+    assert!(doc_loader::load_documents_from_roots(&roots, "missing").is_err());
+}
+
+#[tokio::test]
+async fn test_load_documents_prefers_local_docs_of_the_requested_version() {
+    let root = tempfile::tempdir().expect("Failed to create temp directory");
+    let local = root.path().join("doc");
+    fs::create_dir_all(local.join("my_crate")).unwrap();
+    fs::write(
+        local.join("my_crate").join("index.html"),
+        r#"<html><body><span class="version">1.2.3</span><section id="main-content" class="content">Local docs.</section></body></html>"#,
+    )
+    .unwrap();
+
+    let mut config = Config {
+        offline: true,
+        ..Default::default()
+    };
+    config.docs.dir = local;
+    config.docs.project_dir = root.path().to_path_buf();
+    config.cache.global_dir = Some(root.path().join("global"));
+
+    for version in [None, Some("1"), Some("1.2"), Some("=1.2.3")] {
+        let docs = doc_loader::load_documents_with_config("my-crate", version, None, &config)
+            .await
+            .unwrap();
+        assert!(docs[0].content.contains("Local docs."), "{:?}", version);
+    }
+
+    // Another release comes from the docs generated into the global cache
+    let spec = CrateSpec::parse("my-crate@1.3", Vec::new()).unwrap();
+    let generated = config
+        .global_cache()
+        .unwrap()
+        .docs_dir(&spec.cache_key())
+        .join("my_crate");
+    fs::create_dir_all(&generated).unwrap();
+    fs::write(
+        generated.join("index.html"),
+        r#"<html><body><section id="main-content" class="content">Generated docs.</section></body></html>"#,
+    )
+    .unwrap();
+    let docs = doc_loader::load_documents_with_config("my-crate", Some("1.3"), None, &config)
+        .await
+        .unwrap();
+    assert!(docs[0].content.contains("Generated docs."));
+}
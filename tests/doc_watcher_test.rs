@@ -0,0 +1,55 @@
+use rustdocs_mcp_server::doc_watcher::{DocWatcher, crate_for_path};
+use std::{fs, path::Path, time::Duration};
+use tempfile::tempdir;
+
+#[test]
+fn test_crate_for_path() {
+    let doc_dir = Path::new("/project/target/doc");
+
+    assert_eq!(
+        crate_for_path(doc_dir, &doc_dir.join("serde_json/struct.Value.html")),
+        Some("serde_json".to_string())
+    );
+    assert_eq!(
+        crate_for_path(doc_dir, &doc_dir.join("my_crate")),
+        Some("my_crate".to_string())
+    );
+
+    // Shared rustdoc assets and top-level files don't map to a crate
+    assert_eq!(
+        crate_for_path(doc_dir, &doc_dir.join("search-index.js")),
+        None
+    );
+    assert_eq!(
+        crate_for_path(doc_dir, &doc_dir.join("static.files/main.js")),
+        None
+    );
+    assert_eq!(
+        crate_for_path(doc_dir, &doc_dir.join("src/my_crate/lib.rs.html")),
+        None
+    );
+    assert_eq!(
+        crate_for_path(doc_dir, Path::new("/elsewhere/file.html")),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_watcher_reports_changed_crates() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let mut watcher = DocWatcher::new(temp_dir.path(), Duration::from_millis(200))
+        .expect("Failed to start watcher");
+
+    let crate_dir = watcher.doc_dir().join("watched_crate");
+    fs::create_dir_all(&crate_dir).unwrap();
+    fs::write(crate_dir.join("index.html"), "<html></html>").unwrap();
+    fs::write(watcher.doc_dir().join("search-index.js"), "{}").unwrap();
+
+    let batch = tokio::time::timeout(Duration::from_secs(10), watcher.next_batch())
+        .await
+        .expect("Watcher should report a batch")
+        .expect("Watcher should still be running");
+
+    assert!(batch.contains("watched_crate"));
+    assert_eq!(batch.len(), 1, "Only crate directories should be reported");
+}
@@ -0,0 +1,35 @@
+use rustdocs_mcp_server::doc_workspace::{DocWorkspace, WORKSPACES_DIR_NAME};
+use std::fs;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[test]
+fn test_workspaces_are_reused_and_stale_ones_removed() {
+    let cache_root = tempdir().expect("Failed to create temporary directory");
+    let workspace = DocWorkspace::in_cache_root(cache_root.path()).unwrap();
+    assert_eq!(
+        workspace.root(),
+        cache_root.path().join(WORKSPACES_DIR_NAME)
+    );
+
+    let first = workspace.acquire("shim-serde").unwrap();
+    let dir = first.path().to_path_buf();
+    fs::write(dir.join("Cargo.lock"), "# kept between runs").unwrap();
+    drop(first);
+
+    // Acquiring the same name again hands back the existing directory
+    let second = workspace.acquire("shim-serde").unwrap();
+    assert_eq!(second.path(), dir);
+    assert!(dir.join("Cargo.lock").is_file());
+    drop(second);
+
+    assert_eq!(
+        workspace.cleanup_stale(Duration::from_secs(3600)).unwrap(),
+        0
+    );
+    assert!(dir.is_dir());
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(workspace.cleanup_stale(Duration::ZERO).unwrap(), 1);
+    assert!(!dir.exists());
+}
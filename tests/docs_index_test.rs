@@ -0,0 +1,98 @@
+use rustdocs_mcp_server::{DocsIndex, Document, config::Config, mock::MOCK_PROVIDER};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_page(crate_dir: &Path, file: &str, text: &str) {
+    let html = format!(
+        "<html><body><section id=\"main-content\" class=\"content\"><p>{}</p></section></body></html>",
+        text
+    );
+    fs::write(crate_dir.join(file), html).unwrap();
+}
+
+#[tokio::test]
+async fn test_docs_index_searches_and_answers_with_mock_providers() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let doc_dir = root.path().join("doc");
+    let crate_dir = doc_dir.join("demo");
+    fs::create_dir_all(&crate_dir).unwrap();
+    write_page(&crate_dir, "index.html", "The demo crate builds gadgets.");
+    write_page(
+        &crate_dir,
+        "struct.Widget.html",
+        "Widget is created with Widget::new and resized with Widget::resize.",
+    );
+    write_page(
+        &crate_dir,
+        "fn.parse_config.html",
+        "parse_config reads TOML configuration files from disk.",
+    );
+
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = doc_dir;
+    config.docs.project_dir = root.path().to_path_buf();
+    config.cache.dir = Some(root.path().join("chunks"));
+    config.cache.global_dir = Some(root.path().join("global"));
+
+    let index = DocsIndex::open_with_config("demo", config)
+        .await
+        .expect("Mock providers should index the crate offline");
+    assert_eq!(index.crate_name(), "demo");
+    assert_eq!(index.len(), 3);
+    assert!(index.skipped().is_empty());
+
+    let hits = index
+        .search("read TOML configuration", 2)
+        .await
+        .expect("Search should succeed");
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].path, "fn.parse_config.html");
+    assert!(hits[0].content.contains("parse_config reads TOML"));
+    assert!(hits[0].score >= hits[1].score);
+
+    let answer = index
+        .answer("How do I resize a Widget?")
+        .await
+        .expect("Answer should succeed");
+    assert!(answer.contains("Mock answer about 'demo'"), "{}", answer);
+    assert!(answer.contains("struct.Widget.html"), "{}", answer);
+}
+
+#[tokio::test]
+async fn test_ingested_documents_are_searched_as_a_pseudo_crate() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = root.path().join("doc");
+    config.docs.project_dir = root.path().to_path_buf();
+    config.cache.dir = Some(root.path().join("chunks"));
+    config.cache.global_dir = Some(root.path().join("global"));
+
+    let documents = vec![
+        Document {
+            path: "adr/0001-storage.md".to_string(),
+            content: "# Storage\n\nWe store sessions in Postgres, not Redis.".to_string(),
+        },
+        Document {
+            path: "runbooks/deploy.txt".to_string(),
+            content: "Deploys roll out region by region behind a feature flag.".to_string(),
+        },
+    ];
+    let index = DocsIndex::ingest_with_config("team-docs", &documents, config)
+        .await
+        .expect("Mock providers should index the documents offline");
+    assert_eq!(index.crate_name(), "team_docs");
+    // Both documents and the generated overview page
+    assert_eq!(index.len(), 3);
+
+    let hits = index
+        .search("sessions in Postgres or Redis", 1)
+        .await
+        .expect("Search should succeed");
+    assert_eq!(hits[0].path, "adr-0001-storage-md.html");
+    assert!(hits[0].content.contains("sessions in Postgres"));
+}
@@ -0,0 +1,79 @@
+use rustdocs_mcp_server::docs_links::{ItemLink, docs_rs_url, format_links, item_links};
+
+fn pages() -> Vec<String> {
+    [
+        "index.html",
+        "struct.Client.html",
+        "de/fn.from_str.html",
+        "de/struct.Error.html",
+        "ser/struct.Error.html",
+        "macro.json.html",
+        "all.html",
+    ]
+    .iter()
+    .map(|page| page.to_string())
+    .collect()
+}
+
+#[test]
+fn test_docs_rs_url() {
+    assert_eq!(
+        docs_rs_url("serde-json", Some("1.0.120"), "de/fn.from_str.html"),
+        "https://docs.rs/serde-json/1.0.120/serde_json/de/fn.from_str.html"
+    );
+    assert_eq!(
+        docs_rs_url("serde_json", None, "index.html"),
+        "https://docs.rs/serde_json/latest/serde_json/index.html"
+    );
+}
+
+#[test]
+fn test_mentioned_items_are_linked() {
+    let answer = "Parse with `de::from_str`, then send it with `Client::send` or the \
+                  `json!` macro. `Vec` and `Error` are not linked.";
+    let links = item_links(answer, "serde_json", Some("1.0.0"), &pages());
+    let base = "https://docs.rs/serde_json/1.0.0/serde_json";
+    assert_eq!(
+        links,
+        vec![
+            ItemLink {
+                name: "de::from_str".to_string(),
+                url: format!("{}/de/fn.from_str.html", base),
+            },
+            ItemLink {
+                name: "Client::send".to_string(),
+                url: format!("{}/struct.Client.html#method.send", base),
+            },
+            ItemLink {
+                name: "json".to_string(),
+                url: format!("{}/macro.json.html", base),
+            },
+        ]
+    );
+    // Full paths link too, and ambiguous names become unambiguous with a module
+    let links = item_links(
+        "See `serde_json::ser::Error`.",
+        "serde_json",
+        None,
+        &pages(),
+    );
+    assert_eq!(links.len(), 1);
+    assert!(
+        links[0]
+            .url
+            .ends_with("/latest/serde_json/ser/struct.Error.html")
+    );
+}
+
+#[test]
+fn test_format_links() {
+    assert_eq!(format_links(&[]), "");
+    let links = [ItemLink {
+        name: "Client".to_string(),
+        url: "https://docs.rs/demo/1.0.0/demo/struct.Client.html".to_string(),
+    }];
+    assert_eq!(
+        format_links(&links),
+        "\n\nDocs:\n- `Client`: https://docs.rs/demo/1.0.0/demo/struct.Client.html"
+    );
+}
@@ -0,0 +1,103 @@
+use rustdocs_mcp_server::doctor::{self, Check, CheckStatus, StartupMode};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_check_doc_dir_missing() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let check = doctor::check_doc_dir(&temp_dir.path().join("missing"));
+    assert_eq!(check.status, CheckStatus::Fail);
+    assert!(check.fix.is_some(), "Failing checks should suggest a fix");
+}
+
+#[test]
+fn test_check_doc_dir_counts_crates() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    // Empty doc directory is only a warning
+    let check = doctor::check_doc_dir(temp_dir.path());
+    assert_eq!(check.status, CheckStatus::Warn);
+
+    let crate_dir = temp_dir.path().join("my_crate");
+    fs::create_dir_all(&crate_dir).unwrap();
+    fs::write(crate_dir.join("index.html"), "<html></html>").unwrap();
+
+    let check = doctor::check_doc_dir(temp_dir.path());
+    assert_eq!(check.status, CheckStatus::Ok);
+    assert!(check.detail.starts_with("1 crates"));
+}
+
+#[test]
+fn test_check_cache_writable() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let check = doctor::check_cache_writable("cache", &temp_dir.path().join("nested"));
+    assert_eq!(check.status, CheckStatus::Ok);
+    assert_eq!(
+        fs::read_dir(temp_dir.path().join("nested"))
+            .unwrap()
+            .count(),
+        0
+    );
+
+    // A file where the directory should be can't be written into
+    let blocked = temp_dir.path().join("blocked");
+    fs::write(&blocked, "not a directory").unwrap();
+    let check = doctor::check_cache_writable("cache", &blocked);
+    assert_eq!(check.status, CheckStatus::Fail);
+}
+
+#[test]
+fn test_parse_rustdoc_minor() {
+    assert_eq!(
+        doctor::parse_rustdoc_minor("rustdoc 1.86.0 (05f9846f8 2025-03-31)"),
+        Some(86)
+    );
+    assert_eq!(
+        doctor::parse_rustdoc_minor("rustdoc 1.88.0-nightly"),
+        Some(88)
+    );
+    assert_eq!(doctor::parse_rustdoc_minor("garbage"), None);
+}
+
+fn check(name: &'static str, status: CheckStatus, detail: &str) -> Check {
+    Check {
+        name,
+        status,
+        detail: detail.to_string(),
+        fix: Some("fix it".to_string()),
+    }
+}
+
+#[test]
+fn test_startup_mode_degrades_or_fails_fast() {
+    let healthy = [
+        check("cargo doc output", CheckStatus::Warn, "no crates"),
+        check("API connectivity", CheckStatus::Ok, "reachable"),
+    ];
+    assert_eq!(
+        doctor::startup_mode(&healthy, true).unwrap(),
+        StartupMode::Full
+    );
+
+    let unreachable = [
+        check("chunk cache", CheckStatus::Ok, "writable"),
+        check("API connectivity", CheckStatus::Fail, "could not reach api"),
+    ];
+    assert_eq!(
+        doctor::startup_mode(&unreachable, false).unwrap(),
+        StartupMode::RetrievalOnly {
+            reason: "could not reach api".to_string()
+        }
+    );
+    let err = doctor::startup_mode(&unreachable, true).unwrap_err();
+    assert!(err.to_string().contains("could not reach api"));
+    assert!(err.to_string().contains("fix it"));
+
+    // Other failures only stop the server in strict mode
+    let no_docs = [check("cargo doc output", CheckStatus::Fail, "not found")];
+    assert_eq!(
+        doctor::startup_mode(&no_docs, false).unwrap(),
+        StartupMode::Full
+    );
+    assert!(doctor::startup_mode(&no_docs, true).is_err());
+}
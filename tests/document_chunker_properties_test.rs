@@ -0,0 +1,74 @@
+use proptest::prelude::*;
+use rustdocs_mcp_server::document_chunker::DocumentChunker;
+
+/// Text mixing ASCII prose, code, emoji, right-to-left scripts and combining
+/// marks, so chunk boundaries land next to multi-byte characters
+fn document() -> impl Strategy<Value = String> {
+    let piece = prop_oneof![
+        "[a-zA-Z0-9 .,:;()_]{1,40}",
+        Just("\n\n".to_string()),
+        Just("fn main() { println!(\"🦀\"); }".to_string()),
+        Just("🦀🚀👩‍👩‍👧".to_string()),
+        Just("שלום עולם".to_string()),
+        Just("مرحبا بالعالم".to_string()),
+        Just("e\u{301}\u{302}".to_string()),
+        Just("日本語のドキュメント".to_string()),
+        any::<String>(),
+    ];
+    prop::collection::vec(piece, 0..200).prop_map(|pieces| pieces.concat())
+}
+
+/// Chunker parameters satisfying `min <= target <= max`, small enough that
+/// generated documents are split
+fn chunker() -> impl Strategy<Value = DocumentChunker> {
+    (4usize..64, 0usize..64, 0usize..64).prop_map(|(min, target, max)| {
+        let target = min + target;
+        DocumentChunker::with_params(min, target, target + max)
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_chunks_reassemble_to_the_document(chunker in chunker(), text in document()) {
+        let chunks = chunker.chunk_document(&text);
+        let joined: String = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        prop_assert_eq!(joined, text);
+    }
+
+    #[test]
+    fn test_chunks_are_never_empty_or_too_long(chunker in chunker(), text in document()) {
+        let chunks = chunker.chunk_document(&text);
+        prop_assert!(!chunks.is_empty());
+        for chunk in &chunks {
+            let length = chunk.content.len();
+            prop_assert!(length <= chunker.max_chunk_size(), "{} bytes", length);
+            prop_assert!(!chunk.content.is_empty() || text.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_chunk_ids_are_deterministic(chunker in chunker(), text in document()) {
+        let first = chunker.chunk_document(&text);
+        let second = chunker.chunk_document(&text);
+        prop_assert_eq!(first.len(), second.len());
+        for (a, b) in first.iter().zip(&second) {
+            prop_assert_eq!(&a.id, &b.id);
+            prop_assert_eq!(&a.id, &chunker.generate_chunk_id(&a.content));
+        }
+    }
+
+    #[test]
+    fn test_streamed_chunks_match_chunk_document(chunker in chunker(), text in document()) {
+        let streamed: Vec<String> = chunker.chunks(&text).map(|chunk| chunk.id).collect();
+        let collected: Vec<String> =
+            chunker.chunk_document(&text).into_iter().map(|chunk| chunk.id).collect();
+        prop_assert_eq!(streamed, collected);
+    }
+
+    #[test]
+    fn test_arbitrary_text_never_panics(text in any::<String>(), min in 1usize..16) {
+        let chunker = DocumentChunker::with_params(min, min * 2, min * 4);
+        chunker.chunk_document(&text);
+        chunker.embeddable_chunks(&text);
+    }
+}
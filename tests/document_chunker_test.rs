@@ -1,4 +1,6 @@
-use rustdocs_mcp_server::document_chunker::DocumentChunker;
+use rustdocs_mcp_server::document_chunker::{
+    CHUNK_ID_VERSION, DocumentChunker, chunk_id_hash, chunk_id_prefix, chunk_id_with_prefix,
+};
 
 #[test]
 fn test_document_chunker_basic() {
@@ -108,4 +110,36 @@ fn test_generate_chunk_id() {
     let id3 = chunker.generate_chunk_id(different_content);
     
     assert_ne!(id1, id3, "Different content should produce different chunk IDs");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_chunks_end_between_characters() {
+    // Every size limit falls inside a multi-byte character
+    let chunker = DocumentChunker::with_params(5, 7, 10);
+    let doc = "🦀שלום".repeat(20);
+
+    let chunks = chunker.chunk_document(&doc);
+    assert!(chunks.len() > 1);
+    assert!(chunks.iter().all(|chunk| chunk.content.len() <= 10));
+    let joined: String = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+    assert_eq!(joined, doc);
+}
+
+#[test]
+fn test_chunk_ids_name_scheme_and_params() {
+    let chunker = DocumentChunker::new();
+    let id = chunker.generate_chunk_id("Versioned chunk id");
+
+    assert!(id.starts_with(&format!("v{}-", CHUNK_ID_VERSION)));
+    assert_eq!(chunk_id_prefix(&id), Some(chunker.id_prefix()));
+    assert_eq!(chunk_id_hash(&id).len(), 64);
+    assert_eq!(chunk_id_with_prefix("", "Versioned chunk id"), chunk_id_hash(&id));
+
+    // Other chunking parameters name their chunks differently
+    let other = DocumentChunker::with_params(100, 200, 400);
+    assert_ne!(other.id_prefix(), chunker.id_prefix());
+    assert_ne!(other.generate_chunk_id("Versioned chunk id"), id);
+
+    // Bare hashes of earlier versions belong to no current scheme
+    assert_eq!(chunk_id_prefix(chunk_id_hash(&id)), None);
+}
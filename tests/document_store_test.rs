@@ -0,0 +1,48 @@
+use rustdocs_mcp_server::{Document, document_store::DocumentStore};
+use std::fs;
+use tempfile::tempdir;
+
+fn documents() -> Vec<Document> {
+    ["struct.Client.html", "fn.get.html", "index.html"]
+        .iter()
+        .map(|path| Document {
+            path: path.to_string(),
+            content: format!("Documentation text for {} with unicode: é ✓", path),
+        })
+        .collect()
+}
+
+#[test]
+fn test_documents_within_budget_stay_in_memory() {
+    let spill_dir = tempdir().expect("Failed to create temporary directory");
+    let store = DocumentStore::new(documents(), Some(1024 * 1024), spill_dir.path()).unwrap();
+
+    assert!(!store.is_spilled());
+    assert_eq!(store.len(), 3);
+    assert!(store.memory_bytes() > 0);
+    assert_eq!(fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_documents_over_budget_are_spilled_and_read_back() {
+    let spill_dir = tempdir().expect("Failed to create temporary directory");
+    let expected = documents();
+    let store = DocumentStore::new(expected.clone(), Some(16), spill_dir.path()).unwrap();
+
+    assert!(store.is_spilled());
+    assert_eq!(store.len(), 3);
+    assert_eq!(store.memory_bytes(), 0);
+    for doc in &expected {
+        let read_back = store
+            .get(&doc.path)
+            .unwrap()
+            .expect("Document should exist");
+        assert_eq!(read_back.content, doc.content);
+    }
+    assert!(store.get("missing.html").unwrap().is_none());
+
+    // The spill file goes away with the store
+    assert_eq!(fs::read_dir(spill_dir.path()).unwrap().count(), 1);
+    drop(store);
+    assert_eq!(fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+}
@@ -1,5 +1,7 @@
+use rustdocs_mcp_server::config::Config;
+use rustdocs_mcp_server::error::ServerError;
 use rustdocs_mcp_server::embeddings::{Embedding, EmbeddingProvider};
-use rustdocs_mcp_server::embedding_cache_service::EmbeddingCacheService;
+use rustdocs_mcp_server::embedding_cache_service::{EmbeddingCacheService, QUARANTINE_DIR_NAME};
 use std::env;
 
 #[tokio::test]
@@ -240,4 +242,358 @@ async fn test_generate_openai_embedding() {
     } else {
         println!("Skipping OpenAI embedding tests due to API errors");
     }
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_offline_mode_uses_only_cached_embeddings() {
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config {
+        offline: true,
+        ..Default::default()
+    };
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+
+    // No API key is needed because the service never calls the API
+    let service = EmbeddingCacheService::with_config(String::new(), &config)
+        .expect("Failed to create offline embedding cache service");
+    assert!(service.is_offline());
+
+    let result = service.get_embedding("uncached question").await;
+    assert!(
+        matches!(result, Err(ServerError::Offline(_))),
+        "Cache misses should fail instead of calling the API"
+    );
+
+    // Seed the cache the same way the service writes entries
+    let content = "cached question";
+    let chunk_id = service.chunker().generate_chunk_id(content);
+    let json = serde_json::json!({
+        "vector": [0.6, 0.8],
+        "document": content,
+        "model": "test-model",
+        "provider": "OpenAI",
+    });
+    std::fs::write(cache_dir.path().join(chunk_id), json.to_string()).unwrap();
+
+    let embedding = service
+        .get_embedding(content)
+        .await
+        .expect("Cached embeddings should be served offline");
+    assert_eq!(embedding.values, vec![0.6, 0.8]);
+}
+
+#[tokio::test]
+async fn test_cache_entries_without_norm_are_migrated_on_read() {
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config {
+        offline: true,
+        ..Default::default()
+    };
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+    let service = EmbeddingCacheService::with_config(String::new(), &config)
+        .expect("Failed to create offline embedding cache service");
+
+    // An entry written before norms were stored alongside the vector
+    let content = "legacy question";
+    let path = cache_dir.path().join(service.chunker().generate_chunk_id(content));
+    let json = serde_json::json!({
+        "vector": [3.0, 4.0],
+        "document": content,
+        "model": "test-model",
+        "provider": "OpenAI",
+    });
+    std::fs::write(&path, json.to_string()).unwrap();
+
+    let embedding = service
+        .get_embedding(content)
+        .await
+        .expect("Legacy entries should still be served");
+    assert_eq!(embedding.values, vec![3.0, 4.0]);
+    assert!((embedding.norm - 5.0).abs() < 1e-6);
+
+    let migrated: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(migrated["norm"], serde_json::json!(5.0));
+    assert_eq!(migrated["vector"], serde_json::json!([3.0, 4.0]));
+}
+
+#[tokio::test]
+async fn test_get_embeddings_preserves_input_order() {
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config {
+        offline: true,
+        ..Default::default()
+    };
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+    config.embedding.concurrency = 2;
+    let service = EmbeddingCacheService::with_config(String::new(), &config)
+        .expect("Failed to create offline embedding cache service");
+
+    let documents = ["first document", "second document", "third document"];
+    for (i, content) in documents.iter().enumerate() {
+        let json = serde_json::json!({
+            "vector": [i as f32, 1.0],
+            "document": content,
+            "model": "test-model",
+            "provider": "OpenAI",
+        });
+        let chunk_id = service.chunker().generate_chunk_id(content);
+        std::fs::write(cache_dir.path().join(chunk_id), json.to_string()).unwrap();
+    }
+
+    let embeddings = service
+        .get_embeddings(&documents)
+        .await
+        .expect("Cached embeddings should be served offline");
+    let firsts: Vec<f32> = embeddings.iter().map(|e| e.values[0]).collect();
+    assert_eq!(firsts, vec![0.0, 1.0, 2.0]);
+}
+
+#[tokio::test]
+async fn test_mismatched_cache_entry_is_regenerated() {
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config {
+        offline: true,
+        ..Default::default()
+    };
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+    let service = EmbeddingCacheService::with_config(String::new(), &config)
+        .expect("Failed to create offline embedding cache service");
+
+    // An entry for different content under this chunk's id is treated as a miss
+    let content = "colliding question";
+    let chunk_id = service.chunker().generate_chunk_id(content);
+    let json = serde_json::json!({
+        "vector": [0.6, 0.8],
+        "document": "some other text",
+        "model": "test-model",
+        "provider": "OpenAI",
+    });
+    std::fs::write(cache_dir.path().join(chunk_id), json.to_string()).unwrap();
+
+    let error = service
+        .get_embedding(content)
+        .await
+        .expect_err("Offline regeneration should fail");
+    assert!(matches!(error, ServerError::Offline(_)));
+    let mcp_error = error.into_mcp_error("Failed to get embedding");
+    assert_eq!(mcp_error.code, rmcp::model::ErrorCode::INVALID_REQUEST);
+}
+
+#[test]
+fn test_chunks_of_the_wrong_dimensionality_are_quarantined() {
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config {
+        offline: true,
+        ..Default::default()
+    };
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+    let service = EmbeddingCacheService::with_config(String::new(), &config)
+        .expect("Failed to create offline embedding cache service");
+
+    let write = |content: &str, vector: serde_json::Value| {
+        let json = serde_json::json!({
+            "vector": vector,
+            "document": content,
+            "model": "test-model",
+            "provider": "OpenAI",
+        });
+        let path = cache_dir.path().join(service.chunker().generate_chunk_id(content));
+        std::fs::write(&path, json.to_string()).unwrap();
+        path
+    };
+    let good = write("good document", serde_json::json!([0.6, 0.8]));
+    let bad = write("bad document", serde_json::json!([0.6, 0.8, 0.0]));
+
+    assert!(service.quarantine_chunks("good document", 2).is_empty());
+    assert!(good.exists());
+    assert_eq!(service.quarantine_chunks("bad document", 2), vec![bad.clone()]);
+    assert!(!bad.exists());
+    assert!(cache_dir
+        .path()
+        .join(QUARANTINE_DIR_NAME)
+        .join(bad.file_name().unwrap())
+        .exists());
+}
+
+#[test]
+fn test_unusable_cache_dir_falls_back_instead_of_failing() {
+    let scratch = tempfile::tempdir().expect("Failed to create temporary directory");
+    let blocker = scratch.path().join("not-a-directory");
+    std::fs::write(&blocker, "").unwrap();
+    let mut config = Config::default();
+    config.cache.dir = Some(blocker.join("cache"));
+
+    let service = EmbeddingCacheService::with_config(String::new(), &config)
+        .expect("An unusable cache directory shouldn't stop the service");
+    if let Some(dir) = service.cache_dir() {
+        assert!(dir.starts_with(std::env::temp_dir()));
+        assert!(dir.is_dir());
+    }
+}
+
+#[tokio::test]
+async fn test_try_get_embeddings_reports_failures_per_document() {
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config {
+        offline: true,
+        ..Default::default()
+    };
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+    let service = EmbeddingCacheService::with_config(String::new(), &config)
+        .expect("Failed to create offline embedding cache service");
+
+    let cached = "cached document";
+    let json = serde_json::json!({
+        "vector": [1.0, 0.0],
+        "document": cached,
+        "model": "test-model",
+        "provider": "OpenAI",
+    });
+    let chunk_id = service.chunker().generate_chunk_id(cached);
+    std::fs::write(cache_dir.path().join(chunk_id), json.to_string()).unwrap();
+
+    let results = service
+        .try_get_embeddings(&["uncached document", cached])
+        .await;
+    assert!(matches!(results[0], Err(ServerError::Offline(_))));
+    assert_eq!(results[1].as_ref().unwrap().values, vec![1.0, 0.0]);
+    assert!(service.get_embeddings(&["uncached document", cached]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_embedding_requests_use_the_configured_api_base_and_headers() {
+    use std::io::{Read, Write};
+
+    // A one-shot OpenAI-compatible endpoint recording the request it gets
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+        while !String::from_utf8_lossy(&request).contains("\"model\"") {
+            let read = stream.read(&mut buffer).unwrap();
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+        let body = r#"{"data":[{"embedding":[0.6,0.8]}],"model":"proxy-model"}"#;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        String::from_utf8_lossy(&request).to_lowercase()
+    });
+
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config::default();
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+    config.embedding.api_base = Some(format!("http://{}/v1/", address));
+    config
+        .embedding
+        .headers
+        .insert("X-Proxy-Route".to_string(), "embeddings".to_string());
+    let service = EmbeddingCacheService::with_config("test-key".to_string(), &config)
+        .expect("Failed to create embedding cache service");
+
+    let embedding = service
+        .get_embedding("proxied document")
+        .await
+        .expect("The proxy should answer");
+    assert_eq!(embedding.model, "proxy-model");
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("post /v1/embeddings "), "{}", request);
+    assert!(request.contains("x-proxy-route: embeddings"), "{}", request);
+    assert!(request.contains("authorization: bearer test-key"), "{}", request);
+
+    config
+        .embedding
+        .headers
+        .insert("Bad Header".to_string(), "value".to_string());
+    assert!(config.validate().is_err());
+    assert!(EmbeddingCacheService::with_config(String::new(), &config).is_err());
+}
+
+#[tokio::test]
+async fn test_edited_documents_only_embed_their_new_chunks() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    // An OpenAI-compatible endpoint answering every request, so the embeddings
+    // end up in the on-disk cache
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { break };
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+            let mut request = vec![0; content_length];
+            let _ = reader.read_exact(&mut request);
+            let body = r#"{"data":[{"embedding":[0.6,0.8]}],"model":"proxy-model"}"#;
+            let _ = write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+
+    let cache_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let mut config = Config::default();
+    config.cache.dir = Some(cache_dir.path().to_path_buf());
+    config.embedding.api_base = Some(format!("http://{}/v1/", address));
+    config.chunker.min_chunk_size = 16;
+    config.chunker.target_chunk_size = 64;
+    config.chunker.max_chunk_size = 128;
+    let service = EmbeddingCacheService::with_config("test-key".to_string(), &config)
+        .expect("Failed to create embedding cache service");
+
+    let mut paragraphs: Vec<String> = (0..20)
+        .map(|i| format!("Paragraph {i} explains how resize_{i} scales a widget.\n\n"))
+        .collect();
+    let old = paragraphs.concat();
+    paragraphs[19] = "The last paragraph was rewritten from scratch.\n\n".to_string();
+    let new = paragraphs.concat();
+    service.get_embedding(&old).await.unwrap();
+
+    let (unchanged, added, removed) = service.chunk_changes(&old, &new);
+    assert!(unchanged > 0 && added > 0 && removed > 0);
+    let (hits, misses) = service.chunk_cache_counts();
+    service.get_embedding(&new).await.unwrap();
+    let (new_hits, new_misses) = service.chunk_cache_counts();
+    assert_eq!(new_hits - hits, unchanged as u64);
+    assert_eq!(new_misses - misses, added as u64);
+
+    // Chunks only the old text had can be dropped from the cache
+    let new_keys = service.chunk_keys(&new);
+    let stale: Vec<String> = service
+        .chunk_keys(&old)
+        .into_iter()
+        .filter(|key| !new_keys.contains(key))
+        .collect();
+    assert_eq!(service.remove_chunks(&stale), removed);
+    assert_eq!(service.remove_chunks(&stale), 0);
+    for key in &stale {
+        assert!(!cache_dir.path().join(key).exists());
+    }
+    for key in &new_keys {
+        assert!(cache_dir.path().join(key).exists());
+    }
+}
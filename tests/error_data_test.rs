@@ -0,0 +1,67 @@
+use rmcp::Error as McpError;
+use rustdocs_mcp_server::error::ServerError;
+use rustdocs_mcp_server::error_data::{self, Suggestion};
+use serde_json::json;
+use std::time::Duration;
+
+#[test]
+fn test_fields_close_to_the_failure_win() {
+    let error = McpError::internal_error("Failed to read document text", None);
+    let error = error_data::with_doc_path(error, "struct.Client.html");
+    let error = error_data::with_crate(error, "reqwest");
+    // Added on the way out, e.g. from the tool's arguments
+    let error = error_data::with_crate(error, "Reqwest");
+    let error = error_data::finish(error);
+    assert_eq!(
+        error.data.unwrap(),
+        json!({
+            "doc_path": "struct.Client.html",
+            "crate_name": "reqwest",
+            "retryable": false,
+        })
+    );
+
+    // Data that isn't an object is kept
+    let error = McpError::internal_error("boom", Some(json!("detail text")));
+    let error = error_data::with_crate(error, "serde");
+    assert_eq!(
+        error.data.unwrap(),
+        json!({ "detail": "detail text", "crate_name": "serde" })
+    );
+}
+
+#[test]
+fn test_finish_suggests_what_to_do() {
+    let suggestion =
+        |error: McpError| error_data::finish(error).data.unwrap()["suggestion"].clone();
+
+    let body = r#"{"error":{"message":"Rate limit reached","code":"rate_limit_exceeded"}}"#;
+    let rate_limited = ServerError::from_api_response(429, Some(Duration::from_secs(5)), body)
+        .into_mcp_error("Failed to get embedding");
+    let rate_limited = error_data::with_provider(rate_limited, "openai");
+    let data = error_data::finish(rate_limited).data.unwrap();
+    assert_eq!(data["suggestion"], "retry");
+    assert_eq!(data["provider"], "openai");
+    assert_eq!(data["retryable"], true);
+
+    let body = r#"{"error":{"code":"invalid_api_key","message":"bad key"}}"#;
+    let auth =
+        ServerError::from_api_response(401, None, body).into_mcp_error("OpenAI chat API error");
+    assert_eq!(suggestion(auth), "ask_user");
+
+    let out_of_budget = ServerError::BudgetExceeded("1000 of 1000 tokens used".to_string())
+        .into_mcp_error("Failed to get embedding");
+    assert_eq!(suggestion(out_of_budget), "ask_user");
+
+    let bad_question = McpError::invalid_params("question must not be empty", None);
+    assert_eq!(suggestion(bad_question), "fix_arguments");
+
+    // Explicit suggestions are kept, and internal errors get none
+    let missing_docs = error_data::with_suggestion(
+        McpError::internal_error("No documentation found", None),
+        Suggestion::GenerateDocs,
+    );
+    assert_eq!(suggestion(missing_docs), "generate_docs");
+    let internal = McpError::internal_error("Missing question embedding", None);
+    assert!(suggestion(internal).is_null());
+}
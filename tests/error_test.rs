@@ -0,0 +1,87 @@
+use rmcp::model::ErrorCode;
+use rustdocs_mcp_server::error::{
+    PROVIDER_AUTH_ERROR, PROVIDER_QUOTA_EXCEEDED, PROVIDER_RATE_LIMITED, ProviderErrorKind,
+    ServerError,
+};
+use std::time::Duration;
+
+#[test]
+fn test_classify_openai_failures() {
+    let cases = [
+        (
+            401,
+            r#"{"error":{"message":"Incorrect API key","code":"invalid_api_key"}}"#,
+            ProviderErrorKind::Auth,
+        ),
+        (
+            429,
+            r#"{"error":{"message":"You exceeded your current quota","type":"insufficient_quota"}}"#,
+            ProviderErrorKind::QuotaExceeded,
+        ),
+        (
+            429,
+            r#"{"error":{"message":"Rate limit reached","code":"rate_limit_exceeded"}}"#,
+            ProviderErrorKind::RateLimited,
+        ),
+        (
+            400,
+            r#"{"error":{"message":"Too many tokens","code":"context_length_exceeded"}}"#,
+            ProviderErrorKind::ContextLength,
+        ),
+        (
+            503,
+            "upstream connect error",
+            ProviderErrorKind::Unavailable,
+        ),
+        (418, "", ProviderErrorKind::Other),
+    ];
+    for (status, body, expected) in cases {
+        match ServerError::from_api_response(status, None, body) {
+            ServerError::Provider { kind, .. } => assert_eq!(kind, expected, "status {}", status),
+            other => panic!("Unexpected error: {}", other),
+        }
+    }
+}
+
+#[test]
+fn test_provider_errors_map_to_distinct_mcp_codes() {
+    let body = r#"{"error":{"message":"Rate limit reached","code":"rate_limit_exceeded"}}"#;
+    let error = ServerError::from_api_response(429, Some(Duration::from_secs(20)), body)
+        .into_mcp_error("Failed to get embedding");
+    assert_eq!(error.code, PROVIDER_RATE_LIMITED);
+    assert!(error.message.contains("Rate limit reached"));
+    let data = error.data.expect("rate limit errors carry data");
+    assert_eq!(data["kind"], "rate_limited");
+    assert_eq!(data["retryable"], true);
+    assert_eq!(data["retry_after_secs"], 20);
+
+    let codes: Vec<ErrorCode> = [
+        (
+            401,
+            r#"{"error":{"code":"invalid_api_key","message":"bad key"}}"#,
+        ),
+        (
+            429,
+            r#"{"error":{"type":"insufficient_quota","message":"no quota"}}"#,
+        ),
+        (
+            400,
+            r#"{"error":{"code":"context_length_exceeded","message":"too long"}}"#,
+        ),
+    ]
+    .into_iter()
+    .map(|(status, body)| {
+        ServerError::from_api_response(status, None, body)
+            .into_mcp_error("OpenAI chat API error")
+            .code
+    })
+    .collect();
+    assert_eq!(
+        codes,
+        vec![
+            PROVIDER_AUTH_ERROR,
+            PROVIDER_QUOTA_EXCEEDED,
+            ErrorCode::INVALID_PARAMS
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+use rustdocs_mcp_server::extractive::{excerpts, sentences};
+
+#[test]
+fn test_sentences_join_lines_and_split_at_sentence_ends() {
+    let text = "Creates a new\n`Widget`\n.\nWidgets are resized with\nWidget::resize\n! Version 1.0 is\nstable";
+    assert_eq!(
+        sentences(text),
+        vec![
+            "Creates a new `Widget` .",
+            "Widgets are resized with Widget::resize !",
+            "Version 1.0 is stable",
+        ]
+    );
+    assert!(sentences(" \n ").is_empty());
+}
+
+#[test]
+fn test_excerpts_pick_the_sentences_sharing_the_question_words() {
+    let documents = [
+        "Widgets are drawn on a canvas. Call Widget::resize to resize a widget. Colors come from the theme.",
+        "The theme holds colors. A resized widget is redrawn on the next frame.",
+    ];
+    let found = excerpts("How do I resize a widget?", &documents, 2);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].document, 0);
+    assert_eq!(
+        found[0].text,
+        "Call Widget::resize to **resize** a **widget**."
+    );
+    assert_eq!(found[1].document, 1);
+    assert_eq!(
+        found[1].text,
+        "A **resized** **widget** is redrawn on the next frame."
+    );
+    assert!(
+        found
+            .iter()
+            .all(|excerpt| excerpt.score > 0.0 && excerpt.score <= 1.0)
+    );
+}
+
+#[test]
+fn test_excerpts_keep_document_order() {
+    let documents = [
+        "Parsing is lenient by default. Strict parsing rejects unknown keys in a config.",
+        "Config files are parsed with parse_config from a path.",
+    ];
+    let found = excerpts("parse a config file", &documents, 3);
+    let order: Vec<usize> = found.iter().map(|excerpt| excerpt.document).collect();
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(order, sorted);
+}
+
+#[test]
+fn test_no_excerpts_without_shared_words() {
+    let documents = ["Widgets are drawn on a canvas with colors."];
+    assert!(excerpts("What is the timeout?", &documents, 3).is_empty());
+    // Stop words alone don't count
+    assert!(excerpts("How does it do that?", &documents, 3).is_empty());
+    assert!(excerpts("canvas", &documents, 0).is_empty());
+}
@@ -0,0 +1,50 @@
+use rustdocs_mcp_server::identifier_boost::{IdentifierIndex, question_identifiers};
+
+#[test]
+fn test_question_identifiers() {
+    assert_eq!(
+        question_identifiers("How do I use HashMap::entry with or_insert_with?"),
+        vec!["HashMap::entry", "or_insert_with"]
+    );
+    assert_eq!(
+        question_identifiers("When does tokio::select! poll its branches? What about StreamExt?"),
+        vec!["tokio::select", "StreamExt"]
+    );
+    // Quoted names count even when they don't look like identifiers
+    assert_eq!(
+        question_identifiers("What does `Vec<T>::push()` return, and `len`?"),
+        vec!["Vec", "push", "len"]
+    );
+    assert!(question_identifiers("How do I create a Widget? Is it fast?").is_empty());
+    assert!(question_identifiers("What is a :: token?").is_empty());
+}
+
+#[test]
+fn test_rare_identifiers_weigh_more() {
+    let texts = [
+        "A Widget is created with Widget::new and cleared with reset_all.",
+        "Widget::resize changes the size of a Widget.",
+        "The Widget trait is implemented for every Widget.",
+        "Configuration is read from TOML files.",
+    ];
+    let index = IdentifierIndex::new(texts);
+    assert!(index.idf("reset_all") > index.idf("Widget"));
+    assert_eq!(index.idf("missing"), 1.0);
+    // Paths are as rare as their last segment
+    assert_eq!(index.idf("demo::reset_all"), index.idf("reset_all"));
+    assert_eq!(IdentifierIndex::default().idf("Widget"), 0.0);
+
+    let identifiers = vec!["Widget".to_string(), "reset_all".to_string()];
+    let both = index.score(&identifiers, texts[0], None);
+    let common = index.score(&identifiers, texts[1], None);
+    assert!(both > common && common > 0.0);
+    assert_eq!(index.score(&identifiers, texts[3], None), 0.0);
+    assert_eq!(index.score(&[], texts[0], None), 0.0);
+
+    // Only whole words match, but the item path counts too
+    let identifiers = vec!["reset".to_string()];
+    assert_eq!(index.score(&identifiers, texts[0], None), 0.0);
+    assert!(index.score(&identifiers, "", Some("demo::Widget::reset")) > 0.0);
+    let identifiers = vec!["Widget::resize".to_string()];
+    assert!(index.score(&identifiers, "Calls demo::Widget::resize.", None) > 0.0);
+}
@@ -0,0 +1,106 @@
+use rustdocs_mcp_server::doc_loader::Document;
+use rustdocs_mcp_server::document_chunker::DocumentChunker;
+use rustdocs_mcp_server::index_manifest::IndexManifest;
+use tempfile::tempdir;
+
+fn documents(text: &str) -> Vec<Document> {
+    vec![
+        Document {
+            path: "index.html".to_string(),
+            content: "The demo crate.".to_string(),
+        },
+        Document {
+            path: "struct.Widget.html".to_string(),
+            content: text.to_string(),
+        },
+    ]
+}
+
+#[test]
+fn test_manifest_names_changed_inputs() {
+    let chunker = DocumentChunker::new();
+    let manifest = IndexManifest::new(
+        "demo",
+        &documents("A widget."),
+        &chunker,
+        "openai",
+        "text-embedding-3-small",
+    );
+    assert_eq!(manifest.documents, 2);
+    assert_eq!(manifest.content_hash.len(), 64);
+    assert_eq!(manifest.server_version, env!("CARGO_PKG_VERSION"));
+
+    let same = IndexManifest::new(
+        "demo",
+        &documents("A widget."),
+        &chunker,
+        "openai",
+        "text-embedding-3-small",
+    );
+    assert_eq!(same, manifest);
+    assert!(same.changes_since(&manifest).is_empty());
+
+    // Edited docs keep the embeddings of unchanged documents
+    let edited = IndexManifest::new(
+        "demo",
+        &documents("A resizable widget."),
+        &chunker,
+        "openai",
+        "text-embedding-3-small",
+    );
+    assert_eq!(edited.changes_since(&manifest), vec!["documentation"]);
+    assert!(edited.embeddings_compatible(&manifest));
+
+    let rebuilt = IndexManifest::new(
+        "demo",
+        &documents("A widget."),
+        &DocumentChunker::with_params(500, 2000, 4000),
+        "openai",
+        "text-embedding-3-large",
+    );
+    assert_eq!(
+        rebuilt.changes_since(&manifest),
+        vec!["chunker settings", "embedding model"]
+    );
+    assert!(!rebuilt.embeddings_compatible(&manifest));
+}
+
+#[test]
+fn test_manifest_round_trips_through_json() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("manifest.json");
+    assert_eq!(IndexManifest::load(&path).unwrap(), None);
+
+    let manifest = IndexManifest::new(
+        "demo",
+        &documents("A widget."),
+        &DocumentChunker::new(),
+        "mock",
+        "mock",
+    );
+    manifest.save(&path).unwrap();
+    assert_eq!(IndexManifest::load(&path).unwrap(), Some(manifest.clone()));
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(IndexManifest::load(&path).is_err());
+
+    let text = manifest.to_string();
+    assert!(text.starts_with("Crate: demo\nDocuments: 2 (content sha256 "));
+    assert!(
+        text.contains("\nChunker: min 1000, target 4000, max 8000 bytes, boilerplate skipped\n")
+    );
+    assert!(text.contains("\nEmbeddings: mock (mock)\n"));
+}
+
+#[test]
+fn test_load_error_names_the_manifest() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("manifest.json");
+    std::fs::write(&path, "{ not a manifest").unwrap();
+    let err = IndexManifest::load(&path).unwrap_err();
+    assert!(
+        err.to_string().contains(&path.display().to_string()),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,111 @@
+use rustdocs_mcp_server::Document;
+use rustdocs_mcp_server::doc_loader;
+use rustdocs_mcp_server::ingest::{ingest_documents, read_documents};
+use std::fs;
+use tempfile::tempdir;
+
+fn document(path: &str, content: &str) -> Document {
+    Document {
+        path: path.to_string(),
+        content: content.to_string(),
+    }
+}
+
+#[test]
+fn test_read_documents_picks_up_markdown_and_text_files() {
+    let dir = tempdir().unwrap();
+    let corpus = dir.path().join("wiki");
+    fs::create_dir_all(corpus.join("adr")).unwrap();
+    fs::write(corpus.join("adr/0001-storage.md"), "# Storage").unwrap();
+    fs::write(corpus.join("notes.txt"), "Plain notes").unwrap();
+    fs::write(corpus.join("logo.png"), [0u8, 1, 2]).unwrap();
+    let single = dir.path().join("README.markdown");
+    fs::write(&single, "Read me").unwrap();
+
+    let documents = read_documents(&[corpus, single]).unwrap();
+    let paths: Vec<&str> = documents.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        ["adr/0001-storage.md", "notes.txt", "README.markdown"]
+    );
+    assert_eq!(documents[1].content, "Plain notes");
+
+    assert!(read_documents(&[dir.path().join("missing")]).is_err());
+}
+
+#[test]
+fn test_ingested_pages_load_like_rustdoc_output() {
+    let dir = tempdir().unwrap();
+    let documents = [
+        document(
+            "adr/0001-storage.md",
+            "Sessions live in <Postgres> & Redis.",
+        ),
+        document("adr-0001-storage.md", "A second page flattening alike."),
+    ];
+    let report = ingest_documents(dir.path(), "team-docs", &documents).unwrap();
+    assert_eq!(report.crate_name, "team_docs");
+    assert_eq!(report.written, 2);
+    assert_eq!(report.removed, 0);
+
+    let loaded = doc_loader::load_documents_from_doc_dir(dir.path(), "team_docs").unwrap();
+    let mut paths: Vec<&str> = loaded.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort_unstable();
+    assert_eq!(
+        paths,
+        [
+            "adr-0001-storage-md-2.html",
+            "adr-0001-storage-md.html",
+            "index.html"
+        ]
+    );
+    let page = loaded
+        .iter()
+        .find(|doc| doc.path == "adr-0001-storage-md.html")
+        .unwrap();
+    assert_eq!(
+        page.content,
+        "adr/0001-storage.md\nSessions live in <Postgres> & Redis."
+    );
+    // Page names never read as rustdoc item pages
+    for path in paths {
+        assert_eq!(
+            doc_loader::item_path("team_docs", std::path::Path::new(path)).as_deref(),
+            (path == "index.html").then_some("team_docs")
+        );
+    }
+}
+
+#[test]
+fn test_ingesting_again_rewrites_only_changed_documents() {
+    let dir = tempdir().unwrap();
+    let first = [document("a.md", "First"), document("b.md", "Second")];
+    ingest_documents(dir.path(), "corpus", &first).unwrap();
+
+    let second = [document("a.md", "First"), document("c.md", "Third")];
+    let report = ingest_documents(dir.path(), "corpus", &second).unwrap();
+    assert_eq!(report.written, 1);
+    assert_eq!(report.unchanged, 1);
+    assert_eq!(report.removed, 1);
+    assert!(!dir.path().join("corpus/b-md.html").exists());
+    assert!(dir.path().join("corpus/c-md.html").exists());
+
+    let report = ingest_documents(dir.path(), "corpus", &second).unwrap();
+    assert_eq!(
+        (report.written, report.unchanged, report.removed),
+        (0, 2, 0)
+    );
+}
+
+#[test]
+fn test_names_that_are_not_crate_names_are_rejected() {
+    let dir = tempdir().unwrap();
+    let documents = [document("a.md", "Text")];
+    for name in ["", "../escape", "team docs"] {
+        assert!(
+            ingest_documents(dir.path(), name, &documents).is_err(),
+            "{:?}",
+            name
+        );
+    }
+}
@@ -24,12 +24,6 @@ async fn test_document_processing_pipeline() {
         return;
     }
     
-    // Initialize OpenAI client
-    let client = async_openai::Client::new();
-    if rustdocs_mcp_server::embeddings::OPENAI_CLIENT.set(client).is_err() {
-        println!("Failed to initialize OpenAI client, but continuing with test");
-    }
-    
     // Default embedding model name
     let _embedding_model = "text-embedding-3-small";
         
@@ -112,12 +106,6 @@ async fn test_synthetic_pipeline() {
         }
     };
     
-    // Initialize OpenAI client
-    let client = async_openai::Client::new();
-    if rustdocs_mcp_server::embeddings::OPENAI_CLIENT.set(client).is_err() {
-        println!("Failed to initialize OpenAI client, but continuing with test");
-    }
-    
     // Create a synthetic document
     let doc = Document {
         path: "test/module.html".to_string(),
@@ -0,0 +1,114 @@
+use rustdocs_mcp_server::item_finder::{FoundItem, find_items, format_found_items};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write(path: &Path, html: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, html).unwrap();
+}
+
+fn page(body: &str) -> String {
+    format!(
+        r#"<html><body><section id="main-content" class="content">{}</section></body></html>"#,
+        body
+    )
+}
+
+fn write_fixture(doc_dir: &Path) {
+    let streams = doc_dir.join("streams");
+    write(&streams.join("index.html"), &page("<h1>Crate streams</h1>"));
+    write(
+        &streams.join("stream").join("trait.StreamExt.html"),
+        &page(r#"<section id="method.buffer_unordered" class="method"></section>"#),
+    );
+    write(
+        &streams.join("stream").join("struct.Buffer.html"),
+        &page("<h1>Buffer</h1>"),
+    );
+    // Left behind by `pub use inner::Buffer` from a private module
+    write(
+        &streams.join("inner").join("struct.Buffer.html"),
+        r#"<html><head><meta http-equiv="refresh" content="0;URL=../../streams/stream/struct.Buffer.html"></head></html>"#,
+    );
+
+    let bytes = doc_dir.join("bytes");
+    write(&bytes.join("index.html"), &page("<h1>Crate bytes</h1>"));
+    write(&bytes.join("struct.Buffer.html"), &page("<h1>Buffer</h1>"));
+    write(&bytes.join("fn.buffer.html"), &page("<h1>buffer</h1>"));
+}
+
+fn item(crate_name: &str, path: &str, kind: &str) -> FoundItem {
+    FoundItem {
+        crate_name: crate_name.to_string(),
+        path: path.to_string(),
+        kind: kind.to_string(),
+    }
+}
+
+#[test]
+fn test_find_items_across_crates() {
+    let dir = tempdir().unwrap();
+    write_fixture(dir.path());
+    let crates = vec![
+        ("streams".to_string(), dir.path()),
+        ("bytes".to_string(), dir.path()),
+    ];
+
+    // Redirects aren't reported, and exact matches come before case-insensitive ones
+    let found = find_items(&crates, "Buffer", 10).unwrap();
+    assert_eq!(
+        found,
+        vec![
+            item("bytes", "bytes::Buffer", "struct"),
+            item("streams", "streams::stream::Buffer", "struct"),
+            item("bytes", "bytes::buffer", "fn"),
+        ]
+    );
+    assert_eq!(find_items(&crates, "Buffer", 1).unwrap().len(), 1);
+
+    let found = find_items(&crates, "stream::Buffer", 10).unwrap();
+    assert_eq!(
+        found,
+        vec![item("streams", "streams::stream::Buffer", "struct")]
+    );
+
+    let found = find_items(&crates, "bytes", 10).unwrap();
+    assert_eq!(found, vec![item("bytes", "bytes", "crate")]);
+
+    assert!(find_items(&crates, "Missing", 10).unwrap().is_empty());
+    assert!(find_items(&crates, " :: ", 10).unwrap().is_empty());
+}
+
+#[test]
+fn test_find_items_looks_up_methods_on_their_types() {
+    let dir = tempdir().unwrap();
+    write_fixture(dir.path());
+    let crates = vec![
+        ("streams".to_string(), dir.path()),
+        ("bytes".to_string(), dir.path()),
+    ];
+
+    let expected = vec![item(
+        "streams",
+        "streams::stream::StreamExt::buffer_unordered",
+        "method",
+    )];
+    assert_eq!(
+        find_items(&crates, "StreamExt::buffer_unordered", 10).unwrap(),
+        expected
+    );
+    assert_eq!(
+        find_items(&crates, "buffer_unordered", 10).unwrap(),
+        expected
+    );
+    assert!(
+        find_items(&crates, "Buffer::buffer_unordered", 10)
+            .unwrap()
+            .is_empty()
+    );
+
+    let text = format_found_items("buffer_unordered", &expected);
+    assert!(text.starts_with("`buffer_unordered` matches 1 item(s) in crate(s) streams:"));
+    assert!(text.contains("- `streams::stream::StreamExt::buffer_unordered` (method)"));
+}
@@ -0,0 +1,119 @@
+use rustdocs_mcp_server::item_source::{ItemSource, find_item_source, format_item_source};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write(path: &Path, html: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, html).unwrap();
+}
+
+/// An item page whose heading links to its source, with a method documented below
+fn item_page(heading_link: &str, method_link: &str) -> String {
+    format!(
+        r#"<html><body><section id="main-content" class="content">
+<div class="main-heading"><h1>Item</h1><rustdoc-toolbar></rustdoc-toolbar>
+<span class="sub-heading"><a class="src" href="{}">Source</a></span></div>
+<details><section id="method.new" class="method"><a class="src rightside" href="{}">Source</a>
+<h4 class="code-header">pub fn new() -&gt; Self</h4></section></details>
+</section></body></html>"#,
+        heading_link, method_link
+    )
+}
+
+/// A source page in the current rustdoc layout, with inline line numbers
+fn source_page(code: &[&str]) -> String {
+    let lines: Vec<String> = code
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            format!(
+                "<a href=#{n} id={n} data-nosnippet>{n}</a>{}",
+                line,
+                n = i + 1
+            )
+        })
+        .collect();
+    format!(
+        r#"<html><body><pre class="rust"><code>{}</code></pre></body></html>"#,
+        lines.join("\n")
+    )
+}
+
+fn write_fixture(doc_dir: &Path) {
+    write(
+        &doc_dir.join("demo").join("net").join("struct.Client.html"),
+        &item_page(
+            "../../src/demo/net/client.rs.html#2-4",
+            "../../src/demo/net/client.rs.html#7-9",
+        ),
+    );
+    write(
+        &doc_dir
+            .join("src")
+            .join("demo")
+            .join("net")
+            .join("client.rs.html"),
+        &source_page(&[
+            "<span class=\"doccomment\">/// A client.</span>",
+            "<span class=\"kw\">pub struct</span> Client {",
+            "    retries: u8,",
+            "}",
+            "",
+            "<span class=\"kw\">impl</span> Client {",
+            "    <span class=\"kw\">pub fn</span> new() -&gt; <span class=\"self\">Self</span> {",
+            "        Client { retries: 3 }",
+            "    }",
+            "}",
+        ]),
+    );
+}
+
+#[test]
+fn test_item_source_follows_source_links() {
+    let doc_dir = tempdir().unwrap();
+    write_fixture(doc_dir.path());
+
+    let client = find_item_source(doc_dir.path(), "demo", "Client", 100)
+        .unwrap()
+        .expect("Client should be found");
+    assert_eq!(
+        client,
+        ItemSource {
+            item: "demo::net::Client".to_string(),
+            file: "net/client.rs".to_string(),
+            lines: (2, 4),
+            code: "pub struct Client {\n    retries: u8,\n}".to_string(),
+            truncated: false,
+        }
+    );
+    assert_eq!(
+        find_item_source(doc_dir.path(), "demo", "demo::net::Client", 100).unwrap(),
+        Some(client)
+    );
+
+    // Methods are found on their type's page
+    let new = find_item_source(doc_dir.path(), "demo", "Client::new", 2)
+        .unwrap()
+        .expect("Client::new should be found");
+    assert_eq!(new.item, "demo::net::Client::new");
+    assert_eq!(new.lines, (7, 9));
+    assert_eq!(
+        new.code,
+        "    pub fn new() -> Self {\n        Client { retries: 3 }"
+    );
+    assert!(new.truncated);
+    assert_eq!(
+        format_item_source(&new),
+        "Source of `demo::net::Client::new` (net/client.rs, lines 7-9):\n\n```rust\n    pub fn new() -> Self {\n        Client { retries: 3 }\n```\n\nTruncated after 2 lines."
+    );
+
+    assert_eq!(
+        find_item_source(doc_dir.path(), "demo", "Server", 100).unwrap(),
+        None
+    );
+    assert_eq!(
+        find_item_source(doc_dir.path(), "demo", "Client::missing", 100).unwrap(),
+        None
+    );
+}
@@ -0,0 +1,38 @@
+use rustdocs_mcp_server::item_status::ItemStatus;
+
+const DEPRECATED_PAGE: &str = r##"<html><body><section id="main-content" class="content">
+<div class="main-heading"><h1>Function <span class="fn">connect_old</span></h1></div>
+<pre class="rust item-decl"><code>pub fn connect_old()</code></pre>
+<span class="item-info"><div class="stab deprecated"><span class="emoji">👎</span><span>Deprecated since 1.2.0: use <code>connect</code> instead</span></div><div class="stab portability">Available on <strong>crate feature <code>net</code></strong> only.</div></span>
+<details class="toggle method-toggle" open><summary><section id="method.inner" class="method"><h4 class="code-header">pub fn inner()</h4></section></summary>
+<span class="item-info"><div class="stab unstable"><span class="emoji">🔬</span><span>This is a nightly-only experimental API.</span></div></span></details>
+</section></body></html>"##;
+
+#[test]
+fn test_reads_the_banners_of_the_page_item() {
+    let status = ItemStatus::from_html(DEPRECATED_PAGE);
+    assert_eq!(
+        status,
+        ItemStatus {
+            deprecated: Some("Deprecated since 1.2.0: use connect instead".to_string()),
+            // The method's banner isn't the item's
+            unstable: None,
+            availability: Some("Available on crate feature net only.".to_string()),
+        }
+    );
+    assert_eq!(
+        status.notes(),
+        vec![
+            "Deprecated since 1.2.0: use connect instead",
+            "Available on crate feature net only."
+        ]
+    );
+}
+
+#[test]
+fn test_pages_without_banners() {
+    let page = r#"<section id="main-content" class="content"><p>Plain.</p></section>"#;
+    let status = ItemStatus::from_html(page);
+    assert!(status.is_empty());
+    assert!(status.notes().is_empty());
+}
@@ -0,0 +1,105 @@
+use rustdocs_mcp_server::identifier_boost;
+use rustdocs_mcp_server::keyword_index::{KeywordIndex, terms};
+use tempfile::tempdir;
+
+const DOCUMENTS: [(&str, &str); 4] = [
+    (
+        "struct.Widget.html",
+        "A Widget is created with Widget::new and cleared with reset_all.",
+    ),
+    (
+        "fn.resize.html",
+        "Calls demo::Widget::resize to change the size of a Widget.",
+    ),
+    (
+        "trait.Draw.html",
+        "The Widget trait is implemented for every Widget.",
+    ),
+    ("config.html", "Configuration is read from TOML files."),
+];
+
+#[test]
+fn test_terms_include_paths_and_their_parts() {
+    let found: Vec<String> = terms("Use tokio::select! or `a:b`, not 9lives.").collect();
+    assert_eq!(
+        found,
+        vec![
+            "Use",
+            "tokio",
+            "select",
+            "tokio::select",
+            "or",
+            "a",
+            "b",
+            "not"
+        ]
+    );
+
+    let found: Vec<String> = terms("demo::Widget::resize").collect();
+    for term in ["demo::Widget", "Widget::resize", "demo::Widget::resize"] {
+        assert!(found.iter().any(|t| t == term), "{} in {:?}", term, found);
+    }
+}
+
+#[test]
+fn test_lookups_use_whole_words_and_paths() {
+    let index = KeywordIndex::new(DOCUMENTS);
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.document_frequency("Widget"), 3);
+    assert!(index.contains("struct.Widget.html", "reset_all"));
+    assert!(!index.contains("struct.Widget.html", "reset"));
+    assert!(index.contains("fn.resize.html", "Widget::resize"));
+    assert!(!index.contains("missing.html", "Widget"));
+    assert!(index.idf("reset_all") > index.idf("Widget"));
+    assert_eq!(index.idf("demo::reset_all"), index.idf("reset_all"));
+    assert_eq!(KeywordIndex::default().idf("Widget"), 0.0);
+}
+
+#[test]
+fn test_search_ranks_by_bm25() {
+    let index = KeywordIndex::new(DOCUMENTS);
+
+    let hits = index.search("How do I call reset_all on my Widget?", 10);
+    assert_eq!(hits[0].0, "struct.Widget.html");
+    assert_eq!(hits.len(), 3);
+    assert!(hits.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+    assert_eq!(index.search("Widget", 1).len(), 1);
+    assert!(index.search("nothing matches", 10).is_empty());
+}
+
+#[test]
+fn test_indexed_score_matches_the_text_scan() {
+    let index = KeywordIndex::new(DOCUMENTS);
+    let identifiers = vec!["Widget".to_string(), "reset_all".to_string()];
+
+    let both = identifier_boost::indexed_score(&index, &identifiers, "struct.Widget.html", None);
+    let common = identifier_boost::indexed_score(&index, &identifiers, "fn.resize.html", None);
+    assert!(both > common && common > 0.0);
+    assert_eq!(
+        identifier_boost::indexed_score(&index, &identifiers, "config.html", None),
+        0.0
+    );
+
+    // The item path counts even if the text doesn't mention the name
+    let identifiers = vec!["resize".to_string()];
+    assert!(
+        identifier_boost::indexed_score(
+            &index,
+            &identifiers,
+            "config.html",
+            Some("demo::Widget::resize")
+        ) > 0.0
+    );
+}
+
+#[test]
+fn test_save_and_load_round_trip() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("keywords.bin");
+    assert_eq!(KeywordIndex::load(&path).unwrap(), None);
+
+    let index = KeywordIndex::new(DOCUMENTS);
+    index.save(&path).unwrap();
+    assert_eq!(KeywordIndex::load(&path).unwrap(), Some(index));
+}
@@ -0,0 +1,30 @@
+use rustdocs_mcp_server::logging::{self, MCP_LOG_TARGET};
+use std::fs;
+use tempfile::tempdir;
+
+// The subscriber is process-global, so everything is checked in a single test
+#[tokio::test]
+async fn test_init_writes_file_and_mirrors_warnings() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let log_file = temp_dir.path().join("server.log");
+
+    assert!(logging::init("not a [valid filter", None).is_err());
+    logging::init("debug", Some(&log_file)).expect("Logging should initialize");
+    let mut events = logging::take_mirrored_events().expect("Mirror stream should exist");
+    assert!(logging::take_mirrored_events().is_none());
+
+    tracing::info!("informational message");
+    tracing::warn!(crate_name = "serde", "loading failed");
+    tracing::warn!(target: MCP_LOG_TARGET, "delivery failure");
+
+    let event = events.try_recv().expect("Warning should be mirrored");
+    assert_eq!(event.message, "loading failed crate_name=\"serde\"");
+    assert!(
+        events.try_recv().is_err(),
+        "Info events and MCP delivery events are not mirrored"
+    );
+
+    let contents = fs::read_to_string(&log_file).unwrap();
+    assert!(contents.contains("informational message"));
+    assert!(contents.contains("loading failed"));
+}
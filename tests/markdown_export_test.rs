@@ -0,0 +1,90 @@
+use rustdocs_mcp_server::{
+    Document,
+    markdown_export::{render_crate_overview, render_llms_txt},
+};
+
+fn doc(path: &str, content: &str) -> Document {
+    Document {
+        path: path.to_string(),
+        content: content.to_string(),
+    }
+}
+
+#[test]
+fn test_render_groups_pages_by_item_kind() {
+    let documents = vec![
+        doc("fn.from_str.html", "Deserialize an instance from a string."),
+        doc("index.html", "A serialization framework."),
+        doc("de/struct.Error.html", "Errors raised while deserializing."),
+        doc("struct.Value.html", "Any valid JSON value."),
+        doc("de/index.html", "Deserialization support."),
+        doc("all.html", "List of all items."),
+    ];
+
+    let markdown = render_llms_txt("demo", &documents);
+
+    assert!(markdown.starts_with("# demo\n\n> API documentation for the Rust crate `demo`"));
+    let position = |needle: &str| {
+        markdown
+            .find(needle)
+            .unwrap_or_else(|| panic!("missing {:?} in:\n{}", needle, markdown))
+    };
+    let order = [
+        "## Overview\n\nA serialization framework.",
+        "## Modules",
+        "### `demo::de`\n\nDeserialization support.",
+        "## Structs",
+        "### `demo::Value`",
+        "### `demo::de::Error`",
+        "## Functions",
+        "### `demo::from_str`",
+        "## Other Pages",
+        "### `demo::all`",
+    ];
+    for pair in order.windows(2) {
+        assert!(
+            position(pair[0]) < position(pair[1]),
+            "{:?} before {:?}",
+            pair[0],
+            pair[1]
+        );
+    }
+}
+
+#[test]
+fn test_crate_overview_lists_modules_and_counts_items() {
+    let documents = vec![
+        doc("index.html", "A serialization framework."),
+        doc("de/index.html", "Deserialization support."),
+        doc("de/struct.Error.html", "Errors raised while deserializing."),
+        doc("struct.Value.html", "Any valid JSON value."),
+        doc("fn.from_str.html", "Deserialize an instance from a string."),
+    ];
+
+    let overview = render_crate_overview("demo", Some("1.2.3"), &documents);
+
+    assert!(overview.starts_with("# demo 1.2.3\n\n"), "{}", overview);
+    assert!(
+        overview.contains("3 documented items in 2 modules"),
+        "{}",
+        overview
+    );
+    assert!(overview.contains("`crate://demo/llms.txt`"), "{}", overview);
+    assert!(
+        overview.contains("## Overview\n\nA serialization framework.\n"),
+        "{}",
+        overview
+    );
+    assert!(
+        overview.contains("## Modules\n\n- `demo`\n- `demo::de`\n"),
+        "{}",
+        overview
+    );
+    assert!(
+        overview.contains("| Structs | 2 |\n| Functions | 1 |\n"),
+        "{}",
+        overview
+    );
+    // Page text other than the front page is left to llms.txt
+    assert!(!overview.contains("Any valid JSON value."), "{}", overview);
+}
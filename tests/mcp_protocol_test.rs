@@ -0,0 +1,641 @@
+use rmcp::{
+    ClientHandler, Error as McpError, Peer, RoleClient, ServiceExt,
+    model::{
+        CallToolRequestParam, ClientCapabilities, ClientInfo, ListRootsResult,
+        ReadResourceRequestParam, ResourceContents, ResourceUpdatedNotificationParam, Root,
+        SubscribeRequestParam, UnsubscribeRequestParam,
+    },
+    service::{RequestContext, RunningService},
+};
+use rustdocs_mcp_server::{
+    RustDocsServer,
+    config::{Config, ProjectSettings},
+    mock::MOCK_PROVIDER,
+};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use tempfile::{TempDir, tempdir};
+use tokio::sync::mpsc;
+
+/// A server with mock providers and fixture docs, connected to an rmcp client
+/// over an in-process duplex stream instead of stdio
+struct McpHarness {
+    client: RunningService<RoleClient, ()>,
+    // Holds the fixture docs and caches for the lifetime of the test
+    _root: TempDir,
+}
+
+impl McpHarness {
+    /// Starts the server over a duplex transport and completes the
+    /// initialize handshake from the client side
+    async fn start() -> Self {
+        let root = tempdir().expect("Failed to create temporary directory");
+        let doc_dir = root.path().join("doc");
+        write_fixture_crate(&doc_dir.join("demo"));
+
+        let mut config = Config::default();
+        config.embedding.provider = MOCK_PROVIDER.to_string();
+        config.llm.provider = MOCK_PROVIDER.to_string();
+        config.docs.dir = doc_dir;
+        config.docs.project_dir = root.path().to_path_buf();
+        config.cache.dir = Some(root.path().join("chunks"));
+        config.cache.global_dir = Some(root.path().join("global"));
+        let server = RustDocsServer::with_config("Fixture server ready".to_string(), config)
+            .expect("Failed to create server");
+
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let running = server
+                .serve(tokio::io::split(server_io))
+                .await
+                .expect("Server failed to start");
+            let _ = running.waiting().await;
+        });
+        let client =
+            ().serve(tokio::io::split(client_io))
+                .await
+                .expect("Client failed to initialize");
+
+        Self {
+            client,
+            _root: root,
+        }
+    }
+}
+
+fn write_fixture_crate(crate_dir: &Path) {
+    fs::create_dir_all(crate_dir).unwrap();
+    for (file, text) in [
+        ("index.html", "The demo crate builds gadgets and widgets."),
+        (
+            "struct.Widget.html",
+            "Widget is created with Widget::new and resized with Widget::resize.",
+        ),
+        (
+            "fn.parse_config.html",
+            "parse_config reads TOML configuration files from disk.",
+        ),
+    ] {
+        let html = format!(
+            "<html><body><section id=\"main-content\" class=\"content\"><p>{}</p></section></body></html>",
+            text
+        );
+        fs::write(crate_dir.join(file), html).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_protocol_exchange_with_mock_providers() {
+    let harness = McpHarness::start().await;
+    let client = &harness.client;
+
+    let info = client.peer_info();
+    assert_eq!(info.server_info.name, "rust-docs-mcp-server");
+    assert!(info.capabilities.tools.is_some());
+    assert!(info.capabilities.resources.is_some());
+
+    let tools = client.list_all_tools().await.expect("tools/list failed");
+    let mut names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        vec![
+            "find_item",
+            "index_status",
+            "list_crate_features",
+            "query_rust_docs",
+            "query_rust_docs_batch"
+        ]
+    );
+    // Every tool but the cross-crate search takes a crate
+    for tool in tools.iter().filter(|tool| tool.name != "find_item") {
+        assert!(tool.input_schema["properties"]["crate_name"].is_object());
+    }
+
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: "find_item".into(),
+            arguments: json!({ "item": "Widget" }).as_object().cloned(),
+        })
+        .await
+        .expect("tools/call failed");
+    let text = &result.content[0]
+        .raw
+        .as_text()
+        .expect("find_item returns text")
+        .text;
+    assert!(text.contains("- `demo::Widget` (struct)"), "{}", text);
+
+    let resources = client
+        .list_all_resources()
+        .await
+        .expect("resources/list failed");
+    let uris: Vec<&str> = resources.iter().map(|r| r.uri.as_str()).collect();
+    assert_eq!(uris, vec!["crate://demo"]);
+    let read = client
+        .read_resource(ReadResourceRequestParam {
+            uri: "crate://demo".to_string(),
+        })
+        .await
+        .expect("resources/read failed");
+    let ResourceContents::TextResourceContents {
+        mime_type, text, ..
+    } = &read.contents[0]
+    else {
+        panic!("crate overview should be text");
+    };
+    assert_eq!(mime_type.as_deref(), Some("text/markdown"));
+    assert!(text.starts_with("# demo"), "{}", text);
+    assert!(text.contains("## Modules\n\n- `demo`"), "{}", text);
+    assert!(text.contains("| Structs | 1 |"), "{}", text);
+
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: "query_rust_docs".into(),
+            arguments: json!({ "crate_name": "demo", "question": "How do I resize a Widget?" })
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .expect("tools/call failed");
+    assert_ne!(result.is_error, Some(true));
+    let text = &result.content[0]
+        .raw
+        .as_text()
+        .expect("query_rust_docs returns text")
+        .text;
+    assert!(text.starts_with("From demo docs: Mock answer"), "{}", text);
+    assert!(text.contains("struct.Widget.html"), "{}", text);
+}
+
+#[tokio::test]
+async fn test_crate_docs_are_readable_as_markdown() {
+    let harness = McpHarness::start().await;
+    let client = &harness.client;
+
+    let templates = client
+        .list_all_resource_templates()
+        .await
+        .expect("resources/templates/list failed");
+    assert_eq!(
+        templates[0].raw.uri_template,
+        "crate://{crate_name}/llms.txt"
+    );
+
+    let read = client
+        .read_resource(ReadResourceRequestParam {
+            uri: "crate://demo/llms.txt".to_string(),
+        })
+        .await
+        .expect("resources/read failed");
+    let ResourceContents::TextResourceContents {
+        mime_type, text, ..
+    } = &read.contents[0]
+    else {
+        panic!("llms.txt should be text");
+    };
+    assert_eq!(mime_type.as_deref(), Some("text/markdown"));
+    assert!(text.starts_with("# demo\n"), "{}", text);
+    assert!(text.contains("### `demo::Widget`"), "{}", text);
+}
+
+#[tokio::test]
+async fn test_batch_queries_answer_each_question_in_order() {
+    let harness = McpHarness::start().await;
+
+    let result = harness
+        .client
+        .call_tool(CallToolRequestParam {
+            name: "query_rust_docs_batch".into(),
+            arguments: json!({
+                "crate_name": "demo",
+                "questions": [
+                    { "question": "How do I resize a Widget?" },
+                    { "question": "What is this?", "crate_name": "missing" },
+                    { "question": "How do I create a Widget?" },
+                ],
+            })
+            .as_object()
+            .cloned(),
+        })
+        .await
+        .expect("tools/call failed");
+    assert_ne!(result.is_error, Some(true));
+    let texts: Vec<&str> = result
+        .content
+        .iter()
+        .map(|content| {
+            content
+                .raw
+                .as_text()
+                .expect("answers are text")
+                .text
+                .as_str()
+        })
+        .collect();
+    assert_eq!(texts.len(), 3);
+    assert!(
+        texts[0].starts_with("Question 1: How do I resize a Widget?\n\nFrom demo docs:"),
+        "{}",
+        texts[0]
+    );
+    // One failing question doesn't fail the others
+    assert!(
+        texts[1].starts_with("Question 2: What is this?\n\nError:"),
+        "{}",
+        texts[1]
+    );
+    assert!(
+        texts[2].contains("for: How do I create a Widget?"),
+        "{}",
+        texts[2]
+    );
+
+    let error = harness
+        .client
+        .call_tool(CallToolRequestParam {
+            name: "query_rust_docs_batch".into(),
+            arguments: json!({ "questions": [{ "question": "Which crate?" }] })
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .expect_err("Questions without a crate must be rejected");
+    assert!(error.to_string().contains("no crate_name"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_invalid_tool_arguments_return_protocol_errors() {
+    let harness = McpHarness::start().await;
+
+    let error = harness
+        .client
+        .call_tool(CallToolRequestParam {
+            name: "query_rust_docs".into(),
+            arguments: json!({ "crate_name": "../etc", "question": "Anything?" })
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .expect_err("Invalid crate names must be rejected");
+    assert!(
+        error.to_string().contains("Invalid crate name"),
+        "{}",
+        error
+    );
+
+    // Opt-in tools can't be called unless enabled
+    let error = harness
+        .client
+        .call_tool(CallToolRequestParam {
+            name: "get_source".into(),
+            arguments: json!({ "crate_name": "demo", "item": "Widget" })
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .expect_err("Disabled tools must be rejected");
+    assert!(error.to_string().contains("not enabled"), "{}", error);
+}
+
+/// A client reporting a single workspace root, like an editor with one folder open
+#[derive(Clone)]
+struct RootsClient {
+    root_uri: String,
+}
+
+impl ClientHandler for RootsClient {
+    async fn list_roots(
+        &self,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<ListRootsResult, McpError> {
+        Ok(ListRootsResult {
+            roots: vec![Root {
+                uri: self.root_uri.clone(),
+                name: Some("project".to_string()),
+            }],
+        })
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            capabilities: ClientCapabilities::builder().enable_roots().build(),
+            ..ClientInfo::default()
+        }
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleClient>> {
+        None
+    }
+
+    fn set_peer(&mut self, _peer: Peer<RoleClient>) {}
+}
+
+#[tokio::test]
+async fn test_doc_paths_resolve_against_client_roots() {
+    // The default `./target/doc` must be found in the client's project,
+    // not in the directory the server was started from
+    let project = tempdir().expect("Failed to create temporary directory");
+    fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"roots_fixture\"\nversion = \"0.1.0\"\n",
+    )
+    .unwrap();
+    write_fixture_crate(&project.path().join("target").join("doc").join("demo"));
+
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.poll_interval_secs = 0;
+    config.cache.dir = Some(project.path().join("chunks"));
+    config.cache.global_dir = Some(project.path().join("global"));
+    let server = RustDocsServer::with_config("Fixture server ready".to_string(), config)
+        .expect("Failed to create server");
+    let handle = server.clone();
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let running = server
+            .serve(tokio::io::split(server_io))
+            .await
+            .expect("Server failed to start");
+        let _ = running.waiting().await;
+    });
+    let client = RootsClient {
+        root_uri: format!("file://{}", project.path().display()),
+    }
+    .serve(tokio::io::split(client_io))
+    .await
+    .expect("Client failed to initialize");
+
+    // The server asks for the roots once the handshake completes
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(10);
+    while handle.project_root().is_none() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "Server never adopted the client's root"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(
+        handle.project_root().unwrap().canonicalize().unwrap(),
+        project.path().canonicalize().unwrap()
+    );
+
+    let resources = client
+        .list_all_resources()
+        .await
+        .expect("resources/list failed");
+    assert!(
+        resources
+            .iter()
+            .any(|resource| resource.uri == "crate://demo"),
+        "{:?}",
+        resources
+    );
+}
+
+/// A client forwarding the URIs of resource update notifications
+#[derive(Clone)]
+struct UpdatesClient {
+    updates: mpsc::UnboundedSender<String>,
+}
+
+impl ClientHandler for UpdatesClient {
+    async fn on_resource_updated(&self, params: ResourceUpdatedNotificationParam) {
+        let _ = self.updates.send(params.uri);
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleClient>> {
+        None
+    }
+
+    fn set_peer(&mut self, _peer: Peer<RoleClient>) {}
+}
+
+#[tokio::test]
+async fn test_subscribers_hear_when_a_crate_is_reindexed() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let doc_dir = root.path().join("doc");
+    write_fixture_crate(&doc_dir.join("demo"));
+
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = doc_dir;
+    config.docs.project_dir = root.path().to_path_buf();
+    config.docs.poll_interval_secs = 0;
+    config.cache.dir = Some(root.path().join("chunks"));
+    config.cache.global_dir = Some(root.path().join("global"));
+    let server = RustDocsServer::with_config("Fixture server ready".to_string(), config)
+        .expect("Failed to create server");
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let running = server
+            .serve(tokio::io::split(server_io))
+            .await
+            .expect("Server failed to start");
+        let _ = running.waiting().await;
+    });
+    let (updates, mut received) = mpsc::unbounded_channel();
+    let client = UpdatesClient { updates }
+        .serve(tokio::io::split(client_io))
+        .await
+        .expect("Client failed to initialize");
+
+    let resources = client.peer_info().capabilities.resources.clone().unwrap();
+    assert_eq!(resources.subscribe, Some(true));
+    assert!(
+        client
+            .subscribe(SubscribeRequestParam {
+                uri: "file:///demo".to_string(),
+            })
+            .await
+            .is_err()
+    );
+    for uri in ["crate://demo", "crate://demo/llms.txt", "crate://other"] {
+        client
+            .subscribe(SubscribeRequestParam {
+                uri: uri.to_string(),
+            })
+            .await
+            .expect("resources/subscribe failed");
+    }
+    client
+        .unsubscribe(UnsubscribeRequestParam {
+            uri: "crate://demo/llms.txt".to_string(),
+        })
+        .await
+        .expect("resources/unsubscribe failed");
+
+    // Indexing the crate for a query rebuilds its index
+    client
+        .call_tool(CallToolRequestParam {
+            name: "query_rust_docs".into(),
+            arguments: json!({ "crate_name": "demo", "question": "How do I resize a Widget?" })
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .expect("tools/call failed");
+
+    let uri = tokio::time::timeout(std::time::Duration::from_secs(10), received.recv())
+        .await
+        .expect("no resource update received")
+        .unwrap();
+    assert_eq!(uri, "crate://demo");
+    assert!(received.try_recv().is_err());
+}
+
+/// A client counting resource list change notifications
+#[derive(Clone)]
+struct ListChangedClient {
+    changes: mpsc::UnboundedSender<()>,
+}
+
+impl ClientHandler for ListChangedClient {
+    async fn on_resource_list_changed(&self) {
+        let _ = self.changes.send(());
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleClient>> {
+        None
+    }
+
+    fn set_peer(&mut self, _peer: Peer<RoleClient>) {}
+}
+
+#[tokio::test]
+async fn test_clients_hear_when_documented_crates_change() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let doc_dir = root.path().join("doc");
+    write_fixture_crate(&doc_dir.join("demo"));
+
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = doc_dir.clone();
+    config.docs.project_dir = root.path().to_path_buf();
+    config.cache.dir = Some(root.path().join("chunks"));
+    config.cache.global_dir = Some(root.path().join("global"));
+    let server = RustDocsServer::with_config("Fixture server ready".to_string(), config)
+        .expect("Failed to create server");
+    server.spawn_resource_monitor(std::time::Duration::from_millis(50));
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let running = server
+            .serve(tokio::io::split(server_io))
+            .await
+            .expect("Server failed to start");
+        let _ = running.waiting().await;
+    });
+    let (changes, mut received) = mpsc::unbounded_channel();
+    let _client = ListChangedClient { changes }
+        .serve(tokio::io::split(client_io))
+        .await
+        .expect("Client failed to initialize");
+
+    // Nothing changed since the monitor first listed the crates
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert!(received.try_recv().is_err());
+
+    // As after running `cargo doc` for another crate
+    write_fixture_crate(&doc_dir.join("other"));
+    tokio::time::timeout(std::time::Duration::from_secs(10), received.recv())
+        .await
+        .expect("no resource list change received")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_tool_schemas_describe_every_argument() {
+    let harness = McpHarness::start().await;
+    let tools = harness
+        .client
+        .list_all_tools()
+        .await
+        .expect("tools/list failed");
+
+    for tool in &tools {
+        assert!(
+            tool.description
+                .ends_with("calls are safe to repeat or retry."),
+            "{}: {}",
+            tool.name,
+            tool.description
+        );
+        let schema = tool.schema_as_json_value();
+        let properties = schema["properties"].as_object().unwrap();
+        for (name, property) in properties {
+            assert!(
+                property["description"].is_string(),
+                "{}.{} has no description",
+                tool.name,
+                name
+            );
+        }
+        let required = schema["required"].as_array().into_iter().flatten();
+        for required in required {
+            let property = &properties[required.as_str().unwrap()];
+            assert!(
+                property["minLength"] == 1 || property["minItems"] == 1,
+                "{}.{} accepts empty values",
+                tool.name,
+                required
+            );
+        }
+    }
+
+    let query = tools
+        .iter()
+        .find(|tool| tool.name == "query_rust_docs")
+        .unwrap();
+    let properties = &query.input_schema["properties"];
+    assert_eq!(properties["crate_name"]["examples"], json!(["tokio"]));
+    assert!(properties["question"]["examples"][0].is_string());
+
+    let batch = tools
+        .iter()
+        .find(|tool| tool.name == "query_rust_docs_batch")
+        .unwrap();
+    assert_eq!(
+        batch.input_schema["properties"]["questions"]["maxItems"],
+        10
+    );
+}
+
+#[tokio::test]
+async fn test_project_arguments_are_limited_to_configured_projects() {
+    let root = tempdir().unwrap();
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = root.path().join("doc");
+    config.cache.dir = Some(root.path().join("chunks"));
+    config.cache.global_dir = Some(root.path().join("global"));
+    let server = RustDocsServer::with_config("ready".to_string(), config.clone()).unwrap();
+    for tool in server.tools() {
+        assert!(
+            tool.input_schema["properties"]["project"]
+                .get("enum")
+                .is_none()
+        );
+    }
+
+    config.projects = vec![ProjectSettings {
+        name: "backend".to_string(),
+        dir: root.path().join("backend"),
+    }];
+    let server = RustDocsServer::with_config("ready".to_string(), config).unwrap();
+    for tool in server.tools() {
+        assert_eq!(
+            tool.input_schema["properties"]["project"]["enum"],
+            json!(["backend", null]),
+            "{}",
+            tool.name
+        );
+    }
+}
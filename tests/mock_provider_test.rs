@@ -0,0 +1,1055 @@
+use rustdocs_mcp_server::{
+    EmbeddingCacheService, RustDocsServer,
+    answer_format::AnswerFormat,
+    config::Config,
+    embeddings::cosine_similarity,
+    global_cache::CacheKey,
+    index_manifest::IndexManifest,
+    mock::{MOCK_PROVIDER, MockEmbeddingBackend},
+    server::QueryOptions,
+    telemetry::Telemetry,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+use tokio_util::sync::CancellationToken;
+
+fn write_page(crate_dir: &Path, file: &str, text: &str) {
+    let html = format!(
+        "<html><body><section id=\"main-content\" class=\"content\"><p>{}</p></section></body></html>",
+        text
+    );
+    fs::write(crate_dir.join(file), html).unwrap();
+}
+
+/// Pages of a `demo` crate documenting how to create and resize a `Widget`
+const WIDGET_PAGES: &[(&str, &str)] = &[
+    ("index.html", "The demo crate builds gadgets."),
+    (
+        "struct.Widget.html",
+        "Widget is created with Widget::new and resized with Widget::resize.",
+    ),
+];
+
+/// Writes `(file, text)` pages of a `demo` crate to `root/doc/demo` and
+/// returns that directory
+fn demo_crate(root: &Path, pages: &[(&str, &str)]) -> PathBuf {
+    let crate_dir = root.join("doc").join("demo");
+    fs::create_dir_all(&crate_dir).unwrap();
+    for (file, text) in pages {
+        write_page(&crate_dir, file, text);
+    }
+    crate_dir
+}
+
+/// A config answering with mock providers from the docs under `root/doc`,
+/// caching under `root`
+fn mock_config(root: &Path) -> Config {
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = root.join("doc");
+    config.docs.project_dir = root.to_path_buf();
+    config.cache.dir = Some(root.join("chunks"));
+    config.cache.global_dir = Some(root.join("global"));
+    config
+}
+
+#[test]
+fn test_mock_embeddings_are_deterministic_and_word_based() {
+    let backend = MockEmbeddingBackend::default();
+    let widget = backend.embed("Create a Widget with Widget::new");
+
+    assert_eq!(
+        widget.values,
+        backend.embed("Create a Widget with Widget::new").values
+    );
+    let similar = backend.embed("how do I create a widget");
+    let unrelated = backend.embed("parse TOML configuration files");
+    let score = |other: &rustdocs_mcp_server::Embedding| {
+        cosine_similarity(widget.to_array().view(), other.to_array().view())
+    };
+    assert!(score(&similar) > score(&unrelated));
+}
+
+#[tokio::test]
+async fn test_query_pipeline_runs_offline_with_mock_providers() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(
+        root.path(),
+        &[
+            ("index.html", "The demo crate builds gadgets and widgets."),
+            (
+                "struct.Widget.html",
+                "Widget is created with Widget::new and resized with Widget::resize.",
+            ),
+            (
+                "fn.parse_config.html",
+                "parse_config reads TOML configuration files from disk.",
+            ),
+        ],
+    );
+
+    let config = mock_config(root.path());
+    assert!(!config.requires_api_key());
+
+    let server = RustDocsServer::with_config("test".to_string(), config)
+        .expect("Mock providers shouldn't need an API key");
+    let answer = server
+        .answer_query(
+            "demo",
+            "How do I resize a Widget?",
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .expect("Query should succeed with mock providers");
+
+    assert!(
+        answer.starts_with("From demo docs: Mock answer about 'demo'"),
+        "{}",
+        answer
+    );
+    assert!(answer.contains("struct.Widget.html"), "{}", answer);
+    assert!(!answer.contains("fn.parse_config.html"), "{}", answer);
+    assert!(!answer.contains("Language:"), "{}", answer);
+    assert!(answer.contains("\n\nConfidence: "), "{}", answer);
+
+    // The answer language is passed to the chat provider, not added to the question
+    let answer = server
+        .answer_query(
+            "demo",
+            "How do I resize a Widget?",
+            Some("Japanese"),
+            &CancellationToken::new(),
+        )
+        .await
+        .expect("Query should succeed with mock providers");
+    assert!(
+        answer.contains("for: How do I resize a Widget?\n"),
+        "{}",
+        answer
+    );
+    assert!(answer.contains("\nLanguage: Japanese\n"), "{}", answer);
+}
+
+#[tokio::test]
+async fn test_answers_warn_when_docs_differ_from_the_locked_version() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let crate_dir = demo_crate(root.path(), &[]);
+    fs::write(
+        crate_dir.join("index.html"),
+        "<html><body><span class=\"version\">1.0.0</span>\
+         <section id=\"main-content\" class=\"content\"><p>The demo crate.</p></section></body></html>",
+    )
+    .unwrap();
+    let lockfile = |version: &str| {
+        fs::write(
+            root.path().join("Cargo.lock"),
+            format!("[[package]]\nname = \"demo\"\nversion = \"{}\"\n", version),
+        )
+        .unwrap()
+    };
+
+    let server = RustDocsServer::with_config("test".to_string(), mock_config(root.path())).unwrap();
+    let cancel = CancellationToken::new();
+
+    lockfile("1.0.0");
+    let answer = server
+        .answer_query("demo", "What is demo?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(answer.starts_with("From demo docs:"), "{}", answer);
+
+    lockfile("1.2.0");
+    let answer = server
+        .answer_query("demo", "What is demo?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(
+        answer.starts_with(
+            "Warning: these docs were generated for demo 1.0.0, but Cargo.lock resolves 1.2.0."
+        ),
+        "{}",
+        answer
+    );
+    assert!(answer.contains("`cargo doc --package demo`"), "{}", answer);
+}
+
+#[tokio::test]
+async fn test_projects_are_indexed_separately() {
+    use rustdocs_mcp_server::config::ProjectSettings;
+
+    let root = tempdir().expect("Failed to create temporary directory");
+    let mut projects = Vec::new();
+    for (name, page, text) in [
+        (
+            "api",
+            "struct.Client.html",
+            "Client sends requests to the API.",
+        ),
+        (
+            "cli",
+            "fn.run.html",
+            "run parses arguments and runs the CLI.",
+        ),
+    ] {
+        // Both projects document a crate called `shared`, with different items
+        let project_dir = root.path().join(name);
+        let crate_dir = project_dir.join("target").join("doc").join("shared");
+        fs::create_dir_all(&crate_dir).unwrap();
+        write_page(&crate_dir, "index.html", "The shared crate.");
+        write_page(&crate_dir, page, text);
+        projects.push(ProjectSettings {
+            name: name.to_string(),
+            dir: project_dir,
+        });
+    }
+
+    let mut config = mock_config(root.path());
+    // Each project's docs are found relative to its own directory
+    config.docs = Default::default();
+    config.projects = projects;
+    config.validate().expect("Projects should be valid");
+
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let cancel = CancellationToken::new();
+    let api = server
+        .for_project("api")
+        .unwrap()
+        .answer_query(
+            "shared",
+            "How does the Client send requests?",
+            None,
+            &cancel,
+        )
+        .await
+        .expect("Query in the api project should succeed");
+    assert!(api.contains("struct.Client.html"), "{}", api);
+    let cli = server
+        .for_project("cli")
+        .unwrap()
+        .answer_query("shared", "How does run parse arguments?", None, &cancel)
+        .await
+        .expect("Query in the cli project should succeed");
+    assert!(cli.contains("fn.run.html"), "{}", cli);
+
+    let error = server
+        .for_project("web")
+        .err()
+        .expect("Unknown projects should be rejected");
+    assert!(error.message.contains("Unknown project 'web'"));
+    assert_eq!(
+        error.data.unwrap()["available_projects"],
+        serde_json::json!(["api", "cli"])
+    );
+}
+
+#[tokio::test]
+async fn test_question_embeddings_are_cached_by_normalized_text() {
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.embedding.question_cache_size = 2;
+    let service = EmbeddingCacheService::with_config(String::new(), &config).unwrap();
+
+    let first = service
+        .get_question_embedding("How do I create a Widget?")
+        .await
+        .unwrap();
+    let repeated = service
+        .get_question_embedding("  How do I\n create a   Widget? ")
+        .await
+        .unwrap();
+    assert_eq!(first.values, repeated.values);
+    assert_eq!(service.cached_question_count(), 1);
+
+    service.get_question_embedding("second").await.unwrap();
+    service.get_question_embedding("third").await.unwrap();
+    assert_eq!(service.cached_question_count(), 2);
+
+    config.embedding.question_cache_size = 0;
+    let uncached = EmbeddingCacheService::with_config(String::new(), &config).unwrap();
+    uncached.get_question_embedding("first").await.unwrap();
+    assert_eq!(uncached.cached_question_count(), 0);
+}
+
+#[tokio::test]
+async fn test_index_manifest_tracks_the_inputs_of_each_index() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(
+        root.path(),
+        &[("index.html", "The demo crate builds widgets.")],
+    );
+
+    let mut config = mock_config(root.path());
+    let cancel = CancellationToken::new();
+
+    let server = RustDocsServer::with_config("test".to_string(), config.clone()).unwrap();
+    assert!(
+        server
+            .describe_indexes(None)
+            .unwrap()
+            .starts_with("No crate is indexed yet")
+    );
+    assert!(server.describe_indexes(Some("demo")).is_err());
+
+    server
+        .answer_query("demo", "What is demo?", None, &cancel)
+        .await
+        .unwrap();
+    let status = server.describe_indexes(Some("demo")).unwrap();
+    assert!(status.starts_with("Crate: demo\nDocuments: 1 (content sha256 "));
+    assert!(
+        status.contains("Chunker: min 1000, target 4000, max 8000 bytes"),
+        "{}",
+        status
+    );
+    assert!(status.contains("Embeddings: mock (mock)"), "{}", status);
+    assert!(
+        status.contains(&format!("Server version: {}", env!("CARGO_PKG_VERSION"))),
+        "{}",
+        status
+    );
+    assert!(status.ends_with("Status: current"), "{}", status);
+    assert_eq!(server.describe_indexes(None).unwrap(), status);
+
+    let manifest_path = root
+        .path()
+        .join("global")
+        .join("demo")
+        .join("local")
+        .join(CacheKey::local("demo").features_hash())
+        .join("manifest.json");
+    let saved = IndexManifest::load(&manifest_path).unwrap().unwrap();
+    assert_eq!(saved.chunker.target_chunk_size, 4000);
+
+    // Changed chunker settings rebuild the index and its manifest
+    config.chunker.target_chunk_size = 2000;
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    server
+        .answer_query("demo", "What is demo?", None, &cancel)
+        .await
+        .unwrap();
+    let rebuilt = IndexManifest::load(&manifest_path).unwrap().unwrap();
+    assert_eq!(rebuilt.changes_since(&saved), vec!["chunker settings"]);
+    assert!(
+        server
+            .describe_indexes(Some("demo"))
+            .unwrap()
+            .contains("target 2000")
+    );
+}
+
+#[tokio::test]
+async fn test_query_options_override_retrieval_settings() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(
+        root.path(),
+        &[
+            ("index.html", "The demo crate builds widgets."),
+            (
+                "struct.Widget.html",
+                "Widget is resized with Widget::resize.",
+            ),
+            ("fn.parse_config.html", "parse_config reads TOML files."),
+        ],
+    );
+
+    let mut config = mock_config(root.path());
+    config.retrieval.max_top_k = 2;
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let cancel = CancellationToken::new();
+    let sources = |answer: &str| {
+        let line = answer
+            .lines()
+            .find(|line| line.starts_with("Sources: "))
+            .unwrap();
+        line.split(", ").count()
+    };
+
+    let answer = server
+        .answer_query("demo", "How do I resize a Widget?", None, &cancel)
+        .await
+        .unwrap();
+    assert_eq!(sources(&answer), 1, "{}", answer);
+
+    // More documents may be asked for, up to `retrieval.max_top_k`
+    let options = QueryOptions {
+        top_k: Some(5),
+        ..QueryOptions::default()
+    };
+    let answer = server
+        .answer_query_with("demo", "How do I resize a Widget?", &options, &cancel)
+        .await
+        .unwrap();
+    assert_eq!(sources(&answer), 2, "{}", answer);
+
+    let options = QueryOptions {
+        max_context_tokens: Some(0),
+        ..QueryOptions::default()
+    };
+    assert!(
+        server
+            .answer_query_with("demo", "How do I resize a Widget?", &options, &cancel)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_answers_are_returned_in_the_requested_format() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(
+        root.path(),
+        &[("index.html", "The demo crate builds widgets.")],
+    );
+
+    let server = RustDocsServer::with_config("test".to_string(), mock_config(root.path())).unwrap();
+    let cancel = CancellationToken::new();
+
+    let options = QueryOptions {
+        format: AnswerFormat::Json,
+        ..QueryOptions::default()
+    };
+    let answer = server
+        .answer_query_with("demo", "What is demo?", &options, &cancel)
+        .await
+        .unwrap();
+    let value: serde_json::Value = serde_json::from_str(&answer).unwrap();
+    assert_eq!(value["crate"], "demo");
+    assert!(
+        value["answer"]
+            .as_str()
+            .unwrap()
+            .contains("Sources: index.html"),
+        "{}",
+        answer
+    );
+
+    let options = QueryOptions {
+        format: AnswerFormat::Markdown,
+        ..QueryOptions::default()
+    };
+    let answer = server
+        .answer_query_with("demo", "What is demo?", &options, &cancel)
+        .await
+        .unwrap();
+    assert!(answer.starts_with("## From demo docs\n\n"), "{}", answer);
+}
+
+#[tokio::test]
+async fn test_extractive_answers_quote_the_documentation() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(
+        root.path(),
+        &[
+            (
+                "struct.Widget.html",
+                "Widgets are drawn on a canvas. A widget is resized with Widget::resize.",
+            ),
+            ("index.html", "The demo crate."),
+        ],
+    );
+
+    let mut config = mock_config(root.path());
+    config.llm.extractive = true;
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let cancel = CancellationToken::new();
+
+    let answer = server
+        .answer_query("demo", "How is a widget resized?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(!answer.contains("Mock answer"), "{}", answer);
+    assert!(answer.contains("## struct.Widget.html\n\n>"), "{}", answer);
+    assert!(
+        answer.contains("> A **widget** is **resized** with Widget::resize."),
+        "{}",
+        answer
+    );
+
+    // Queries may still ask for a generated answer
+    let options = QueryOptions {
+        extractive: Some(false),
+        ..QueryOptions::default()
+    };
+    let answer = server
+        .answer_query_with("demo", "How is a widget resized?", &options, &cancel)
+        .await
+        .unwrap();
+    assert!(answer.contains("Mock answer"), "{}", answer);
+}
+
+#[tokio::test]
+async fn test_large_pages_are_indexed_in_the_background() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let sections: Vec<String> = (0..100)
+        .map(|i| format!("Gadget::tune_{i} tunes a gadget to frequency {i}."))
+        .collect();
+    demo_crate(
+        root.path(),
+        &[
+            ("index.html", "The demo crate builds gadgets and widgets."),
+            ("struct.Gadget.html", &sections.join(" ")),
+        ],
+    );
+
+    let mut config = mock_config(root.path());
+    config.docs.large_document_kb = 1;
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let cancel = CancellationToken::new();
+
+    // The first answer comes from the small pages, noting the large one
+    let answer = server
+        .answer_query("demo", "How do I tune a gadget?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(
+        answer.contains("1 large documents are still being indexed"),
+        "{}",
+        answer
+    );
+    assert!(answer.contains("\nSources: index.html\n"), "{}", answer);
+
+    // Later answers search it
+    let mut answer = String::new();
+    for _ in 0..100 {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        answer = server
+            .answer_query("demo", "How do I tune a gadget?", None, &cancel)
+            .await
+            .unwrap();
+        if !answer.contains("still being indexed") {
+            break;
+        }
+    }
+    assert!(!answer.contains("still being indexed"), "{}", answer);
+    assert!(
+        answer.contains("\nSources: struct.Gadget.html"),
+        "{}",
+        answer
+    );
+}
+
+#[tokio::test]
+async fn test_edited_pages_are_indexed_again() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let crate_dir = demo_crate(
+        root.path(),
+        &[
+            ("index.html", "The demo crate builds widgets."),
+            (
+                "struct.Widget.html",
+                "A widget is resized with Widget::resize.",
+            ),
+        ],
+    );
+
+    let mut config = mock_config(root.path());
+    config.docs.freshness_interval_secs = 0;
+    config.llm.extractive = true;
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let cancel = CancellationToken::new();
+
+    let answer = server
+        .answer_query("demo", "How is a widget resized?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(answer.contains("Widget::resize."), "{}", answer);
+
+    // Only the widget page changed; index.html is untouched
+    write_page(
+        &crate_dir,
+        "struct.Widget.html",
+        "A widget is resized with Widget::scale since version 2.",
+    );
+    let answer = server
+        .answer_query("demo", "How is a widget resized?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(
+        answer.contains("Widget::scale since version 2."),
+        "{}",
+        answer
+    );
+    assert!(!answer.contains("Widget::resize."), "{}", answer);
+}
+
+#[tokio::test]
+async fn test_companion_crates_are_searched_with_the_queried_crate() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(
+        root.path(),
+        &[
+            ("index.html", "The demo crate runs tasks."),
+            ("fn.spawn.html", "spawn runs a task in the background."),
+        ],
+    );
+    let companion_dir = root.path().join("doc").join("demo_util");
+    fs::create_dir_all(&companion_dir).unwrap();
+    write_page(&companion_dir, "index.html", "Utilities for demo.");
+    write_page(
+        &companion_dir,
+        "struct.CancellationToken.html",
+        "CancellationToken cancels a running task when cancel is called.",
+    );
+
+    let mut config = mock_config(root.path());
+    config
+        .docs
+        .crates
+        .entry("demo".to_string())
+        .or_default()
+        .companions = vec!["demo-util".to_string(), "missing".to_string()];
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+
+    // The companion's page wins; the missing companion is skipped
+    let answer = server
+        .answer_query(
+            "demo",
+            "How do I cancel a task with CancellationToken?",
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+    assert!(
+        answer.starts_with("From demo (with demo_util) docs:"),
+        "{}",
+        answer
+    );
+    assert!(
+        answer.contains("struct.CancellationToken.html"),
+        "{}",
+        answer
+    );
+
+    // Companions aren't searched the other way around
+    let answer = server
+        .answer_query(
+            "demo_util",
+            "How do I spawn a task?",
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+    assert!(answer.starts_with("From demo_util docs:"), "{}", answer);
+}
+
+#[tokio::test]
+async fn test_answers_warn_about_deprecated_and_gated_items() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let crate_dir = demo_crate(
+        root.path(),
+        &[("index.html", "The demo crate opens connections.")],
+    );
+    fs::write(
+        crate_dir.join("fn.connect_old.html"),
+        "<html><body><section id=\"main-content\" class=\"content\">\
+         <pre class=\"rust item-decl\"><code>pub fn connect_old()</code></pre>\
+         <span class=\"item-info\"><div class=\"stab deprecated\"><span>Deprecated since 1.2.0: use connect</span></div>\
+         <div class=\"stab portability\">Available on <strong>crate feature <code>net</code></strong> only.</div></span>\
+         <p>connect_old opens a connection to the server.</p></section></body></html>",
+    )
+    .unwrap();
+
+    let server = RustDocsServer::with_config("test".to_string(), mock_config(root.path())).unwrap();
+
+    let answer = server
+        .answer_query(
+            "demo",
+            "How does connect_old open a connection?",
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+    assert!(
+        answer.contains(
+            "\n\nWarning: `demo::connect_old` is deprecated: Deprecated since 1.2.0: use connect"
+        ),
+        "{}",
+        answer
+    );
+    assert!(
+        answer.contains(
+            "\n\nWarning: `demo::connect_old` is feature-gated: Available on crate feature net only."
+        ),
+        "{}",
+        answer
+    );
+}
+
+#[tokio::test]
+async fn test_item_and_version_questions_are_answered_without_the_llm() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let crate_dir = demo_crate(
+        root.path(),
+        &[("struct.Widget.html", "Widget is created with Widget::new.")],
+    );
+    fs::write(
+        crate_dir.join("index.html"),
+        "<html><body><span class=\"version\">1.0.0</span>\
+         <section id=\"main-content\" class=\"content\"><p>The demo crate.</p></section></body></html>",
+    )
+    .unwrap();
+    fs::write(
+        root.path().join("Cargo.lock"),
+        "[[package]]\nname = \"demo\"\nversion = \"1.2.0\"\n",
+    )
+    .unwrap();
+
+    let mut config = mock_config(root.path());
+    config.retrieval.route_questions = true;
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let cancel = CancellationToken::new();
+
+    let answer = server
+        .answer_query("demo", "Where is `Widget` defined?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(answer.contains("- `demo::Widget` (struct)"), "{}", answer);
+
+    let answer = server
+        .answer_query("demo", "Which version of demo do we use?", None, &cancel)
+        .await
+        .unwrap();
+    assert_eq!(
+        answer,
+        "The project uses `demo` version 1.2.0. \
+         The documentation was generated for version 1.0.0."
+    );
+
+    // Lookups that find nothing fall back to the LLM
+    let answer = server
+        .answer_query("demo", "Where is `Gadget` defined?", None, &cancel)
+        .await
+        .unwrap();
+    assert!(answer.contains("Mock answer about 'demo'"), "{}", answer);
+
+    // Batched questions are routed the same way
+    let answers = server
+        .answer_queries(
+            &[
+                ("demo", "Where is `Widget` defined?"),
+                ("demo", "Where is `Gadget` defined?"),
+                ("demo", "Which version of demo do we use?"),
+            ],
+            None,
+            &cancel,
+        )
+        .await;
+    let answers: Vec<String> = answers.into_iter().map(Result::unwrap).collect();
+    assert!(
+        answers[0].contains("- `demo::Widget` (struct)"),
+        "{}",
+        answers[0]
+    );
+    assert!(
+        answers[1].contains("Mock answer about 'demo'"),
+        "{}",
+        answers[1]
+    );
+    assert!(
+        answers[2].starts_with("The project uses `demo` version 1.2.0."),
+        "{}",
+        answers[2]
+    );
+}
+
+#[tokio::test]
+async fn test_answers_past_the_latency_budget_are_partial() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(root.path(), WIDGET_PAGES);
+    // A chat endpoint that accepts the request but never answers
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(5));
+        drop(stream);
+    });
+    let key_file = root.path().join("key");
+    fs::write(&key_file, "sk-test").unwrap();
+
+    let mut config = mock_config(root.path());
+    config.llm.provider = "openai".to_string();
+    config.llm.api_base = Some(api_base);
+    config.openai.api_key_file = Some(key_file);
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    server.index_crate("demo").await.unwrap();
+
+    let options = QueryOptions {
+        latency_budget: Some(std::time::Duration::from_millis(300)),
+        format: AnswerFormat::Json,
+        ..QueryOptions::default()
+    };
+    let started = std::time::Instant::now();
+    let answer = server
+        .answer_query_with(
+            "demo",
+            "How do I resize a Widget?",
+            &options,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    let value: serde_json::Value = serde_json::from_str(&answer).unwrap();
+    assert_eq!(value["partial"], true);
+    let body = value["answer"].as_str().unwrap();
+    assert!(body.starts_with("Latency budget exceeded"), "{}", body);
+    assert!(body.contains("Widget::resize"), "{}", body);
+}
+
+#[tokio::test]
+async fn test_batched_answers_past_the_latency_budget_are_partial() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(root.path(), WIDGET_PAGES);
+    // A chat endpoint that accepts requests but never answers them
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let streams: Vec<_> = listener.incoming().take(2).collect();
+        std::thread::sleep(std::time::Duration::from_secs(5));
+        drop(streams);
+    });
+    let key_file = root.path().join("key");
+    fs::write(&key_file, "sk-test").unwrap();
+
+    let mut config = mock_config(root.path());
+    config.llm.provider = "openai".to_string();
+    config.llm.api_base = Some(api_base);
+    config.openai.api_key_file = Some(key_file);
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    server.index_crate("demo").await.unwrap();
+
+    let options = QueryOptions {
+        latency_budget: Some(std::time::Duration::from_millis(300)),
+        format: AnswerFormat::Json,
+        ..QueryOptions::default()
+    };
+    let started = std::time::Instant::now();
+    let answers = server
+        .answer_queries_with(
+            &[
+                ("demo", "How do I resize a Widget?"),
+                ("demo", "How do I create a Widget?"),
+            ],
+            &options,
+            &CancellationToken::new(),
+        )
+        .await;
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    for answer in answers {
+        let value: serde_json::Value = serde_json::from_str(&answer.unwrap()).unwrap();
+        assert_eq!(value["partial"], true);
+        let body = value["answer"].as_str().unwrap();
+        assert!(body.starts_with("Latency budget exceeded"), "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn test_answered_questions_are_written_to_the_audit_log() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(root.path(), WIDGET_PAGES);
+
+    let audit_path = root.path().join("audit").join("audit.jsonl");
+    let mut config = mock_config(root.path());
+    config.audit.enabled = true;
+    config.audit.path = Some(audit_path.clone());
+    config.audit.max_answer_chars = 20;
+
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    server
+        .answer_query(
+            "demo",
+            "How do I resize a Widget?",
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .expect("Query should succeed with mock providers");
+
+    let log = fs::read_to_string(&audit_path).unwrap();
+    let lines: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1, "{}", log);
+    let line = &lines[0];
+    assert_eq!(line["event"], "query");
+    assert_eq!(line["crate_name"], "demo");
+    assert_eq!(line["question"], "How do I resize a Widget?");
+    assert_eq!(line["retrieval"], "semantic");
+    assert_eq!(line["matches"][0]["path"], "struct.Widget.html");
+    assert!(line["matches"][0]["score"].as_f64().unwrap() > 0.0);
+    assert_eq!(line["llm_model"], Config::default().llm.model);
+    assert!(line["context_tokens"].as_u64().unwrap() > 0);
+    assert_eq!(line["partial"], false);
+    assert_eq!(line["answer"].as_str().unwrap().chars().count(), 21);
+    assert!(line["recorded_at"].is_string());
+}
+
+/// Writes a `demo` crate and a config answering with mock providers and
+/// recording telemetry to `telemetry.sqlite3` under `root`
+fn telemetry_fixture(root: &Path) -> Config {
+    demo_crate(root, WIDGET_PAGES);
+    let mut config = mock_config(root);
+    config.telemetry.enabled = true;
+    config.telemetry.path = Some(root.join("telemetry.sqlite3"));
+    config
+}
+
+/// Number of times `demo` was indexed according to the telemetry database
+fn index_builds(root: &Path) -> usize {
+    Telemetry::open(&root.join("telemetry.sqlite3"))
+        .unwrap()
+        .report(None)
+        .unwrap()
+        .crates
+        .iter()
+        .find(|report| report.crate_name == "demo")
+        .map_or(0, |report| report.index_builds)
+}
+
+#[tokio::test]
+async fn test_concurrent_queries_index_a_crate_once() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let server =
+        RustDocsServer::with_config("test".to_string(), telemetry_fixture(root.path())).unwrap();
+
+    let cancel = CancellationToken::new();
+    let (first, second) = tokio::join!(
+        server.answer_query("demo", "How do I resize a Widget?", None, &cancel),
+        server.answer_query("demo", "What does the demo crate build?", None, &cancel),
+    );
+    assert!(first.unwrap().contains("Mock answer about 'demo'"));
+    assert!(second.unwrap().contains("Mock answer about 'demo'"));
+    assert_eq!(index_builds(root.path()), 1);
+
+    // Later queries reuse the index too
+    server
+        .answer_query("demo", "How do I create a Widget?", None, &cancel)
+        .await
+        .unwrap();
+    assert_eq!(index_builds(root.path()), 1);
+}
+
+#[tokio::test]
+async fn test_preloaded_crates_are_indexed_before_the_first_query() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let mut config = telemetry_fixture(root.path());
+    config.docs.preload = vec!["demo".to_string(), "missing".to_string()];
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+
+    server.spawn_preload();
+    let started = std::time::Instant::now();
+    while index_builds(root.path()) == 0 {
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(10),
+            "demo was never preloaded"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    // The query is answered from the preloaded index
+    let answer = server
+        .answer_query(
+            "demo",
+            "How do I resize a Widget?",
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+    assert!(answer.contains("Mock answer about 'demo'"), "{}", answer);
+    assert_eq!(index_builds(root.path()), 1);
+}
+
+#[tokio::test]
+async fn test_queries_past_the_latency_budget_while_indexing_are_partial() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    // An embedding endpoint that takes its time, so indexing outlasts the budget
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { break };
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+                let mut request = vec![0; content_length];
+                let _ = reader.read_exact(&mut request);
+                std::thread::sleep(std::time::Duration::from_secs(3));
+                let body = r#"{"data":[{"embedding":[0.6,0.8]}],"model":"slow-model"}"#;
+                let _ = write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            });
+        }
+    });
+    let root = tempdir().expect("Failed to create temporary directory");
+    let key_file = root.path().join("key");
+    fs::write(&key_file, "sk-test").unwrap();
+    let mut config = telemetry_fixture(root.path());
+    config.embedding.provider = "openai".to_string();
+    config.embedding.api_base = Some(api_base);
+    config.openai.api_key_file = Some(key_file);
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+
+    let options = QueryOptions {
+        latency_budget: Some(std::time::Duration::from_millis(200)),
+        format: AnswerFormat::Json,
+        ..QueryOptions::default()
+    };
+    let started = std::time::Instant::now();
+    let answer = server
+        .answer_query_with(
+            "demo",
+            "How do I resize a Widget?",
+            &options,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(3));
+    let value: serde_json::Value = serde_json::from_str(&answer).unwrap();
+    assert_eq!(value["partial"], true);
+    let body = value["answer"].as_str().unwrap();
+    assert!(body.contains("still being indexed"), "{}", body);
+
+    // Indexing went on in the background
+    server.index_crate("demo").await.unwrap();
+    assert_eq!(index_builds(root.path()), 1);
+}
+
+#[tokio::test]
+async fn test_crate_names_outside_the_doc_root_are_rejected() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    demo_crate(root.path(), WIDGET_PAGES);
+    let mut config = mock_config(root.path());
+    config.docs.auto_generate = true;
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+
+    // `../doc/demo` resolves to the demo crate's docs when joined to the root
+    assert!(server.crate_features("../doc/demo").await.is_err());
+    assert!(server.item_source("../doc/demo", "Widget").await.is_err());
+    assert!(server.describe_indexes(Some("../doc/demo")).is_err());
+}
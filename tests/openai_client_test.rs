@@ -0,0 +1,315 @@
+use rustdocs_mcp_server::cassette::{Cassette, CassetteMode};
+use rustdocs_mcp_server::config::{Config, HttpSettings, OpenAiSettings};
+use rustdocs_mcp_server::error::{ProviderErrorKind, ServerError};
+use rustdocs_mcp_server::openai_client::OpenAiClient;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Serves one canned response per connection, in order, and returns the
+/// requests it received
+fn serve(responses: Vec<(&'static str, &'static str)>) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for (status, body) in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+            }
+            let mut content = vec![0; content_length];
+            reader.read_exact(&mut content).unwrap();
+            request.push_str(&String::from_utf8_lossy(&content));
+            requests.push(request);
+
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nRetry-After: 0\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+        requests
+    });
+    (api_base, server)
+}
+
+fn client(api_base: &str, max_retries: u32) -> OpenAiClient {
+    let settings = HttpSettings {
+        max_retries,
+        ..HttpSettings::default()
+    };
+    let headers = BTreeMap::from([("X-Team".to_string(), "docs".to_string())]);
+    OpenAiClient::new("test-key".to_string(), Some(api_base), &headers, &settings).unwrap()
+}
+
+#[tokio::test]
+async fn test_embeddings_are_retried_when_rate_limited() {
+    let (api_base, server) = serve(vec![
+        (
+            "429 Too Many Requests",
+            r#"{"error":{"message":"slow down","code":"rate_limit_exceeded"}}"#,
+        ),
+        (
+            "200 OK",
+            r#"{"data":[{"embedding":[0.6,0.8]}],"model":"m"}"#,
+        ),
+    ]);
+
+    let (vector, model) = client(&api_base, 1).embed("m", "text", None).await.unwrap();
+    assert_eq!(vector, vec![0.6, 0.8]);
+    assert_eq!(model, "m");
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        let lower = request.to_lowercase();
+        assert!(lower.starts_with("post /v1/embeddings "), "{}", request);
+        assert!(
+            lower.contains("authorization: bearer test-key"),
+            "{}",
+            request
+        );
+        assert!(lower.contains("x-team: docs"), "{}", request);
+        assert!(request.contains(r#""input":"text""#), "{}", request);
+    }
+}
+
+#[tokio::test]
+async fn test_embeddings_send_the_input_type_when_given() {
+    let body = r#"{"data":[{"embedding":[1.0]}],"model":"m"}"#;
+    let (api_base, server) = serve(vec![("200 OK", body), ("200 OK", body)]);
+    let client = client(&api_base, 0);
+
+    client
+        .embed("m", "a question", Some(("input_type", "search_query")))
+        .await
+        .unwrap();
+    client.embed("m", "a chunk", None).await.unwrap();
+
+    let requests = server.join().unwrap();
+    assert!(
+        requests[0].contains(r#""input_type":"search_query""#),
+        "{}",
+        requests[0]
+    );
+    assert!(!requests[1].contains("input_type"), "{}", requests[1]);
+}
+
+#[tokio::test]
+async fn test_chat_sends_both_prompts_and_reads_the_reply() {
+    let (api_base, server) = serve(vec![(
+        "200 OK",
+        r#"{"choices":[{"message":{"role":"assistant","content":"Use Widget::new."}}]}"#,
+    )]);
+
+    let answer = client(&api_base, 0)
+        .chat("gpt", "Be brief.", "How do I make a Widget?")
+        .await
+        .unwrap();
+    assert_eq!(answer.as_deref(), Some("Use Widget::new."));
+
+    let request = &server.join().unwrap()[0];
+    assert!(
+        request.starts_with("POST /v1/chat/completions "),
+        "{}",
+        request
+    );
+    assert!(
+        request.contains(r#"{"content":"Be brief.","role":"system"}"#),
+        "{}",
+        request
+    );
+    assert!(request.contains(r#""model":"gpt""#), "{}", request);
+}
+
+#[tokio::test]
+async fn test_failures_are_classified_and_not_retried_when_permanent() {
+    let (api_base, server) = serve(vec![(
+        "401 Unauthorized",
+        r#"{"error":{"message":"bad key","code":"invalid_api_key"}}"#,
+    )]);
+
+    let error = client(&api_base, 3).check_access().await.unwrap_err();
+    assert!(
+        matches!(
+            error,
+            ServerError::Provider {
+                kind: ProviderErrorKind::Auth,
+                status: Some(401),
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].starts_with("GET /v1/models "));
+}
+
+#[tokio::test]
+async fn test_requests_are_attributed_and_use_the_rotated_key() {
+    let body = r#"{"data":[{"embedding":[1.0]}],"model":"m"}"#;
+    let (api_base, server) = serve(vec![("200 OK", body), ("200 OK", body)]);
+    let account = OpenAiSettings {
+        organization: Some("org-docs".to_string()),
+        project: Some("proj_search".to_string()),
+        ..OpenAiSettings::default()
+    };
+    let client = client(&api_base, 0).with_account(&account).unwrap();
+
+    client.embed("m", "first", None).await.unwrap();
+    // Clones share the key, like the server's chat and embedding clients
+    client.clone().set_api_key("rotated-key".to_string());
+    client.embed("m", "second", None).await.unwrap();
+
+    let requests: Vec<String> = server
+        .join()
+        .unwrap()
+        .iter()
+        .map(|request| request.to_lowercase())
+        .collect();
+    for request in &requests {
+        assert!(
+            request.contains("openai-organization: org-docs"),
+            "{}",
+            request
+        );
+        assert!(
+            request.contains("openai-project: proj_search"),
+            "{}",
+            request
+        );
+        assert!(request.contains("x-team: docs"), "{}", request);
+    }
+    assert!(requests[0].contains("authorization: bearer test-key"));
+    assert!(requests[1].contains("authorization: bearer rotated-key"));
+}
+
+#[tokio::test]
+async fn test_recorded_traffic_replays_without_the_provider() {
+    let (api_base, server) = serve(vec![
+        (
+            "429 Too Many Requests",
+            r#"{"error":{"message":"slow down","code":"rate_limit_exceeded"}}"#,
+        ),
+        (
+            "200 OK",
+            r#"{"data":[{"embedding":[0.6,0.8]}],"model":"m"}"#,
+        ),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cassettes/embed.json");
+    let settings = HttpSettings {
+        max_retries: 1,
+        cassette: Some(path.clone()),
+        cassette_mode: CassetteMode::Record,
+        ..HttpSettings::default()
+    };
+    let recording = OpenAiClient::new(
+        "test-key".to_string(),
+        Some(&api_base),
+        &BTreeMap::new(),
+        &settings,
+    )
+    .unwrap();
+    recording.embed("m", "text", None).await.unwrap();
+    server.join().unwrap();
+
+    let recorded = fs::read_to_string(&path).unwrap();
+    assert!(!recorded.contains("test-key"), "{}", recorded);
+    let cassette = Cassette::open(&path, CassetteMode::Replay).unwrap();
+    let statuses: Vec<u16> = cassette
+        .interactions()
+        .iter()
+        .map(|interaction| interaction.status)
+        .collect();
+    assert_eq!(statuses, vec![429, 200]);
+
+    // Nothing listens at the API base any more
+    let replaying = client(&api_base, 1).with_cassette(Arc::new(cassette));
+    let (vector, model) = replaying.embed("m", "text", None).await.unwrap();
+    assert_eq!(vector, vec![0.6, 0.8]);
+    assert_eq!(model, "m");
+}
+
+#[tokio::test]
+async fn test_a_server_that_never_answers_times_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+    // Accept the connection like a hung proxy, but never respond
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        thread::sleep(Duration::from_secs(3));
+        drop(stream);
+    });
+    let settings = HttpSettings {
+        read_timeout_secs: 1,
+        max_retries: 0,
+        ..HttpSettings::default()
+    };
+    let client =
+        OpenAiClient::new(String::new(), Some(&api_base), &BTreeMap::new(), &settings).unwrap();
+
+    let started = Instant::now();
+    let error = client.chat("gpt", "Be brief.", "Hello?").await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(3));
+    assert!(
+        matches!(
+            error,
+            ServerError::Provider {
+                kind: ProviderErrorKind::Network,
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert!(error.to_string().contains("within 1s"), "{}", error);
+    server.join().unwrap();
+}
+
+#[test]
+fn test_invalid_settings_are_rejected() {
+    let bad_header = BTreeMap::from([("Bad Header".to_string(), "value".to_string())]);
+    assert!(OpenAiClient::new(String::new(), None, &bad_header, &HttpSettings::default()).is_err());
+
+    let bad_proxy = HttpSettings {
+        proxy: Some("not a url".to_string()),
+        ..HttpSettings::default()
+    };
+    assert!(OpenAiClient::new(String::new(), None, &BTreeMap::new(), &bad_proxy).is_err());
+
+    let mut config = Config::default();
+    config.http.read_timeout_secs = 0;
+    assert!(config.validate().is_err());
+
+    let client = OpenAiClient::new(
+        String::new(),
+        Some("https://proxy.example.com/v1/"),
+        &BTreeMap::new(),
+        &HttpSettings::default(),
+    )
+    .unwrap();
+    assert_eq!(client.api_base(), "https://proxy.example.com/v1");
+}
@@ -0,0 +1,42 @@
+use rustdocs_mcp_server::page_text::{extract, page_since};
+use scraper::{Html, Selector};
+
+const VEC_PAGE: &str = r##"<html><body>
+<section id="main-content" class="content">
+<div class="main-heading"><h1>Struct <span class="struct">Vec</span><button id="copy-path" title="Copy item path to clipboard">Copy item path</button></h1>
+<span class="out-of-band"><span class="since" title="Stable since Rust version 1.0.0">1.0.0</span> · <a class="src" href="#">Source</a></span></div>
+<details class="toggle top-doc" open><summary class="hideme"><span>Expand description</span></summary><div class="docblock">
+<p>A contiguous growable array type.</p>
+<h2 id="examples"><a class="doc-anchor" href="#examples">§</a>Examples</h2>
+<div class="example-wrap"><pre class="rust rust-example-rendered"><code>let v = Vec::new();</code></pre><a class="test-arrow" href="#">Run</a></div>
+</div></details>
+<section id="method.push" class="method"><span class="rightside"><span class="since" title="Stable since Rust version 1.0.0">1.0.0</span> · <a class="src" href="#">Source</a></span><a href="#method.push" class="anchor">§</a><h4 class="code-header">pub fn push(&amp;mut self, value: T)</h4></section>
+<div class="item-info"><span class="stab deprecated">Deprecated since 1.1.0: use extend</span></div>
+<ul class="item-table"><li><div class="item-name"><a href="#">Drain</a><span class="stab unstable" title="">Experimental</span></div><div class="desc docblock-short">A draining iterator.</div></li></ul>
+</section></body></html>"##;
+
+fn main_content(page: &Html) -> String {
+    let selector = Selector::parse("section#main-content").unwrap();
+    extract(page.select(&selector).next().unwrap())
+}
+
+#[test]
+fn test_extract_leaves_out_interface_chrome() {
+    let text = main_content(&Html::parse_document(VEC_PAGE));
+    assert_eq!(
+        text,
+        "Struct\nVec\nA contiguous growable array type.\nExamples\nlet v = Vec::new();\n\
+         pub fn push(&mut self, value: T)\nDeprecated since 1.1.0: use extend\n\
+         Drain\nA draining iterator."
+    );
+}
+
+#[test]
+fn test_page_since_reads_the_item_version() {
+    assert_eq!(
+        page_since(&Html::parse_document(VEC_PAGE)).as_deref(),
+        Some("1.0.0")
+    );
+    let third_party = r#"<section id="main-content" class="content"><p>No versions.</p></section>"#;
+    assert_eq!(page_since(&Html::parse_document(third_party)), None);
+}
@@ -0,0 +1,159 @@
+use rustdocs_mcp_server::embedding_cache_service::migrate_cache_dir;
+use rustdocs_mcp_server::global_cache::{CacheKey, GlobalCache};
+use rustdocs_mcp_server::platform_paths::{ProjectDirs, long_path, portable_path, safe_file_name};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+#[test]
+fn test_file_names_are_valid_on_every_platform() {
+    assert_eq!(safe_file_name("serde_json"), "serde_json");
+    assert_eq!(safe_file_name("1.0.219"), "1.0.219");
+    assert_eq!(safe_file_name(">=1.2, <2"), "__1.2___2");
+    assert_eq!(
+        safe_file_name("a:b\\c/d*e?f\"g<h>i|j"),
+        "a_b_c_d_e_f_g_h_i_j"
+    );
+
+    // Names Windows reserves for devices, whatever their case or extension
+    assert_eq!(safe_file_name("con"), "con_");
+    assert_eq!(safe_file_name("AUX"), "AUX_");
+    assert_eq!(safe_file_name("nul.html"), "nul_.html");
+    assert_eq!(safe_file_name("com1.tar.gz"), "com1_.tar.gz");
+    assert_eq!(safe_file_name("console"), "console");
+    assert_eq!(safe_file_name("lpt10"), "lpt10");
+
+    // Windows drops trailing dots, and empty names aren't names at all
+    assert_eq!(safe_file_name("1.0."), "1.0._");
+    assert_eq!(safe_file_name(".."), ".._");
+    assert_eq!(safe_file_name(""), "_");
+}
+
+#[test]
+fn test_cache_entries_of_reserved_crate_names_are_usable() {
+    let dir = TempDir::new().unwrap();
+    let cache = GlobalCache::with_root(dir.path().to_path_buf()).unwrap();
+    let key = CacheKey {
+        crate_name: "aux".to_string(),
+        version: "^1.0".to_string(),
+        features: Vec::new(),
+    };
+
+    let entry = cache.index_path(&key);
+    let relative = entry.strip_prefix(dir.path()).unwrap();
+    let components: Vec<String> = relative
+        .iter()
+        .map(|part| part.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(components[0], "aux_");
+    assert_eq!(components[1], "_1.0");
+}
+
+#[test]
+fn test_document_paths_use_forward_slashes() {
+    let relative: PathBuf = ["tokio", "sync", "struct.Mutex.html"].iter().collect();
+    assert_eq!(portable_path(&relative), "tokio/sync/struct.Mutex.html");
+    assert_eq!(portable_path(Path::new("index.html")), "index.html");
+}
+
+#[test]
+fn test_project_dirs_are_per_user_and_distinct() {
+    let dirs = ProjectDirs::new().unwrap();
+    assert!(dirs.config_dir().ends_with("rustdocs-mcp"));
+    assert!(dirs.data_dir().ends_with("rustdocs-mcp-server"));
+    assert_ne!(dirs.data_dir(), dirs.embedding_cache_dir());
+    assert!(!dirs.embedding_cache_dir().starts_with(dirs.data_dir()));
+    assert_ne!(
+        dirs.legacy_embedding_cache_dir(),
+        Some(dirs.embedding_cache_dir())
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_long_paths_are_unchanged_outside_windows() {
+    let long: PathBuf = std::iter::repeat_n("segment", 60).collect();
+    let long = Path::new("/").join(long);
+    assert_eq!(long_path(&long), long);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_long_windows_paths_get_the_verbatim_prefix() {
+    let short = Path::new(r"C:\Users\dev\cache\index.bin");
+    assert_eq!(long_path(short), short);
+
+    let deep = format!(r"C:\Users\dev\{}embeddings.bin", "nested\\".repeat(40));
+    assert_eq!(
+        long_path(Path::new(&deep)),
+        PathBuf::from(format!(r"\\?\{}", deep))
+    );
+
+    let share = format!(r"\\server\share\{}embeddings.bin", "nested\\".repeat(40));
+    assert_eq!(
+        long_path(Path::new(&share)),
+        PathBuf::from(format!(r"\\?\UNC\{}", &share[2..]))
+    );
+
+    // Relative paths can't be made verbatim
+    let relative = format!(r"cache\{}embeddings.bin", "nested\\".repeat(40));
+    assert_eq!(long_path(Path::new(&relative)), Path::new(&relative));
+}
+
+#[cfg(windows)]
+#[test]
+fn test_cache_entries_deeper_than_max_path_can_be_stored() {
+    use rustdocs_mcp_server::global_cache::CachedCrateIndex;
+
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("a".repeat(120)).join("b".repeat(120));
+    let cache = GlobalCache::with_root(long_path(&root)).unwrap();
+    let key = CacheKey {
+        crate_name: "con".to_string(),
+        version: "1.0.0".to_string(),
+        features: vec!["full".to_string()],
+    };
+    assert!(cache.index_path(&key).to_string_lossy().len() > 260);
+
+    let index = CachedCrateIndex {
+        crate_name: "con".to_string(),
+        version: "1.0.0".to_string(),
+        features: vec!["full".to_string()],
+        model: "test-model".to_string(),
+        documents: Vec::new(),
+    };
+    cache.store(&key, &index).unwrap();
+    let loaded = cache.load(&key).unwrap().expect("index should be cached");
+    assert_eq!(loaded.crate_name, "con");
+}
+
+#[test]
+fn test_legacy_chunk_cache_is_migrated_once() {
+    let dir = TempDir::new().unwrap();
+    let legacy = dir.path().join(".rust-doc-embedding-cache");
+    fs::create_dir_all(&legacy).unwrap();
+    fs::write(legacy.join("chunk-a"), "a").unwrap();
+    fs::write(legacy.join("chunk-b"), "b").unwrap();
+
+    // Renamed as a whole when the new location doesn't exist yet
+    let target = dir
+        .path()
+        .join("cache")
+        .join("rustdocs-mcp-server")
+        .join("embeddings");
+    assert_eq!(migrate_cache_dir(&legacy, &target).unwrap(), 2);
+    assert!(!legacy.exists());
+    assert_eq!(fs::read_to_string(target.join("chunk-a")).unwrap(), "a");
+
+    // Merged entry by entry into an existing cache, keeping its entries
+    fs::create_dir_all(&legacy).unwrap();
+    fs::write(legacy.join("chunk-b"), "old b").unwrap();
+    fs::write(legacy.join("chunk-c"), "c").unwrap();
+    assert_eq!(migrate_cache_dir(&legacy, &target).unwrap(), 1);
+    assert!(!legacy.exists());
+    assert_eq!(fs::read_to_string(target.join("chunk-b")).unwrap(), "b");
+    assert_eq!(fs::read_to_string(target.join("chunk-c")).unwrap(), "c");
+
+    // Nothing left to migrate
+    assert_eq!(migrate_cache_dir(&legacy, &target).unwrap(), 0);
+}
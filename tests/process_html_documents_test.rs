@@ -207,6 +207,7 @@ fn test_process_html_documents_direct() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_unwrap)]
 fn test_process_html_documents_empty_directory() {
     // Create empty directory
     let _temp_dir = tempdir().expect("Failed to create temporary directory");
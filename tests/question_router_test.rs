@@ -0,0 +1,50 @@
+use rustdocs_mcp_server::question_router::{Route, classify};
+
+fn lookup(item: &str) -> Route {
+    Route::ItemLookup(item.to_string())
+}
+
+#[test]
+fn test_bare_identifiers_and_where_questions_are_lookups() {
+    assert_eq!(classify("HashMap::entry"), lookup("HashMap::entry"));
+    assert_eq!(classify("`buffer_unordered`?"), lookup("buffer_unordered"));
+    assert_eq!(classify("select!"), lookup("select"));
+    assert_eq!(classify("Where is `Widget` defined?"), lookup("Widget"));
+    assert_eq!(
+        classify("where is the StreamExt trait"),
+        lookup("StreamExt")
+    );
+    assert_eq!(classify("Find Widget::resize()"), lookup("Widget::resize"));
+    assert_eq!(
+        classify("Path of serde_json::Value"),
+        lookup("serde_json::Value")
+    );
+}
+
+#[test]
+fn test_version_questions_go_to_cargo_metadata() {
+    assert_eq!(
+        classify("Which version of tokio do we use?"),
+        Route::Version
+    );
+    assert_eq!(classify("what version am I on"), Route::Version);
+    // About an item's history, which the docs answer
+    assert_eq!(
+        classify("Since which version is `Widget::new` stable?"),
+        Route::Semantic
+    );
+}
+
+#[test]
+fn test_other_questions_are_semantic() {
+    for question in [
+        "How do I resize a Widget?",
+        "What does `Widget::new` return?",
+        "Where is the configuration file read from?",
+        "Find a way to retry requests",
+        "",
+        "Ünïcödé?",
+    ] {
+        assert_eq!(classify(question), Route::Semantic, "{}", question);
+    }
+}
@@ -0,0 +1,183 @@
+use rustdocs_mcp_server::{
+    RustDocsServer,
+    config::{Config, CrateQuerySettings, RedactionRule},
+    mock::MOCK_PROVIDER,
+    retrieval_debug::{Outcome, preview},
+    server::QueryOptions,
+};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use tokio_util::sync::CancellationToken;
+
+fn write_page(crate_dir: &Path, file: &str, text: &str) {
+    let html = format!(
+        "<html><body><section id=\"main-content\" class=\"content\"><p>{}</p></section></body></html>",
+        text
+    );
+    fs::write(crate_dir.join(file), html).unwrap();
+}
+
+#[test]
+fn test_preview_collapses_whitespace_and_cuts_long_text() {
+    assert_eq!(preview("  Widget\n\n  docs ", 20), "Widget docs");
+    assert_eq!(preview("Widget docs", 6), "Widget…");
+    assert_eq!(preview("äöü", 2), "äö…");
+}
+
+#[tokio::test]
+async fn test_report_ranks_every_document_and_shows_the_prompt() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let doc_dir = root.path().join("doc");
+    let crate_dir = doc_dir.join("demo");
+    fs::create_dir_all(&crate_dir).unwrap();
+    write_page(
+        &crate_dir,
+        "index.html",
+        "The demo crate builds gadgets and widgets.",
+    );
+    write_page(
+        &crate_dir,
+        "struct.Widget.html",
+        "Widget is created with Widget::new and resized with Widget::resize.",
+    );
+    write_page(
+        &crate_dir,
+        "fn.parse_config.html",
+        "parse_config reads TOML configuration files from disk.",
+    );
+
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = doc_dir;
+    config.docs.project_dir = root.path().to_path_buf();
+    config.cache.dir = Some(root.path().join("chunks"));
+    config.cache.global_dir = Some(root.path().join("global"));
+    config.retrieval.min_score = 0.2;
+    config.redaction.rules = vec![RedactionRule {
+        pattern: "Widget::resize".to_string(),
+        replacement: None,
+    }];
+
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let options = QueryOptions {
+        top_k: Some(1),
+        ..QueryOptions::default()
+    };
+    let report = server
+        .explain_retrieval(
+            "demo",
+            "How do I resize a Widget?",
+            &options,
+            true,
+            &CancellationToken::new(),
+        )
+        .await
+        .expect("Retrieval should be explained");
+
+    assert_eq!(report.top_k, 1);
+    let paths: Vec<&str> = report.candidates.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(paths.len(), 3, "{:?}", paths);
+    assert_eq!(paths[0], "struct.Widget.html");
+    assert!(matches!(
+        report.candidates[0].outcome,
+        Outcome::Sent { tokens } if tokens > 0
+    ));
+    assert!(
+        report.candidates[0]
+            .preview
+            .starts_with("Widget is created")
+    );
+    for candidate in &report.candidates[1..] {
+        let expected = if candidate.similarity < 0.2 {
+            Outcome::BelowMinScore
+        } else {
+            Outcome::BeyondTopK
+        };
+        assert_eq!(candidate.outcome, expected, "{:?}", candidate);
+    }
+
+    let (system, user) = report.prompt.clone().expect("The LLM is asked");
+    assert!(system.contains("Rust crate 'demo'"));
+    assert!(
+        user.contains("Question: How do I resize a Widget?"),
+        "{}",
+        user
+    );
+    assert!(!user.contains("Widget::resize"), "{}", user);
+
+    let text = report.to_string();
+    assert!(text.contains("Answer: mock LLM"), "{}", text);
+    assert!(text.contains("[sent, "), "{}", text);
+    assert!(text.contains("User prompt, redacted:"), "{}", text);
+}
+
+#[tokio::test]
+async fn test_crate_section_sets_top_k_and_prompt() {
+    let root = tempdir().expect("Failed to create temporary directory");
+    let doc_dir = root.path().join("doc");
+    let crate_dir = doc_dir.join("demo_lib");
+    fs::create_dir_all(&crate_dir).unwrap();
+    write_page(&crate_dir, "index.html", "The demo crate builds gadgets.");
+    write_page(
+        &crate_dir,
+        "struct.Gadget.html",
+        "Gadget is built with Gadget::new.",
+    );
+    write_page(
+        &crate_dir,
+        "fn.build.html",
+        "build assembles a gadget from parts.",
+    );
+
+    let mut config = Config::default();
+    config.embedding.provider = MOCK_PROVIDER.to_string();
+    config.llm.provider = MOCK_PROVIDER.to_string();
+    config.docs.dir = doc_dir;
+    config.docs.project_dir = root.path().to_path_buf();
+    config.cache.dir = Some(root.path().join("chunks"));
+    config.cache.global_dir = Some(root.path().join("global"));
+    config.crates.insert(
+        "demo-lib".to_string(),
+        CrateQuerySettings {
+            top_k: Some(2),
+            llm_model: Some("big-model".to_string()),
+            prompt_template: Some("You explain the {crate} crate.".to_string()),
+        },
+    );
+
+    let server = RustDocsServer::with_config("test".to_string(), config).unwrap();
+    let report = server
+        .explain_retrieval(
+            "demo-lib",
+            "How is a Gadget built?",
+            &QueryOptions::default(),
+            false,
+            &CancellationToken::new(),
+        )
+        .await
+        .expect("Retrieval should be explained");
+
+    assert_eq!(report.top_k, 2);
+    let (system, _) = report.prompt.expect("The LLM is asked");
+    assert!(
+        system.starts_with("You explain the demo_lib crate."),
+        "{}",
+        system
+    );
+    assert!(!system.contains("expert technical assistant"), "{}", system);
+
+    // Settings follow the crate a misspelled name resolves to
+    let report = server
+        .explain_retrieval(
+            "demo-lob",
+            "How is a Gadget built?",
+            &QueryOptions::default(),
+            false,
+            &CancellationToken::new(),
+        )
+        .await
+        .expect("Retrieval should be explained");
+    assert_eq!(report.top_k, 2);
+}
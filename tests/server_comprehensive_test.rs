@@ -1,7 +1,7 @@
 use ndarray::ArrayView1;
 use rmcp::Service;
 use rustdocs_mcp_server::{
-    embeddings::{Embedding, EmbeddingProvider, OPENAI_CLIENT, cosine_similarity},
+    embeddings::{Embedding, EmbeddingProvider, cosine_similarity},
     server::RustDocsServer,
 };
 use std::env;
@@ -15,12 +15,6 @@ fn setup_env() {
             env::set_var("OPENAI_API_KEY", "dummy_key_for_tests");
         }
     }
-
-    // Initialize the OpenAI client if not already set
-    if OPENAI_CLIENT.get().is_none() {
-        let client = async_openai::Client::new();
-        let _ = OPENAI_CLIENT.set(client);
-    }
 }
 
 #[test]
@@ -0,0 +1,140 @@
+use rustdocs_mcp_server::config::{Config, DocBackend};
+use rustdocs_mcp_server::source_loader::{load_crate_documents, load_documents};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const LIB_RS: &str = r#"//! Widgets for everyone.
+
+/// A resizable widget.
+#[derive(Debug, Clone)]
+pub struct Widget {
+    size: u32,
+}
+
+impl Widget {
+    /// Resizes the widget.
+    pub fn resize<F>(&mut self, scale: F) -> u32
+    where
+        F: Fn(u32) -> u32,
+    {
+        self.size = scale(self.size);
+        self.size
+    }
+
+    fn internal(&self) {}
+}
+
+impl Default for Widget {
+    fn default() -> Self {
+        Self { size: 1 }
+    }
+}
+
+/// Things that draw.
+pub trait Draw {
+    /// Draws onto `canvas`.
+    fn draw(&self, canvas: &mut Vec<u8>);
+}
+
+fn helper() -> u32 {
+    2
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_resize() {}
+}
+"#;
+
+fn write_crate(dir: &Path) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/lib.rs"), LIB_RS).unwrap();
+}
+
+fn content<'a>(documents: &'a [rustdocs_mcp_server::Document], path: &str) -> Option<&'a str> {
+    documents
+        .iter()
+        .find(|doc| doc.path == path)
+        .map(|doc| doc.content.as_str())
+}
+
+#[test]
+fn test_items_become_documents_with_their_signatures() {
+    let dir = tempdir().unwrap();
+    write_crate(dir.path());
+
+    let documents = load_documents(dir.path(), "my-crate", false).unwrap();
+    let paths: Vec<&str> = documents.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "my_crate",
+            "my_crate::Widget",
+            "my_crate::Widget::resize",
+            "my_crate::Draw",
+            "my_crate::Draw::draw"
+        ]
+    );
+
+    assert_eq!(
+        content(&documents, "my_crate::Widget::resize").unwrap(),
+        "Method `my_crate::Widget::resize`\n\n```rust\npub fn resize<F>(&mut self, scale: F) -> u32\nwhere\n    F: Fn(u32) -> u32,\n```\n\nResizes the widget."
+    );
+    assert_eq!(
+        content(&documents, "my_crate::Widget").unwrap(),
+        "Struct `my_crate::Widget`\n\n```rust\n#[derive(Debug, Clone)]\npub struct Widget {\n    size: u32,\n}\n```\n\nA resizable widget."
+    );
+    assert_eq!(
+        content(&documents, "my_crate::Draw").unwrap(),
+        "Trait `my_crate::Draw`\n\n```rust\npub trait Draw\n```\n\nThings that draw."
+    );
+    assert_eq!(
+        content(&documents, "my_crate").unwrap(),
+        "Module `my_crate`\n\nWidgets for everyone."
+    );
+}
+
+#[test]
+fn test_private_items_are_included_on_request() {
+    let dir = tempdir().unwrap();
+    write_crate(dir.path());
+
+    let documents = load_documents(dir.path(), "my_crate", true).unwrap();
+    assert_eq!(
+        content(&documents, "my_crate::helper").unwrap(),
+        "Function `my_crate::helper`\n\n```rust\nfn helper() -> u32\n```"
+    );
+    assert!(content(&documents, "my_crate::Widget::internal").is_some());
+    // Undocumented trait impl members and test modules are left out
+    assert!(content(&documents, "my_crate::Widget::default").is_none());
+    assert!(content(&documents, "my_crate::tests").is_none());
+    assert!(content(&documents, "my_crate::tests::test_resize").is_none());
+}
+
+#[tokio::test]
+async fn test_source_backend_is_selected_per_crate() {
+    let dir = tempdir().unwrap();
+    write_crate(&dir.path().join("widgets"));
+    let path = dir.path().join("rustdocs-mcp.toml");
+    fs::write(
+        &path,
+        r#"
+[docs.crates.my-crate]
+backend = "source"
+source_dir = "widgets"
+document_private_items = true
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(Some(&path)).expect("Config should load");
+    assert_eq!(config.docs.backend("my_crate"), DocBackend::Source);
+    assert_eq!(config.docs.backend("serde"), DocBackend::Html);
+
+    let docs = config.docs.rebased(dir.path());
+    let (crate_dir, documents) = load_crate_documents("my_crate", None, &docs).await.unwrap();
+    assert_eq!(crate_dir, dir.path().join("widgets"));
+    assert!(content(&documents, "my_crate::helper").is_some());
+}
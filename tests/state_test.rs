@@ -0,0 +1,174 @@
+use rustdocs_mcp_server::{
+    Document, Embedding, EmbeddingProvider,
+    config::MemorySettings,
+    document_store::DocumentStore,
+    state::{CrateIndex, ServerState},
+    vector_store::VectorStore,
+};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+fn index_for(doc_dir: &Path) -> CrateIndex {
+    let documents = vec![Document {
+        path: "index.html".to_string(),
+        content: "Overview of my_crate".to_string(),
+    }];
+    let store = DocumentStore::new(documents, None, doc_dir).unwrap();
+    let embedding = Embedding::new(
+        vec![1.0, 0.0],
+        EmbeddingProvider::OpenAI,
+        "test-model".to_string(),
+    );
+    let vectors =
+        VectorStore::new(vec![("index.html".to_string(), embedding)], None, doc_dir).unwrap();
+    CrateIndex::new("my-crate", doc_dir, store, vectors)
+}
+
+fn memory(spill_dir: &Path) -> MemorySettings {
+    MemorySettings {
+        spill_dir: Some(spill_dir.to_path_buf()),
+        ..MemorySettings::default()
+    }
+}
+
+#[test]
+fn test_state_reuses_index_until_docs_change() {
+    let doc_dir = tempdir().expect("Failed to create temporary directory");
+    let index_file = doc_dir.path().join("my_crate").join("index.html");
+    fs::create_dir_all(index_file.parent().unwrap()).unwrap();
+    fs::write(&index_file, "<html></html>").unwrap();
+
+    let state = ServerState::new();
+    state.insert(index_for(doc_dir.path()));
+    let index = state.get("my-crate").expect("index should be loaded");
+    assert_eq!(index.crate_name, "my_crate");
+    assert_eq!(index.embeddings.len(), 1);
+    assert_eq!(state.crate_names(), vec!["my_crate".to_string()]);
+
+    // Regenerated docs make the in-memory index stale
+    let later = SystemTime::now() + Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(&index_file)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    assert!(state.get("my_crate").is_none());
+
+    state.insert(index_for(doc_dir.path()));
+    assert!(state.get("my_crate").is_some());
+    assert!(state.remove("my-crate").is_some());
+    assert!(state.get("my_crate").is_none());
+}
+
+#[test]
+fn test_index_records_the_documented_version() {
+    let doc_dir = tempdir().expect("Failed to create temporary directory");
+    let index_file = doc_dir.path().join("my_crate").join("index.html");
+    fs::create_dir_all(index_file.parent().unwrap()).unwrap();
+    assert_eq!(index_for(doc_dir.path()).version, None);
+
+    fs::write(
+        &index_file,
+        r#"<html><body><nav class="sidebar"><div class="sidebar-crate"><h2><a href="../my_crate/index.html">my_crate</a><span class="version">0.4.2</span></h2></div></nav></body></html>"#,
+    )
+    .unwrap();
+    assert_eq!(index_for(doc_dir.path()).version.as_deref(), Some("0.4.2"));
+
+    // Older rustdoc releases label the version
+    fs::write(
+        &index_file,
+        r#"<html><body><div class="block version"><p>Version 0.3.0</p></div></body></html>"#,
+    )
+    .unwrap();
+    assert_eq!(index_for(doc_dir.path()).version.as_deref(), Some("0.3.0"));
+}
+
+#[test]
+fn test_snapshot_restores_indexes_whose_docs_are_unchanged() {
+    let doc_dir = tempdir().expect("Failed to create temporary directory");
+    let index_file = doc_dir.path().join("my_crate").join("index.html");
+    fs::create_dir_all(index_file.parent().unwrap()).unwrap();
+    fs::write(&index_file, "<html></html>").unwrap();
+    let snapshot = doc_dir.path().join("snapshot.bin");
+
+    let state = ServerState::new();
+    assert_eq!(
+        state
+            .load_snapshot(&snapshot, &memory(doc_dir.path()), |_| true)
+            .unwrap(),
+        0
+    );
+    state.insert(index_for(doc_dir.path()));
+    assert_eq!(state.save_snapshot(&snapshot).unwrap(), 1);
+
+    let restored = ServerState::new();
+    assert_eq!(
+        restored
+            .load_snapshot(&snapshot, &memory(doc_dir.path()), |_| true)
+            .unwrap(),
+        1
+    );
+    let index = restored.get("my_crate").expect("index should be restored");
+    assert_eq!(
+        index.embeddings.get("index.html").unwrap().values,
+        vec![1.0, 0.0]
+    );
+    let doc = index.documents.get("index.html").unwrap().unwrap();
+    assert_eq!(doc.content, "Overview of my_crate");
+
+    // Rejected indexes and indexes of regenerated docs are left out
+    let rejected = ServerState::new();
+    assert_eq!(
+        rejected
+            .load_snapshot(&snapshot, &memory(doc_dir.path()), |_| false)
+            .unwrap(),
+        0
+    );
+    let later = SystemTime::now() + Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(&index_file)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    let stale = ServerState::new();
+    assert_eq!(
+        stale
+            .load_snapshot(&snapshot, &memory(doc_dir.path()), |_| true)
+            .unwrap(),
+        0
+    );
+    assert!(stale.crate_names().is_empty());
+}
+
+#[test]
+fn test_quarantined_documents_are_reported_once() {
+    let doc_dir = tempdir().expect("Failed to create temporary directory");
+    let index = index_for(doc_dir.path());
+    assert!(!index.is_quarantined("index.html"));
+    assert_eq!(index.quarantine(&["index.html"]), vec!["index.html"]);
+    assert!(index.quarantine(&["index.html"]).is_empty());
+    assert!(index.is_quarantined("index.html"));
+}
+
+#[test]
+fn test_aliases_find_the_same_index() {
+    let doc_dir = tempdir().expect("Failed to create temporary directory");
+    let index_file = doc_dir.path().join("my_crate").join("index.html");
+    fs::create_dir_all(index_file.parent().unwrap()).unwrap();
+    fs::write(&index_file, "<html></html>").unwrap();
+
+    let state = ServerState::new();
+    state.insert(index_for(doc_dir.path()));
+    assert!(state.get("My_Crate").is_some());
+    assert!(state.get("mycrate").is_none());
+
+    state.add_alias("mycrate", "my-crate");
+    assert_eq!(state.index_name("mycrate"), "my_crate");
+    assert!(state.get("mycrate").is_some());
+    assert!(state.remove("mycrate").is_some());
+    assert!(state.loaded("my_crate").is_none());
+}
@@ -0,0 +1,82 @@
+use std::time::{Duration, SystemTime};
+
+use rustdocs_mcp_server::telemetry::{IndexRecord, QueryRecord, Telemetry};
+
+fn query(
+    crate_name: &str,
+    total_ms: u64,
+    question_cached: bool,
+    top_score: Option<f32>,
+) -> QueryRecord {
+    QueryRecord {
+        crate_name: crate_name.to_string(),
+        load: Duration::from_millis(10),
+        embed: Duration::from_millis(20),
+        search: Duration::from_millis(5),
+        generate: Duration::from_millis(total_ms - 35),
+        total: Duration::from_millis(total_ms),
+        question_cached,
+        top_score,
+    }
+}
+
+#[test]
+fn test_report_summarizes_queries_and_index_builds_per_crate() {
+    let dir = tempfile::tempdir().unwrap();
+    let telemetry = Telemetry::open(&dir.path().join("nested").join("telemetry.sqlite3")).unwrap();
+    telemetry
+        .record_query(&query("serde", 100, false, Some(0.5)))
+        .unwrap();
+    telemetry
+        .record_query(&query("serde", 300, true, Some(0.7)))
+        .unwrap();
+    telemetry
+        .record_query(&query("serde", 200, false, None))
+        .unwrap();
+    telemetry
+        .record_query(&query("tokio", 50, true, Some(0.9)))
+        .unwrap();
+    telemetry
+        .record_index(&IndexRecord {
+            crate_name: "serde".to_string(),
+            documents: 40,
+            reused: 30,
+            chunk_hits: 6,
+            chunk_misses: 2,
+            duration: Duration::from_millis(1500),
+        })
+        .unwrap();
+
+    let report = telemetry.report(None).unwrap();
+    let names: Vec<&str> = report
+        .crates
+        .iter()
+        .map(|c| c.crate_name.as_str())
+        .collect();
+    assert_eq!(names, ["serde", "tokio"]);
+
+    let serde = &report.crates[0];
+    assert_eq!(serde.queries, 3);
+    assert_eq!(serde.total_p50_ms, 200.0);
+    assert_eq!(serde.total_p95_ms, 300.0);
+    assert!((serde.phase_ms[0] - 10.0).abs() < 1e-6);
+    assert_eq!(serde.question_cache_hits, 1);
+    assert_eq!(serde.unmatched, 1);
+    assert!((serde.mean_top_score.unwrap() - 0.6).abs() < 1e-6);
+    assert_eq!(serde.index_builds, 1);
+    assert_eq!((serde.documents_indexed, serde.documents_reused), (40, 30));
+    assert_eq!((serde.chunk_hits, serde.chunk_misses), (6, 2));
+
+    let text = report.to_string();
+    assert!(
+        text.contains("3 queries: p50 200 ms, p95 300 ms"),
+        "{}",
+        text
+    );
+    assert!(text.contains("Question cache: 1/3 hits (33%)"), "{}", text);
+    assert!(text.contains("chunk cache 6/8 hits (75%)"), "{}", text);
+
+    // Records before the cutoff are left out
+    let later = SystemTime::now() + Duration::from_secs(3600);
+    assert!(telemetry.report(Some(later)).unwrap().crates.is_empty());
+}
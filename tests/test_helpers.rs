@@ -1,9 +1,7 @@
-use async_openai::{Client as OpenAIClient};
-use rustdocs_mcp_server::embeddings::OPENAI_CLIENT;
 use std::{env, path::Path};
 
-/// Initializes the test environment for tests that need an OpenAI client.
-/// Returns true if the environment was successfully set up, false otherwise.
+/// Checks the test environment for tests that need the OpenAI API.
+/// Returns true if the API key is set, false otherwise.
 pub fn setup_openai_env() -> bool {
     // Skip if no API key is provided
     if env::var("OPENAI_API_KEY").is_err() {
@@ -11,13 +9,6 @@ pub fn setup_openai_env() -> bool {
         return false;
     }
 
-    // Initialize OpenAI client
-    let client = OpenAIClient::new();
-    if OPENAI_CLIENT.set(client).is_err() {
-        eprintln!("Failed to initialize OpenAI client");
-        return false;
-    }
-    
     true
 }
 
@@ -112,3 +112,34 @@ fn test_with_context_other_error() {
         _ => panic!("Expected ServerError::Config, got {:?}", err),
     }
 }
+
+#[test]
+fn test_with_context_keeps_provider_error_kind() {
+    use rustdocs_mcp_server::error::ProviderErrorKind;
+
+    let original: std::result::Result<(), ServerError> = Err(ServerError::Provider {
+        kind: ProviderErrorKind::RateLimited,
+        status: Some(429),
+        message: "slow down".to_string(),
+        retry_after: None,
+    });
+    let err = with_context(original, || "Failed to embed chunk abc123".to_string()).unwrap_err();
+
+    // Still retryable, and the message names the chunk
+    assert!(err.is_retryable());
+    assert!(err.to_string().contains("Failed to embed chunk abc123: slow down"));
+}
+
+#[test]
+fn test_with_context_names_file_of_malformed_data() {
+    let original = serde_json::from_str::<Vec<String>>("not json");
+    let err = with_context(original, || "Failed to read chunk references /cache/chunks.json".to_string())
+        .unwrap_err();
+    match err {
+        ServerError::Io(io_err) => {
+            assert_eq!(io_err.kind(), ErrorKind::InvalidData);
+            assert!(io_err.to_string().contains("/cache/chunks.json"));
+        }
+        _ => panic!("Expected ServerError::Io, got {:?}", err),
+    }
+}
@@ -0,0 +1,73 @@
+use rustdocs_mcp_server::{Embedding, EmbeddingProvider, vector_store::VectorStore};
+use std::fs;
+use tempfile::tempdir;
+
+fn embeddings() -> Vec<(String, Embedding)> {
+    [
+        ("struct.Client.html", vec![1.0, 0.0, 0.0]),
+        ("fn.get.html", vec![0.6, 0.8, 0.0]),
+        ("index.html", vec![0.0, 0.0, 2.0]),
+        ("struct.Old.html", vec![1.0, 1.0]),
+    ]
+    .into_iter()
+    .map(|(path, values)| {
+        let embedding = Embedding::new(values, EmbeddingProvider::OpenAI, "test-model".into());
+        (path.to_string(), embedding)
+    })
+    .collect()
+}
+
+fn sorted(mut scores: Vec<(&str, f32)>) -> Vec<(&str, f32)> {
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores
+}
+
+#[test]
+fn test_vectors_within_budget_stay_in_memory() {
+    let spill_dir = tempdir().expect("Failed to create temporary directory");
+    let store = VectorStore::new(embeddings(), Some(1024), spill_dir.path()).unwrap();
+
+    assert!(!store.is_mapped());
+    assert_eq!(store.len(), 4);
+    assert_eq!(store.memory_bytes(), 11 * 4);
+    assert_eq!(fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_vectors_over_budget_are_mapped_and_scored_alike() {
+    let spill_dir = tempdir().expect("Failed to create temporary directory");
+    let in_memory = VectorStore::new(embeddings(), None, spill_dir.path()).unwrap();
+    let mapped = VectorStore::new(embeddings(), Some(16), spill_dir.path()).unwrap();
+
+    assert!(mapped.is_mapped());
+    assert_eq!(mapped.len(), 4);
+    assert_eq!(mapped.memory_bytes(), 0);
+    assert_eq!(mapped.mismatched(3), vec!["struct.Old.html"]);
+
+    let question = Embedding::new(
+        vec![1.0, 0.0, 0.0],
+        EmbeddingProvider::OpenAI,
+        "test-model".into(),
+    );
+    let scores = sorted(mapped.scores(&question, 0.1));
+    assert_eq!(scores, sorted(in_memory.scores(&question, 0.1)));
+    assert_eq!(
+        scores,
+        vec![("struct.Client.html", 1.0), ("fn.get.html", 0.6)]
+    );
+
+    // Values and metadata read back as they were stored
+    for (path, embedding) in embeddings() {
+        let read_back = mapped.get(&path).expect("Vector should exist");
+        assert_eq!(read_back.values, embedding.values);
+        assert_eq!(read_back.norm, embedding.norm);
+        assert_eq!(read_back.model, "test-model");
+    }
+    assert!(mapped.get("missing.html").is_none());
+    assert_eq!(mapped.embeddings().len(), 4);
+
+    // The vector file goes away with the store
+    assert_eq!(fs::read_dir(spill_dir.path()).unwrap().count(), 1);
+    drop(mapped);
+    assert_eq!(fs::read_dir(spill_dir.path()).unwrap().count(), 0);
+}